@@ -0,0 +1,51 @@
+use anyhow::Result;
+
+use crate::logging;
+use crate::token_vault::state::Vault;
+use crate::{TokenVaultClient, TokenVaultError};
+
+/// Refuse a withdrawal of `amount` that would take the vault's
+/// `total_deposited` below zero. The program clamps the counter to zero and
+/// sets `accounting_anomaly` instead of failing; this catches it beforehand.
+pub fn check_total_deposited(vault: &Vault, amount: u64) -> Result<(), TokenVaultError> {
+    if amount > vault.total_deposited {
+        return Err(TokenVaultError::AccountingUnderflow {
+            counter: "total_deposited",
+            recorded: vault.total_deposited,
+            amount,
+        });
+    }
+    Ok(())
+}
+
+/// `total_deposited` after a withdrawal of `amount` under the program's
+/// underflow policy, and whether the vault gets flagged
+pub fn total_deposited_after_withdrawal(total_deposited: u64, amount: u64) -> (u64, bool) {
+    match total_deposited.checked_sub(amount) {
+        Some(remaining) => (remaining, false),
+        None => (0, true),
+    }
+}
+
+impl TokenVaultClient {
+    /// Refuse withdrawals that would underflow the vault's `total_deposited`
+    /// with `TokenVaultError::AccountingUnderflow`, instead of warning and
+    /// letting the program clamp the counter
+    pub fn with_strict_accounting(&mut self, strict: bool) -> &mut Self {
+        self.strict_accounting = strict;
+        self
+    }
+
+    /// Pre-flight for a withdrawal of `amount`: warn about an imminent
+    /// underflow of `total_deposited`, or refuse it in strict mode
+    pub(crate) fn check_accounting(&self, vault_data: &Vault, amount: u64) -> Result<()> {
+        match check_total_deposited(vault_data, amount) {
+            Err(err) if self.strict_accounting => Err(err.into()),
+            Err(err) => {
+                logging::warn!(error = %err, "accounting check failed; continuing");
+                Ok(())
+            }
+            Ok(()) => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,475 @@
+use anchor_client::solana_client::{
+    rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
+    rpc_filter::{Memcmp, RpcFilterType},
+};
+use anchor_client::solana_sdk::{
+    instruction::Instruction, pubkey::Pubkey, signature::Signature, system_program,
+};
+use anchor_lang::{AccountDeserialize, Discriminator, InstructionData, ToAccountMetas};
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use solana_account_decoder::UiAccountEncoding;
+use std::collections::BTreeSet;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use crate::pagination::{check_limit, paginate, program_accounts_with_slot};
+use crate::plan::format_sol;
+use crate::signer::{AsSigner, VaultSigner};
+use crate::token_vault::{accounts, instruction, pda, state::AllowlistEntry};
+use crate::{
+    Cursor, DeadlinePhase, Operation, OperationKind, Page, TokenVaultClient, TokenVaultError,
+};
+
+/// Entry changes per transaction; keeps each transaction well under the size limit
+pub const DEFAULT_ALLOWLIST_BATCH_SIZE: usize = 10;
+
+/// Fee for a transaction with the authority as its only signer
+const LAMPORTS_PER_SIGNATURE: u64 = 5_000;
+
+const CSV_HEADER: &str = "wallet";
+
+/// A single allowlist entry to create or close
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AllowlistChange {
+    #[serde(with = "crate::serde_utils::pubkey")]
+    Add(Pubkey),
+    #[serde(with = "crate::serde_utils::pubkey")]
+    Remove(Pubkey),
+}
+
+impl AllowlistChange {
+    pub fn wallet(&self) -> Pubkey {
+        match self {
+            Self::Add(wallet) | Self::Remove(wallet) => *wallet,
+        }
+    }
+}
+
+impl fmt::Display for AllowlistChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Add(wallet) => write!(f, "+ {}", wallet),
+            Self::Remove(wallet) => write!(f, "- {}", wallet),
+        }
+    }
+}
+
+/// Changes that bring a vault's on-chain allowlist to a desired set of wallets
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AllowlistSyncPlan {
+    #[serde(with = "crate::serde_utils::pubkey")]
+    pub vault: Pubkey,
+    #[serde(with = "crate::serde_utils::pubkey")]
+    pub authority: Pubkey,
+    #[serde(with = "crate::serde_utils::pubkey_set")]
+    pub additions: BTreeSet<Pubkey>,
+    #[serde(with = "crate::serde_utils::pubkey_set")]
+    pub removals: BTreeSet<Pubkey>,
+    pub unchanged: usize,
+    /// Rent-exempt minimum of one entry account
+    pub entry_rent: u64,
+    pub batch_size: usize,
+}
+
+impl AllowlistSyncPlan {
+    /// Diff the wallets currently on chain against the desired ones
+    pub fn new(
+        vault: Pubkey,
+        authority: Pubkey,
+        current: &BTreeSet<Pubkey>,
+        desired: &BTreeSet<Pubkey>,
+        entry_rent: u64,
+    ) -> Self {
+        Self {
+            vault,
+            authority,
+            additions: desired.difference(current).copied().collect(),
+            removals: current.difference(desired).copied().collect(),
+            unchanged: current.intersection(desired).count(),
+            entry_rent,
+            batch_size: DEFAULT_ALLOWLIST_BATCH_SIZE,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.additions.is_empty() && self.removals.is_empty()
+    }
+
+    /// Every change, removals first so their refunded rent can fund additions
+    pub fn changes(&self) -> Vec<AllowlistChange> {
+        self.removals
+            .iter()
+            .map(|wallet| AllowlistChange::Remove(*wallet))
+            .chain(
+                self.additions
+                    .iter()
+                    .map(|wallet| AllowlistChange::Add(*wallet)),
+            )
+            .collect()
+    }
+
+    pub fn transaction_count(&self) -> usize {
+        let changes = self.additions.len() + self.removals.len();
+        changes.div_ceil(self.batch_size.max(1))
+    }
+
+    /// Lamports the authority locks up in rent (negative when rent is refunded)
+    pub fn rent_delta(&self) -> i128 {
+        (self.additions.len() as i128 - self.removals.len() as i128) * self.entry_rent as i128
+    }
+
+    pub fn fee_estimate(&self) -> u64 {
+        self.transaction_count() as u64 * LAMPORTS_PER_SIGNATURE
+    }
+}
+
+impl fmt::Display for AllowlistSyncPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Allowlist sync for vault {}: {} to add, {} to remove, {} unchanged",
+            self.vault,
+            self.additions.len(),
+            self.removals.len(),
+            self.unchanged
+        )?;
+        for change in self.changes() {
+            writeln!(f, "  {}", change)?;
+        }
+        let rent = self.rent_delta();
+        let rent = if rent >= 0 {
+            format!("{} paid", format_sol(rent as u64))
+        } else {
+            format!("{} refunded", format_sol(rent.unsigned_abs() as u64))
+        };
+        writeln!(f, "  rent: {}", rent)?;
+        write!(
+            f,
+            "  transactions: {} (~{} in fees)",
+            self.transaction_count(),
+            format_sol(self.fee_estimate())
+        )
+    }
+}
+
+/// A batch of changes known to have landed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AppliedBatch {
+    /// Missing when the batch's transaction failed but some of its changes
+    /// were found on chain afterwards
+    #[serde(with = "crate::serde_utils::option_signature")]
+    pub signature: Option<Signature>,
+    pub changes: Vec<AllowlistChange>,
+}
+
+/// Durable record of an allowlist sync; rerunning with the same record skips
+/// what was already applied
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AllowlistSyncRecord {
+    #[serde(with = "crate::serde_utils::pubkey")]
+    pub vault: Pubkey,
+    pub applied: Vec<AppliedBatch>,
+    /// Changes of a failed batch whose outcome could not be determined
+    pub uncertain: Vec<AllowlistChange>,
+}
+
+impl AllowlistSyncRecord {
+    pub fn new(vault: Pubkey) -> Self {
+        Self {
+            vault,
+            applied: Vec::new(),
+            uncertain: Vec::new(),
+        }
+    }
+
+    pub fn is_applied(&self, change: &AllowlistChange) -> bool {
+        self.applied
+            .iter()
+            .any(|batch| batch.changes.contains(change))
+    }
+
+    pub fn applied_count(&self) -> usize {
+        self.applied.iter().map(|batch| batch.changes.len()).sum()
+    }
+
+    fn load(path: &Path, vault: Pubkey) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::new(vault));
+        }
+        let record: Self = serde_json::from_str(&fs::read_to_string(path)?)?;
+        if record.vault != vault {
+            return Err(anyhow!(
+                "Sync record {} belongs to vault {}, not {}",
+                path.display(),
+                record.vault,
+                vault
+            ));
+        }
+        Ok(record)
+    }
+
+    fn save(&self, path: &Path) -> Result<()> {
+        // Write-then-rename so a kill mid-write never leaves a truncated record
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, serde_json::to_vec_pretty(self)?)?;
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+}
+
+/// Wallets from a CSV whose first column holds base58 addresses; a `wallet`
+/// header, blank lines and `#` comments are skipped
+pub fn parse_allowlist_csv(input: &str) -> Result<BTreeSet<Pubkey>> {
+    let mut wallets = BTreeSet::new();
+    for (number, line) in input.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let field = line.split(',').next().unwrap_or_default();
+        let field = field.trim().trim_matches('"');
+        if field.eq_ignore_ascii_case(CSV_HEADER) {
+            continue;
+        }
+        let wallet = field
+            .parse()
+            .map_err(|_| anyhow!("Line {}: invalid wallet address '{}'", number + 1, field))?;
+        wallets.insert(wallet);
+    }
+    Ok(wallets)
+}
+
+/// CSV in the format `parse_allowlist_csv` reads
+pub fn allowlist_csv(wallets: &BTreeSet<Pubkey>) -> String {
+    let mut csv = format!("{}\n", CSV_HEADER);
+    for wallet in wallets {
+        csv.push_str(&format!("{}\n", wallet));
+    }
+    csv
+}
+
+impl TokenVaultClient {
+    /// Wallets with an allowlist entry for the vault
+    pub fn get_allowlist(&self) -> Result<BTreeSet<Pubkey>> {
+        let (wallets, _) = self.scan_allowlist()?;
+        Ok(wallets.into_iter().collect())
+    }
+
+    /// Up to `limit` allowlisted wallets after `cursor`, in address order
+    pub fn get_allowlist_page(
+        &self,
+        cursor: Option<&Cursor<Pubkey>>,
+        limit: usize,
+    ) -> Result<Page<Pubkey>> {
+        check_limit(limit)?;
+        let (wallets, slot) = self.scan_allowlist()?;
+        Ok(paginate(wallets, cursor, limit, slot))
+    }
+
+    /// The vault's allowlisted wallets, unsorted, and the slot they were read at
+    fn scan_allowlist(&self) -> Result<(Vec<Pubkey>, u64)> {
+        let vault = self.current_vault()?;
+        let rpc = self.rpc_within(self.deadline(), DeadlinePhase::Fetch)?;
+        let config = RpcProgramAccountsConfig {
+            filters: Some(vec![
+                RpcFilterType::DataSize(AllowlistEntry::LEN as u64),
+                RpcFilterType::Memcmp(Memcmp::new_raw_bytes(
+                    0,
+                    AllowlistEntry::DISCRIMINATOR.to_vec(),
+                )),
+                RpcFilterType::Memcmp(Memcmp::new_raw_bytes(8, vault.to_bytes().to_vec())),
+            ]),
+            account_config: RpcAccountInfoConfig {
+                encoding: Some(UiAccountEncoding::Base64),
+                commitment: Some(rpc.commitment()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let (accounts, slot) = program_accounts_with_slot(&rpc, &self.program.id(), config)?;
+        let wallets = accounts
+            .into_iter()
+            .map(|(_, account)| {
+                let entry = AllowlistEntry::try_deserialize(&mut account.data.as_slice())?;
+                Ok(entry.wallet)
+            })
+            .collect::<Result<_>>()?;
+        Ok((wallets, slot))
+    }
+
+    /// Plan the changes that make the vault's allowlist exactly `desired`.
+    /// Nothing is sent; apply the plan with `execute_allowlist_sync`.
+    pub fn sync_allowlist(
+        &self,
+        authority: Pubkey,
+        desired: &[Pubkey],
+    ) -> Result<AllowlistSyncPlan> {
+        let deadline = self.deadline();
+        let vault = self.current_vault()?;
+        let vault_data = self.fetch_vault(vault, deadline)?;
+        if vault_data.authority != authority {
+            return Err(anyhow!(
+                "{} is not the authority of vault {}",
+                authority,
+                vault
+            ));
+        }
+
+        let current = self.get_allowlist()?;
+        let entry_rent = self
+            .rpc_within(deadline, DeadlinePhase::Fetch)?
+            .get_minimum_balance_for_rent_exemption(AllowlistEntry::LEN)?;
+        let desired = desired.iter().copied().collect();
+        Ok(AllowlistSyncPlan::new(
+            vault, authority, &current, &desired, entry_rent,
+        ))
+    }
+
+    /// Apply `plan` in batches of `plan.batch_size`.
+    ///
+    /// With a `record_path`, every landed batch is recorded there before the
+    /// next is sent, and a rerun skips the changes already recorded. When a
+    /// batch fails, its entries are checked on chain so the record says exactly
+    /// which changes were applied before the error is returned.
+    pub fn execute_allowlist_sync(
+        &self,
+        authority: &dyn VaultSigner,
+        plan: &AllowlistSyncPlan,
+        record_path: Option<&Path>,
+    ) -> Result<AllowlistSyncRecord> {
+        self.check_signer(&authority.pubkey())?;
+        if authority.pubkey() != plan.authority {
+            return Err(anyhow!(
+                "Plan was made for authority {}, not {}",
+                plan.authority,
+                authority.pubkey()
+            ));
+        }
+        let deadline = self.deadline();
+        self.check_features(deadline)?;
+
+        let mut record = match record_path {
+            Some(path) => AllowlistSyncRecord::load(path, plan.vault)?,
+            None => AllowlistSyncRecord::new(plan.vault),
+        };
+        let save = |record: &AllowlistSyncRecord| match record_path {
+            Some(path) => record.save(path),
+            None => Ok(()),
+        };
+
+        let pending: Vec<_> = plan
+            .changes()
+            .into_iter()
+            .filter(|change| !record.is_applied(change))
+            .collect();
+        for batch in pending.chunks(plan.batch_size.max(1)) {
+            let request = batch
+                .iter()
+                .fold(self.vault_request(&[plan.vault])?, |request, change| {
+                    request.instruction(self.allowlist_instruction(plan, change))
+                });
+            let operation = Operation::new(
+                OperationKind::AllowlistSync,
+                authority.pubkey(),
+                Some(plan.vault),
+                0,
+            );
+            let signer = AsSigner(authority);
+            match self.send_operation(operation, request.signer(&signer), deadline) {
+                Ok(signature) => {
+                    record.applied.push(AppliedBatch {
+                        signature: Some(signature),
+                        changes: batch.to_vec(),
+                    });
+                    record.uncertain.clear();
+                    save(&record)?;
+                }
+                Err(err) => {
+                    let signature = match err.downcast_ref::<TokenVaultError>() {
+                        Some(TokenVaultError::DeadlineExceeded { signature, .. }) => *signature,
+                        _ => None,
+                    };
+                    match self.landed_changes(plan.vault, batch) {
+                        Ok(landed) if !landed.is_empty() => record.applied.push(AppliedBatch {
+                            signature,
+                            changes: landed,
+                        }),
+                        Ok(_) => {}
+                        Err(_) => record.uncertain = batch.to_vec(),
+                    }
+                    save(&record)?;
+                    return Err(err).context(format!(
+                        "Allowlist sync stopped with {} of {} changes applied",
+                        record.applied_count(),
+                        plan.changes().len()
+                    ));
+                }
+            }
+        }
+
+        Ok(record)
+    }
+
+    fn allowlist_instruction(
+        &self,
+        plan: &AllowlistSyncPlan,
+        change: &AllowlistChange,
+    ) -> Instruction {
+        let (allowlist_entry, _) =
+            pda::find_allowlist_entry_address(&self.program.id(), &plan.vault, &change.wallet());
+        let (accounts, data) = match change {
+            AllowlistChange::Add(wallet) => (
+                accounts::AddAllowlistEntry {
+                    authority: plan.authority,
+                    vault: plan.vault,
+                    allowlist_entry,
+                    system_program: system_program::ID,
+                }
+                .to_account_metas(None),
+                instruction::AddAllowlistEntry { wallet: *wallet }.data(),
+            ),
+            AllowlistChange::Remove(_) => (
+                accounts::RemoveAllowlistEntry {
+                    authority: plan.authority,
+                    vault: plan.vault,
+                    allowlist_entry,
+                }
+                .to_account_metas(None),
+                instruction::RemoveAllowlistEntry {}.data(),
+            ),
+        };
+        Instruction {
+            program_id: self.program.id(),
+            accounts,
+            data,
+        }
+    }
+
+    /// Changes of `batch` that are visible on chain
+    fn landed_changes(
+        &self,
+        vault: Pubkey,
+        batch: &[AllowlistChange],
+    ) -> Result<Vec<AllowlistChange>> {
+        let entries: Vec<_> = batch
+            .iter()
+            .map(|change| {
+                pda::find_allowlist_entry_address(&self.program.id(), &vault, &change.wallet()).0
+            })
+            .collect();
+        // The batch's own deadline may be spent; the check gets a fresh connection
+        let accounts = self.program.rpc().get_multiple_accounts(&entries)?;
+        Ok(batch
+            .iter()
+            .zip(accounts)
+            .filter(|(change, account)| match change {
+                AllowlistChange::Add(_) => account.is_some(),
+                AllowlistChange::Remove(_) => account.is_none(),
+            })
+            .map(|(change, _)| *change)
+            .collect())
+    }
+}
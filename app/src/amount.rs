@@ -0,0 +1,213 @@
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::signer::VaultSigner;
+use crate::vault_config::{split_decimal, ui_to_base_units};
+use crate::{TokenVaultClient, TokenVaultError, TxReceipt};
+
+/// A token amount that knows which mint it is denominated in, so amounts of
+/// vaults with different decimals cannot be mixed up
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct MintAmount {
+    /// In base units of the mint
+    pub raw: u64,
+    #[serde(with = "crate::serde_utils::pubkey")]
+    pub mint: Pubkey,
+    pub decimals: u8,
+}
+
+impl MintAmount {
+    pub fn new(raw: u64, mint: Pubkey, decimals: u8) -> Self {
+        Self {
+            raw,
+            mint,
+            decimals,
+        }
+    }
+
+    /// Parse a decimal amount of whole tokens, e.g. `"1000.5"`, refusing
+    /// precision the mint cannot represent
+    pub fn from_ui(amount: &str, mint: Pubkey, decimals: u8) -> Result<Self> {
+        Ok(Self::new(
+            ui_to_base_units(amount, decimals)?,
+            mint,
+            decimals,
+        ))
+    }
+
+    /// The amount in whole tokens, without trailing zeros
+    pub fn ui(&self) -> String {
+        base_units_to_ui(self.raw, self.decimals)
+    }
+
+    /// Refuse an amount of another mint than `mint`
+    pub fn ensure_mint(&self, mint: Pubkey) -> Result<(), TokenVaultError> {
+        ensure_mint(mint, Some(self.mint))
+    }
+
+    /// `self + other`, refusing amounts of different mints and overflow
+    pub fn checked_add(&self, other: &Self) -> Result<Self> {
+        other.ensure_mint(self.mint)?;
+        let raw = self
+            .raw
+            .checked_add(other.raw)
+            .ok_or_else(|| anyhow!("{} + {} overflows", self, other))?;
+        Ok(Self { raw, ..*self })
+    }
+
+    /// `self - other`, refusing amounts of different mints and underflow
+    pub fn checked_sub(&self, other: &Self) -> Result<Self> {
+        other.ensure_mint(self.mint)?;
+        let raw = self
+            .raw
+            .checked_sub(other.raw)
+            .ok_or_else(|| anyhow!("{} - {} is negative", self, other))?;
+        Ok(Self { raw, ..*self })
+    }
+
+    /// Render with the mint's symbol from `book`, or its address when unknown
+    pub fn display<'a>(&'a self, book: &'a AddressBook) -> impl fmt::Display + 'a {
+        DisplayWithSymbol { amount: self, book }
+    }
+}
+
+impl fmt::Display for MintAmount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.ui(), self.mint)
+    }
+}
+
+struct DisplayWithSymbol<'a> {
+    amount: &'a MintAmount,
+    book: &'a AddressBook,
+}
+
+impl fmt::Display for DisplayWithSymbol<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.book.symbol(&self.amount.mint) {
+            Some(symbol) => write!(f, "{} {}", self.amount.ui(), symbol),
+            None => self.amount.fmt(f),
+        }
+    }
+}
+
+/// A decimal amount of whole tokens such as `"1000.5"`, kept as written so
+/// no float rounding creeps in. It becomes base units only once the mint's
+/// decimals are known.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct UiAmount(String);
+
+impl UiAmount {
+    /// The amount in base units of a mint with `decimals` decimals, refusing
+    /// more decimal places than the mint has and amounts beyond `u64`
+    pub fn to_base_units(&self, decimals: u8) -> Result<u64> {
+        ui_to_base_units(&self.0, decimals)
+    }
+}
+
+impl FromStr for UiAmount {
+    type Err = anyhow::Error;
+
+    fn from_str(amount: &str) -> Result<Self> {
+        split_decimal(amount)?;
+        Ok(Self(amount.to_string()))
+    }
+}
+
+impl fmt::Display for UiAmount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Symbols of known mints, for display
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AddressBook {
+    symbols: BTreeMap<Pubkey, String>,
+}
+
+impl AddressBook {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, mint: Pubkey, symbol: impl Into<String>) {
+        self.symbols.insert(mint, symbol.into());
+    }
+
+    pub fn symbol(&self, mint: &Pubkey) -> Option<&str> {
+        self.symbols.get(mint).map(String::as_str)
+    }
+}
+
+impl<S: Into<String>> FromIterator<(Pubkey, S)> for AddressBook {
+    fn from_iter<I: IntoIterator<Item = (Pubkey, S)>>(entries: I) -> Self {
+        let mut book = Self::new();
+        for (mint, symbol) in entries {
+            book.insert(mint, symbol);
+        }
+        book
+    }
+}
+
+/// Refuse an amount denominated in `actual` where `expected` is required;
+/// an amount without a mint is taken as-is
+pub fn ensure_mint(expected: Pubkey, actual: Option<Pubkey>) -> Result<(), TokenVaultError> {
+    match actual {
+        Some(actual) if actual != expected => {
+            Err(TokenVaultError::MintMismatch { expected, actual })
+        }
+        _ => Ok(()),
+    }
+}
+
+/// `raw` base units of a mint with `decimals` decimals, in whole tokens
+/// without trailing zeros
+pub fn base_units_to_ui(raw: u64, decimals: u8) -> String {
+    let digits = format!("{:0>width$}", raw, width = decimals as usize + 1);
+    let (whole, fraction) = digits.split_at(digits.len() - decimals as usize);
+    match fraction.trim_end_matches('0') {
+        "" => whole.to_string(),
+        fraction => format!("{}.{}", whole, fraction),
+    }
+}
+
+impl TokenVaultClient {
+    /// Deposit `amount` whole tokens into the vault, converted with the
+    /// decimals of the vault's mint
+    pub fn deposit_ui(&self, depositor: &dyn VaultSigner, amount: &UiAmount) -> Result<TxReceipt> {
+        let amount = self.mint_amount(amount)?;
+        self.deposit_amount(depositor, amount)
+    }
+
+    /// Withdraw `amount` whole tokens from the vault to the withdrawer,
+    /// converted with the decimals of the vault's mint
+    pub fn withdraw_ui(
+        &self,
+        withdrawer: &dyn VaultSigner,
+        amount: &UiAmount,
+    ) -> Result<TxReceipt> {
+        let amount = self.mint_amount(amount)?;
+        self.withdraw_amount(withdrawer, amount, None)
+    }
+
+    /// `raw` base units of the vault's mint in whole tokens, e.g. `"12.5"`
+    pub fn format_amount(&self, raw: u64) -> Result<String> {
+        let mint = self.get_vault_info()?.token_mint;
+        Ok(base_units_to_ui(raw, self.mint_decimals(&mint)?))
+    }
+
+    fn mint_amount(&self, amount: &UiAmount) -> Result<MintAmount> {
+        let mint = self.get_vault_info()?.token_mint;
+        let decimals = self.mint_decimals(&mint)?;
+        Ok(MintAmount::new(
+            amount.to_base_units(decimals)?,
+            mint,
+            decimals,
+        ))
+    }
+}
@@ -0,0 +1,77 @@
+use anchor_client::solana_sdk::{
+    hash::Hash,
+    instruction::{AccountMeta, Instruction},
+    message::Message,
+    pubkey::Pubkey,
+    signature::Signer,
+    transaction::Transaction,
+};
+use anyhow::{Context, Result};
+use std::collections::HashSet;
+
+use crate::TokenVaultError;
+
+/// `signers` with every signer whose key an earlier one already has dropped,
+/// in their original order. Roles held by one key, such as an authority that
+/// also deposits, then sign once.
+pub fn dedup_signers<'a>(signers: &[&'a dyn Signer]) -> Vec<&'a dyn Signer> {
+    let mut seen = HashSet::new();
+    signers
+        .iter()
+        .filter(|signer| seen.insert(signer.pubkey()))
+        .copied()
+        .collect()
+}
+
+/// The account list of a transaction carrying `metas`: one entry per key, in
+/// first-seen order, signing or writable when any of its entries is. Use it
+/// across instructions only; within one instruction every position is an
+/// account the program reads, even when two positions hold the same key.
+pub fn merge_account_metas(metas: impl IntoIterator<Item = AccountMeta>) -> Vec<AccountMeta> {
+    let mut merged: Vec<AccountMeta> = Vec::new();
+    for meta in metas {
+        match merged
+            .iter_mut()
+            .find(|merged| merged.pubkey == meta.pubkey)
+        {
+            Some(merged) => {
+                merged.is_signer |= meta.is_signer;
+                merged.is_writable |= meta.is_writable;
+            }
+            None => merged.push(meta),
+        }
+    }
+    merged
+}
+
+/// `instructions` as a transaction paid by `payer` and signed by it and
+/// `signers`. A key may appear more than once among them; it signs once.
+/// Fails when a signer the instructions require is missing, or a signer is
+/// given that they do not require.
+pub fn sign_transaction(
+    instructions: &[Instruction],
+    payer: &dyn Signer,
+    signers: &[&dyn Signer],
+    recent_blockhash: Hash,
+) -> Result<Transaction> {
+    let mut all_signers = vec![payer];
+    all_signers.extend_from_slice(signers);
+    let all_signers = dedup_signers(&all_signers);
+
+    let message = Message::new(instructions, Some(&payer.pubkey()));
+    let mut transaction = Transaction::new_unsigned(message);
+    transaction
+        .try_sign(&all_signers, recent_blockhash)
+        .context("Failed to sign transaction")?;
+    Ok(transaction)
+}
+
+/// Refuse a token transfer from `source` into `destination` when they are
+/// the same account: it would move nothing yet still pay fees and, for a
+/// vault, count as a deposit or withdrawal that never happened
+pub fn check_not_self_transfer(source: &Pubkey, destination: &Pubkey) -> Result<()> {
+    if source == destination {
+        return Err(TokenVaultError::SelfTransfer { account: *source }.into());
+    }
+    Ok(())
+}
@@ -0,0 +1,153 @@
+//! A `Send` handle to a `TokenVaultClient`, for async code.
+//!
+//! ```no_run
+//! # use token_vault_client::AsyncTokenVaultClient;
+//! # use anchor_client::{solana_sdk::{pubkey::Pubkey, signature::Keypair}, Cluster};
+//! # use std::sync::Arc;
+//! # async fn run(payer: Keypair, program_id: Pubkey, vault: Pubkey) -> anyhow::Result<()> {
+//! let client = AsyncTokenVaultClient::new(Cluster::Devnet, payer, program_id).await?;
+//! client.set_vault(vault).await?;
+//! let depositor = Arc::new(Keypair::new());
+//! let task = tokio::spawn({
+//!     let client = client.clone();
+//!     async move { client.deposit(depositor, 1_000).await }
+//! });
+//! println!("{}", task.await??);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! `TokenVaultClient` blocks while it sends and confirms, which would stall
+//! the executor. The handle owns a thread running one `TokenVaultClient` and
+//! hands it each call, so PDA derivation, pre-flight checks and account
+//! building are the sync client's own. Calls run one at a time, in the order they were made,
+//! and never block the awaiting task. Clones share the thread, which stops
+//! once every clone is dropped and the calls already made have run.
+
+use anchor_client::solana_sdk::{pubkey::Pubkey, signature::Keypair};
+use anchor_client::Cluster;
+use anyhow::{anyhow, Result};
+use futures::channel::oneshot;
+use std::sync::{mpsc, Arc};
+use std::thread;
+
+use crate::signer::VaultSigner;
+use crate::token_vault::state::Vault;
+use crate::{InitializedVault, SweepReceipt, TokenVaultClient, TxReceipt, VaultConfig};
+
+type Call = Box<dyn FnOnce(&mut TokenVaultClient) + Send>;
+
+/// A signer the client thread can use; an `Arc<Keypair>` converts to one
+pub type SharedSigner = Arc<dyn VaultSigner + Send + Sync>;
+
+/// `TokenVaultClient` for async code; see the module docs
+#[derive(Debug, Clone)]
+pub struct AsyncTokenVaultClient {
+    calls: mpsc::Sender<Call>,
+}
+
+fn stopped() -> anyhow::Error {
+    anyhow!("The client thread stopped")
+}
+
+impl AsyncTokenVaultClient {
+    /// Start a client thread and create the client on it, as
+    /// `TokenVaultClient::new` does
+    pub async fn new(cluster: Cluster, payer: Keypair, program_id: Pubkey) -> Result<Self> {
+        let (calls, queue) = mpsc::channel::<Call>();
+        let (ready, created) = oneshot::channel();
+        thread::Builder::new()
+            .name("token-vault-client".to_string())
+            .spawn(move || {
+                let mut client = match TokenVaultClient::new(cluster, payer, program_id) {
+                    Ok(client) => client,
+                    Err(err) => {
+                        let _ = ready.send(Err(err));
+                        return;
+                    }
+                };
+                let _ = ready.send(Ok(()));
+                for call in queue {
+                    call(&mut client);
+                }
+            })?;
+        created.await.map_err(|_| stopped())??;
+        Ok(Self { calls })
+    }
+
+    /// Run `f` against the client on its thread. Everything the sync client
+    /// offers, setters included, is reachable this way.
+    pub async fn call<R, F>(&self, f: F) -> Result<R>
+    where
+        R: Send + 'static,
+        F: FnOnce(&mut TokenVaultClient) -> Result<R> + Send + 'static,
+    {
+        let (reply, response) = oneshot::channel();
+        self.calls
+            .send(Box::new(move |client| {
+                let _ = reply.send(f(client));
+            }))
+            .map_err(|_| stopped())?;
+        // A panic in `f` ends the thread and drops `reply`
+        response.await.map_err(|_| stopped())?
+    }
+
+    /// Set the vault address to interact with
+    pub async fn set_vault(&self, vault_address: Pubkey) -> Result<()> {
+        self.call(move |client| {
+            client.set_vault(vault_address);
+            Ok(())
+        })
+        .await
+    }
+
+    /// `TokenVaultClient::initialize_vault`
+    pub async fn initialize_vault(
+        &self,
+        authority: SharedSigner,
+        token_mint: Pubkey,
+        config: VaultConfig,
+    ) -> Result<InitializedVault> {
+        self.call(move |client| client.initialize_vault(&*authority, token_mint, &config))
+            .await
+    }
+
+    /// `TokenVaultClient::deposit`
+    pub async fn deposit(&self, depositor: SharedSigner, amount: u64) -> Result<TxReceipt> {
+        self.call(move |client| client.deposit(&*depositor, amount))
+            .await
+    }
+
+    /// `TokenVaultClient::withdraw`
+    pub async fn withdraw(
+        &self,
+        withdrawer: SharedSigner,
+        amount: u64,
+        recipient: Option<Pubkey>,
+    ) -> Result<TxReceipt> {
+        self.call(move |client| client.withdraw(&*withdrawer, amount, recipient))
+            .await
+    }
+
+    /// `TokenVaultClient::deposit_all`
+    pub async fn deposit_all(&self, depositor: SharedSigner, keep: u64) -> Result<SweepReceipt> {
+        self.call(move |client| client.deposit_all(&*depositor, keep))
+            .await
+    }
+
+    /// `TokenVaultClient::withdraw_all`
+    pub async fn withdraw_all(&self, withdrawer: SharedSigner) -> Result<SweepReceipt> {
+        self.call(move |client| client.withdraw_all(&*withdrawer))
+            .await
+    }
+
+    /// `TokenVaultClient::get_vault_info`
+    pub async fn get_vault_info(&self) -> Result<Vault> {
+        self.call(|client| client.get_vault_info()).await
+    }
+
+    /// `TokenVaultClient::get_vault_info_fresh`
+    pub async fn get_vault_info_fresh(&self) -> Result<Vault> {
+        self.call(|client| client.get_vault_info_fresh()).await
+    }
+}
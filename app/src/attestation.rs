@@ -0,0 +1,158 @@
+use anchor_client::solana_sdk::{pubkey::Pubkey, signature::Signature};
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::signer::VaultSigner;
+use crate::TokenVaultClient;
+
+/// Domain separator prepended to every attestation message so the signature
+/// can never be replayed as a transaction or another protocol's message
+pub const ATTESTATION_DOMAIN: &[u8] = b"token-vault:attestation:v1";
+
+/// An off-chain statement, signed by the vault authority, that it controls the vault
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Attestation {
+    #[serde(with = "crate::serde_utils::pubkey")]
+    pub program_id: Pubkey,
+    #[serde(with = "crate::serde_utils::pubkey")]
+    pub vault: Pubkey,
+    #[serde(with = "crate::serde_utils::pubkey")]
+    pub authority: Pubkey,
+    pub nonce: u64,
+    /// Unix timestamp after which the attestation is no longer valid
+    pub expiry: i64,
+    #[serde(with = "crate::serde_utils::signature")]
+    pub signature: Signature,
+}
+
+/// The claim proven by a successfully verified attestation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VerifiedClaim {
+    #[serde(with = "crate::serde_utils::pubkey")]
+    pub vault: Pubkey,
+    #[serde(with = "crate::serde_utils::pubkey")]
+    pub authority: Pubkey,
+    pub nonce: u64,
+    pub expiry: i64,
+}
+
+impl Attestation {
+    /// Build the exact bytes that are signed for the given fields
+    pub fn message(
+        program_id: &Pubkey,
+        vault: &Pubkey,
+        authority: &Pubkey,
+        nonce: u64,
+        expiry: i64,
+    ) -> Vec<u8> {
+        let mut message = Vec::with_capacity(ATTESTATION_DOMAIN.len() + 32 * 3 + 16);
+        message.extend_from_slice(ATTESTATION_DOMAIN);
+        message.extend_from_slice(program_id.as_ref());
+        message.extend_from_slice(vault.as_ref());
+        message.extend_from_slice(authority.as_ref());
+        message.extend_from_slice(&nonce.to_le_bytes());
+        message.extend_from_slice(&expiry.to_le_bytes());
+        message
+    }
+
+    /// Check that the signature was produced by `authority` over this attestation
+    pub fn verify_signature(&self) -> bool {
+        let message = Self::message(
+            &self.program_id,
+            &self.vault,
+            &self.authority,
+            self.nonce,
+            self.expiry,
+        );
+        self.signature.verify(self.authority.as_ref(), &message)
+    }
+
+    /// Encode as JSON
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    /// Decode from JSON
+    pub fn from_json(json: &str) -> Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    /// Encode as base64 of the JSON form, convenient for headers and query strings
+    pub fn to_base64(&self) -> Result<String> {
+        Ok(STANDARD.encode(self.to_json()?))
+    }
+
+    /// Decode from the base64 form produced by `to_base64`
+    pub fn from_base64(encoded: &str) -> Result<Self> {
+        let bytes = STANDARD
+            .decode(encoded.trim())
+            .map_err(|e| anyhow!("Invalid base64 attestation: {}", e))?;
+        let json = String::from_utf8(bytes).map_err(|_| anyhow!("Attestation is not UTF-8"))?;
+        Self::from_json(&json)
+    }
+}
+
+impl TokenVaultClient {
+    /// Sign an attestation that `authority` controls `vault`, valid until `expiry`
+    pub fn sign_vault_attestation(
+        &self,
+        authority: &dyn VaultSigner,
+        vault: Pubkey,
+        nonce: u64,
+        expiry: i64,
+    ) -> Result<Attestation> {
+        self.check_signer(&authority.pubkey())?;
+        let program_id = self.program.id();
+        let message = Attestation::message(&program_id, &vault, &authority.pubkey(), nonce, expiry);
+        let signature = authority.sign_message(&message)?;
+
+        Ok(Attestation {
+            program_id,
+            vault,
+            authority: authority.pubkey(),
+            nonce,
+            expiry,
+            signature,
+        })
+    }
+
+    /// Verify an attestation's signature, expiry, and that its signer is the
+    /// vault's current on-chain authority
+    pub fn verify_vault_attestation(&self, attestation: &Attestation) -> Result<VerifiedClaim> {
+        if attestation.program_id != self.program.id() {
+            return Err(anyhow!(
+                "Attestation is for program {}, expected {}",
+                attestation.program_id,
+                self.program.id()
+            ));
+        }
+
+        if !attestation.verify_signature() {
+            return Err(anyhow!("Attestation signature is invalid"));
+        }
+
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64;
+        if now > attestation.expiry {
+            return Err(anyhow!("Attestation expired at {}", attestation.expiry));
+        }
+
+        let vault_data = self.fetch_vault(attestation.vault, self.deadline())?;
+        if vault_data.authority != attestation.authority {
+            return Err(anyhow!(
+                "Attestation signer {} is not the current authority {} of vault {}",
+                attestation.authority,
+                vault_data.authority,
+                attestation.vault
+            ));
+        }
+
+        Ok(VerifiedClaim {
+            vault: attestation.vault,
+            authority: attestation.authority,
+            nonce: attestation.nonce,
+            expiry: attestation.expiry,
+        })
+    }
+}
@@ -0,0 +1,531 @@
+// solana's ClientError is large, but it is only ever passed straight through
+#![allow(clippy::result_large_err)]
+
+use anchor_client::{
+    solana_client::{
+        client_error::ClientError,
+        rpc_client::{GetConfirmedSignaturesForAddress2Config, RpcClient},
+        rpc_config::RpcTransactionConfig,
+        rpc_response::RpcConfirmedTransactionStatusWithSignature,
+    },
+    solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey, signature::Signature},
+};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use solana_transaction_status::UiTransactionEncoding;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::history::{
+    decode_transaction_within, parse_signature, DecodeStats, VaultTransaction,
+    DEFAULT_MAX_TRANSACTION_BYTES,
+};
+use crate::json::TransactionView;
+use crate::pagination::check_limit;
+use crate::{Cursor, Deadline, DeadlinePhase, Page, TokenVaultClient, TokenVaultError};
+
+/// Maximum page size accepted by `getSignaturesForAddress`
+pub const MAX_SIGNATURE_PAGE_SIZE: usize = 1_000;
+
+/// Tuning knobs for a history backfill
+#[derive(Debug, Clone)]
+pub struct BackfillConfig {
+    /// Signatures requested per `getSignaturesForAddress` page (max 1000)
+    pub page_size: usize,
+    /// Pause between consecutive RPC requests
+    pub pacing: Duration,
+    /// Transactions fetched in parallel
+    pub max_concurrent_fetches: usize,
+    /// Retries for a single request that was rate limited (HTTP 429)
+    pub max_retries: u32,
+    /// First backoff after a 429, doubled on every further 429
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    /// Stop at transactions older than this unix timestamp
+    pub since: Option<i64>,
+    /// Stop at transactions in this slot or older
+    pub after_slot: Option<u64>,
+    /// Transactions whose encoding and logs together are larger keep only
+    /// the token vault program's own log lines
+    pub max_transaction_bytes: usize,
+    /// File where progress is persisted so a killed run can resume without gaps
+    pub checkpoint_path: Option<PathBuf>,
+    pub commitment: CommitmentConfig,
+    /// Give up with `DeadlineExceeded` once this passes; the checkpoint keeps
+    /// everything delivered so far
+    pub deadline: Deadline,
+}
+
+impl Default for BackfillConfig {
+    fn default() -> Self {
+        Self {
+            page_size: MAX_SIGNATURE_PAGE_SIZE,
+            pacing: Duration::from_millis(100),
+            max_concurrent_fetches: 4,
+            max_retries: 8,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+            since: None,
+            after_slot: None,
+            max_transaction_bytes: DEFAULT_MAX_TRANSACTION_BYTES,
+            checkpoint_path: None,
+            commitment: CommitmentConfig::confirmed(),
+            deadline: Deadline::NONE,
+        }
+    }
+}
+
+/// Progress snapshot passed to the progress callback after every page
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BackfillProgress {
+    pub signatures_discovered: usize,
+    pub transactions_decoded: usize,
+    /// Block time of the oldest transaction processed so far
+    pub oldest_block_time: Option<i64>,
+    /// Only available when `since` is set and block times are known
+    pub eta: Option<Duration>,
+}
+
+/// Summary of a finished backfill pass
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BackfillReport {
+    pub signatures_discovered: usize,
+    pub transactions_decoded: usize,
+    pub rate_limited_retries: usize,
+    pub resumed: bool,
+    /// Encoding plus logs of the largest transaction fetched
+    pub largest_transaction_bytes: usize,
+    /// Transactions over `max_transaction_bytes`, whose logs were trimmed
+    pub trimmed_transactions: usize,
+    /// Most decoded bytes held at once, which is one page's worth
+    pub peak_retained_bytes: usize,
+}
+
+/// Destination for decoded transactions, in newest-to-oldest order.
+///
+/// After a crash the last partially delivered page is delivered again, so
+/// sinks should treat the signature as an idempotency key.
+pub trait TransactionSink {
+    fn write(&mut self, transaction: &VaultTransaction) -> Result<()>;
+
+    /// Called before each checkpoint is persisted
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl<F> TransactionSink for F
+where
+    F: FnMut(&VaultTransaction) -> Result<()>,
+{
+    fn write(&mut self, transaction: &VaultTransaction) -> Result<()> {
+        self(transaction)
+    }
+}
+
+/// Sink writing one JSON object per line, in the `json::TransactionView` shape
+pub struct JsonLinesSink<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> JsonLinesSink<W> {
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+}
+
+impl<W: Write> TransactionSink for JsonLinesSink<W> {
+    fn write(&mut self, transaction: &VaultTransaction) -> Result<()> {
+        serde_json::to_writer(&mut self.writer, &TransactionView::from(transaction))?;
+        self.writer.write_all(b"\n")?;
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Persisted position of a backfill.
+///
+/// A pass walks from the newest signature (`pass_start`) back to the oldest
+/// wanted one, recording the oldest fully delivered signature in `cursor`.
+/// When a pass finishes, `synced_through` moves to `pass_start` so the next
+/// run only walks back until it meets already-delivered history.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackfillCheckpoint {
+    pub address: String,
+    pub pass_start: Option<String>,
+    pub cursor: Option<String>,
+    pub synced_through: Option<String>,
+}
+
+type ProgressCallback<'a> = Box<dyn FnMut(&BackfillProgress) + 'a>;
+
+/// Paced, resumable walk over every transaction touching an address
+pub struct HistoryBackfill<'a> {
+    rpc: RpcClient,
+    program_id: Pubkey,
+    address: Pubkey,
+    config: BackfillConfig,
+    on_progress: Option<ProgressCallback<'a>>,
+}
+
+impl TokenVaultClient {
+    /// Create a backfill runner over the history of `address` (usually a vault)
+    ///
+    /// The run is bounded by the earlier of `config.deadline` and the client's deadline.
+    pub fn history_backfill(
+        &self,
+        address: Pubkey,
+        mut config: BackfillConfig,
+    ) -> HistoryBackfill<'_> {
+        config.deadline = config.deadline.min(self.deadline());
+        let rpc = self.read_rpc_until(config.deadline);
+        HistoryBackfill::new(rpc, self.program.id(), address, config)
+    }
+
+    /// Up to `limit` transactions of the current vault before `cursor`,
+    /// newest first. The page's context slot is read after its signatures,
+    /// so it is at or after every transaction on the page.
+    pub fn get_history_page(
+        &self,
+        cursor: Option<&Cursor<VaultTransaction>>,
+        limit: usize,
+    ) -> Result<Page<VaultTransaction>> {
+        check_limit(limit)?;
+        let vault = self.current_vault()?;
+        let before = cursor
+            .map(|cursor| parse_signature(cursor.key()))
+            .transpose()?;
+        let backfill = self.history_backfill(vault, BackfillConfig::default());
+        let config = &backfill.config;
+
+        // One extra signature tells whether another page follows
+        let requested = (limit + 1).min(MAX_SIGNATURE_PAGE_SIZE);
+        let (mut statuses, _) = with_backoff(config, || {
+            backfill.rpc.get_signatures_for_address_with_config(
+                &vault,
+                GetConfirmedSignaturesForAddress2Config {
+                    before,
+                    until: None,
+                    limit: Some(requested),
+                    commitment: Some(config.commitment),
+                },
+            )
+        })?;
+        let (context_slot, _) = with_backoff(config, || {
+            backfill.rpc.get_slot_with_commitment(config.commitment)
+        })?;
+
+        let more = statuses.len() > limit || statuses.len() == MAX_SIGNATURE_PAGE_SIZE;
+        statuses.truncate(limit);
+        let wanted: Vec<_> = statuses.iter().collect();
+        let items = backfill.fetch_page(&wanted, &mut BackfillReport::default())?;
+        let next_cursor = match more {
+            true => items.last().map(Cursor::after),
+            false => None,
+        };
+        Ok(Page {
+            items,
+            next_cursor,
+            context_slot,
+        })
+    }
+}
+
+impl<'a> HistoryBackfill<'a> {
+    pub fn new(
+        rpc: RpcClient,
+        program_id: Pubkey,
+        address: Pubkey,
+        config: BackfillConfig,
+    ) -> Self {
+        Self {
+            rpc,
+            program_id,
+            address,
+            config,
+            on_progress: None,
+        }
+    }
+
+    /// Register a callback invoked after every processed page
+    pub fn on_progress(mut self, callback: impl FnMut(&BackfillProgress) + 'a) -> Self {
+        self.on_progress = Some(Box::new(callback));
+        self
+    }
+
+    /// Run until the history is exhausted, `since` or `after_slot` is reached,
+    /// or an error occurs
+    pub fn run(&mut self, sink: &mut dyn TransactionSink) -> Result<BackfillReport> {
+        if self.config.page_size == 0 || self.config.page_size > MAX_SIGNATURE_PAGE_SIZE {
+            return Err(anyhow!(
+                "Page size must be between 1 and {}",
+                MAX_SIGNATURE_PAGE_SIZE
+            ));
+        }
+
+        let mut checkpoint = self.load_checkpoint()?;
+        let mut report = BackfillReport {
+            resumed: checkpoint.cursor.is_some(),
+            ..Default::default()
+        };
+        let mut progress = BackfillProgress::default();
+        let started = Instant::now();
+        let mut newest_block_time = None;
+
+        let until = checkpoint
+            .synced_through
+            .as_deref()
+            .map(parse_signature)
+            .transpose()?;
+
+        loop {
+            self.config.deadline.check(DeadlinePhase::Backfill, None)?;
+            let before = checkpoint
+                .cursor
+                .as_deref()
+                .map(parse_signature)
+                .transpose()?;
+            let (page, retries) = with_backoff(&self.config, || {
+                let config = GetConfirmedSignaturesForAddress2Config {
+                    before,
+                    until,
+                    limit: Some(self.config.page_size),
+                    commitment: Some(self.config.commitment),
+                };
+                self.rpc
+                    .get_signatures_for_address_with_config(&self.address, config)
+            })?;
+            report.rate_limited_retries += retries;
+            self.config
+                .deadline
+                .sleep(self.config.pacing, DeadlinePhase::Backfill)?;
+
+            if checkpoint.pass_start.is_none() {
+                checkpoint.pass_start = page.first().map(|s| s.signature.clone());
+            }
+            if newest_block_time.is_none() {
+                newest_block_time = page.first().and_then(|s| s.block_time);
+            }
+
+            let reached_since = match self.config.since {
+                Some(since) => page.iter().any(|s| s.block_time.is_some_and(|t| t < since)),
+                None => false,
+            } || match self.config.after_slot {
+                Some(after_slot) => page.iter().any(|s| s.slot <= after_slot),
+                None => false,
+            };
+            let wanted: Vec<&RpcConfirmedTransactionStatusWithSignature> = page
+                .iter()
+                .filter(|s| match (self.config.since, s.block_time) {
+                    (Some(since), Some(block_time)) => block_time >= since,
+                    _ => true,
+                })
+                .filter(|s| {
+                    self.config
+                        .after_slot
+                        .is_none_or(|after_slot| s.slot > after_slot)
+                })
+                .collect();
+
+            progress.signatures_discovered += wanted.len();
+            for transaction in self.fetch_page(&wanted, &mut report)? {
+                progress.oldest_block_time = transaction.block_time.or(progress.oldest_block_time);
+                sink.write(&transaction)?;
+                progress.transactions_decoded += 1;
+            }
+            sink.flush()?;
+
+            let exhausted = page.len() < self.config.page_size || reached_since;
+            if exhausted {
+                // The pass is complete: everything up to its starting point is delivered
+                if checkpoint.pass_start.is_some() {
+                    checkpoint.synced_through = checkpoint.pass_start.take();
+                }
+                checkpoint.cursor = None;
+            } else {
+                checkpoint.cursor = page.last().map(|s| s.signature.clone());
+            }
+            self.save_checkpoint(&checkpoint)?;
+
+            progress.eta = estimate_eta(
+                started.elapsed(),
+                newest_block_time,
+                progress.oldest_block_time,
+                self.config.since,
+            );
+            if let Some(callback) = self.on_progress.as_mut() {
+                callback(&progress);
+            }
+
+            if exhausted {
+                break;
+            }
+        }
+
+        report.signatures_discovered = progress.signatures_discovered;
+        report.transactions_decoded = progress.transactions_decoded;
+        Ok(report)
+    }
+
+    /// Fetch and decode a page of transactions, at most `max_concurrent_fetches` at a time
+    fn fetch_page(
+        &self,
+        statuses: &[&RpcConfirmedTransactionStatusWithSignature],
+        report: &mut BackfillReport,
+    ) -> Result<Vec<VaultTransaction>> {
+        let (rpc, config, program_id) = (&self.rpc, &self.config, &self.program_id);
+        let concurrency = config.max_concurrent_fetches.max(1);
+        let mut decoded = Vec::with_capacity(statuses.len());
+
+        for chunk in statuses.chunks(concurrency) {
+            let results: Vec<Result<(VaultTransaction, DecodeStats, usize)>> =
+                thread::scope(|scope| {
+                    let handles: Vec<_> = chunk
+                        .iter()
+                        .map(|status| {
+                            scope.spawn(move || {
+                                fetch_transaction(rpc, config, program_id, &status.signature)
+                            })
+                        })
+                        .collect();
+                    handles
+                        .into_iter()
+                        .map(|handle| {
+                            handle.join().unwrap_or_else(|_| {
+                                Err(anyhow!("Transaction fetch thread panicked"))
+                            })
+                        })
+                        .collect()
+                });
+
+            for result in results {
+                let (transaction, stats, retries) = result?;
+                report.rate_limited_retries += retries;
+                report.largest_transaction_bytes =
+                    report.largest_transaction_bytes.max(stats.fetched_bytes);
+                report.trimmed_transactions += stats.trimmed as usize;
+                decoded.push(transaction);
+            }
+            self.config
+                .deadline
+                .sleep(self.config.pacing, DeadlinePhase::Backfill)?;
+        }
+
+        let retained = decoded.iter().map(VaultTransaction::retained_bytes).sum();
+        report.peak_retained_bytes = report.peak_retained_bytes.max(retained);
+        Ok(decoded)
+    }
+
+    fn load_checkpoint(&self) -> Result<BackfillCheckpoint> {
+        let fresh = BackfillCheckpoint {
+            address: self.address.to_string(),
+            ..Default::default()
+        };
+        let path = match &self.config.checkpoint_path {
+            Some(path) if path.exists() => path,
+            _ => return Ok(fresh),
+        };
+
+        let checkpoint: BackfillCheckpoint = serde_json::from_str(&fs::read_to_string(path)?)?;
+        if checkpoint.address != fresh.address {
+            return Err(anyhow!(
+                "Checkpoint {} belongs to {}, not {}",
+                path.display(),
+                checkpoint.address,
+                fresh.address
+            ));
+        }
+        Ok(checkpoint)
+    }
+
+    fn save_checkpoint(&self, checkpoint: &BackfillCheckpoint) -> Result<()> {
+        let path = match &self.config.checkpoint_path {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+
+        // Write-then-rename so a kill mid-write never leaves a truncated checkpoint
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, serde_json::to_vec_pretty(checkpoint)?)?;
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+}
+
+pub(crate) fn fetch_transaction(
+    rpc: &RpcClient,
+    config: &BackfillConfig,
+    program_id: &Pubkey,
+    signature: &str,
+) -> Result<(VaultTransaction, DecodeStats, usize)> {
+    let signature: Signature = parse_signature(signature)?;
+    let (fetched, retries) = with_backoff(config, || {
+        let tx_config = RpcTransactionConfig {
+            encoding: Some(UiTransactionEncoding::Base64),
+            commitment: Some(config.commitment),
+            max_supported_transaction_version: Some(0),
+        };
+        rpc.get_transaction_with_config(&signature, tx_config)
+    })?;
+    let (transaction, stats) =
+        decode_transaction_within(program_id, signature, fetched, config.max_transaction_bytes)?;
+    Ok((transaction, stats, retries))
+}
+
+/// Retry `op` with exponential backoff while the RPC answers with HTTP 429,
+/// returning the value and the number of retries it took. Backoff never
+/// sleeps past `config.deadline`.
+fn with_backoff<T>(
+    config: &BackfillConfig,
+    mut op: impl FnMut() -> Result<T, ClientError>,
+) -> Result<(T, usize)> {
+    let mut delay = config.initial_backoff;
+    let mut retries = 0;
+    loop {
+        match op() {
+            Ok(value) => return Ok((value, retries)),
+            Err(err) if is_rate_limited(&err) && retries < config.max_retries as usize => {
+                retries += 1;
+                config.deadline.sleep(delay, DeadlinePhase::Backfill)?;
+                delay = (delay * 2).min(config.max_backoff);
+            }
+            Err(_) if config.deadline.is_expired() => {
+                return Err(TokenVaultError::DeadlineExceeded {
+                    phase: DeadlinePhase::Backfill,
+                    signature: None,
+                }
+                .into())
+            }
+            Err(err) => return Err(err.into()),
+        }
+    }
+}
+
+fn is_rate_limited(err: &ClientError) -> bool {
+    let message = err.to_string();
+    message.contains("429") || message.contains("Too Many Requests")
+}
+
+/// Linear extrapolation of the remaining time from the block-time range covered so far
+fn estimate_eta(
+    elapsed: Duration,
+    newest: Option<i64>,
+    oldest: Option<i64>,
+    since: Option<i64>,
+) -> Option<Duration> {
+    let (newest, oldest, since) = (newest?, oldest?, since?);
+    let total = (newest - since) as f64;
+    let covered = (newest - oldest) as f64;
+    if total <= 0.0 || covered <= 0.0 {
+        return None;
+    }
+    let fraction = (covered / total).min(1.0);
+    Some(elapsed.mul_f64((1.0 - fraction) / fraction))
+}
@@ -0,0 +1,394 @@
+use anchor_client::solana_sdk::{pubkey::Pubkey, signature::Signature};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{hash_map::Entry, HashMap};
+use std::fmt;
+
+use crate::json::ErrorBody;
+use crate::pause::check_not_paused;
+use crate::signer::VaultSigner;
+use crate::token_vault::state::Vault;
+use crate::{accounting, amount};
+use crate::{
+    CostEstimate, DepositQuote, DepositSource, OperationKind, Payout, TokenVaultClient,
+    VaultLifecycle, WithdrawOptions, WithdrawalQuote,
+};
+
+/// One operation of a batch, as scripts write it:
+///
+/// ```json
+/// {"op": "withdraw", "amount": 500, "to": "9xQe...", "vault": "Gk3f..."}
+/// ```
+///
+/// `vault` defaults to the client's vault. `mint`, when given, must be the
+/// vault's mint, so amounts meant for another vault are rejected. Unknown fields are rejected, so a
+/// misspelt recipient can't silently fall back to the signer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case", deny_unknown_fields)]
+pub enum VaultOperation {
+    Deposit {
+        #[serde(default, with = "crate::serde_utils::option_pubkey")]
+        vault: Option<Pubkey>,
+        amount: u64,
+        /// Mint the amount is denominated in; must be the vault's
+        #[serde(default, with = "crate::serde_utils::option_pubkey")]
+        mint: Option<Pubkey>,
+    },
+    Withdraw {
+        #[serde(default, with = "crate::serde_utils::option_pubkey")]
+        vault: Option<Pubkey>,
+        amount: u64,
+        /// Mint the amount is denominated in; must be the vault's
+        #[serde(default, with = "crate::serde_utils::option_pubkey")]
+        mint: Option<Pubkey>,
+        /// Recipient wallet; the signer's own when absent
+        #[serde(default, with = "crate::serde_utils::option_pubkey")]
+        to: Option<Pubkey>,
+    },
+}
+
+impl VaultOperation {
+    pub fn vault(&self) -> Option<Pubkey> {
+        match self {
+            Self::Deposit { vault, .. } | Self::Withdraw { vault, .. } => *vault,
+        }
+    }
+
+    pub fn mint(&self) -> Option<Pubkey> {
+        match self {
+            Self::Deposit { mint, .. } | Self::Withdraw { mint, .. } => *mint,
+        }
+    }
+}
+
+impl fmt::Display for VaultOperation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Deposit { amount, .. } => write!(f, "deposit {}", amount),
+            Self::Withdraw {
+                amount, to: None, ..
+            } => write!(f, "withdraw {}", amount),
+            Self::Withdraw {
+                amount,
+                to: Some(to),
+                ..
+            } => write!(f, "withdraw {} to {}", amount, to),
+        }
+    }
+}
+
+/// A non-blank input line, parsed or not; `line` counts from 1
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BatchLine {
+    pub line: usize,
+    pub request: Result<VaultOperation, String>,
+}
+
+/// Parse newline-delimited JSON operations. Blank lines are skipped; a
+/// malformed line is kept with its error so it can be reported in order.
+pub fn parse_operations(input: &str) -> Vec<BatchLine> {
+    input
+        .lines()
+        .enumerate()
+        .filter(|(_, text)| !text.trim().is_empty())
+        .map(|(index, text)| BatchLine {
+            line: index + 1,
+            request: serde_json::from_str(text).map_err(|err| err.to_string()),
+        })
+        .collect()
+}
+
+/// A validated operation, ready to send; `vault` is resolved
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlannedOperation {
+    pub vault: Pubkey,
+    pub operation: VaultOperation,
+    /// Fee and payout of a withdrawal
+    pub quote: Option<WithdrawalQuote>,
+    /// Reserve carve-out of a deposit
+    pub deposit_quote: Option<DepositQuote>,
+}
+
+/// `ErrorBody::code` of a line that is not a valid operation
+pub const MALFORMED_OPERATION: &str = "malformed_operation";
+
+/// One line of a `BatchPlan`: planned, or rejected with the reason
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlannedLine {
+    pub line: usize,
+    pub plan: Result<PlannedOperation, ErrorBody>,
+}
+
+/// Every line of a batch, in input order
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BatchPlan {
+    pub lines: Vec<PlannedLine>,
+    /// Fees for sending the planned lines, if estimates were available
+    pub cost: Option<CostEstimate>,
+}
+
+impl BatchPlan {
+    pub fn planned(&self) -> impl Iterator<Item = (usize, &PlannedOperation)> {
+        self.lines
+            .iter()
+            .filter_map(|line| line.plan.as_ref().ok().map(|plan| (line.line, plan)))
+    }
+
+    pub fn rejected(&self) -> impl Iterator<Item = (usize, &str)> {
+        self.lines.iter().filter_map(|line| {
+            line.plan
+                .as_ref()
+                .err()
+                .map(|err| (line.line, err.message.as_str()))
+        })
+    }
+
+    pub fn has_rejections(&self) -> bool {
+        self.rejected().next().is_some()
+    }
+
+    /// Results for a batch that will not run: rejected lines as such, every
+    /// planned line skipped for `reason`
+    pub fn skipped(&self, reason: &str) -> Vec<BatchResult> {
+        self.lines
+            .iter()
+            .map(|line| BatchResult {
+                line: line.line,
+                outcome: match &line.plan {
+                    Ok(_) => BatchOutcome::Skipped {
+                        reason: reason.to_string(),
+                    },
+                    Err(error) => BatchOutcome::Rejected {
+                        error: error.clone(),
+                    },
+                },
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for BatchPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (mut deposited, mut withdrawn, mut fees, mut reserved) = (0u128, 0u128, 0u128, 0u128);
+        writeln!(f, "Batch of {} operations:", self.planned().count())?;
+        for (line, plan) in self.planned() {
+            write!(
+                f,
+                "  line {}: {} on vault {}",
+                line, plan.operation, plan.vault
+            )?;
+            match plan.operation {
+                VaultOperation::Deposit { amount, .. } => deposited += amount as u128,
+                VaultOperation::Withdraw { amount, .. } => withdrawn += amount as u128,
+            }
+            if let Some(quote) = &plan.quote {
+                fees += quote.fee as u128;
+                write!(f, " (fee {}, receive {})", quote.fee, quote.net_amount)?;
+            }
+            if let Some(quote) = plan.deposit_quote.filter(|quote| quote.reserve > 0) {
+                reserved += quote.reserve as u128;
+                write!(f, " (reserve {}, credit {})", quote.reserve, quote.credited)?;
+            }
+            writeln!(f)?;
+        }
+        write!(
+            f,
+            "Total: deposit {} (reserve {}), withdraw {} (fees {})",
+            deposited, reserved, withdrawn, fees
+        )?;
+        if let Some(cost) = &self.cost {
+            write!(f, "\nNetwork cost: {}", cost)?;
+        }
+        if self.has_rejections() {
+            write!(f, "\nRejected:")?;
+            for (line, error) in self.rejected() {
+                write!(f, "\n  line {}: {}", line, error)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Outcome of one input line, emitted as one JSON object per line
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BatchResult {
+    pub line: usize,
+    #[serde(flatten)]
+    pub outcome: BatchOutcome,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum BatchOutcome {
+    Executed {
+        #[serde(with = "crate::serde_utils::signature")]
+        signature: Signature,
+    },
+    /// Malformed or failed validation; never sent
+    Rejected { error: ErrorBody },
+    /// Sent, or attempted, and failed
+    Failed { error: ErrorBody },
+    /// Valid but not run
+    Skipped { reason: String },
+}
+
+impl TokenVaultClient {
+    /// Validate every line against current vault state, as sent by
+    /// `signer`. Lines that fail are rejected individually; nothing is sent.
+    /// Each deposit is checked against the signer's balance less the
+    /// deposits planned before it.
+    pub fn plan_batch(&self, signer: &Pubkey, lines: &[BatchLine]) -> BatchPlan {
+        let deadline = self.deadline();
+        let mut vaults: HashMap<Pubkey, Result<Vault, String>> = HashMap::new();
+        let mut lifecycles: HashMap<Pubkey, VaultLifecycle> = HashMap::new();
+        let mut deposited: HashMap<Pubkey, u64> = HashMap::new();
+
+        let mut plan_line = |operation: &VaultOperation| -> Result<PlannedOperation> {
+            let vault = operation
+                .vault()
+                .or(self.vault_address)
+                .ok_or_else(|| anyhow!("No vault: set \"vault\" or pass a default vault"))?;
+            let vault_data = vaults
+                .entry(vault)
+                .or_insert_with(|| {
+                    self.fetch_vault(vault, deadline)
+                        .map_err(|err| format!("{:#}", err))
+                })
+                .clone()
+                .map_err(|err| anyhow!(err))?;
+            amount::ensure_mint(vault_data.token_mint, operation.mint())?;
+
+            let (quote, deposit_quote) = match *operation {
+                VaultOperation::Deposit { amount, .. } => {
+                    let deposit_quote = DepositQuote::for_vault(&vault_data, amount)?;
+                    let lifecycle = match lifecycles.entry(vault) {
+                        Entry::Occupied(entry) => entry.into_mut(),
+                        Entry::Vacant(entry) => {
+                            entry.insert(self.vault_lifecycle(vault, &vault_data, deadline)?)
+                        }
+                    };
+                    lifecycle.check(OperationKind::Deposit)?;
+                    let mint = vault_data.token_mint;
+                    let total = deposited.get(&mint).copied().unwrap_or(0);
+                    let total = total
+                        .checked_add(amount)
+                        .ok_or_else(|| anyhow!("Deposits of mint {} overflow", mint))?;
+                    self.verify_deposit_source(signer, &mint, total, deadline)?;
+                    deposited.insert(mint, total);
+                    (None, Some(deposit_quote))
+                }
+                VaultOperation::Withdraw { amount, .. } => {
+                    let quote = WithdrawalQuote::for_vault(&vault_data, amount)?;
+                    check_not_paused(&vault_data)?;
+                    if self.strict_accounting {
+                        accounting::check_total_deposited(&vault_data, amount)?;
+                    }
+                    (Some(quote), None)
+                }
+            };
+            Ok(PlannedOperation {
+                vault,
+                operation: *operation,
+                quote,
+                deposit_quote,
+            })
+        };
+
+        let lines: Vec<PlannedLine> = lines
+            .iter()
+            .map(|line| PlannedLine {
+                line: line.line,
+                plan: match &line.request {
+                    Ok(operation) => plan_line(operation).map_err(|err| ErrorBody::of(&err)),
+                    Err(err) => Err(ErrorBody::new(
+                        MALFORMED_OPERATION,
+                        format!("Malformed operation: {}", err),
+                    )),
+                },
+            })
+            .collect();
+        BatchPlan {
+            cost: self.estimate_batch_cost(signer, &lines),
+            lines,
+        }
+    }
+
+    /// Base fees for one transaction per planned line, signed by `signer`
+    /// and the payer. `None` without estimates, which never fails a plan.
+    fn estimate_batch_cost(&self, signer: &Pubkey, lines: &[PlannedLine]) -> Option<CostEstimate> {
+        let planned = lines.iter().filter(|line| line.plan.is_ok()).count() as u64;
+        let (tables, stale) = self.estimates().ok()?;
+        let signatures = if *signer == self.program.payer() {
+            1
+        } else {
+            2
+        };
+        Some(tables.cost(0, planned * tables.fee_for(signatures), stale))
+    }
+
+    /// Send the planned lines in order, reporting every line's outcome to
+    /// `emit` as soon as it is known. A failed operation does not stop the
+    /// ones after it.
+    pub fn execute_batch(
+        &self,
+        signer: &dyn VaultSigner,
+        plan: &BatchPlan,
+        emit: &mut dyn FnMut(BatchResult) -> Result<()>,
+    ) -> Result<()> {
+        for line in &plan.lines {
+            let outcome = match &line.plan {
+                Err(error) => BatchOutcome::Rejected {
+                    error: error.clone(),
+                },
+                Ok(planned) => {
+                    match self.send_vault_operation(planned.vault, signer, &planned.operation) {
+                        Ok(signature) => BatchOutcome::Executed { signature },
+                        Err(err) => BatchOutcome::Failed {
+                            error: ErrorBody::of(&err),
+                        },
+                    }
+                }
+            };
+            emit(BatchResult {
+                line: line.line,
+                outcome,
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Send `operation` to `vault`, which need not be the current vault
+    pub(crate) fn send_vault_operation(
+        &self,
+        vault: Pubkey,
+        signer: &dyn VaultSigner,
+        operation: &VaultOperation,
+    ) -> Result<Signature> {
+        match *operation {
+            VaultOperation::Deposit { amount, mint, .. } => self
+                .send_deposit(
+                    vault,
+                    signer,
+                    DepositSource::Associated,
+                    amount,
+                    mint,
+                    None,
+                    None,
+                )
+                .map(|(signature, _)| signature),
+            VaultOperation::Withdraw {
+                amount, mint, to, ..
+            } => {
+                let recipient = to.unwrap_or_else(|| signer.pubkey());
+                self.send_withdraw(
+                    vault,
+                    signer,
+                    amount,
+                    mint,
+                    Payout::Wallet(recipient),
+                    WithdrawOptions::default(),
+                )
+            }
+        }
+    }
+}
@@ -1,13 +1,12 @@
 use anchor_client::{
-    solana_sdk::{
-        pubkey::Pubkey,
-        signature::Keypair,
-    },
+    solana_sdk::pubkey::Pubkey,
     Cluster,
 };
 use anyhow::Result;
 use std::str::FromStr;
-use token_vault_client::{TokenVaultClient, utils};
+use std::sync::Arc;
+use std::time::Duration;
+use token_vault_client::{AsyncTokenVaultClient, DepositWindow, DustPolicy, VaultConfig, utils};
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -20,50 +19,59 @@ async fn main() -> Result<()> {
     // Connect to Solana cluster
     let cluster = Cluster::Devnet;
     
-    // Create token vault client
-    let mut client = TokenVaultClient::new(
+    // Create token vault client; its calls return futures and can be made from spawned tasks
+    let client = AsyncTokenVaultClient::new(
         cluster, 
         payer,
         program_id,
-    )?;
+    ).await?;
     
     // Example: Initialize a new vault
     let token_mint = Pubkey::from_str("YourTokenMintAddressHere")?;
-    let authority = utils::load_keypair("~/.config/solana/id.json")?;
+    let authority = Arc::new(utils::load_keypair("~/.config/solana/id.json")?);
     
-    let vault_address = client.initialize_vault(
-        &authority,
-        token_mint,
-        "My Token Vault",
-        100, // 1% fee (in basis points)
-        86400, // 1 day timelock (in seconds)
-        1000_000_000, // Withdrawal limit (adjust decimal places based on token decimals)
-    )?;
+    let config = VaultConfig::builder()
+        .name("My Token Vault")
+        .fee_bps(100) // 1% fee (in basis points)
+        .timelock(Duration::from_secs(86400)) // 1 day timelock
+        .withdrawal_limit_ui("1000") // Withdrawal limit in whole tokens, converted with the mint's decimals
+        .dust_policy(DustPolicy::reject_below(1_000)) // Refuse withdrawals too small to charge a fee on
+        .build()?;
+    let vault_address = client.initialize_vault(authority.clone(), token_mint, config).await?.address;
     
     println!("Vault initialized with address: {}", vault_address);
     
     // Set the vault address for future operations
-    client.with_vault(vault_address);
+    client.set_vault(vault_address).await?;
     
     // Example: Deposit tokens
-    client.deposit(
-        &authority,
-        1000_000_000, // Amount to deposit (adjust decimal places based on token decimals)
-    )?;
+    let receipt = client.deposit(
+        authority.clone(),
+        1_000_000_000, // Amount to deposit (adjust decimal places based on token decimals)
+    ).await?;
+    println!("Deposited in {}", receipt);
     
     // Example: Withdraw tokens
     client.withdraw(
-        &authority,
+        authority,
         500_000_000, // Amount to withdraw (adjust decimal places based on token decimals)
-    )?;
+        None,        // Or Some(recipient) to withdraw straight to a third party
+    ).await?;
     
     // Example: Get vault information
-    let vault_info = client.get_vault_info()?;
+    let vault_info = client.get_vault_info().await?;
     println!("Vault Info:");
     println!("  Authority: {}", vault_info.authority);
     println!("  Token Mint: {}", vault_info.token_mint);
     println!("  Fee Percentage: {}", vault_info.fee_percentage);
     println!("  Total Deposited: {}", vault_info.total_deposited);
+    println!("  Immutable: {}", vault_info.immutable);
+    let deposit_window = DepositWindow::of(&vault_info);
+    println!(
+        "  Deposit Window: {} ({})",
+        deposit_window,
+        deposit_window.describe(client.call(|c| c.cluster_time()).await?)
+    );
     
     Ok(())
 }
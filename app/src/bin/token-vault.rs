@@ -0,0 +1,1313 @@
+use anchor_client::{
+    solana_client::rpc_client::RpcClient,
+    solana_sdk::{
+        native_token::sol_to_lamports,
+        pubkey::Pubkey,
+        signature::{Keypair, Signer},
+    },
+    Cluster,
+};
+use anyhow::{anyhow, Result};
+use clap::ArgMatches;
+use clap_complete::Shell;
+use std::fs::{self, OpenOptions};
+use std::io::{self, BufRead, Read, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+use token_vault_client::allowlist::{allowlist_csv, parse_allowlist_csv};
+use token_vault_client::backfill::JsonLinesSink;
+use token_vault_client::batch::parse_operations;
+use token_vault_client::cli::{self, CliConfig, Profile};
+use token_vault_client::features::{fetch_program_features, CLIENT_VERSION};
+use token_vault_client::fee_pool;
+use token_vault_client::json::{
+    BooksView, ErrorBody, LiquidityView, PageView, RepairView, TransactionView, TransferView,
+    VaultView,
+};
+use token_vault_client::key_policy::is_mainnet;
+use token_vault_client::plan::format_sol;
+use token_vault_client::reserve::reserve_unlocks_at;
+use token_vault_client::schedule::format_timestamp;
+use token_vault_client::support::{EffectiveConfig, RedactionRules};
+use token_vault_client::{
+    token_vault, utils, verify_action_receipt, ActionReceipt, Attestation, BackfillConfig,
+    BatchOutcome, BatchResult, ConfigChanges, Cursor, DepositOptions, DepositWindow, DustPolicy,
+    EndpointHealth, FeatureSet, FeeReservePool, LaunchOptions, MaintenanceStatus, MintAmount,
+    MirrorOptions, NotificationConfig, Page, Paginated, ReservePolicy, StepStatus,
+    TokenVaultClient, TokenVaultError, TxReceipt, UpdateVaultParams, VaultConfig, VaultIndex,
+    WithdrawOptions,
+};
+
+const DEFAULT_KEYPAIR: &str = "~/.config/solana/id.json";
+
+fn main() -> Result<()> {
+    let matches = cli::build_cli().get_matches();
+    let json = wants_json(&matches);
+    match run(matches) {
+        // A script asking for JSON gets the failure as JSON too
+        Err(err) if json => {
+            println!("{}", serde_json::to_string_pretty(&ErrorBody::of(&err))?);
+            std::process::exit(1);
+        }
+        result => result,
+    }
+}
+
+/// Whether the invoked command, at any depth, was asked for JSON output
+fn wants_json(matches: &ArgMatches) -> bool {
+    let json = matches!(matches.try_get_one::<bool>("json"), Ok(Some(true)))
+        || matches!(
+            matches.try_get_one::<String>("format"),
+            Ok(Some(format)) if format == "json"
+        );
+    json || matches.subcommand().is_some_and(|(_, sub)| wants_json(sub))
+}
+
+fn run(matches: ArgMatches) -> Result<()> {
+    let config = CliConfig::load(&CliConfig::default_path())?;
+
+    // None of these needs a connection
+    match matches.subcommand() {
+        Some(("completions", sub)) => {
+            let shell = *sub.get_one::<Shell>("shell").expect("required");
+            cli::write_completions(shell, &config, &mut io::stdout());
+            return Ok(());
+        }
+        Some(("man", _)) => return cli::write_man_page(&mut io::stdout()),
+        Some(("index", sub)) if sub.subcommand_name() != Some("refresh") => return edit_index(sub),
+        Some(("verify-receipt", sub)) => return verify_receipts(sub),
+        _ => {}
+    }
+
+    let profile = match matches.get_one::<String>("profile") {
+        Some(name) => config.profile(name)?.clone(),
+        None => Default::default(),
+    };
+    let url = matches
+        .get_one::<String>("url")
+        .or(profile.url.as_ref())
+        .map(String::as_str)
+        .unwrap_or("devnet");
+    let keypair_path = matches
+        .get_one::<String>("keypair")
+        .or(profile.keypair.as_ref())
+        .map(String::as_str)
+        .unwrap_or(DEFAULT_KEYPAIR);
+    let program_id = match matches
+        .get_one::<String>("program-id")
+        .or(profile.program_id.as_ref())
+    {
+        Some(id) => parse_pubkey("program id", id)?,
+        None => token_vault::ID,
+    };
+
+    let write_url = matches
+        .get_one::<String>("write-url")
+        .or(profile.write_url.as_ref());
+    let read_url = matches
+        .get_one::<String>("read-url")
+        .or(profile.read_url.as_ref());
+
+    let cluster = Cluster::from_str(url)?;
+    let signer = utils::load_keypair(keypair_path)?;
+    let vault = resolve_vault(&matches, &config, &program_id)?;
+    if let Some(("doctor", _)) = matches.subcommand() {
+        return doctor(
+            &cluster,
+            program_id,
+            keypair_path,
+            &signer,
+            &profile,
+            vault,
+            (write_url, read_url),
+        );
+    }
+
+    let mut client = TokenVaultClient::new(cluster.clone(), signer.insecure_clone(), program_id)?;
+    if let Some(url) = write_url {
+        client.with_write_rpc(url)?;
+    }
+    if let Some(url) = read_url {
+        client.with_read_rpc(url)?;
+    }
+    client.with_strict_mint_policy(profile.strict_mint_policy);
+    client.with_strict_accounting(profile.strict_accounting);
+    client.with_estimates_path(CliConfig::estimates_path())?;
+    if let Some(policy) = profile.key_policy.clone() {
+        if matches.get_flag("override-key-policy") {
+            eprintln!("Warning: key policy overridden for {}", signer.pubkey());
+        } else {
+            client.with_key_policy(policy)?;
+        }
+    }
+    if let Some(vault) = vault {
+        // Each run works on the one vault it was given
+        #[allow(deprecated)]
+        client.with_vault(vault);
+    }
+    let require_vault = || {
+        vault
+            .ok_or_else(|| anyhow!("--vault, --vault-name or --alias is required for this command"))
+    };
+
+    let (name, sub) = matches.subcommand().expect("subcommand is required");
+    match name {
+        "init" => {
+            let timelock = *sub.get_one::<i64>("timelock").expect("required");
+            let timelock =
+                u64::try_from(timelock).map_err(|_| anyhow!("--timelock cannot be negative"))?;
+            let builder = VaultConfig::builder()
+                .name(sub.get_one::<String>("name").expect("required"))
+                .fee_bps(*sub.get_one::<u16>("fee-bps").expect("required"))
+                .timelock(Duration::from_secs(timelock));
+            let mut builder = match sub.get_one::<String>("withdrawal-limit-ui") {
+                Some(amount) => builder.withdrawal_limit_ui(amount),
+                None => builder
+                    .withdrawal_limit(*sub.get_one::<u64>("withdrawal-limit").expect("required")),
+            }
+            .immutable(sub.get_flag("immutable"))
+            .reserve(ReservePolicy::new(
+                *sub.get_one::<u16>("reserve-bps").expect("defaulted"),
+                *sub.get_one::<i64>("reserve-timelock").expect("defaulted"),
+            )?);
+            if let Some(window) = deposit_window(sub)? {
+                builder = builder.deposit_window(window);
+            }
+            if let Some(policy) = dust_policy(sub) {
+                builder = builder.dust_policy(policy);
+            }
+            if let Some(fee_collector) = sub.get_one::<String>("fee-collector") {
+                builder = builder.fee_collector(parse_pubkey("fee collector", fee_collector)?);
+            }
+            let config = builder
+                .acknowledge_freezable_mint(sub.get_flag("acknowledge-freezable-mint"))
+                .build()?;
+            let mint = parse_pubkey("mint", sub.get_one::<String>("mint").expect("required"))?;
+            if sub.get_flag("dry-run") {
+                println!(
+                    "{}",
+                    client.plan_initialize_vault(signer.pubkey(), mint, &config)?
+                );
+                return Ok(());
+            }
+            let vault = client.initialize_vault(&signer, mint, &config)?;
+            println!("{}", vault.address);
+        }
+        "deposit" => {
+            let amount = *sub.get_one::<u64>("amount").expect("required");
+            let source = sub
+                .get_one::<String>("from")
+                .map(|from| parse_pubkey("source token account", from))
+                .transpose()?;
+            let deposited = match source {
+                Some(source) => client.deposit_from(&signer, source, amount),
+                None if sub.get_flag("sol") => client.deposit_sol(&signer, amount),
+                None => {
+                    let options = DepositOptions {
+                        memo: sub.get_one::<String>("memo").cloned(),
+                    };
+                    client.deposit_with_options(&signer, amount, options)
+                }
+            };
+            let receipt = match deposited {
+                Ok(receipt) => receipt,
+                Err(err) => {
+                    if let Some(TokenVaultError::SourceMintMismatch {
+                        vault_mint,
+                        source_mint,
+                        suggestion,
+                    }) = err.downcast_ref()
+                    {
+                        let book = config.address_book()?;
+                        let name = |mint: &Pubkey| match book.symbol(mint) {
+                            Some(symbol) => format!("{} ({})", symbol, mint),
+                            None => mint.to_string(),
+                        };
+                        eprintln!(
+                            "This vault takes {}, but you hold {}.",
+                            name(vault_mint),
+                            name(source_mint)
+                        );
+                        eprintln!("Hint: {}", suggestion);
+                    }
+                    return Err(err);
+                }
+            };
+            if sub.get_flag("json") {
+                let amount = vault_amount(&client, amount)?;
+                let view = TransferView::deposit(require_vault()?, amount, &receipt);
+                println!("{}", serde_json::to_string_pretty(&view)?);
+            } else {
+                print_receipt(&receipt);
+            }
+        }
+        "withdraw" => {
+            let amount = *sub.get_one::<u64>("amount").expect("required");
+            let recipient = sub
+                .get_one::<String>("to")
+                .map(|to| parse_pubkey("recipient", to))
+                .transpose()?;
+            if let Some(recipient) = recipient.filter(|r| *r != signer.pubkey()) {
+                println!("This withdrawal sends {} tokens to ANOTHER WALLET:", amount);
+                println!();
+                println!("    {}", recipient);
+                println!();
+                if !sub.get_flag("yes") && !confirm("Send the tokens to this wallet?")? {
+                    return Err(anyhow!("Aborted"));
+                }
+            }
+            let account = sub
+                .get_one::<String>("to-account")
+                .map(|to| parse_pubkey("destination token account", to))
+                .transpose()?;
+            let receipt = match account {
+                Some(account) => {
+                    println!(
+                        "This withdrawal sends {} tokens to the token account:",
+                        amount
+                    );
+                    println!();
+                    println!("    {}", account);
+                    println!();
+                    if !sub.get_flag("yes") && !confirm("Send the tokens to this account?")? {
+                        return Err(anyhow!("Aborted"));
+                    }
+                    client.withdraw_to(&signer, account, amount)?
+                }
+                None if sub.get_flag("sol") => client.withdraw_sol(&signer, amount)?,
+                None => {
+                    let options = WithdrawOptions {
+                        create_ata: sub.get_flag("create-ata"),
+                        skip_limit_check: sub.get_flag("skip-limit-check"),
+                        memo: sub.get_one::<String>("memo").cloned(),
+                    };
+                    client.withdraw_with_options(&signer, amount, recipient, options)?
+                }
+            };
+            if sub.get_flag("json") {
+                let amount = vault_amount(&client, amount)?;
+                let view = TransferView::withdraw(require_vault()?, amount, &receipt);
+                println!("{}", serde_json::to_string_pretty(&view)?);
+            } else {
+                print_receipt(&receipt);
+            }
+        }
+        "withdrawal" => match sub.subcommand().expect("subcommand is required") {
+            ("request", args) => {
+                let amount = *args.get_one::<u64>("amount").expect("required");
+                println!("{}", client.request_withdrawal(&signer, amount)?);
+            }
+            ("execute", args) => {
+                let request = parse_pubkey(
+                    "withdrawal request",
+                    args.get_one::<String>("request").expect("required"),
+                )?;
+                print_receipt(&client.execute_withdrawal(&signer, request)?);
+            }
+            ("cancel", args) => {
+                let request = parse_pubkey(
+                    "withdrawal request",
+                    args.get_one::<String>("request").expect("required"),
+                )?;
+                print_receipt(&client.cancel_withdrawal(&signer, request)?);
+            }
+            ("pending", args) => {
+                let pending = client.get_pending_withdrawals(signer.pubkey())?;
+                if args.get_flag("json") {
+                    println!("{}", serde_json::to_string_pretty(&pending)?);
+                } else if pending.is_empty() {
+                    println!("No pending withdrawals");
+                } else {
+                    for request in &pending {
+                        println!("{}", request);
+                    }
+                }
+            }
+            _ => unreachable!("subcommand_required"),
+        },
+        "quote" => {
+            let amount = *sub.get_one::<u64>("amount").expect("required");
+            if sub.get_flag("deposit") {
+                println!("{}", client.quote_deposit(amount)?);
+            } else {
+                println!("{}", client.preview_withdraw(amount)?);
+            }
+        }
+        "info" if sub.get_flag("json") => {
+            client.with_extensions(sub.get_flag("extensions"));
+            let summary = client.get_vault_summary()?;
+            let view = VaultView::of(&summary, client.mint_decimals(&summary.token_mint)?);
+            println!("{}", serde_json::to_string_pretty(&view)?);
+        }
+        "info" => {
+            let vault = client.get_vault_info()?;
+            println!("Name: {}", vault.name);
+            println!("Authority: {}", vault.authority);
+            println!("Token Mint: {}", vault.token_mint);
+            println!("Fee Collector: {}", vault.fee_collector);
+            println!("Fee Percentage: {}", vault.fee_percentage);
+            println!("Withdrawal Timelock: {} seconds", vault.withdrawal_timelock);
+            println!("Withdrawal Limit: {}", vault.withdrawal_limit);
+            println!("Total Deposited: {}", vault.total_deposited);
+            if vault.accrued_fees > 0 {
+                println!("Accrued Fees: {}", vault.accrued_fees);
+            }
+            println!("Immutable: {}", vault.immutable);
+            if vault.paused {
+                println!("Paused: deposits and withdrawals are stopped");
+            }
+            if vault.accounting_anomaly {
+                println!("Accounting Anomaly: total_deposited was clamped; run verify-books");
+            }
+            println!("Dust Policy: {}", DustPolicy::of(&vault));
+            println!("Reserve Policy: {}", ReservePolicy::of(&vault));
+            println!("Freeze: {}", client.get_freeze_status()?);
+            if vault.reserve_bps > 0 || vault.total_reserved > 0 {
+                println!("Total Reserved: {}", vault.total_reserved);
+                println!(
+                    "Reserve Unlocks At: {}",
+                    format_timestamp(reserve_unlocks_at(&vault))
+                );
+            }
+            let now = client.cluster_time()?;
+            println!(
+                "Deposit Window: {} ({})",
+                DepositWindow::of(&vault),
+                DepositWindow::of(&vault).describe(now)
+            );
+            let maintenance: Vec<_> = client
+                .get_maintenance_windows()?
+                .into_iter()
+                .filter(|window| window.status(now) != MaintenanceStatus::Ended)
+                .collect();
+            if maintenance.is_empty() {
+                println!("Maintenance: none scheduled");
+            }
+            for window in maintenance {
+                println!("Maintenance: {} ({})", window, window.describe(now));
+            }
+            let lifecycle = client.get_lifecycle()?;
+            println!("Lifecycle: {}", lifecycle);
+            println!(
+                "Permitted: {}",
+                lifecycle
+                    .permitted_operations()
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+            if sub.get_flag("extensions") {
+                match client.get_notification_config()? {
+                    Some(notifications) => println!("{}", notifications),
+                    None => println!("Notification Config: none"),
+                }
+            }
+        }
+        "estimates" => {
+            let tables = if sub.get_flag("refresh") {
+                client.refresh_estimates()?
+            } else {
+                client.estimates()?.0
+            };
+            println!("{}", tables);
+        }
+        "liquidity" => {
+            let profile = client.get_liquidity_profile()?;
+            match sub.get_one::<String>("format").map(String::as_str) {
+                Some("json") => println!(
+                    "{}",
+                    serde_json::to_string_pretty(&LiquidityView::from(&profile))?
+                ),
+                _ => println!("{}", profile),
+            }
+        }
+        "update" => {
+            let params = UpdateVaultParams {
+                fee_percentage: sub.get_one::<u16>("fee-bps").copied(),
+                withdrawal_timelock: sub.get_one::<i64>("timelock").copied(),
+                withdrawal_limit: sub.get_one::<u64>("withdrawal-limit").copied(),
+            };
+            print!("{}", client.plan_update(&params.into())?);
+            if sub.get_flag("yes") || confirm("Send the update?")? {
+                save_receipt(&client.update_vault_config(&signer, params)?)?;
+            }
+        }
+        "transfer-authority" => {
+            let to = sub.get_one::<String>("to").expect("required");
+            let vault = client.transfer_authority(&signer, parse_pubkey("new authority", to)?)?;
+            println!("Authority: {}", vault.authority);
+        }
+        "set-fee-collector" => {
+            let to = sub.get_one::<String>("to").expect("required");
+            let vault = client.set_fee_collector(&signer, parse_pubkey("fee collector", to)?)?;
+            println!("Fee collector: {}", vault.fee_collector);
+        }
+        "claim-fees" => println!("Claimed: {}", client.claim_fees(&signer)?),
+        "pause" => save_receipt(&client.pause_vault(&signer)?)?,
+        "unpause" => save_receipt(&client.unpause_vault(&signer)?)?,
+        "plan" => match sub.subcommand().expect("subcommand is required") {
+            ("update", args) => {
+                let changes = ConfigChanges {
+                    fee_percentage: args.get_one::<u16>("fee-bps").copied(),
+                    withdrawal_timelock: args.get_one::<i64>("timelock").copied(),
+                    withdrawal_limit: args.get_one::<u64>("withdrawal-limit").copied(),
+                    deposit_window: deposit_window(args)?,
+                    dust_policy: dust_policy(args),
+                };
+                print!("{}", client.plan_update(&changes)?);
+            }
+            ("set-fee-collector", args) => {
+                let fee_collector = args.get_one::<String>("fee-collector").expect("required");
+                print!(
+                    "{}",
+                    client.plan_set_fee_collector(parse_pubkey("fee collector", fee_collector)?)?
+                );
+            }
+            ("close", _) => println!("{}", client.plan_close_vault()?),
+            (other, _) => unreachable!("unknown plan subcommand {}", other),
+        },
+        "attest" => {
+            let vault = require_vault()?;
+            let attestation = client.sign_vault_attestation(
+                &signer,
+                vault,
+                *sub.get_one::<u64>("nonce").expect("required"),
+                *sub.get_one::<i64>("expiry").expect("required"),
+            )?;
+            println!("{}", attestation.to_json()?);
+        }
+        "verify-attestation" => {
+            let input = read_input(sub.get_one::<String>("file").expect("required"))?;
+            let input = input.trim();
+            let attestation = if input.starts_with('{') {
+                Attestation::from_json(input)?
+            } else {
+                Attestation::from_base64(input)?
+            };
+            let claim = client.verify_vault_attestation(&attestation)?;
+            println!(
+                "Valid: {} controls vault {} (nonce {}, expires {})",
+                claim.authority,
+                claim.vault,
+                claim.nonce,
+                format_timestamp(claim.expiry)
+            );
+        }
+        "backfill" => {
+            let mut config = BackfillConfig {
+                checkpoint_path: sub.get_one::<String>("checkpoint").map(PathBuf::from),
+                since: sub.get_one::<i64>("since").copied(),
+                ..Default::default()
+            };
+            if let Some(page_size) = sub.get_one::<usize>("page-size") {
+                config.page_size = *page_size;
+            }
+            let output = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(sub.get_one::<String>("output").expect("required"))?;
+            let mut sink = JsonLinesSink::new(io::BufWriter::new(output));
+            let report = client
+                .history_backfill(require_vault()?, config)
+                .on_progress(|progress| {
+                    eprintln!(
+                        "{} signatures discovered, {} transactions decoded",
+                        progress.signatures_discovered, progress.transactions_decoded
+                    )
+                })
+                .run(&mut sink)?;
+            println!(
+                "Decoded {} transactions ({} rate-limited retries)",
+                report.transactions_decoded, report.rate_limited_retries
+            );
+            if report.trimmed_transactions > 0 {
+                println!(
+                    "Kept only the program's logs of {} oversized transactions (largest {} bytes)",
+                    report.trimmed_transactions, report.largest_transaction_bytes
+                );
+            }
+        }
+        "support-bundle" => {
+            let effective = EffectiveConfig {
+                profile: matches.get_one::<String>("profile").cloned(),
+                cluster: cluster.to_string(),
+                write_url: write_url.cloned(),
+                read_url: read_url.cloned(),
+                keypair_path: Some(keypair_path.to_string()),
+                program_id: program_id.to_string(),
+                key_policy: profile.key_policy.is_some(),
+                strict_mint_policy: profile.strict_mint_policy,
+                strict_accounting: profile.strict_accounting,
+            };
+            let bundle = client.collect_support_bundle(
+                Some(effective),
+                *sub.get_one::<usize>("last").expect("defaulted"),
+            )?;
+            let json = bundle.to_redacted_json(RedactionRules {
+                hash_pubkeys: sub.get_flag("hash-pubkeys"),
+            })?;
+            match sub.get_one::<String>("output") {
+                Some(path) => {
+                    fs::write(path, json)?;
+                    eprintln!("Wrote support bundle to {}", path);
+                }
+                None => println!("{}", json),
+            }
+        }
+        "stranded" => println!("{}", client.get_stranded_lamports()?),
+        "sweep-lamports" => {
+            let destination = sub.get_one::<String>("destination").expect("required");
+            let swept =
+                client.sweep_lamports(&signer, parse_pubkey("destination", destination)?)?;
+            println!("Swept {}", format_sol(swept));
+        }
+        "reserve" => match sub.subcommand().expect("subcommand is required") {
+            ("balance", _) => println!(
+                "{}",
+                client
+                    .get_reserve_balance()?
+                    .display(&config.address_book()?)
+            ),
+            ("release", args) => {
+                let destination = args
+                    .get_one::<String>("to")
+                    .map(|to| parse_pubkey("recipient", to))
+                    .transpose()?
+                    .unwrap_or_else(|| signer.pubkey());
+                let receipt = client.release_reserve(
+                    &signer,
+                    *args.get_one::<u64>("amount").expect("required"),
+                    destination,
+                )?;
+                save_receipt(&receipt)?;
+            }
+            _ => unreachable!("subcommand_required"),
+        },
+        "verify-books" => {
+            let report = client.verify_books()?;
+            if sub.get_flag("json") {
+                let mint = client.get_vault_info()?.token_mint;
+                let view = BooksView::of(&report, client.mint_decimals(&mint)?);
+                println!("{}", serde_json::to_string_pretty(&view)?);
+            } else {
+                println!("{}", report);
+            }
+            if !report.is_consistent() {
+                std::process::exit(1);
+            }
+        }
+        "allowlist" => match sub.subcommand().expect("subcommand is required") {
+            ("sync", args) => {
+                let desired = parse_allowlist_csv(&read_input(
+                    args.get_one::<String>("file").expect("required"),
+                )?)?;
+                let desired: Vec<_> = desired.into_iter().collect();
+                let plan = client.sync_allowlist(signer.pubkey(), &desired)?;
+                println!("{}", plan);
+                if args.get_flag("dry-run") || plan.is_empty() {
+                    return Ok(());
+                }
+                if !args.get_flag("yes") && !confirm("Apply these changes?")? {
+                    return Err(anyhow!("Aborted"));
+                }
+                let record_path = args.get_one::<String>("record").map(PathBuf::from);
+                let record =
+                    client.execute_allowlist_sync(&signer, &plan, record_path.as_deref())?;
+                println!(
+                    "Applied {} changes in {} transactions",
+                    record.applied_count(),
+                    record.applied.len()
+                );
+            }
+            ("export", args) => {
+                let csv = allowlist_csv(&client.get_allowlist()?);
+                match args.get_one::<String>("output") {
+                    Some(path) => fs::write(path, csv)?,
+                    None => print!("{}", csv),
+                }
+            }
+            (other, _) => unreachable!("unknown allowlist subcommand {}", other),
+        },
+        "notification-config" => match sub.subcommand().expect("subcommand is required") {
+            ("set", args) => {
+                let mut notifications = NotificationConfig::default();
+                if let Some(explorer) = args.get_one::<String>("explorer") {
+                    notifications = notifications.explorer(explorer);
+                }
+                if let Some(contact) = args.get_one::<String>("support-contact") {
+                    notifications = notifications.support_contact(contact);
+                }
+                if let Some(url) = args.get_one::<String>("webhook-url") {
+                    notifications = notifications.webhook_url(url);
+                }
+                save_receipt(&client.set_notification_config(&signer, &notifications)?)?;
+            }
+            ("get", _) => match client.get_notification_config()? {
+                Some(notifications) => println!("{}", notifications),
+                None => println!("No notification config"),
+            },
+            ("clear", _) => save_receipt(&client.clear_notification_config(&signer)?)?,
+            (other, _) => unreachable!("unknown notification-config subcommand {}", other),
+        },
+        "maintenance" => match sub.subcommand().expect("subcommand is required") {
+            ("schedule", args) => {
+                let reason = args.get_one::<String>("reason").expect("required");
+                save_receipt(&client.schedule_maintenance(
+                    &signer,
+                    *args.get_one::<i64>("start").expect("required"),
+                    *args.get_one::<i64>("end").expect("required"),
+                    reason.clone(),
+                )?)?;
+            }
+            ("list", _) => {
+                let windows = client.get_maintenance_windows()?;
+                if windows.is_empty() {
+                    println!("No maintenance scheduled");
+                }
+                let now = client.cluster_time()?;
+                for window in windows {
+                    println!("{} ({})", window, window.describe(now));
+                }
+            }
+            (other, _) => unreachable!("unknown maintenance subcommand {}", other),
+        },
+        "repair" => match sub.subcommand().expect("subcommand is required") {
+            ("fee-path", args) => {
+                let report = client.repair_fee_path(&signer)?;
+                if args.get_flag("json") {
+                    let view = RepairView::from(&report);
+                    println!("{}", serde_json::to_string_pretty(&view)?);
+                } else {
+                    println!("{}", report);
+                }
+            }
+            (other, _) => unreachable!("unknown repair subcommand {}", other),
+        },
+        "mirror" => {
+            let profile = |arg: &str| config.profile(sub.get_one::<String>(arg).expect("required"));
+            let (mut source, _) = profile_client(profile("from")?)?;
+            let (mut destination, authority) = profile_client(profile("to")?)?;
+            #[allow(deprecated)]
+            source.with_vault(require_vault()?);
+            let include: Vec<&String> = sub.get_many("include").unwrap_or_default().collect();
+            let options = MirrorOptions {
+                allowlist: include.iter().any(|item| *item == "allowlist"),
+                metadata: include.iter().any(|item| *item == "metadata"),
+                destination_mint: sub
+                    .get_one::<String>("dest-mint")
+                    .map(|mint| parse_pubkey("destination mint", mint))
+                    .transpose()?,
+                dry_run: sub.get_flag("dry-run"),
+                allow_mainnet_destination: sub.get_flag("allow-mainnet-dest"),
+            };
+            let report = source.mirror_vault_config(&mut destination, &authority, &options)?;
+            for receipt in &report.receipts {
+                save_receipt(receipt)?;
+            }
+            if sub.get_flag("json") {
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            } else {
+                println!("{}", report);
+            }
+            if report.has_failures() {
+                std::process::exit(1);
+            }
+        }
+        "launch" => {
+            let plan_path = Path::new(sub.get_one::<String>("plan").expect("required"));
+            let plan = cli::load_launch_plan(plan_path)?;
+            let resume_path = sub
+                .get_one::<String>("resume")
+                .map(PathBuf::from)
+                .unwrap_or_else(|| cli::launch_resume_path(plan_path));
+            let options = LaunchOptions {
+                resume_path: Some(resume_path),
+                dry_run: true,
+            };
+            // Always validate and simulate first, so nothing is confirmed blind
+            let preview = client.launch_vault(&signer, &plan, options.clone())?;
+            let report = if sub.get_flag("dry-run") {
+                preview.clone()
+            } else {
+                eprintln!("{}", preview);
+                let question = if is_mainnet(&cluster) {
+                    format!("Launch vault {} on MAINNET?", preview.vault)
+                } else {
+                    format!("Launch vault {}?", preview.vault)
+                };
+                if !sub.get_flag("yes") && !confirm_on_terminal(&question)? {
+                    return Err(anyhow!("Launch not confirmed; nothing was sent"));
+                }
+                let options = LaunchOptions {
+                    dry_run: false,
+                    ..options
+                };
+                client.launch_vault(&signer, &plan, options)?
+            };
+            // Receipts of steps finished by an earlier run were saved by it
+            for (record, before) in report.steps.iter().zip(&preview.steps) {
+                if before.outcome != StepStatus::Applied {
+                    for receipt in &record.receipts {
+                        save_receipt(receipt)?;
+                    }
+                }
+            }
+            if let Some(path) = sub.get_one::<String>("report") {
+                fs::write(path, serde_json::to_vec_pretty(&report)?)?;
+            }
+            if sub.get_flag("json") {
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            } else {
+                println!("{}", report);
+            }
+            if report.checks.iter().any(|check| !check.passed) {
+                std::process::exit(1);
+            }
+        }
+        "exec" => {
+            let pool = match sub.get_one::<String>("fee-pool") {
+                Some(dir) => {
+                    let payers = FeeReservePool::load_payers(Path::new(dir))?;
+                    let mut pool = FeeReservePool::new(payers, fee_pool::DEFAULT_FLOOR_LAMPORTS)?;
+                    pool.with_reserve(signer.insecure_clone(), fee_pool::DEFAULT_TOP_UP_LAMPORTS)?;
+                    let pool = Arc::new(pool);
+                    client.with_fee_pool(pool.clone());
+                    Some(pool)
+                }
+                None => None,
+            };
+            let input = read_input(sub.get_one::<String>("file").map_or("-", String::as_str))?;
+            let plan = client.plan_batch(&signer.pubkey(), &parse_operations(&input));
+            eprintln!("{}", plan);
+
+            let mut stdout = io::stdout().lock();
+            let mut unsuccessful = 0;
+            let mut emit = |result: BatchResult| -> Result<()> {
+                if !matches!(result.outcome, BatchOutcome::Executed { .. }) {
+                    unsuccessful += 1;
+                }
+                writeln!(stdout, "{}", serde_json::to_string(&result)?)?;
+                stdout.flush()?;
+                Ok(())
+            };
+            let skip_reason = if sub.get_flag("strict") && plan.has_rejections() {
+                Some("--strict and the batch has rejected lines")
+            } else if plan.planned().next().is_none() {
+                Some("nothing to execute")
+            } else if !sub.get_flag("yes") && !confirm_on_terminal("Execute this batch?")? {
+                Some("not confirmed")
+            } else {
+                None
+            };
+            match skip_reason {
+                Some(reason) => {
+                    for result in plan.skipped(reason) {
+                        emit(result)?;
+                    }
+                }
+                None => client.execute_batch(&signer, &plan, &mut emit)?,
+            }
+            if pool.is_some() {
+                eprintln!("Fee pool:");
+                for spend in client.fee_pool_report()? {
+                    eprintln!("  {}", spend);
+                }
+            }
+            if unsuccessful > 0 {
+                std::process::exit(1);
+            }
+        }
+        "dev" => match sub.subcommand().expect("subcommand is required") {
+            ("fund-pool", args) => {
+                let count = *args.get_one::<usize>("payers").expect("defaulted");
+                let each = *args.get_one::<f64>("each").expect("defaulted");
+                if !(each.is_finite() && each > 0.0) {
+                    return Err(anyhow!("--each must be a positive amount of SOL"));
+                }
+                let dir = Path::new(args.get_one::<String>("dir").expect("defaulted"));
+                let payers = FeeReservePool::save_payers(dir, count)?;
+                let payers: Vec<Pubkey> = payers.iter().map(Signer::pubkey).collect();
+                let (funding, signature) =
+                    client.fund_fee_payers(&signer, &payers, sol_to_lamports(each))?;
+                for funding in &funding {
+                    println!(
+                        "{}  had {}, sent {}",
+                        funding.payer,
+                        format_sol(funding.balance_before),
+                        format_sol(funding.transferred)
+                    );
+                }
+                match signature {
+                    Some(signature) => println!("Signature: {}", signature),
+                    None => println!("Every payer already holds {} SOL", each),
+                }
+                println!("Keypairs: {}", dir.display());
+            }
+            (other, _) => unreachable!("unknown dev subcommand {}", other),
+        },
+        "list" => {
+            let (listing, args) = sub.subcommand().expect("subcommand is required");
+            let limit = *args.get_one::<usize>("limit").expect("defaulted");
+            match listing {
+                "vaults" => {
+                    let authority = args
+                        .get_one::<String>("authority")
+                        .map(|authority| parse_pubkey("authority", authority))
+                        .transpose()?;
+                    let cursor = parse_cursor(args)?;
+                    let page = client.list_vaults_page(authority, cursor.as_ref(), limit)?;
+                    print_page(
+                        args,
+                        &page,
+                        |vaults| client.vault_views(vaults),
+                        |vault| vault.to_string(),
+                    )?;
+                }
+                "allowlist" => {
+                    require_vault()?;
+                    let page = client.get_allowlist_page(parse_cursor(args)?.as_ref(), limit)?;
+                    print_page(
+                        args,
+                        &page,
+                        |wallets| Ok(wallets.iter().map(ToString::to_string).collect()),
+                        |wallet| wallet.to_string(),
+                    )?;
+                }
+                "history" => {
+                    require_vault()?;
+                    let page = client.get_history_page(parse_cursor(args)?.as_ref(), limit)?;
+                    print_page(
+                        args,
+                        &page,
+                        |transactions| Ok(transactions.iter().map(TransactionView::from).collect()),
+                        |transaction| {
+                            let status = if transaction.succeeded {
+                                ""
+                            } else {
+                                " (failed)"
+                            };
+                            format!(
+                                "{} slot {}{}",
+                                transaction.signature, transaction.slot, status
+                            )
+                        },
+                    )?;
+                }
+                other => unreachable!("unknown list subcommand {}", other),
+            }
+        }
+        "index" => {
+            let args = sub
+                .subcommand_matches("refresh")
+                .expect("others handled offline");
+            let authority = args
+                .get_one::<String>("authority")
+                .map(|authority| parse_pubkey("authority", authority))
+                .transpose()?;
+            let vaults = client.list_vaults(authority)?;
+            let path = CliConfig::index_path();
+            let mut index = VaultIndex::load(&path)?.unwrap_or_default();
+            if index.program_id != program_id && !index.vaults.is_empty() {
+                eprintln!(
+                    "Warning: replacing an index of program {} with program {}",
+                    index.program_id, program_id
+                );
+            }
+            index.program_id = program_id;
+            index.refresh(cluster.url(), chrono::Utc::now().timestamp(), vaults);
+            index.save(&path)?;
+            println!(
+                "Indexed {} vaults in {}",
+                index.vaults.len(),
+                path.display()
+            );
+        }
+        other => unreachable!("unknown subcommand {}", other),
+    }
+
+    Ok(())
+}
+
+/// `--cursor`, when given
+fn parse_cursor<T: Paginated>(args: &ArgMatches) -> Result<Option<Cursor<T>>> {
+    args.get_one::<String>("cursor")
+        .map(|cursor| cursor.parse())
+        .transpose()
+}
+
+/// `raw` base units of the client's vault's mint
+fn vault_amount(client: &TokenVaultClient, raw: u64) -> Result<MintAmount> {
+    let mint = client.get_vault_info()?.token_mint;
+    Ok(MintAmount::new(raw, mint, client.mint_decimals(&mint)?))
+}
+
+/// Print where a confirmed transaction landed and what it cost, when the
+/// client looked it up
+fn print_receipt(receipt: &TxReceipt) {
+    if let Some(slot) = receipt.slot {
+        println!("Slot: {}", slot);
+    }
+    if let Some(fee) = receipt.fee_paid {
+        println!("Fee paid: {} lamports", fee);
+    }
+    if receipt.attempts > 1 {
+        println!("Attempts: {}", receipt.attempts);
+    }
+}
+
+/// Print a page's items, one per line, and how to get the next page, or the
+/// whole page as JSON with `--json`, its items turned into views by `views`
+fn print_page<T: Paginated, V: serde::Serialize>(
+    args: &ArgMatches,
+    page: &Page<T>,
+    views: impl FnOnce(&[T]) -> Result<Vec<V>>,
+    describe: impl Fn(&T) -> String,
+) -> Result<()> {
+    if args.get_flag("json") {
+        let page = PageView::of(page, views(&page.items)?);
+        println!("{}", serde_json::to_string_pretty(&page)?);
+        return Ok(());
+    }
+    for item in &page.items {
+        println!("{}", describe(item));
+    }
+    match &page.next_cursor {
+        Some(cursor) => eprintln!(
+            "{} items read at slot {}; next page: --cursor {}",
+            page.items.len(),
+            page.context_slot,
+            cursor
+        ),
+        None => eprintln!(
+            "{} items read at slot {}; last page",
+            page.items.len(),
+            page.context_slot
+        ),
+    }
+    Ok(())
+}
+
+/// Resolve `--vault`, `--alias` or `--vault-name`, whichever was given.
+/// Aliases from the config file take precedence over those in the index.
+fn resolve_vault(
+    matches: &ArgMatches,
+    config: &CliConfig,
+    program_id: &Pubkey,
+) -> Result<Option<Pubkey>> {
+    if let Some(vault) = matches.get_one::<String>("vault") {
+        return parse_pubkey("vault", config.resolve_vault(vault)).map(Some);
+    }
+    if let Some(alias) = matches.get_one::<String>("alias") {
+        if let Some(address) = config.vaults.get(alias) {
+            return parse_pubkey("vault", address).map(Some);
+        }
+        return load_index(program_id)?.resolve_alias(alias).map(Some);
+    }
+    match matches.get_one::<String>("vault-name") {
+        Some(name) => load_index(program_id)?.resolve_name(name).map(Some),
+        None => Ok(None),
+    }
+}
+
+/// The vault index, which must exist; warns if it was built for another program
+fn load_index(program_id: &Pubkey) -> Result<VaultIndex> {
+    let path = CliConfig::index_path();
+    let index = VaultIndex::load(&path)?.ok_or_else(|| {
+        anyhow!(
+            "No vault index at {}; run `token-vault index refresh` first",
+            path.display()
+        )
+    })?;
+    if index.program_id != *program_id {
+        eprintln!(
+            "Warning: the vault index was built for program {}, not {}",
+            index.program_id, program_id
+        );
+    }
+    Ok(index)
+}
+
+/// Append `receipt` to the receipts file next to the config
+fn save_receipt(receipt: &ActionReceipt) -> Result<()> {
+    let path = CliConfig::receipts_path();
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(&path)?;
+    writeln!(file, "{}", receipt.to_json()?)?;
+    eprintln!("Receipt appended to {}", path.display());
+    Ok(())
+}
+
+/// `verify-receipt`: check every receipt in the file, failing if any is invalid
+fn verify_receipts(sub: &ArgMatches) -> Result<()> {
+    let authority = parse_pubkey(
+        "authority",
+        sub.get_one::<String>("authority").expect("required"),
+    )?;
+    let path = sub
+        .get_one::<String>("file")
+        .map(PathBuf::from)
+        .unwrap_or_else(CliConfig::receipts_path);
+    let contents = fs::read_to_string(&path)
+        .map_err(|e| anyhow!("Cannot read receipts {}: {}", path.display(), e))?;
+    let mut invalid = 0;
+    for (number, line) in contents.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let checked = ActionReceipt::from_json(line).and_then(|receipt| {
+            verify_action_receipt(&receipt, &authority)?;
+            Ok(receipt)
+        });
+        match checked {
+            Ok(receipt) => println!("valid   line {}: {}", number + 1, receipt),
+            Err(err) => {
+                invalid += 1;
+                println!("INVALID line {}: {}", number + 1, err);
+            }
+        }
+    }
+    if invalid > 0 {
+        return Err(anyhow!(
+            "{} invalid receipts in {}",
+            invalid,
+            path.display()
+        ));
+    }
+    Ok(())
+}
+
+/// `index list`, `index alias` and `index unalias`, which only touch the index file
+fn edit_index(sub: &ArgMatches) -> Result<()> {
+    let path = CliConfig::index_path();
+    let mut index = VaultIndex::load(&path)?.unwrap_or_default();
+    match sub.subcommand().expect("subcommand is required") {
+        ("list", args) => {
+            let vaults = match args.get_one::<String>("pattern") {
+                Some(pattern) => index.find(pattern),
+                None => index.vaults.iter().collect(),
+            };
+            for vault in vaults {
+                println!("{}", vault);
+            }
+            for (alias, address) in &index.aliases {
+                println!("alias {} -> {}", alias, address);
+            }
+            if index.refreshed_at == 0 {
+                eprintln!("The index has never been refreshed; run `token-vault index refresh`");
+            } else {
+                eprintln!(
+                    "Refreshed {} from {}",
+                    format_timestamp(index.refreshed_at),
+                    index.cluster
+                );
+            }
+            return Ok(());
+        }
+        ("alias", args) => {
+            let alias = args.get_one::<String>("alias").expect("required");
+            let target = args.get_one::<String>("target").expect("required");
+            let address = match Pubkey::from_str(target) {
+                Ok(address) => address,
+                Err(_) => index.resolve_name(target)?,
+            };
+            index.aliases.insert(alias.clone(), address.to_string());
+            println!("{} -> {}", alias, address);
+        }
+        ("unalias", args) => {
+            let alias = args.get_one::<String>("alias").expect("required");
+            if index.aliases.remove(alias).is_none() {
+                return Err(anyhow!("Unknown alias '{}'", alias));
+            }
+        }
+        (other, _) => unreachable!("unknown index subcommand {}", other),
+    }
+    index.save(&path)
+}
+
+/// Report the resolved configuration and whether the cluster and program are reachable
+fn doctor(
+    cluster: &Cluster,
+    program_id: Pubkey,
+    keypair_path: &str,
+    signer: &Keypair,
+    profile: &Profile,
+    vault: Option<Pubkey>,
+    (write_url, read_url): (Option<&String>, Option<&String>),
+) -> Result<()> {
+    println!("Cluster: {} ({})", cluster, cluster.url());
+    println!("Program: {}", program_id);
+    println!("Signer: {} ({})", signer.pubkey(), keypair_path);
+
+    match &profile.key_policy {
+        Some(policy) => {
+            println!("Key policy bucket: {}", policy.bucket(&signer.pubkey()));
+            match policy.check(&signer.pubkey(), cluster) {
+                Ok(()) => println!("Key policy: ok on this cluster"),
+                Err(err) => println!("Key policy: VIOLATION: {}", err),
+            }
+        }
+        None => println!("Key policy: none configured"),
+    }
+
+    let write_url = match write_url {
+        Some(url) => Cluster::from_str(url)?.url().to_string(),
+        None => cluster.url().to_string(),
+    };
+    let read_url = read_url
+        .map(|url| Cluster::from_str(url).map(|cluster| cluster.url().to_string()))
+        .transpose()?;
+    let rpc = RpcClient::new(read_url.clone().unwrap_or_else(|| write_url.clone()));
+    match rpc.get_version() {
+        Ok(version) => println!("RPC: reachable (solana-core {})", version.solana_core),
+        Err(err) => println!("RPC: unreachable: {}", err),
+    }
+    println!(
+        "{}",
+        EndpointHealth::probe(&write_url, read_url.as_deref(), rpc.commitment())
+    );
+    match rpc.get_account(&program_id) {
+        Ok(account) if account.executable => println!("Program account: deployed"),
+        Ok(_) => println!("Program account: exists but is not executable"),
+        Err(_) => println!("Program account: not found"),
+    }
+    println!(
+        "Client: {} (supports {})",
+        CLIENT_VERSION,
+        FeatureSet::SUPPORTED
+    );
+    match fetch_program_features(&rpc, &program_id) {
+        Ok(Some(features)) => {
+            println!("Feature flags: {}", features);
+            match features.check(FeatureSet::SUPPORTED, CLIENT_VERSION) {
+                Ok(()) => println!("Client compatibility: ok"),
+                Err(err) => println!("Client compatibility: INCOMPATIBLE: {}", err),
+            }
+        }
+        Ok(None) => println!("Feature flags: none published"),
+        Err(err) => println!("Feature flags: unavailable: {}", err),
+    }
+    match rpc.get_balance(&signer.pubkey()) {
+        Ok(lamports) => println!("Signer balance: {}", format_sol(lamports)),
+        Err(err) => println!("Signer balance: unavailable: {}", err),
+    }
+
+    if let Some(vault) = vault {
+        let mut client =
+            match TokenVaultClient::new(cluster.clone(), signer.insecure_clone(), program_id) {
+                Ok(client) => client,
+                Err(err) => {
+                    println!("Stranded lamports: unavailable: {}", err);
+                    return Ok(());
+                }
+            };
+        #[allow(deprecated)]
+        client.with_vault(vault);
+        if let Some(url) = &read_url {
+            client.with_read_rpc(url)?;
+        }
+        match client.get_freeze_status() {
+            Ok(status) if status.vault_token_account_frozen => {
+                println!(
+                    "Freeze: VAULT TOKEN ACCOUNT FROZEN: {}",
+                    status.check().unwrap_err()
+                )
+            }
+            Ok(status) => println!("Freeze: {}", status),
+            Err(err) => println!("Freeze: unavailable: {}", err),
+        }
+        match client.get_vault_info() {
+            Ok(vault) if vault.accounting_anomaly => println!(
+                "Accounting: ANOMALY: a withdrawal underflowed total_deposited; \
+                 run verify-books to reconcile"
+            ),
+            Ok(_) => println!("Accounting: ok"),
+            Err(err) => println!("Accounting: unavailable: {}", err),
+        }
+        match client.get_stranded_lamports() {
+            Ok(report) => println!("Stranded lamports:\n{}", report),
+            Err(err) => println!("Stranded lamports: unavailable: {}", err),
+        }
+    }
+    Ok(())
+}
+
+/// Client and signer for a named profile, set up as the global flags would
+fn profile_client(profile: &Profile) -> Result<(TokenVaultClient, Keypair)> {
+    let cluster = Cluster::from_str(profile.url.as_deref().unwrap_or("devnet"))?;
+    let signer = utils::load_keypair(profile.keypair.as_deref().unwrap_or(DEFAULT_KEYPAIR))?;
+    let program_id = match &profile.program_id {
+        Some(id) => parse_pubkey("program id", id)?,
+        None => token_vault::ID,
+    };
+    let mut client = TokenVaultClient::new(cluster, signer.insecure_clone(), program_id)?;
+    if let Some(url) = &profile.write_url {
+        client.with_write_rpc(url)?;
+    }
+    if let Some(url) = &profile.read_url {
+        client.with_read_rpc(url)?;
+    }
+    client.with_strict_mint_policy(profile.strict_mint_policy);
+    if let Some(policy) = profile.key_policy.clone() {
+        client.with_key_policy(policy)?;
+    }
+    Ok((client, signer))
+}
+
+fn parse_pubkey(what: &str, value: &str) -> Result<Pubkey> {
+    Pubkey::from_str(value).map_err(|_| anyhow!("Invalid {} '{}'", what, value))
+}
+
+/// The window from `--deposit-opens`/`--deposit-closes`, if either was given
+fn deposit_window(args: &ArgMatches) -> Result<Option<DepositWindow>> {
+    let opens = args.get_one::<i64>("deposit-opens").copied();
+    let closes = args.get_one::<i64>("deposit-closes").copied();
+    if opens.is_none() && closes.is_none() {
+        return Ok(None);
+    }
+    DepositWindow::new(opens.unwrap_or(0), closes.unwrap_or(0)).map(Some)
+}
+
+/// The policy from `--dust-threshold`/`--waive-dust-fee`, if a threshold was given
+fn dust_policy(args: &ArgMatches) -> Option<DustPolicy> {
+    let threshold = *args.get_one::<u64>("dust-threshold")?;
+    Some(if args.get_flag("waive-dust-fee") {
+        DustPolicy::waive_fee_below(threshold)
+    } else {
+        DustPolicy::reject_below(threshold)
+    })
+}
+
+/// Ask a yes/no question on the terminal; anything but "y" or "yes" is no
+fn confirm(question: &str) -> Result<bool> {
+    print!("{} [y/N] ", question);
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+/// Like `confirm`, but prompting on stderr and reading the terminal, for
+/// commands whose stdin and stdout carry data
+fn confirm_on_terminal(question: &str) -> Result<bool> {
+    let tty = fs::File::open("/dev/tty")
+        .map_err(|_| anyhow!("No terminal to confirm on; pass --yes to skip confirmation"))?;
+    eprint!("{} [y/N] ", question);
+    let mut answer = String::new();
+    io::BufReader::new(tty).read_line(&mut answer)?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+fn read_input(path: &str) -> Result<String> {
+    if path == "-" {
+        let mut input = String::new();
+        io::stdin().read_to_string(&mut input)?;
+        Ok(input)
+    } else {
+        Ok(fs::read_to_string(path)?)
+    }
+}
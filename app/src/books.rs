@@ -0,0 +1,428 @@
+use anchor_client::solana_sdk::{program_pack::Pack, pubkey::Pubkey, signature::Signature};
+use anchor_lang::AnchorDeserialize;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::dust::{withdrawal_fee, DustMode, DustPolicy};
+use crate::history::{VaultInstructionKind, VaultTransaction};
+use crate::reserve::reserve_carve_out;
+use crate::token_vault::{instruction, state::Vault};
+use crate::{BackfillConfig, DeadlinePhase, TokenVaultClient};
+
+/// Deposits and withdrawals of a single owner, as replayed from history
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UserPosition {
+    pub deposited: u64,
+    pub withdrawn: u64,
+    pub fees_paid: u64,
+    /// Part of `deposited` carved out into the insurance reserve
+    #[serde(default)]
+    pub reserve_paid: u64,
+}
+
+impl UserPosition {
+    /// Amount still attributable to the owner
+    pub fn balance(&self) -> i128 {
+        self.deposited as i128 - self.reserve_paid as i128 - self.withdrawn as i128
+    }
+}
+
+/// A value that replaying history does not reproduce
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Discrepancy {
+    pub field: String,
+    pub expected: String,
+    pub actual: String,
+    /// First replayed transaction after which the books could no longer be
+    /// right, when it can be pinned down
+    #[serde(with = "crate::serde_utils::option_signature")]
+    pub first_divergent_signature: Option<Signature>,
+}
+
+/// Running totals of a vault rebuilt from its instructions, oldest first
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BooksReplay {
+    pub vault: Pubkey,
+    pub total_deposited: i128,
+    /// Tokens that should sit in the insurance reserve
+    pub total_reserved: i128,
+    pub fees_collected: u64,
+    /// Withdrawal fees that should still sit in the vault token account,
+    /// waiting for the fee collector to claim them
+    pub accrued_fees: i128,
+    pub positions: BTreeMap<Pubkey, UserPosition>,
+    /// Requested withdrawals not yet executed or cancelled, by withdrawer
+    pub pending_withdrawals: BTreeMap<Pubkey, u64>,
+    pub transactions_replayed: usize,
+    /// The vault's initialization was part of the replayed history
+    pub saw_initialization: bool,
+    pub oldest_slot: Option<u64>,
+    pub last_signature: Option<Signature>,
+    /// First transaction that took a replayed total below zero
+    pub first_negative: Option<Signature>,
+}
+
+impl BooksReplay {
+    pub fn new(vault: Pubkey) -> Self {
+        Self {
+            vault,
+            ..Default::default()
+        }
+    }
+
+    /// Apply one transaction; failed transactions and other vaults' instructions are skipped
+    pub fn apply(
+        &mut self,
+        transaction: &VaultTransaction,
+        fee_percentage: u16,
+        dust: DustPolicy,
+        reserve_bps: u16,
+    ) {
+        if self.oldest_slot.is_none() {
+            self.oldest_slot = Some(transaction.slot);
+        }
+        if !transaction.succeeded {
+            return;
+        }
+
+        let vault = self.vault.to_string();
+        let mut touched = false;
+        for ix in &transaction.instructions {
+            // Every vault instruction lists its signer first and the vault second
+            if ix.accounts.get(1) != Some(&vault) {
+                continue;
+            }
+            let owner = match ix.accounts.first().and_then(|key| key.parse().ok()) {
+                Some(owner) => owner,
+                None => continue,
+            };
+            let args = ix.data.get(8..).unwrap_or_default();
+            match ix.kind {
+                VaultInstructionKind::InitializeVault => self.saw_initialization = true,
+                VaultInstructionKind::Deposit => {
+                    if let Ok(deposit) = instruction::Deposit::try_from_slice(args) {
+                        let reserve = reserve_carve_out(deposit.amount, reserve_bps);
+                        self.total_deposited += (deposit.amount - reserve) as i128;
+                        self.total_reserved += reserve as i128;
+                        let position = self.positions.entry(owner).or_default();
+                        position.deposited += deposit.amount;
+                        position.reserve_paid += reserve;
+                    }
+                }
+                VaultInstructionKind::Withdraw => {
+                    if let Ok(withdraw) = instruction::Withdraw::try_from_slice(args) {
+                        self.withdraw(owner, withdraw.amount, fee_percentage, dust);
+                    }
+                }
+                VaultInstructionKind::RequestWithdrawal => {
+                    if let Ok(request) = instruction::RequestWithdrawal::try_from_slice(args) {
+                        self.pending_withdrawals.insert(owner, request.amount);
+                    }
+                }
+                // The amount is the request's; a request made before the
+                // replayed history is known only from the withdrawal's event
+                VaultInstructionKind::ExecuteWithdrawal => {
+                    let amount = self.pending_withdrawals.remove(&owner).or_else(|| {
+                        transaction
+                            .withdraw_events()
+                            .into_iter()
+                            .find(|event| event.vault == self.vault && event.withdrawer == owner)
+                            .map(|event| event.amount)
+                    });
+                    match amount {
+                        Some(amount) => self.withdraw(owner, amount, fee_percentage, dust),
+                        None => continue,
+                    }
+                }
+                VaultInstructionKind::CancelWithdrawal => {
+                    self.pending_withdrawals.remove(&owner);
+                }
+                VaultInstructionKind::ReleaseReserve => {
+                    if let Ok(release) = instruction::ReleaseReserve::try_from_slice(args) {
+                        self.total_reserved -= release.amount as i128;
+                    }
+                }
+                // A claim pays out everything accrued so far
+                VaultInstructionKind::ClaimFees => self.accrued_fees = 0,
+                // Sweeps and allowlist changes move lamports only, never tokens
+                VaultInstructionKind::SweepLamports
+                | VaultInstructionKind::AddAllowlistEntry
+                | VaultInstructionKind::RemoveAllowlistEntry
+                | VaultInstructionKind::Unknown => continue,
+            }
+            touched = true;
+        }
+
+        if touched {
+            self.transactions_replayed += 1;
+            self.last_signature = Some(transaction.signature);
+            if (self.total_deposited < 0 || self.total_reserved < 0 || self.accrued_fees < 0)
+                && self.first_negative.is_none()
+            {
+                self.first_negative = Some(transaction.signature);
+            }
+        }
+    }
+
+    fn withdraw(&mut self, owner: Pubkey, amount: u64, fee_percentage: u16, dust: DustPolicy) {
+        let fee = if dust.is_dust(amount) && dust.mode == DustMode::WaiveFee {
+            0
+        } else {
+            withdrawal_fee(amount, fee_percentage)
+        };
+        self.total_deposited -= amount as i128;
+        self.accrued_fees += fee as i128;
+        self.fees_collected += fee;
+        let position = self.positions.entry(owner).or_default();
+        position.withdrawn += amount;
+        position.fees_paid += fee;
+    }
+}
+
+/// Result of checking a vault's on-chain counters against its replayed history
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BooksReport {
+    pub vault: String,
+    pub transactions_replayed: usize,
+    pub expected_total_deposited: i128,
+    pub actual_total_deposited: u64,
+    /// Tokens that should sit in the vault token account: deposits plus
+    /// accrued fees
+    pub expected_token_balance: i128,
+    pub actual_token_balance: u64,
+    #[serde(default)]
+    pub expected_accrued_fees: i128,
+    #[serde(default)]
+    pub actual_accrued_fees: u64,
+    pub expected_total_reserved: i128,
+    pub actual_total_reserved: u64,
+    /// Tokens that should sit in the reserve token account
+    pub expected_reserve_balance: i128,
+    pub actual_reserve_balance: u64,
+    pub fees_collected: u64,
+    pub positions: BTreeMap<String, UserPosition>,
+    pub discrepancies: Vec<Discrepancy>,
+    /// History before this slot is no longer served by the RPC node, so the
+    /// books can only be checked from there on
+    pub unverifiable_before_slot: Option<u64>,
+}
+
+impl BooksReport {
+    /// Replay reproduced every checked value
+    pub fn is_consistent(&self) -> bool {
+        self.discrepancies.is_empty()
+    }
+
+    /// The replay started at the vault's initialization
+    pub fn is_complete(&self) -> bool {
+        self.unverifiable_before_slot.is_none()
+    }
+}
+
+impl fmt::Display for BooksReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Books of vault {} ({} transactions replayed)",
+            self.vault, self.transactions_replayed
+        )?;
+        writeln!(
+            f,
+            "  total_deposited: expected {}, on-chain {}",
+            self.expected_total_deposited, self.actual_total_deposited
+        )?;
+        writeln!(
+            f,
+            "  token balance: expected {}, on-chain {}",
+            self.expected_token_balance, self.actual_token_balance
+        )?;
+        writeln!(
+            f,
+            "  accrued fees: expected {}, on-chain {}",
+            self.expected_accrued_fees, self.actual_accrued_fees
+        )?;
+        writeln!(
+            f,
+            "  total_reserved: expected {}, on-chain {}",
+            self.expected_total_reserved, self.actual_total_reserved
+        )?;
+        writeln!(
+            f,
+            "  reserve balance: expected {}, on-chain {}",
+            self.expected_reserve_balance, self.actual_reserve_balance
+        )?;
+        writeln!(f, "  fees collected: {}", self.fees_collected)?;
+        if let Some(slot) = self.unverifiable_before_slot {
+            writeln!(f, "  unverifiable before slot {}: history is pruned", slot)?;
+        }
+        if self.discrepancies.is_empty() {
+            return write!(f, "  books balance");
+        }
+        for discrepancy in &self.discrepancies {
+            write!(
+                f,
+                "  MISMATCH {}: expected {}, actual {}",
+                discrepancy.field, discrepancy.expected, discrepancy.actual
+            )?;
+            if let Some(signature) = discrepancy.first_divergent_signature {
+                write!(f, " (first divergent transaction {})", signature)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl TokenVaultClient {
+    /// Replay the vault's full history and compare the result with its
+    /// on-chain `total_deposited`, `total_reserved`, `accrued_fees` and
+    /// token balances
+    pub fn verify_books(&self) -> Result<BooksReport> {
+        let vault = self.current_vault()?;
+        let deadline = self.deadline();
+
+        let mut history = Vec::new();
+        let config = BackfillConfig {
+            deadline,
+            ..Default::default()
+        };
+        self.history_backfill(vault, config)
+            .run(&mut |transaction: &VaultTransaction| {
+                history.push(transaction.clone());
+                Ok(())
+            })?;
+        // Backfill delivers newest first
+        history.reverse();
+
+        let vault_data = self.fetch_vault(vault, deadline)?;
+        let mut replay = BooksReplay::new(vault);
+        for transaction in &history {
+            replay.apply(
+                transaction,
+                vault_data.fee_percentage,
+                DustPolicy::of(&vault_data),
+                vault_data.reserve_bps,
+            );
+        }
+
+        let actual_token_balance = self.vault_token_balance(vault, deadline)?;
+        let actual_reserve_balance = self.reserve_token_balance(vault, deadline)?;
+        Ok(books_report(
+            &replay,
+            &vault_data,
+            actual_token_balance,
+            actual_reserve_balance,
+        ))
+    }
+
+    fn vault_token_balance(&self, vault: Pubkey, deadline: crate::Deadline) -> Result<u64> {
+        let (vault_token_account, _) =
+            crate::token_vault::pda::find_vault_token_account_address(&self.program.id(), &vault);
+        let rpc = self.rpc_within(deadline, DeadlinePhase::Fetch)?;
+        let account = rpc.get_account(&vault_token_account)?;
+        Ok(anchor_spl::token::spl_token::state::Account::unpack(&account.data)?.amount)
+    }
+}
+
+/// Compare a replay against the vault's current state
+pub fn books_report(
+    replay: &BooksReplay,
+    vault: &Vault,
+    actual_token_balance: u64,
+    actual_reserve_balance: u64,
+) -> BooksReport {
+    let unverifiable_before_slot = if replay.saw_initialization {
+        None
+    } else {
+        replay.oldest_slot
+    };
+    // Without the start of history only changes can be checked, not absolute values
+    let divergence = replay.first_negative.or(replay.last_signature);
+    let expected_token_balance = replay.total_deposited + replay.accrued_fees;
+
+    let mut discrepancies = Vec::new();
+    if unverifiable_before_slot.is_none() {
+        if replay.total_deposited != vault.total_deposited as i128 {
+            discrepancies.push(Discrepancy {
+                field: "total_deposited".to_string(),
+                expected: replay.total_deposited.to_string(),
+                actual: vault.total_deposited.to_string(),
+                first_divergent_signature: divergence,
+            });
+        }
+        if replay.accrued_fees != vault.accrued_fees as i128 {
+            discrepancies.push(Discrepancy {
+                field: "accrued_fees".to_string(),
+                expected: replay.accrued_fees.to_string(),
+                actual: vault.accrued_fees.to_string(),
+                first_divergent_signature: divergence,
+            });
+        }
+        if expected_token_balance != actual_token_balance as i128 {
+            discrepancies.push(Discrepancy {
+                field: "vault_token_balance".to_string(),
+                expected: expected_token_balance.to_string(),
+                actual: actual_token_balance.to_string(),
+                first_divergent_signature: divergence,
+            });
+        }
+        if replay.total_reserved != vault.total_reserved as i128 {
+            discrepancies.push(Discrepancy {
+                field: "total_reserved".to_string(),
+                expected: replay.total_reserved.to_string(),
+                actual: vault.total_reserved.to_string(),
+                first_divergent_signature: divergence,
+            });
+        }
+        if replay.total_reserved != actual_reserve_balance as i128 {
+            discrepancies.push(Discrepancy {
+                field: "reserve_token_balance".to_string(),
+                expected: replay.total_reserved.to_string(),
+                actual: actual_reserve_balance.to_string(),
+                first_divergent_signature: divergence,
+            });
+        }
+    }
+    if let Some(signature) = replay.first_negative {
+        discrepancies.push(Discrepancy {
+            field: "replayed_total".to_string(),
+            expected: "never below 0".to_string(),
+            actual: "negative".to_string(),
+            first_divergent_signature: Some(signature),
+        });
+    }
+    // The program clamped a counter that a withdrawal would have underflowed
+    if vault.accounting_anomaly {
+        discrepancies.push(Discrepancy {
+            field: "accounting_anomaly".to_string(),
+            expected: "false".to_string(),
+            actual: "true".to_string(),
+            first_divergent_signature: replay.first_negative,
+        });
+    }
+
+    BooksReport {
+        vault: replay.vault.to_string(),
+        transactions_replayed: replay.transactions_replayed,
+        expected_total_deposited: replay.total_deposited,
+        actual_total_deposited: vault.total_deposited,
+        expected_token_balance,
+        actual_token_balance,
+        expected_accrued_fees: replay.accrued_fees,
+        actual_accrued_fees: vault.accrued_fees,
+        expected_total_reserved: replay.total_reserved,
+        actual_total_reserved: vault.total_reserved,
+        expected_reserve_balance: replay.total_reserved,
+        actual_reserve_balance,
+        fees_collected: replay.fees_collected,
+        positions: replay
+            .positions
+            .iter()
+            .map(|(owner, position)| (owner.to_string(), *position))
+            .collect(),
+        discrepancies,
+        unverifiable_before_slot,
+    }
+}
@@ -0,0 +1,95 @@
+//! Reading many vaults at once. `get_vaults` and `get_vault_balances` fetch
+//! their accounts with `getMultipleAccounts`, up to `MAX_MULTIPLE_ACCOUNTS`
+//! per request, and answer in the order they were asked. A missing account
+//! is `None` rather than an error, so one closed vault does not fail a
+//! dashboard's whole poll.
+
+use anchor_client::solana_sdk::{account::Account, program_pack::Pack, pubkey::Pubkey};
+use anchor_lang::AccountDeserialize;
+use anchor_spl::token::spl_token::state::Account as TokenAccount;
+use anyhow::{anyhow, Context, Result};
+
+use crate::logging;
+use crate::token_vault::{pda, state::Vault};
+use crate::{DeadlinePhase, TokenVaultClient};
+
+/// Most addresses one `getMultipleAccounts` request may carry
+pub const MAX_MULTIPLE_ACCOUNTS: usize = 100;
+
+/// `fetch` applied to `addresses` at most `MAX_MULTIPLE_ACCOUNTS` at a time,
+/// its answers concatenated in order. Each chunk must answer every address.
+pub fn in_chunks<T>(
+    addresses: &[Pubkey],
+    mut fetch: impl FnMut(&[Pubkey]) -> Result<Vec<T>>,
+) -> Result<Vec<T>> {
+    let mut all = Vec::with_capacity(addresses.len());
+    for chunk in addresses.chunks(MAX_MULTIPLE_ACCOUNTS) {
+        let answers = fetch(chunk)?;
+        if answers.len() != chunk.len() {
+            return Err(anyhow!(
+                "Asked for {} accounts, got {}",
+                chunk.len(),
+                answers.len()
+            ));
+        }
+        all.extend(answers);
+    }
+    Ok(all)
+}
+
+/// The vault stored in `account`, if there is one at `address`
+pub fn decode_vault(address: &Pubkey, account: Option<&Account>) -> Result<Option<Vault>> {
+    account
+        .map(|account| Vault::try_deserialize(&mut account.data.as_slice()))
+        .transpose()
+        .with_context(|| format!("Account {} is not a vault", address))
+}
+
+/// The token balance stored in `account`, if there is one at `address`
+pub fn decode_token_balance(address: &Pubkey, account: Option<&Account>) -> Result<Option<u64>> {
+    account
+        .map(|account| TokenAccount::unpack(&account.data).map(|token| token.amount))
+        .transpose()
+        .with_context(|| format!("Account {} is not a token account", address))
+}
+
+impl TokenVaultClient {
+    /// The vaults at `addresses`, in the same order, with `None` for an
+    /// address that holds no account. Accounts that exist but are not vaults
+    /// fail the call. Chunks are separate requests and may be read at
+    /// different slots.
+    pub fn get_vaults(&self, addresses: &[Pubkey]) -> Result<Vec<Option<Vault>>> {
+        let accounts = self.fetch_multiple_accounts(addresses)?;
+        addresses
+            .iter()
+            .zip(&accounts)
+            .map(|(address, account)| decode_vault(address, account.as_ref()))
+            .collect()
+    }
+
+    /// Token balances of the vaults at `addresses`, in the same order, read
+    /// from each vault's token account. `None` when the token account does
+    /// not exist, for example because the address is not a vault.
+    pub fn get_vault_balances(&self, vaults: &[Pubkey]) -> Result<Vec<Option<u64>>> {
+        let token_accounts: Vec<Pubkey> = vaults
+            .iter()
+            .map(|vault| pda::find_vault_token_account_address(&self.program.id(), vault).0)
+            .collect();
+        let accounts = self.fetch_multiple_accounts(&token_accounts)?;
+        token_accounts
+            .iter()
+            .zip(&accounts)
+            .map(|(address, account)| decode_token_balance(address, account.as_ref()))
+            .collect()
+    }
+
+    fn fetch_multiple_accounts(&self, addresses: &[Pubkey]) -> Result<Vec<Option<Account>>> {
+        let deadline = self.deadline();
+        let rpc = self.rpc_within(deadline, DeadlinePhase::Fetch)?;
+        let commitment = self.read_commitment_for(&rpc);
+        in_chunks(addresses, |chunk| {
+            deadline.check(DeadlinePhase::Fetch, None)?;
+            Ok(logging::get_multiple_accounts(&rpc, chunk, commitment)?.value)
+        })
+    }
+}
@@ -0,0 +1,249 @@
+use anchor_client::{
+    solana_client::rpc_client::RpcClient,
+    solana_sdk::{account::Account, commitment_config::CommitmentConfig, pubkey::Pubkey},
+};
+use anyhow::{anyhow, Result};
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::{logging, Provenance};
+
+/// What a cached account holds, which decides how long it stays fresh
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CachedKind {
+    /// Mint accounts practically never change
+    Mint,
+    Vault,
+    TokenAccount,
+    Other,
+}
+
+/// Per-kind freshness windows for the RPC cache
+#[derive(Debug, Clone)]
+pub struct RpcCacheConfig {
+    pub mint_ttl: Duration,
+    pub vault_ttl: Duration,
+    pub token_account_ttl: Duration,
+    pub other_ttl: Duration,
+    /// Serve stale entries immediately and refresh them on a background thread
+    /// instead of blocking the caller on a fresh fetch
+    pub refresh_in_background: bool,
+}
+
+impl Default for RpcCacheConfig {
+    fn default() -> Self {
+        Self {
+            mint_ttl: Duration::from_secs(24 * 60 * 60),
+            vault_ttl: Duration::from_secs(5),
+            token_account_ttl: Duration::from_secs(5),
+            other_ttl: Duration::from_secs(5),
+            refresh_in_background: true,
+        }
+    }
+}
+
+impl RpcCacheConfig {
+    fn ttl(&self, kind: CachedKind) -> Duration {
+        match kind {
+            CachedKind::Mint => self.mint_ttl,
+            CachedKind::Vault => self.vault_ttl,
+            CachedKind::TokenAccount => self.token_account_ttl,
+            CachedKind::Other => self.other_ttl,
+        }
+    }
+}
+
+/// Counters describing how well the cache is doing
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheMetrics {
+    pub hits: u64,
+    pub stale_hits: u64,
+    pub misses: u64,
+    pub background_refreshes: u64,
+    pub invalidations: u64,
+}
+
+/// An account as last seen by the cache, tagged with the slot and commitment
+/// it was read at
+#[derive(Debug, Clone)]
+pub struct CachedAccount {
+    pub account: Account,
+    pub context_slot: u64,
+    pub commitment: CommitmentConfig,
+    pub kind: CachedKind,
+    pub fetched_at: Instant,
+}
+
+impl CachedAccount {
+    pub fn provenance(&self) -> Provenance {
+        Provenance {
+            slot: self.context_slot,
+            commitment: self.commitment.commitment,
+        }
+    }
+}
+
+type CacheKey = (Pubkey, CommitmentConfig);
+
+#[derive(Default)]
+struct Counters {
+    hits: AtomicU64,
+    stale_hits: AtomicU64,
+    misses: AtomicU64,
+    background_refreshes: AtomicU64,
+    invalidations: AtomicU64,
+}
+
+struct Shared {
+    entries: Mutex<HashMap<CacheKey, CachedAccount>>,
+    refreshing: Mutex<HashSet<CacheKey>>,
+    counters: Counters,
+}
+
+impl Shared {
+    /// Store a fetched account unless a newer slot is already cached
+    fn store(&self, key: CacheKey, entry: CachedAccount) {
+        let mut entries = self.entries.lock().unwrap();
+        match entries.get(&key) {
+            Some(existing) if existing.context_slot > entry.context_slot => {}
+            _ => {
+                entries.insert(key, entry);
+            }
+        }
+    }
+}
+
+/// Account cache keyed by (address, commitment), with stale-while-revalidate refreshes
+pub struct RpcCache {
+    config: RpcCacheConfig,
+    shared: Arc<Shared>,
+    refresh_tx: Option<Mutex<Sender<(CacheKey, CachedKind)>>>,
+}
+
+impl RpcCache {
+    /// Create a cache; background refreshes use their own connection to `rpc_url`
+    pub fn new(config: RpcCacheConfig, rpc_url: String) -> Self {
+        let shared = Arc::new(Shared {
+            entries: Mutex::new(HashMap::new()),
+            refreshing: Mutex::new(HashSet::new()),
+            counters: Counters::default(),
+        });
+
+        let refresh_tx = if config.refresh_in_background {
+            let (tx, rx) = mpsc::channel::<(CacheKey, CachedKind)>();
+            let worker = Arc::clone(&shared);
+            // The thread exits once the cache (and with it the sender) is dropped
+            thread::spawn(move || {
+                let rpc = RpcClient::new(rpc_url);
+                for (key, kind) in rx {
+                    if let Ok(Some(entry)) = fetch(&rpc, &key, kind) {
+                        worker.store(key, entry);
+                        worker
+                            .counters
+                            .background_refreshes
+                            .fetch_add(1, Ordering::Relaxed);
+                    }
+                    worker.refreshing.lock().unwrap().remove(&key);
+                }
+            });
+            Some(Mutex::new(tx))
+        } else {
+            None
+        };
+
+        Self {
+            config,
+            shared,
+            refresh_tx,
+        }
+    }
+
+    /// The same cache with background refreshes going to `rpc_url` instead;
+    /// cached entries are dropped
+    pub fn reconnect(&self, rpc_url: String) -> Self {
+        Self::new(self.config.clone(), rpc_url)
+    }
+
+    /// Return the cached account, fetching it through `rpc` when absent or
+    /// (without background refresh) expired
+    pub fn get_account(
+        &self,
+        rpc: &RpcClient,
+        address: Pubkey,
+        commitment: CommitmentConfig,
+        kind: CachedKind,
+    ) -> Result<CachedAccount> {
+        let key = (address, commitment);
+        let cached = self.shared.entries.lock().unwrap().get(&key).cloned();
+        let counters = &self.shared.counters;
+
+        if let Some(entry) = cached {
+            if entry.fetched_at.elapsed() <= self.config.ttl(kind) {
+                logging::log_cache_lookup(&address, kind, "hit");
+                counters.hits.fetch_add(1, Ordering::Relaxed);
+                return Ok(entry);
+            }
+            if let Some(tx) = &self.refresh_tx {
+                logging::log_cache_lookup(&address, kind, "stale");
+                counters.stale_hits.fetch_add(1, Ordering::Relaxed);
+                // Queue at most one refresh per key at a time
+                if self.shared.refreshing.lock().unwrap().insert(key) {
+                    let _ = tx.lock().unwrap().send((key, kind));
+                }
+                return Ok(entry);
+            }
+        }
+
+        logging::log_cache_lookup(&address, kind, "miss");
+        counters.misses.fetch_add(1, Ordering::Relaxed);
+        let entry =
+            fetch(rpc, &key, kind)?.ok_or_else(|| anyhow!("Account {} not found", address))?;
+        self.shared.store(key, entry.clone());
+        Ok(entry)
+    }
+
+    /// Drop every cached commitment level of `address`
+    pub fn invalidate(&self, address: &Pubkey) {
+        let mut entries = self.shared.entries.lock().unwrap();
+        let before = entries.len();
+        entries.retain(|(key, _), _| key != address);
+        if entries.len() != before {
+            self.shared
+                .counters
+                .invalidations
+                .fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Drop everything
+    pub fn clear(&self) {
+        self.shared.entries.lock().unwrap().clear();
+    }
+
+    pub fn metrics(&self) -> CacheMetrics {
+        let counters = &self.shared.counters;
+        CacheMetrics {
+            hits: counters.hits.load(Ordering::Relaxed),
+            stale_hits: counters.stale_hits.load(Ordering::Relaxed),
+            misses: counters.misses.load(Ordering::Relaxed),
+            background_refreshes: counters.background_refreshes.load(Ordering::Relaxed),
+            invalidations: counters.invalidations.load(Ordering::Relaxed),
+        }
+    }
+}
+
+fn fetch(rpc: &RpcClient, key: &CacheKey, kind: CachedKind) -> Result<Option<CachedAccount>> {
+    let (address, commitment) = key;
+    let response = logging::get_account(rpc, address, *commitment)?;
+    Ok(response.value.map(|account| CachedAccount {
+        account,
+        context_slot: response.context.slot,
+        commitment: *commitment,
+        kind,
+        fetched_at: Instant::now(),
+    }))
+}
@@ -0,0 +1,451 @@
+//! A changelog of vault state for downstream caches. `get_changes_since`
+//! turns the vault's history after a slot into normalized `StateChange`s,
+//! and `merge_into_summary` applies them to a cached `VaultSummary`. A
+//! summary read at slot N with everything after N merged in equals a
+//! summary read at the slot of the last change, except for `freeze`, which
+//! no instruction of the program changes and history cannot show.
+
+use anchor_client::solana_sdk::{hash::Hash, pubkey::Pubkey, signature::Signature};
+use anchor_lang::{AnchorDeserialize, Discriminator};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::plan::{format_bps, format_duration};
+use crate::reserve::reserve_carve_out;
+use crate::token_vault::{events::WithdrawEvent, instruction};
+use crate::{
+    BackfillConfig, MaintenanceWindow, NotificationConfig, TokenVaultClient, VaultInstructionKind,
+    VaultSummary, VaultTransaction,
+};
+
+/// One transition of a vault's state
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "change", rename_all = "snake_case")]
+pub enum VaultChange {
+    /// The vault was created with this configuration
+    Initialized {
+        name: String,
+        #[serde(with = "crate::serde_utils::pubkey")]
+        authority: Pubkey,
+        #[serde(with = "crate::serde_utils::pubkey")]
+        token_mint: Pubkey,
+        #[serde(with = "crate::serde_utils::pubkey")]
+        fee_collector: Pubkey,
+        fee_percentage: u16,
+        withdrawal_timelock: i64,
+        withdrawal_limit: u64,
+        reserve_bps: u16,
+        immutable: bool,
+    },
+    /// `amount` came in, `reserved` of it into the insurance reserve
+    Deposited {
+        amount: u64,
+        reserved: u64,
+    },
+    /// `amount` left the books; `fee` of it stayed in the vault as accrued
+    /// fees
+    Withdrawn {
+        amount: u64,
+        #[serde(default)]
+        fee: u64,
+    },
+    ReserveReleased {
+        amount: u64,
+    },
+    /// The fee collector claimed every accrued fee
+    FeesClaimed,
+    AllowlistAdded {
+        #[serde(with = "crate::serde_utils::pubkey")]
+        wallet: Pubkey,
+    },
+    /// The allowlist entry account was closed
+    AllowlistRemoved {
+        #[serde(with = "crate::serde_utils::pubkey")]
+        entry: Pubkey,
+    },
+    NotificationsSet {
+        config: NotificationConfig,
+    },
+    NotificationsCleared,
+    MaintenanceScheduled {
+        window: MaintenanceWindow,
+    },
+    /// `update_vault_config` changed the settings that are `Some`
+    ConfigUpdated {
+        fee_percentage: Option<u16>,
+        withdrawal_timelock: Option<i64>,
+        withdrawal_limit: Option<u64>,
+    },
+    AuthorityTransferred {
+        #[serde(with = "crate::serde_utils::pubkey")]
+        new_authority: Pubkey,
+    },
+    FeeCollectorSet {
+        #[serde(with = "crate::serde_utils::pubkey")]
+        fee_collector: Pubkey,
+    },
+    Paused,
+    Unpaused,
+    /// The program clamped `counter` to zero instead of letting it underflow
+    AccountingAnomaly {
+        counter: String,
+    },
+}
+
+impl fmt::Display for VaultChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Initialized {
+                name, authority, ..
+            } => write!(f, "initialized '{}' (authority {})", name, authority),
+            Self::Deposited { amount, reserved } => {
+                write!(f, "deposited {} ({} reserved)", amount, reserved)
+            }
+            Self::Withdrawn { amount, fee } => write!(f, "withdrawn {} ({} fee)", amount, fee),
+            Self::ReserveReleased { amount } => write!(f, "reserve released {}", amount),
+            Self::FeesClaimed => write!(f, "fees claimed"),
+            Self::AllowlistAdded { wallet } => write!(f, "allowlisted {}", wallet),
+            Self::AllowlistRemoved { entry } => write!(f, "allowlist entry {} removed", entry),
+            Self::NotificationsSet { config } => write!(f, "notifications set: {}", config),
+            Self::NotificationsCleared => write!(f, "notifications cleared"),
+            Self::MaintenanceScheduled { window } => write!(f, "maintenance scheduled {}", window),
+            Self::ConfigUpdated {
+                fee_percentage,
+                withdrawal_timelock,
+                withdrawal_limit,
+            } => {
+                let settings: Vec<_> = [
+                    fee_percentage.map(|fee| format!("fee {}", format_bps(fee))),
+                    withdrawal_timelock.map(|timelock| {
+                        format!("withdrawal timelock {}", format_duration(timelock))
+                    }),
+                    withdrawal_limit.map(|limit| format!("withdrawal limit {}", limit)),
+                ]
+                .into_iter()
+                .flatten()
+                .collect();
+                write!(f, "config updated: {}", settings.join(", "))
+            }
+            Self::AuthorityTransferred { new_authority } => {
+                write!(f, "authority transferred to {}", new_authority)
+            }
+            Self::FeeCollectorSet { fee_collector } => {
+                write!(f, "fee collector set to {}", fee_collector)
+            }
+            Self::Paused => write!(f, "paused"),
+            Self::Unpaused => write!(f, "unpaused"),
+            Self::AccountingAnomaly { counter } => write!(f, "{} clamped to zero", counter),
+        }
+    }
+}
+
+/// A vault change and the transaction that made it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StateChange {
+    pub slot: u64,
+    #[serde(with = "crate::serde_utils::signature")]
+    pub signature: Signature,
+    #[serde(flatten)]
+    pub change: VaultChange,
+}
+
+impl fmt::Display for StateChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "slot {} {}: {}", self.slot, self.signature, self.change)
+    }
+}
+
+/// The changes `transaction` made to `vault`, in instruction order with
+/// logged anomalies last. Failed transactions change nothing.
+/// `reserve_bps` is the vault's, which is fixed at creation.
+pub fn vault_changes(
+    vault: &Pubkey,
+    reserve_bps: u16,
+    transaction: &VaultTransaction,
+) -> Vec<StateChange> {
+    if !transaction.succeeded {
+        return Vec::new();
+    }
+    let withdrawals = transaction.withdraw_events();
+    let vault = vault.to_string();
+    let mut changes = Vec::new();
+    for ix in &transaction.instructions {
+        // Every vault instruction lists its signer first and the vault second
+        if ix.accounts.get(1) != Some(&vault) {
+            continue;
+        }
+        if let Some(change) =
+            decode_change(ix.kind, &ix.accounts, &ix.data, reserve_bps, &withdrawals)
+        {
+            changes.push(change);
+        }
+    }
+    changes.extend(
+        transaction
+            .accounting_anomalies()
+            .into_iter()
+            .filter(|anomaly| anomaly.vault.to_string() == vault)
+            .map(|anomaly| VaultChange::AccountingAnomaly {
+                counter: anomaly.counter,
+            }),
+    );
+    changes
+        .into_iter()
+        .map(|change| StateChange {
+            slot: transaction.slot,
+            signature: transaction.signature,
+            change,
+        })
+        .collect()
+}
+
+fn decode_change(
+    kind: VaultInstructionKind,
+    accounts: &[String],
+    data: &[u8],
+    reserve_bps: u16,
+    withdrawals: &[WithdrawEvent],
+) -> Option<VaultChange> {
+    let args = data.get(8..).unwrap_or_default();
+    let account = |index: usize| accounts.get(index)?.parse::<Pubkey>().ok();
+    // Every withdrawal lists its withdrawer first and the vault second
+    let withdrawal = || {
+        let (withdrawer, vault) = (account(0)?, account(1)?);
+        withdrawals
+            .iter()
+            .find(|event| event.vault == vault && event.withdrawer == withdrawer)
+    };
+    let change = match kind {
+        VaultInstructionKind::InitializeVault => {
+            let init = instruction::InitializeVault::try_from_slice(args).ok()?;
+            VaultChange::Initialized {
+                name: init.name,
+                authority: account(0)?,
+                token_mint: account(4)?,
+                fee_collector: init.fee_collector,
+                fee_percentage: init.fee_percentage,
+                withdrawal_timelock: init.withdrawal_timelock,
+                withdrawal_limit: init.withdrawal_limit,
+                reserve_bps: init.reserve_bps,
+                immutable: init.immutable,
+            }
+        }
+        VaultInstructionKind::Deposit => {
+            let amount = instruction::Deposit::try_from_slice(args).ok()?.amount;
+            VaultChange::Deposited {
+                amount,
+                reserved: reserve_carve_out(amount, reserve_bps),
+            }
+        }
+        // The fee is the one the withdrawal's event logged, if any
+        VaultInstructionKind::Withdraw => VaultChange::Withdrawn {
+            amount: instruction::Withdraw::try_from_slice(args).ok()?.amount,
+            fee: withdrawal().map_or(0, |event| event.fee),
+        },
+        // The request, and with it the amount, is closed by the time the
+        // execution is read back; the withdrawal's event still carries it
+        VaultInstructionKind::ExecuteWithdrawal => {
+            let event = withdrawal()?;
+            VaultChange::Withdrawn {
+                amount: event.amount,
+                fee: event.fee,
+            }
+        }
+        // Nothing moves until a request is executed
+        VaultInstructionKind::RequestWithdrawal | VaultInstructionKind::CancelWithdrawal => {
+            return None
+        }
+        VaultInstructionKind::ReleaseReserve => VaultChange::ReserveReleased {
+            amount: instruction::ReleaseReserve::try_from_slice(args)
+                .ok()?
+                .amount,
+        },
+        VaultInstructionKind::ClaimFees => VaultChange::FeesClaimed,
+        VaultInstructionKind::AddAllowlistEntry => VaultChange::AllowlistAdded {
+            wallet: instruction::AddAllowlistEntry::try_from_slice(args)
+                .ok()?
+                .wallet,
+        },
+        VaultInstructionKind::RemoveAllowlistEntry => {
+            VaultChange::AllowlistRemoved { entry: account(2)? }
+        }
+        // Sweeps move lamports only, never tokens
+        VaultInstructionKind::SweepLamports => return None,
+        // Notification configs are metadata, outside the history's kinds
+        VaultInstructionKind::Unknown => match data.get(..8)? {
+            d if d == instruction::SetNotificationConfig::DISCRIMINATOR => {
+                let set = instruction::SetNotificationConfig::try_from_slice(args).ok()?;
+                VaultChange::NotificationsSet {
+                    config: NotificationConfig {
+                        webhook_url_hash: set.webhook_url_hash.map(Hash::new_from_array),
+                        explorer: set.explorer,
+                        support_contact: set.support_contact,
+                    },
+                }
+            }
+            d if d == instruction::ClearNotificationConfig::DISCRIMINATOR => {
+                VaultChange::NotificationsCleared
+            }
+            d if d == instruction::UpdateVaultConfig::DISCRIMINATOR => {
+                let update = instruction::UpdateVaultConfig::try_from_slice(args).ok()?;
+                VaultChange::ConfigUpdated {
+                    fee_percentage: update.fee_percentage,
+                    withdrawal_timelock: update.withdrawal_timelock,
+                    withdrawal_limit: update.withdrawal_limit,
+                }
+            }
+            d if d == instruction::TransferAuthority::DISCRIMINATOR => {
+                VaultChange::AuthorityTransferred {
+                    new_authority: instruction::TransferAuthority::try_from_slice(args)
+                        .ok()?
+                        .new_authority,
+                }
+            }
+            d if d == instruction::SetFeeCollector::DISCRIMINATOR => VaultChange::FeeCollectorSet {
+                fee_collector: instruction::SetFeeCollector::try_from_slice(args)
+                    .ok()?
+                    .new_fee_collector,
+            },
+            d if d == instruction::PauseVault::DISCRIMINATOR => VaultChange::Paused,
+            d if d == instruction::UnpauseVault::DISCRIMINATOR => VaultChange::Unpaused,
+            _ => return None,
+        },
+    };
+    Some(change)
+}
+
+/// Apply `changes`, oldest first, to `summary`. Changes at or before the
+/// summary's provenance slot are already in it and are skipped, so merging
+/// the same changes twice is harmless; the provenance then moves to the
+/// last change's slot. Notifications match a summary read with
+/// `with_extensions(true)`. A live balance follows deposits, withdrawals
+/// and fee claims, but not tokens sent straight to the token account.
+pub fn merge_into_summary(summary: &mut VaultSummary, changes: &[StateChange]) {
+    let read_at = summary.provenance.map(|provenance| provenance.slot);
+    for change in changes {
+        if read_at.is_some_and(|read_at| change.slot <= read_at) {
+            continue;
+        }
+        if let Some(provenance) = summary.provenance.as_mut() {
+            provenance.slot = provenance.slot.max(change.slot);
+        }
+        let claimed = summary.accrued_fees;
+        match &change.change {
+            VaultChange::Initialized {
+                name,
+                authority,
+                token_mint,
+                fee_collector,
+                fee_percentage,
+                withdrawal_timelock,
+                withdrawal_limit,
+                reserve_bps,
+                immutable,
+            } => {
+                summary.name = name.clone();
+                summary.authority = *authority;
+                summary.token_mint = *token_mint;
+                summary.fee_collector = *fee_collector;
+                summary.fee_percentage = *fee_percentage;
+                summary.withdrawal_timelock = *withdrawal_timelock;
+                summary.withdrawal_limit = *withdrawal_limit;
+                summary.reserve_bps = *reserve_bps;
+                summary.immutable = *immutable;
+                summary.total_deposited = 0;
+                summary.total_reserved = 0;
+                summary.accrued_fees = 0;
+                summary.accounting_anomaly = false;
+            }
+            VaultChange::Deposited { amount, reserved } => {
+                summary.total_deposited += amount - reserved;
+                summary.total_reserved += reserved;
+            }
+            // The program clamps at zero, and logs an anomaly when it does
+            VaultChange::Withdrawn { amount, fee } => {
+                summary.total_deposited = summary.total_deposited.saturating_sub(*amount);
+                summary.accrued_fees += fee;
+            }
+            VaultChange::ReserveReleased { amount } => {
+                summary.total_reserved = summary.total_reserved.saturating_sub(*amount)
+            }
+            VaultChange::FeesClaimed => summary.accrued_fees = 0,
+            VaultChange::NotificationsSet { config } => {
+                summary.notifications = Some(config.clone())
+            }
+            VaultChange::NotificationsCleared => summary.notifications = None,
+            VaultChange::AuthorityTransferred { new_authority } => {
+                summary.authority = *new_authority
+            }
+            VaultChange::FeeCollectorSet { fee_collector } => {
+                summary.fee_collector = *fee_collector
+            }
+            VaultChange::ConfigUpdated {
+                fee_percentage,
+                withdrawal_timelock,
+                withdrawal_limit,
+            } => {
+                if let Some(fee_percentage) = fee_percentage {
+                    summary.fee_percentage = *fee_percentage;
+                }
+                if let Some(withdrawal_timelock) = withdrawal_timelock {
+                    summary.withdrawal_timelock = *withdrawal_timelock;
+                }
+                if let Some(withdrawal_limit) = withdrawal_limit {
+                    summary.withdrawal_limit = *withdrawal_limit;
+                }
+            }
+            VaultChange::MaintenanceScheduled { window } => {
+                summary.maintenance.push(window.clone());
+                summary.maintenance.sort_by_key(|window| window.start);
+            }
+            VaultChange::Paused => summary.paused = true,
+            VaultChange::Unpaused => summary.paused = false,
+            VaultChange::AccountingAnomaly { .. } => summary.accounting_anomaly = true,
+            // Summaries do not carry the allowlist
+            VaultChange::AllowlistAdded { .. } | VaultChange::AllowlistRemoved { .. } => {}
+        }
+        if let Some(balance) = summary.balance.as_mut() {
+            match &change.change {
+                VaultChange::Initialized { .. } => balance.amount = 0,
+                VaultChange::Deposited { amount, reserved } => balance.amount += amount - reserved,
+                // The fee stays behind until it is claimed
+                VaultChange::Withdrawn { amount, fee } => {
+                    balance.amount = balance.amount.saturating_sub(amount - fee)
+                }
+                VaultChange::FeesClaimed => balance.amount = balance.amount.saturating_sub(claimed),
+                _ => {}
+            }
+            balance.discrepancy = balance.amount as i128
+                - summary.total_deposited as i128
+                - summary.accrued_fees as i128;
+        }
+    }
+}
+
+impl TokenVaultClient {
+    /// Every change to the current vault after `slot`, oldest first
+    pub fn get_changes_since(&self, slot: u64) -> Result<Vec<StateChange>> {
+        let vault = self.current_vault()?;
+        let deadline = self.deadline();
+        let vault_data = self.fetch_vault(vault, deadline)?;
+
+        let mut history = Vec::new();
+        let config = BackfillConfig {
+            after_slot: Some(slot),
+            deadline,
+            ..Default::default()
+        };
+        self.history_backfill(vault, config)
+            .run(&mut |transaction: &VaultTransaction| {
+                history.push(transaction.clone());
+                Ok(())
+            })?;
+        // Backfill delivers newest first
+        Ok(history
+            .iter()
+            .rev()
+            .flat_map(|transaction| vault_changes(&vault, vault_data.reserve_bps, transaction))
+            .collect())
+    }
+}
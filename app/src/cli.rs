@@ -0,0 +1,1219 @@
+//! Command-line definition of the `token-vault` binary.
+//!
+//! The parser, the shell completions and the man page are all generated from
+//! `build_cli`, so they cannot drift apart.
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use anyhow::{anyhow, Result};
+use clap::builder::PossibleValuesParser;
+use clap::{value_parser, Arg, ArgAction, ArgGroup, Command};
+use clap_complete::Shell;
+use serde::Deserialize;
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::{AddressBook, KeyPolicy, LaunchPlan};
+
+pub const BIN_NAME: &str = "token-vault";
+
+/// Environment variable overriding the CLI config file location
+pub use crate::config::CONFIG_ENV;
+
+/// File name of the vault index, kept next to the config file
+const VAULT_INDEX_FILE: &str = "index.json";
+
+/// File name of the receipts of administrative actions, kept next to the config file
+const RECEIPTS_FILE: &str = "receipts.jsonl";
+
+/// File name of the rent and fee estimate tables, kept next to the config file
+const ESTIMATES_FILE: &str = "estimates.json";
+
+/// The full `token-vault` command tree
+pub fn build_cli() -> Command {
+    Command::new(BIN_NAME)
+        .about("Manage token vaults from the terminal")
+        .version(env!("CARGO_PKG_VERSION"))
+        .subcommand_required(true)
+        .arg_required_else_help(true)
+        .arg(
+            Arg::new("profile")
+                .long("profile")
+                .short('p')
+                .global(true)
+                .value_name("NAME")
+                .help("Profile from the config file supplying cluster, keypair and program id"),
+        )
+        .arg(
+            Arg::new("url")
+                .long("url")
+                .short('u')
+                .global(true)
+                .value_name("CLUSTER")
+                .help("Cluster moniker (devnet, testnet, mainnet, localnet) or RPC URL"),
+        )
+        .arg(
+            Arg::new("write-url")
+                .long("write-url")
+                .global(true)
+                .value_name("URL")
+                .help("RPC URL to send transactions through [default: the cluster's]"),
+        )
+        .arg(
+            Arg::new("read-url")
+                .long("read-url")
+                .global(true)
+                .value_name("URL")
+                .help("RPC URL for account reads and history, e.g. a replica [default: write URL]"),
+        )
+        .arg(
+            Arg::new("keypair")
+                .long("keypair")
+                .short('k')
+                .global(true)
+                .value_name("PATH")
+                .value_hint(clap::ValueHint::FilePath)
+                .help("Signer keypair file [default: ~/.config/solana/id.json]"),
+        )
+        .arg(
+            Arg::new("program-id")
+                .long("program-id")
+                .global(true)
+                .value_name("PUBKEY")
+                .help("Token vault program id"),
+        )
+        .arg(
+            Arg::new("vault")
+                .long("vault")
+                .global(true)
+                .value_name("ADDRESS|ALIAS")
+                .help("Vault address, or an alias from the config file"),
+        )
+        .arg(
+            Arg::new("vault-name")
+                .long("vault-name")
+                .global(true)
+                .value_name("NAME")
+                .conflicts_with_all(["vault", "alias"])
+                .help("Vault whose name starts with or contains NAME, from the vault index"),
+        )
+        .arg(
+            Arg::new("alias")
+                .long("alias")
+                .global(true)
+                .value_name("ALIAS")
+                .conflicts_with("vault")
+                .help("Vault alias from the config file or the vault index"),
+        )
+        .arg(
+            Arg::new("override-key-policy")
+                .long("override-key-policy")
+                .global(true)
+                .action(ArgAction::SetTrue)
+                .help("Sign even if the profile's key policy forbids this key on this cluster"),
+        )
+        .subcommand(
+            Command::new("init")
+                .about("Initialize a new vault owned by the signer")
+                .arg(pubkey_arg("mint", "Mint of the token the vault holds").required(true))
+                .arg(
+                    Arg::new("name")
+                        .long("name")
+                        .required(true)
+                        .help("Vault name, at most 32 bytes"),
+                )
+                // Like the config builder, nothing here has a default: a
+                // withdrawal limit of 0 would refuse every withdrawal
+                .arg(fee_bps_arg().required(true))
+                .arg(timelock_arg().required(true))
+                .arg(withdrawal_limit_arg())
+                .arg(
+                    Arg::new("withdrawal-limit-ui")
+                        .long("withdrawal-limit-ui")
+                        .value_name("TOKENS")
+                        .help("Maximum amount per withdrawal in whole tokens, e.g. 1000.5"),
+                )
+                .group(
+                    ArgGroup::new("limit")
+                        .args(["withdrawal-limit", "withdrawal-limit-ui"])
+                        .required(true),
+                )
+                .arg(pubkey_arg(
+                    "fee-collector",
+                    "Receives withdrawal fees; defaults to the signer",
+                ))
+                .arg(
+                    Arg::new("immutable")
+                        .long("immutable")
+                        .action(ArgAction::SetTrue)
+                        .requires("i-understand")
+                        .help(
+                            "Never allow the configuration or authority to change (irreversible; \
+                             needs --i-understand)",
+                        ),
+                )
+                .arg(
+                    Arg::new("i-understand")
+                        .long("i-understand")
+                        .action(ArgAction::SetTrue)
+                        .requires("immutable")
+                        .help("Confirm that --immutable can never be undone"),
+                )
+                .args(deposit_window_args())
+                .args(dust_args())
+                .args(reserve_args())
+                .arg(
+                    Arg::new("acknowledge-freezable-mint")
+                        .long("acknowledge-freezable-mint")
+                        .action(ArgAction::SetTrue)
+                        .help(
+                            "Create the vault even though the mint's freeze authority can \
+                             freeze its tokens (required by strict_mint_policy)",
+                        ),
+                )
+                .arg(
+                    Arg::new("dry-run")
+                        .long("dry-run")
+                        .action(ArgAction::SetTrue)
+                        .help("Show the vault that would be created without sending anything"),
+                ),
+        )
+        .subcommand(
+            Command::new("deposit")
+                .about("Deposit tokens into the vault")
+                .arg(amount_arg())
+                .arg(
+                    Arg::new("from")
+                        .long("from")
+                        .value_name("TOKEN_ACCOUNT")
+                        .help("Pay from this token account instead of your associated one"),
+                )
+                .arg(
+                    Arg::new("sol")
+                        .long("sol")
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("from")
+                        .help("Wrap AMOUNT lamports of native SOL into the native-mint vault"),
+                )
+                .arg(memo_arg().conflicts_with_all(["from", "sol"]))
+                .arg(
+                    Arg::new("json")
+                        .long("json")
+                        .action(ArgAction::SetTrue)
+                        .help("Print the confirmed deposit as JSON"),
+                ),
+        )
+        .subcommand(
+            Command::new("withdraw")
+                .about("Withdraw tokens from the vault")
+                .arg(amount_arg())
+                .arg(
+                    Arg::new("to")
+                        .long("to")
+                        .value_name("PUBKEY")
+                        .help("Send the tokens to this wallet's token account instead of yours"),
+                )
+                .arg(
+                    Arg::new("to-account")
+                        .long("to-account")
+                        .value_name("TOKEN_ACCOUNT")
+                        .conflicts_with("to")
+                        .help("Send the tokens to this token account"),
+                )
+                .arg(
+                    Arg::new("create-ata")
+                        .long("create-ata")
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("to-account")
+                        .help("Create your missing token account in the same transaction"),
+                )
+                .arg(
+                    Arg::new("skip-limit-check")
+                        .long("skip-limit-check")
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with_all(["to-account", "sol"])
+                        .help(
+                            "Send a withdrawal over the vault's withdrawal limit instead of \
+                             refusing it; the program still enforces its limit",
+                        ),
+                )
+                .arg(
+                    Arg::new("sol")
+                        .long("sol")
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with_all(["to", "to-account", "create-ata"])
+                        .help("Unwrap the withdrawn lamports of the native-mint vault to native SOL"),
+                )
+                .arg(memo_arg().conflicts_with_all(["to-account", "sol"]))
+                .arg(
+                    Arg::new("yes")
+                        .long("yes")
+                        .short('y')
+                        .action(ArgAction::SetTrue)
+                        .help("Do not ask to confirm a withdrawal to another wallet or account"),
+                )
+                .arg(
+                    Arg::new("json")
+                        .long("json")
+                        .action(ArgAction::SetTrue)
+                        .help("Print the confirmed withdrawal as JSON"),
+                ),
+        )
+        .subcommand(
+            Command::new("withdrawal")
+                .about("Request, execute or cancel a timelocked withdrawal")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("request")
+                        .about("Start the vault's withdrawal timelock on an amount")
+                        .arg(amount_arg()),
+                )
+                .subcommand(
+                    Command::new("execute")
+                        .about("Withdraw a requested amount once its timelock has passed")
+                        .arg(
+                            pubkey_arg("request", "The withdrawal request to execute")
+                                .required(true),
+                        ),
+                )
+                .subcommand(
+                    Command::new("cancel")
+                        .about("Close a withdrawal request without withdrawing")
+                        .arg(
+                            pubkey_arg("request", "The withdrawal request to cancel")
+                                .required(true),
+                        ),
+                )
+                .subcommand(
+                    Command::new("pending")
+                        .about("Show your withdrawal requests and the time left on each")
+                        .arg(
+                            Arg::new("json")
+                                .long("json")
+                                .action(ArgAction::SetTrue)
+                                .help("Print the requests as JSON"),
+                        ),
+                ),
+        )
+        .subcommand(
+            Command::new("quote")
+                .about("Show the fee and net amount of a withdrawal")
+                .arg(amount_arg())
+                .arg(
+                    Arg::new("deposit")
+                        .long("deposit")
+                        .action(ArgAction::SetTrue)
+                        .help("Quote a deposit instead: the reserve carve-out and amount credited"),
+                ),
+        )
+        .subcommand(
+            Command::new("info")
+                .about("Show the vault's on-chain configuration")
+                .arg(
+                    Arg::new("extensions")
+                        .long("extensions")
+                        .action(ArgAction::SetTrue)
+                        .help("Also show optional accounts such as the notification config"),
+                )
+                .arg(
+                    Arg::new("json")
+                        .long("json")
+                        .action(ArgAction::SetTrue)
+                        .help("Print the vault as JSON"),
+                ),
+        )
+        .subcommand(
+            Command::new("liquidity")
+                .about("Break the vault's holdings down by what it takes to move them")
+                .arg(
+                    Arg::new("format")
+                        .long("format")
+                        .value_parser(["text", "json"])
+                        .default_value("text")
+                        .help("Output format"),
+                ),
+        )
+        .subcommand(
+            Command::new("estimates")
+                .about("Show the rent and fee estimates that dry runs use")
+                .arg(
+                    Arg::new("refresh")
+                        .long("refresh")
+                        .action(ArgAction::SetTrue)
+                        .help("Read them from the cluster even if they are fresh"),
+                ),
+        )
+        .subcommand(
+            Command::new("update")
+                .about("Change the vault's fee, timelock or withdrawal limit (authority only)")
+                .arg(fee_bps_arg())
+                .arg(timelock_arg())
+                .arg(withdrawal_limit_arg())
+                .arg(
+                    Arg::new("yes")
+                        .long("yes")
+                        .short('y')
+                        .action(ArgAction::SetTrue)
+                        .help("Send the update without asking for confirmation"),
+                ),
+        )
+        .subcommand(
+            Command::new("transfer-authority")
+                .about("Hand the vault to a new authority (authority only)")
+                .arg(pubkey_arg("to", "The new authority, for example a multisig").required(true)),
+        )
+        .subcommand(
+            Command::new("set-fee-collector")
+                .about("Send future withdrawal fees to a new collector (authority only)")
+                .arg(pubkey_arg("to", "The new fee collector").required(true)),
+        )
+        .subcommand(
+            Command::new("claim-fees")
+                .about("Move the vault's accrued fees to the fee collector (fee collector only)"),
+        )
+        .subcommand(
+            Command::new("pause").about("Stop deposits and withdrawals (authority only)"),
+        )
+        .subcommand(
+            Command::new("unpause").about("Let deposits and withdrawals through again (authority only)"),
+        )
+        .subcommand(
+            Command::new("plan")
+                .about("Preview a change without sending a transaction")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("update")
+                        .about("Preview a configuration update")
+                        .arg(fee_bps_arg())
+                        .arg(timelock_arg())
+                        .arg(withdrawal_limit_arg())
+                        .args(deposit_window_args())
+                        .args(dust_args()),
+                )
+                .subcommand(
+                    Command::new("set-fee-collector")
+                        .about("Preview a fee collector change")
+                        .arg(
+                            Arg::new("fee-collector")
+                                .required(true)
+                                .value_name("PUBKEY")
+                                .help("New fee collector"),
+                        ),
+                )
+                .subcommand(
+                    Command::new("close").about("Preview closing the vault and reclaiming rent"),
+                ),
+        )
+        .subcommand(
+            Command::new("attest")
+                .about("Sign an off-chain attestation that the signer controls the vault")
+                .arg(
+                    Arg::new("nonce")
+                        .long("nonce")
+                        .required(true)
+                        .value_parser(value_parser!(u64))
+                        .help("Nonce chosen by the party requesting the proof"),
+                )
+                .arg(
+                    Arg::new("expiry")
+                        .long("expiry")
+                        .required(true)
+                        .value_parser(value_parser!(i64))
+                        .help("Unix timestamp after which the attestation is invalid"),
+                ),
+        )
+        .subcommand(
+            Command::new("verify-attestation")
+                .about("Verify an attestation against the vault's current authority")
+                .arg(
+                    Arg::new("file")
+                        .required(true)
+                        .value_name("FILE")
+                        .value_hint(clap::ValueHint::FilePath)
+                        .help("Attestation as JSON or base64, or - for stdin"),
+                ),
+        )
+        .subcommand(
+            Command::new("backfill")
+                .about("Download the vault's transaction history as JSON lines")
+                .arg(
+                    Arg::new("output")
+                        .long("output")
+                        .short('o')
+                        .required(true)
+                        .value_name("FILE")
+                        .value_hint(clap::ValueHint::FilePath)
+                        .help("File decoded transactions are appended to"),
+                )
+                .arg(
+                    Arg::new("checkpoint")
+                        .long("checkpoint")
+                        .value_name("FILE")
+                        .value_hint(clap::ValueHint::FilePath)
+                        .help("Checkpoint file to resume from and update"),
+                )
+                .arg(
+                    Arg::new("page-size")
+                        .long("page-size")
+                        .value_parser(value_parser!(usize))
+                        .help("Signatures requested per page (max 1000)"),
+                )
+                .arg(
+                    Arg::new("since")
+                        .long("since")
+                        .value_parser(value_parser!(i64))
+                        .value_name("UNIX_TIME")
+                        .help("Stop at transactions older than this"),
+                ),
+        )
+        .subcommand(
+            Command::new("verify-books")
+                .about("Replay the vault's history and compare it with on-chain state")
+                .long_about(
+                    "Replay the vault's history and compare it with on-chain state. \
+                     Exits with status 1 when the books do not balance.",
+                )
+                .arg(
+                    Arg::new("json")
+                        .long("json")
+                        .action(ArgAction::SetTrue)
+                        .help("Print the report as JSON"),
+                ),
+        )
+        .subcommand(
+            Command::new("stranded")
+                .about("Show lamports stranded above rent exemption in the vault's accounts"),
+        )
+        .subcommand(
+            Command::new("sweep-lamports")
+                .about("Send stranded lamports to a system account")
+                .arg(
+                    pubkey_arg("destination", "System account receiving the lamports")
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            Command::new("reserve")
+                .about("Inspect or release the vault's insurance reserve")
+                .subcommand_required(true)
+                .subcommand(Command::new("balance").about("Show the tokens held in the reserve"))
+                .subcommand(
+                    Command::new("release")
+                        .about("Move tokens out of the reserve (authority only, timelocked)")
+                        .arg(amount_arg())
+                        .arg(pubkey_arg(
+                            "to",
+                            "Wallet receiving the tokens; the signer's when absent",
+                        )),
+                ),
+        )
+        .subcommand(
+            Command::new("allowlist")
+                .about("Manage the wallets approved for the vault")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("sync")
+                        .about("Make the on-chain allowlist match a CSV of wallets")
+                        .arg(
+                            Arg::new("file")
+                                .long("file")
+                                .required(true)
+                                .value_name("CSV")
+                                .value_hint(clap::ValueHint::FilePath)
+                                .help("CSV with one wallet address per line, or - for stdin"),
+                        )
+                        .arg(
+                            Arg::new("dry-run")
+                                .long("dry-run")
+                                .action(ArgAction::SetTrue)
+                                .help("Print the plan without sending anything"),
+                        )
+                        .arg(
+                            Arg::new("yes")
+                                .long("yes")
+                                .short('y')
+                                .action(ArgAction::SetTrue)
+                                .help("Apply the plan without asking for confirmation"),
+                        )
+                        .arg(
+                            Arg::new("record")
+                                .long("record")
+                                .value_name("FILE")
+                                .value_hint(clap::ValueHint::FilePath)
+                                .help("Record of applied changes to resume from and update"),
+                        ),
+                )
+                .subcommand(
+                    Command::new("export")
+                        .about("Print the on-chain allowlist as CSV")
+                        .arg(
+                            Arg::new("output")
+                                .long("output")
+                                .value_name("FILE")
+                                .value_hint(clap::ValueHint::FilePath)
+                                .help("Write to this file instead of stdout"),
+                        ),
+                ),
+        )
+        .subcommand(
+            Command::new("notification-config")
+                .about("Manage the notification metadata wallets read for the vault")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("set")
+                        .about("Publish or replace the vault's notification config")
+                        .arg(
+                            Arg::new("explorer")
+                                .long("explorer")
+                                .value_name("URL")
+                                .help("Base URL of the preferred block explorer"),
+                        )
+                        .arg(
+                            Arg::new("support-contact")
+                                .long("support-contact")
+                                .value_name("CONTACT")
+                                .help("Email address, URL or handle for support requests"),
+                        )
+                        .arg(
+                            Arg::new("webhook-url")
+                                .long("webhook-url")
+                                .value_name("URL")
+                                .help("Webhook URL; only its SHA-256 hash is published"),
+                        ),
+                )
+                .subcommand(Command::new("get").about("Show the vault's notification config"))
+                .subcommand(
+                    Command::new("clear")
+                        .about("Remove the vault's notification config and reclaim its rent"),
+                ),
+        )
+        .subcommand(
+            Command::new("maintenance")
+                .about("Schedule time-boxed maintenance, during which deposits are refused")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("schedule")
+                        .about("Add a maintenance window; deposits resume on their own at its end")
+                        .arg(
+                            Arg::new("start")
+                                .long("start")
+                                .required(true)
+                                .value_parser(value_parser!(i64))
+                                .value_name("UNIX_TIME")
+                                .help("When deposits stop being accepted"),
+                        )
+                        .arg(
+                            Arg::new("end")
+                                .long("end")
+                                .required(true)
+                                .value_parser(value_parser!(i64))
+                                .value_name("UNIX_TIME")
+                                .help("When deposits resume"),
+                        )
+                        .arg(
+                            Arg::new("reason")
+                                .long("reason")
+                                .required(true)
+                                .value_name("TEXT")
+                                .help("Shown to users; at most 64 bytes"),
+                        ),
+                )
+                .subcommand(
+                    Command::new("list").about("Show the vault's maintenance windows with countdowns"),
+                ),
+        )
+        .subcommand(
+            Command::new("repair")
+                .about("Fix vault state that blocks users")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("fee-path")
+                        .about("Recreate the fee collector's token account when claims fail for lack of it")
+                        .long_about(
+                            "Recreate the fee collector's token account when fee claims fail \
+                             for lack of it. The signer pays the account's rent; the account \
+                             belongs to the fee collector.",
+                        )
+                        .arg(
+                            Arg::new("json")
+                                .long("json")
+                                .action(ArgAction::SetTrue)
+                                .help("Print the repair report as JSON"),
+                        ),
+                ),
+        )
+        .subcommand(
+            Command::new("mirror")
+                .about("Copy a vault's config to another cluster for staging parity")
+                .long_about(
+                    "Copy a vault's config from the --from profile's cluster to the --to \
+                     profile's, creating the destination vault when it does not exist. \
+                     The --to profile's keypair becomes the destination vault's authority. \
+                     Fields of an existing destination vault that differ are reported, not \
+                     changed: the program cannot change them after creation.",
+                )
+                .arg(
+                    Arg::new("from")
+                        .long("from")
+                        .required(true)
+                        .value_name("PROFILE")
+                        .help("Profile of the cluster holding the source vault"),
+                )
+                .arg(
+                    Arg::new("to")
+                        .long("to")
+                        .required(true)
+                        .value_name("PROFILE")
+                        .help("Profile of the cluster to mirror into"),
+                )
+                .arg(
+                    Arg::new("include")
+                        .long("include")
+                        .value_delimiter(',')
+                        .value_parser(["allowlist", "metadata"])
+                        .help("Also mirror the allowlist and/or the notification metadata"),
+                )
+                .arg(pubkey_arg(
+                    "dest-mint",
+                    "Mint of the destination vault, when the source mint does not exist there",
+                ))
+                .arg(
+                    Arg::new("dry-run")
+                        .long("dry-run")
+                        .action(ArgAction::SetTrue)
+                        .help("Show what would change without sending anything"),
+                )
+                .arg(
+                    Arg::new("allow-mainnet-dest")
+                        .long("allow-mainnet-dest")
+                        .action(ArgAction::SetTrue)
+                        .help("Allow mirroring into mainnet"),
+                )
+                .arg(
+                    Arg::new("json")
+                        .long("json")
+                        .action(ArgAction::SetTrue)
+                        .help("Print the mirror report as JSON"),
+                ),
+        )
+        .subcommand(
+            Command::new("launch")
+                .about("Launch a new vault from a TOML launch plan")
+                .long_about(
+                    "Launch a new vault from a TOML launch plan: create it, make sure its fee \
+                     account exists, then publish its metadata, sync its allowlist and \
+                     schedule its maintenance windows. Every account the plan names is \
+                     checked before anything is sent, and each step is simulated right \
+                     before it is sent. Finished steps are recorded in the resume file, so a \
+                     rerun after a failure continues where the launch stopped; steps already \
+                     in effect on chain are skipped. Prints a report signed by the vault \
+                     authority, and exits with status 1 if a post-launch check failed.",
+                )
+                .arg(
+                    Arg::new("plan")
+                        .long("plan")
+                        .required(true)
+                        .value_name("FILE")
+                        .help("The launch plan"),
+                )
+                .arg(
+                    Arg::new("resume")
+                        .long("resume")
+                        .value_name("FILE")
+                        .help("Resume file [default: the plan path with .resume.json]"),
+                )
+                .arg(
+                    Arg::new("report")
+                        .long("report")
+                        .value_name("FILE")
+                        .help("Also write the signed report as JSON to FILE"),
+                )
+                .arg(
+                    Arg::new("dry-run")
+                        .long("dry-run")
+                        .action(ArgAction::SetTrue)
+                        .help("Check the plan and show what would be sent, without sending anything"),
+                )
+                .arg(
+                    Arg::new("yes")
+                        .long("yes")
+                        .action(ArgAction::SetTrue)
+                        .help("Launch without asking for confirmation"),
+                )
+                .arg(
+                    Arg::new("json")
+                        .long("json")
+                        .action(ArgAction::SetTrue)
+                        .help("Print the launch report as JSON"),
+                ),
+        )
+        .subcommand(
+            Command::new("exec")
+                .about("Run newline-delimited JSON operations as one batch")
+                .long_about(
+                    "Run newline-delimited JSON operations as one batch. Every line is \
+                     validated and the plan printed to stderr before anything is sent. \
+                     stdout gets one JSON result per input line, in input order.",
+                )
+                .arg(
+                    Arg::new("stdin")
+                        .long("stdin")
+                        .action(ArgAction::SetTrue)
+                        .help("Read operations from stdin"),
+                )
+                .arg(
+                    Arg::new("file")
+                        .long("file")
+                        .value_name("JSONL")
+                        .value_hint(clap::ValueHint::FilePath)
+                        .help("Read operations from this file"),
+                )
+                .group(
+                    ArgGroup::new("input")
+                        .args(["stdin", "file"])
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("yes")
+                        .long("yes")
+                        .short('y')
+                        .action(ArgAction::SetTrue)
+                        .help("Execute without asking for confirmation"),
+                )
+                .arg(
+                    Arg::new("strict")
+                        .long("strict")
+                        .action(ArgAction::SetTrue)
+                        .help("Execute nothing if any line is malformed or invalid"),
+                )
+                .arg(
+                    Arg::new("fee-pool")
+                        .long("fee-pool")
+                        .value_name("DIR")
+                        .value_hint(clap::ValueHint::DirPath)
+                        .help(
+                            "Pay fees from the payers `dev fund-pool` saved here, topped up from \
+                             your keypair",
+                        ),
+                ),
+        )
+        .subcommand(
+            Command::new("list")
+                .about("Page through vaults, the allowlist or history")
+                .subcommand_required(true)
+                .subcommand(
+                    page_args(Command::new("vaults").about("Vaults of the program, by name"))
+                        .arg(pubkey_arg("authority", "Only vaults of this authority")),
+                )
+                .subcommand(page_args(
+                    Command::new("allowlist").about("Wallets allowlisted for the vault"),
+                ))
+                .subcommand(page_args(
+                    Command::new("history").about("Transactions of the vault, newest first"),
+                )),
+        )
+        .subcommand(
+            Command::new("index")
+                .about("Maintain the local index of vault names and aliases")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("refresh")
+                        .about("Download every vault's name and address into the index")
+                        .arg(pubkey_arg(
+                            "authority",
+                            "Only index vaults of this authority",
+                        )),
+                )
+                .subcommand(
+                    Command::new("list")
+                        .about("Show indexed vaults, optionally only those matching a name")
+                        .arg(Arg::new("pattern").help("Name, name prefix or part of a name")),
+                )
+                .subcommand(
+                    Command::new("alias")
+                        .about("Point an alias at a vault")
+                        .arg(Arg::new("alias").required(true).help("Alias to define"))
+                        .arg(
+                            Arg::new("target")
+                                .required(true)
+                                .value_name("ADDRESS|NAME")
+                                .help("Vault address, or a name resolved through the index"),
+                        ),
+                )
+                .subcommand(
+                    Command::new("unalias")
+                        .about("Remove an alias")
+                        .arg(Arg::new("alias").required(true).help("Alias to remove")),
+                ),
+        )
+        .subcommand(
+            Command::new("verify-receipt")
+                .about("Check signed receipts of administrative actions, without a connection")
+                .arg(
+                    pubkey_arg("authority", "Key the receipts must be signed by")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("file")
+                        .value_name("FILE")
+                        .help("JSON-lines receipts; defaults to receipts.jsonl next to the config"),
+                ),
+        )
+        .subcommand(
+            Command::new("doctor")
+                .about("Check the resolved cluster, program and signer configuration"),
+        )
+        .subcommand(
+            Command::new("support-bundle")
+                .about("Collect a redacted JSON report to attach to bug reports")
+                .long_about(
+                    "Collect a redacted JSON report to attach to bug reports: client version, \
+                     effective configuration, endpoint health, the decoded vault account and \
+                     its recent transactions. Keypairs are never included and RPC URLs are cut \
+                     down to their host.",
+                )
+                .arg(
+                    Arg::new("last")
+                        .long("last")
+                        .value_parser(value_parser!(usize))
+                        .default_value("20")
+                        .value_name("N")
+                        .help("Include the vault's last N transactions (max 100)"),
+                )
+                .arg(
+                    Arg::new("hash-pubkeys")
+                        .long("hash-pubkeys")
+                        .action(ArgAction::SetTrue)
+                        .help("Replace public keys with stable hashes"),
+                )
+                .arg(
+                    Arg::new("output")
+                        .long("output")
+                        .short('o')
+                        .value_name("FILE")
+                        .value_hint(clap::ValueHint::FilePath)
+                        .help("Write the bundle here instead of stdout"),
+                ),
+        )
+        .subcommand(
+            Command::new("dev")
+                .about("Tools for devnet and test runs")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("fund-pool")
+                        .about("Create and fund fee payers for `exec --fee-pool`")
+                        .long_about(
+                            "Create and fund fee payers for `exec --fee-pool`. Keypairs are \
+                             saved in the pool directory, and existing ones are reused. Each \
+                             payer is topped up to the given balance from your keypair with a \
+                             system transfer, not an airdrop.",
+                        )
+                        .arg(
+                            Arg::new("payers")
+                                .long("payers")
+                                .value_parser(value_parser!(usize))
+                                .default_value("5")
+                                .help("Number of payers"),
+                        )
+                        .arg(
+                            Arg::new("each")
+                                .long("each")
+                                .value_parser(value_parser!(f64))
+                                .default_value("2")
+                                .value_name("SOL")
+                                .help("Balance to bring each payer up to"),
+                        )
+                        .arg(
+                            Arg::new("dir")
+                                .long("dir")
+                                .default_value("fee-pool")
+                                .value_hint(clap::ValueHint::DirPath)
+                                .help("Directory of the pool's keypairs"),
+                        ),
+                ),
+        )
+        .subcommand(
+            Command::new("completions")
+                .about("Print a shell completion script")
+                .arg(
+                    Arg::new("shell")
+                        .required(true)
+                        .value_parser(value_parser!(Shell))
+                        .help("Shell to generate completions for"),
+                ),
+        )
+        .subcommand(Command::new("man").about("Print the man page in roff format"))
+}
+
+fn pubkey_arg(name: &'static str, help: &'static str) -> Arg {
+    Arg::new(name).long(name).value_name("PUBKEY").help(help)
+}
+
+/// `--cursor`, `--limit` and `--json` for a paged listing
+fn page_args(command: Command) -> Command {
+    command
+        .arg(
+            Arg::new("cursor")
+                .long("cursor")
+                .value_name("CURSOR")
+                .help("Cursor printed by the previous page"),
+        )
+        .arg(
+            Arg::new("limit")
+                .long("limit")
+                .value_parser(value_parser!(usize))
+                .default_value("100")
+                .help("Items per page (max 1000)"),
+        )
+        .arg(
+            Arg::new("json")
+                .long("json")
+                .action(ArgAction::SetTrue)
+                .help("Print the page, with its cursor and slot, as JSON"),
+        )
+}
+
+fn amount_arg() -> Arg {
+    Arg::new("amount")
+        .required(true)
+        .value_parser(value_parser!(u64))
+        .help("Amount in base units of the token")
+}
+
+fn memo_arg() -> Arg {
+    Arg::new("memo")
+        .long("memo")
+        .value_name("TEXT")
+        .help("Attach this memo, such as an invoice id, to the transaction")
+}
+
+fn fee_bps_arg() -> Arg {
+    Arg::new("fee-bps")
+        .long("fee-bps")
+        .value_parser(value_parser!(u16))
+        .help("Withdrawal fee in basis points")
+}
+
+fn timelock_arg() -> Arg {
+    Arg::new("timelock")
+        .long("timelock")
+        .value_parser(value_parser!(i64))
+        .value_name("SECONDS")
+        .help("Withdrawal timelock")
+}
+
+fn withdrawal_limit_arg() -> Arg {
+    Arg::new("withdrawal-limit")
+        .long("withdrawal-limit")
+        .value_parser(value_parser!(u64))
+        .help("Maximum amount per withdrawal, in base units")
+}
+
+fn deposit_window_args() -> [Arg; 2] {
+    [
+        Arg::new("deposit-opens")
+            .long("deposit-opens")
+            .value_parser(value_parser!(i64))
+            .value_name("UNIX_TIME")
+            .help("Deposits are accepted from this time (0 = no bound)"),
+        Arg::new("deposit-closes")
+            .long("deposit-closes")
+            .value_parser(value_parser!(i64))
+            .value_name("UNIX_TIME")
+            .help("Deposits are rejected from this time (0 = no bound)"),
+    ]
+}
+
+fn dust_args() -> [Arg; 2] {
+    [
+        Arg::new("dust-threshold")
+            .long("dust-threshold")
+            .value_parser(value_parser!(u64))
+            .help("Withdrawals below this amount are dust (0 = no dust handling)"),
+        Arg::new("waive-dust-fee")
+            .long("waive-dust-fee")
+            .action(ArgAction::SetTrue)
+            .requires("dust-threshold")
+            .help("Allow dust withdrawals without a fee instead of rejecting them"),
+    ]
+}
+
+fn reserve_args() -> [Arg; 2] {
+    [
+        Arg::new("reserve-bps")
+            .long("reserve-bps")
+            .value_parser(value_parser!(u16))
+            .default_value("0")
+            .help("Share of every deposit, in basis points, set aside in the insurance reserve"),
+        Arg::new("reserve-timelock")
+            .long("reserve-timelock")
+            .value_parser(value_parser!(i64))
+            .default_value("0")
+            .value_name("SECONDS")
+            .help("Minimum time between reserve releases"),
+    ]
+}
+
+/// `build_cli` with profile names and vault aliases offered for `--profile` and `--vault`.
+///
+/// Only used to generate completions: `--vault` must keep accepting raw
+/// addresses when parsing.
+pub fn build_completion_cli(config: &CliConfig) -> Command {
+    let mut cmd = build_cli();
+    if !config.profiles.is_empty() {
+        let profiles: Vec<String> = config.profiles.keys().cloned().collect();
+        cmd = cmd.mut_arg("profile", |arg| {
+            arg.value_parser(PossibleValuesParser::new(profiles))
+        });
+    }
+    if !config.vaults.is_empty() {
+        let aliases: Vec<String> = config.vaults.keys().cloned().collect();
+        cmd = cmd.mut_arg("vault", |arg| {
+            arg.value_parser(PossibleValuesParser::new(aliases))
+        });
+    }
+    cmd
+}
+
+/// Write the completion script for `shell`, including the names found in `config`
+pub fn write_completions(shell: Shell, config: &CliConfig, out: &mut dyn Write) {
+    let mut cmd = build_completion_cli(config);
+    clap_complete::generate(shell, &mut cmd, BIN_NAME, out);
+}
+
+/// Write the man page generated from `build_cli`
+pub fn write_man_page(out: &mut dyn Write) -> Result<()> {
+    clap_mangen::Man::new(build_cli()).render(out)?;
+    Ok(())
+}
+
+/// Connection settings a profile can supply
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct Profile {
+    #[serde(alias = "rpc_url")]
+    pub url: Option<String>,
+    /// Endpoint transactions are sent through, when not the cluster's own
+    pub write_url: Option<String>,
+    /// Endpoint reads go to, typically a cheaper replica
+    pub read_url: Option<String>,
+    #[serde(alias = "keypair_path")]
+    pub keypair: Option<String>,
+    pub program_id: Option<String>,
+    /// Keys that may only sign on test clusters or only on mainnet
+    pub key_policy: Option<KeyPolicy>,
+    /// Refuse to create vaults for mints with a freeze authority unless
+    /// `--acknowledge-freezable-mint` is given
+    #[serde(default)]
+    pub strict_mint_policy: bool,
+    /// Refuse withdrawals that would underflow the vault's `total_deposited`
+    /// instead of warning
+    #[serde(default)]
+    pub strict_accounting: bool,
+}
+
+/// Read the launch plan at `path`; see `LaunchPlan` for the format
+pub fn load_launch_plan(path: &Path) -> Result<LaunchPlan> {
+    let contents = fs::read_to_string(path)
+        .map_err(|e| anyhow!("Cannot read launch plan {}: {}", path.display(), e))?;
+    toml::from_str(&contents).map_err(|e| anyhow!("Invalid launch plan {}: {}", path.display(), e))
+}
+
+/// Where `launch` records its progress for `plan` unless told otherwise
+pub fn launch_resume_path(plan: &Path) -> PathBuf {
+    plan.with_extension("resume.json")
+}
+
+/// Named profiles and vault aliases, read from `~/.config/token-vault/config.toml`
+/// (or `$TOKEN_VAULT_CONFIG`):
+///
+/// ```toml
+/// [profiles.devnet]
+/// url = "devnet"
+/// keypair = "~/.config/solana/devnet.json"
+///
+/// [profiles.mainnet]
+/// url = "mainnet"
+/// write_url = "https://premium.example.com"
+/// read_url = "https://replica.example.com"
+/// strict_mint_policy = true
+///
+/// [profiles.mainnet.key_policy]
+/// test_only = ["8qbHbw2BbbTHBW1sbeqakYXVKRQM8Ne7pLK7m6CVfeR"]
+///
+/// [vaults]
+/// treasury = "9xQeWvG816bUx9EPjHmaT23yvVM2ZWbrrpZb9PusVFin"
+///
+/// [mints]
+/// USDC = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v"
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct CliConfig {
+    #[serde(default)]
+    pub profiles: BTreeMap<String, Profile>,
+    /// Vault alias -> address
+    #[serde(default)]
+    pub vaults: BTreeMap<String, String>,
+    /// Mint symbol -> address, for displaying amounts
+    #[serde(default)]
+    pub mints: BTreeMap<String, String>,
+}
+
+impl CliConfig {
+    pub fn default_path() -> PathBuf {
+        crate::config::default_path()
+    }
+
+    /// Where `token-vault index` keeps the vault index: next to the config file
+    pub fn index_path() -> PathBuf {
+        Self::default_path().with_file_name(VAULT_INDEX_FILE)
+    }
+
+    /// Where admin commands append their signed receipts: next to the config file
+    pub fn receipts_path() -> PathBuf {
+        Self::default_path().with_file_name(RECEIPTS_FILE)
+    }
+
+    /// Where the client keeps its estimate tables: next to the config file
+    pub fn estimates_path() -> PathBuf {
+        Self::default_path().with_file_name(ESTIMATES_FILE)
+    }
+
+    /// Load the config at `path`; a missing file is an empty config
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let contents = fs::read_to_string(path)?;
+        toml::from_str(&contents).map_err(|e| anyhow!("Invalid config {}: {}", path.display(), e))
+    }
+
+    pub fn profile(&self, name: &str) -> Result<&Profile> {
+        self.profiles.get(name).ok_or_else(|| {
+            anyhow!(
+                "Unknown profile '{}'; known profiles: {}",
+                name,
+                self.profiles.keys().cloned().collect::<Vec<_>>().join(", ")
+            )
+        })
+    }
+
+    /// The `[mints]` table as an address book
+    pub fn address_book(&self) -> Result<AddressBook> {
+        self.mints
+            .iter()
+            .map(|(symbol, mint)| {
+                Pubkey::from_str(mint)
+                    .map(|mint| (mint, symbol.clone()))
+                    .map_err(|_| anyhow!("Invalid mint '{}' for symbol {}", mint, symbol))
+            })
+            .collect()
+    }
+
+    /// Resolve `--vault`: an alias from the config, otherwise the value itself
+    pub fn resolve_vault<'a>(&'a self, vault: &'a str) -> &'a str {
+        self.vaults.get(vault).map(String::as_str).unwrap_or(vault)
+    }
+}
@@ -0,0 +1,58 @@
+use anchor_client::{solana_sdk::commitment_config::CommitmentConfig, Cluster};
+use anyhow::Result;
+use std::time::Duration;
+
+use crate::endpoints::custom_cluster;
+use crate::{CacheConfig, PriorityFeeConfig, TransactionVersion};
+
+/// Connection settings for `TokenVaultClient::new_with_config`. The default
+/// is what `new` uses: `confirmed`, no timeout, the cluster's own endpoints,
+/// no priority fee, legacy transactions and no vault cache.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientConfig {
+    /// Commitment transactions are confirmed at, and reads made at unless
+    /// `read_commitment` says otherwise
+    pub commitment: CommitmentConfig,
+    /// Commitment for account reads; see `with_read_commitment`
+    pub read_commitment: Option<CommitmentConfig>,
+    /// Default time budget of every call; see `with_timeout`
+    pub rpc_timeout: Option<Duration>,
+    /// HTTP endpoint to use instead of the cluster's
+    pub rpc_url: Option<String>,
+    /// WebSocket endpoint to use instead of the cluster's; inferred from
+    /// `rpc_url` when only that is set
+    pub ws_url: Option<String>,
+    /// Compute budget for every transaction; see `with_priority_fee`
+    pub priority_fee: Option<PriorityFeeConfig>,
+    /// Kind of transaction to send; see `with_transaction_version`
+    pub transaction_version: TransactionVersion,
+    /// Reuse of decoded vaults between calls; see `with_vault_cache`
+    pub vault_cache: CacheConfig,
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        Self {
+            commitment: CommitmentConfig::confirmed(),
+            read_commitment: None,
+            rpc_timeout: None,
+            rpc_url: None,
+            ws_url: None,
+            priority_fee: None,
+            transaction_version: TransactionVersion::Legacy,
+            vault_cache: CacheConfig::default(),
+        }
+    }
+}
+
+impl ClientConfig {
+    /// The cluster to connect to: `cluster`, with its endpoints replaced by
+    /// the configured ones, which are validated like `custom_cluster` does
+    pub fn transport(&self, cluster: &Cluster) -> Result<Cluster> {
+        match (&self.rpc_url, &self.ws_url) {
+            (None, None) => Ok(cluster.clone()),
+            (Some(rpc_url), ws_url) => custom_cluster(rpc_url, ws_url.as_deref()),
+            (None, Some(ws_url)) => custom_cluster(cluster.url(), Some(ws_url)),
+        }
+    }
+}
@@ -0,0 +1,50 @@
+use anyhow::Result;
+
+/// Where the client reads the current time for its schedule checks
+/// (deposit and maintenance windows). Production clients read the
+/// cluster's Clock sysvar; tests can inject a `ManualClock` with the
+/// `test-hooks` feature. Sources are shared with the client's clones and
+/// threads.
+pub trait ClockSource: Send + Sync {
+    /// Current unix timestamp
+    fn unix_timestamp(&self) -> Result<i64>;
+}
+
+#[cfg(feature = "test-hooks")]
+pub use manual::ManualClock;
+
+#[cfg(feature = "test-hooks")]
+mod manual {
+    use super::ClockSource;
+    use anyhow::Result;
+    use std::sync::atomic::{AtomicI64, Ordering};
+
+    /// Clock that only moves when told to
+    #[derive(Debug, Default)]
+    pub struct ManualClock {
+        now: AtomicI64,
+    }
+
+    impl ManualClock {
+        pub fn new(unix_timestamp: i64) -> Self {
+            Self {
+                now: AtomicI64::new(unix_timestamp),
+            }
+        }
+
+        /// Jump to `unix_timestamp`, forwards or backwards
+        pub fn warp_to_timestamp(&self, unix_timestamp: i64) {
+            self.now.store(unix_timestamp, Ordering::Relaxed);
+        }
+
+        pub fn advance(&self, seconds: i64) {
+            self.now.fetch_add(seconds, Ordering::Relaxed);
+        }
+    }
+
+    impl ClockSource for ManualClock {
+        fn unix_timestamp(&self) -> Result<i64> {
+            Ok(self.now.load(Ordering::Relaxed))
+        }
+    }
+}
@@ -0,0 +1,266 @@
+//! Connection settings from a TOML file, so programs need not thread the
+//! cluster, program id and keypair through by hand. The file is the one the
+//! `token-vault` command line reads, `~/.config/token-vault/config.toml`
+//! unless `$TOKEN_VAULT_CONFIG` names another:
+//!
+//! ```toml
+//! # Used when no profile is selected, and under every profile
+//! rpc_url = "devnet"
+//! keypair_path = "~/.config/solana/id.json"
+//! commitment = "confirmed"
+//!
+//! [profiles.mainnet]
+//! rpc_url = "https://api.mainnet-beta.solana.com"
+//! ws_url = "wss://api.mainnet-beta.solana.com"
+//! program_id = "Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS"
+//! keypair_path = "~/.config/solana/mainnet.json"
+//! default_vault = "9xQeWvG816bUx9EPjHmaT23yvVM2ZWbrrpZb9PusVFin"
+//! commitment = "finalized"
+//! ```
+//!
+//! A profile's settings take precedence over the top-level ones. Settings
+//! that neither gives, or a missing file, fall back to devnet, the deployed
+//! program, the Solana CLI's default keypair and `confirmed`. Keys the
+//! client does not know are ignored with a warning, so a typo does not stop
+//! a program that would otherwise run.
+
+use anchor_client::solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey};
+use anchor_client::Cluster;
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::logging;
+use crate::{token_vault, utils, ClientConfig, TokenVaultClient};
+
+/// Environment variable overriding the config file location
+pub const CONFIG_ENV: &str = "TOKEN_VAULT_CONFIG";
+
+const DEFAULT_CONFIG_PATH: &str = "~/.config/token-vault/config.toml";
+
+/// Keypair used when the config names none: the Solana CLI's default
+pub const DEFAULT_KEYPAIR_PATH: &str = "~/.config/solana/id.json";
+
+/// Keys of a settings table. `url` and `keypair` are the command line's
+/// names for `rpc_url` and `keypair_path`.
+const SETTING_KEYS: &[&str] = &[
+    "rpc_url",
+    "url",
+    "ws_url",
+    "program_id",
+    "keypair_path",
+    "keypair",
+    "default_vault",
+    "commitment",
+];
+
+/// Keys only the command line reads: in profiles, and tables at the top level
+const CLI_PROFILE_KEYS: &[&str] = &[
+    "write_url",
+    "read_url",
+    "key_policy",
+    "strict_mint_policy",
+    "strict_accounting",
+];
+const CLI_TABLES: &[&str] = &["vaults", "mints"];
+
+/// The config file location: `$TOKEN_VAULT_CONFIG`, else
+/// `~/.config/token-vault/config.toml`
+pub fn default_path() -> PathBuf {
+    match std::env::var_os(CONFIG_ENV) {
+        Some(path) => PathBuf::from(path),
+        None => PathBuf::from(shellexpand::tilde(DEFAULT_CONFIG_PATH).as_ref()),
+    }
+}
+
+/// Settings of the top level or of one profile; unset ones are `None`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct ConnectionSettings {
+    /// Cluster moniker (devnet, testnet, mainnet, localnet) or HTTP endpoint
+    #[serde(alias = "url")]
+    pub rpc_url: Option<String>,
+    /// WebSocket endpoint, when not the one `rpc_url` implies
+    pub ws_url: Option<String>,
+    pub program_id: Option<String>,
+    #[serde(alias = "keypair")]
+    pub keypair_path: Option<String>,
+    /// Vault the client starts on, as with `with_vault`
+    pub default_vault: Option<String>,
+    /// `processed`, `confirmed` or `finalized`
+    pub commitment: Option<String>,
+}
+
+impl ConnectionSettings {
+    /// These settings, with `overrides` taking precedence where it sets one
+    pub fn overridden_by(&self, overrides: &Self) -> Self {
+        let pick = |over: &Option<String>, base: &Option<String>| over.clone().or(base.clone());
+        Self {
+            rpc_url: pick(&overrides.rpc_url, &self.rpc_url),
+            ws_url: pick(&overrides.ws_url, &self.ws_url),
+            program_id: pick(&overrides.program_id, &self.program_id),
+            keypair_path: pick(&overrides.keypair_path, &self.keypair_path),
+            default_vault: pick(&overrides.default_vault, &self.default_vault),
+            commitment: pick(&overrides.commitment, &self.commitment),
+        }
+    }
+
+    /// Parse the settings, filling in the devnet defaults
+    pub fn resolve(&self) -> Result<ResolvedConfig> {
+        let cluster = Cluster::from_str(self.rpc_url.as_deref().unwrap_or("devnet"))?;
+        let program_id = match &self.program_id {
+            Some(id) => parse_pubkey("program_id", id)?,
+            None => token_vault::ID,
+        };
+        let default_vault = self
+            .default_vault
+            .as_deref()
+            .map(|vault| parse_pubkey("default_vault", vault))
+            .transpose()?;
+        let commitment = match self.commitment.as_deref() {
+            None | Some("confirmed") => CommitmentConfig::confirmed(),
+            Some("processed") => CommitmentConfig::processed(),
+            Some("finalized") => CommitmentConfig::finalized(),
+            Some(other) => {
+                return Err(anyhow!(
+                    "Invalid commitment '{}'; expected processed, confirmed or finalized",
+                    other
+                ))
+            }
+        };
+        Ok(ResolvedConfig {
+            cluster,
+            program_id,
+            keypair_path: self
+                .keypair_path
+                .clone()
+                .unwrap_or_else(|| DEFAULT_KEYPAIR_PATH.to_string()),
+            default_vault,
+            client_config: ClientConfig {
+                commitment,
+                ws_url: self.ws_url.clone(),
+                ..ClientConfig::default()
+            },
+        })
+    }
+}
+
+fn parse_pubkey(key: &str, value: &str) -> Result<Pubkey> {
+    Pubkey::from_str(value).map_err(|_| anyhow!("Invalid {} '{}'", key, value))
+}
+
+/// Everything a client is built from
+#[derive(Debug, Clone)]
+pub struct ResolvedConfig {
+    pub cluster: Cluster,
+    pub program_id: Pubkey,
+    pub keypair_path: String,
+    pub default_vault: Option<Pubkey>,
+    /// Commitment and WebSocket endpoint
+    pub client_config: ClientConfig,
+}
+
+/// A parsed config file; see the module docs
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct ConfigFile {
+    /// Top-level settings
+    #[serde(flatten)]
+    pub defaults: ConnectionSettings,
+    #[serde(default)]
+    pub profiles: BTreeMap<String, ConnectionSettings>,
+    /// Keys that were ignored because the client does not know them, such
+    /// as `profiles.devnet.rpc_ulr`
+    #[serde(skip)]
+    pub ignored_keys: Vec<String>,
+}
+
+impl ConfigFile {
+    /// Load the config at `path`, warning about ignored keys. A missing file
+    /// is an empty config, so every setting takes its default.
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let contents = fs::read_to_string(path)
+            .map_err(|e| anyhow!("Cannot read config {}: {}", path.display(), e))?;
+        let config = Self::parse(&contents)
+            .map_err(|e| anyhow!("Invalid config {}: {}", path.display(), e))?;
+        for key in &config.ignored_keys {
+            logging::warn!(path = %path.display(), key = %key, "ignored unknown config key");
+        }
+        Ok(config)
+    }
+
+    /// Parse a config from TOML, listing unknown keys in `ignored_keys`
+    pub fn parse(contents: &str) -> Result<Self> {
+        let table: toml::value::Table = toml::from_str(contents)?;
+        let mut ignored_keys = unknown_keys(&table, "", &[SETTING_KEYS, CLI_TABLES, &["profiles"]]);
+        if let Some(profiles) = table.get("profiles").and_then(toml::Value::as_table) {
+            for (name, profile) in profiles {
+                if let Some(profile) = profile.as_table() {
+                    let prefix = format!("profiles.{}.", name);
+                    ignored_keys.extend(unknown_keys(
+                        profile,
+                        &prefix,
+                        &[SETTING_KEYS, CLI_PROFILE_KEYS],
+                    ));
+                }
+            }
+        }
+        let mut config: Self = toml::Value::Table(table).try_into()?;
+        config.ignored_keys = ignored_keys;
+        Ok(config)
+    }
+
+    /// The top-level settings, overridden by those of `profile` if given
+    pub fn settings(&self, profile: Option<&str>) -> Result<ConnectionSettings> {
+        let Some(name) = profile else {
+            return Ok(self.defaults.clone());
+        };
+        let profile = self.profiles.get(name).ok_or_else(|| {
+            anyhow!(
+                "Unknown profile '{}'; known profiles: {}",
+                name,
+                self.profiles.keys().cloned().collect::<Vec<_>>().join(", ")
+            )
+        })?;
+        Ok(self.defaults.overridden_by(profile))
+    }
+}
+
+/// Keys of `table` in none of `known`, each written after `prefix`
+fn unknown_keys(table: &toml::value::Table, prefix: &str, known: &[&[&str]]) -> Vec<String> {
+    table
+        .keys()
+        .filter(|key| !known.iter().any(|keys| keys.contains(&key.as_str())))
+        .map(|key| format!("{}{}", prefix, key))
+        .collect()
+}
+
+impl TokenVaultClient {
+    /// A client set up from the config file at `path`, or at
+    /// `config::default_path()`, with its top-level settings
+    pub fn from_config(path: Option<&Path>) -> Result<Self> {
+        Self::from_config_profile(path, None)
+    }
+
+    /// Like `from_config`, with the settings of `profile` taking precedence
+    /// over the top-level ones
+    pub fn from_config_profile(path: Option<&Path>, profile: Option<&str>) -> Result<Self> {
+        let path = path.map_or_else(default_path, Path::to_path_buf);
+        let config = ConfigFile::load(&path)?.settings(profile)?.resolve()?;
+        let payer = utils::load_keypair(&config.keypair_path)?;
+        let mut client = Self::new_with_config(
+            config.cluster,
+            payer,
+            config.program_id,
+            config.client_config,
+        )?;
+        if let Some(vault) = config.default_vault {
+            client.set_vault(vault);
+        }
+        Ok(client)
+    }
+}
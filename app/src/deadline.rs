@@ -0,0 +1,315 @@
+use anchor_client::{
+    solana_client::rpc_client::RpcClient,
+    solana_sdk::{
+        instruction::InstructionError,
+        signature::Signature,
+        transaction::{TransactionError, VersionedTransaction},
+    },
+};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::thread;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+use crate::error::parse_program_error;
+use crate::request::VaultRequest;
+use crate::{logging, Simulation, TokenVaultClient, TokenVaultError};
+
+/// How often a deadline-bound send polls for confirmation
+const CONFIRMATION_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Bytes of the signature a send returns
+const SIGNATURE_BYTES: usize = 64;
+
+/// Point in time by which an operation has to finish, or none
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Deadline(Option<Instant>);
+
+/// Where an operation was when its deadline ran out
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum DeadlinePhase {
+    /// Reading accounts before building a transaction
+    Fetch,
+    /// Building, signing or submitting a transaction
+    Send,
+    /// Waiting for a submitted transaction to be confirmed
+    Confirm,
+    /// Paging through history, including rate-limit backoff
+    Backfill,
+}
+
+impl fmt::Display for DeadlinePhase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let phase = match self {
+            Self::Fetch => "fetch",
+            Self::Send => "send",
+            Self::Confirm => "confirm",
+            Self::Backfill => "backfill",
+        };
+        write!(f, "{}", phase)
+    }
+}
+
+impl Deadline {
+    /// No deadline: operations may take as long as the RPC lets them
+    pub const NONE: Self = Self(None);
+
+    pub fn at(instant: Instant) -> Self {
+        Self(Some(instant))
+    }
+
+    pub fn after(budget: Duration) -> Self {
+        Self(Instant::now().checked_add(budget))
+    }
+
+    pub fn instant(&self) -> Option<Instant> {
+        self.0
+    }
+
+    pub fn is_none(&self) -> bool {
+        self.0.is_none()
+    }
+
+    /// Time left, or `None` without a deadline
+    pub fn remaining(&self) -> Option<Duration> {
+        self.0
+            .map(|instant| instant.saturating_duration_since(Instant::now()))
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.remaining() == Some(Duration::ZERO)
+    }
+
+    /// Fail with `DeadlineExceeded` if the deadline has passed
+    pub fn check(
+        &self,
+        phase: DeadlinePhase,
+        signature: Option<Signature>,
+    ) -> Result<(), TokenVaultError> {
+        if self.is_expired() {
+            return Err(TokenVaultError::DeadlineExceeded { phase, signature });
+        }
+        Ok(())
+    }
+
+    /// Sleep for `duration`, failing instead if that would overrun the deadline
+    pub fn sleep(&self, duration: Duration, phase: DeadlinePhase) -> Result<(), TokenVaultError> {
+        if let Some(remaining) = self.remaining() {
+            if duration >= remaining {
+                return Err(TokenVaultError::DeadlineExceeded {
+                    phase,
+                    signature: None,
+                });
+            }
+        }
+        thread::sleep(duration);
+        Ok(())
+    }
+
+    /// The earlier of two deadlines
+    pub fn min(self, other: Self) -> Self {
+        match (self.0, other.0) {
+            (Some(a), Some(b)) => Self(Some(a.min(b))),
+            (a, b) => Self(a.or(b)),
+        }
+    }
+}
+
+/// Stops an operation run by `simulating` at its first transaction, which
+/// the cluster simulated instead of receiving
+#[derive(Debug, Clone, Error)]
+#[error("Transaction simulated; nothing was sent")]
+pub(crate) struct Simulated(Simulation);
+
+impl From<Instant> for Deadline {
+    fn from(instant: Instant) -> Self {
+        Self::at(instant)
+    }
+}
+
+impl TokenVaultClient {
+    /// Give every call that is not run `within` an explicit deadline this
+    /// much time to complete
+    pub fn with_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Run `op` with all of its RPC reads, sends, confirmation polling and
+    /// retries bounded by `deadline` instead of the client's default timeout
+    pub fn within<T>(
+        &self,
+        deadline: impl Into<Deadline>,
+        op: impl FnOnce(&Self) -> Result<T>,
+    ) -> Result<T> {
+        let previous = self.call_deadline.replace(Some(deadline.into()));
+        let result = op(self);
+        self.call_deadline.set(previous);
+        result
+    }
+
+    /// Run `op` with its first transaction simulated instead of sent, and
+    /// stop there. Returns the simulation, or `None` when `op` finished
+    /// without sending anything. Errors of `op` before that are returned
+    /// as they are.
+    pub(crate) fn simulating<T>(
+        &self,
+        op: impl FnOnce(&Self) -> Result<T>,
+    ) -> Result<Option<Simulation>> {
+        let previous = self.simulate_sends.replace(Some(true));
+        let result = op(self);
+        self.simulate_sends.set(previous);
+        match result {
+            Ok(_) => Ok(None),
+            Err(err) => {
+                let simulated = err
+                    .chain()
+                    .find_map(|cause| cause.downcast_ref::<Simulated>());
+                match simulated {
+                    Some(Simulated(simulation)) => Ok(Some(simulation.clone())),
+                    None => Err(err),
+                }
+            }
+        }
+    }
+
+    /// Deadline of the call in progress: the one passed to `within`, else
+    /// `now + timeout`, else none
+    pub fn deadline(&self) -> Deadline {
+        match (self.call_deadline.get(), self.timeout) {
+            (Some(deadline), _) => deadline,
+            (None, Some(timeout)) => Deadline::after(timeout),
+            (None, None) => Deadline::NONE,
+        }
+    }
+
+    /// RPC connection for `phase` whose request timeout ends at `deadline`,
+    /// failing right away if the deadline has already passed. Sends and
+    /// confirmations go to the write endpoint, everything else to the read
+    /// endpoint.
+    pub(crate) fn rpc_within(&self, deadline: Deadline, phase: DeadlinePhase) -> Result<RpcClient> {
+        deadline.check(phase, None)?;
+        Ok(match phase {
+            DeadlinePhase::Send | DeadlinePhase::Confirm => self.write_rpc_until(deadline),
+            DeadlinePhase::Fetch | DeadlinePhase::Backfill => self.read_rpc_until(deadline),
+        })
+    }
+
+    /// Send `request` and wait for confirmation, giving up at `deadline`.
+    ///
+    /// Without a deadline this is anchor's regular send-and-confirm. With one,
+    /// the transaction is submitted once and its status polled; a timeout after
+    /// submission reports the signature so the caller can keep tracking it.
+    /// Failures the client's `RetryPolicy` covers are resubmitted; see
+    /// `send_with_retries`.
+    pub(crate) fn send_request(
+        &self,
+        request: VaultRequest,
+        deadline: Deadline,
+    ) -> Result<Signature> {
+        if self.simulate_sends.get() == Some(true) {
+            return Err(Simulated(self.simulate_request(request, deadline)?).into());
+        }
+        self.send_with_retries(&request, deadline)
+    }
+
+    /// Submit a signed `transaction` once and wait for its confirmation
+    pub(crate) fn submit(
+        &self,
+        transaction: &VersionedTransaction,
+        deadline: Deadline,
+    ) -> Result<Signature> {
+        if deadline.is_none() {
+            let request_bytes = logging::transaction_size(transaction);
+            self.submitted.set(Some(true));
+            let signature = match self
+                .write_rpc_until(deadline)
+                .send_and_confirm_transaction(transaction)
+            {
+                Ok(signature) => signature,
+                Err(err) => {
+                    logging::log_rpc_error("sendAndConfirmTransaction", request_bytes, &err);
+                    return Err(err.into());
+                }
+            };
+            logging::log_rpc_response("sendAndConfirmTransaction", request_bytes, SIGNATURE_BYTES);
+            self.note_write();
+            return Ok(signature);
+        }
+
+        let request_bytes = logging::transaction_size(transaction);
+        let rpc = self.rpc_within(deadline, DeadlinePhase::Send)?;
+        self.submitted.set(Some(true));
+        let sent = rpc.send_transaction(transaction);
+        match &sent {
+            Ok(_) => logging::log_rpc_response("sendTransaction", request_bytes, SIGNATURE_BYTES),
+            Err(err) => logging::log_rpc_error("sendTransaction", request_bytes, err),
+        }
+        let signature = match sent {
+            Ok(signature) => signature,
+            // The submission may still have reached the cluster
+            Err(_) if deadline.is_expired() => {
+                return Err(TokenVaultError::DeadlineExceeded {
+                    phase: DeadlinePhase::Send,
+                    signature: transaction.signatures.first().copied(),
+                }
+                .into())
+            }
+            Err(err) => return Err(err.into()),
+        };
+
+        let mut attempt = 0u32;
+        loop {
+            attempt += 1;
+            logging::debug!(attempt, "awaiting confirmation");
+            let status =
+                match rpc.get_signature_status_with_commitment(&signature, rpc.commitment()) {
+                    Ok(status) => status,
+                    // The poll itself timed out against the deadline
+                    Err(_) if deadline.is_expired() => None,
+                    Err(err) => return Err(err.into()),
+                };
+            if let Some(result) = status {
+                result?;
+                self.note_write();
+                return Ok(signature);
+            }
+            deadline.check(DeadlinePhase::Confirm, Some(signature))?;
+            let remaining = deadline.remaining().unwrap_or(CONFIRMATION_POLL_INTERVAL);
+            thread::sleep(remaining.min(CONFIRMATION_POLL_INTERVAL));
+        }
+    }
+
+    fn simulate_request(&self, request: VaultRequest, deadline: Deadline) -> Result<Simulation> {
+        deadline.check(DeadlinePhase::Send, None)?;
+        let transaction = self.sign_request(&request, deadline)?;
+        let rpc = self.rpc_within(deadline, DeadlinePhase::Send)?;
+        let simulation = rpc.simulate_transaction(&transaction)?.value;
+        let logs = simulation.logs.unwrap_or_default();
+        let error = match (simulation.err, parse_program_error(&logs)) {
+            (None, _) => None,
+            (Some(_), Some((code, name))) => Some(TokenVaultError::ProgramError {
+                code,
+                name: Some(name),
+                logs: logs.clone(),
+            }),
+            (Some(TransactionError::InstructionError(_, InstructionError::Custom(code))), None) => {
+                Some(TokenVaultError::ProgramError {
+                    code,
+                    name: None,
+                    logs: logs.clone(),
+                })
+            }
+            (Some(err), None) => Some(TokenVaultError::Other {
+                message: format!("Simulation failed: {}", err),
+            }),
+        };
+        Ok(Simulation {
+            units_consumed: simulation.units_consumed,
+            logs,
+            error,
+        })
+    }
+}
@@ -0,0 +1,285 @@
+//! Catch deposits that would fail on chain because the depositor holds the
+//! wrong token. Deposits come from the depositor's associated token account
+//! for the vault's mint unless the caller names another; when that account
+//! is missing or short, the depositor's other token accounts show whether
+//! they hold another mint. A named account is checked on its own, as is a
+//! named withdrawal destination.
+
+use anchor_client::solana_client::{
+    rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
+    rpc_filter::{Memcmp, RpcFilterType},
+};
+use anchor_client::solana_sdk::{
+    account::Account, program_option::COption, program_pack::Pack, pubkey::Pubkey,
+};
+use anchor_spl::associated_token::get_associated_token_address;
+use anchor_spl::token::{self, spl_token};
+use anyhow::{anyhow, Result};
+use solana_account_decoder::UiAccountEncoding;
+use spl_token::state::Account as TokenAccount;
+use std::fmt;
+
+use crate::{Deadline, DeadlinePhase, TokenVaultClient, TokenVaultError};
+
+/// Byte offset of the owner in an SPL token account, after the mint
+const TOKEN_ACCOUNT_OWNER_OFFSET: usize = 32;
+
+/// A token account of the depositor
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenHolding {
+    pub address: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+}
+
+/// Where the depositor's tokens of the vault's mint are, or would be
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SourceSuggestion {
+    /// The depositor's associated token account for the vault's mint
+    Account { address: Pubkey, balance: u64 },
+    /// The depositor has no associated token account for the vault's mint;
+    /// this is where it would be
+    NoAccount { address: Pubkey },
+}
+
+impl fmt::Display for SourceSuggestion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Account { address, balance } => write!(
+                f,
+                "deposits come from your token account {} for the vault's mint, which holds {}",
+                address, balance
+            ),
+            Self::NoAccount { address } => write!(
+                f,
+                "you have no token account for the vault's mint; deposits would come from {}",
+                address
+            ),
+        }
+    }
+}
+
+/// Check that `depositor` can deposit `amount` of `vault_mint`, given all
+/// of its token accounts. A shortfall while holding another mint is a
+/// `TokenVaultError::SourceMintMismatch` naming the largest such holding.
+pub fn check_deposit_source(
+    depositor: &Pubkey,
+    vault_mint: &Pubkey,
+    amount: u64,
+    holdings: &[TokenHolding],
+) -> Result<()> {
+    let source = get_associated_token_address(depositor, vault_mint);
+    let balance = holdings
+        .iter()
+        .find(|holding| holding.address == source && holding.mint == *vault_mint)
+        .map(|holding| holding.amount);
+    if balance.is_some_and(|balance| balance >= amount) {
+        return Ok(());
+    }
+
+    let suggestion = match balance {
+        Some(balance) => SourceSuggestion::Account {
+            address: source,
+            balance,
+        },
+        None => SourceSuggestion::NoAccount { address: source },
+    };
+    let other_mint = holdings
+        .iter()
+        .filter(|holding| holding.mint != *vault_mint && holding.amount > 0)
+        .max_by_key(|holding| holding.amount);
+    if let Some(other) = other_mint {
+        return Err(TokenVaultError::SourceMintMismatch {
+            vault_mint: *vault_mint,
+            source_mint: other.mint,
+            suggestion,
+        }
+        .into());
+    }
+    Err(match balance {
+        Some(balance) => TokenVaultError::InsufficientBalance {
+            account: source,
+            have: balance,
+            need: amount,
+        }
+        .into(),
+        None => anyhow!(
+            "{} has no token account for mint {}; deposits come from {}",
+            depositor,
+            vault_mint,
+            source
+        ),
+    })
+}
+
+/// Check a token account the caller named for the vault's tokens to move
+/// through: it must exist and be an SPL token account of `vault_mint`, or
+/// the transfer would fail on chain. Returns its state.
+pub fn check_token_account_mint(
+    address: &Pubkey,
+    account: Option<&Account>,
+    vault_mint: &Pubkey,
+) -> Result<TokenAccount> {
+    let account = account.ok_or_else(|| anyhow!("Token account {} does not exist", address))?;
+    if account.owner != token::ID {
+        return Err(anyhow!(
+            "{} is not a token account: it is owned by {}, not the token program",
+            address,
+            account.owner
+        ));
+    }
+    let state = TokenAccount::unpack(&account.data)
+        .map_err(|err| anyhow!("{} is not a token account: {}", address, err))?;
+    if state.mint != *vault_mint {
+        return Err(TokenVaultError::TokenAccountMintMismatch {
+            account: *address,
+            vault_mint: *vault_mint,
+            account_mint: state.mint,
+        }
+        .into());
+    }
+    Ok(state)
+}
+
+/// Check that `depositor` may move `amount` out of the token account at
+/// `address`, as its owner or as a delegate approved for at least `amount`,
+/// and that it holds that much
+pub fn check_source_account(
+    address: &Pubkey,
+    state: &TokenAccount,
+    depositor: &Pubkey,
+    amount: u64,
+) -> Result<()> {
+    let delegated = match state.delegate {
+        COption::Some(delegate) if delegate == *depositor => state.delegated_amount,
+        _ => 0,
+    };
+    if state.owner != *depositor && delegated < amount {
+        return Err(match delegated {
+            0 => anyhow!(
+                "Token account {} belongs to {}; {} is neither its owner nor a delegate",
+                address,
+                state.owner,
+                depositor
+            ),
+            delegated => anyhow!(
+                "{} may move only {} out of token account {} as a delegate, less than the \
+                 deposit of {}",
+                depositor,
+                delegated,
+                address,
+                amount
+            ),
+        });
+    }
+    if state.amount < amount {
+        return Err(TokenVaultError::InsufficientBalance {
+            account: *address,
+            have: state.amount,
+            need: amount,
+        }
+        .into());
+    }
+    Ok(())
+}
+
+impl TokenVaultClient {
+    /// `check_deposit_source` against the cluster. Only the depositor's
+    /// token account for the vault's mint is read when it holds enough.
+    pub(crate) fn verify_deposit_source(
+        &self,
+        depositor: &Pubkey,
+        vault_mint: &Pubkey,
+        amount: u64,
+        deadline: Deadline,
+    ) -> Result<()> {
+        let source = get_associated_token_address(depositor, vault_mint);
+        let rpc = self.rpc_within(deadline, DeadlinePhase::Fetch)?;
+        if let Some(account) = rpc
+            .get_account_with_commitment(&source, rpc.commitment())?
+            .value
+        {
+            let state = spl_token::state::Account::unpack(&account.data)?;
+            if state.mint == *vault_mint && state.amount >= amount {
+                return Ok(());
+            }
+        }
+        let holdings = self.token_holdings(depositor, deadline)?;
+        check_deposit_source(depositor, vault_mint, amount, &holdings)
+    }
+
+    /// Every SPL token account owned by `owner`
+    pub(crate) fn token_holdings(
+        &self,
+        owner: &Pubkey,
+        deadline: Deadline,
+    ) -> Result<Vec<TokenHolding>> {
+        let rpc = self.rpc_within(deadline, DeadlinePhase::Fetch)?;
+        let config = RpcProgramAccountsConfig {
+            filters: Some(vec![
+                RpcFilterType::DataSize(spl_token::state::Account::LEN as u64),
+                RpcFilterType::Memcmp(Memcmp::new_raw_bytes(
+                    TOKEN_ACCOUNT_OWNER_OFFSET,
+                    owner.to_bytes().to_vec(),
+                )),
+            ]),
+            account_config: RpcAccountInfoConfig {
+                encoding: Some(UiAccountEncoding::Base64),
+                commitment: Some(rpc.commitment()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        rpc.get_program_accounts_with_config(&token::ID, config)?
+            .into_iter()
+            .map(|(address, account)| {
+                let state = spl_token::state::Account::unpack(&account.data)?;
+                Ok(TokenHolding {
+                    address,
+                    mint: state.mint,
+                    amount: state.amount,
+                })
+            })
+            .collect()
+    }
+
+    /// `check_token_account_mint` and `check_source_account` for a source
+    /// account the caller named
+    pub(crate) fn verify_source_account(
+        &self,
+        depositor: &Pubkey,
+        source: &Pubkey,
+        vault_mint: &Pubkey,
+        amount: u64,
+        deadline: Deadline,
+    ) -> Result<()> {
+        let account = self.fetch_optional_account(source, deadline)?;
+        let state = check_token_account_mint(source, account.as_ref(), vault_mint)?;
+        check_source_account(source, &state, depositor, amount)
+    }
+
+    /// `check_token_account_mint` for a withdrawal destination the caller
+    /// named. Unlike a recipient's associated token account, it is never
+    /// created, so it must already exist.
+    pub(crate) fn verify_destination_account(
+        &self,
+        destination: &Pubkey,
+        vault_mint: &Pubkey,
+        deadline: Deadline,
+    ) -> Result<()> {
+        let account = self.fetch_optional_account(destination, deadline)?;
+        check_token_account_mint(destination, account.as_ref(), vault_mint)?;
+        Ok(())
+    }
+
+    pub(crate) fn fetch_optional_account(
+        &self,
+        address: &Pubkey,
+        deadline: Deadline,
+    ) -> Result<Option<Account>> {
+        let rpc = self.rpc_within(deadline, DeadlinePhase::Fetch)?;
+        Ok(rpc
+            .get_account_with_commitment(address, rpc.commitment())?
+            .value)
+    }
+}
@@ -0,0 +1,473 @@
+use anchor_client::solana_client::{
+    rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
+    rpc_filter::{Memcmp, RpcFilterType},
+};
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use anchor_lang::{AccountDeserialize, Discriminator};
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use solana_account_decoder::UiAccountEncoding;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use crate::pagination::{check_limit, paginate, program_accounts_with_slot};
+use crate::token_vault::state::Vault;
+use crate::{
+    Cursor, DeadlinePhase, DepositWindow, DustMode, DustPolicy, FreezeStatus, MaintenanceWindow,
+    NotificationConfig, Page, Paginated, Provenance, ReservePolicy, TokenVaultClient,
+    TokenVaultError, VaultBalance,
+};
+
+/// Byte offset of `Vault::authority`, just after the discriminator
+const AUTHORITY_OFFSET: usize = 8;
+
+/// Byte offset of `Vault::token_mint`, just after the authority
+const TOKEN_MINT_OFFSET: usize = AUTHORITY_OFFSET + 32;
+
+/// A key field of the `Vault` layout that a scan of the program's vaults can
+/// be narrowed to on the RPC node
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VaultFilter {
+    Authority(Pubkey),
+    TokenMint(Pubkey),
+}
+
+impl VaultFilter {
+    /// The `getProgramAccounts` filter matching the field in a vault account
+    pub fn memcmp(&self) -> Memcmp {
+        let (offset, key) = match self {
+            Self::Authority(authority) => (AUTHORITY_OFFSET, authority),
+            Self::TokenMint(mint) => (TOKEN_MINT_OFFSET, mint),
+        };
+        Memcmp::new_raw_bytes(offset, key.to_bytes().to_vec())
+    }
+}
+
+/// How a vault name matched a search pattern, best first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum NameMatch {
+    Exact,
+    Prefix,
+    Substring,
+}
+
+impl NameMatch {
+    /// How `name` matches `pattern`, ignoring case; `None` if it doesn't
+    pub fn of(name: &str, pattern: &str) -> Option<Self> {
+        let name = name.to_lowercase();
+        let pattern = pattern.to_lowercase();
+        if name == pattern {
+            Some(Self::Exact)
+        } else if name.starts_with(&pattern) {
+            Some(Self::Prefix)
+        } else if name.contains(&pattern) {
+            Some(Self::Substring)
+        } else {
+            None
+        }
+    }
+}
+
+/// A vault's state and settings, with what a single vault's fetch adds:
+/// its live balance, freeze status, provenance and optional accounts
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VaultSummary {
+    #[serde(with = "crate::serde_utils::pubkey")]
+    pub address: Pubkey,
+    pub name: String,
+    #[serde(with = "crate::serde_utils::pubkey")]
+    pub authority: Pubkey,
+    #[serde(with = "crate::serde_utils::pubkey")]
+    pub token_mint: Pubkey,
+    #[serde(default, with = "crate::serde_utils::pubkey")]
+    pub fee_collector: Pubkey,
+    /// Withdrawal fee in basis points
+    #[serde(default)]
+    pub fee_percentage: u16,
+    /// Seconds between a withdrawal request and its execution
+    #[serde(default)]
+    pub withdrawal_timelock: i64,
+    /// Largest single withdrawal, in base units
+    #[serde(default)]
+    pub withdrawal_limit: u64,
+    /// Withdrawals below this amount are dust (0 = no dust handling)
+    #[serde(default)]
+    pub dust_threshold: u64,
+    /// Dust withdrawals are let through without a fee instead of refused
+    #[serde(default)]
+    pub waive_dust_fee: bool,
+    /// Share of deposits carved out into the insurance reserve
+    #[serde(default)]
+    pub reserve_bps: u16,
+    /// Seconds that must pass after a reserve release before the next one
+    #[serde(default)]
+    pub reserve_timelock: i64,
+    #[serde(default)]
+    pub total_deposited: u64,
+    #[serde(default)]
+    pub total_reserved: u64,
+    /// Withdrawal fees held in the vault token account for the fee collector
+    #[serde(default)]
+    pub accrued_fees: u64,
+    /// A counter was clamped to zero; the books need reconciling
+    #[serde(default)]
+    pub accounting_anomaly: bool,
+    /// Deposits and withdrawals are stopped until the authority unpauses
+    #[serde(default)]
+    pub paused: bool,
+    /// The configuration and authority can never change
+    #[serde(default)]
+    pub immutable: bool,
+    /// Deposits are accepted from this unix timestamp (0 = no lower bound)
+    #[serde(default)]
+    pub deposit_window_start: i64,
+    /// Deposits are rejected from this unix timestamp (0 = no upper bound)
+    #[serde(default)]
+    pub deposit_window_end: i64,
+    /// Who can freeze the vault's tokens, when the summary came from a
+    /// single vault's fetch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub freeze: Option<FreezeStatus>,
+    /// The vault token account's live balance against `total_deposited`,
+    /// when the summary came from a single vault's fetch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub balance: Option<VaultBalance>,
+    /// Slot and commitment of the read, when the summary came from a single
+    /// account fetch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provenance: Option<Provenance>,
+    /// The cluster's unix time at the read, when the summary came from a
+    /// single vault's fetch; `deposit_countdown` counts from it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cluster_time: Option<i64>,
+    /// The vault's notification config, when it has one and the client was
+    /// asked for extensions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notifications: Option<NotificationConfig>,
+    /// The vault's maintenance windows, when the client was asked for
+    /// extensions; see `MaintenanceWindow::describe` for a countdown
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub maintenance: Vec<MaintenanceWindow>,
+}
+
+impl VaultSummary {
+    pub fn of(address: Pubkey, vault: &Vault) -> Self {
+        Self {
+            address,
+            name: vault.name.clone(),
+            authority: vault.authority,
+            token_mint: vault.token_mint,
+            fee_collector: vault.fee_collector,
+            fee_percentage: vault.fee_percentage,
+            withdrawal_timelock: vault.withdrawal_timelock,
+            withdrawal_limit: vault.withdrawal_limit,
+            dust_threshold: vault.dust_threshold,
+            waive_dust_fee: vault.waive_dust_fee,
+            reserve_bps: vault.reserve_bps,
+            reserve_timelock: vault.reserve_timelock,
+            total_deposited: vault.total_deposited,
+            total_reserved: vault.total_reserved,
+            accrued_fees: vault.accrued_fees,
+            accounting_anomaly: vault.accounting_anomaly,
+            paused: vault.paused,
+            immutable: vault.immutable,
+            deposit_window_start: vault.deposit_window_start,
+            deposit_window_end: vault.deposit_window_end,
+            freeze: None,
+            balance: None,
+            provenance: None,
+            cluster_time: None,
+            notifications: None,
+            maintenance: Vec::new(),
+        }
+    }
+
+    /// How withdrawals below `dust_threshold` are treated
+    pub fn dust_policy(&self) -> DustPolicy {
+        DustPolicy {
+            threshold: self.dust_threshold,
+            mode: if self.waive_dust_fee {
+                DustMode::WaiveFee
+            } else {
+                DustMode::Reject
+            },
+        }
+    }
+
+    /// The reserve's share of deposits and the wait between releases
+    pub fn reserve_policy(&self) -> ReservePolicy {
+        ReservePolicy {
+            bps: self.reserve_bps,
+            timelock: self.reserve_timelock,
+        }
+    }
+
+    /// The period in which the vault accepts deposits
+    pub fn deposit_window(&self) -> DepositWindow {
+        DepositWindow {
+            start: self.deposit_window_start,
+            end: self.deposit_window_end,
+        }
+    }
+
+    /// The deposit window's state with a countdown from `cluster_time`, e.g.
+    /// "open, closes in 2h 5m"; `None` when the summary was read without
+    /// the cluster's clock
+    pub fn deposit_countdown(&self) -> Option<String> {
+        self.cluster_time
+            .map(|now| self.deposit_window().describe(now))
+    }
+}
+
+impl fmt::Display for VaultSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} (authority {}, mint {}",
+            self.address, self.name, self.authority, self.token_mint
+        )?;
+        if self.reserve_bps > 0 {
+            write!(f, ", reserve {} bps", self.reserve_bps)?;
+        }
+        if self.immutable {
+            write!(f, ", immutable")?;
+        }
+        if self.paused {
+            write!(f, ", paused")?;
+        }
+        let window = self.deposit_window();
+        if !window.is_unrestricted() {
+            match self.deposit_countdown() {
+                Some(countdown) => write!(f, ", deposits {}", countdown)?,
+                None => write!(f, ", deposits {}", window)?,
+            }
+        }
+        if let Some(freeze) = &self.freeze {
+            if freeze.vault_token_account_frozen || freeze.freeze_authority.is_some() {
+                write!(f, ", {}", freeze)?;
+            }
+        }
+        if let Some(balance) = &self.balance {
+            if balance.discrepancy != 0 {
+                write!(f, ", balance off the books by {:+}", balance.discrepancy)?;
+            }
+        }
+        write!(f, ")")
+    }
+}
+
+/// `vaults` matching `pattern`, best match first, then by name
+pub fn match_vaults<'a>(
+    vaults: impl IntoIterator<Item = &'a VaultSummary>,
+    pattern: &str,
+) -> Vec<&'a VaultSummary> {
+    let mut matches: Vec<_> = vaults
+        .into_iter()
+        .filter_map(|vault| NameMatch::of(&vault.name, pattern).map(|kind| (kind, vault)))
+        .collect();
+    matches.sort_by(|(a_kind, a), (b_kind, b)| {
+        a_kind
+            .cmp(b_kind)
+            .then_with(|| a.name.cmp(&b.name))
+            .then_with(|| a.address.cmp(&b.address))
+    });
+    matches.into_iter().map(|(_, vault)| vault).collect()
+}
+
+/// The one vault `pattern` names: the exact matches if there are any,
+/// otherwise every prefix or substring match. Fails with `AmbiguousVaultName`
+/// when more than one vault fits.
+pub fn resolve_vault_name<'a>(
+    vaults: impl IntoIterator<Item = &'a VaultSummary>,
+    pattern: &str,
+) -> Result<&'a VaultSummary> {
+    let matches = match_vaults(vaults, pattern);
+    let exact: Vec<_> = matches
+        .iter()
+        .copied()
+        .filter(|vault| NameMatch::of(&vault.name, pattern) == Some(NameMatch::Exact))
+        .collect();
+    let candidates = if exact.is_empty() { matches } else { exact };
+    match candidates.as_slice() {
+        [] => Err(anyhow!("No vault named like '{}'", pattern)),
+        [vault] => Ok(vault),
+        _ => Err(TokenVaultError::AmbiguousVaultName {
+            pattern: pattern.to_string(),
+            matches: candidates
+                .iter()
+                .map(|vault| (vault.name.clone(), vault.address))
+                .collect(),
+        }
+        .into()),
+    }
+}
+
+/// Local snapshot of the program's vaults plus user-defined aliases, so names
+/// resolve instantly and offline. Refresh it with `TokenVaultClient::list_vaults`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VaultIndex {
+    #[serde(with = "crate::serde_utils::pubkey")]
+    pub program_id: Pubkey,
+    /// RPC URL the snapshot was taken from
+    pub cluster: String,
+    /// Unix timestamp of the last refresh
+    pub refreshed_at: i64,
+    pub vaults: Vec<VaultSummary>,
+    /// Alias -> vault address; kept across refreshes
+    #[serde(default)]
+    pub aliases: BTreeMap<String, String>,
+}
+
+impl VaultIndex {
+    /// Load the index at `path`; `None` if it was never written
+    pub fn load(path: &Path) -> Result<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let contents = fs::read_to_string(path)?;
+        let index = serde_json::from_str(&contents)
+            .with_context(|| format!("Invalid vault index {}", path.display()))?;
+        Ok(Some(index))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        // Write-then-rename so a kill mid-write never leaves a truncated index
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, serde_json::to_vec_pretty(self)?)?;
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    /// Replace the vault snapshot, keeping the aliases
+    pub fn refresh(&mut self, cluster: &str, refreshed_at: i64, vaults: Vec<VaultSummary>) {
+        self.cluster = cluster.to_string();
+        self.refreshed_at = refreshed_at;
+        self.vaults = vaults;
+    }
+
+    /// Vaults whose name matches `pattern`, best match first
+    pub fn find(&self, pattern: &str) -> Vec<&VaultSummary> {
+        match_vaults(&self.vaults, pattern)
+    }
+
+    /// The one vault named like `pattern`
+    pub fn resolve_name(&self, pattern: &str) -> Result<Pubkey> {
+        resolve_vault_name(&self.vaults, pattern).map(|vault| vault.address)
+    }
+
+    /// The vault an alias points to
+    pub fn resolve_alias(&self, alias: &str) -> Result<Pubkey> {
+        let address = self.aliases.get(alias).ok_or_else(|| {
+            anyhow!(
+                "Unknown alias '{}'; known aliases: {}",
+                alias,
+                self.aliases.keys().cloned().collect::<Vec<_>>().join(", ")
+            )
+        })?;
+        address
+            .parse()
+            .map_err(|_| anyhow!("Alias '{}' points to invalid address '{}'", alias, address))
+    }
+}
+
+impl TokenVaultClient {
+    /// Every vault of the program, or only those of `authority`, sorted by name
+    pub fn list_vaults(&self, authority: Option<Pubkey>) -> Result<Vec<VaultSummary>> {
+        let (mut vaults, _) = self.scan_vaults(authority)?;
+        vaults.sort_by_key(|vault| vault.page_key());
+        Ok(vaults)
+    }
+
+    /// Up to `limit` vaults after `cursor`, in `list_vaults` order
+    pub fn list_vaults_page(
+        &self,
+        authority: Option<Pubkey>,
+        cursor: Option<&Cursor<VaultSummary>>,
+        limit: usize,
+    ) -> Result<Page<VaultSummary>> {
+        check_limit(limit)?;
+        let (vaults, slot) = self.scan_vaults(authority)?;
+        Ok(paginate(vaults, cursor, limit, slot))
+    }
+
+    /// Every vault of `authority` with its state, sorted by name; empty if
+    /// it has none
+    pub fn list_vaults_by_authority(&self, authority: Pubkey) -> Result<Vec<(Pubkey, Vault)>> {
+        self.list_vault_states(VaultFilter::Authority(authority))
+    }
+
+    /// Every vault holding `mint` with its state, sorted by name; empty if
+    /// there are none
+    pub fn list_vaults_by_mint(&self, mint: Pubkey) -> Result<Vec<(Pubkey, Vault)>> {
+        self.list_vault_states(VaultFilter::TokenMint(mint))
+    }
+
+    fn list_vault_states(&self, filter: VaultFilter) -> Result<Vec<(Pubkey, Vault)>> {
+        let (mut vaults, _) = self.scan_vault_states(Some(filter))?;
+        vaults.sort_by(|(a, vault_a), (b, vault_b)| {
+            vault_a.name.cmp(&vault_b.name).then_with(|| a.cmp(b))
+        });
+        Ok(vaults)
+    }
+
+    /// Every vault of the program, or only those of `authority`, unsorted,
+    /// and the slot they were read at
+    fn scan_vaults(&self, authority: Option<Pubkey>) -> Result<(Vec<VaultSummary>, u64)> {
+        let (vaults, slot) = self.scan_vault_states(authority.map(VaultFilter::Authority))?;
+        let vaults = vaults
+            .iter()
+            .map(|(address, vault)| VaultSummary::of(*address, vault))
+            .collect();
+        Ok((vaults, slot))
+    }
+
+    /// Every vault of the program matching `filter`, decoded, unsorted, and
+    /// the slot they were read at
+    fn scan_vault_states(
+        &self,
+        filter: Option<VaultFilter>,
+    ) -> Result<(Vec<(Pubkey, Vault)>, u64)> {
+        let rpc = self.rpc_within(self.deadline(), DeadlinePhase::Fetch)?;
+        let mut filters = vec![RpcFilterType::Memcmp(Memcmp::new_raw_bytes(
+            0,
+            Vault::DISCRIMINATOR.to_vec(),
+        ))];
+        filters.extend(filter.map(|filter| RpcFilterType::Memcmp(filter.memcmp())));
+        let config = RpcProgramAccountsConfig {
+            filters: Some(filters),
+            account_config: RpcAccountInfoConfig {
+                encoding: Some(UiAccountEncoding::Base64),
+                commitment: Some(rpc.commitment()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let (accounts, slot) = program_accounts_with_slot(&rpc, &self.program.id(), config)?;
+        let vaults = accounts
+            .into_iter()
+            .map(|(address, account)| {
+                let vault = Vault::try_deserialize(&mut account.data.as_slice())
+                    .with_context(|| format!("Failed to decode vault {}", address))?;
+                Ok((address, vault))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok((vaults, slot))
+    }
+
+    /// Vaults whose name equals, starts with or contains `pattern`, ignoring
+    /// case, best match first. Searches the program's vaults on chain; use a
+    /// `VaultIndex` to search offline.
+    pub fn find_vaults_by_name(&self, pattern: &str) -> Result<Vec<VaultSummary>> {
+        let vaults = self.list_vaults(None)?;
+        Ok(match_vaults(&vaults, pattern)
+            .into_iter()
+            .cloned()
+            .collect())
+    }
+}
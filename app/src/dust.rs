@@ -0,0 +1,257 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::token_vault::state::Vault;
+use crate::{CheckedAgainst, TokenVaultClient, TokenVaultError};
+
+/// Basis points in 100%
+pub const BPS_DENOMINATOR: u64 = 10_000;
+
+/// What a vault does with withdrawals below its dust threshold
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum DustMode {
+    /// Refuse the withdrawal
+    #[default]
+    Reject,
+    /// Allow it without charging a fee
+    WaiveFee,
+}
+
+/// Vault-level policy for tiny withdrawals; a threshold of 0 disables it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct DustPolicy {
+    /// Withdrawals strictly below this amount, in base units, are dust
+    pub threshold: u64,
+    pub mode: DustMode,
+}
+
+impl DustPolicy {
+    pub const DISABLED: Self = Self {
+        threshold: 0,
+        mode: DustMode::Reject,
+    };
+
+    pub fn reject_below(threshold: u64) -> Self {
+        Self {
+            threshold,
+            mode: DustMode::Reject,
+        }
+    }
+
+    pub fn waive_fee_below(threshold: u64) -> Self {
+        Self {
+            threshold,
+            mode: DustMode::WaiveFee,
+        }
+    }
+
+    /// The policy stored on a vault
+    pub fn of(vault: &Vault) -> Self {
+        Self {
+            threshold: vault.dust_threshold,
+            mode: if vault.waive_dust_fee {
+                DustMode::WaiveFee
+            } else {
+                DustMode::Reject
+            },
+        }
+    }
+
+    pub fn is_dust(&self, amount: u64) -> bool {
+        amount < self.threshold
+    }
+}
+
+impl fmt::Display for DustPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.threshold, self.mode) {
+            (0, _) => write!(f, "none"),
+            (threshold, DustMode::Reject) => write!(f, "reject below {}", threshold),
+            (threshold, DustMode::WaiveFee) => write!(f, "no fee below {}", threshold),
+        }
+    }
+}
+
+/// What a withdrawal would pay out, computed the way the program does
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WithdrawalQuote {
+    pub amount: u64,
+    pub fee: u64,
+    /// Amount received by the withdrawer
+    pub net_amount: u64,
+    /// Below the vault's dust threshold
+    pub is_dust: bool,
+    /// The fee was waived because the withdrawal is dust
+    pub fee_waived: bool,
+    /// The vault charges a fee but it rounds down to zero for this amount
+    pub fee_rounds_to_zero: bool,
+}
+
+impl WithdrawalQuote {
+    /// Quote withdrawing `amount` from `vault`, enforcing the zero-amount and dust rules
+    pub fn for_vault(vault: &Vault, amount: u64) -> Result<Self, TokenVaultError> {
+        ensure_nonzero("withdraw", amount)?;
+
+        let policy = DustPolicy::of(vault);
+        let is_dust = policy.is_dust(amount);
+        if is_dust && policy.mode == DustMode::Reject {
+            return Err(TokenVaultError::DustWithdrawal {
+                amount,
+                threshold: policy.threshold,
+            });
+        }
+
+        let fee_waived = is_dust && policy.mode == DustMode::WaiveFee;
+        let fee = if fee_waived {
+            0
+        } else {
+            withdrawal_fee(amount, vault.fee_percentage)
+        };
+        Ok(Self {
+            amount,
+            fee,
+            net_amount: amount - fee,
+            is_dust,
+            fee_waived,
+            fee_rounds_to_zero: !fee_waived && fee == 0 && vault.fee_percentage > 0,
+        })
+    }
+}
+
+impl fmt::Display for WithdrawalQuote {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "withdraw {}: fee {}, receive {}",
+            self.amount, self.fee, self.net_amount
+        )?;
+        if self.fee_waived {
+            write!(f, " (dust: fee waived)")?;
+        } else if self.fee_rounds_to_zero {
+            write!(f, " (fee rounds to zero)")?;
+        }
+        Ok(())
+    }
+}
+
+/// A `WithdrawalQuote` checked against the vault's per-withdrawal limit,
+/// without a signer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WithdrawPreview {
+    #[serde(flatten)]
+    pub quote: WithdrawalQuote,
+    /// Most a single withdrawal from the vault may take
+    pub withdrawal_limit: u64,
+    /// The program would refuse the withdrawal for exceeding `withdrawal_limit`
+    pub exceeds_limit: bool,
+}
+
+impl WithdrawPreview {
+    /// Preview withdrawing `amount` from `vault`; fails only where `for_vault`
+    /// on `WithdrawalQuote` does
+    pub fn for_vault(vault: &Vault, amount: u64) -> Result<Self, TokenVaultError> {
+        Ok(Self {
+            quote: WithdrawalQuote::for_vault(vault, amount)?,
+            withdrawal_limit: vault.withdrawal_limit,
+            exceeds_limit: amount > vault.withdrawal_limit,
+        })
+    }
+}
+
+impl fmt::Display for WithdrawPreview {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.quote)?;
+        if self.exceeds_limit {
+            write!(
+                f,
+                " (exceeds the withdrawal limit of {})",
+                self.withdrawal_limit
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Fee charged on a withdrawal. Like the program, this rounds down, so the
+/// withdrawer never pays more than `fee_percentage` basis points; amounts
+/// below `10_000 / fee_percentage` pay no fee at all. The product is taken
+/// in 128 bits and cannot overflow.
+pub fn withdrawal_fee(amount: u64, fee_percentage: u16) -> u64 {
+    (amount as u128 * fee_percentage as u128 / BPS_DENOMINATOR as u128) as u64
+}
+
+/// How much the next withdrawal from a vault may take. The program caps
+/// each withdrawal at `withdrawal_limit` and tracks nothing across
+/// withdrawals, so nothing is used up: `remaining` is the cap itself and
+/// there is no window to reset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WithdrawCapacity {
+    /// Most the next withdrawal may take
+    pub remaining: u64,
+    /// When a rolling window would restore the capacity, as a unix
+    /// timestamp; always `None` for the current program
+    pub window_resets_at: Option<i64>,
+}
+
+impl WithdrawCapacity {
+    /// The capacity `vault` leaves for its next withdrawal
+    pub fn of(vault: &Vault) -> Self {
+        Self {
+            remaining: vault.withdrawal_limit,
+            window_resets_at: None,
+        }
+    }
+
+    /// Refuse withdrawing `amount`, which the program would reject
+    pub fn check(&self, amount: u64) -> Result<(), TokenVaultError> {
+        if amount > self.remaining {
+            return Err(TokenVaultError::WithdrawalLimitExceeded {
+                requested: amount,
+                remaining: self.remaining,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Refuse a withdrawal the program would reject for exceeding the vault's
+/// per-withdrawal limit
+pub fn check_withdrawal_limit(vault: &Vault, amount: u64) -> Result<(), TokenVaultError> {
+    WithdrawCapacity::of(vault).check(amount)
+}
+
+/// Refuse zero-amount operations, which only pollute history
+pub fn ensure_nonzero(operation: &'static str, amount: u64) -> Result<(), TokenVaultError> {
+    if amount == 0 {
+        return Err(TokenVaultError::ZeroAmount { operation });
+    }
+    Ok(())
+}
+
+impl TokenVaultClient {
+    /// Preview a withdrawal from the current vault: fee, net amount and dust handling
+    pub fn quote_withdrawal(&self, amount: u64) -> Result<WithdrawalQuote> {
+        let vault = self.current_vault()?;
+        let (vault_data, provenance) = self.fetch_vault_state(vault, self.deadline(), false)?;
+        WithdrawalQuote::for_vault(&vault_data, amount).context(CheckedAgainst(provenance))
+    }
+
+    /// Preview a withdrawal from the current vault: gross, fee, net and
+    /// whether it exceeds the withdrawal limit. Needs no signer and reads the
+    /// vault through the client's cache, so read-only frontends can use it.
+    pub fn preview_withdraw(&self, amount: u64) -> Result<WithdrawPreview> {
+        let vault = self.current_vault()?;
+        let (vault_data, provenance) = self.fetch_vault_state(vault, self.deadline(), false)?;
+        WithdrawPreview::for_vault(&vault_data, amount).context(CheckedAgainst(provenance))
+    }
+
+    /// How much the next withdrawal from the current vault may take, read
+    /// through the client's cache. `withdraw` refuses larger amounts before
+    /// sending unless `WithdrawOptions::skip_limit_check` is set.
+    pub fn get_remaining_withdrawal_capacity(&self) -> Result<WithdrawCapacity> {
+        let vault = self.current_vault()?;
+        let (vault_data, _) = self.fetch_vault_state(vault, self.deadline(), false)?;
+        Ok(WithdrawCapacity::of(&vault_data))
+    }
+}
@@ -0,0 +1,275 @@
+use anchor_client::{
+    solana_client::rpc_client::RpcClient,
+    solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey, signature::Keypair},
+    Cluster,
+};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::{ClientConfig, Deadline, TokenVaultClient};
+
+/// Longest a read waits for the read endpoint to catch up with the client's
+/// own writes before it is sent to the write endpoint instead
+const REPLICA_CATCH_UP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How often a lagging read endpoint is asked for its slot again
+const REPLICA_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// An RPC endpoint and the slot it reported, or why it could not
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EndpointStatus {
+    pub url: String,
+    pub slot: Result<u64, String>,
+}
+
+impl EndpointStatus {
+    fn probe(url: &str, commitment: CommitmentConfig) -> Self {
+        let rpc = RpcClient::new_with_commitment(url.to_string(), commitment);
+        Self {
+            url: url.to_string(),
+            slot: rpc
+                .get_slot_with_commitment(commitment)
+                .map_err(|err| err.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for EndpointStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.slot {
+            Ok(slot) => write!(f, "{} at slot {}", self.url, slot),
+            Err(err) => write!(f, "{} unreachable: {}", self.url, err),
+        }
+    }
+}
+
+/// Slots of the write endpoint and, when separate, the read endpoint
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EndpointHealth {
+    pub write: EndpointStatus,
+    pub read: Option<EndpointStatus>,
+}
+
+impl EndpointHealth {
+    /// Ask both endpoints for their slot at `commitment`
+    pub fn probe(write_url: &str, read_url: Option<&str>, commitment: CommitmentConfig) -> Self {
+        Self {
+            write: EndpointStatus::probe(write_url, commitment),
+            read: read_url.map(|url| EndpointStatus::probe(url, commitment)),
+        }
+    }
+
+    /// Slots the read endpoint is behind the write endpoint; negative when it
+    /// is ahead. `None` without a separate read endpoint or when either is down.
+    pub fn lag(&self) -> Option<i64> {
+        let write = *self.write.slot.as_ref().ok()?;
+        let read = *self.read.as_ref()?.slot.as_ref().ok()?;
+        Some(write as i64 - read as i64)
+    }
+}
+
+impl fmt::Display for EndpointHealth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Write RPC: {}", self.write)?;
+        match &self.read {
+            None => write!(f, "\nRead RPC: same as write"),
+            Some(read) => {
+                write!(f, "\nRead RPC: {}", read)?;
+                if let Some(lag) = self.lag() {
+                    write!(f, "\nReplica lag: {} slots", lag)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// A cluster for a custom RPC node at `rpc_url`. Without `ws_url`, the
+/// WebSocket endpoint is the same address with `http` swapped for `ws` and
+/// `https` for `wss`.
+pub fn custom_cluster(rpc_url: &str, ws_url: Option<&str>) -> Result<Cluster> {
+    check_endpoint(rpc_url, &["http", "https"], "RPC")?;
+    let ws_url = match ws_url {
+        Some(ws_url) => {
+            check_endpoint(ws_url, &["ws", "wss"], "WebSocket")?;
+            ws_url.to_string()
+        }
+        None => infer_ws_url(rpc_url)?,
+    };
+    Ok(Cluster::Custom(rpc_url.to_string(), ws_url))
+}
+
+/// The WebSocket URL of the RPC node at `rpc_url`: `http` becomes `ws`,
+/// `https` becomes `wss`, the rest is kept
+pub fn infer_ws_url(rpc_url: &str) -> Result<String> {
+    let scheme = check_endpoint(rpc_url, &["http", "https"], "RPC")?;
+    let ws_scheme = if scheme == "https" { "wss" } else { "ws" };
+    Ok(format!("{}{}", ws_scheme, &rpc_url[scheme.len()..]))
+}
+
+/// Refuse `url` unless it is `<scheme>://<host>...` with one of `schemes`
+/// and no whitespace; returns the scheme, lowercased
+fn check_endpoint(url: &str, schemes: &[&str], what: &str) -> Result<String> {
+    let invalid = |reason: String| anyhow!("Invalid {} URL '{}': {}", what, url, reason);
+    let (scheme, rest) = url
+        .split_once("://")
+        .ok_or_else(|| invalid("missing scheme, e.g. https://".to_string()))?;
+    let scheme = scheme.to_ascii_lowercase();
+    if !schemes.contains(&scheme.as_str()) {
+        return Err(invalid(format!("scheme must be {}", schemes.join(" or "))));
+    }
+    let host = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    if host.is_empty() || host.starts_with(':') {
+        return Err(invalid("missing host".to_string()));
+    }
+    if url.chars().any(char::is_whitespace) {
+        return Err(invalid("contains whitespace".to_string()));
+    }
+    Ok(scheme)
+}
+
+impl TokenVaultClient {
+    /// Create a client for a custom RPC node, validating its URLs first; see
+    /// `custom_cluster` for how a missing `ws_url` is filled in
+    pub fn new_with_rpc(
+        rpc_url: &str,
+        ws_url: Option<&str>,
+        payer: Keypair,
+        program_id: Pubkey,
+    ) -> Result<Self> {
+        let cluster = custom_cluster(rpc_url, ws_url)?;
+        Self::new_with_config(cluster, payer, program_id, ClientConfig::default())
+    }
+
+    /// The cluster the client was created for
+    pub fn cluster(&self) -> &Cluster {
+        &self.cluster
+    }
+
+    /// URL transactions are sent to; the same as `write_rpc_url`
+    pub fn rpc_url(&self) -> String {
+        self.write_rpc_url()
+    }
+
+    /// Send and simulate transactions through `url` (a cluster moniker or an
+    /// RPC URL) instead of the cluster's default endpoint. The cluster the
+    /// client was created for still decides key policy checks.
+    pub fn with_write_rpc(&mut self, url: &str) -> Result<&mut Self> {
+        let transport = Cluster::from_str(url)?;
+        self.program = self.program.with_cluster(transport);
+        Ok(self)
+    }
+
+    /// Send account fetches, program account scans, history and cache
+    /// refreshes to `url` (a cluster moniker or an RPC URL). After each of
+    /// the client's own mutations, reads wait for this endpoint to reach the
+    /// slot the write endpoint confirmed at, and fall back to the write
+    /// endpoint if it does not catch up in time.
+    pub fn with_read_rpc(&mut self, url: &str) -> Result<&mut Self> {
+        let url = Cluster::from_str(url)?.url().to_string();
+        self.cache = self
+            .cache
+            .as_ref()
+            .map(|cache| Arc::new(cache.reconnect(url.clone())));
+        self.vault_cache.clear();
+        self.read_rpc_url = Some(url);
+        Ok(self)
+    }
+
+    /// URL reads are sent to
+    pub fn read_rpc_url(&self) -> String {
+        self.read_rpc_url
+            .clone()
+            .unwrap_or_else(|| self.program.rpc().url())
+    }
+
+    /// URL transactions are sent to
+    pub fn write_rpc_url(&self) -> String {
+        self.program.rpc().url()
+    }
+
+    /// Current slots of the write and read endpoints and the lag between them
+    pub fn endpoint_health(&self) -> EndpointHealth {
+        let commitment = self.read_commitment_for(&self.program.rpc());
+        EndpointHealth::probe(
+            &self.write_rpc_url(),
+            self.read_rpc_url.as_deref(),
+            commitment,
+        )
+    }
+
+    /// Connection to the write endpoint whose request timeout ends at `deadline`
+    pub(crate) fn write_rpc_until(&self, deadline: Deadline) -> RpcClient {
+        let rpc = self.program.rpc();
+        match deadline.remaining() {
+            Some(remaining) => {
+                RpcClient::new_with_timeout_and_commitment(rpc.url(), remaining, rpc.commitment())
+            }
+            None => rpc,
+        }
+    }
+
+    /// Connection to the read endpoint whose request timeout ends at
+    /// `deadline`. Until the read endpoint has caught up with the client's
+    /// last write, this is the write endpoint.
+    pub(crate) fn read_rpc_until(&self, deadline: Deadline) -> RpcClient {
+        let Some(url) = &self.read_rpc_url else {
+            return self.write_rpc_until(deadline);
+        };
+        let commitment = self.program.rpc().commitment();
+        let read = match deadline.remaining() {
+            Some(remaining) => {
+                RpcClient::new_with_timeout_and_commitment(url.clone(), remaining, commitment)
+            }
+            None => RpcClient::new_with_commitment(url.clone(), commitment),
+        };
+        if self.replica_caught_up(&read, deadline) {
+            read
+        } else {
+            self.write_rpc_until(deadline)
+        }
+    }
+
+    /// Wait, within `deadline` and `REPLICA_CATCH_UP_TIMEOUT`, for `read` to
+    /// reach the minimum context slot of the client's last write
+    fn replica_caught_up(&self, read: &RpcClient, deadline: Deadline) -> bool {
+        let min_context_slot = self.min_context_slot.load(Ordering::Relaxed);
+        if self.replica_slot.load(Ordering::Relaxed) >= min_context_slot {
+            return true;
+        }
+
+        let give_up = Instant::now() + REPLICA_CATCH_UP_TIMEOUT;
+        loop {
+            if let Ok(slot) = read.get_slot_with_commitment(read.commitment()) {
+                self.replica_slot.fetch_max(slot, Ordering::Relaxed);
+                if slot >= min_context_slot {
+                    return true;
+                }
+            }
+            let now = Instant::now();
+            let stop = deadline.instant().map_or(give_up, |at| at.min(give_up));
+            if now >= stop {
+                return false;
+            }
+            thread::sleep((stop - now).min(REPLICA_POLL_INTERVAL));
+        }
+    }
+
+    /// Remember the write endpoint's slot after a confirmed mutation, so later
+    /// reads never see state from before it
+    pub(crate) fn note_write(&self) {
+        if self.read_rpc_url.is_none() {
+            return;
+        }
+        let rpc = self.program.rpc();
+        if let Ok(slot) = rpc.get_slot_with_commitment(rpc.commitment()) {
+            self.min_context_slot.fetch_max(slot, Ordering::Relaxed);
+        }
+    }
+}
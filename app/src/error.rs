@@ -0,0 +1,385 @@
+use anchor_client::solana_client::{
+    client_error::{ClientError, ClientErrorKind},
+    rpc_request::{RpcError, RpcResponseErrorData},
+};
+use anchor_client::solana_sdk::{
+    instruction::InstructionError, pubkey::Pubkey, signature::Signature,
+    transaction::TransactionError,
+};
+use thiserror::Error;
+
+use crate::deadline::DeadlinePhase;
+use crate::deposit_source::SourceSuggestion;
+use crate::fee_path::FeePathStatus;
+use crate::intent::IntentViolation;
+
+/// Typed failures of the client.
+///
+/// Methods return `anyhow::Result`, keeping the context of each failure.
+/// Failures the client detects itself are in the error's chain; match on
+/// them with `err.downcast_ref::<TokenVaultError>()`. `TokenVaultError::of`
+/// and `TokenVaultError::from` type every failure, including failed RPC
+/// requests and program errors.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum TokenVaultError {
+    #[error("Vault address not set")]
+    VaultNotSet,
+
+    #[error("Vault {vault} not found")]
+    VaultNotFound { vault: Pubkey },
+
+    #[error("Vault {vault} is immutable: its configuration and authority can never change")]
+    VaultImmutable { vault: Pubkey },
+
+    #[error(
+        "Deposits are closed: window opens at {opens_at} and closes at {closes_at} (0 = unbounded)"
+    )]
+    DepositWindowClosed { opens_at: i64, closes_at: i64 },
+
+    /// Deposits resume on their own at `ends_at`; nothing needs to be sent
+    #[error(
+        "The vault is under maintenance until {}: {reason}",
+        crate::schedule::format_timestamp(*ends_at)
+    )]
+    MaintenanceWindow { ends_at: i64, reason: String },
+
+    #[error(
+        "Maintenance window overlaps the one from {} to {}: {reason}",
+        crate::schedule::format_timestamp(*start),
+        crate::schedule::format_timestamp(*end)
+    )]
+    MaintenanceOverlap {
+        start: i64,
+        end: i64,
+        reason: String,
+    },
+
+    #[error("Refusing to {operation} an amount of zero")]
+    ZeroAmount { operation: &'static str },
+
+    #[error("Withdrawal of {amount} is below the vault's dust threshold of {threshold}")]
+    DustWithdrawal { amount: u64, threshold: u64 },
+
+    #[error(
+        "Withdrawal of {requested} exceeds the vault's withdrawal limit; at most {remaining} \
+         can be withdrawn"
+    )]
+    WithdrawalLimitExceeded { requested: u64, remaining: u64 },
+
+    #[error("Memo of {len} bytes is longer than the {max} a transaction can carry")]
+    MemoTooLong { len: usize, max: usize },
+
+    #[error("Transaction of {size} bytes is {} over the {max} a packet holds; split the batch", size - max)]
+    TransactionTooLarge { size: usize, max: usize },
+
+    #[error("The insurance reserve is timelocked until {unlocks_at}")]
+    ReserveLocked { unlocks_at: i64 },
+
+    #[error("The withdrawal request is timelocked until {executable_at}")]
+    WithdrawalTimelocked { executable_at: i64 },
+
+    #[error("Cannot release {requested} from the insurance reserve, which holds {available}")]
+    ReserveInsufficient { requested: u64, available: u64 },
+
+    /// The mint's freeze authority froze the vault's token account, so the
+    /// token program rejects every deposit and withdrawal until it is thawed
+    #[error("The vault's token account is frozen; {}", thaw_by(freeze_authority))]
+    VaultTokenAccountFrozen { freeze_authority: Option<Pubkey> },
+
+    /// The authority stopped deposits and withdrawals; see `pause_vault`
+    #[error(
+        "Vault is paused; deposits and withdrawals resume once its authority {authority} \
+         unpauses it"
+    )]
+    VaultPaused { authority: Pubkey },
+
+    #[error("Token account {account} is frozen; {}", thaw_by(freeze_authority))]
+    TokenAccountFrozen {
+        account: Pubkey,
+        freeze_authority: Option<Pubkey>,
+    },
+
+    #[error(
+        "Mint {mint} has freeze authority {freeze_authority}, which can freeze the vault's \
+         tokens; acknowledge the freezable mint to create the vault anyway"
+    )]
+    FreezableMintNotAcknowledged {
+        mint: Pubkey,
+        freeze_authority: Pubkey,
+    },
+
+    #[error(
+        "Withdrawal of {amount} exceeds the vault's {counter} of {recorded}; the program would \
+         clamp it to zero and flag the vault. Run verify-books to reconcile"
+    )]
+    AccountingUnderflow {
+        counter: &'static str,
+        recorded: u64,
+        amount: u64,
+    },
+
+    #[error("Amount is denominated in mint {actual}, but mint {expected} is required")]
+    MintMismatch { expected: Pubkey, actual: Pubkey },
+
+    /// A token account the caller named, rather than one the client derived,
+    /// holds another mint than the vault's
+    #[error(
+        "Token account {account} holds mint {account_mint}, but the vault takes mint {vault_mint}"
+    )]
+    TokenAccountMintMismatch {
+        account: Pubkey,
+        vault_mint: Pubkey,
+        account_mint: Pubkey,
+    },
+
+    /// The depositor lacks the vault's mint but holds `source_mint`, the
+    /// usual sign of picking a vault for the wrong token
+    #[error(
+        "Vault takes mint {vault_mint}, but the depositor's tokens are of mint {source_mint}; \
+         {suggestion}"
+    )]
+    SourceMintMismatch {
+        vault_mint: Pubkey,
+        source_mint: Pubkey,
+        suggestion: SourceSuggestion,
+    },
+
+    #[error("Token account {account} holds {have}, less than the {need} needed")]
+    InsufficientBalance {
+        account: Pubkey,
+        have: u64,
+        need: u64,
+    },
+
+    #[error(
+        "Fees of vault {vault} are claimed into {account}, which is {status}; \
+         run `token-vault repair fee-path --vault {vault}`"
+    )]
+    FeePathBroken {
+        vault: Pubkey,
+        account: Pubkey,
+        status: FeePathStatus,
+    },
+
+    #[error(
+        "All {payers} payers of the fee pool are below the floor of {floor_lamports} lamports, \
+         and the reserve cannot top them up; run `token-vault dev fund-pool`"
+    )]
+    FeePoolExhausted { payers: usize, floor_lamports: u64 },
+
+    /// A withdrawal would pay into `owner`'s associated token account, which
+    /// does not exist and was not to be created
+    #[error(
+        "Token account {account} of {owner} does not exist; withdraw with `create_ata` to \
+         create it in the same transaction"
+    )]
+    TokenAccountMissing { owner: Pubkey, account: Pubkey },
+
+    #[error(
+        "Token account {account} is both source and destination; the transfer would move nothing"
+    )]
+    SelfTransfer { account: Pubkey },
+
+    #[error("Key {key} is not allowed to sign on {cluster} by the key policy")]
+    KeyClusterPolicyViolation { key: Pubkey, cluster: String },
+
+    #[error(
+        "Program requires feature '{feature}', which client {} does not support; \
+         upgrade token-vault-client",
+        crate::features::CLIENT_VERSION
+    )]
+    FeatureRequired { feature: String },
+
+    #[error(
+        "Client {} is older than the minimum version {minimum} the program supports; \
+         upgrade token-vault-client",
+        crate::features::CLIENT_VERSION
+    )]
+    ClientTooOld { minimum: String },
+
+    /// `resets_at` is when the oldest spend counted in the window rolls out
+    #[error(
+        "Spending limit exceeded: {window_remaining} left in the window; \
+         more frees up at {resets_at}"
+    )]
+    SpendingLimitExceeded {
+        window_remaining: u64,
+        resets_at: i64,
+    },
+
+    #[error("Refusing to sign: instruction {instruction} {violation}")]
+    UnsafeTransaction {
+        instruction: usize,
+        violation: IntentViolation,
+    },
+
+    #[error("'{pattern}' matches more than one vault: {}", list_matches(matches))]
+    AmbiguousVaultName {
+        pattern: String,
+        matches: Vec<(String, Pubkey)>,
+    },
+
+    /// The operation ran out of time; `signature` is set when the transaction
+    /// was already submitted and may still land
+    #[error("Deadline exceeded during {phase}{}", sent_suffix(signature))]
+    DeadlineExceeded {
+        phase: DeadlinePhase,
+        signature: Option<Signature>,
+    },
+
+    /// The RPC request failed or was refused, for a reason other than a
+    /// program error
+    #[error("RPC request failed: {message}")]
+    Rpc { message: String },
+
+    /// The program failed the transaction with `code`. `name` and `logs`
+    /// come from the preflight simulation, when there was one.
+    #[error("Program error {code}{}", program_error_name(name))]
+    ProgramError {
+        code: u32,
+        name: Option<String>,
+        logs: Vec<String>,
+    },
+
+    /// Any other failure, with its context
+    #[error("{message}")]
+    Other { message: String },
+}
+
+impl TokenVaultError {
+    /// Stable snake_case name of the variant, the `code` of its JSON error
+    /// body; see `json::ErrorBody`
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::VaultNotSet => "vault_not_set",
+            Self::VaultNotFound { .. } => "vault_not_found",
+            Self::VaultImmutable { .. } => "vault_immutable",
+            Self::DepositWindowClosed { .. } => "deposit_window_closed",
+            Self::MaintenanceWindow { .. } => "maintenance_window",
+            Self::MaintenanceOverlap { .. } => "maintenance_overlap",
+            Self::ZeroAmount { .. } => "zero_amount",
+            Self::DustWithdrawal { .. } => "dust_withdrawal",
+            Self::WithdrawalLimitExceeded { .. } => "withdrawal_limit_exceeded",
+            Self::MemoTooLong { .. } => "memo_too_long",
+            Self::TransactionTooLarge { .. } => "transaction_too_large",
+            Self::ReserveLocked { .. } => "reserve_locked",
+            Self::WithdrawalTimelocked { .. } => "withdrawal_timelocked",
+            Self::ReserveInsufficient { .. } => "reserve_insufficient",
+            Self::VaultTokenAccountFrozen { .. } => "vault_token_account_frozen",
+            Self::VaultPaused { .. } => "vault_paused",
+            Self::TokenAccountFrozen { .. } => "token_account_frozen",
+            Self::FreezableMintNotAcknowledged { .. } => "freezable_mint_not_acknowledged",
+            Self::AccountingUnderflow { .. } => "accounting_underflow",
+            Self::MintMismatch { .. } => "mint_mismatch",
+            Self::TokenAccountMintMismatch { .. } => "token_account_mint_mismatch",
+            Self::SourceMintMismatch { .. } => "source_mint_mismatch",
+            Self::InsufficientBalance { .. } => "insufficient_balance",
+            Self::FeePathBroken { .. } => "fee_path_broken",
+            Self::TokenAccountMissing { .. } => "token_account_missing",
+            Self::FeePoolExhausted { .. } => "fee_pool_exhausted",
+            Self::SelfTransfer { .. } => "self_transfer",
+            Self::KeyClusterPolicyViolation { .. } => "key_cluster_policy_violation",
+            Self::FeatureRequired { .. } => "feature_required",
+            Self::ClientTooOld { .. } => "client_too_old",
+            Self::SpendingLimitExceeded { .. } => "spending_limit_exceeded",
+            Self::UnsafeTransaction { .. } => "unsafe_transaction",
+            Self::AmbiguousVaultName { .. } => "ambiguous_vault_name",
+            Self::DeadlineExceeded { .. } => "deadline_exceeded",
+            Self::Rpc { .. } => "rpc",
+            Self::ProgramError { .. } => "program_error",
+            Self::Other { .. } => crate::json::OTHER_ERROR,
+        }
+    }
+
+    /// The typed failure behind `err`: the first `TokenVaultError` in its
+    /// chain, else a failed RPC request or program error, else `Other` with
+    /// the whole message
+    pub fn of(err: &anyhow::Error) -> Self {
+        if let Some(typed) = err.chain().find_map(|cause| cause.downcast_ref::<Self>()) {
+            return typed.clone();
+        }
+        match err
+            .chain()
+            .find_map(|cause| cause.downcast_ref::<ClientError>())
+        {
+            Some(client) => Self::of_client_error(client),
+            None => Self::Other {
+                message: format!("{:#}", err),
+            },
+        }
+    }
+
+    fn of_client_error(err: &ClientError) -> Self {
+        let logs = match &err.kind {
+            ClientErrorKind::RpcError(RpcError::RpcResponseError {
+                data: RpcResponseErrorData::SendTransactionPreflightFailure(simulation),
+                ..
+            }) => simulation.logs.clone().unwrap_or_default(),
+            _ => Vec::new(),
+        };
+        if let Some((code, name)) = parse_program_error(&logs) {
+            return Self::ProgramError {
+                code,
+                name: Some(name),
+                logs,
+            };
+        }
+        match err.kind.get_transaction_error() {
+            Some(TransactionError::InstructionError(_, InstructionError::Custom(code))) => {
+                Self::ProgramError {
+                    code,
+                    name: None,
+                    logs,
+                }
+            }
+            _ => Self::Rpc {
+                message: err.to_string(),
+            },
+        }
+    }
+}
+
+impl From<anyhow::Error> for TokenVaultError {
+    fn from(err: anyhow::Error) -> Self {
+        Self::of(&err)
+    }
+}
+
+/// Code and name of the Anchor error in a transaction's logs, from its
+/// "Error Code: <name>. Error Number: <code>." line
+pub fn parse_program_error(logs: &[String]) -> Option<(u32, String)> {
+    logs.iter().find_map(|line| {
+        let (_, rest) = line.split_once("Error Code: ")?;
+        let (name, rest) = rest.split_once(". Error Number: ")?;
+        let (code, _) = rest.split_once('.')?;
+        Some((code.parse().ok()?, name.to_string()))
+    })
+}
+
+fn program_error_name(name: &Option<String>) -> String {
+    name.as_ref()
+        .map(|name| format!(" ({})", name))
+        .unwrap_or_default()
+}
+
+fn sent_suffix(signature: &Option<Signature>) -> String {
+    signature
+        .map(|signature| format!(" (transaction {} was sent)", signature))
+        .unwrap_or_default()
+}
+
+/// Who can thaw a frozen token account of a mint with `freeze_authority`
+pub(crate) fn thaw_by(freeze_authority: &Option<Pubkey>) -> String {
+    match freeze_authority {
+        Some(authority) => format!("only the mint's freeze authority {} can thaw it", authority),
+        None => "the mint's freeze authority was revoked, so it can never be thawed".to_string(),
+    }
+}
+
+fn list_matches(matches: &[(String, Pubkey)]) -> String {
+    matches
+        .iter()
+        .map(|(name, address)| format!("{} ({})", name, address))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
@@ -0,0 +1,289 @@
+//! Rent and fee estimates without a round trip per question. Rent per byte,
+//! the base signature fee and recent priority fees change rarely, so the
+//! client keeps them in `EstimateTables`, refreshed on a TTL or explicitly,
+//! and saved to a file so the next process starts from the last values seen.
+//! Planning answers from the tables, and when the cluster cannot be reached
+//! falls back to them however old they are; every estimate names the slot
+//! its tables were read at.
+
+use anchor_client::solana_sdk::{
+    account::from_account, message::Message, program_pack::Pack, pubkey::Pubkey, rent::Rent,
+    signature::Signer, sysvar,
+};
+use anchor_spl::token::spl_token::state::Account as TokenAccount;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::logging;
+use crate::{DeadlinePhase, TokenVaultClient};
+
+/// How long tables stay fresh unless `with_estimates_ttl` says otherwise
+pub const DEFAULT_ESTIMATES_TTL: Duration = Duration::from_secs(60 * 60);
+
+/// Size of a vault account with the longest name the program accepts
+pub const VAULT_ACCOUNT_LEN: usize = 8 // discriminator
+    + 32 + 32 + 32 // authority, token_mint, fee_collector
+    + 2 + 8 + 8 + 8 // fee_percentage, withdrawal_timelock, withdrawal_limit, total_deposited
+    + (4 + crate::token_vault::pda::MAX_VAULT_NAME_LEN) // name
+    + 1 + 1 // bump, immutable
+    + 8 + 8 // deposit window
+    + 8 + 1 // dust_threshold, waive_dust_fee
+    + 2 + 8 + 8 + 8 // reserve_bps, reserve_timelock, last_reserve_release, total_reserved
+    + 1 // accounting_anomaly
+    + 1 // paused
+    + 8; // accrued_fees
+
+/// Recent priority fees in micro-lamports per compute unit
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PriorityFees {
+    pub p50: u64,
+    pub p75: u64,
+    pub p90: u64,
+}
+
+impl PriorityFees {
+    /// Percentiles of `samples`, one per recent slot; all zero without samples
+    pub fn of(samples: &[u64]) -> Self {
+        let mut sorted = samples.to_vec();
+        sorted.sort_unstable();
+        let percentile = |p: usize| match sorted.len() {
+            0 => 0,
+            len => sorted[(len - 1) * p / 100],
+        };
+        Self {
+            p50: percentile(50),
+            p75: percentile(75),
+            p90: percentile(90),
+        }
+    }
+}
+
+/// What rent and fees cost on one cluster, as read at `slot`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EstimateTables {
+    /// RPC URL the tables were read from; tables from another cluster are
+    /// never used
+    pub cluster: String,
+    pub slot: u64,
+    /// Unix time of the refresh, by the local clock
+    pub refreshed_at: i64,
+    pub lamports_per_byte_year: u64,
+    pub exemption_threshold: f64,
+    pub lamports_per_signature: u64,
+    pub priority_fees: PriorityFees,
+}
+
+impl EstimateTables {
+    /// Lamports an account of `data_len` bytes needs to be rent exempt
+    pub fn rent_exempt_minimum(&self, data_len: usize) -> u64 {
+        Rent {
+            lamports_per_byte_year: self.lamports_per_byte_year,
+            exemption_threshold: self.exemption_threshold,
+            ..Rent::default()
+        }
+        .minimum_balance(data_len)
+    }
+
+    /// Base fee of a transaction with `signatures` signatures, before any
+    /// priority fee
+    pub fn fee_for(&self, signatures: u64) -> u64 {
+        self.lamports_per_signature * signatures
+    }
+
+    /// Whether the tables are at least `ttl` old at unix time `now`
+    pub fn is_stale(&self, now: i64, ttl: Duration) -> bool {
+        now.saturating_sub(self.refreshed_at) >= ttl.as_secs() as i64
+    }
+
+    /// `rent` and `fee` as a `CostEstimate` from these tables
+    pub fn cost(&self, rent_lamports: u64, fee_lamports: u64, stale: bool) -> CostEstimate {
+        CostEstimate {
+            rent_lamports,
+            fee_lamports,
+            as_of_slot: self.slot,
+            stale,
+        }
+    }
+
+    /// The tables saved at `path` for `cluster`; `None` if there are none
+    /// or they were read from another cluster
+    pub fn load(path: &Path, cluster: &str) -> Result<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let tables: Self = serde_json::from_str(&fs::read_to_string(path)?)
+            .map_err(|err| anyhow!("Failed to read estimates {}: {}", path.display(), err))?;
+        Ok((tables.cluster == cluster).then_some(tables))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        // Write-then-rename so a kill mid-write never leaves truncated tables
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, serde_json::to_vec_pretty(self)?)?;
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+}
+
+impl fmt::Display for EstimateTables {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Estimates as of slot {} ({})", self.slot, self.cluster)?;
+        writeln!(
+            f,
+            "  rent: {} lamports per byte-year, exempt at {} years",
+            self.lamports_per_byte_year, self.exemption_threshold
+        )?;
+        writeln!(
+            f,
+            "  base fee: {} lamports per signature",
+            self.lamports_per_signature
+        )?;
+        write!(
+            f,
+            "  priority fees: p50 {}, p75 {}, p90 {} micro-lamports per compute unit",
+            self.priority_fees.p50, self.priority_fees.p75, self.priority_fees.p90
+        )
+    }
+}
+
+/// Lamports an operation costs beyond the tokens it moves
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CostEstimate {
+    pub rent_lamports: u64,
+    /// Base transaction fees, without priority fees
+    pub fee_lamports: u64,
+    /// Slot the tables behind the estimate were read at
+    pub as_of_slot: u64,
+    /// The tables were past their TTL and could not be refreshed
+    pub stale: bool,
+}
+
+impl CostEstimate {
+    pub fn total_lamports(&self) -> u64 {
+        self.rent_lamports + self.fee_lamports
+    }
+}
+
+impl fmt::Display for CostEstimate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "~{} lamports (rent {}, fees {}; estimates as of slot {}",
+            self.total_lamports(),
+            self.rent_lamports,
+            self.fee_lamports,
+            self.as_of_slot
+        )?;
+        if self.stale {
+            write!(f, ", STALE")?;
+        }
+        write!(f, ")")
+    }
+}
+
+fn unix_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+impl TokenVaultClient {
+    /// Start from `tables` instead of reading them on first use
+    pub fn with_estimate_tables(&mut self, tables: EstimateTables) -> &mut Self {
+        *self.estimates.lock().unwrap() = Some(tables);
+        self
+    }
+
+    /// Refresh the tables once they are `ttl` old; `DEFAULT_ESTIMATES_TTL`
+    /// otherwise
+    pub fn with_estimates_ttl(&mut self, ttl: Duration) -> &mut Self {
+        self.estimates_ttl = ttl;
+        self
+    }
+
+    /// Keep the tables at `path`: load them from there now, if they were
+    /// saved for this cluster, and save them after every refresh
+    pub fn with_estimates_path(&mut self, path: PathBuf) -> Result<&mut Self> {
+        if let Some(tables) = EstimateTables::load(&path, &self.write_rpc_url())? {
+            *self.estimates.lock().unwrap() = Some(tables);
+        }
+        self.estimates_path = Some(path);
+        Ok(self)
+    }
+
+    /// Read the tables from the cluster, replacing the cached ones and
+    /// saving them if a path was given
+    pub fn refresh_estimates(&self) -> Result<EstimateTables> {
+        let rpc = self.rpc_within(self.deadline(), DeadlinePhase::Fetch)?;
+        let slot = rpc.get_slot()?;
+        let rent: Rent = from_account(&rpc.get_account(&sysvar::rent::ID)?)
+            .ok_or_else(|| anyhow!("Failed to decode Rent sysvar"))?;
+        // A message with no instructions still needs the payer's signature
+        let message = Message::new(&[], Some(&self.payer.pubkey()));
+        let lamports_per_signature = rpc.get_fee_for_message(&message)?;
+        let samples: Vec<u64> = rpc
+            .get_recent_prioritization_fees(&[])?
+            .into_iter()
+            .map(|fee| fee.prioritization_fee)
+            .collect();
+
+        let tables = EstimateTables {
+            cluster: self.write_rpc_url(),
+            slot,
+            refreshed_at: unix_now(),
+            lamports_per_byte_year: rent.lamports_per_byte_year,
+            exemption_threshold: rent.exemption_threshold,
+            lamports_per_signature,
+            priority_fees: PriorityFees::of(&samples),
+        };
+        if let Some(path) = &self.estimates_path {
+            tables.save(path)?;
+        }
+        *self.estimates.lock().unwrap() = Some(tables.clone());
+        Ok(tables)
+    }
+
+    /// The tables, refreshed first if they are past their TTL. When the
+    /// refresh fails, older tables are used anyway and reported stale.
+    /// Returns the tables and whether they are stale.
+    pub fn estimates(&self) -> Result<(EstimateTables, bool)> {
+        let cached = self.estimates.lock().unwrap().clone();
+        match cached {
+            Some(tables) if !tables.is_stale(unix_now(), self.estimates_ttl) => Ok((tables, false)),
+            cached => match (self.refresh_estimates(), cached) {
+                (Ok(tables), _) => Ok((tables, false)),
+                (Err(err), Some(tables)) => {
+                    logging::warn!(
+                        slot = tables.slot,
+                        error = %logging::redacted(&err, false),
+                        "could not refresh estimates; using saved ones"
+                    );
+                    Ok((tables, true))
+                }
+                (Err(err), None) => Err(err.context("No estimates saved to fall back on")),
+            },
+        }
+    }
+
+    /// What creating a vault costs `authority`: rent for the vault and its
+    /// two token accounts, and the transaction fee
+    pub fn estimate_init_cost(&self, authority: &Pubkey) -> Result<CostEstimate> {
+        let (tables, stale) = self.estimates()?;
+        let rent = tables.rent_exempt_minimum(VAULT_ACCOUNT_LEN)
+            + 2 * tables.rent_exempt_minimum(TokenAccount::LEN);
+        let signatures = if *authority == self.payer.pubkey() {
+            1
+        } else {
+            2
+        };
+        Ok(tables.cost(rent, tables.fee_for(signatures), stale))
+    }
+}
@@ -0,0 +1,182 @@
+//! What the program recorded, read back from a transaction's logs. Anchor
+//! writes each `emit!` as a `Program data: <base64>` line; the first eight
+//! bytes name the event. Lines that do not decode, or name an event this
+//! client does not know, are skipped with a warning, so one odd line does
+//! not hide the events around it.
+
+use anchor_client::solana_client::rpc_config::RpcTransactionConfig;
+use anchor_client::solana_sdk::{pubkey::Pubkey, signature::Signature};
+use anchor_lang::{AnchorDeserialize, Discriminator};
+use anyhow::Result;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::{Deserialize, Serialize};
+use solana_transaction_status::{EncodedConfirmedTransactionWithStatusMeta, UiTransactionEncoding};
+
+use crate::history::{program_logs, EVENT_LOG_PREFIX};
+use crate::logging;
+use crate::memo::transaction_memos;
+use crate::token_vault::events;
+use crate::{DeadlinePhase, TokenVaultClient};
+
+/// A deposit as the program recorded it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DepositEvent {
+    #[serde(with = "crate::serde_utils::pubkey")]
+    pub vault: Pubkey,
+    #[serde(with = "crate::serde_utils::pubkey")]
+    pub depositor: Pubkey,
+    pub amount: u64,
+    /// Block time of the transaction, when the cluster reported one
+    pub timestamp: Option<i64>,
+    /// Memo the transaction carried; `None` when there was none or only the
+    /// logs were read
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memo: Option<String>,
+}
+
+/// A withdrawal as the program recorded it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WithdrawEvent {
+    #[serde(with = "crate::serde_utils::pubkey")]
+    pub vault: Pubkey,
+    #[serde(with = "crate::serde_utils::pubkey")]
+    pub withdrawer: Pubkey,
+    /// Owner of the destination token account
+    #[serde(with = "crate::serde_utils::pubkey")]
+    pub recipient: Pubkey,
+    pub amount: u64,
+    pub fee: u64,
+    /// Block time of the transaction, when the cluster reported one
+    pub timestamp: Option<i64>,
+    /// Memo the transaction carried, as for `DepositEvent`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memo: Option<String>,
+}
+
+/// An event the token vault program logged
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum VaultEvent {
+    Deposit(DepositEvent),
+    Withdraw(WithdrawEvent),
+}
+
+impl VaultEvent {
+    /// The vault the event happened in
+    pub fn vault(&self) -> Pubkey {
+        match self {
+            Self::Deposit(event) => event.vault,
+            Self::Withdraw(event) => event.vault,
+        }
+    }
+
+    /// The memo of the transaction the event was logged in, if known
+    pub fn memo(&self) -> Option<&str> {
+        match self {
+            Self::Deposit(event) => event.memo.as_deref(),
+            Self::Withdraw(event) => event.memo.as_deref(),
+        }
+    }
+
+    fn set_memo(&mut self, memo: Option<String>) {
+        match self {
+            Self::Deposit(event) => event.memo = memo,
+            Self::Withdraw(event) => event.memo = memo,
+        }
+    }
+}
+
+/// Deposit and withdraw events in `logs`, in the order they were logged,
+/// stamped with the transaction's `timestamp`. Accounting anomalies are
+/// left to `VaultTransaction::accounting_anomalies`; other lines that do
+/// not decode to a known event are skipped with a warning.
+pub fn parse_events(logs: &[String], timestamp: Option<i64>) -> Vec<VaultEvent> {
+    logs.iter()
+        .filter_map(|log| log.strip_prefix(EVENT_LOG_PREFIX))
+        .filter_map(|data| match parse_event(data, timestamp) {
+            Ok(event) => event,
+            Err(reason) => {
+                logging::warn!(data, reason, "skipped event log");
+                None
+            }
+        })
+        .collect()
+}
+
+fn parse_event(data: &str, timestamp: Option<i64>) -> Result<Option<VaultEvent>, &'static str> {
+    let bytes = STANDARD.decode(data).map_err(|_| "not base64")?;
+    if bytes.len() < 8 {
+        return Err("shorter than a discriminator");
+    }
+    let (discriminator, body) = bytes.split_at(8);
+    let event = match discriminator {
+        d if d == events::DepositEvent::DISCRIMINATOR => {
+            let event = decode::<events::DepositEvent>(body)?;
+            VaultEvent::Deposit(DepositEvent {
+                vault: event.vault,
+                depositor: event.depositor,
+                amount: event.amount,
+                timestamp,
+                memo: None,
+            })
+        }
+        d if d == events::WithdrawEvent::DISCRIMINATOR => {
+            let event = decode::<events::WithdrawEvent>(body)?;
+            VaultEvent::Withdraw(WithdrawEvent {
+                vault: event.vault,
+                withdrawer: event.withdrawer,
+                recipient: event.recipient,
+                amount: event.amount,
+                fee: event.fee,
+                timestamp,
+                memo: None,
+            })
+        }
+        d if d == events::AccountingAnomaly::DISCRIMINATOR => return Ok(None),
+        _ => return Err("unknown discriminator"),
+    };
+    Ok(Some(event))
+}
+
+fn decode<E: AnchorDeserialize>(body: &[u8]) -> Result<E, &'static str> {
+    E::try_from_slice(body).map_err(|_| "malformed event")
+}
+
+/// Events `program_id` logged in `transaction`, leaving out lines of the
+/// programs it called. Each carries the transaction's memo; several memos
+/// are joined with newlines.
+pub fn transaction_events(
+    program_id: &Pubkey,
+    transaction: &EncodedConfirmedTransactionWithStatusMeta,
+) -> Vec<VaultEvent> {
+    let logs = transaction
+        .transaction
+        .meta
+        .as_ref()
+        .and_then(|meta| Option::<Vec<String>>::from(meta.log_messages.clone()))
+        .unwrap_or_default();
+    let logs = program_logs(logs, &program_id.to_string());
+    let memos = transaction_memos(transaction);
+    let memo = (!memos.is_empty()).then(|| memos.join("\n"));
+    let mut events = parse_events(&logs, transaction.block_time);
+    for event in &mut events {
+        event.set_memo(memo.clone());
+    }
+    events
+}
+
+impl TokenVaultClient {
+    /// Events the token vault program logged in the confirmed transaction
+    /// `signature`, with the transaction's memo if it carried one
+    pub fn parse_events_from_signature(&self, signature: &Signature) -> Result<Vec<VaultEvent>> {
+        let deadline = self.deadline();
+        let rpc = self.rpc_within(deadline, DeadlinePhase::Fetch)?;
+        let config = RpcTransactionConfig {
+            encoding: Some(UiTransactionEncoding::Base64),
+            commitment: Some(rpc.commitment()),
+            max_supported_transaction_version: Some(0),
+        };
+        let transaction = rpc.get_transaction_with_config(signature, config)?;
+        Ok(transaction_events(&self.program.id(), &transaction))
+    }
+}
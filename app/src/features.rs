@@ -0,0 +1,265 @@
+use anchor_client::solana_client::rpc_client::RpcClient;
+use anchor_client::solana_sdk::{pubkey::Pubkey, system_program};
+use anchor_lang::AccountDeserialize;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::logging;
+use crate::signer::{AsSigner, VaultSigner};
+use crate::token_vault::{accounts, instruction, pda, state::FeatureFlags};
+use crate::{Deadline, DeadlinePhase, Operation, OperationKind, TokenVaultClient, TokenVaultError};
+
+/// Version of this client build, compared against the program's minimum
+pub const CLIENT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// A program feature that changes which transactions are valid
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Feature {
+    DepositWindows,
+    DustPolicy,
+    LamportSweep,
+    Allowlist,
+    /// Withdrawals must be requested and executed in separate transactions
+    TwoStepWithdrawals,
+    /// Deposits carve out a share into a timelocked insurance reserve
+    InsuranceReserve,
+}
+
+impl Feature {
+    pub const ALL: [Feature; 6] = [
+        Feature::DepositWindows,
+        Feature::DustPolicy,
+        Feature::LamportSweep,
+        Feature::Allowlist,
+        Feature::TwoStepWithdrawals,
+        Feature::InsuranceReserve,
+    ];
+
+    /// Position of the feature in the on-chain bitset
+    pub fn bit(self) -> u64 {
+        1 << self as u64
+    }
+}
+
+impl fmt::Display for Feature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::DepositWindows => "deposit-windows",
+            Self::DustPolicy => "dust-policy",
+            Self::LamportSweep => "lamport-sweep",
+            Self::Allowlist => "allowlist",
+            Self::TwoStepWithdrawals => "two-step-withdrawals",
+            Self::InsuranceReserve => "insurance-reserve",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Set of features, stored as the program's bitset
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct FeatureSet(pub u64);
+
+impl FeatureSet {
+    pub const EMPTY: FeatureSet = FeatureSet(0);
+
+    /// Everything this client build knows how to build transactions for
+    pub const SUPPORTED: FeatureSet = FeatureSet(
+        1 << Feature::DepositWindows as u64
+            | 1 << Feature::DustPolicy as u64
+            | 1 << Feature::LamportSweep as u64
+            | 1 << Feature::Allowlist as u64
+            | 1 << Feature::InsuranceReserve as u64,
+    );
+
+    pub fn of(features: &[Feature]) -> Self {
+        Self(
+            features
+                .iter()
+                .fold(0, |bits, feature| bits | feature.bit()),
+        )
+    }
+
+    pub fn contains(&self, feature: Feature) -> bool {
+        self.0 & feature.bit() != 0
+    }
+
+    pub fn features(&self) -> Vec<Feature> {
+        Feature::ALL
+            .into_iter()
+            .filter(|feature| self.contains(*feature))
+            .collect()
+    }
+
+    /// Set bits no known `Feature` corresponds to
+    pub fn unknown_bits(&self) -> u64 {
+        self.0 & !FeatureSet::of(&Feature::ALL).0
+    }
+}
+
+impl fmt::Display for FeatureSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut names: Vec<_> = self.features().iter().map(Feature::to_string).collect();
+        let unknown = self.unknown_bits();
+        if unknown != 0 {
+            names.push(format!("unknown bits {:#x}", unknown));
+        }
+        if names.is_empty() {
+            write!(f, "none")
+        } else {
+            write!(f, "{}", names.join(", "))
+        }
+    }
+}
+
+/// Feature state published by the program
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProgramFeatures {
+    pub enabled: FeatureSet,
+    pub min_client_version: String,
+}
+
+impl ProgramFeatures {
+    /// Fail with `ClientTooOld` or `FeatureRequired` if a client supporting
+    /// `supported` at `version` would build transactions the program rejects
+    pub fn check(&self, supported: FeatureSet, version: &str) -> Result<(), TokenVaultError> {
+        if !self.min_client_version.is_empty()
+            && parse_version(version) < parse_version(&self.min_client_version)
+        {
+            return Err(TokenVaultError::ClientTooOld {
+                minimum: self.min_client_version.clone(),
+            });
+        }
+        if let Some(feature) = self
+            .enabled
+            .features()
+            .into_iter()
+            .find(|feature| !supported.contains(*feature))
+        {
+            return Err(TokenVaultError::FeatureRequired {
+                feature: feature.to_string(),
+            });
+        }
+        let unknown = self.enabled.unknown_bits();
+        if unknown != 0 {
+            return Err(TokenVaultError::FeatureRequired {
+                feature: format!("bit {}", unknown.trailing_zeros()),
+            });
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for ProgramFeatures {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let minimum = if self.min_client_version.is_empty() {
+            "any"
+        } else {
+            &self.min_client_version
+        };
+        write!(
+            f,
+            "enabled: {}; minimum client version: {}",
+            self.enabled, minimum
+        )
+    }
+}
+
+/// `major.minor.patch` as numbers; missing or non-numeric parts count as 0
+/// and pre-release suffixes are ignored
+fn parse_version(version: &str) -> (u64, u64, u64) {
+    let mut parts = version
+        .split(['-', '+'])
+        .next()
+        .unwrap_or_default()
+        .split('.')
+        .map(|part| part.trim().parse().unwrap_or(0));
+    (
+        parts.next().unwrap_or(0),
+        parts.next().unwrap_or(0),
+        parts.next().unwrap_or(0),
+    )
+}
+
+/// Read the program's feature flags; `None` when the program has not published any
+pub fn fetch_program_features(
+    rpc: &RpcClient,
+    program_id: &Pubkey,
+) -> Result<Option<ProgramFeatures>> {
+    let (address, _) = pda::find_feature_flags_address(program_id);
+    let account = match rpc
+        .get_account_with_commitment(&address, rpc.commitment())?
+        .value
+    {
+        Some(account) => account,
+        None => return Ok(None),
+    };
+    let flags = FeatureFlags::try_deserialize(&mut account.data.as_slice())?;
+    Ok(Some(ProgramFeatures {
+        enabled: FeatureSet(flags.enabled),
+        min_client_version: flags.min_client_version,
+    }))
+}
+
+impl TokenVaultClient {
+    /// Feature state published by the program, if any
+    pub fn get_program_features(&self) -> Result<Option<ProgramFeatures>> {
+        let rpc = self.rpc_within(self.deadline(), DeadlinePhase::Fetch)?;
+        fetch_program_features(&rpc, &self.program.id())
+    }
+
+    /// Refuse to build transactions for a program that enforces features this
+    /// client does not support or requires a newer client
+    pub(crate) fn check_features(&self, deadline: Deadline) -> Result<()> {
+        let rpc = self.rpc_within(deadline, DeadlinePhase::Fetch)?;
+        if let Some(features) = fetch_program_features(&rpc, &self.program.id())? {
+            features.check(FeatureSet::SUPPORTED, CLIENT_VERSION)?;
+        }
+        Ok(())
+    }
+
+    /// Publish the program's feature flags. The first call creates the flags
+    /// account with `admin` as its admin; later calls must be signed by it.
+    pub fn set_feature_flags(
+        &self,
+        admin: &dyn VaultSigner,
+        flags: &ProgramFeatures,
+    ) -> Result<()> {
+        self.check_signer(&admin.pubkey())?;
+        let deadline = self.deadline();
+        let (feature_flags, _) = pda::find_feature_flags_address(&self.program.id());
+
+        let rpc = self.rpc_within(deadline, DeadlinePhase::Fetch)?;
+        if let Some(account) = rpc
+            .get_account_with_commitment(&feature_flags, rpc.commitment())?
+            .value
+        {
+            let current = FeatureFlags::try_deserialize(&mut account.data.as_slice())?;
+            if current.admin != admin.pubkey() {
+                return Err(anyhow!(
+                    "{} is not the feature flags admin {}",
+                    admin.pubkey(),
+                    current.admin
+                ));
+            }
+        }
+
+        let signer = AsSigner(admin);
+        let request = self
+            .vault_request(&[feature_flags])?
+            .accounts(accounts::SetFeatureFlags {
+                admin: admin.pubkey(),
+                feature_flags,
+                system_program: system_program::ID,
+            })
+            .args(instruction::SetFeatureFlags {
+                enabled: flags.enabled.0,
+                min_client_version: flags.min_client_version.clone(),
+            })
+            .signer(&signer);
+        let operation = Operation::new(OperationKind::SetFeatureFlags, admin.pubkey(), None, 0);
+        self.send_operation(operation, request, deadline)?;
+        logging::debug!(flags = %flags, "feature flags set");
+        Ok(())
+    }
+}
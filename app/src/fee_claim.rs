@@ -0,0 +1,87 @@
+//! Withdrawal fees the vault holds for its fee collector. `accrued_fees`
+//! counts them inside the vault token account; `claim_fees` moves them to
+//! the collector's associated token account.
+
+use anchor_spl::associated_token::get_associated_token_address;
+use anchor_spl::token;
+use anyhow::{anyhow, Context, Result};
+
+use crate::logging;
+use crate::signer::{AsSigner, VaultSigner};
+use crate::token_vault::{accounts, instruction, pda};
+use crate::{CheckedAgainst, Operation, OperationKind, TokenVaultClient};
+
+impl TokenVaultClient {
+    /// Fees the current vault holds for its fee collector
+    pub fn get_accrued_fees(&self) -> Result<u64> {
+        let vault = self.current_vault()?;
+        let deadline = self.deadline();
+        let (vault_data, _) = self.fetch_vault_state(vault, deadline, false)?;
+        Ok(vault_data.accrued_fees)
+    }
+
+    /// Move the current vault's accrued fees to `fee_collector`'s associated
+    /// token account, which must exist; `repair_fee_path` creates it. Returns
+    /// the amount claimed, which is 0, with nothing sent, when no fees have
+    /// accrued.
+    pub fn claim_fees(&self, fee_collector: &dyn VaultSigner) -> Result<u64> {
+        self.check_signer(&fee_collector.pubkey())?;
+        let deadline = self.deadline();
+        self.check_features(deadline)?;
+        let vault = self.current_vault()?;
+
+        let (vault_data, provenance) = self.fetch_vault_state(vault, deadline, true)?;
+        if vault_data.fee_collector != fee_collector.pubkey() {
+            return Err(anyhow!(
+                "{} is not the fee collector of vault {}",
+                fee_collector.pubkey(),
+                vault
+            ))
+            .context(CheckedAgainst(provenance));
+        }
+        let amount = vault_data.accrued_fees;
+        if amount == 0 {
+            logging::info!(
+                vault = %logging::log_key(&vault, self.log_redaction),
+                "no fees to claim"
+            );
+            return Ok(0);
+        }
+
+        let (vault_token_account, _) =
+            pda::find_vault_token_account_address(&self.program.id(), &vault);
+        let fee_collector_token_account =
+            get_associated_token_address(&vault_data.fee_collector, &vault_data.token_mint);
+        self.fetch_fee_path(vault, &vault_data, deadline)?.check()?;
+        self.check_not_frozen(
+            vault,
+            &vault_data,
+            Some(fee_collector_token_account),
+            deadline,
+        )?;
+
+        let signer = AsSigner(fee_collector);
+        let request = self
+            .vault_request(&[vault])?
+            .accounts(accounts::ClaimFees {
+                fee_collector: fee_collector.pubkey(),
+                vault,
+                vault_token_account,
+                fee_collector_token_account,
+                token_program: token::ID,
+            })
+            .args(instruction::ClaimFees {})
+            .signer(&signer);
+        let operation = Operation::new(
+            OperationKind::ClaimFees,
+            fee_collector.pubkey(),
+            Some(vault),
+            amount,
+        );
+        self.send_operation(operation, request, deadline)?;
+        self.invalidate(&vault);
+        self.invalidate(&vault_token_account);
+        self.invalidate(&fee_collector_token_account);
+        Ok(amount)
+    }
+}
@@ -0,0 +1,239 @@
+use anchor_client::solana_sdk::{
+    account::Account, message::Message, program_pack::Pack, pubkey::Pubkey, signature::Signature,
+};
+use anchor_spl::associated_token::get_associated_token_address;
+use anchor_spl::token::{self, spl_token::state::Account as TokenAccount};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::logging;
+use crate::plan::format_sol;
+use crate::signer::{AsSigner, VaultSigner};
+use crate::support::hash_pubkey;
+use crate::token_vault::state::Vault;
+use crate::{Deadline, DeadlinePhase, Operation, OperationKind, TokenVaultClient, TokenVaultError};
+
+/// State of the token account a vault's accrued fees are claimed into
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FeePathStatus {
+    Ok,
+    /// Nothing at the fee collector's associated token address, typically
+    /// because the account was closed to reclaim its rent
+    Missing,
+    /// Something at the address that is not the fee collector's token
+    /// account for the vault's mint; creating the account cannot fix it
+    Invalid,
+}
+
+impl FeePathStatus {
+    /// Status of `account`, found at the associated token address of
+    /// `fee_collector` for `mint`
+    pub fn of(account: Option<&Account>, fee_collector: &Pubkey, mint: &Pubkey) -> Self {
+        let Some(account) = account else {
+            return Self::Missing;
+        };
+        if account.owner != token::ID {
+            return Self::Invalid;
+        }
+        match TokenAccount::unpack(&account.data) {
+            Ok(token_account)
+                if token_account.owner == *fee_collector && token_account.mint == *mint =>
+            {
+                Self::Ok
+            }
+            _ => Self::Invalid,
+        }
+    }
+}
+
+impl fmt::Display for FeePathStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let status = match self {
+            Self::Ok => "ok",
+            Self::Missing => "missing",
+            Self::Invalid => "invalid",
+        };
+        write!(f, "{}", status)
+    }
+}
+
+/// Where a vault's accrued fees go when claimed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeePath {
+    #[serde(with = "crate::serde_utils::pubkey")]
+    pub vault: Pubkey,
+    #[serde(with = "crate::serde_utils::pubkey")]
+    pub fee_collector: Pubkey,
+    #[serde(with = "crate::serde_utils::pubkey")]
+    pub mint: Pubkey,
+    /// The fee collector's associated token account for the mint
+    #[serde(with = "crate::serde_utils::pubkey")]
+    pub account: Pubkey,
+    pub status: FeePathStatus,
+}
+
+impl FeePath {
+    fn of(vault: Pubkey, vault_data: &Vault, account: Option<&Account>) -> Self {
+        Self {
+            vault,
+            fee_collector: vault_data.fee_collector,
+            mint: vault_data.token_mint,
+            account: get_associated_token_address(
+                &vault_data.fee_collector,
+                &vault_data.token_mint,
+            ),
+            status: FeePathStatus::of(account, &vault_data.fee_collector, &vault_data.token_mint),
+        }
+    }
+}
+
+impl FeePath {
+    /// Pre-flight for a fee claim: refuse it while the fees have nowhere to go
+    pub(crate) fn check(&self) -> Result<()> {
+        if self.status != FeePathStatus::Ok {
+            return Err(TokenVaultError::FeePathBroken {
+                vault: self.vault,
+                account: self.account,
+                status: self.status,
+            }
+            .into());
+        }
+        Ok(())
+    }
+}
+
+/// What `repair_fee_path` found, created and paid
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RepairReport {
+    pub before: FeePath,
+    pub after: FeePathStatus,
+    /// Set when the fee collector's token account was created
+    #[serde(with = "crate::serde_utils::option_signature")]
+    pub signature: Option<Signature>,
+    /// Rent deposited into the created account, paid by the repair's payer
+    pub rent_lamports: u64,
+    /// Transaction fee, paid by the client's payer
+    pub fee_lamports: u64,
+}
+
+impl RepairReport {
+    pub fn created(&self) -> bool {
+        self.signature.is_some()
+    }
+}
+
+impl fmt::Display for RepairReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let before = &self.before;
+        writeln!(f, "Vault: {}", before.vault)?;
+        writeln!(f, "Fee collector: {}", before.fee_collector)?;
+        writeln!(f, "Fee account: {} ({})", before.account, before.status)?;
+        match self.signature {
+            Some(signature) => {
+                writeln!(f, "Created fee account: {}", before.account)?;
+                writeln!(f, "  Rent: {}", format_sol(self.rent_lamports))?;
+                writeln!(f, "  Fee: {}", format_sol(self.fee_lamports))?;
+                writeln!(f, "  Signature: {}", signature)?;
+            }
+            None => writeln!(f, "Nothing to create")?,
+        }
+        write!(f, "Fee path: {}", self.after)
+    }
+}
+
+impl TokenVaultClient {
+    /// Check the token account the current vault's fees are claimed into
+    pub fn verify_fee_path(&self) -> Result<FeePath> {
+        let vault = self.current_vault()?;
+        let deadline = self.deadline();
+        let (vault_data, _) = self.fetch_vault_state(vault, deadline, false)?;
+        self.fetch_fee_path(vault, &vault_data, deadline)
+    }
+
+    pub(crate) fn fetch_fee_path(
+        &self,
+        vault: Pubkey,
+        vault_data: &Vault,
+        deadline: Deadline,
+    ) -> Result<FeePath> {
+        let account =
+            get_associated_token_address(&vault_data.fee_collector, &vault_data.token_mint);
+        let rpc = self.rpc_within(deadline, DeadlinePhase::Fetch)?;
+        let account = logging::get_account(&rpc, &account, rpc.commitment())?.value;
+        Ok(FeePath::of(vault, vault_data, account.as_ref()))
+    }
+
+    /// Recreate the current vault's missing fee collector token account so
+    /// fee claims work again; `payer` funds its rent. Anyone can run it:
+    /// the account belongs to the fee collector no matter who pays.
+    pub fn repair_fee_path(&self, payer: &dyn VaultSigner) -> Result<RepairReport> {
+        let vault = self.current_vault()?;
+        self.check_signer(&payer.pubkey())?;
+        let deadline = self.deadline();
+        let (vault_data, _) = self.fetch_vault_state(vault, deadline, true)?;
+        let before = self.fetch_fee_path(vault, &vault_data, deadline)?;
+        match before.status {
+            FeePathStatus::Ok => {
+                return Ok(RepairReport {
+                    after: before.status,
+                    before,
+                    signature: None,
+                    rent_lamports: 0,
+                    fee_lamports: 0,
+                })
+            }
+            FeePathStatus::Invalid => {
+                return Err(anyhow!(
+                    "Fee account {} exists but is not the fee collector's token account for \
+                     mint {}; only the program can move the vault's fees elsewhere",
+                    before.account,
+                    before.mint
+                ))
+            }
+            FeePathStatus::Missing => {}
+        }
+
+        let request = self.vault_request(&[before.account])?.instruction(
+            spl_associated_token_account::instruction::create_associated_token_account_idempotent(
+                &payer.pubkey(),
+                &before.fee_collector,
+                &before.mint,
+                &token::ID,
+            ),
+        );
+        let signer = AsSigner(payer);
+        let request = request.signer(&signer);
+        let rpc = self.rpc_within(deadline, DeadlinePhase::Fetch)?;
+        let rent_lamports = rpc.get_minimum_balance_for_rent_exemption(TokenAccount::LEN)?;
+        let message = Message::new(&request.instructions()?, Some(&self.program.payer()));
+        let fee_lamports = rpc.get_fee_for_message(&message)?;
+
+        let operation =
+            Operation::new(OperationKind::RepairFeePath, payer.pubkey(), Some(vault), 0);
+        let signature = self.send_operation(operation, request, deadline)?;
+        self.invalidate(&before.account);
+
+        let after = self.fetch_fee_path(vault, &vault_data, deadline)?.status;
+        if after != FeePathStatus::Ok {
+            return Err(anyhow!(
+                "Created fee account {} in {}, but it still checks as {}",
+                before.account,
+                signature,
+                after
+            ));
+        }
+        logging::debug!(
+            account = %hash_pubkey(&before.account),
+            "recreated fee account"
+        );
+        Ok(RepairReport {
+            before,
+            after,
+            signature: Some(signature),
+            rent_lamports,
+            fee_lamports,
+        })
+    }
+}
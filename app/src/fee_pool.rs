@@ -0,0 +1,413 @@
+//! Pay transaction fees from a pool of pre-funded keypairs instead of the
+//! client's payer. Long devnet runs would otherwise drain a single payer,
+//! and airdrops are rate limited. The pool rotates to the next payer when
+//! one drops below a floor. When every payer is below it, the current payer
+//! is topped up from a reserve account with a system transfer.
+
+use anchor_client::solana_sdk::{
+    native_token::LAMPORTS_PER_SOL,
+    pubkey::Pubkey,
+    signature::{read_keypair_file, write_keypair_file, Keypair, Signature, Signer},
+    system_instruction,
+    transaction::Transaction,
+};
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use crate::logging;
+use crate::plan::format_sol;
+use crate::request::VaultRequest;
+use crate::shared::SharedKeypair;
+// `VaultSigner` is named by path: in scope, its `pubkey` would clash with
+// `Signer::pubkey` on the pool's keypairs
+use crate::signer::AsSigner;
+use crate::{Deadline, DeadlinePhase, Operation, OperationKind, TokenVaultClient, TokenVaultError};
+
+/// Balance below which a payer is rotated out
+pub const DEFAULT_FLOOR_LAMPORTS: u64 = LAMPORTS_PER_SOL / 20;
+/// Balance a payer is topped up to from the reserve
+pub const DEFAULT_TOP_UP_LAMPORTS: u64 = LAMPORTS_PER_SOL;
+
+/// Which payer the next transaction uses
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PayerChoice {
+    /// The payer at this index has at least the floor
+    Use(usize),
+    /// Every payer is below the floor; top up the one at `payer` by
+    /// `lamports` from the reserve, then use it
+    TopUp { payer: usize, lamports: u64 },
+}
+
+/// What one payer of the pool has spent
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PayerSpend {
+    #[serde(with = "crate::serde_utils::pubkey")]
+    pub payer: Pubkey,
+    /// Transactions the payer paid for
+    pub operations: u64,
+    /// Lamports the payer's balance went down by, as observed between uses
+    pub spent_lamports: u64,
+    /// Lamports the reserve sent the payer
+    pub topped_up_lamports: u64,
+    /// Last balance seen, if the payer has been checked yet
+    pub balance_lamports: Option<u64>,
+}
+
+impl fmt::Display for PayerSpend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} operations, spent {}, topped up {}",
+            self.payer,
+            self.operations,
+            format_sol(self.spent_lamports),
+            format_sol(self.topped_up_lamports)
+        )?;
+        if let Some(balance) = self.balance_lamports {
+            write!(f, ", balance {}", format_sol(balance))?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+struct PoolState {
+    current: usize,
+    spend: Vec<PayerSpend>,
+    /// Lamports the reserve has sent so far
+    topped_up: u64,
+}
+
+/// Pre-funded keypairs that take turns paying transaction fees; see
+/// `TokenVaultClient::with_fee_pool`
+pub struct FeeReservePool {
+    payers: Vec<Arc<Keypair>>,
+    reserve: Option<Arc<Keypair>>,
+    floor_lamports: u64,
+    top_up_lamports: u64,
+    budget_lamports: Option<u64>,
+    state: Mutex<PoolState>,
+}
+
+impl FeeReservePool {
+    /// A pool of `payers`, rotating away from any below `floor_lamports`.
+    /// Without a reserve, the pool is exhausted once every payer is below it.
+    pub fn new(payers: Vec<Keypair>, floor_lamports: u64) -> Result<Self> {
+        if payers.is_empty() {
+            return Err(anyhow!("A fee pool needs at least one payer"));
+        }
+        let spend = payers
+            .iter()
+            .map(|payer| PayerSpend {
+                payer: payer.pubkey(),
+                operations: 0,
+                spent_lamports: 0,
+                topped_up_lamports: 0,
+                balance_lamports: None,
+            })
+            .collect();
+        Ok(Self {
+            payers: payers.into_iter().map(Arc::new).collect(),
+            reserve: None,
+            floor_lamports,
+            top_up_lamports: DEFAULT_TOP_UP_LAMPORTS.max(floor_lamports),
+            budget_lamports: None,
+            state: Mutex::new(PoolState {
+                current: 0,
+                spend,
+                topped_up: 0,
+            }),
+        })
+    }
+
+    /// Top payers up to `top_up_lamports` from `reserve` when every payer is
+    /// below the floor
+    pub fn with_reserve(&mut self, reserve: Keypair, top_up_lamports: u64) -> Result<&mut Self> {
+        if top_up_lamports <= self.floor_lamports {
+            return Err(anyhow!(
+                "Payers must be topped up above the floor of {}, not to {}",
+                format_sol(self.floor_lamports),
+                format_sol(top_up_lamports)
+            ));
+        }
+        self.reserve = Some(Arc::new(reserve));
+        self.top_up_lamports = top_up_lamports;
+        Ok(self)
+    }
+
+    /// Send at most `budget_lamports` from the reserve over the pool's life
+    pub fn with_top_up_budget(&mut self, budget_lamports: u64) -> &mut Self {
+        self.budget_lamports = Some(budget_lamports);
+        self
+    }
+
+    /// Payers saved by `save_payers` in `dir`, in name order
+    pub fn load_payers(dir: &Path) -> Result<Vec<Keypair>> {
+        let mut paths = std::fs::read_dir(dir)
+            .with_context(|| format!("Failed to read fee pool directory {}", dir.display()))?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<std::io::Result<Vec<_>>>()?;
+        paths.retain(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "json")
+        });
+        paths.sort();
+        paths
+            .iter()
+            .map(|path| {
+                read_keypair_file(path)
+                    .map_err(|err| anyhow!("Failed to read payer {}: {}", path.display(), err))
+            })
+            .collect()
+    }
+
+    /// The first `count` payers in `dir`, generating and saving any missing
+    /// as `payer-<nnn>.json`
+    pub fn save_payers(dir: &Path, count: usize) -> Result<Vec<Keypair>> {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create fee pool directory {}", dir.display()))?;
+        let mut payers = Self::load_payers(dir)?;
+        payers.truncate(count);
+        for index in payers.len()..count {
+            let path = dir.join(format!("payer-{:03}.json", index));
+            if path.exists() {
+                return Err(anyhow!(
+                    "{} exists but sorts after other payers; rename the pool's files",
+                    path.display()
+                ));
+            }
+            let payer = Keypair::new();
+            write_keypair_file(&payer, &path)
+                .map_err(|err| anyhow!("Failed to write payer {}: {}", path.display(), err))?;
+            payers.push(payer);
+        }
+        Ok(payers)
+    }
+
+    pub fn payers(&self) -> Vec<Pubkey> {
+        self.payers.iter().map(|payer| payer.pubkey()).collect()
+    }
+
+    pub fn floor_lamports(&self) -> u64 {
+        self.floor_lamports
+    }
+
+    /// Index of the payer the pool is on
+    pub fn current(&self) -> usize {
+        self.state.lock().unwrap().current
+    }
+
+    /// What each payer has spent, as of the last balances seen
+    pub fn report(&self) -> Vec<PayerSpend> {
+        self.state.lock().unwrap().spend.clone()
+    }
+
+    /// Lamports the reserve has sent so far
+    pub fn topped_up_lamports(&self) -> u64 {
+        self.state.lock().unwrap().topped_up
+    }
+
+    /// Pick the payer for the next transaction, starting at the current one
+    /// and reading balances with `balance`. Each balance read counts what
+    /// the payer spent since the previous one.
+    pub fn choose(&self, mut balance: impl FnMut(&Pubkey) -> Result<u64>) -> Result<PayerChoice> {
+        let start = self.current();
+        let mut start_balance = 0;
+        for step in 0..self.payers.len() {
+            let index = (start + step) % self.payers.len();
+            let lamports = balance(&self.payers[index].pubkey())?;
+            self.observe(index, lamports);
+            if step == 0 {
+                start_balance = lamports;
+            }
+            if lamports >= self.floor_lamports {
+                if step > 0 {
+                    logging::info!(
+                        from = %self.payers[start].pubkey(),
+                        to = %self.payers[index].pubkey(),
+                        "fee payer below the floor; rotating"
+                    );
+                }
+                self.state.lock().unwrap().current = index;
+                return Ok(PayerChoice::Use(index));
+            }
+        }
+
+        let lamports = self.top_up_lamports - start_balance;
+        let within_budget = self
+            .budget_lamports
+            .is_none_or(|budget| self.topped_up_lamports() + lamports <= budget);
+        if self.reserve.is_none() || !within_budget {
+            return Err(TokenVaultError::FeePoolExhausted {
+                payers: self.payers.len(),
+                floor_lamports: self.floor_lamports,
+            }
+            .into());
+        }
+        Ok(PayerChoice::TopUp {
+            payer: start,
+            lamports,
+        })
+    }
+
+    /// Record a balance of the payer at `index`; a drop since the last one
+    /// was spent
+    pub fn observe(&self, index: usize, balance_lamports: u64) {
+        let mut state = self.state.lock().unwrap();
+        let spend = &mut state.spend[index];
+        if let Some(last) = spend.balance_lamports {
+            spend.spent_lamports += last.saturating_sub(balance_lamports);
+        }
+        spend.balance_lamports = Some(balance_lamports);
+    }
+
+    /// Record that the reserve sent `lamports` to the payer at `index`
+    pub fn note_top_up(&self, index: usize, lamports: u64) {
+        let mut state = self.state.lock().unwrap();
+        state.topped_up += lamports;
+        let spend = &mut state.spend[index];
+        spend.topped_up_lamports += lamports;
+        spend.balance_lamports = spend.balance_lamports.map(|balance| balance + lamports);
+    }
+
+    /// Record a transaction paid for by the payer at `index`
+    pub fn note_operation(&self, index: usize) {
+        self.state.lock().unwrap().spend[index].operations += 1;
+    }
+}
+
+/// A payer `fund_fee_payers` checked, and what it sent it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PayerFunding {
+    #[serde(with = "crate::serde_utils::pubkey")]
+    pub payer: Pubkey,
+    pub balance_before: u64,
+    pub transferred: u64,
+}
+
+impl TokenVaultClient {
+    /// Pay every transaction's fee from `pool` instead of the client's
+    /// payer. Rent for accounts the client creates still comes from the
+    /// client's payer.
+    pub fn with_fee_pool(&mut self, pool: Arc<FeeReservePool>) -> &mut Self {
+        self.fee_pool = Some(pool);
+        self
+    }
+
+    /// Per-payer spend of the fee pool, with fresh balances
+    pub fn fee_pool_report(&self) -> Result<Vec<PayerSpend>> {
+        let pool = self
+            .fee_pool
+            .as_ref()
+            .ok_or_else(|| anyhow!("No fee pool set"))?;
+        let rpc = self.rpc_within(self.deadline(), DeadlinePhase::Fetch)?;
+        for (index, payer) in pool.payers().iter().enumerate() {
+            pool.observe(index, rpc.get_balance(payer)?);
+        }
+        Ok(pool.report())
+    }
+
+    /// Bring each of `payers` up to `lamports_each` from `funder` with
+    /// system transfers, in one transaction. Payers already holding that
+    /// much are left alone.
+    pub fn fund_fee_payers(
+        &self,
+        funder: &dyn crate::VaultSigner,
+        payers: &[Pubkey],
+        lamports_each: u64,
+    ) -> Result<(Vec<PayerFunding>, Option<Signature>)> {
+        self.check_signer(&funder.pubkey())?;
+        let deadline = self.deadline();
+        let rpc = self.rpc_within(deadline, DeadlinePhase::Fetch)?;
+        let funding = payers
+            .iter()
+            .map(|payer| {
+                let balance_before = rpc.get_balance(payer)?;
+                Ok(PayerFunding {
+                    payer: *payer,
+                    balance_before,
+                    transferred: lamports_each.saturating_sub(balance_before),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let total: u64 = funding.iter().map(|funding| funding.transferred).sum();
+        if total == 0 {
+            return Ok((funding, None));
+        }
+
+        let mut request = self.vault_request(payers)?;
+        for funding in funding.iter().filter(|funding| funding.transferred > 0) {
+            request = request.instruction(system_instruction::transfer(
+                &funder.pubkey(),
+                &funding.payer,
+                funding.transferred,
+            ));
+        }
+        let signer = AsSigner(funder);
+        let request = request.signer(&signer);
+        let operation = Operation::new(OperationKind::FundFeePayers, funder.pubkey(), None, total);
+        let signature = self.send_operation(operation, request, deadline)?;
+        Ok((funding, Some(signature)))
+    }
+
+    /// Have the fee pool, if any, pay for `request`, topping a payer up from
+    /// the reserve first when every payer is below the floor
+    pub(crate) fn draw_fee_payer<'a>(
+        &'a self,
+        request: VaultRequest<'a>,
+        deadline: Deadline,
+    ) -> Result<VaultRequest<'a>> {
+        let Some(pool) = &self.fee_pool else {
+            return Ok(request);
+        };
+        deadline.check(DeadlinePhase::Fetch, None)?;
+        // Balances right after a write; a replica may not have them yet
+        let rpc = self.write_rpc_until(deadline);
+        let index = match pool.choose(|payer| Ok(rpc.get_balance(payer)?))? {
+            PayerChoice::Use(index) => index,
+            PayerChoice::TopUp { payer, lamports } => {
+                let reserve = pool.reserve.as_ref().expect("top-ups need a reserve");
+                let transaction = Transaction::new_signed_with_payer(
+                    &[system_instruction::transfer(
+                        &reserve.pubkey(),
+                        &pool.payers[payer].pubkey(),
+                        lamports,
+                    )],
+                    Some(&reserve.pubkey()),
+                    &[reserve.as_ref()],
+                    rpc.get_latest_blockhash()?,
+                );
+                let signature = rpc
+                    .send_and_confirm_transaction(&transaction)
+                    .context("Failed to top up the fee payer from the reserve")?;
+                logging::info!(
+                    payer = %pool.payers[payer].pubkey(),
+                    lamports,
+                    %signature,
+                    "topped up fee payer from the reserve"
+                );
+                pool.note_top_up(payer, lamports);
+                payer
+            }
+        };
+        pool.note_operation(index);
+
+        // Instructions may still name the client's payer, for example to
+        // fund a new account, and then it must sign as well
+        let client_payer = self.payer.pubkey();
+        let payer_signs = request.instructions()?.iter().any(|instruction| {
+            instruction
+                .accounts
+                .iter()
+                .any(|meta| meta.is_signer && meta.pubkey == client_payer)
+        });
+        let mut request = request.payer(SharedKeypair::rc(&pool.payers[index]));
+        if payer_signs {
+            request = request.signer(self.payer.as_ref());
+        }
+        Ok(request)
+    }
+}
@@ -0,0 +1,353 @@
+//! Ready-made vaults on a local validator, for integration tests of code
+//! built on this client.
+//!
+//! ```no_run
+//! # use token_vault_client::fixtures::VaultFixture;
+//! let fixture = VaultFixture::builder()
+//!     .fee_bps(50)
+//!     .timelock_secs(3600)
+//!     .users(3)
+//!     .build()?;
+//! fixture.deposit_as(0, 1_000_000)?;
+//! assert_eq!(fixture.balance_of(0)?.raw, fixture.initial_balance() - 1_000_000);
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+//!
+//! Every fixture creates its own payer, mint, vault and users, so fixtures
+//! share no accounts and tests using them can run in parallel. Nothing is
+//! left to tear down beyond dropping the fixture.
+
+use anchor_client::{
+    solana_client::rpc_client::RpcClient,
+    solana_sdk::{
+        commitment_config::CommitmentConfig,
+        instruction::Instruction,
+        native_token::LAMPORTS_PER_SOL,
+        program_pack::Pack,
+        pubkey::Pubkey,
+        signature::{Keypair, Signer},
+        system_instruction,
+    },
+    Cluster,
+};
+use anchor_spl::associated_token::get_associated_token_address;
+use anchor_spl::token::{self, spl_token};
+use anyhow::{anyhow, Context, Result};
+use std::str::FromStr;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::{assembly, token_vault, MintAmount, TokenVaultClient, TxReceipt, VaultConfig};
+
+/// RPC URL or cluster moniker of the validator fixtures run against
+pub const VALIDATOR_ENV: &str = "TOKEN_VAULT_TEST_VALIDATOR";
+
+/// Program ID of the deployed token vault program, when not the default
+pub const PROGRAM_ID_ENV: &str = "TOKEN_VAULT_TEST_PROGRAM_ID";
+
+/// SOL airdropped to each fixture's payer, which funds everything else
+const PAYER_AIRDROP: u64 = 10 * LAMPORTS_PER_SOL;
+
+/// SOL each user gets for transaction fees
+const USER_LAMPORTS: u64 = LAMPORTS_PER_SOL / 10;
+
+/// How long to wait for the payer's airdrop to confirm
+const AIRDROP_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A funded user of a fixture's vault
+pub struct FixtureUser {
+    pub keypair: Keypair,
+    /// The user's associated token account for the fixture's mint
+    pub token_account: Pubkey,
+}
+
+/// Parameters of a `VaultFixture`
+#[derive(Debug, Clone)]
+pub struct VaultFixtureBuilder {
+    fee_bps: u16,
+    timelock_secs: u64,
+    users: usize,
+    decimals: u8,
+    initial_balance: u64,
+    validator: Option<String>,
+    program_id: Option<Pubkey>,
+}
+
+impl Default for VaultFixtureBuilder {
+    fn default() -> Self {
+        Self {
+            fee_bps: 0,
+            timelock_secs: 0,
+            users: 1,
+            decimals: 6,
+            initial_balance: 1_000_000_000,
+            validator: None,
+            program_id: None,
+        }
+    }
+}
+
+impl VaultFixtureBuilder {
+    pub fn fee_bps(mut self, fee_bps: u16) -> Self {
+        self.fee_bps = fee_bps;
+        self
+    }
+
+    pub fn timelock_secs(mut self, timelock_secs: u64) -> Self {
+        self.timelock_secs = timelock_secs;
+        self
+    }
+
+    /// Number of funded users; 1 by default
+    pub fn users(mut self, users: usize) -> Self {
+        self.users = users;
+        self
+    }
+
+    /// Decimals of the fixture's mint; 6 by default
+    pub fn decimals(mut self, decimals: u8) -> Self {
+        self.decimals = decimals;
+        self
+    }
+
+    /// Tokens, in base units, minted to every user
+    pub fn initial_balance(mut self, initial_balance: u64) -> Self {
+        self.initial_balance = initial_balance;
+        self
+    }
+
+    /// Validator to run against instead of `$TOKEN_VAULT_TEST_VALIDATOR`,
+    /// which itself defaults to localnet
+    pub fn validator(mut self, url: &str) -> Self {
+        self.validator = Some(url.to_string());
+        self
+    }
+
+    /// Program to use instead of `$TOKEN_VAULT_TEST_PROGRAM_ID`, which itself
+    /// defaults to the program's declared ID
+    pub fn program_id(mut self, program_id: Pubkey) -> Self {
+        self.program_id = Some(program_id);
+        self
+    }
+
+    /// Create the payer, mint, users and vault on the validator
+    pub fn build(self) -> Result<VaultFixture> {
+        let validator = match self.validator {
+            Some(url) => url,
+            None => std::env::var(VALIDATOR_ENV).unwrap_or_else(|_| "localnet".to_string()),
+        };
+        let cluster = Cluster::from_str(&validator)?;
+        let program_id = match self.program_id {
+            Some(program_id) => program_id,
+            None => match std::env::var(PROGRAM_ID_ENV) {
+                Ok(id) => Pubkey::from_str(&id)
+                    .map_err(|_| anyhow!("Invalid {} '{}'", PROGRAM_ID_ENV, id))?,
+                Err(_) => token_vault::ID,
+            },
+        };
+        let rpc = RpcClient::new_with_commitment(
+            cluster.url().to_string(),
+            CommitmentConfig::confirmed(),
+        );
+
+        let payer = Keypair::new();
+        airdrop(&rpc, &payer.pubkey(), PAYER_AIRDROP)
+            .with_context(|| format!("Failed to fund a fixture payer on {}", cluster.url()))?;
+
+        let mint = Keypair::new();
+        let mint_rent = rpc.get_minimum_balance_for_rent_exemption(spl_token::state::Mint::LEN)?;
+        send(
+            &rpc,
+            &payer,
+            &[&mint],
+            &[
+                system_instruction::create_account(
+                    &payer.pubkey(),
+                    &mint.pubkey(),
+                    mint_rent,
+                    spl_token::state::Mint::LEN as u64,
+                    &token::ID,
+                ),
+                spl_token::instruction::initialize_mint(
+                    &token::ID,
+                    &mint.pubkey(),
+                    &payer.pubkey(),
+                    None,
+                    self.decimals,
+                )?,
+            ],
+        )?;
+
+        let users: Vec<_> = (0..self.users)
+            .map(|_| {
+                let keypair = Keypair::new();
+                let token_account = get_associated_token_address(&keypair.pubkey(), &mint.pubkey());
+                FixtureUser {
+                    keypair,
+                    token_account,
+                }
+            })
+            .collect();
+        for user in &users {
+            send(
+                &rpc,
+                &payer,
+                &[],
+                &[
+                    system_instruction::transfer(
+                        &payer.pubkey(),
+                        &user.keypair.pubkey(),
+                        USER_LAMPORTS,
+                    ),
+                    spl_associated_token_account::instruction::create_associated_token_account(
+                        &payer.pubkey(),
+                        &user.keypair.pubkey(),
+                        &mint.pubkey(),
+                        &token::ID,
+                    ),
+                    spl_token::instruction::mint_to(
+                        &token::ID,
+                        &mint.pubkey(),
+                        &user.token_account,
+                        &payer.pubkey(),
+                        &[],
+                        self.initial_balance,
+                    )?,
+                ],
+            )?;
+        }
+
+        let mut client = TokenVaultClient::new(cluster, payer.insecure_clone(), program_id)?;
+        let config = VaultConfig::builder()
+            .name(format!("fixture-{}", &mint.pubkey().to_string()[..8]))
+            .fee_bps(self.fee_bps)
+            .timelock(Duration::from_secs(self.timelock_secs))
+            .withdrawal_limit(u64::MAX)
+            .build()?;
+        let vault = client
+            .initialize_vault(&payer, mint.pubkey(), &config)?
+            .address;
+        client.set_vault(vault);
+
+        Ok(VaultFixture {
+            client,
+            rpc,
+            authority: payer,
+            mint: mint.pubkey(),
+            decimals: self.decimals,
+            initial_balance: self.initial_balance,
+            vault,
+            users,
+        })
+    }
+}
+
+/// A vault on a validator with a fresh mint and funded users
+pub struct VaultFixture {
+    client: TokenVaultClient,
+    rpc: RpcClient,
+    authority: Keypair,
+    mint: Pubkey,
+    decimals: u8,
+    initial_balance: u64,
+    vault: Pubkey,
+    users: Vec<FixtureUser>,
+}
+
+impl VaultFixture {
+    pub fn builder() -> VaultFixtureBuilder {
+        VaultFixtureBuilder::default()
+    }
+
+    /// Client with the fixture's vault selected, paying with the authority
+    pub fn client(&self) -> &TokenVaultClient {
+        &self.client
+    }
+
+    pub fn client_mut(&mut self) -> &mut TokenVaultClient {
+        &mut self.client
+    }
+
+    /// The vault's authority and fee collector, which also pays for the fixture
+    pub fn authority(&self) -> &Keypair {
+        &self.authority
+    }
+
+    pub fn vault(&self) -> Pubkey {
+        self.vault
+    }
+
+    pub fn mint(&self) -> Pubkey {
+        self.mint
+    }
+
+    pub fn users(&self) -> &[FixtureUser] {
+        &self.users
+    }
+
+    pub fn user(&self, index: usize) -> Result<&FixtureUser> {
+        self.users.get(index).ok_or_else(|| {
+            anyhow!(
+                "Fixture has {} users; there is no user {}",
+                self.users.len(),
+                index
+            )
+        })
+    }
+
+    /// Tokens, in base units, every user started with
+    pub fn initial_balance(&self) -> u64 {
+        self.initial_balance
+    }
+
+    /// An amount of the fixture's mint
+    pub fn amount(&self, raw: u64) -> MintAmount {
+        MintAmount::new(raw, self.mint, self.decimals)
+    }
+
+    pub fn deposit_as(&self, user: usize, amount: u64) -> Result<TxReceipt> {
+        self.client
+            .deposit_amount(&self.user(user)?.keypair, self.amount(amount))
+    }
+
+    pub fn withdraw_as(&self, user: usize, amount: u64) -> Result<TxReceipt> {
+        self.client
+            .withdraw_amount(&self.user(user)?.keypair, self.amount(amount), None)
+    }
+
+    /// Tokens in the user's token account
+    pub fn balance_of(&self, user: usize) -> Result<MintAmount> {
+        let account = self.rpc.get_account(&self.user(user)?.token_account)?;
+        let account = spl_token::state::Account::unpack(&account.data)?;
+        Ok(self.amount(account.amount))
+    }
+}
+
+/// Airdrop `lamports` to `to` and wait for it to confirm
+pub(crate) fn airdrop(rpc: &RpcClient, to: &Pubkey, lamports: u64) -> Result<()> {
+    let signature = rpc.request_airdrop(to, lamports)?;
+    let give_up = Instant::now() + AIRDROP_TIMEOUT;
+    while !rpc.confirm_transaction(&signature)? {
+        if Instant::now() >= give_up {
+            return Err(anyhow!("Airdrop {} did not confirm", signature));
+        }
+        thread::sleep(Duration::from_millis(250));
+    }
+    Ok(())
+}
+
+pub(crate) fn send(
+    rpc: &RpcClient,
+    payer: &Keypair,
+    signers: &[&Keypair],
+    instructions: &[Instruction],
+) -> Result<()> {
+    let signers: Vec<&dyn Signer> = signers
+        .iter()
+        .map(|signer| *signer as &dyn Signer)
+        .collect();
+    let transaction =
+        assembly::sign_transaction(instructions, payer, &signers, rpc.get_latest_blockhash()?)?;
+    rpc.send_and_confirm_transaction(&transaction)?;
+    Ok(())
+}
@@ -0,0 +1,189 @@
+use anchor_client::solana_sdk::{program_option::COption, program_pack::Pack, pubkey::Pubkey};
+use anchor_spl::token::spl_token::state::{Account as TokenAccount, Mint};
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::token_vault::{pda, state::Vault};
+use crate::{Deadline, DeadlinePhase, TokenVaultClient, TokenVaultError};
+
+/// Whether a vault's tokens can be frozen by a third party, and whether they are
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FreezeStatus {
+    /// The mint's freeze authority, which can freeze any of its token accounts
+    #[serde(with = "crate::serde_utils::option_pubkey")]
+    pub freeze_authority: Option<Pubkey>,
+    pub vault_token_account_frozen: bool,
+}
+
+impl FreezeStatus {
+    /// Refuse to operate on a vault whose token account is frozen
+    pub fn check(&self) -> Result<(), TokenVaultError> {
+        if self.vault_token_account_frozen {
+            return Err(TokenVaultError::VaultTokenAccountFrozen {
+                freeze_authority: self.freeze_authority,
+            });
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for FreezeStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.freeze_authority, self.vault_token_account_frozen) {
+            (_, true) => write!(
+                f,
+                "vault token account FROZEN; {}",
+                crate::error::thaw_by(&self.freeze_authority)
+            ),
+            (Some(authority), false) => write!(f, "freezable by {}", authority),
+            (None, false) => write!(f, "mint has no freeze authority"),
+        }
+    }
+}
+
+/// The freeze authority of `mint`, if it has one
+pub fn freeze_authority(mint: &Mint) -> Option<Pubkey> {
+    match mint.freeze_authority {
+        COption::Some(authority) => Some(authority),
+        COption::None => None,
+    }
+}
+
+/// Refuse to create a vault for a freezable mint unless the risk was
+/// acknowledged, when `strict`
+pub fn check_freezable_mint(
+    mint_address: Pubkey,
+    mint: &Mint,
+    strict: bool,
+    acknowledged: bool,
+) -> Result<(), TokenVaultError> {
+    match freeze_authority(mint) {
+        Some(freeze_authority) if strict && !acknowledged => {
+            Err(TokenVaultError::FreezableMintNotAcknowledged {
+                mint: mint_address,
+                freeze_authority,
+            })
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Refuse a transfer the token program would reject because the vault's
+/// token account or the user's (`address`, `account`) is frozen
+pub fn check_token_accounts(
+    mint: &Mint,
+    vault_token_account: &TokenAccount,
+    user_token_account: Option<(Pubkey, &TokenAccount)>,
+) -> Result<(), TokenVaultError> {
+    FreezeStatus {
+        freeze_authority: freeze_authority(mint),
+        vault_token_account_frozen: vault_token_account.is_frozen(),
+    }
+    .check()?;
+    match user_token_account {
+        Some((account, user)) if user.is_frozen() => Err(TokenVaultError::TokenAccountFrozen {
+            account,
+            freeze_authority: freeze_authority(mint),
+        }),
+        _ => Ok(()),
+    }
+}
+
+impl TokenVaultClient {
+    /// Refuse to create vaults for mints with a freeze authority unless
+    /// `VaultConfigBuilder::acknowledge_freezable_mint` was set
+    pub fn with_strict_mint_policy(&mut self, strict: bool) -> &mut Self {
+        self.strict_mint_policy = strict;
+        self
+    }
+
+    /// Who can freeze the current vault's tokens, and whether they have
+    pub fn get_freeze_status(&self) -> Result<FreezeStatus> {
+        let vault = self.current_vault()?;
+        let deadline = self.deadline();
+        let (vault_data, _) = self.fetch_vault_state(vault, deadline, false)?;
+        self.freeze_status(vault, &vault_data, deadline)
+    }
+
+    pub(crate) fn freeze_status(
+        &self,
+        vault: Pubkey,
+        vault_data: &Vault,
+        deadline: Deadline,
+    ) -> Result<FreezeStatus> {
+        let (mint, vault_token_account, _) =
+            self.fetch_token_accounts(vault, vault_data, None, deadline)?;
+        Ok(FreezeStatus {
+            freeze_authority: freeze_authority(&mint),
+            vault_token_account_frozen: vault_token_account.is_frozen(),
+        })
+    }
+
+    /// Pre-flight for a transfer between `vault` and `user_token_account`:
+    /// refuse it when either token account is frozen. A user token account
+    /// that does not exist yet is not checked; returns whether it exists.
+    pub(crate) fn check_not_frozen(
+        &self,
+        vault: Pubkey,
+        vault_data: &Vault,
+        user_token_account: Option<Pubkey>,
+        deadline: Deadline,
+    ) -> Result<bool> {
+        let (mint, vault_token_account, user) =
+            self.fetch_token_accounts(vault, vault_data, user_token_account, deadline)?;
+        let user_exists = user.is_some();
+        let user = user_token_account.zip(user);
+        check_token_accounts(
+            &mint,
+            &vault_token_account,
+            user.as_ref().map(|(address, account)| (*address, account)),
+        )?;
+        Ok(user_exists)
+    }
+
+    /// The vault's mint, its token account and, if it exists, the user's
+    /// token account, in a single request
+    fn fetch_token_accounts(
+        &self,
+        vault: Pubkey,
+        vault_data: &Vault,
+        user_token_account: Option<Pubkey>,
+        deadline: Deadline,
+    ) -> Result<(Mint, TokenAccount, Option<TokenAccount>)> {
+        let (vault_token_account, _) =
+            pda::find_vault_token_account_address(&self.program.id(), &vault);
+        let mut addresses = vec![vault_data.token_mint, vault_token_account];
+        addresses.extend(user_token_account);
+        let rpc = self.rpc_within(deadline, DeadlinePhase::Fetch)?;
+        let mut accounts = rpc.get_multiple_accounts(&addresses)?.into_iter();
+
+        let mint = accounts
+            .next()
+            .flatten()
+            .ok_or_else(|| anyhow!("Mint {} not found", vault_data.token_mint))?;
+        let vault_token = accounts
+            .next()
+            .flatten()
+            .ok_or_else(|| anyhow!("Vault token account {} not found", vault_token_account))?;
+        let user = accounts
+            .next()
+            .flatten()
+            .map(|account| TokenAccount::unpack(&account.data))
+            .transpose()?;
+        Ok((
+            Mint::unpack(&mint.data)?,
+            TokenAccount::unpack(&vault_token.data)?,
+            user,
+        ))
+    }
+
+    /// Read and decode a mint account, bounded by `deadline`
+    pub(crate) fn fetch_mint(&self, mint: Pubkey, deadline: Deadline) -> Result<Mint> {
+        let rpc = self.rpc_within(deadline, DeadlinePhase::Fetch)?;
+        let account = rpc
+            .get_account(&mint)
+            .with_context(|| format!("Failed to fetch mint {}", mint))?;
+        Ok(Mint::unpack(&account.data)?)
+    }
+}
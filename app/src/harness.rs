@@ -0,0 +1,382 @@
+//! A `solana-test-validator` started and stopped by the test that needs it,
+//! with the token vault program deployed. Unlike `fixtures`, which expects a
+//! validator to be running already, a `TestContext` brings its own:
+//!
+//! ```no_run
+//! # use token_vault_client::harness::TestContext;
+//! let ctx = TestContext::start()?;
+//! let user = ctx.funded_user(1_000_000)?;
+//! let client = ctx.client()?;
+//! assert_eq!(ctx.token_balance(&user.token_account)?, 1_000_000);
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+//!
+//! The program is loaded from `$TOKEN_VAULT_TEST_PROGRAM_SO`, by default the
+//! workspace's `target/deploy/token_vault.so` as `anchor build` leaves it.
+//! Each validator gets its own ports and ledger, so contexts can run side by
+//! side. Dropping the context, including while a failed test unwinds, kills
+//! the validator; the ledger is kept after a panic so its log can be read.
+
+use anchor_client::{
+    solana_client::rpc_client::RpcClient,
+    solana_sdk::{
+        commitment_config::CommitmentConfig,
+        native_token::LAMPORTS_PER_SOL,
+        program_pack::Pack,
+        pubkey::Pubkey,
+        signature::{Keypair, Signer},
+        system_instruction,
+    },
+    Cluster,
+};
+use anchor_spl::associated_token::get_associated_token_address;
+use anchor_spl::token::{self, spl_token};
+use anyhow::{anyhow, Context, Result};
+use std::fs;
+use std::net::{TcpListener, UdpSocket};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU16, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::endpoints::custom_cluster;
+use crate::fixtures::{airdrop, send, FixtureUser, PROGRAM_ID_ENV};
+use crate::logging;
+use crate::{token_vault, TokenVaultClient};
+
+/// Path of the program's shared object, when not the workspace build
+pub const PROGRAM_SO_ENV: &str = "TOKEN_VAULT_TEST_PROGRAM_SO";
+
+/// The validator binary, when `solana-test-validator` is not on the `PATH`
+pub const VALIDATOR_BIN_ENV: &str = "TOKEN_VAULT_TEST_VALIDATOR_BIN";
+
+/// SOL airdropped to the context's payer
+const PAYER_AIRDROP: u64 = 100 * LAMPORTS_PER_SOL;
+
+/// SOL each funded user gets for transaction fees
+const USER_LAMPORTS: u64 = LAMPORTS_PER_SOL / 10;
+
+/// How long a validator may take to answer its first request
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Ports each validator takes: RPC, WebSocket, faucet, gossip, then the
+/// range the validator binds its other sockets in
+const PORT_BLOCK: u16 = 32;
+const FIRST_PORT: u16 = 20_000;
+const LAST_PORT: u16 = 60_000;
+
+/// Where the next validator's port search starts, so validators started by
+/// one process do not race for the same block
+static NEXT_PORT: AtomicU16 = AtomicU16::new(FIRST_PORT);
+
+/// A `solana-test-validator` process, killed when dropped
+pub struct TestValidator {
+    process: Child,
+    ledger: PathBuf,
+    rpc_port: u16,
+}
+
+impl TestValidator {
+    /// Start a validator with the program at `program_so` deployed as
+    /// `program_id`, and wait until it answers requests
+    pub fn start(program_id: Pubkey, program_so: &Path) -> Result<Self> {
+        if !program_so.exists() {
+            return Err(anyhow!(
+                "Program {} not found; run `anchor build` or set {}",
+                program_so.display(),
+                PROGRAM_SO_ENV
+            ));
+        }
+        let rpc_port = free_port_block()?;
+        let ledger = std::env::temp_dir().join(format!("token-vault-ledger-{}", rpc_port));
+        let binary = std::env::var(VALIDATOR_BIN_ENV)
+            .unwrap_or_else(|_| "solana-test-validator".to_string());
+        let process = Command::new(&binary)
+            .arg("--reset")
+            .arg("--quiet")
+            .arg("--ledger")
+            .arg(&ledger)
+            .args(["--rpc-port", &rpc_port.to_string()])
+            .args(["--faucet-port", &(rpc_port + 2).to_string()])
+            .args(["--gossip-port", &(rpc_port + 3).to_string()])
+            .args([
+                "--dynamic-port-range",
+                &format!("{}-{}", rpc_port + 4, rpc_port + PORT_BLOCK - 1),
+            ])
+            .arg("--bpf-program")
+            .arg(program_id.to_string())
+            .arg(program_so)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .with_context(|| {
+                format!(
+                    "Failed to start {}; install the Solana CLI or set {}",
+                    binary, VALIDATOR_BIN_ENV
+                )
+            })?;
+
+        // From here on, dropping `validator` stops the process
+        let mut validator = Self {
+            process,
+            ledger,
+            rpc_port,
+        };
+        validator.wait_until_ready()?;
+        Ok(validator)
+    }
+
+    pub fn rpc_url(&self) -> String {
+        format!("http://127.0.0.1:{}", self.rpc_port)
+    }
+
+    pub fn ws_url(&self) -> String {
+        format!("ws://127.0.0.1:{}", self.rpc_port + 1)
+    }
+
+    pub fn cluster(&self) -> Result<Cluster> {
+        custom_cluster(&self.rpc_url(), Some(&self.ws_url()))
+    }
+
+    /// The validator's ledger directory, which holds its `validator.log`
+    pub fn ledger(&self) -> &Path {
+        &self.ledger
+    }
+
+    fn wait_until_ready(&mut self) -> Result<()> {
+        let rpc = RpcClient::new(self.rpc_url());
+        let give_up = Instant::now() + STARTUP_TIMEOUT;
+        loop {
+            if let Some(status) = self.process.try_wait()? {
+                return Err(anyhow!(
+                    "Validator exited with {} during startup; see {}",
+                    status,
+                    self.ledger.join("validator.log").display()
+                ));
+            }
+            if rpc.get_latest_blockhash().is_ok() {
+                return Ok(());
+            }
+            if Instant::now() >= give_up {
+                return Err(anyhow!(
+                    "Validator did not answer on {} within {:?}",
+                    self.rpc_url(),
+                    STARTUP_TIMEOUT
+                ));
+            }
+            thread::sleep(Duration::from_millis(250));
+        }
+    }
+}
+
+impl Drop for TestValidator {
+    fn drop(&mut self) {
+        let _ = self.process.kill();
+        let _ = self.process.wait();
+        if thread::panicking() {
+            logging::warn!(ledger = %self.ledger.display(), "kept the test validator's ledger");
+        } else {
+            let _ = fs::remove_dir_all(&self.ledger);
+        }
+    }
+}
+
+/// The first block of `PORT_BLOCK` ports, from `NEXT_PORT` on, that are all
+/// free for both TCP and UDP; returns its first port
+fn free_port_block() -> Result<u16> {
+    loop {
+        let base = NEXT_PORT.fetch_add(PORT_BLOCK, Ordering::SeqCst);
+        if base > LAST_PORT {
+            return Err(anyhow!(
+                "No free block of {} ports between {} and {}",
+                PORT_BLOCK,
+                FIRST_PORT,
+                LAST_PORT
+            ));
+        }
+        let free = (base..base + PORT_BLOCK).all(|port| {
+            TcpListener::bind(("0.0.0.0", port)).is_ok()
+                && UdpSocket::bind(("0.0.0.0", port)).is_ok()
+        });
+        if free {
+            return Ok(base);
+        }
+    }
+}
+
+/// A fresh validator with the program deployed, a funded payer and a mint
+/// the payer can mint
+pub struct TestContext {
+    rpc: RpcClient,
+    payer: Keypair,
+    mint: Pubkey,
+    decimals: u8,
+    program_id: Pubkey,
+    // Dropped last, after everything talking to it
+    validator: TestValidator,
+}
+
+impl TestContext {
+    /// Start a validator with the program from `$TOKEN_VAULT_TEST_PROGRAM_SO`
+    /// deployed at `$TOKEN_VAULT_TEST_PROGRAM_ID`, or at the program's
+    /// declared ID, and a mint with 6 decimals
+    pub fn start() -> Result<Self> {
+        let program_id = match std::env::var(PROGRAM_ID_ENV) {
+            Ok(id) => {
+                Pubkey::from_str(&id).map_err(|_| anyhow!("Invalid {} '{}'", PROGRAM_ID_ENV, id))?
+            }
+            Err(_) => token_vault::ID,
+        };
+        let program_so = match std::env::var_os(PROGRAM_SO_ENV) {
+            Some(path) => PathBuf::from(path),
+            None => default_program_so(),
+        };
+        Self::start_with(program_id, &program_so, 6)
+    }
+
+    /// Like `start`, with the program and mint decimals given
+    pub fn start_with(program_id: Pubkey, program_so: &Path, decimals: u8) -> Result<Self> {
+        let validator = TestValidator::start(program_id, program_so)?;
+        let rpc =
+            RpcClient::new_with_commitment(validator.rpc_url(), CommitmentConfig::confirmed());
+
+        let payer = Keypair::new();
+        airdrop(&rpc, &payer.pubkey(), PAYER_AIRDROP).context("Failed to fund the test payer")?;
+
+        let mint = Keypair::new();
+        let mint_rent = rpc.get_minimum_balance_for_rent_exemption(spl_token::state::Mint::LEN)?;
+        send(
+            &rpc,
+            &payer,
+            &[&mint],
+            &[
+                system_instruction::create_account(
+                    &payer.pubkey(),
+                    &mint.pubkey(),
+                    mint_rent,
+                    spl_token::state::Mint::LEN as u64,
+                    &token::ID,
+                ),
+                spl_token::instruction::initialize_mint(
+                    &token::ID,
+                    &mint.pubkey(),
+                    &payer.pubkey(),
+                    None,
+                    decimals,
+                )?,
+            ],
+        )?;
+
+        Ok(Self {
+            rpc,
+            payer,
+            mint: mint.pubkey(),
+            decimals,
+            program_id,
+            validator,
+        })
+    }
+
+    pub fn validator(&self) -> &TestValidator {
+        &self.validator
+    }
+
+    pub fn rpc_url(&self) -> String {
+        self.validator.rpc_url()
+    }
+
+    pub fn rpc(&self) -> &RpcClient {
+        &self.rpc
+    }
+
+    /// Funded with SOL, and the mint's mint authority
+    pub fn payer(&self) -> &Keypair {
+        &self.payer
+    }
+
+    pub fn mint(&self) -> Pubkey {
+        self.mint
+    }
+
+    pub fn decimals(&self) -> u8 {
+        self.decimals
+    }
+
+    pub fn program_id(&self) -> Pubkey {
+        self.program_id
+    }
+
+    /// A client of the validator paying with the context's payer, with no
+    /// vault selected
+    pub fn client(&self) -> Result<TokenVaultClient> {
+        TokenVaultClient::new(
+            self.validator.cluster()?,
+            self.payer.insecure_clone(),
+            self.program_id,
+        )
+    }
+
+    /// Create `owner`'s associated token account for the mint, holding
+    /// `amount` base units; returns its address
+    pub fn create_token_account(&self, owner: &Pubkey, amount: u64) -> Result<Pubkey> {
+        let account = get_associated_token_address(owner, &self.mint);
+        send(
+            &self.rpc,
+            &self.payer,
+            &[],
+            &[
+                spl_associated_token_account::instruction::create_associated_token_account(
+                    &self.payer.pubkey(),
+                    owner,
+                    &self.mint,
+                    &token::ID,
+                ),
+                spl_token::instruction::mint_to(
+                    &token::ID,
+                    &self.mint,
+                    &account,
+                    &self.payer.pubkey(),
+                    &[],
+                    amount,
+                )?,
+            ],
+        )?;
+        Ok(account)
+    }
+
+    /// A new keypair with SOL for fees and a token account holding `amount`
+    pub fn funded_user(&self, amount: u64) -> Result<FixtureUser> {
+        let keypair = Keypair::new();
+        send(
+            &self.rpc,
+            &self.payer,
+            &[],
+            &[system_instruction::transfer(
+                &self.payer.pubkey(),
+                &keypair.pubkey(),
+                USER_LAMPORTS,
+            )],
+        )?;
+        let token_account = self.create_token_account(&keypair.pubkey(), amount)?;
+        Ok(FixtureUser {
+            keypair,
+            token_account,
+        })
+    }
+
+    /// Base units held by a token account of any mint
+    pub fn token_balance(&self, account: &Pubkey) -> Result<u64> {
+        let data = self.rpc.get_account_data(account)?;
+        Ok(spl_token::state::Account::unpack(&data)?.amount)
+    }
+}
+
+/// `target/deploy/token_vault.so` in the workspace
+fn default_program_so() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .parent()
+        .expect("the client lives inside the workspace")
+        .join("target/deploy/token_vault.so")
+}
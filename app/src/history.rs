@@ -0,0 +1,275 @@
+use anchor_client::solana_sdk::{message::VersionedMessage, pubkey::Pubkey, signature::Signature};
+use anchor_lang::{AnchorDeserialize, Discriminator};
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::{Deserialize, Serialize};
+use solana_transaction_status::{
+    EncodedConfirmedTransactionWithStatusMeta, EncodedTransaction,
+    EncodedTransactionWithStatusMeta, UiLoadedAddresses,
+};
+use std::str::FromStr;
+
+use crate::token_vault::{
+    events::{AccountingAnomaly, WithdrawEvent},
+    instruction,
+};
+
+/// Prefix of the log line `emit!` writes an event to
+pub(crate) const EVENT_LOG_PREFIX: &str = "Program data: ";
+
+/// The token vault instruction an instruction's data decodes to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum VaultInstructionKind {
+    InitializeVault,
+    Deposit,
+    Withdraw,
+    RequestWithdrawal,
+    ExecuteWithdrawal,
+    CancelWithdrawal,
+    SweepLamports,
+    ReleaseReserve,
+    ClaimFees,
+    AddAllowlistEntry,
+    RemoveAllowlistEntry,
+    Unknown,
+}
+
+impl VaultInstructionKind {
+    /// Identify an instruction from the Anchor discriminator at the start of its data
+    pub fn from_data(data: &[u8]) -> Self {
+        match data.get(..8) {
+            Some(d) if d == instruction::InitializeVault::DISCRIMINATOR => Self::InitializeVault,
+            Some(d) if d == instruction::Deposit::DISCRIMINATOR => Self::Deposit,
+            Some(d) if d == instruction::Withdraw::DISCRIMINATOR => Self::Withdraw,
+            Some(d) if d == instruction::RequestWithdrawal::DISCRIMINATOR => {
+                Self::RequestWithdrawal
+            }
+            Some(d) if d == instruction::ExecuteWithdrawal::DISCRIMINATOR => {
+                Self::ExecuteWithdrawal
+            }
+            Some(d) if d == instruction::CancelWithdrawal::DISCRIMINATOR => Self::CancelWithdrawal,
+            Some(d) if d == instruction::SweepLamports::DISCRIMINATOR => Self::SweepLamports,
+            Some(d) if d == instruction::ReleaseReserve::DISCRIMINATOR => Self::ReleaseReserve,
+            Some(d) if d == instruction::ClaimFees::DISCRIMINATOR => Self::ClaimFees,
+            Some(d) if d == instruction::AddAllowlistEntry::DISCRIMINATOR => {
+                Self::AddAllowlistEntry
+            }
+            Some(d) if d == instruction::RemoveAllowlistEntry::DISCRIMINATOR => {
+                Self::RemoveAllowlistEntry
+            }
+            _ => Self::Unknown,
+        }
+    }
+}
+
+/// A single token vault instruction found in a transaction
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VaultInstruction {
+    pub kind: VaultInstructionKind,
+    /// Position of the instruction in the transaction's top-level instruction list
+    pub index: usize,
+    pub accounts: Vec<String>,
+    pub data: Vec<u8>,
+}
+
+/// A confirmed transaction that touched the token vault program
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VaultTransaction {
+    #[serde(with = "crate::serde_utils::signature")]
+    pub signature: Signature,
+    pub slot: u64,
+    pub block_time: Option<i64>,
+    pub succeeded: bool,
+    pub instructions: Vec<VaultInstruction>,
+    pub logs: Vec<String>,
+}
+
+impl VaultTransaction {
+    /// Withdraw events the program logged, including the true recipient of each withdrawal
+    pub fn withdraw_events(&self) -> Vec<WithdrawEvent> {
+        self.events()
+    }
+
+    /// Counters the program clamped to zero instead of letting them underflow
+    pub fn accounting_anomalies(&self) -> Vec<AccountingAnomaly> {
+        self.events()
+    }
+
+    /// Bytes of instruction data, account keys and log lines held
+    pub fn retained_bytes(&self) -> usize {
+        let instructions: usize = self
+            .instructions
+            .iter()
+            .map(|ix| ix.data.len() + ix.accounts.iter().map(String::len).sum::<usize>())
+            .sum();
+        instructions + self.logs.iter().map(String::len).sum::<usize>()
+    }
+
+    fn events<E: AnchorDeserialize + Discriminator>(&self) -> Vec<E> {
+        events(&self.logs)
+    }
+}
+
+/// Events of type `E` logged as `Program data: <base64>` in `logs`
+pub(crate) fn events<E: AnchorDeserialize + Discriminator>(logs: &[String]) -> Vec<E> {
+    logs.iter()
+        .filter_map(|log| log.strip_prefix(EVENT_LOG_PREFIX))
+        .filter_map(|data| STANDARD.decode(data).ok())
+        .filter(|data| data.starts_with(&E::DISCRIMINATOR))
+        .filter_map(|data| E::try_from_slice(&data[8..]).ok())
+        .collect()
+}
+
+/// Fetched transactions larger than this keep only the log lines of the
+/// token vault program; see `decode_transaction_within`
+pub const DEFAULT_MAX_TRANSACTION_BYTES: usize = 64 * 1024;
+
+/// What decoding one transaction fetched and what it kept
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DecodeStats {
+    /// Encoded transaction plus log lines, as the RPC returned them
+    pub fetched_bytes: usize,
+    /// Instruction data, account keys and log lines of the decoded transaction
+    pub retained_bytes: usize,
+    /// The transaction was over the size cap and other programs' logs were dropped
+    pub trimmed: bool,
+}
+
+/// Decode a fetched transaction, keeping only instructions addressed to `program_id`
+pub fn decode_transaction(
+    program_id: &Pubkey,
+    signature: Signature,
+    fetched: EncodedConfirmedTransactionWithStatusMeta,
+) -> Result<VaultTransaction> {
+    decode_transaction_within(program_id, signature, fetched, usize::MAX)
+        .map(|(transaction, _)| transaction)
+}
+
+/// Decode a fetched transaction, keeping only instructions addressed to
+/// `program_id`. Instructions are taken one at a time and other programs'
+/// are dropped without copying their data or resolving their accounts.
+/// Over `max_transaction_bytes`, only the log lines `program_id` wrote
+/// itself are kept, which is all its events need.
+pub fn decode_transaction_within(
+    program_id: &Pubkey,
+    signature: Signature,
+    fetched: EncodedConfirmedTransactionWithStatusMeta,
+    max_transaction_bytes: usize,
+) -> Result<(VaultTransaction, DecodeStats)> {
+    let EncodedConfirmedTransactionWithStatusMeta {
+        slot,
+        transaction:
+            EncodedTransactionWithStatusMeta {
+                transaction: encoded,
+                meta,
+                ..
+            },
+        block_time,
+    } = fetched;
+    let encoded_bytes = match &encoded {
+        EncodedTransaction::LegacyBinary(blob) | EncodedTransaction::Binary(blob, _) => blob.len(),
+        EncodedTransaction::Json(_) | EncodedTransaction::Accounts(_) => 0,
+    };
+    let transaction = encoded
+        .decode()
+        .ok_or_else(|| anyhow!("Transaction {} uses an unsupported encoding", signature))?;
+    drop(encoded);
+
+    let succeeded = meta.as_ref().map(|m| m.err.is_none()).unwrap_or(false);
+    let (loaded, logs) = match meta {
+        Some(meta) => (
+            Option::<UiLoadedAddresses>::from(meta.loaded_addresses).unwrap_or_default(),
+            Option::<Vec<String>>::from(meta.log_messages).unwrap_or_default(),
+        ),
+        None => Default::default(),
+    };
+
+    // Static keys first, then any keys loaded from address lookup tables
+    let (static_keys, instructions) = match transaction.message {
+        VersionedMessage::Legacy(message) => (message.account_keys, message.instructions),
+        VersionedMessage::V0(message) => (message.account_keys, message.instructions),
+    };
+    let program_key = program_id.to_string();
+    let key = |index: usize| match static_keys.get(index) {
+        Some(key) => Some(key.to_string()),
+        None => loaded_key(&loaded, index - static_keys.len()).cloned(),
+    };
+    let instructions: Vec<VaultInstruction> = instructions
+        .into_iter()
+        .enumerate()
+        // Program ids are never loaded from lookup tables
+        .filter(|(_, ix)| static_keys.get(ix.program_id_index as usize) == Some(program_id))
+        .map(|(index, ix)| VaultInstruction {
+            kind: VaultInstructionKind::from_data(&ix.data),
+            index,
+            accounts: ix
+                .accounts
+                .iter()
+                .filter_map(|i| key(*i as usize))
+                .collect(),
+            data: ix.data,
+        })
+        .collect();
+
+    let fetched_bytes = encoded_bytes + logs.iter().map(String::len).sum::<usize>();
+    let trimmed = fetched_bytes > max_transaction_bytes;
+    let logs = match trimmed {
+        true => program_logs(logs, &program_key),
+        false => logs,
+    };
+    let transaction = VaultTransaction {
+        signature,
+        slot,
+        block_time,
+        succeeded,
+        instructions,
+        logs,
+    };
+    let stats = DecodeStats {
+        fetched_bytes,
+        retained_bytes: transaction.retained_bytes(),
+        trimmed,
+    };
+    Ok((transaction, stats))
+}
+
+fn loaded_key(loaded: &UiLoadedAddresses, index: usize) -> Option<&String> {
+    match index.checked_sub(loaded.writable.len()) {
+        None => loaded.writable.get(index),
+        Some(readonly) => loaded.readonly.get(readonly),
+    }
+}
+
+/// The log lines `program_key` wrote while it was executing, including its
+/// own invoke and result lines but not those of programs it called
+pub fn program_logs(logs: Vec<String>, program_key: &str) -> Vec<String> {
+    // Whether each program on the invocation stack is ours
+    let mut stack: Vec<bool> = Vec::new();
+    logs.into_iter()
+        .filter(|line| {
+            let mut words = line.split(' ');
+            let (Some("Program"), Some(id), Some(verb)) =
+                (words.next(), words.next(), words.next())
+            else {
+                return stack.last().copied().unwrap_or(false);
+            };
+            // `Program log:`, `Program data:` and `Program return:` lines
+            if id.ends_with(':') {
+                return stack.last().copied().unwrap_or(false);
+            }
+            match verb {
+                "invoke" => {
+                    stack.push(id == program_key);
+                    id == program_key
+                }
+                "success" | "failed:" => stack.pop().unwrap_or(false),
+                _ => stack.last().copied().unwrap_or(false),
+            }
+        })
+        .collect()
+}
+
+/// Parse a signature string returned by the RPC
+pub(crate) fn parse_signature(signature: &str) -> Result<Signature> {
+    Signature::from_str(signature).map_err(|e| anyhow!("Invalid signature {}: {}", signature, e))
+}
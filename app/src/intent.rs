@@ -0,0 +1,799 @@
+use anchor_client::solana_sdk::{
+    program_pack::Pack, pubkey::Pubkey, system_program, transaction::Transaction,
+};
+use anchor_lang::{AccountDeserialize, AnchorDeserialize};
+use anchor_spl::associated_token::get_associated_token_address;
+use anchor_spl::token::{self, spl_token};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use thiserror::Error;
+
+use crate::dust::WithdrawalQuote;
+use crate::history::VaultInstructionKind;
+use crate::token_vault::{
+    instruction, pda,
+    state::{Vault, WithdrawalRequest},
+};
+use crate::{DeadlinePhase, TokenVaultClient, TokenVaultError};
+
+/// Why a transaction built elsewhere is unsafe to sign
+#[derive(Debug, Clone, PartialEq, Eq, Error, Serialize, Deserialize)]
+pub enum IntentViolation {
+    #[error("instruction data does not match any token vault instruction")]
+    UnknownInstruction,
+    #[error("instruction is malformed: {reason}")]
+    Malformed { reason: String },
+    #[error("{account} is expected to sign but the transaction does not require its signature")]
+    MissingSigner { account: Pubkey },
+    #[error("vault {vault} does not exist")]
+    VaultNotFound { vault: Pubkey },
+    #[error("vault address {actual} does not match the derived address {expected}")]
+    VaultAddressMismatch { expected: Pubkey, actual: Pubkey },
+    #[error("vault token account {actual} is not the vault's token account {expected}")]
+    VaultTokenAccountMismatch { expected: Pubkey, actual: Pubkey },
+    #[error("reserve token account {actual} is not the vault's reserve account {expected}")]
+    ReserveTokenAccountMismatch { expected: Pubkey, actual: Pubkey },
+    #[error("token program {actual} is not the SPL token program")]
+    UnexpectedTokenProgram { actual: Pubkey },
+    #[error(
+        "fee collector account {actual} is not the vault fee collector's token account {expected}"
+    )]
+    FeeCollectorMismatch { expected: Pubkey, actual: Pubkey },
+    #[error("token account {account} is not a {mint} account owned by signer {signer}")]
+    NotOwnedBySigner {
+        account: Pubkey,
+        signer: Pubkey,
+        mint: Pubkey,
+    },
+    #[error("{signer} is not the authority {authority} of the vault")]
+    NotVaultAuthority { signer: Pubkey, authority: Pubkey },
+    #[error("{signer} is not the fee collector {fee_collector} of the vault")]
+    NotFeeCollector {
+        signer: Pubkey,
+        fee_collector: Pubkey,
+    },
+    #[error("sweep destination {destination} is not a system account")]
+    DestinationNotSystemAccount { destination: Pubkey },
+    #[error("allowlist entry {actual} is not the derived entry address {expected}")]
+    AllowlistEntryMismatch { expected: Pubkey, actual: Pubkey },
+    #[error("position {actual} is not the signer's position {expected}")]
+    PositionMismatch { expected: Pubkey, actual: Pubkey },
+    #[error("withdrawal request {actual} is not the signer's request {expected}")]
+    WithdrawalRequestMismatch { expected: Pubkey, actual: Pubkey },
+    #[error("withdrawal is not allowed: {reason}")]
+    WithdrawalRejected { reason: String },
+}
+
+/// One token vault action a transaction would authorize
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum VerifiedAction {
+    InitializeVault {
+        vault: Pubkey,
+        authority: Pubkey,
+        token_mint: Pubkey,
+        name: String,
+    },
+    Deposit {
+        vault: Pubkey,
+        depositor: Pubkey,
+        source: Pubkey,
+        amount: u64,
+    },
+    Withdraw {
+        vault: Pubkey,
+        withdrawer: Pubkey,
+        destination: Pubkey,
+        amount: u64,
+        fee: u64,
+        net_amount: u64,
+        fee_collector: Pubkey,
+    },
+    RequestWithdrawal {
+        vault: Pubkey,
+        withdrawer: Pubkey,
+        amount: u64,
+    },
+    CancelWithdrawal {
+        vault: Pubkey,
+        withdrawer: Pubkey,
+        withdrawal_request: Pubkey,
+    },
+    SweepLamports {
+        vault: Pubkey,
+        authority: Pubkey,
+        destination: Pubkey,
+    },
+    ReleaseReserve {
+        vault: Pubkey,
+        authority: Pubkey,
+        destination: Pubkey,
+        amount: u64,
+    },
+    ClaimFees {
+        vault: Pubkey,
+        fee_collector: Pubkey,
+        destination: Pubkey,
+        amount: u64,
+    },
+    AddAllowlistEntry {
+        vault: Pubkey,
+        authority: Pubkey,
+        wallet: Pubkey,
+    },
+    RemoveAllowlistEntry {
+        vault: Pubkey,
+        authority: Pubkey,
+        allowlist_entry: Pubkey,
+    },
+}
+
+impl fmt::Display for VerifiedAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InitializeVault {
+                vault,
+                authority,
+                token_mint,
+                name,
+            } => write!(
+                f,
+                "create vault '{}' ({}) for mint {} owned by {}",
+                name, vault, token_mint, authority
+            ),
+            Self::Deposit {
+                vault,
+                depositor,
+                source,
+                amount,
+            } => write!(
+                f,
+                "{} deposits {} from {} into vault {}",
+                depositor, amount, source, vault
+            ),
+            Self::Withdraw {
+                vault,
+                withdrawer,
+                destination,
+                amount,
+                fee,
+                net_amount,
+                fee_collector,
+            } => write!(
+                f,
+                "{} withdraws {} from vault {}: {} to {}, fee {} accrues to {}",
+                withdrawer, amount, vault, net_amount, destination, fee, fee_collector
+            ),
+            Self::RequestWithdrawal {
+                vault,
+                withdrawer,
+                amount,
+            } => write!(
+                f,
+                "{} requests to withdraw {} from vault {}",
+                withdrawer, amount, vault
+            ),
+            Self::CancelWithdrawal {
+                vault,
+                withdrawer,
+                withdrawal_request,
+            } => write!(
+                f,
+                "{} cancels withdrawal request {} of vault {}",
+                withdrawer, withdrawal_request, vault
+            ),
+            Self::SweepLamports {
+                vault,
+                authority,
+                destination,
+            } => write!(
+                f,
+                "{} sweeps stranded lamports of vault {} to {}",
+                authority, vault, destination
+            ),
+            Self::ReleaseReserve {
+                vault,
+                authority,
+                destination,
+                amount,
+            } => write!(
+                f,
+                "{} releases {} from the reserve of vault {} to {}",
+                authority, amount, vault, destination
+            ),
+            Self::ClaimFees {
+                vault,
+                fee_collector,
+                destination,
+                amount,
+            } => write!(
+                f,
+                "{} claims {} in accrued fees of vault {} to {}",
+                fee_collector, amount, vault, destination
+            ),
+            Self::AddAllowlistEntry {
+                vault,
+                authority,
+                wallet,
+            } => write!(f, "{} approves {} for vault {}", authority, wallet, vault),
+            Self::RemoveAllowlistEntry {
+                vault,
+                authority,
+                allowlist_entry,
+            } => write!(
+                f,
+                "{} closes allowlist entry {} of vault {}",
+                authority, allowlist_entry, vault
+            ),
+        }
+    }
+}
+
+/// Everything signing a transaction would authorize, after verification
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VerifiedIntent {
+    pub fee_payer: Option<Pubkey>,
+    pub actions: Vec<VerifiedAction>,
+    /// Programs other than the token vault that the transaction also invokes;
+    /// these are not verified and deserve a look of their own
+    pub other_programs: Vec<Pubkey>,
+}
+
+impl fmt::Display for VerifiedIntent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(fee_payer) = self.fee_payer {
+            writeln!(f, "fee payer: {}", fee_payer)?;
+        }
+        for action in &self.actions {
+            writeln!(f, "- {}", action)?;
+        }
+        for program in &self.other_programs {
+            writeln!(f, "! also invokes unverified program {}", program)?;
+        }
+        Ok(())
+    }
+}
+
+/// On-chain state the verifier needs; implemented over RPC by the client
+pub trait AccountLookup {
+    fn vault(&self, address: &Pubkey) -> Result<Option<Vault>>;
+    /// `(owner, mint)` of an SPL token account
+    fn token_account(&self, address: &Pubkey) -> Result<Option<(Pubkey, Pubkey)>>;
+    /// Owning program of any account
+    fn account_owner(&self, address: &Pubkey) -> Result<Option<Pubkey>>;
+    /// A pending withdrawal request
+    fn withdrawal_request(&self, address: &Pubkey) -> Result<Option<WithdrawalRequest>>;
+}
+
+impl TokenVaultClient {
+    /// Decode and check every token vault instruction in a transaction built
+    /// elsewhere before signing it: derived addresses, the stored fee
+    /// collector, and that tokens only move between accounts of the signer
+    pub fn verify_unsigned_transaction(&self, transaction: &Transaction) -> Result<VerifiedIntent> {
+        let lookup = RpcLookup { client: self };
+        verify_transaction(&self.program.id(), transaction, &lookup)
+    }
+}
+
+struct RpcLookup<'a> {
+    client: &'a TokenVaultClient,
+}
+
+impl RpcLookup<'_> {
+    fn account(
+        &self,
+        address: &Pubkey,
+    ) -> Result<Option<anchor_client::solana_sdk::account::Account>> {
+        let rpc = self
+            .client
+            .rpc_within(self.client.deadline(), DeadlinePhase::Fetch)?;
+        Ok(rpc
+            .get_account_with_commitment(address, rpc.commitment())?
+            .value)
+    }
+}
+
+impl AccountLookup for RpcLookup<'_> {
+    fn vault(&self, address: &Pubkey) -> Result<Option<Vault>> {
+        match self.account(address)? {
+            Some(account) => Ok(Vault::try_deserialize(&mut account.data.as_slice()).ok()),
+            None => Ok(None),
+        }
+    }
+
+    fn token_account(&self, address: &Pubkey) -> Result<Option<(Pubkey, Pubkey)>> {
+        match self.account(address)? {
+            Some(account) if account.owner == token::ID => {
+                Ok(spl_token::state::Account::unpack(&account.data)
+                    .ok()
+                    .map(|state| (state.owner, state.mint)))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    fn account_owner(&self, address: &Pubkey) -> Result<Option<Pubkey>> {
+        Ok(self.account(address)?.map(|account| account.owner))
+    }
+
+    fn withdrawal_request(&self, address: &Pubkey) -> Result<Option<WithdrawalRequest>> {
+        match self.account(address)? {
+            Some(account) => {
+                Ok(WithdrawalRequest::try_deserialize(&mut account.data.as_slice()).ok())
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+/// Verify `transaction` against the state served by `lookup`
+pub fn verify_transaction(
+    program_id: &Pubkey,
+    transaction: &Transaction,
+    lookup: &dyn AccountLookup,
+) -> Result<VerifiedIntent> {
+    let message = &transaction.message;
+    let mut intent = VerifiedIntent {
+        fee_payer: message.account_keys.first().copied(),
+        actions: Vec::new(),
+        other_programs: Vec::new(),
+    };
+
+    for (index, ix) in message.instructions.iter().enumerate() {
+        let program = message.account_keys[ix.program_id_index as usize];
+        if program != *program_id {
+            if !intent.other_programs.contains(&program) {
+                intent.other_programs.push(program);
+            }
+            continue;
+        }
+
+        let accounts: Vec<Pubkey> = ix
+            .accounts
+            .iter()
+            .map(|&i| message.account_keys[i as usize])
+            .collect();
+        let verified = match accounts.first() {
+            Some(&signer) if !message.is_signer(ix.accounts[0] as usize) => {
+                Err(IntentViolation::MissingSigner { account: signer }.into())
+            }
+            _ => verify_instruction(program_id, &ix.data, &accounts, lookup),
+        };
+        match verified {
+            Ok(action) => intent.actions.push(action),
+            Err(Rejection::Violation(violation)) => {
+                return Err(TokenVaultError::UnsafeTransaction {
+                    instruction: index,
+                    violation,
+                }
+                .into())
+            }
+            Err(Rejection::Lookup(err)) => return Err(err),
+        }
+    }
+
+    Ok(intent)
+}
+
+/// Why verifying an instruction stopped: a violation, or failing to read state
+enum Rejection {
+    Violation(IntentViolation),
+    Lookup(anyhow::Error),
+}
+
+impl From<IntentViolation> for Rejection {
+    fn from(violation: IntentViolation) -> Self {
+        Self::Violation(violation)
+    }
+}
+
+impl From<anyhow::Error> for Rejection {
+    fn from(err: anyhow::Error) -> Self {
+        Self::Lookup(err)
+    }
+}
+
+fn decode<T: AnchorDeserialize>(args: &[u8]) -> Result<T, IntentViolation> {
+    T::try_from_slice(args).map_err(|_| IntentViolation::Malformed {
+        reason: "arguments do not decode".to_string(),
+    })
+}
+
+fn verify_instruction(
+    program_id: &Pubkey,
+    data: &[u8],
+    accounts: &[Pubkey],
+    lookup: &dyn AccountLookup,
+) -> Result<VerifiedAction, Rejection> {
+    let kind = VaultInstructionKind::from_data(data);
+    let expected_accounts = match kind {
+        VaultInstructionKind::InitializeVault => 8,
+        VaultInstructionKind::Deposit => 8,
+        VaultInstructionKind::Withdraw => 6,
+        VaultInstructionKind::RequestWithdrawal => 4,
+        VaultInstructionKind::ExecuteWithdrawal => 7,
+        VaultInstructionKind::CancelWithdrawal => 3,
+        VaultInstructionKind::SweepLamports => 5,
+        VaultInstructionKind::ReleaseReserve => 5,
+        VaultInstructionKind::ClaimFees => 5,
+        VaultInstructionKind::AddAllowlistEntry => 4,
+        VaultInstructionKind::RemoveAllowlistEntry => 3,
+        VaultInstructionKind::Unknown => return Err(IntentViolation::UnknownInstruction.into()),
+    };
+    if accounts.len() < expected_accounts {
+        return Err(IntentViolation::Malformed {
+            reason: format!(
+                "{} accounts, expected {}",
+                accounts.len(),
+                expected_accounts
+            ),
+        }
+        .into());
+    }
+    let args = &data[8..];
+    let (signer, vault) = (accounts[0], accounts[1]);
+
+    if kind == VaultInstructionKind::InitializeVault {
+        let args: instruction::InitializeVault = decode(args)?;
+        let token_mint = accounts[4];
+        let (expected, _) =
+            pda::try_find_vault_address(program_id, &signer, &token_mint, &args.name).ok_or_else(
+                || IntentViolation::Malformed {
+                    reason: "vault name is too long".to_string(),
+                },
+            )?;
+        if vault != expected {
+            return Err(IntentViolation::VaultAddressMismatch {
+                expected,
+                actual: vault,
+            }
+            .into());
+        }
+        check_vault_accounts(program_id, &vault, accounts[2], accounts[5])?;
+        check_reserve_account(program_id, &vault, accounts[3])?;
+        return Ok(VerifiedAction::InitializeVault {
+            vault,
+            authority: signer,
+            token_mint,
+            name: args.name,
+        });
+    }
+
+    let vault_data = lookup
+        .vault(&vault)?
+        .ok_or(IntentViolation::VaultNotFound { vault })?;
+    if let VaultInstructionKind::AddAllowlistEntry | VaultInstructionKind::RemoveAllowlistEntry =
+        kind
+    {
+        return verify_allowlist_change(program_id, kind, args, accounts, &vault_data);
+    }
+    if kind == VaultInstructionKind::ReleaseReserve {
+        return verify_reserve_release(program_id, args, accounts, &vault_data);
+    }
+    if let VaultInstructionKind::RequestWithdrawal | VaultInstructionKind::CancelWithdrawal = kind {
+        return verify_withdrawal_request(program_id, kind, args, accounts);
+    }
+    let token_program = match kind {
+        VaultInstructionKind::Deposit | VaultInstructionKind::ExecuteWithdrawal => accounts[5],
+        _ => accounts[4],
+    };
+    check_vault_accounts(program_id, &vault, accounts[2], token_program)?;
+
+    match kind {
+        VaultInstructionKind::Deposit => {
+            let args: instruction::Deposit = decode(args)?;
+            check_reserve_account(program_id, &vault, accounts[3])?;
+            let source = accounts[4];
+            check_owned_by(lookup, source, signer, vault_data.token_mint)?;
+            check_position(program_id, &vault, &signer, accounts[6])?;
+            if accounts[7] != system_program::ID {
+                return Err(IntentViolation::Malformed {
+                    reason: format!("{} is not the system program", accounts[7]),
+                }
+                .into());
+            }
+            Ok(VerifiedAction::Deposit {
+                vault,
+                depositor: signer,
+                source,
+                amount: args.amount,
+            })
+        }
+        VaultInstructionKind::Withdraw => {
+            let args: instruction::Withdraw = decode(args)?;
+            check_position(program_id, &vault, &signer, accounts[5])?;
+            verify_withdrawal(lookup, &vault_data, accounts, args.amount)
+        }
+        // The amount is the pending request's, not the instruction's
+        VaultInstructionKind::ExecuteWithdrawal => {
+            let withdrawal_request = accounts[4];
+            check_withdrawal_request(program_id, &vault, &signer, withdrawal_request)?;
+            let request = lookup
+                .withdrawal_request(&withdrawal_request)?
+                .ok_or_else(|| IntentViolation::WithdrawalRejected {
+                    reason: format!("withdrawal request {} does not exist", withdrawal_request),
+                })?;
+            check_position(program_id, &vault, &signer, accounts[6])?;
+            verify_withdrawal(lookup, &vault_data, accounts, request.amount)
+        }
+        // Claims pay out everything accrued, only to the fee collector's own
+        // associated token account
+        VaultInstructionKind::ClaimFees => {
+            let destination = accounts[3];
+            if signer != vault_data.fee_collector {
+                return Err(IntentViolation::NotFeeCollector {
+                    signer,
+                    fee_collector: vault_data.fee_collector,
+                }
+                .into());
+            }
+            let expected =
+                get_associated_token_address(&vault_data.fee_collector, &vault_data.token_mint);
+            if destination != expected {
+                return Err(IntentViolation::FeeCollectorMismatch {
+                    expected,
+                    actual: destination,
+                }
+                .into());
+            }
+            Ok(VerifiedAction::ClaimFees {
+                vault,
+                fee_collector: signer,
+                destination,
+                amount: vault_data.accrued_fees,
+            })
+        }
+        VaultInstructionKind::SweepLamports => {
+            let destination = accounts[3];
+            if signer != vault_data.authority {
+                return Err(IntentViolation::NotVaultAuthority {
+                    signer,
+                    authority: vault_data.authority,
+                }
+                .into());
+            }
+            match lookup.account_owner(&destination)? {
+                Some(owner) if owner != system_program::ID => {
+                    Err(IntentViolation::DestinationNotSystemAccount { destination }.into())
+                }
+                _ => Ok(VerifiedAction::SweepLamports {
+                    vault,
+                    authority: signer,
+                    destination,
+                }),
+            }
+        }
+        _ => unreachable!("handled above"),
+    }
+}
+
+/// A withdrawal pays all but its fee to an account of the signer; the fee
+/// stays in the vault for its fee collector. `Withdraw` and
+/// `ExecuteWithdrawal` both list the withdrawer, vault, vault token account
+/// and destination first
+fn verify_withdrawal(
+    lookup: &dyn AccountLookup,
+    vault_data: &Vault,
+    accounts: &[Pubkey],
+    amount: u64,
+) -> Result<VerifiedAction, Rejection> {
+    let (signer, vault, destination) = (accounts[0], accounts[1], accounts[3]);
+    check_owned_by(lookup, destination, signer, vault_data.token_mint)?;
+    let quote = WithdrawalQuote::for_vault(vault_data, amount).map_err(|err| {
+        IntentViolation::WithdrawalRejected {
+            reason: err.to_string(),
+        }
+    })?;
+    Ok(VerifiedAction::Withdraw {
+        vault,
+        withdrawer: signer,
+        destination,
+        amount: quote.amount,
+        fee: quote.fee,
+        net_amount: quote.net_amount,
+        fee_collector: vault_data.fee_collector,
+    })
+}
+
+/// Withdrawers request and cancel only through their own request PDA
+fn verify_withdrawal_request(
+    program_id: &Pubkey,
+    kind: VaultInstructionKind,
+    args: &[u8],
+    accounts: &[Pubkey],
+) -> Result<VerifiedAction, Rejection> {
+    let (signer, vault, withdrawal_request) = (accounts[0], accounts[1], accounts[2]);
+    check_withdrawal_request(program_id, &vault, &signer, withdrawal_request)?;
+    if kind == VaultInstructionKind::CancelWithdrawal {
+        return Ok(VerifiedAction::CancelWithdrawal {
+            vault,
+            withdrawer: signer,
+            withdrawal_request,
+        });
+    }
+
+    let args: instruction::RequestWithdrawal = decode(args)?;
+    if accounts[3] != system_program::ID {
+        return Err(IntentViolation::Malformed {
+            reason: format!("{} is not the system program", accounts[3]),
+        }
+        .into());
+    }
+    Ok(VerifiedAction::RequestWithdrawal {
+        vault,
+        withdrawer: signer,
+        amount: args.amount,
+    })
+}
+
+/// Only the vault authority may change the allowlist, and only through derived entries
+fn verify_allowlist_change(
+    program_id: &Pubkey,
+    kind: VaultInstructionKind,
+    args: &[u8],
+    accounts: &[Pubkey],
+    vault_data: &Vault,
+) -> Result<VerifiedAction, Rejection> {
+    let (signer, vault, allowlist_entry) = (accounts[0], accounts[1], accounts[2]);
+    if signer != vault_data.authority {
+        return Err(IntentViolation::NotVaultAuthority {
+            signer,
+            authority: vault_data.authority,
+        }
+        .into());
+    }
+    if kind == VaultInstructionKind::RemoveAllowlistEntry {
+        return Ok(VerifiedAction::RemoveAllowlistEntry {
+            vault,
+            authority: signer,
+            allowlist_entry,
+        });
+    }
+
+    let args: instruction::AddAllowlistEntry = decode(args)?;
+    let (expected, _) = pda::find_allowlist_entry_address(program_id, &vault, &args.wallet);
+    if allowlist_entry != expected {
+        return Err(IntentViolation::AllowlistEntryMismatch {
+            expected,
+            actual: allowlist_entry,
+        }
+        .into());
+    }
+    if accounts[3] != system_program::ID {
+        return Err(IntentViolation::Malformed {
+            reason: format!("{} is not the system program", accounts[3]),
+        }
+        .into());
+    }
+    Ok(VerifiedAction::AddAllowlistEntry {
+        vault,
+        authority: signer,
+        wallet: args.wallet,
+    })
+}
+
+/// Only the vault authority may release the reserve, through the reserve PDA
+/// and SPL token; the destination may belong to anyone
+fn verify_reserve_release(
+    program_id: &Pubkey,
+    args: &[u8],
+    accounts: &[Pubkey],
+    vault_data: &Vault,
+) -> Result<VerifiedAction, Rejection> {
+    let (signer, vault, destination) = (accounts[0], accounts[1], accounts[3]);
+    if signer != vault_data.authority {
+        return Err(IntentViolation::NotVaultAuthority {
+            signer,
+            authority: vault_data.authority,
+        }
+        .into());
+    }
+    check_reserve_account(program_id, &vault, accounts[2])?;
+    if accounts[4] != token::ID {
+        return Err(IntentViolation::UnexpectedTokenProgram {
+            actual: accounts[4],
+        }
+        .into());
+    }
+    let args: instruction::ReleaseReserve = decode(args)?;
+    Ok(VerifiedAction::ReleaseReserve {
+        vault,
+        authority: signer,
+        destination,
+        amount: args.amount,
+    })
+}
+
+/// The reserve token account must be the vault's reserve PDA
+fn check_reserve_account(
+    program_id: &Pubkey,
+    vault: &Pubkey,
+    reserve_token_account: Pubkey,
+) -> Result<(), IntentViolation> {
+    let (expected, _) = pda::find_reserve_token_account_address(program_id, vault);
+    if reserve_token_account != expected {
+        return Err(IntentViolation::ReserveTokenAccountMismatch {
+            expected,
+            actual: reserve_token_account,
+        });
+    }
+    Ok(())
+}
+
+/// The vault token account must be the vault's PDA and tokens must move through SPL token
+fn check_vault_accounts(
+    program_id: &Pubkey,
+    vault: &Pubkey,
+    vault_token_account: Pubkey,
+    token_program: Pubkey,
+) -> Result<(), IntentViolation> {
+    let (expected, _) = pda::find_vault_token_account_address(program_id, vault);
+    if vault_token_account != expected {
+        return Err(IntentViolation::VaultTokenAccountMismatch {
+            expected,
+            actual: vault_token_account,
+        });
+    }
+    if token_program != token::ID {
+        return Err(IntentViolation::UnexpectedTokenProgram {
+            actual: token_program,
+        });
+    }
+    Ok(())
+}
+
+/// Deposits and withdrawals only touch the signer's own position
+fn check_position(
+    program_id: &Pubkey,
+    vault: &Pubkey,
+    signer: &Pubkey,
+    position: Pubkey,
+) -> Result<(), IntentViolation> {
+    let (expected, _) = pda::find_position_address(program_id, vault, signer);
+    if position != expected {
+        return Err(IntentViolation::PositionMismatch {
+            expected,
+            actual: position,
+        });
+    }
+    Ok(())
+}
+
+/// Withdrawal requests are the signer's own request PDA
+fn check_withdrawal_request(
+    program_id: &Pubkey,
+    vault: &Pubkey,
+    signer: &Pubkey,
+    withdrawal_request: Pubkey,
+) -> Result<(), IntentViolation> {
+    let (expected, _) = pda::find_withdrawal_request_address(program_id, vault, signer);
+    if withdrawal_request != expected {
+        return Err(IntentViolation::WithdrawalRequestMismatch {
+            expected,
+            actual: withdrawal_request,
+        });
+    }
+    Ok(())
+}
+
+/// A token account of `mint` owned by `signer`: its associated account, or an
+/// existing account with that owner and mint
+fn check_owned_by(
+    lookup: &dyn AccountLookup,
+    account: Pubkey,
+    signer: Pubkey,
+    mint: Pubkey,
+) -> Result<(), Rejection> {
+    if account == get_associated_token_address(&signer, &mint) {
+        return Ok(());
+    }
+    match lookup.token_account(&account)? {
+        Some((owner, account_mint)) if owner == signer && account_mint == mint => Ok(()),
+        _ => Err(IntentViolation::NotOwnedBySigner {
+            account,
+            signer,
+            mint,
+        }
+        .into()),
+    }
+}
@@ -0,0 +1,752 @@
+//! The JSON contract every `--json` output and export follows:
+//!
+//! - field names are snake_case
+//! - public keys and signatures are base58 strings
+//! - token amounts are `Amount` objects, `{"raw": "1500000", "ui": "1.5",
+//!   "decimals": 6}`, with `raw` a string so JavaScript reads it exactly
+//! - timestamps are ISO 8601 UTC, e.g. `"2023-11-14T22:13:20Z"`
+//! - errors are `ErrorBody` objects, `{"code", "message", "details"}`
+//!
+//! The views here are what gets printed. Domain types keep the encodings
+//! they are stored or signed in: the vault index, backfill checkpoints and
+//! action receipts do not change shape when the output does.
+
+use anchor_client::solana_sdk::{pubkey::Pubkey, signature::Signature};
+use anyhow::Result;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::{DateTime, SecondsFormat};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::collections::{hash_map::Entry, BTreeMap, HashMap};
+
+use crate::amount::base_units_to_ui;
+use crate::deposit_source::SourceSuggestion;
+use crate::{
+    BooksReport, Discrepancy, FeePath, FeePathStatus, FreezeStatus, LiquidityProfile,
+    MaintenanceWindow, MintAmount, NotificationConfig, Page, Paginated, Provenance, RepairReport,
+    TokenVaultClient, TokenVaultError, TxReceipt, UserPosition, VaultBalance, VaultInstructionKind,
+    VaultSummary, VaultTransaction,
+};
+
+/// Decimals of SOL, for amounts in lamports
+pub const LAMPORT_DECIMALS: u8 = 9;
+
+/// Code of an error with no `TokenVaultError` underneath, such as an RPC
+/// failure
+pub const OTHER_ERROR: &str = "other";
+
+/// `ts` as ISO 8601 UTC with second precision
+pub fn iso_timestamp(ts: i64) -> String {
+    DateTime::from_timestamp(ts, 0)
+        .map(|dt| dt.to_rfc3339_opts(SecondsFormat::Secs, true))
+        .unwrap_or_else(|| ts.to_string())
+}
+
+/// A token amount in base units and in whole tokens
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Amount {
+    /// Base units, as a decimal string
+    pub raw: String,
+    /// Whole tokens without trailing zeros
+    pub ui: String,
+    pub decimals: u8,
+}
+
+impl Amount {
+    pub fn new(raw: u64, decimals: u8) -> Self {
+        Self {
+            raw: raw.to_string(),
+            ui: base_units_to_ui(raw, decimals),
+            decimals,
+        }
+    }
+
+    pub fn lamports(lamports: u64) -> Self {
+        Self::new(lamports, LAMPORT_DECIMALS)
+    }
+
+    /// An amount that can come out negative, like a replayed balance
+    pub fn signed(raw: i128, decimals: u8) -> Self {
+        let digits = format!(
+            "{:0>width$}",
+            raw.unsigned_abs(),
+            width = decimals as usize + 1
+        );
+        let (whole, fraction) = digits.split_at(digits.len() - decimals as usize);
+        let sign = if raw < 0 { "-" } else { "" };
+        let ui = match fraction.trim_end_matches('0') {
+            "" => format!("{}{}", sign, whole),
+            fraction => format!("{}{}.{}", sign, whole, fraction),
+        };
+        Self {
+            raw: raw.to_string(),
+            ui,
+            decimals,
+        }
+    }
+}
+
+impl From<MintAmount> for Amount {
+    fn from(amount: MintAmount) -> Self {
+        Self::new(amount.raw, amount.decimals)
+    }
+}
+
+/// A failure, as printed in place of a command's output
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ErrorBody {
+    /// `TokenVaultError::code` of the typed error underneath, or
+    /// `OTHER_ERROR`
+    pub code: String,
+    /// The whole error chain, outermost context first
+    pub message: String,
+    /// The typed error's fields; empty for `OTHER_ERROR`. Amounts in here
+    /// are base units as strings, since the mint's decimals are not known
+    /// where the error is raised.
+    pub details: Map<String, Value>,
+}
+
+impl ErrorBody {
+    pub fn new(code: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            code: code.into(),
+            message: message.into(),
+            details: Map::new(),
+        }
+    }
+
+    /// The body of `err`, typed by `TokenVaultError::of`
+    pub fn of(err: &anyhow::Error) -> Self {
+        Self {
+            message: format!("{:#}", err),
+            ..Self::from(&TokenVaultError::of(err))
+        }
+    }
+}
+
+impl From<&TokenVaultError> for ErrorBody {
+    fn from(err: &TokenVaultError) -> Self {
+        let details = match error_details(err) {
+            Value::Object(details) => details,
+            _ => Map::new(),
+        };
+        Self {
+            code: err.code().to_string(),
+            message: err.to_string(),
+            details,
+        }
+    }
+}
+
+/// Deposit window bounds use 0 for "unbounded", which is null here
+fn window_bound(ts: i64) -> Value {
+    match ts {
+        0 => Value::Null,
+        ts => iso_timestamp(ts).into(),
+    }
+}
+
+fn error_details(err: &TokenVaultError) -> Value {
+    let key = |key: &Pubkey| key.to_string();
+    let optional_key = |key: &Option<Pubkey>| key.as_ref().map(Pubkey::to_string);
+    match err {
+        TokenVaultError::VaultNotSet => json!({}),
+        TokenVaultError::VaultNotFound { vault } => json!({ "vault": key(vault) }),
+        TokenVaultError::VaultImmutable { vault } => json!({ "vault": key(vault) }),
+        TokenVaultError::DepositWindowClosed {
+            opens_at,
+            closes_at,
+        } => json!({
+            "opens_at": window_bound(*opens_at),
+            "closes_at": window_bound(*closes_at),
+        }),
+        TokenVaultError::MaintenanceWindow { ends_at, reason } => json!({
+            "ends_at": iso_timestamp(*ends_at),
+            "reason": reason,
+        }),
+        TokenVaultError::MaintenanceOverlap { start, end, reason } => json!({
+            "start": iso_timestamp(*start),
+            "end": iso_timestamp(*end),
+            "reason": reason,
+        }),
+        TokenVaultError::ZeroAmount { operation } => json!({ "operation": operation }),
+        TokenVaultError::DustWithdrawal { amount, threshold } => json!({
+            "amount": amount.to_string(),
+            "threshold": threshold.to_string(),
+        }),
+        TokenVaultError::WithdrawalLimitExceeded {
+            requested,
+            remaining,
+        } => json!({
+            "requested": requested.to_string(),
+            "remaining": remaining.to_string(),
+        }),
+        TokenVaultError::MemoTooLong { len, max } => json!({ "len": len, "max": max }),
+        TokenVaultError::TransactionTooLarge { size, max } => {
+            json!({ "size": size, "max": max })
+        }
+        TokenVaultError::ReserveLocked { unlocks_at } => {
+            json!({ "unlocks_at": iso_timestamp(*unlocks_at) })
+        }
+        TokenVaultError::WithdrawalTimelocked { executable_at } => {
+            json!({ "executable_at": iso_timestamp(*executable_at) })
+        }
+        TokenVaultError::ReserveInsufficient {
+            requested,
+            available,
+        } => json!({
+            "requested": requested.to_string(),
+            "available": available.to_string(),
+        }),
+        TokenVaultError::VaultTokenAccountFrozen { freeze_authority } => {
+            json!({ "freeze_authority": optional_key(freeze_authority) })
+        }
+        TokenVaultError::VaultPaused { authority } => json!({ "authority": key(authority) }),
+        TokenVaultError::TokenAccountFrozen {
+            account,
+            freeze_authority,
+        } => json!({
+            "account": key(account),
+            "freeze_authority": optional_key(freeze_authority),
+        }),
+        TokenVaultError::FreezableMintNotAcknowledged {
+            mint,
+            freeze_authority,
+        } => json!({
+            "mint": key(mint),
+            "freeze_authority": key(freeze_authority),
+        }),
+        TokenVaultError::AccountingUnderflow {
+            counter,
+            recorded,
+            amount,
+        } => json!({
+            "counter": counter,
+            "recorded": recorded.to_string(),
+            "amount": amount.to_string(),
+        }),
+        TokenVaultError::MintMismatch { expected, actual } => json!({
+            "expected": key(expected),
+            "actual": key(actual),
+        }),
+        TokenVaultError::TokenAccountMintMismatch {
+            account,
+            vault_mint,
+            account_mint,
+        } => json!({
+            "account": key(account),
+            "vault_mint": key(vault_mint),
+            "account_mint": key(account_mint),
+        }),
+        TokenVaultError::SourceMintMismatch {
+            vault_mint,
+            source_mint,
+            suggestion,
+        } => json!({
+            "vault_mint": key(vault_mint),
+            "source_mint": key(source_mint),
+            "suggestion": match suggestion {
+                SourceSuggestion::Account { address, balance } => json!({
+                    "account": key(address),
+                    "balance": balance.to_string(),
+                }),
+                SourceSuggestion::NoAccount { address } => json!({
+                    "account": key(address),
+                    "balance": Value::Null,
+                }),
+            },
+        }),
+        TokenVaultError::InsufficientBalance {
+            account,
+            have,
+            need,
+        } => json!({
+            "account": key(account),
+            "have": have.to_string(),
+            "need": need.to_string(),
+        }),
+        TokenVaultError::FeePathBroken {
+            vault,
+            account,
+            status,
+        } => json!({
+            "vault": key(vault),
+            "account": key(account),
+            "status": status,
+        }),
+        TokenVaultError::FeePoolExhausted {
+            payers,
+            floor_lamports,
+        } => json!({
+            "payers": payers,
+            "floor": Amount::lamports(*floor_lamports),
+        }),
+        TokenVaultError::TokenAccountMissing { owner, account } => json!({
+            "owner": key(owner),
+            "account": key(account),
+        }),
+        TokenVaultError::SelfTransfer { account } => json!({ "account": key(account) }),
+        TokenVaultError::KeyClusterPolicyViolation {
+            key: signer,
+            cluster,
+        } => json!({
+            "key": key(signer),
+            "cluster": cluster,
+        }),
+        TokenVaultError::FeatureRequired { feature } => json!({ "feature": feature }),
+        TokenVaultError::ClientTooOld { minimum } => json!({
+            "minimum": minimum,
+            "client": crate::features::CLIENT_VERSION,
+        }),
+        TokenVaultError::SpendingLimitExceeded {
+            window_remaining,
+            resets_at,
+        } => json!({
+            "window_remaining": window_remaining.to_string(),
+            "resets_at": iso_timestamp(*resets_at),
+        }),
+        TokenVaultError::UnsafeTransaction {
+            instruction,
+            violation,
+        } => json!({
+            "instruction": instruction,
+            "violation": violation.to_string(),
+        }),
+        TokenVaultError::AmbiguousVaultName { pattern, matches } => json!({
+            "pattern": pattern,
+            "matches": matches
+                .iter()
+                .map(|(name, address)| json!({ "name": name, "address": key(address) }))
+                .collect::<Vec<_>>(),
+        }),
+        TokenVaultError::DeadlineExceeded { phase, signature } => json!({
+            "phase": phase.to_string(),
+            "signature": signature.as_ref().map(Signature::to_string),
+        }),
+        // The message says it all
+        TokenVaultError::Rpc { .. } | TokenVaultError::Other { .. } => json!({}),
+        TokenVaultError::ProgramError { code, name, logs } => json!({
+            "code": code,
+            "name": name,
+            "logs": logs,
+        }),
+    }
+}
+
+/// One page of a listing; `next_cursor` is passed back as `--cursor`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PageView<V> {
+    pub items: Vec<V>,
+    pub next_cursor: Option<String>,
+    pub context_slot: u64,
+}
+
+impl<V> PageView<V> {
+    /// `page` with its items replaced by their views, in order
+    pub fn of<T: Paginated>(page: &Page<T>, items: Vec<V>) -> Self {
+        Self {
+            items,
+            next_cursor: page.next_cursor.as_ref().map(ToString::to_string),
+            context_slot: page.context_slot,
+        }
+    }
+}
+
+/// A maintenance window with its bounds as timestamps
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MaintenanceWindowView {
+    #[serde(serialize_with = "crate::serde_utils::timestamp::serialize")]
+    pub start: i64,
+    #[serde(serialize_with = "crate::serde_utils::timestamp::serialize")]
+    pub end: i64,
+    pub reason: String,
+}
+
+impl From<&MaintenanceWindow> for MaintenanceWindowView {
+    fn from(window: &MaintenanceWindow) -> Self {
+        Self {
+            start: window.start,
+            end: window.end,
+            reason: window.reason.clone(),
+        }
+    }
+}
+
+/// A deposit window, with null for a bound the window does not have
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DepositWindowView {
+    #[serde(serialize_with = "crate::serde_utils::option_timestamp::serialize")]
+    pub start: Option<i64>,
+    #[serde(serialize_with = "crate::serde_utils::option_timestamp::serialize")]
+    pub end: Option<i64>,
+    /// State and countdown at the cluster time of the read, e.g. "open,
+    /// closes in 2h 5m"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+}
+
+impl DepositWindowView {
+    fn of(summary: &VaultSummary) -> Self {
+        let bound = |ts: i64| (ts != 0).then_some(ts);
+        Self {
+            start: bound(summary.deposit_window_start),
+            end: bound(summary.deposit_window_end),
+            status: summary.deposit_countdown(),
+        }
+    }
+}
+
+/// The live balance of a vault's token account against its books
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BalanceView {
+    #[serde(with = "crate::serde_utils::pubkey")]
+    pub token_account: Pubkey,
+    pub amount: Amount,
+    /// `amount - total_deposited`; negative when the books claim more
+    pub discrepancy: Amount,
+}
+
+impl From<&VaultBalance> for BalanceView {
+    fn from(balance: &VaultBalance) -> Self {
+        Self {
+            token_account: balance.token_account,
+            amount: Amount::new(balance.amount, balance.decimals),
+            discrepancy: Amount::signed(balance.discrepancy, balance.decimals),
+        }
+    }
+}
+
+/// A vault, as `info --json` and `list vaults --json` print it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct VaultView {
+    #[serde(with = "crate::serde_utils::pubkey")]
+    pub address: Pubkey,
+    pub name: String,
+    #[serde(with = "crate::serde_utils::pubkey")]
+    pub authority: Pubkey,
+    #[serde(with = "crate::serde_utils::pubkey")]
+    pub token_mint: Pubkey,
+    #[serde(with = "crate::serde_utils::pubkey")]
+    pub fee_collector: Pubkey,
+    /// Basis points
+    pub fee_percentage: u16,
+    /// Seconds
+    pub withdrawal_timelock: i64,
+    pub withdrawal_limit: Amount,
+    pub dust_threshold: Amount,
+    pub waive_dust_fee: bool,
+    pub reserve_bps: u16,
+    /// Seconds
+    pub reserve_timelock: i64,
+    pub total_deposited: Amount,
+    pub total_reserved: Amount,
+    pub accrued_fees: Amount,
+    pub accounting_anomaly: bool,
+    pub paused: bool,
+    pub immutable: bool,
+    pub deposit_window: DepositWindowView,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub balance: Option<BalanceView>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub freeze: Option<FreezeStatus>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provenance: Option<Provenance>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub notifications: Option<NotificationConfig>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub maintenance: Vec<MaintenanceWindowView>,
+}
+
+impl VaultView {
+    /// View of `summary`, whose mint has `decimals` decimals
+    pub fn of(summary: &VaultSummary, decimals: u8) -> Self {
+        Self {
+            address: summary.address,
+            name: summary.name.clone(),
+            authority: summary.authority,
+            token_mint: summary.token_mint,
+            fee_collector: summary.fee_collector,
+            fee_percentage: summary.fee_percentage,
+            withdrawal_timelock: summary.withdrawal_timelock,
+            withdrawal_limit: Amount::new(summary.withdrawal_limit, decimals),
+            dust_threshold: Amount::new(summary.dust_threshold, decimals),
+            waive_dust_fee: summary.waive_dust_fee,
+            reserve_bps: summary.reserve_bps,
+            reserve_timelock: summary.reserve_timelock,
+            total_deposited: Amount::new(summary.total_deposited, decimals),
+            total_reserved: Amount::new(summary.total_reserved, decimals),
+            accrued_fees: Amount::new(summary.accrued_fees, decimals),
+            accounting_anomaly: summary.accounting_anomaly,
+            paused: summary.paused,
+            immutable: summary.immutable,
+            deposit_window: DepositWindowView::of(summary),
+            balance: summary.balance.as_ref().map(BalanceView::from),
+            freeze: summary.freeze,
+            provenance: summary.provenance,
+            notifications: summary.notifications.clone(),
+            maintenance: summary.maintenance.iter().map(Into::into).collect(),
+        }
+    }
+}
+
+/// A confirmed deposit or withdrawal, as `deposit --json` and
+/// `withdraw --json` print it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TransferView {
+    /// `deposit` or `withdraw`
+    pub operation: &'static str,
+    #[serde(with = "crate::serde_utils::pubkey")]
+    pub vault: Pubkey,
+    pub amount: Amount,
+    #[serde(with = "crate::serde_utils::signature")]
+    pub signature: Signature,
+    pub slot: Option<u64>,
+    /// Transaction fee, when the receipt carries it
+    pub fee_paid: Option<Amount>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memo: Option<String>,
+}
+
+impl TransferView {
+    pub fn deposit(vault: Pubkey, amount: MintAmount, receipt: &TxReceipt) -> Self {
+        Self::of("deposit", vault, amount, receipt)
+    }
+
+    pub fn withdraw(vault: Pubkey, amount: MintAmount, receipt: &TxReceipt) -> Self {
+        Self::of("withdraw", vault, amount, receipt)
+    }
+
+    fn of(operation: &'static str, vault: Pubkey, amount: MintAmount, receipt: &TxReceipt) -> Self {
+        Self {
+            operation,
+            vault,
+            amount: amount.into(),
+            signature: receipt.signature,
+            slot: receipt.slot,
+            fee_paid: receipt.fee_paid.map(Amount::lamports),
+            memo: receipt.memo.clone(),
+        }
+    }
+}
+
+/// One token vault instruction of a transaction
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct InstructionView {
+    pub kind: &'static str,
+    pub index: usize,
+    pub accounts: Vec<String>,
+    /// Instruction data, base64
+    pub data: String,
+}
+
+/// A transaction from the vault's history, as `list history --json` and
+/// history exports write it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TransactionView {
+    #[serde(with = "crate::serde_utils::signature")]
+    pub signature: Signature,
+    pub slot: u64,
+    #[serde(serialize_with = "crate::serde_utils::option_timestamp::serialize")]
+    pub block_time: Option<i64>,
+    pub succeeded: bool,
+    pub instructions: Vec<InstructionView>,
+    pub logs: Vec<String>,
+}
+
+impl From<&VaultTransaction> for TransactionView {
+    fn from(transaction: &VaultTransaction) -> Self {
+        Self {
+            signature: transaction.signature,
+            slot: transaction.slot,
+            block_time: transaction.block_time,
+            succeeded: transaction.succeeded,
+            instructions: transaction
+                .instructions
+                .iter()
+                .map(|instruction| InstructionView {
+                    kind: instruction_kind(instruction.kind),
+                    index: instruction.index,
+                    accounts: instruction.accounts.clone(),
+                    data: STANDARD.encode(&instruction.data),
+                })
+                .collect(),
+            logs: transaction.logs.clone(),
+        }
+    }
+}
+
+fn instruction_kind(kind: VaultInstructionKind) -> &'static str {
+    match kind {
+        VaultInstructionKind::InitializeVault => "initialize_vault",
+        VaultInstructionKind::Deposit => "deposit",
+        VaultInstructionKind::Withdraw => "withdraw",
+        VaultInstructionKind::RequestWithdrawal => "request_withdrawal",
+        VaultInstructionKind::ExecuteWithdrawal => "execute_withdrawal",
+        VaultInstructionKind::CancelWithdrawal => "cancel_withdrawal",
+        VaultInstructionKind::SweepLamports => "sweep_lamports",
+        VaultInstructionKind::ReleaseReserve => "release_reserve",
+        VaultInstructionKind::ClaimFees => "claim_fees",
+        VaultInstructionKind::AddAllowlistEntry => "add_allowlist_entry",
+        VaultInstructionKind::RemoveAllowlistEntry => "remove_allowlist_entry",
+        VaultInstructionKind::Unknown => "unknown",
+    }
+}
+
+/// Where a vault's tokens are, as `liquidity --format json` prints it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LiquidityView {
+    #[serde(with = "crate::serde_utils::pubkey")]
+    pub mint: Pubkey,
+    #[serde(serialize_with = "crate::serde_utils::timestamp::serialize")]
+    pub taken_at: i64,
+    pub total: Amount,
+    pub withdrawable: Amount,
+    pub timelocked: Amount,
+    pub reserved: Amount,
+    pub frozen: Amount,
+    pub max_single_withdrawal: Amount,
+    /// Seconds a depositor waits between depositing and withdrawing
+    pub withdrawal_timelock: i64,
+}
+
+impl From<&LiquidityProfile> for LiquidityView {
+    fn from(profile: &LiquidityProfile) -> Self {
+        Self {
+            mint: profile.total.mint,
+            taken_at: profile.taken_at,
+            total: profile.total.into(),
+            withdrawable: profile.withdrawable.into(),
+            timelocked: profile.timelocked.into(),
+            reserved: profile.reserved.into(),
+            frozen: profile.frozen.into(),
+            max_single_withdrawal: profile.max_single_withdrawal.into(),
+            withdrawal_timelock: profile.withdrawal_timelock,
+        }
+    }
+}
+
+/// One owner's replayed deposits and withdrawals
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PositionView {
+    pub deposited: Amount,
+    pub withdrawn: Amount,
+    pub fees_paid: Amount,
+    pub reserve_paid: Amount,
+    pub balance: Amount,
+}
+
+impl PositionView {
+    pub fn of(position: &UserPosition, decimals: u8) -> Self {
+        Self {
+            deposited: Amount::new(position.deposited, decimals),
+            withdrawn: Amount::new(position.withdrawn, decimals),
+            fees_paid: Amount::new(position.fees_paid, decimals),
+            reserve_paid: Amount::new(position.reserve_paid, decimals),
+            balance: Amount::signed(position.balance(), decimals),
+        }
+    }
+}
+
+/// The result of `verify-books --json`. Expected values come from replay
+/// and go negative when the history does not add up.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BooksView {
+    pub vault: String,
+    pub consistent: bool,
+    pub transactions_replayed: usize,
+    pub expected_total_deposited: Amount,
+    pub actual_total_deposited: Amount,
+    pub expected_token_balance: Amount,
+    pub actual_token_balance: Amount,
+    pub expected_accrued_fees: Amount,
+    pub actual_accrued_fees: Amount,
+    pub expected_total_reserved: Amount,
+    pub actual_total_reserved: Amount,
+    pub expected_reserve_balance: Amount,
+    pub actual_reserve_balance: Amount,
+    pub fees_collected: Amount,
+    pub positions: BTreeMap<String, PositionView>,
+    pub discrepancies: Vec<Discrepancy>,
+    pub unverifiable_before_slot: Option<u64>,
+}
+
+impl BooksView {
+    /// View of `report` for a vault whose mint has `decimals` decimals
+    pub fn of(report: &BooksReport, decimals: u8) -> Self {
+        Self {
+            vault: report.vault.clone(),
+            consistent: report.is_consistent(),
+            transactions_replayed: report.transactions_replayed,
+            expected_total_deposited: Amount::signed(report.expected_total_deposited, decimals),
+            actual_total_deposited: Amount::new(report.actual_total_deposited, decimals),
+            expected_token_balance: Amount::signed(report.expected_token_balance, decimals),
+            actual_token_balance: Amount::new(report.actual_token_balance, decimals),
+            expected_accrued_fees: Amount::signed(report.expected_accrued_fees, decimals),
+            actual_accrued_fees: Amount::new(report.actual_accrued_fees, decimals),
+            expected_total_reserved: Amount::signed(report.expected_total_reserved, decimals),
+            actual_total_reserved: Amount::new(report.actual_total_reserved, decimals),
+            expected_reserve_balance: Amount::signed(report.expected_reserve_balance, decimals),
+            actual_reserve_balance: Amount::new(report.actual_reserve_balance, decimals),
+            fees_collected: Amount::new(report.fees_collected, decimals),
+            positions: report
+                .positions
+                .iter()
+                .map(|(owner, position)| (owner.clone(), PositionView::of(position, decimals)))
+                .collect(),
+            discrepancies: report.discrepancies.clone(),
+            unverifiable_before_slot: report.unverifiable_before_slot,
+        }
+    }
+}
+
+/// The result of `repair fee-path --json`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RepairView {
+    pub before: FeePath,
+    pub after: FeePathStatus,
+    #[serde(with = "crate::serde_utils::option_signature")]
+    pub signature: Option<Signature>,
+    /// Rent deposited into the created account
+    pub rent: Amount,
+    /// Transaction fee
+    pub fee: Amount,
+}
+
+impl From<&RepairReport> for RepairView {
+    fn from(report: &RepairReport) -> Self {
+        Self {
+            before: report.before.clone(),
+            after: report.after,
+            signature: report.signature,
+            rent: Amount::lamports(report.rent_lamports),
+            fee: Amount::lamports(report.fee_lamports),
+        }
+    }
+}
+
+impl TokenVaultClient {
+    /// Decimals of `mint`, read once per client
+    pub fn mint_decimals(&self, mint: &Pubkey) -> Result<u8> {
+        if let Some(decimals) = self.mint_decimals.lock().unwrap().get(mint) {
+            return Ok(*decimals);
+        }
+        let decimals = self.fetch_mint(*mint, self.deadline())?.decimals;
+        self.mint_decimals.lock().unwrap().insert(*mint, decimals);
+        Ok(decimals)
+    }
+
+    /// Views of `summaries`, reading each distinct mint once
+    pub fn vault_views(&self, summaries: &[VaultSummary]) -> Result<Vec<VaultView>> {
+        let mut decimals = HashMap::new();
+        summaries
+            .iter()
+            .map(|summary| {
+                let decimals = match decimals.entry(summary.token_mint) {
+                    Entry::Occupied(entry) => *entry.get(),
+                    Entry::Vacant(entry) => *entry.insert(self.mint_decimals(&summary.token_mint)?),
+                };
+                Ok(VaultView::of(summary, decimals))
+            })
+            .collect()
+    }
+}
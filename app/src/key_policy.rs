@@ -0,0 +1,97 @@
+use anchor_client::{solana_sdk::pubkey::Pubkey, Cluster};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fmt;
+
+use crate::{TokenVaultClient, TokenVaultError};
+
+/// Which clusters a key may sign for under a `KeyPolicy`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum KeyBucket {
+    /// Throwaway key that must never sign on mainnet
+    TestOnly,
+    /// Production key that must never sign anywhere but mainnet
+    ProductionOnly,
+    /// Not listed in the policy
+    Unrestricted,
+}
+
+impl fmt::Display for KeyBucket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let bucket = match self {
+            Self::TestOnly => "test-only",
+            Self::ProductionOnly => "production-only",
+            Self::Unrestricted => "unrestricted",
+        };
+        write!(f, "{}", bucket)
+    }
+}
+
+/// Guardrail keeping test keys off mainnet and production keys off test clusters
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyPolicy {
+    #[serde(default, with = "crate::serde_utils::pubkey_set")]
+    pub test_only: BTreeSet<Pubkey>,
+    #[serde(default, with = "crate::serde_utils::pubkey_set")]
+    pub production_only: BTreeSet<Pubkey>,
+}
+
+impl KeyPolicy {
+    pub fn bucket(&self, key: &Pubkey) -> KeyBucket {
+        if self.test_only.contains(key) {
+            KeyBucket::TestOnly
+        } else if self.production_only.contains(key) {
+            KeyBucket::ProductionOnly
+        } else {
+            KeyBucket::Unrestricted
+        }
+    }
+
+    /// Fail with `KeyClusterPolicyViolation` if `key` may not sign for `cluster`
+    pub fn check(&self, key: &Pubkey, cluster: &Cluster) -> Result<(), TokenVaultError> {
+        let allowed = match self.bucket(key) {
+            KeyBucket::TestOnly => !is_mainnet(cluster),
+            KeyBucket::ProductionOnly => is_mainnet(cluster),
+            KeyBucket::Unrestricted => true,
+        };
+        if allowed {
+            Ok(())
+        } else {
+            Err(TokenVaultError::KeyClusterPolicyViolation {
+                key: *key,
+                cluster: cluster.to_string(),
+            })
+        }
+    }
+}
+
+/// Mainnet-beta, either by moniker or by an RPC URL pointing at it
+pub fn is_mainnet(cluster: &Cluster) -> bool {
+    match cluster {
+        Cluster::Mainnet => true,
+        Cluster::Custom(url, _) => url.contains("mainnet"),
+        _ => false,
+    }
+}
+
+impl TokenVaultClient {
+    /// Enforce `policy` on the payer now and on every signer from here on
+    pub fn with_key_policy(&mut self, policy: KeyPolicy) -> Result<&mut Self> {
+        policy.check(&self.program.payer(), &self.cluster)?;
+        self.key_policy = Some(policy);
+        Ok(self)
+    }
+
+    pub fn key_policy(&self) -> Option<&KeyPolicy> {
+        self.key_policy.as_ref()
+    }
+
+    /// Refuse to sign with `key` if the key policy forbids it on this cluster
+    pub(crate) fn check_signer(&self, key: &Pubkey) -> Result<(), TokenVaultError> {
+        match &self.key_policy {
+            Some(policy) => policy.check(key, &self.cluster),
+            None => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,968 @@
+//! Guarded launch of a new vault from a declarative plan. Every account the
+//! plan names is checked before anything is sent, each step is simulated
+//! right before it is sent, and a resume file records the steps that landed,
+//! so a launch that stopped halfway picks up where it left off. Steps whose
+//! effect is already on chain are skipped, so running a plan twice is safe.
+
+use anchor_client::solana_sdk::{
+    hash::{hash, Hash},
+    pubkey::Pubkey,
+    signature::Signature,
+};
+use anchor_lang::AccountDeserialize;
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::maintenance::schedule_window;
+use crate::mirror::config_fields;
+use crate::signer::VaultSigner;
+use crate::token_vault::{pda, state::Vault};
+use crate::vault_config::ui_to_base_units;
+use crate::{
+    freeze, logging, ActionReceipt, DeadlinePhase, DepositWindow, DustPolicy, FeePathStatus,
+    MaintenanceWindow, NotificationConfig, ReservePolicy, TokenVaultClient, TokenVaultError,
+    VaultConfig,
+};
+
+/// Domain separator prepended to every launch report message, so its
+/// signature can never pass as a transaction, receipt or attestation
+pub const LAUNCH_REPORT_DOMAIN: &[u8] = b"token-vault:launch-report:v1";
+
+/// The vault a launch creates. The program fixes all of this at creation,
+/// including the fee collector and the deposit window.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LaunchVault {
+    pub name: String,
+    #[serde(with = "crate::serde_utils::pubkey")]
+    pub mint: Pubkey,
+    pub fee_bps: u16,
+    /// Seconds between a deposit and its withdrawal
+    #[serde(default)]
+    pub timelock: u64,
+    /// Maximum amount per withdrawal in base units; give this or
+    /// `withdrawal_limit_ui`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub withdrawal_limit: Option<u64>,
+    /// Maximum amount per withdrawal in whole tokens, e.g. `"1000.5"`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub withdrawal_limit_ui: Option<String>,
+    /// Receives withdrawal fees; the authority when absent
+    #[serde(
+        default,
+        with = "crate::serde_utils::option_pubkey",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub fee_collector: Option<Pubkey>,
+    #[serde(default)]
+    pub immutable: bool,
+    /// Unix time deposits open at; 0 for already open
+    #[serde(default)]
+    pub deposit_opens: i64,
+    /// Unix time deposits close at; 0 for never
+    #[serde(default)]
+    pub deposit_closes: i64,
+    #[serde(default)]
+    pub dust_threshold: u64,
+    /// Waive the fee below the dust threshold instead of rejecting
+    #[serde(default)]
+    pub waive_dust_fee: bool,
+    #[serde(default)]
+    pub reserve_bps: u16,
+    #[serde(default)]
+    pub reserve_timelock: i64,
+    #[serde(default)]
+    pub acknowledge_freezable_mint: bool,
+}
+
+/// Notification metadata published after the vault is created
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LaunchMetadata {
+    #[serde(default)]
+    pub explorer: String,
+    #[serde(default)]
+    pub support_contact: String,
+    /// Only its hash is published
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub webhook_url: Option<String>,
+}
+
+impl LaunchMetadata {
+    pub fn notification_config(&self) -> NotificationConfig {
+        let config = NotificationConfig::default()
+            .explorer(self.explorer.clone())
+            .support_contact(self.support_contact.clone());
+        match &self.webhook_url {
+            Some(url) => config.webhook_url(url),
+            None => config,
+        }
+    }
+}
+
+/// The wallets the vault's allowlist holds after the launch, and no others
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LaunchAllowlist {
+    #[serde(with = "crate::serde_utils::pubkey_set")]
+    pub wallets: BTreeSet<Pubkey>,
+}
+
+/// Everything a vault launch sets up, read from a TOML launch plan:
+///
+/// ```toml
+/// [vault]
+/// name = "treasury"
+/// mint = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v"
+/// fee_bps = 25
+/// timelock = 86400
+/// withdrawal_limit_ui = "10000"
+/// fee_collector = "9xQeWvG816bUx9EPjHmaT23yvVM2ZWbrrpZb9PusVFin"
+/// deposit_opens = 1767225600
+///
+/// [metadata]
+/// explorer = "https://explorer.solana.com"
+/// support_contact = "support@example.com"
+///
+/// [allowlist]
+/// wallets = ["8qbHbw2BbbTHBW1sbeqakYXVKRQM8Ne7pLK7m6CVfeR"]
+///
+/// [[maintenance]]
+/// start = 1767830400
+/// end = 1767834000
+/// reason = "Custodian migration"
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LaunchPlan {
+    pub vault: LaunchVault,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<LaunchMetadata>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allowlist: Option<LaunchAllowlist>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub maintenance: Vec<MaintenanceWindow>,
+}
+
+impl LaunchPlan {
+    /// The steps of the launch, in the order they run
+    pub fn steps(&self) -> Vec<LaunchStep> {
+        let mut steps = vec![LaunchStep::CreateVault, LaunchStep::FeePath];
+        if self.metadata.is_some() {
+            steps.push(LaunchStep::Metadata);
+        }
+        if self.allowlist.is_some() {
+            steps.push(LaunchStep::Allowlist);
+        }
+        steps.extend((0..self.maintenance.len()).map(LaunchStep::Maintenance));
+        steps
+    }
+
+    /// Fingerprint of the plan; a resume file only resumes the plan it was
+    /// written for
+    pub fn fingerprint(&self) -> Result<Hash> {
+        Ok(hash(&serde_json::to_vec(self)?))
+    }
+
+    /// The vault's config for a mint with `decimals` decimals, owned by `authority`
+    pub fn vault_config(&self, authority: &Pubkey, decimals: u8) -> Result<VaultConfig> {
+        let vault = &self.vault;
+        let builder = VaultConfig::builder()
+            .name(vault.name.clone())
+            .fee_bps(vault.fee_bps)
+            .timelock(Duration::from_secs(vault.timelock));
+        let builder = match (vault.withdrawal_limit, &vault.withdrawal_limit_ui) {
+            (Some(limit), None) => builder.withdrawal_limit(limit),
+            (None, Some(amount)) => builder.withdrawal_limit(
+                ui_to_base_units(amount, decimals).context("Invalid withdrawal limit")?,
+            ),
+            _ => {
+                return Err(anyhow!(
+                    "Give exactly one of withdrawal_limit and withdrawal_limit_ui"
+                ))
+            }
+        };
+        let dust_policy = match vault.waive_dust_fee {
+            true => DustPolicy::waive_fee_below(vault.dust_threshold),
+            false => DustPolicy::reject_below(vault.dust_threshold),
+        };
+        builder
+            .fee_collector(vault.fee_collector.unwrap_or(*authority))
+            .immutable(vault.immutable)
+            .deposit_window(DepositWindow::new(
+                vault.deposit_opens,
+                vault.deposit_closes,
+            )?)
+            .dust_policy(dust_policy)
+            .reserve(ReservePolicy::new(
+                vault.reserve_bps,
+                vault.reserve_timelock,
+            )?)
+            .acknowledge_freezable_mint(vault.acknowledge_freezable_mint)
+            .build()
+    }
+
+    /// Check what can be checked without a cluster: the metadata and the
+    /// maintenance windows
+    pub fn check(&self) -> Result<()> {
+        if let Some(metadata) = &self.metadata {
+            metadata
+                .notification_config()
+                .validate()
+                .context("Invalid [metadata]")?;
+        }
+        for (i, window) in self.maintenance.iter().enumerate() {
+            MaintenanceWindow::new(window.start, window.end, window.reason.clone())
+                .with_context(|| format!("Invalid maintenance window #{}", i + 1))?;
+        }
+        Ok(())
+    }
+}
+
+/// One step of a launch
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "step", content = "index")]
+pub enum LaunchStep {
+    /// Create the vault with its fixed config
+    CreateVault,
+    /// Make sure the fee collector's token account exists
+    FeePath,
+    /// Publish the notification metadata
+    Metadata,
+    /// Make the allowlist exactly the plan's
+    Allowlist,
+    /// Schedule the plan's maintenance window with this index
+    Maintenance(usize),
+}
+
+impl fmt::Display for LaunchStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::CreateVault => write!(f, "create vault"),
+            Self::FeePath => write!(f, "fee path"),
+            Self::Metadata => write!(f, "metadata"),
+            Self::Allowlist => write!(f, "allowlist"),
+            Self::Maintenance(index) => write!(f, "maintenance window #{}", index + 1),
+        }
+    }
+}
+
+/// What became of a step
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "status", content = "reason")]
+pub enum StepStatus {
+    /// Sent by a launch, this run or an earlier one
+    Applied,
+    /// Its effect was already on chain, so nothing was sent
+    AlreadyApplied,
+    /// Would be sent, in a dry run
+    Planned,
+    Failed(String),
+    /// An earlier step failed
+    NotReached,
+}
+
+impl fmt::Display for StepStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Applied => write!(f, "applied"),
+            Self::AlreadyApplied => write!(f, "already applied"),
+            Self::Planned => write!(f, "planned"),
+            Self::Failed(reason) => write!(f, "failed: {}", reason),
+            Self::NotReached => write!(f, "not reached"),
+        }
+    }
+}
+
+/// A step and what became of it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StepRecord {
+    #[serde(flatten)]
+    pub step: LaunchStep,
+    pub outcome: StepStatus,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub signatures: Vec<String>,
+    /// The authority's signed receipts for the step's administrative changes
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub receipts: Vec<ActionReceipt>,
+}
+
+/// What an applied step sent
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AppliedStep {
+    pub signatures: Vec<Signature>,
+    pub receipts: Vec<ActionReceipt>,
+}
+
+/// One post-launch check
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LaunchCheck {
+    pub name: String,
+    pub passed: bool,
+    pub detail: String,
+}
+
+impl LaunchCheck {
+    /// A check that passed with `detail`, or failed with the error
+    pub fn of<T>(name: &str, result: Result<T>, detail: impl FnOnce(T) -> String) -> Self {
+        match result {
+            Ok(value) => Self {
+                name: name.to_string(),
+                passed: true,
+                detail: detail(value),
+            },
+            Err(err) => Self {
+                name: name.to_string(),
+                passed: false,
+                detail: format!("{:#}", err),
+            },
+        }
+    }
+}
+
+/// Durable record of a launch in progress; a rerun with the same file skips
+/// the steps it records as done
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LaunchCheckpoint {
+    /// `LaunchPlan::fingerprint` of the plan being launched
+    pub plan: String,
+    #[serde(with = "crate::serde_utils::pubkey")]
+    pub vault: Pubkey,
+    /// Finished steps, in the order they finished
+    pub completed: Vec<StepRecord>,
+    /// The step the launch last stopped at, and why
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub failed: Option<StepRecord>,
+}
+
+impl LaunchCheckpoint {
+    pub fn new(plan: Hash, vault: Pubkey) -> Self {
+        Self {
+            plan: plan.to_string(),
+            vault,
+            completed: Vec::new(),
+            failed: None,
+        }
+    }
+
+    pub fn completed(&self, step: LaunchStep) -> Option<&StepRecord> {
+        self.completed.iter().find(|record| record.step == step)
+    }
+
+    /// The checkpoint at `path`, or a fresh one when there is none yet.
+    /// Refuses a checkpoint of another plan or vault.
+    pub fn load(path: &Path, plan: Hash, vault: Pubkey) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::new(plan, vault));
+        }
+        let checkpoint: Self = serde_json::from_str(&fs::read_to_string(path)?)
+            .with_context(|| format!("Invalid resume file {}", path.display()))?;
+        if checkpoint.plan != plan.to_string() {
+            return Err(anyhow!(
+                "Resume file {} was written for another launch plan; remove it to start over",
+                path.display()
+            ));
+        }
+        if checkpoint.vault != vault {
+            return Err(anyhow!(
+                "Resume file {} belongs to vault {}, not {}",
+                path.display(),
+                checkpoint.vault,
+                vault
+            ));
+        }
+        Ok(checkpoint)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        // Write-then-rename so a kill mid-write never leaves a truncated file
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, serde_json::to_vec_pretty(self)?)?;
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+}
+
+/// Outcome of a launch, signed by the vault authority over everything else
+/// in it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LaunchReport {
+    #[serde(with = "crate::serde_utils::pubkey")]
+    pub program_id: Pubkey,
+    #[serde(with = "crate::serde_utils::pubkey")]
+    pub vault: Pubkey,
+    #[serde(with = "crate::serde_utils::pubkey")]
+    pub authority: Pubkey,
+    /// `LaunchPlan::fingerprint` of the plan launched
+    pub plan: String,
+    pub dry_run: bool,
+    pub steps: Vec<StepRecord>,
+    /// Post-launch checks; empty for a dry run or a launch that stopped
+    pub checks: Vec<LaunchCheck>,
+    /// The authority's signature over `message()`
+    #[serde(with = "crate::serde_utils::signature")]
+    pub authority_signature: Signature,
+}
+
+impl LaunchReport {
+    /// Sign the report with `authority`, which must be its authority
+    pub fn sign(mut self, authority: &dyn VaultSigner) -> Result<Self> {
+        if self.authority != authority.pubkey() {
+            return Err(anyhow!(
+                "Report is for authority {}, not {}",
+                self.authority,
+                authority.pubkey()
+            ));
+        }
+        self.authority_signature = authority.sign_message(&self.message()?)?;
+        Ok(self)
+    }
+
+    /// The exact bytes that are signed: the domain separator followed by the
+    /// compact JSON of every field but the authority signature, keys sorted
+    pub fn message(&self) -> Result<Vec<u8>> {
+        let mut fields = serde_json::to_value(self)?;
+        if let Some(fields) = fields.as_object_mut() {
+            fields.remove("authority_signature");
+        }
+        let mut message = LAUNCH_REPORT_DOMAIN.to_vec();
+        serde_json::to_writer(&mut message, &fields)?;
+        Ok(message)
+    }
+
+    /// Every step landed or was already in effect, and every check passed
+    pub fn is_complete(&self) -> bool {
+        !self.dry_run
+            && self.steps.iter().all(|record| {
+                matches!(
+                    record.outcome,
+                    StepStatus::Applied | StepStatus::AlreadyApplied
+                )
+            })
+            && self.checks.iter().all(|check| check.passed)
+    }
+}
+
+impl fmt::Display for LaunchReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = if self.dry_run {
+            "Launch plan"
+        } else {
+            "Launch"
+        };
+        writeln!(f, "{} of vault {} by {}", kind, self.vault, self.authority)?;
+        for record in &self.steps {
+            writeln!(f, "  {}: {}", record.step, record.outcome)?;
+            for signature in &record.signatures {
+                writeln!(f, "    {}", signature)?;
+            }
+        }
+        for check in &self.checks {
+            let verdict = if check.passed { "ok" } else { "FAILED" };
+            writeln!(f, "  check {}: {} ({})", check.name, verdict, check.detail)?;
+        }
+        Ok(())
+    }
+}
+
+/// Check, without any network access, that `report` was signed by
+/// `expected_authority` and has not been altered since
+pub fn verify_launch_report(report: &LaunchReport, expected_authority: &Pubkey) -> Result<()> {
+    if report.authority != *expected_authority {
+        return Err(anyhow!(
+            "Report was signed by {}, not {}",
+            report.authority,
+            expected_authority
+        ));
+    }
+    if !report
+        .authority_signature
+        .verify(expected_authority.as_ref(), &report.message()?)
+    {
+        return Err(anyhow!(
+            "Report signature does not match its contents; it was altered or not signed by {}",
+            expected_authority
+        ));
+    }
+    Ok(())
+}
+
+/// What the launcher needs from the cluster; implemented over RPC by the client
+pub trait LaunchTarget {
+    fn program_id(&self) -> Pubkey;
+    /// Address of the vault the plan creates
+    fn vault_address(&self, plan: &LaunchPlan) -> Result<Pubkey>;
+    /// Fail if an account the plan names is missing or unfit, before
+    /// anything is sent
+    fn validate(&self, plan: &LaunchPlan) -> Result<()>;
+    /// Whether the effect of `step` is already on chain
+    fn is_applied(&self, plan: &LaunchPlan, step: LaunchStep) -> Result<bool>;
+    /// Simulate the first transaction of `step` without sending it
+    fn simulate(&self, plan: &LaunchPlan, step: LaunchStep) -> Result<()>;
+    fn apply(&self, plan: &LaunchPlan, step: LaunchStep) -> Result<AppliedStep>;
+    /// Post-launch checks of the vault
+    fn checks(&self, plan: &LaunchPlan) -> Vec<LaunchCheck>;
+}
+
+/// How `VaultLauncher` runs a plan
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LaunchOptions {
+    /// Record finished steps here and skip those already recorded
+    pub resume_path: Option<PathBuf>,
+    /// Validate, and report which steps would be sent, without sending anything
+    pub dry_run: bool,
+}
+
+/// Runs a launch plan step by step against a `LaunchTarget`
+pub struct VaultLauncher<'a, T: LaunchTarget> {
+    target: T,
+    plan: &'a LaunchPlan,
+    options: LaunchOptions,
+}
+
+impl<'a, T: LaunchTarget> VaultLauncher<'a, T> {
+    pub fn new(target: T, plan: &'a LaunchPlan, options: LaunchOptions) -> Self {
+        Self {
+            target,
+            plan,
+            options,
+        }
+    }
+
+    /// Validate the plan, then run its steps in order: each one recorded
+    /// in the resume file or already on chain is skipped, the others are
+    /// simulated and sent. The first failure is recorded in the resume file
+    /// and returned. After the last step the checks run, and the report is
+    /// signed with `authority`.
+    ///
+    /// In a dry run nothing is sent or recorded. Only the first step that
+    /// would be sent is simulated, and the steps after it are reported as
+    /// planned unchecked, as they depend on it.
+    pub fn run(&self, authority: &dyn VaultSigner) -> Result<LaunchReport> {
+        let plan = self.plan;
+        plan.check()?;
+        let fingerprint = plan.fingerprint()?;
+        let vault = self.target.vault_address(plan)?;
+        // Maintenance is scheduled by the authority after creation, which an
+        // immutable vault refuses
+        if plan.vault.immutable && !plan.maintenance.is_empty() {
+            return Err(TokenVaultError::VaultImmutable { vault }.into());
+        }
+        self.target.validate(plan)?;
+
+        let mut checkpoint = match &self.options.resume_path {
+            Some(path) => LaunchCheckpoint::load(path, fingerprint, vault)?,
+            None => LaunchCheckpoint::new(fingerprint, vault),
+        };
+        let save = |checkpoint: &LaunchCheckpoint| match &self.options.resume_path {
+            Some(path) if !self.options.dry_run => checkpoint.save(path),
+            _ => Ok(()),
+        };
+
+        let mut steps = Vec::new();
+        let mut simulated = false;
+        let mut stopped = None;
+        for step in plan.steps() {
+            if stopped.is_some() {
+                steps.push(record(step, StepStatus::NotReached));
+                continue;
+            }
+            if let Some(done) = checkpoint.completed(step) {
+                steps.push(done.clone());
+                continue;
+            }
+            let finished = self.run_step(step, &mut simulated);
+            match finished {
+                Ok(done) => {
+                    if !self.options.dry_run {
+                        checkpoint.completed.push(done.clone());
+                        checkpoint.failed = None;
+                        save(&checkpoint)?;
+                    }
+                    steps.push(done);
+                }
+                Err(err) => {
+                    let failed = record(step, StepStatus::Failed(format!("{:#}", err)));
+                    if !self.options.dry_run {
+                        checkpoint.failed = Some(failed.clone());
+                        save(&checkpoint)?;
+                    }
+                    steps.push(failed);
+                    stopped = Some((step, err));
+                }
+            }
+        }
+
+        if let Some((step, err)) = stopped {
+            let resume = match &self.options.resume_path {
+                Some(path) => format!("; rerun with resume file {} to continue", path.display()),
+                None => String::new(),
+            };
+            return Err(err).context(format!("Launch stopped at step '{}'{}", step, resume));
+        }
+
+        let checks = match self.options.dry_run {
+            true => Vec::new(),
+            false => self.target.checks(plan),
+        };
+        LaunchReport {
+            program_id: self.target.program_id(),
+            vault,
+            authority: authority.pubkey(),
+            plan: fingerprint.to_string(),
+            dry_run: self.options.dry_run,
+            steps,
+            checks,
+            authority_signature: Signature::default(),
+        }
+        .sign(authority)
+    }
+
+    fn run_step(&self, step: LaunchStep, simulated: &mut bool) -> Result<StepRecord> {
+        let plan = self.plan;
+        // Past the first pending step of a dry run, the chain holds none of
+        // the state later steps would be checked against
+        if self.options.dry_run && *simulated {
+            return Ok(record(step, StepStatus::Planned));
+        }
+        if self.target.is_applied(plan, step)? {
+            return Ok(record(step, StepStatus::AlreadyApplied));
+        }
+        if self.options.dry_run {
+            self.target.simulate(plan, step)?;
+            *simulated = true;
+            return Ok(record(step, StepStatus::Planned));
+        }
+        self.target
+            .simulate(plan, step)
+            .context("Simulation failed; nothing was sent")?;
+        let applied = self.target.apply(plan, step)?;
+        Ok(StepRecord {
+            step,
+            outcome: StepStatus::Applied,
+            signatures: applied.signatures.iter().map(ToString::to_string).collect(),
+            receipts: applied.receipts,
+        })
+    }
+}
+
+fn record(step: LaunchStep, outcome: StepStatus) -> StepRecord {
+    StepRecord {
+        step,
+        outcome,
+        signatures: Vec::new(),
+        receipts: Vec::new(),
+    }
+}
+
+impl TokenVaultClient {
+    /// Launch the vault `plan` describes, owned by `authority`, with
+    /// `VaultLauncher`. Afterwards the client has the vault selected.
+    pub fn launch_vault(
+        &mut self,
+        authority: &dyn VaultSigner,
+        plan: &LaunchPlan,
+        options: LaunchOptions,
+    ) -> Result<LaunchReport> {
+        let addresses = pda::VaultAddresses::derive(
+            &self.program.id(),
+            &authority.pubkey(),
+            &plan.vault.mint,
+            &plan.vault.name,
+        )?;
+        self.set_vault(addresses.vault);
+        let target = ClientTarget {
+            client: self,
+            authority,
+        };
+        VaultLauncher::new(target, plan, options).run(authority)
+    }
+}
+
+struct ClientTarget<'a> {
+    client: &'a TokenVaultClient,
+    authority: &'a dyn VaultSigner,
+}
+
+impl ClientTarget<'_> {
+    fn existing_vault(&self, vault: &Pubkey) -> Result<Option<Vault>> {
+        let rpc = self
+            .client
+            .rpc_within(self.client.deadline(), DeadlinePhase::Fetch)?;
+        match logging::get_account(&rpc, vault, rpc.commitment())?.value {
+            Some(account) => Ok(Some(Vault::try_deserialize(&mut account.data.as_slice())?)),
+            None => Ok(None),
+        }
+    }
+
+    fn config(&self, plan: &LaunchPlan) -> Result<VaultConfig> {
+        let client = self.client;
+        let mint = client.fetch_mint(plan.vault.mint, client.deadline())?;
+        plan.vault_config(&self.authority.pubkey(), mint.decimals)
+    }
+
+    fn maintenance_window(plan: &LaunchPlan, index: usize) -> Result<&MaintenanceWindow> {
+        plan.maintenance
+            .get(index)
+            .ok_or_else(|| anyhow!("The plan has no maintenance window #{}", index + 1))
+    }
+
+    fn send(
+        client: &TokenVaultClient,
+        authority: &dyn VaultSigner,
+        plan: &LaunchPlan,
+        config: &VaultConfig,
+        step: LaunchStep,
+    ) -> Result<AppliedStep> {
+        let mut applied = AppliedStep::default();
+        match step {
+            LaunchStep::CreateVault => {
+                let vault = client.initialize_vault(authority, plan.vault.mint, config)?;
+                applied.signatures.push(vault.receipt.signature);
+            }
+            LaunchStep::FeePath => {
+                applied
+                    .signatures
+                    .extend(client.repair_fee_path(authority)?.signature);
+            }
+            LaunchStep::Metadata => {
+                let metadata = plan.metadata.clone().unwrap_or_default();
+                let receipt =
+                    client.set_notification_config(authority, &metadata.notification_config())?;
+                applied.signatures.push(receipt.transaction);
+                applied.receipts.push(receipt);
+            }
+            LaunchStep::Allowlist => {
+                let desired: Vec<_> = plan
+                    .allowlist
+                    .iter()
+                    .flat_map(|allowlist| allowlist.wallets.iter().copied())
+                    .collect();
+                let sync = client.sync_allowlist(authority.pubkey(), &desired)?;
+                let record = client.execute_allowlist_sync(authority, &sync, None)?;
+                applied
+                    .signatures
+                    .extend(record.applied.iter().filter_map(|batch| batch.signature));
+            }
+            LaunchStep::Maintenance(index) => {
+                let window = Self::maintenance_window(plan, index)?;
+                let receipt = client.schedule_maintenance(
+                    authority,
+                    window.start,
+                    window.end,
+                    window.reason.clone(),
+                )?;
+                applied.signatures.push(receipt.transaction);
+                applied.receipts.push(receipt);
+            }
+        }
+        Ok(applied)
+    }
+}
+
+impl LaunchTarget for ClientTarget<'_> {
+    fn program_id(&self) -> Pubkey {
+        self.client.program.id()
+    }
+
+    fn vault_address(&self, plan: &LaunchPlan) -> Result<Pubkey> {
+        let addresses = pda::VaultAddresses::derive(
+            &self.client.program.id(),
+            &self.authority.pubkey(),
+            &plan.vault.mint,
+            &plan.vault.name,
+        )?;
+        Ok(addresses.vault)
+    }
+
+    fn validate(&self, plan: &LaunchPlan) -> Result<()> {
+        let client = self.client;
+        let authority = self.authority.pubkey();
+        client.check_signer(&authority)?;
+        let deadline = client.deadline();
+        client.check_features(deadline)?;
+
+        let mint = client
+            .fetch_mint(plan.vault.mint, deadline)
+            .with_context(|| format!("Mint {} is not usable", plan.vault.mint))?;
+        freeze::check_freezable_mint(
+            plan.vault.mint,
+            &mint,
+            client.strict_mint_policy,
+            plan.vault.acknowledge_freezable_mint,
+        )?;
+        let config = plan.vault_config(&authority, mint.decimals)?;
+
+        // The fee account, if it exists, must be the collector's token
+        // account for the mint; no launch step can fix it otherwise
+        let fee_collector = config.fee_collector.unwrap_or(authority);
+        let fee_account = anchor_spl::associated_token::get_associated_token_address(
+            &fee_collector,
+            &plan.vault.mint,
+        );
+        let rpc = client.rpc_within(deadline, DeadlinePhase::Fetch)?;
+        let account = logging::get_account(&rpc, &fee_account, rpc.commitment())?.value;
+        if FeePathStatus::of(account.as_ref(), &fee_collector, &plan.vault.mint)
+            == FeePathStatus::Invalid
+        {
+            return Err(anyhow!(
+                "Fee account {} exists but is not the token account of {} for mint {}",
+                fee_account,
+                fee_collector,
+                plan.vault.mint
+            ));
+        }
+
+        let vault = self.vault_address(plan)?;
+        let mut windows = Vec::new();
+        if let Some(existing) = self.existing_vault(&vault)? {
+            check_existing_vault(vault, &existing, &config)?;
+            windows = client.fetch_maintenance_windows(vault, deadline)?;
+        }
+        let now = client.cluster_time_within(deadline)?;
+        for (i, window) in plan.maintenance.iter().enumerate() {
+            if !windows.contains(window) {
+                windows = schedule_window(&windows, window.clone(), now)
+                    .with_context(|| format!("Cannot schedule maintenance window #{}", i + 1))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn is_applied(&self, plan: &LaunchPlan, step: LaunchStep) -> Result<bool> {
+        let client = self.client;
+        let vault = self.vault_address(plan)?;
+        match step {
+            LaunchStep::CreateVault => match self.existing_vault(&vault)? {
+                Some(existing) => {
+                    check_existing_vault(vault, &existing, &self.config(plan)?)?;
+                    Ok(true)
+                }
+                None => Ok(false),
+            },
+            LaunchStep::FeePath => Ok(client.verify_fee_path()?.status == FeePathStatus::Ok),
+            LaunchStep::Metadata => {
+                let desired = plan
+                    .metadata
+                    .as_ref()
+                    .map(LaunchMetadata::notification_config);
+                Ok(client.get_notification_config()? == desired)
+            }
+            LaunchStep::Allowlist => {
+                let desired: Vec<_> = plan
+                    .allowlist
+                    .iter()
+                    .flat_map(|allowlist| allowlist.wallets.iter().copied())
+                    .collect();
+                Ok(client
+                    .sync_allowlist(self.authority.pubkey(), &desired)?
+                    .is_empty())
+            }
+            LaunchStep::Maintenance(index) => {
+                let window = Self::maintenance_window(plan, index)?;
+                Ok(client.get_maintenance_windows()?.contains(window))
+            }
+        }
+    }
+
+    fn simulate(&self, plan: &LaunchPlan, step: LaunchStep) -> Result<()> {
+        let config = self.config(plan)?;
+        let simulation = self
+            .client
+            .simulating(|client| Self::send(client, self.authority, plan, &config, step))?;
+        match simulation {
+            Some(simulation) => Ok(simulation.check()?),
+            None => Ok(()),
+        }
+    }
+
+    fn apply(&self, plan: &LaunchPlan, step: LaunchStep) -> Result<AppliedStep> {
+        let config = self.config(plan)?;
+        Self::send(self.client, self.authority, plan, &config, step)
+    }
+
+    fn checks(&self, plan: &LaunchPlan) -> Vec<LaunchCheck> {
+        let client = self.client;
+        let vault = self.vault_address(plan);
+        let config = self.config(plan);
+        vec![
+            LaunchCheck::of(
+                "config",
+                vault.and_then(|vault| {
+                    let existing = self
+                        .existing_vault(&vault)?
+                        .ok_or_else(|| anyhow!("Vault {} does not exist", vault))?;
+                    check_existing_vault(vault, &existing, &config?)
+                }),
+                |_| "matches the plan".to_string(),
+            ),
+            LaunchCheck::of(
+                "freeze",
+                client
+                    .get_freeze_status()
+                    .and_then(|status| Ok(status.check().map(|_| status)?)),
+                |status| status.to_string(),
+            ),
+            LaunchCheck::of(
+                "accounting",
+                client
+                    .get_vault_info()
+                    .and_then(|vault| match vault.accounting_anomaly {
+                        true => Err(anyhow!("a withdrawal underflowed total_deposited")),
+                        false => Ok(()),
+                    }),
+                |_| "ok".to_string(),
+            ),
+            LaunchCheck::of(
+                "fee_path",
+                client
+                    .verify_fee_path()
+                    .and_then(|fee_path| Ok(fee_path.check().map(|_| fee_path)?)),
+                |fee_path| format!("{} ({})", fee_path.account, fee_path.status),
+            ),
+            LaunchCheck::of(
+                "books",
+                client
+                    .verify_books()
+                    .and_then(|report| match report.is_consistent() {
+                        true => Ok(report),
+                        false => Err(anyhow!(
+                            "{} discrepancies; run verify-books",
+                            report.discrepancies.len()
+                        )),
+                    }),
+                |report| format!("{} transactions replayed", report.transactions_replayed),
+            ),
+        ]
+    }
+}
+
+/// Refuse an existing vault the plan cannot launch: another authority, or a
+/// config that differs, which the program cannot change after creation
+fn check_existing_vault(vault: Pubkey, existing: &Vault, config: &VaultConfig) -> Result<()> {
+    let differing: Vec<_> = config_fields(config, Some(existing))
+        .into_iter()
+        .filter(|(_, desired, current)| current.as_ref() != Some(desired))
+        .map(|(field, desired, current)| {
+            format!(
+                "{} is {} instead of {}",
+                field,
+                current.unwrap_or_default(),
+                desired
+            )
+        })
+        .collect();
+    if !differing.is_empty() {
+        return Err(anyhow!(
+            "Vault {} already exists and differs from the plan: {}",
+            vault,
+            differing.join(", ")
+        ));
+    }
+    Ok(())
+}
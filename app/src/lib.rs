@@ -1,22 +1,247 @@
+// Without `tracing` the log events compile away, leaving values computed
+// only for them unused
+#![cfg_attr(not(feature = "tracing"), allow(unused_variables, dead_code))]
+
 use anchor_client::{
     solana_sdk::{
+        address_lookup_table_account::AddressLookupTableAccount,
         commitment_config::CommitmentConfig,
+        instruction::Instruction,
         pubkey::Pubkey,
-        signature::{Keypair, Signer},
+        signature::{Keypair, Signature},
         system_program,
     },
-    Client, Cluster, Program,
+    Cluster,
 };
 use anchor_lang::prelude::*;
-use anchor_spl::token::{self, TokenAccount, Token};
-use anyhow::{anyhow, Result};
-use std::rc::Rc;
-use std::str::FromStr;
-
-/// TokenVaultClient provides a Rust interface to interact with the token vault program
+use anchor_lang::{InstructionData, ToAccountMetas};
+use anchor_spl::token;
+use anyhow::{anyhow, Context, Result};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::AtomicU64;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use shared::{PerThread, SharedProgram};
+use signer::AsSigner;
+use transaction_batch::PreparedOperation;
+
+pub mod accounting;
+pub mod allowlist;
+pub mod amount;
+pub mod assembly;
+#[cfg(feature = "async-client")]
+pub mod async_client;
+pub mod attestation;
+pub mod backfill;
+pub mod batch;
+pub mod books;
+pub mod bulk;
+pub mod cache;
+pub mod changes;
+pub mod client_config;
+#[cfg(feature = "cli")]
+pub mod cli;
+pub mod clock;
+pub mod config;
+pub mod deadline;
+pub mod deposit_source;
+pub mod discovery;
+pub mod dust;
+pub mod endpoints;
+pub mod error;
+pub mod estimates;
+pub mod events;
+pub mod features;
+pub mod fee_claim;
+pub mod fee_path;
+pub mod fee_pool;
+#[cfg(feature = "test-fixtures")]
+pub mod fixtures;
+pub mod freeze;
+#[cfg(feature = "test-fixtures")]
+pub mod harness;
+pub mod history;
+pub mod intent;
+pub mod json;
+pub mod key_policy;
+pub mod launch;
+pub mod lifecycle;
+pub mod liquidity;
+pub mod logging;
+pub mod maintenance;
+pub mod memo;
+pub mod mirror;
+pub mod notification;
+pub mod offline;
+pub mod ops;
+pub mod pagination;
+pub mod pause;
+pub mod plan;
+pub mod policy;
+pub mod positions;
+pub mod priority_fee;
+pub mod provenance;
+pub mod read_only;
+pub mod receipts;
+mod request;
+pub mod reserve;
+pub mod retry;
+pub mod schedule;
+pub mod simulation;
+mod serde_utils;
+mod shared;
+pub mod signer;
+pub mod spending;
+pub mod sponsored;
+pub mod stranded;
+#[cfg(feature = "subscriptions")]
+pub mod subscription;
+#[cfg(feature = "stream")]
+pub mod stream;
+pub mod support;
+pub mod sweep;
+#[cfg(feature = "testing")]
+pub mod testing;
+pub mod transaction_batch;
+pub mod transaction_version;
+pub mod tx_receipt;
+pub mod utils;
+pub mod vault_cache;
+pub mod vault_config;
+pub mod vault_handle;
+pub mod vault_update;
+pub mod withdraw_options;
+pub mod withdrawal_request;
+pub mod wsol;
+
+#[cfg(all(feature = "test-hooks", not(debug_assertions)))]
+compile_error!("`test-hooks` fakes the client's clock and must not be enabled in release builds");
+
+/// Program account, instruction and PDA types, re-exported under their historical path
+pub use token_vault_types as token_vault;
+
+pub use allowlist::{AllowlistChange, AllowlistSyncPlan, AllowlistSyncRecord, AppliedBatch};
+pub use amount::{AddressBook, MintAmount, UiAmount};
+#[cfg(feature = "async-client")]
+pub use async_client::AsyncTokenVaultClient;
+pub use attestation::{Attestation, VerifiedClaim};
+pub use batch::{BatchOutcome, BatchPlan, BatchResult, VaultOperation};
+pub use backfill::{BackfillConfig, BackfillProgress, BackfillReport, HistoryBackfill, TransactionSink};
+pub use books::{BooksReport, Discrepancy, UserPosition};
+pub use cache::{CacheMetrics, CachedKind, RpcCache, RpcCacheConfig};
+pub use changes::{StateChange, VaultChange};
+pub use client_config::ClientConfig;
+pub use clock::ClockSource;
+pub use config::{ConfigFile, ConnectionSettings, ResolvedConfig};
+pub use deadline::{Deadline, DeadlinePhase};
+pub use deposit_source::{SourceSuggestion, TokenHolding};
+pub use discovery::{NameMatch, VaultFilter, VaultIndex, VaultSummary};
+pub use dust::{DustMode, DustPolicy, WithdrawCapacity, WithdrawPreview, WithdrawalQuote};
+pub use endpoints::{EndpointHealth, EndpointStatus};
+pub use error::TokenVaultError;
+pub use estimates::{CostEstimate, EstimateTables, PriorityFees};
+pub use events::{DepositEvent, VaultEvent, WithdrawEvent};
+pub use features::{Feature, FeatureSet, ProgramFeatures};
+pub use fee_path::{FeePath, FeePathStatus, RepairReport};
+pub use fee_pool::{FeeReservePool, PayerChoice, PayerFunding, PayerSpend};
+pub use freeze::FreezeStatus;
+pub use history::{DecodeStats, VaultInstruction, VaultInstructionKind, VaultTransaction};
+pub use intent::{AccountLookup, IntentViolation, VerifiedAction, VerifiedIntent};
+pub use key_policy::{KeyBucket, KeyPolicy};
+pub use launch::{
+    verify_launch_report, LaunchCheck, LaunchOptions, LaunchPlan, LaunchReport, LaunchStep,
+    LaunchTarget, StepRecord, StepStatus, VaultLauncher,
+};
+pub use lifecycle::VaultLifecycle;
+pub use liquidity::{LiquidityProfile, VaultBalance, VaultSnapshot};
+pub use maintenance::{MaintenanceStatus, MaintenanceWindow};
+pub use memo::DepositOptions;
+pub use mirror::{MirrorOptions, MirrorReport, MirrorStatus, MirroredField};
+pub use notification::NotificationConfig;
+pub use ops::VaultOps;
+pub use pagination::{Cursor, Page, Paginated};
+pub use plan::{ClosePlan, ConfigChanges, ConfigDiff, FieldChange, InitPlan};
+pub use policy::{Operation, OperationKind, OperationPolicy};
+pub use priority_fee::{PriorityFeeConfig, PriorityFeeStrategy};
+pub use provenance::{CheckedAgainst, Provenance};
+pub use read_only::ReadOnlyVaultClient;
+pub use receipts::{verify_action_receipt, ActionReceipt, AdminState};
+pub use reserve::{DepositQuote, ReservePolicy};
+pub use retry::{RetryCondition, RetryPolicy};
+pub use schedule::{DepositWindow, DepositWindowStatus};
+pub use signer::{RemoteSigner, VaultSigner};
+pub use simulation::Simulation;
+pub use spending::{
+    FileSpendingStore, MemorySpendingStore, SpendingGuard, SpendingLedger, SpendingLimits,
+    SpendingStore,
+};
+pub use stranded::{StrandedAccount, StrandedReport};
+pub use support::{EffectiveConfig, RedactionRules, SupportBundle};
+pub use sweep::SweepReceipt;
+pub use transaction_batch::TransactionBatch;
+pub use transaction_version::{TransactionVersion, VaultLookupTable};
+pub use tx_receipt::{InitializedVault, TxReceipt};
+pub use utils::KeypairSource;
+pub use vault_cache::{CacheConfig, VaultCache};
+pub use vault_config::{VaultConfig, VaultConfigBuilder, WithdrawalLimit};
+pub use vault_handle::VaultHandle;
+pub use vault_update::UpdateVaultParams;
+pub use withdraw_options::{PayoutAccount, WithdrawOptions};
+pub use withdrawal_request::PendingWithdrawal;
+
+/// TokenVaultClient provides a Rust interface to interact with the token vault program.
+/// It is `Send + Sync`, and clones share its caches, fee pool and policies.
+#[derive(Clone)]
 pub struct TokenVaultClient {
-    program: Program,
+    program: SharedProgram,
+    payer: Arc<Keypair>,
+    cluster: Cluster,
+    /// WebSocket endpoint subscriptions connect to
+    ws_url: String,
     vault_address: Option<Pubkey>,
+    cache: Option<Arc<RpcCache>>,
+    /// Decoded vaults; see `with_vault_cache`
+    vault_cache: Arc<VaultCache>,
+    timeout: Option<Duration>,
+    call_deadline: PerThread<Deadline>,
+    /// Read commitment of the call in progress; see `at_commitment`
+    call_commitment: PerThread<CommitmentConfig>,
+    key_policy: Option<KeyPolicy>,
+    clock: Option<Arc<dyn ClockSource>>,
+    policies: Vec<Arc<dyn OperationPolicy>>,
+    read_commitment: Option<CommitmentConfig>,
+    strict_commitment: bool,
+    strict_mint_policy: bool,
+    strict_accounting: bool,
+    include_extensions: bool,
+    log_redaction: bool,
+    receipt_details: bool,
+    read_rpc_url: Option<String>,
+    fee_pool: Option<Arc<FeeReservePool>>,
+    priority_fee: Option<PriorityFeeConfig>,
+    transaction_version: TransactionVersion,
+    /// Lookup tables read so far; see `with_transaction_version`
+    lookup_tables: Arc<Mutex<HashMap<Pubkey, AddressLookupTableAccount>>>,
+    retry_policy: Option<RetryPolicy>,
+    estimates: Arc<Mutex<Option<EstimateTables>>>,
+    estimates_ttl: Duration,
+    estimates_path: Option<PathBuf>,
+    /// Decimals of the mints read so far, which never change
+    mint_decimals: Arc<Mutex<HashMap<Pubkey, u8>>>,
+    /// Simulate transactions instead of sending them; see `simulating`
+    simulate_sends: PerThread<bool>,
+    /// Submissions the thread's last sent transaction took; see
+    /// `send_with_retries`
+    send_attempts: PerThread<u32>,
+    /// The thread's transaction reached the send endpoint, so it may land
+    /// even if the call fails; see `send_operations`
+    submitted: PerThread<bool>,
+    /// Write endpoint slot after the client's last mutation
+    min_context_slot: Arc<AtomicU64>,
+    /// Highest slot the read endpoint has reported
+    replica_slot: Arc<AtomicU64>,
 }
 
 impl TokenVaultClient {
@@ -26,63 +251,173 @@ impl TokenVaultClient {
         payer: Keypair,
         program_id: Pubkey,
     ) -> Result<Self> {
-        let client = Client::new_with_options(
-            cluster,
-            Rc::new(payer),
-            CommitmentConfig::confirmed(),
-        );
+        Self::new_with_config(cluster, payer, program_id, ClientConfig::default())
+    }
 
-        let program = client.program(program_id);
+    /// Create a client with the commitment, timeout and endpoints in
+    /// `config`. `cluster` still decides key policy checks when the
+    /// endpoints are replaced.
+    pub fn new_with_config(
+        cluster: Cluster,
+        payer: Keypair,
+        program_id: Pubkey,
+        config: ClientConfig,
+    ) -> Result<Self> {
+        let payer = Arc::new(payer);
+        let transport = config.transport(&cluster)?;
+        let ws_url = transport.ws_url().to_string();
+        let program = SharedProgram::new(transport, payer.clone(), program_id, config.commitment);
 
-        Ok(Self {
+        let client = Self {
             program,
+            payer,
+            cluster,
+            ws_url,
             vault_address: None,
-        })
+            cache: None,
+            vault_cache: Arc::new(VaultCache::new(config.vault_cache)),
+            timeout: config.rpc_timeout,
+            call_deadline: PerThread::default(),
+            call_commitment: PerThread::default(),
+            key_policy: None,
+            clock: None,
+            policies: Vec::new(),
+            read_commitment: config.read_commitment,
+            strict_commitment: false,
+            strict_mint_policy: false,
+            strict_accounting: false,
+            include_extensions: false,
+            log_redaction: false,
+            receipt_details: true,
+            read_rpc_url: None,
+            fee_pool: None,
+            priority_fee: config.priority_fee,
+            transaction_version: config.transaction_version,
+            lookup_tables: Arc::new(Mutex::new(HashMap::new())),
+            retry_policy: None,
+            estimates: Arc::new(Mutex::new(None)),
+            estimates_ttl: estimates::DEFAULT_ESTIMATES_TTL,
+            estimates_path: None,
+            mint_decimals: Arc::new(Mutex::new(HashMap::new())),
+            simulate_sends: PerThread::default(),
+            send_attempts: PerThread::default(),
+            submitted: PerThread::default(),
+            min_context_slot: Arc::new(AtomicU64::new(0)),
+            replica_slot: Arc::new(AtomicU64::new(0)),
+        };
+
+        // An unreachable cluster is left for the first real call to report
+        if let Ok(Some(features)) = client.get_program_features() {
+            features.check(FeatureSet::SUPPORTED, features::CLIENT_VERSION)?;
+        }
+        Ok(client)
     }
 
     /// Set the vault address to interact with
+    #[deprecated(note = "bind the vault with `client.vault(address)`, which returns a `VaultHandle`")]
     pub fn with_vault(&mut self, vault_address: Pubkey) -> &mut Self {
+        self.set_vault(vault_address)
+    }
+
+    /// `with_vault`, for the crate's own wrappers and loaders, which keep
+    /// it until the deprecated path is removed
+    pub(crate) fn set_vault(&mut self, vault_address: Pubkey) -> &mut Self {
         self.vault_address = Some(vault_address);
         self
     }
 
-    /// Initialize a new vault
+    /// The vault set with `with_vault`
+    pub(crate) fn current_vault(&self) -> std::result::Result<Pubkey, TokenVaultError> {
+        self.vault_address.ok_or(TokenVaultError::VaultNotSet)
+    }
+
+    /// Enable caching of account reads; the client's own mutations invalidate
+    /// the accounts they touch
+    pub fn with_rpc_cache(&mut self, config: RpcCacheConfig) -> &mut Self {
+        self.cache = Some(Arc::new(RpcCache::new(config, self.read_rpc_url())));
+        self
+    }
+
+    /// Hit/miss counters of the RPC cache, if enabled
+    pub fn cache_metrics(&self) -> Option<CacheMetrics> {
+        self.cache.as_ref().map(|cache| cache.metrics())
+    }
+
+    /// Drop any cached data for `address`
+    pub fn invalidate(&self, address: &Pubkey) {
+        self.vault_cache.invalidate(address);
+        if let Some(cache) = &self.cache {
+            cache.invalidate(address);
+        }
+    }
+
+    /// Initialize a new vault from a config built with `VaultConfig::builder()`
+    ///
+    /// An `immutable` vault can never have its configuration, pause state or
+    /// authority changed after creation. This is irreversible. Deposits are
+    /// only accepted inside `deposit_window`. Withdrawals below the
+    /// `dust_policy` threshold are rejected or fee-free. The `reserve` share
+    /// of every deposit goes to the vault's insurance reserve. A withdrawal
+    /// limit given in whole tokens is converted with the mint's decimals.
     pub fn initialize_vault(
         &self,
-        authority: &Keypair,
+        authority: &dyn VaultSigner,
         token_mint: Pubkey,
-        vault_name: &str,
-        fee_percentage: u16,
-        withdrawal_timelock: i64,
-        withdrawal_limit: u64,
-    ) -> Result<Pubkey> {
-        // Derive vault address
-        let (vault_address, _) = Pubkey::find_program_address(
-            &[
-                b"vault".as_ref(),
-                authority.pubkey().as_ref(),
-                token_mint.as_ref(),
-                vault_name.as_bytes(),
-            ],
+        config: &VaultConfig,
+    ) -> Result<InitializedVault> {
+        self.check_signer(&authority.pubkey())?;
+        let deadline = self.deadline();
+        self.check_features(deadline)?;
+
+        // The mint decides the decimals of a UI withdrawal limit, and whether
+        // a third party can freeze the vault's tokens
+        let mint = self.fetch_mint(token_mint, deadline)?;
+        freeze::check_freezable_mint(
+            token_mint,
+            &mint,
+            self.strict_mint_policy,
+            config.acknowledge_freezable_mint,
+        )?;
+        if let Some(freeze_authority) = freeze::freeze_authority(&mint) {
+            logging::warn!(
+                mint = %token_mint,
+                freeze_authority = %freeze_authority,
+                "mint has a freeze authority, which can freeze the vault's tokens"
+            );
+        }
+        let withdrawal_limit = config
+            .withdrawal_limit
+            .to_base_units(mint.decimals)
+            .context("Invalid withdrawal limit")?;
+        let vault_name = config.name.as_str();
+
+        // Derive the vault, its token account and its reserve
+        let token_vault::pda::VaultAddresses {
+            vault: vault_address,
+            vault_token_account,
+            reserve_token_account,
+            ..
+        } = token_vault::pda::VaultAddresses::derive(
             &self.program.id(),
-        );
+            &authority.pubkey(),
+            &token_mint,
+            vault_name,
+        )?;
 
-        // Derive token account address that will hold the tokens
-        let (vault_token_account, _) = Pubkey::find_program_address(
-            &[b"vault_token_account".as_ref(), vault_address.as_ref()],
-            &self.program.id(),
+        logging::debug!(
+            vault = %support::hash_pubkey(&vault_address),
+            "derived vault addresses"
         );
 
-        println!("Creating vault with address: {}", vault_address);
-
         // Build and send transaction
-        let signature = self
-            .program
-            .request()
+        let signer = AsSigner(authority);
+        let request = self
+            .vault_request(&[vault_address])?
             .accounts(token_vault::accounts::InitializeVault {
                 authority: authority.pubkey(),
                 vault: vault_address,
                 vault_token_account,
+                reserve_token_account,
                 token_mint,
                 token_program: token::ID,
                 system_program: system_program::ID,
@@ -90,222 +425,566 @@ impl TokenVaultClient {
             })
             .args(token_vault::instruction::InitializeVault {
                 name: vault_name.to_string(),
-                fee_percentage,
-                withdrawal_timelock,
+                fee_percentage: config.fee_bps,
+                withdrawal_timelock: config.timelock,
                 withdrawal_limit,
+                immutable: config.immutable,
+                deposit_window_start: config.deposit_window.start,
+                deposit_window_end: config.deposit_window.end,
+                dust_threshold: config.dust_policy.threshold,
+                waive_dust_fee: config.dust_policy.mode == DustMode::WaiveFee,
+                reserve_bps: config.reserve.bps,
+                reserve_timelock: config.reserve.timelock,
+                fee_collector: config.fee_collector.unwrap_or(authority.pubkey()),
             })
-            .signer(authority)
-            .send()?;
+            .signer(&signer);
+        let operation = Operation::new(
+            OperationKind::InitializeVault,
+            authority.pubkey(),
+            Some(vault_address),
+            0,
+        );
+        let signature = self.send_operation(operation, request, deadline)?;
+
+        self.invalidate(&vault_address);
+        self.invalidate(&vault_token_account);
+        self.invalidate(&reserve_token_account);
+
+        Ok(InitializedVault {
+            address: vault_address,
+            receipt: self.tx_receipt(signature),
+        })
+    }
 
-        println!("Vault created successfully! Signature: {}", signature);
-        Ok(vault_address)
+    /// Initialize a new vault from positional parameters
+    #[deprecated(note = "build a `VaultConfig` with `VaultConfig::builder()` and call `initialize_vault`")]
+    #[allow(clippy::too_many_arguments)]
+    pub fn initialize_vault_positional(
+        &self,
+        authority: &dyn VaultSigner,
+        token_mint: Pubkey,
+        vault_name: &str,
+        fee_percentage: u16,
+        withdrawal_timelock: i64,
+        withdrawal_limit: u64,
+        immutable: bool,
+        deposit_window: DepositWindow,
+        dust_policy: DustPolicy,
+        reserve: ReservePolicy,
+    ) -> Result<InitializedVault> {
+        let timelock = u64::try_from(withdrawal_timelock)
+            .map_err(|_| anyhow!("Withdrawal timelock cannot be negative"))?;
+        let config = VaultConfig::builder()
+            .name(vault_name)
+            .fee_bps(fee_percentage)
+            .timelock(Duration::from_secs(timelock))
+            .withdrawal_limit(withdrawal_limit)
+            .immutable(immutable)
+            .deposit_window(deposit_window)
+            .dust_policy(dust_policy)
+            .reserve(reserve)
+            .build()?;
+        self.initialize_vault(authority, token_mint, &config)
     }
 
-    /// Deposit tokens into the vault
+    /// Deposit tokens into the vault from the depositor's associated token
+    /// account for the vault's mint
     pub fn deposit(
         &self,
-        depositor: &Keypair,
+        depositor: &dyn VaultSigner,
         amount: u64,
-    ) -> Result<()> {
-        let vault = self.vault_address.ok_or_else(|| anyhow!("Vault address not set"))?;
+    ) -> Result<TxReceipt> {
+        self.deposit_in_mint(self.current_vault()?, depositor, None, amount, None)
+    }
 
-        // Derive the vault token account address
-        let (vault_token_account, _) = Pubkey::find_program_address(
-            &[b"vault_token_account".as_ref(), vault.as_ref()],
-            &self.program.id(),
-        );
+    /// Deposit tokens into the vault from `source`, any token account of the
+    /// vault's mint that the depositor owns or is a delegate of. A `source`
+    /// of another mint is refused with
+    /// `TokenVaultError::TokenAccountMintMismatch` before sending.
+    pub fn deposit_from(
+        &self,
+        depositor: &dyn VaultSigner,
+        source: Pubkey,
+        amount: u64,
+    ) -> Result<TxReceipt> {
+        self.deposit_in_mint(self.current_vault()?, depositor, Some(source), amount, None)
+    }
 
-        // Get vault data to determine the token mint
-        let vault_data: token_vault::state::Vault = self.program.account(vault)?;
-        let token_mint = vault_data.token_mint;
+    /// Deposit into the vault, refusing an amount of another mint than the
+    /// vault's with `TokenVaultError::MintMismatch`
+    pub fn deposit_amount(
+        &self,
+        depositor: &dyn VaultSigner,
+        amount: MintAmount,
+    ) -> Result<TxReceipt> {
+        let vault = self.current_vault()?;
+        self.deposit_in_mint(vault, depositor, None, amount.raw, Some(amount.mint))
+    }
 
-        // Derive the depositor's token account
-        let depositor_token_account = anchor_client::solana_sdk::associated_token::get_associated_token_address(
-            &depositor.pubkey(),
-            &token_mint,
-        );
+    pub(crate) fn deposit_in_mint(
+        &self,
+        vault: Pubkey,
+        depositor: &dyn VaultSigner,
+        source: Option<Pubkey>,
+        amount: u64,
+        mint: Option<Pubkey>,
+    ) -> Result<TxReceipt> {
+        if let Some(source) = source {
+            logging::debug!(
+                source = %support::hash_pubkey(&source),
+                "depositing from a token account"
+            );
+        }
+        let source = source.map_or(DepositSource::Associated, DepositSource::Account);
+        let (signature, quote) =
+            self.send_deposit(vault, depositor, source, amount, mint, None, None)?;
+        if quote.reserve > 0 {
+            logging::debug!(
+                reserve = quote.reserve,
+                credited = quote.credited,
+                "reserve carve-out"
+            );
+        }
+        Ok(self.tx_receipt(signature))
+    }
 
-        println!("Depositing {} tokens to vault {}", amount, vault);
+    /// `deposit` into `vault`, which need not be the current vault, returning
+    /// the quote the deposit was sent under. The tokens come from `source`. With a
+    /// `mint`, the amount must be denominated in the vault's mint. A `fee_payer`
+    /// pays the transaction fee instead of the client's payer, and a `memo` is
+    /// attached to the transaction.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn send_deposit(
+        &self,
+        vault: Pubkey,
+        depositor: &dyn VaultSigner,
+        source: DepositSource,
+        amount: u64,
+        mint: Option<Pubkey>,
+        fee_payer: Option<&Keypair>,
+        memo: Option<&str>,
+    ) -> Result<(Signature, DepositQuote)> {
+        if let Some(fee_payer) = fee_payer {
+            self.check_signer(&fee_payer.pubkey())?;
+        }
+        let deadline = self.deadline();
+        let (prepared, quote) = self.prepare_deposit(
+            vault,
+            depositor.pubkey(),
+            source,
+            amount,
+            mint,
+            memo,
+            deadline,
+        )?;
+        let signer = AsSigner(depositor);
+        let signature = self.send_prepared(vec![prepared], &[&signer], deadline, fee_payer)?;
+        Ok((signature, quote))
+    }
 
-        // Build and send transaction
-        let signature = self
-            .program
-            .request()
-            .accounts(token_vault::accounts::Deposit {
-                depositor: depositor.pubkey(),
+    /// The checks and instructions of `send_deposit`, for `depositor` to sign
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn prepare_deposit(
+        &self,
+        vault: Pubkey,
+        depositor: Pubkey,
+        source: DepositSource,
+        amount: u64,
+        mint: Option<Pubkey>,
+        memo: Option<&str>,
+        deadline: Deadline,
+    ) -> Result<(PreparedOperation, DepositQuote)> {
+        dust::ensure_nonzero("deposit", amount)?;
+        if let Some(memo) = memo {
+            memo::check_memo(memo)?;
+        }
+        self.check_signer(&depositor)?;
+        self.check_features(deadline)?;
+
+        // Derive the vault token account address
+        let (vault_token_account, _) =
+            token_vault::pda::find_vault_token_account_address(&self.program.id(), &vault);
+        let (reserve_token_account, _) =
+            token_vault::pda::find_reserve_token_account_address(&self.program.id(), &vault);
+        let (position, _) =
+            token_vault::pda::find_position_address(&self.program.id(), &vault, &depositor);
+
+        // Get vault data to determine the token mint and reserve carve-out
+        let (vault_data, provenance) = self.fetch_vault_state(vault, deadline, true)?;
+        let token_mint = vault_data.token_mint;
+        amount::ensure_mint(token_mint, mint).context(CheckedAgainst(provenance))?;
+        let quote = DepositQuote::for_vault(&vault_data, amount)?;
+
+        // Refuse deposits the vault does not accept right now: outside its
+        // deposit window, during maintenance, while frozen or paused
+        self.vault_lifecycle(vault, &vault_data, deadline)?
+            .check(OperationKind::Deposit)
+            .context(CheckedAgainst(provenance))?;
+
+        // The depositor's token account: the one given, else derived
+        let depositor_token_account = match source {
+            DepositSource::Account(source) => source,
+            DepositSource::Associated | DepositSource::WrappedSol => {
+                anchor_spl::associated_token::get_associated_token_address(&depositor, &token_mint)
+            }
+        };
+        assembly::check_not_self_transfer(&depositor_token_account, &vault_token_account)?;
+        assembly::check_not_self_transfer(&depositor_token_account, &reserve_token_account)?;
+        match source {
+            DepositSource::Account(source) => {
+                self.verify_source_account(&depositor, &source, &token_mint, amount, deadline)
+            }
+            DepositSource::Associated => {
+                self.verify_deposit_source(&depositor, &token_mint, amount, deadline)
+            }
+            DepositSource::WrappedSol => self.verify_sol_to_wrap(&depositor, amount, deadline),
+        }
+        .context(CheckedAgainst(provenance))?;
+        let source_exists =
+            self.check_not_frozen(vault, &vault_data, Some(depositor_token_account), deadline)?;
+
+        // Wrapped SOL is funded ahead of the deposit, and a wSOL account
+        // created for it is closed afterwards
+        let mut instructions = Vec::new();
+        if source == DepositSource::WrappedSol {
+            instructions.extend(wsol::wrap_instructions(&depositor, amount)?);
+        }
+        instructions.push(Instruction {
+            program_id: self.program.id(),
+            accounts: token_vault::accounts::Deposit {
+                depositor,
                 vault,
                 vault_token_account,
+                reserve_token_account,
                 depositor_token_account,
                 token_program: token::ID,
-            })
-            .args(token_vault::instruction::Deposit {
-                amount,
-            })
-            .signer(depositor)
-            .send()?;
-
-        println!("Deposit successful! Signature: {}", signature);
-        Ok(())
+                position,
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data: token_vault::instruction::Deposit { amount }.data(),
+        });
+        if source == DepositSource::WrappedSol && !source_exists {
+            instructions.push(wsol::unwrap_instruction(&depositor)?);
+        }
+        if let Some(memo) = memo {
+            instructions.push(memo::memo_instruction(memo, &depositor));
+        }
+        let operation = Operation::new(OperationKind::Deposit, depositor, Some(vault), amount);
+        let prepared = PreparedOperation {
+            operation: Some(operation),
+            instructions,
+            touched: vec![
+                vault,
+                vault_token_account,
+                reserve_token_account,
+                depositor_token_account,
+                position,
+            ],
+        };
+        Ok((prepared, quote))
     }
 
     /// Withdraw tokens from the vault
+    ///
+    /// With a `recipient`, the tokens go to the recipient's associated token
+    /// account, created first if it does not exist, instead of the
+    /// withdrawer's. The withdrawer still signs and is debited.
     pub fn withdraw(
         &self,
-        withdrawer: &Keypair,
+        withdrawer: &dyn VaultSigner,
         amount: u64,
-    ) -> Result<()> {
-        let vault = self.vault_address.ok_or_else(|| anyhow!("Vault address not set"))?;
+        recipient: Option<Pubkey>,
+    ) -> Result<TxReceipt> {
+        let recipient = recipient.unwrap_or_else(|| withdrawer.pubkey());
+        self.withdraw_in_mint(
+            self.current_vault()?,
+            withdrawer,
+            amount,
+            None,
+            Payout::Wallet(recipient),
+            WithdrawOptions::default(),
+        )
+    }
 
-        // Derive the vault token account address
-        let (vault_token_account, _) = Pubkey::find_program_address(
-            &[b"vault_token_account".as_ref(), vault.as_ref()],
-            &self.program.id(),
-        );
+    /// Withdraw tokens from the vault into `destination`, any existing token
+    /// account of the vault's mint. The withdrawer still signs and is
+    /// debited. A `destination` of another mint is refused with
+    /// `TokenVaultError::TokenAccountMintMismatch` before sending; one that
+    /// does not exist is not created.
+    pub fn withdraw_to(
+        &self,
+        withdrawer: &dyn VaultSigner,
+        destination: Pubkey,
+        amount: u64,
+    ) -> Result<TxReceipt> {
+        self.withdraw_in_mint(
+            self.current_vault()?,
+            withdrawer,
+            amount,
+            None,
+            Payout::TokenAccount(destination),
+            WithdrawOptions::default(),
+        )
+    }
 
-        // Get vault data to determine the token mint and authority
-        let vault_data: token_vault::state::Vault = self.program.account(vault)?;
-        let token_mint = vault_data.token_mint;
-        let vault_authority = vault_data.authority;
+    /// Withdraw from the vault, refusing an amount of another mint than the
+    /// vault's with `TokenVaultError::MintMismatch`
+    pub fn withdraw_amount(
+        &self,
+        withdrawer: &dyn VaultSigner,
+        amount: MintAmount,
+        recipient: Option<Pubkey>,
+    ) -> Result<TxReceipt> {
+        let recipient = recipient.unwrap_or_else(|| withdrawer.pubkey());
+        self.withdraw_in_mint(
+            self.current_vault()?,
+            withdrawer,
+            amount.raw,
+            Some(amount.mint),
+            Payout::Wallet(recipient),
+            WithdrawOptions::default(),
+        )
+    }
 
-        // Derive the depositor's token account
-        let withdrawer_token_account = anchor_client::solana_sdk::associated_token::get_associated_token_address(
-            &withdrawer.pubkey(),
-            &token_mint,
-        );
+    pub(crate) fn withdraw_in_mint(
+        &self,
+        vault: Pubkey,
+        withdrawer: &dyn VaultSigner,
+        amount: u64,
+        mint: Option<Pubkey>,
+        payout: Payout,
+        options: WithdrawOptions,
+    ) -> Result<TxReceipt> {
+        match payout {
+            Payout::Wallet(recipient) if recipient != withdrawer.pubkey() => logging::debug!(
+                recipient = %support::hash_pubkey(&recipient),
+                "paying a third party"
+            ),
+            Payout::Wallet(_) => {}
+            Payout::TokenAccount(destination) => logging::debug!(
+                destination = %support::hash_pubkey(&destination),
+                "paying into a token account"
+            ),
+            Payout::Unwrapped => logging::debug!("unwrapping to native SOL"),
+        }
+        let memo = options.memo.clone();
+        let signature = self.send_withdraw(vault, withdrawer, amount, mint, payout, options)?;
+        Ok(TxReceipt {
+            memo,
+            ..self.tx_receipt(signature)
+        })
+    }
 
-        // Derive the fee collector token account
-        let fee_collector_token_account = anchor_client::solana_sdk::associated_token::get_associated_token_address(
-            &vault_data.fee_collector,
-            &token_mint,
-        );
+    /// `withdraw` from `vault`, which need not be the current vault. With a
+    /// `mint`, the amount must be denominated in the vault's mint.
+    pub(crate) fn send_withdraw(
+        &self,
+        vault: Pubkey,
+        withdrawer: &dyn VaultSigner,
+        amount: u64,
+        mint: Option<Pubkey>,
+        payout: Payout,
+        options: WithdrawOptions,
+    ) -> Result<Signature> {
+        let deadline = self.deadline();
+        let prepared = self.prepare_withdraw(
+            vault,
+            withdrawer.pubkey(),
+            amount,
+            mint,
+            payout,
+            &options,
+            deadline,
+        )?;
+        let signer = AsSigner(withdrawer);
+        self.send_prepared(vec![prepared], &[&signer], deadline, None)
+    }
 
-        println!("Withdrawing {} tokens from vault {}", amount, vault);
+    /// The checks and instructions of `send_withdraw`, for `withdrawer` to sign
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn prepare_withdraw(
+        &self,
+        vault: Pubkey,
+        withdrawer: Pubkey,
+        amount: u64,
+        mint: Option<Pubkey>,
+        payout: Payout,
+        options: &WithdrawOptions,
+        deadline: Deadline,
+    ) -> Result<PreparedOperation> {
+        self.check_signer(&withdrawer)?;
+        if let Some(memo) = &options.memo {
+            memo::check_memo(memo)?;
+        }
+        self.check_features(deadline)?;
 
-        // Build and send transaction
-        let signature = self
-            .program
-            .request()
-            .accounts(token_vault::accounts::Withdraw {
-                withdrawer: withdrawer.pubkey(),
+        // Derive the vault token account address
+        let (vault_token_account, _) =
+            token_vault::pda::find_vault_token_account_address(&self.program.id(), &vault);
+        let (position, _) =
+            token_vault::pda::find_position_address(&self.program.id(), &vault, &withdrawer);
+
+        // Get vault data to determine the token mint
+        let (vault_data, provenance) = self.fetch_vault_state(vault, deadline, true)?;
+        let token_mint = vault_data.token_mint;
+        amount::ensure_mint(token_mint, mint).context(CheckedAgainst(provenance))?;
+        pause::check_not_paused(&vault_data).context(CheckedAgainst(provenance))?;
+
+        // Refuse zero, (by vault policy) dust and over-limit withdrawals
+        WithdrawalQuote::for_vault(&vault_data, amount).context(CheckedAgainst(provenance))?;
+        if !options.skip_limit_check {
+            dust::check_withdrawal_limit(&vault_data, amount)
+                .context(CheckedAgainst(provenance))?;
+        }
+        self.check_accounting(&vault_data, amount)
+            .context(CheckedAgainst(provenance))?;
+
+        // The destination token account: the one given, else the recipient's
+        let destination_token_account = match payout {
+            Payout::Wallet(recipient) => {
+                anchor_spl::associated_token::get_associated_token_address(&recipient, &token_mint)
+            }
+            Payout::Unwrapped => wsol::wsol_account(&withdrawer),
+            Payout::TokenAccount(destination) => {
+                self.verify_destination_account(&destination, &token_mint, deadline)
+                    .context(CheckedAgainst(provenance))?;
+                destination
+            }
+        };
+        assembly::check_not_self_transfer(&vault_token_account, &destination_token_account)?;
+        let destination_exists =
+            self.check_not_frozen(vault, &vault_data, Some(destination_token_account), deadline)?;
+
+        // The withdrawer's own token account must exist, or be created first
+        // when the options allow it. The fee stays in the vault, so the fee
+        // collector's account plays no part until `claim_fees`
+        let mut payout_accounts = Vec::new();
+        if payout == Payout::Wallet(withdrawer) {
+            payout_accounts.push(PayoutAccount {
+                owner: withdrawer,
+                address: destination_token_account,
+                exists: destination_exists,
+            });
+        }
+        let create_accounts = withdraw_options::create_missing_accounts(
+            &self.program.payer(),
+            &token_mint,
+            &payout_accounts,
+            options,
+        )
+        .context(CheckedAgainst(provenance))?;
+
+        let mut instructions = create_accounts;
+        let recipient = match payout {
+            Payout::Wallet(recipient) => Some(recipient),
+            Payout::TokenAccount(_) | Payout::Unwrapped => None,
+        };
+        if let Some(recipient) = recipient.filter(|r| *r != withdrawer) {
+            instructions.push(
+                spl_associated_token_account::instruction::create_associated_token_account_idempotent(
+                    &self.program.payer(),
+                    &recipient,
+                    &token_mint,
+                    &token::ID,
+                ),
+            );
+        }
+        // The wSOL account is created and closed around the withdrawal, so
+        // the withdrawer pays its rent and gets it back
+        if payout == Payout::Unwrapped {
+            instructions.push(wsol::create_instruction(&withdrawer));
+        }
+        instructions.push(Instruction {
+            program_id: self.program.id(),
+            accounts: token_vault::accounts::Withdraw {
+                withdrawer,
                 vault,
                 vault_token_account,
-                withdrawer_token_account,
-                fee_collector_token_account,
+                withdrawer_token_account: destination_token_account,
                 token_program: token::ID,
-            })
-            .args(token_vault::instruction::Withdraw {
-                amount,
-            })
-            .signer(withdrawer)
-            .send()?;
-
-        println!("Withdrawal successful! Signature: {}", signature);
-        Ok(())
+                position,
+            }
+            .to_account_metas(None),
+            data: token_vault::instruction::Withdraw { amount }.data(),
+        });
+        if payout == Payout::Unwrapped {
+            instructions.push(wsol::unwrap_instruction(&withdrawer)?);
+        }
+        if let Some(memo) = &options.memo {
+            instructions.push(memo::memo_instruction(memo, &withdrawer));
+        }
+        let operation = Operation::new(OperationKind::Withdraw, withdrawer, Some(vault), amount);
+        Ok(PreparedOperation {
+            operation: Some(operation),
+            instructions,
+            touched: vec![
+                vault,
+                vault_token_account,
+                destination_token_account,
+                position,
+            ],
+        })
     }
 
     /// Get vault information
     pub fn get_vault_info(&self) -> Result<token_vault::state::Vault> {
-        let vault = self.vault_address.ok_or_else(|| anyhow!("Vault address not set"))?;
-        let vault_data: token_vault::state::Vault = self.program.account(vault)?;
-        Ok(vault_data)
-    }
-}
-
-/// Namespace for mock token vault program structures (replace with actual program structures)
-pub mod token_vault {
-    use anchor_lang::prelude::*;
-    
-    pub mod state {
-        use super::*;
-        
-        #[account]
-        pub struct Vault {
-            pub authority: Pubkey,
-            pub token_mint: Pubkey,
-            pub fee_collector: Pubkey,
-            pub fee_percentage: u16,
-            pub withdrawal_timelock: i64,
-            pub withdrawal_limit: u64,
-            pub total_deposited: u64,
-            pub name: String,
-            pub bump: u8,
+        let deadline = self.deadline();
+        let vault = self.current_vault()?;
+        if let Some(cache) = &self.cache {
+            let rpc = self.rpc_within(deadline, DeadlinePhase::Fetch)?;
+            let commitment = self.read_commitment_for(&rpc);
+            let cached = cache.get_account(&rpc, vault, commitment, CachedKind::Vault)?;
+            let vault_data =
+                token_vault::state::Vault::try_deserialize(&mut cached.account.data.as_slice())?;
+            return Ok(vault_data);
         }
+        self.fetch_vault(vault, deadline)
     }
 
-    pub mod accounts {
-        use super::*;
-        
-        #[derive(Accounts)]
-        pub struct InitializeVault<'info> {
-            pub authority: Signer<'info>,
-            #[account(mut)]
-            pub vault: AccountInfo<'info>,
-            #[account(mut)]
-            pub vault_token_account: AccountInfo<'info>,
-            pub token_mint: AccountInfo<'info>,
-            pub token_program: AccountInfo<'info>,
-            pub system_program: AccountInfo<'info>,
-            pub rent: AccountInfo<'info>,
-        }
-
-        #[derive(Accounts)]
-        pub struct Deposit<'info> {
-            pub depositor: Signer<'info>,
-            #[account(mut)]
-            pub vault: AccountInfo<'info>,
-            #[account(mut)]
-            pub vault_token_account: AccountInfo<'info>,
-            #[account(mut)]
-            pub depositor_token_account: AccountInfo<'info>,
-            pub token_program: AccountInfo<'info>,
-        }
-
-        #[derive(Accounts)]
-        pub struct Withdraw<'info> {
-            pub withdrawer: Signer<'info>,
-            #[account(mut)]
-            pub vault: AccountInfo<'info>,
-            #[account(mut)]
-            pub vault_token_account: AccountInfo<'info>,
-            #[account(mut)]
-            pub withdrawer_token_account: AccountInfo<'info>,
-            #[account(mut)]
-            pub fee_collector_token_account: AccountInfo<'info>,
-            pub token_program: AccountInfo<'info>,
-        }
+    /// Read and decode a vault account, bounded by `deadline`
+    pub(crate) fn fetch_vault(
+        &self,
+        vault: Pubkey,
+        deadline: Deadline,
+    ) -> Result<token_vault::state::Vault> {
+        let (vault_data, _) = self.fetch_vault_state(vault, deadline, false)?;
+        Ok(vault_data)
     }
 
-    pub mod instruction {
-        use super::*;
-        
-        #[derive(AnchorSerialize, AnchorDeserialize)]
-        pub struct InitializeVault {
-            pub name: String,
-            pub fee_percentage: u16,
-            pub withdrawal_timelock: i64,
-            pub withdrawal_limit: u64,
-        }
-
-        #[derive(AnchorSerialize, AnchorDeserialize)]
-        pub struct Deposit {
-            pub amount: u64,
-        }
-
-        #[derive(AnchorSerialize, AnchorDeserialize)]
-        pub struct Withdraw {
-            pub amount: u64,
+    /// Refuse to build an authority-gated mutation against an immutable vault
+    pub(crate) fn ensure_mutable(
+        vault: Pubkey,
+        vault_data: &token_vault::state::Vault,
+    ) -> Result<()> {
+        if vault_data.immutable {
+            return Err(TokenVaultError::VaultImmutable { vault }.into());
         }
+        Ok(())
     }
 }
 
-// Utility functions for loading keypair from file
-pub mod utils {
-    use anchor_client::solana_sdk::signature::{Keypair, read_keypair_file};
-    use anyhow::Result;
-    
-    pub fn load_keypair(keypair_path: &str) -> Result<Keypair> {
-        let expanded_path = shellexpand::tilde(keypair_path);
-        let keypair = read_keypair_file(expanded_path.as_ref())?;
-        Ok(keypair)
-    }
+/// Where a withdrawal's tokens go
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Payout {
+    /// The wallet's associated token account, created first when the wallet
+    /// is not the withdrawer's
+    Wallet(Pubkey),
+    /// A token account the caller named, which must already exist
+    TokenAccount(Pubkey),
+    /// The withdrawer's wSOL account, closed after the withdrawal so the
+    /// tokens arrive as native SOL
+    Unwrapped,
+}
+
+/// Where a deposit's tokens come from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum DepositSource {
+    /// The depositor's associated token account
+    Associated,
+    /// A token account the caller named
+    Account(Pubkey),
+    /// Lamports wrapped into the depositor's wSOL account in the same
+    /// transaction
+    WrappedSol,
 }
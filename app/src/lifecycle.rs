@@ -0,0 +1,199 @@
+//! What a vault accepts right now. Its deposit window, scheduled maintenance,
+//! a frozen vault token account and a pause each refuse some operations; `lifecycle`
+//! folds them into one `VaultLifecycle`, and the client's pre-checks and its
+//! `info` output both read that, so what is refused and what is reported
+//! cannot drift apart.
+//!
+//! Immutability is not a stage: it is fixed at creation and refuses
+//! configuration changes in every stage; see `TokenVaultClient::ensure_mutable`.
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use anyhow::Result;
+use std::fmt;
+
+use crate::maintenance::check_maintenance;
+use crate::schedule::{format_timestamp, DepositWindowStatus};
+use crate::token_vault::state::Vault;
+use crate::{
+    Deadline, DepositWindow, FreezeStatus, MaintenanceWindow, OperationKind, TokenVaultClient,
+    TokenVaultError,
+};
+
+/// Every operation on an existing vault, in the order `permitted_operations`
+/// lists them
+pub const VAULT_OPERATIONS: [OperationKind; 18] = [
+    OperationKind::Deposit,
+    OperationKind::Withdraw,
+    OperationKind::RequestWithdrawal,
+    OperationKind::CancelWithdrawal,
+    OperationKind::SweepLamports,
+    OperationKind::ReleaseReserve,
+    OperationKind::UpdateVaultConfig,
+    OperationKind::TransferAuthority,
+    OperationKind::SetFeeCollector,
+    OperationKind::PauseVault,
+    OperationKind::UnpauseVault,
+    OperationKind::ClaimFees,
+    OperationKind::AllowlistSync,
+    OperationKind::SetFeatureFlags,
+    OperationKind::SetNotificationConfig,
+    OperationKind::ClearNotificationConfig,
+    OperationKind::RepairFeePath,
+    OperationKind::ScheduleMaintenance,
+];
+
+/// The stage a vault is in, by what it refuses. When several apply, the
+/// first listed here wins.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VaultLifecycle {
+    /// The mint's freeze authority froze the vault's token account; no
+    /// tokens move in or out, fees included, until it is thawed
+    Frozen { freeze_authority: Option<Pubkey> },
+    /// The authority paused the vault; deposits and withdrawals wait until
+    /// it unpauses
+    Paused { authority: Pubkey },
+    /// The deposit window has not opened yet
+    DepositsNotOpen { window: DepositWindow },
+    /// The deposit window has closed; only changing it reopens deposits
+    DepositsClosed { window: DepositWindow },
+    /// In a maintenance window; deposits resume on their own at `until`
+    Maintenance { until: i64, reason: String },
+    /// Everything is accepted
+    Active,
+}
+
+/// The stage of `vault` at `now`, given its token account's freeze status
+/// and its maintenance windows
+pub fn lifecycle(
+    vault: &Vault,
+    freeze: &FreezeStatus,
+    maintenance: &[MaintenanceWindow],
+    now: i64,
+) -> VaultLifecycle {
+    if freeze.vault_token_account_frozen {
+        return VaultLifecycle::Frozen {
+            freeze_authority: freeze.freeze_authority,
+        };
+    }
+    if vault.paused {
+        return VaultLifecycle::Paused {
+            authority: vault.authority,
+        };
+    }
+    let window = DepositWindow::of(vault);
+    match window.status(now) {
+        DepositWindowStatus::NotYetOpen { .. } => {
+            return VaultLifecycle::DepositsNotOpen { window };
+        }
+        DepositWindowStatus::Closed { .. } => return VaultLifecycle::DepositsClosed { window },
+        DepositWindowStatus::Unrestricted | DepositWindowStatus::Open { .. } => {}
+    }
+    match check_maintenance(maintenance, now) {
+        Err(TokenVaultError::MaintenanceWindow { ends_at, reason }) => {
+            VaultLifecycle::Maintenance {
+                until: ends_at,
+                reason,
+            }
+        }
+        _ => VaultLifecycle::Active,
+    }
+}
+
+impl VaultLifecycle {
+    /// Fail with the reason the vault refuses `kind` in this stage
+    pub fn check(&self, kind: OperationKind) -> Result<(), TokenVaultError> {
+        match (self, kind) {
+            (
+                Self::Frozen { freeze_authority },
+                OperationKind::Deposit | OperationKind::Withdraw | OperationKind::ClaimFees,
+            ) => Err(TokenVaultError::VaultTokenAccountFrozen {
+                freeze_authority: *freeze_authority,
+            }),
+            (Self::Paused { authority }, OperationKind::Deposit | OperationKind::Withdraw) => {
+                Err(TokenVaultError::VaultPaused {
+                    authority: *authority,
+                })
+            }
+            (
+                Self::DepositsNotOpen { window } | Self::DepositsClosed { window },
+                OperationKind::Deposit,
+            ) => Err(TokenVaultError::DepositWindowClosed {
+                opens_at: window.start,
+                closes_at: window.end,
+            }),
+            (Self::Maintenance { until, reason }, OperationKind::Deposit) => {
+                Err(TokenVaultError::MaintenanceWindow {
+                    ends_at: *until,
+                    reason: reason.clone(),
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub fn permits(&self, kind: OperationKind) -> bool {
+        self.check(kind).is_ok()
+    }
+
+    /// The operations in `VAULT_OPERATIONS` this stage allows
+    pub fn permitted_operations(&self) -> Vec<OperationKind> {
+        VAULT_OPERATIONS
+            .into_iter()
+            .filter(|kind| self.permits(*kind))
+            .collect()
+    }
+}
+
+impl fmt::Display for VaultLifecycle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Frozen { freeze_authority } => {
+                write!(f, "frozen; {}", crate::error::thaw_by(freeze_authority))
+            }
+            Self::Paused { authority } => write!(f, "paused by {}", authority),
+            Self::DepositsNotOpen { window } => write!(
+                f,
+                "deposits not open until {}",
+                format_timestamp(window.start)
+            ),
+            Self::DepositsClosed { window } => {
+                write!(f, "deposits closed since {}", format_timestamp(window.end))
+            }
+            Self::Maintenance { until, reason } => write!(
+                f,
+                "maintenance until {}: {}",
+                format_timestamp(*until),
+                reason
+            ),
+            Self::Active => write!(f, "active"),
+        }
+    }
+}
+
+impl TokenVaultClient {
+    /// The current vault's stage; `permitted_operations` on it says what
+    /// the vault accepts right now
+    pub fn get_lifecycle(&self) -> Result<VaultLifecycle> {
+        let vault = self.current_vault()?;
+        let deadline = self.deadline();
+        let (vault_data, _) = self.fetch_vault_state(vault, deadline, false)?;
+        self.vault_lifecycle(vault, &vault_data, deadline)
+    }
+
+    pub(crate) fn vault_lifecycle(
+        &self,
+        vault: Pubkey,
+        vault_data: &Vault,
+        deadline: Deadline,
+    ) -> Result<VaultLifecycle> {
+        let freeze = self.freeze_status(vault, vault_data, deadline)?;
+        let maintenance = self.fetch_maintenance_windows(vault, deadline)?;
+        // Without a window of either kind the time does not matter
+        let now = if DepositWindow::of(vault_data).is_unrestricted() && maintenance.is_empty() {
+            0
+        } else {
+            self.cluster_time_within(deadline)?
+        };
+        Ok(lifecycle(vault_data, &freeze, &maintenance, now))
+    }
+}
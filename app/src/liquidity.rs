@@ -0,0 +1,281 @@
+use anchor_client::solana_sdk::{program_pack::Pack, pubkey::Pubkey};
+use anchor_spl::token::spl_token::state::{Account as TokenAccount, Mint};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::reserve::reserve_unlocks_at;
+use crate::token_vault::{pda, state::Vault};
+use crate::{Deadline, DeadlinePhase, MintAmount, TokenVaultClient};
+
+/// The token balances of a vault and the state that decides who can move
+/// them, at one point in time. Store these to chart liquidity with
+/// `liquidity_history`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VaultSnapshot {
+    /// Cluster time the snapshot was taken at
+    pub taken_at: i64,
+    #[serde(with = "crate::serde_utils::pubkey")]
+    pub vault: Pubkey,
+    #[serde(with = "crate::serde_utils::pubkey")]
+    pub mint: Pubkey,
+    pub decimals: u8,
+    /// Tokens in the vault token account
+    pub vault_token_balance: u64,
+    pub vault_token_frozen: bool,
+    /// Tokens in the insurance reserve token account
+    pub reserve_balance: u64,
+    pub reserve_frozen: bool,
+    /// When the authority may next release from the reserve
+    pub reserve_unlocks_at: i64,
+    /// Most a single withdrawal may take
+    pub withdrawal_limit: u64,
+    /// Seconds a depositor waits between depositing and withdrawing
+    pub withdrawal_timelock: i64,
+}
+
+impl VaultSnapshot {
+    /// Everything the vault holds, in both token accounts
+    pub fn total(&self) -> MintAmount {
+        self.amount(
+            self.vault_token_balance
+                .saturating_add(self.reserve_balance),
+        )
+    }
+
+    fn amount(&self, raw: u64) -> MintAmount {
+        MintAmount::new(raw, self.mint, self.decimals)
+    }
+}
+
+/// The live balance of a vault's token account next to its books, which
+/// hold deposits and the fees accrued for the fee collector. Tokens sent
+/// straight to the token account and fees rounding the wrong way make the
+/// two drift apart.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VaultBalance {
+    #[serde(with = "crate::serde_utils::pubkey")]
+    pub token_account: Pubkey,
+    /// Tokens in the vault token account, in base units
+    pub amount: u64,
+    pub decimals: u8,
+    /// `amount - total_deposited - accrued_fees`: positive for tokens the
+    /// vault holds but neither a deposit nor a fee accounts for, negative
+    /// when the books claim more than it holds
+    pub discrepancy: i128,
+}
+
+impl VaultBalance {
+    pub fn of(token_account: Pubkey, vault: &Vault, amount: u64, decimals: u8) -> Self {
+        Self {
+            token_account,
+            amount,
+            decimals,
+            discrepancy: amount as i128
+                - vault.total_deposited as i128
+                - vault.accrued_fees as i128,
+        }
+    }
+}
+
+/// A vault's holdings split by what it would take to move them. The four
+/// buckets always sum to `total`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LiquidityProfile {
+    pub taken_at: i64,
+    pub total: MintAmount,
+    /// In the vault token account, which depositors withdraw from once their
+    /// own withdrawal timelock has passed
+    pub withdrawable: MintAmount,
+    /// In the reserve, before the reserve timelock allows another release
+    pub timelocked: MintAmount,
+    /// In the reserve, releasable by the authority now
+    pub reserved: MintAmount,
+    /// In a token account frozen by the mint's freeze authority
+    pub frozen: MintAmount,
+    /// Largest single withdrawal possible now: `withdrawable`, capped at the
+    /// vault's withdrawal limit
+    pub max_single_withdrawal: MintAmount,
+    pub withdrawal_timelock: i64,
+}
+
+impl LiquidityProfile {
+    pub fn of(snapshot: &VaultSnapshot) -> Self {
+        let (mut withdrawable, mut timelocked, mut reserved, mut frozen) = (0, 0, 0, 0);
+        if snapshot.vault_token_frozen {
+            frozen += snapshot.vault_token_balance;
+        } else {
+            withdrawable += snapshot.vault_token_balance;
+        }
+        if snapshot.reserve_frozen {
+            frozen += snapshot.reserve_balance;
+        } else if snapshot.taken_at < snapshot.reserve_unlocks_at {
+            timelocked += snapshot.reserve_balance;
+        } else {
+            reserved += snapshot.reserve_balance;
+        }
+        Self {
+            taken_at: snapshot.taken_at,
+            total: snapshot.total(),
+            withdrawable: snapshot.amount(withdrawable),
+            timelocked: snapshot.amount(timelocked),
+            reserved: snapshot.amount(reserved),
+            frozen: snapshot.amount(frozen),
+            max_single_withdrawal: snapshot.amount(withdrawable.min(snapshot.withdrawal_limit)),
+            withdrawal_timelock: snapshot.withdrawal_timelock,
+        }
+    }
+
+    /// The buckets with their names, in display order
+    pub fn buckets(&self) -> [(&'static str, MintAmount); 4] {
+        [
+            ("withdrawable", self.withdrawable),
+            ("timelocked", self.timelocked),
+            ("reserved", self.reserved),
+            ("frozen", self.frozen),
+        ]
+    }
+}
+
+impl fmt::Display for LiquidityProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Total: {}", self.total)?;
+        for (name, amount) in self.buckets() {
+            let share = match self.total.raw {
+                0 => 0.0,
+                total => amount.raw as f64 * 100.0 / total as f64,
+            };
+            writeln!(f, "  {:<13} {} ({:.1}%)", name, amount.ui(), share)?;
+        }
+        write!(
+            f,
+            "Largest single withdrawal: {}",
+            self.max_single_withdrawal.ui()
+        )?;
+        match self.withdrawal_timelock {
+            timelock if timelock > 0 => write!(f, " (depositor timelock {} seconds)", timelock),
+            _ => write!(f, " (no depositor timelock)"),
+        }
+    }
+}
+
+/// Liquidity profiles of stored snapshots of one vault, oldest first
+pub fn liquidity_history(snapshots: &[VaultSnapshot]) -> Result<Vec<LiquidityProfile>> {
+    if let Some(first) = snapshots.first() {
+        if let Some(other) = snapshots
+            .iter()
+            .find(|snapshot| snapshot.vault != first.vault)
+        {
+            return Err(anyhow!(
+                "Snapshots of vault {} and vault {} cannot form one history",
+                first.vault,
+                other.vault
+            ));
+        }
+    }
+    let mut profiles: Vec<_> = snapshots.iter().map(LiquidityProfile::of).collect();
+    profiles.sort_by_key(|profile| profile.taken_at);
+    Ok(profiles)
+}
+
+impl TokenVaultClient {
+    /// Current balances and liquidity state of the current vault
+    pub fn get_vault_snapshot(&self) -> Result<VaultSnapshot> {
+        let vault = self.current_vault()?;
+        let deadline = self.deadline();
+        let (vault_data, _) = self.fetch_vault_state(vault, deadline, false)?;
+        self.snapshot_vault(vault, &vault_data, deadline)
+    }
+
+    /// The live balances of `vault`, whose account reads `vault_data`
+    pub(crate) fn snapshot_vault(
+        &self,
+        vault: Pubkey,
+        vault_data: &Vault,
+        deadline: Deadline,
+    ) -> Result<VaultSnapshot> {
+        let (vault_token_account, _) =
+            pda::find_vault_token_account_address(&self.program.id(), &vault);
+        let (reserve_token_account, _) =
+            pda::find_reserve_token_account_address(&self.program.id(), &vault);
+
+        let rpc = self.rpc_within(deadline, DeadlinePhase::Fetch)?;
+        let mut accounts = rpc
+            .get_multiple_accounts(&[
+                vault_data.token_mint,
+                vault_token_account,
+                reserve_token_account,
+            ])?
+            .into_iter();
+        let mint = accounts
+            .next()
+            .flatten()
+            .ok_or_else(|| anyhow!("Mint {} not found", vault_data.token_mint))?;
+        let vault_token = accounts
+            .next()
+            .flatten()
+            .ok_or_else(|| anyhow!("Vault token account {} not found", vault_token_account))?;
+        let vault_token = TokenAccount::unpack(&vault_token.data)?;
+        // Vaults created before the reserve existed have no reserve account
+        let reserve = accounts
+            .next()
+            .flatten()
+            .map(|account| TokenAccount::unpack(&account.data))
+            .transpose()?;
+
+        Ok(VaultSnapshot {
+            taken_at: self.cluster_time_within(deadline)?,
+            vault,
+            mint: vault_data.token_mint,
+            decimals: Mint::unpack(&mint.data)?.decimals,
+            vault_token_balance: vault_token.amount,
+            vault_token_frozen: vault_token.is_frozen(),
+            reserve_balance: reserve.map_or(0, |reserve| reserve.amount),
+            reserve_frozen: reserve.is_some_and(|reserve| reserve.is_frozen()),
+            reserve_unlocks_at: reserve_unlocks_at(vault_data),
+            withdrawal_limit: vault_data.withdrawal_limit,
+            withdrawal_timelock: vault_data.withdrawal_timelock,
+        })
+    }
+
+    /// The live balance of `vault`'s token account and the decimals of its
+    /// mint, read in one request
+    pub(crate) fn fetch_vault_balance(
+        &self,
+        vault: Pubkey,
+        vault_data: &Vault,
+        deadline: Deadline,
+    ) -> Result<VaultBalance> {
+        let (token_account, _) = pda::find_vault_token_account_address(&self.program.id(), &vault);
+        let rpc = self.rpc_within(deadline, DeadlinePhase::Fetch)?;
+        let mut accounts = rpc
+            .get_multiple_accounts(&[vault_data.token_mint, token_account])?
+            .into_iter();
+        let mint = accounts
+            .next()
+            .flatten()
+            .ok_or_else(|| anyhow!("Mint {} not found", vault_data.token_mint))?;
+        let token = accounts
+            .next()
+            .flatten()
+            .ok_or_else(|| anyhow!("Vault token account {} not found", token_account))?;
+        Ok(VaultBalance::of(
+            token_account,
+            vault_data,
+            TokenAccount::unpack(&token.data)?.amount,
+            Mint::unpack(&mint.data)?.decimals,
+        ))
+    }
+
+    /// Everything the current vault holds: its token account plus its
+    /// insurance reserve
+    pub fn get_vault_balance(&self) -> Result<MintAmount> {
+        Ok(self.get_vault_snapshot()?.total())
+    }
+
+    /// How much of the current vault's holdings can move now, and what
+    /// holds back the rest
+    pub fn get_liquidity_profile(&self) -> Result<LiquidityProfile> {
+        Ok(LiquidityProfile::of(&self.get_vault_snapshot()?))
+    }
+}
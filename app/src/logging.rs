@@ -0,0 +1,256 @@
+//! Layered logging through `tracing`. The client logs nothing until the
+//! application installs a subscriber, and then as much as its level allows:
+//!
+//! - `info`: one line per operation with `correlation_id`, `op`, `vault`,
+//!   `amount` in base units, `signature` and `latency_ms`. A failed or
+//!   refused operation is a `warn` line with `error` instead of `signature`.
+//! - `debug`: the decisions behind it: cache hits and misses, policy
+//!   approvals and refusals, confirmation polls.
+//! - `trace`: RPC exchanges, as `method`, `request_bytes` and
+//!   `response_bytes` or `error`.
+//!
+//! Events logged while a mutation runs are inside its `operation` span,
+//! which carries the `correlation_id`, `vault`, `signer` and `amount`, and
+//! the `signature` once confirmed; several batched into one transaction
+//! share a `transaction` span instead. Below info, keys only appear as
+//! `support::hash_pubkey` stand-ins; with `with_log_redaction(true)` info
+//! lines hash them too. Errors are logged with RPC URLs cut down to their
+//! host. No level logs key material.
+//!
+//! All of this needs the default `tracing` feature. Without it, events
+//! compile to nothing and the library writes nowhere.
+
+use anchor_client::solana_client::{rpc_client::RpcClient, rpc_response::Response as RpcResponse};
+use anchor_client::solana_sdk::{
+    account::Account, commitment_config::CommitmentConfig, pubkey::Pubkey, signature::Signature,
+    transaction::VersionedTransaction,
+};
+use serde_json::Value;
+use std::fmt::Display;
+use std::time::Duration;
+
+use crate::support::{hash_pubkey, redact, RedactionRules};
+use crate::{CachedKind, Operation, TokenVaultClient};
+
+// The rest of the crate logs through these, as `logging::warn!` and so on
+#[cfg(feature = "tracing")]
+pub use tracing::Span;
+#[cfg(feature = "tracing")]
+pub(crate) use tracing::{debug, info, info_span, trace, warn};
+
+#[cfg(not(feature = "tracing"))]
+pub use disabled::Span;
+#[cfg(not(feature = "tracing"))]
+pub(crate) use disabled::{debug, info, info_span, trace, warn};
+
+/// Stand-ins for the `tracing` macros and `Span`, doing nothing and
+/// evaluating none of their arguments
+#[cfg(not(feature = "tracing"))]
+mod disabled {
+    macro_rules! event {
+        ($($arg:tt)*) => {
+            ()
+        };
+    }
+    macro_rules! span {
+        ($($arg:tt)*) => {
+            $crate::logging::Span
+        };
+    }
+    pub(crate) use event as debug;
+    pub(crate) use event as info;
+    pub(crate) use event as trace;
+    pub(crate) use event as warn;
+    pub(crate) use span as info_span;
+
+    /// `tracing::Span` without a subscriber to report to
+    #[derive(Debug, Clone, Default)]
+    pub struct Span;
+
+    impl Span {
+        pub fn enter(&self) -> &Self {
+            self
+        }
+
+        pub fn record<V>(&self, _field: &str, _value: V) -> &Self {
+            self
+        }
+    }
+}
+
+impl TokenVaultClient {
+    /// Hash public keys in info-level operation lines, for deployments
+    /// whose logs must not show which vaults they touch
+    pub fn with_log_redaction(&mut self, redact: bool) -> &mut Self {
+        self.log_redaction = redact;
+        self
+    }
+}
+
+/// Span around one mutation, carrying its correlation id to every event
+/// logged inside it. Its keys are hashed, as debug events inherit them;
+/// `signature` is recorded when the operation confirms.
+pub fn operation_span(operation: &Operation) -> Span {
+    info_span!(
+        "operation",
+        correlation_id = operation.id,
+        op = %operation.kind,
+        vault = %operation.vault.map_or_else(|| "-".to_string(), |vault| hash_pubkey(&vault)),
+        signer = %hash_pubkey(&operation.signer),
+        amount = operation.amount,
+        signature = tracing::field::Empty,
+    )
+}
+
+/// Span around one transaction: the `operation_span` of its only
+/// operation, or a `transaction` span counting the operations it carries,
+/// each of which still logs its own line with its own correlation id
+pub fn transaction_span(operations: &[Operation]) -> Span {
+    match operations {
+        [operation] => operation_span(operation),
+        _ => info_span!(
+            "transaction",
+            operations = operations.len(),
+            signature = tracing::field::Empty,
+        ),
+    }
+}
+
+/// `key` as an info line shows it
+pub fn log_key(key: &Pubkey, redact: bool) -> String {
+    match redact {
+        true => hash_pubkey(key),
+        false => key.to_string(),
+    }
+}
+
+/// `text` with URLs cut down to their host and, with `hash_pubkeys`, keys
+/// replaced by their stand-ins
+pub(crate) fn redacted(text: impl Display, hash_pubkeys: bool) -> String {
+    let mut value = Value::String(format!("{:#}", text));
+    redact(&mut value, RedactionRules { hash_pubkeys });
+    match value {
+        Value::String(text) => text,
+        _ => String::new(),
+    }
+}
+
+/// The info (or, on failure, warn) line summarizing `operation`
+pub fn log_operation(
+    operation: &Operation,
+    outcome: Result<&Signature, &anyhow::Error>,
+    latency: Duration,
+    redact: bool,
+) {
+    let vault = operation
+        .vault
+        .map_or_else(|| "-".to_string(), |vault| log_key(&vault, redact));
+    let latency_ms = latency.as_millis() as u64;
+    match outcome {
+        Ok(signature) => info!(
+            correlation_id = operation.id,
+            op = %operation.kind,
+            vault = %vault,
+            amount = operation.amount,
+            signature = %signature,
+            latency_ms,
+            "{} confirmed",
+            operation.kind
+        ),
+        Err(err) => warn!(
+            correlation_id = operation.id,
+            op = %operation.kind,
+            vault = %vault,
+            amount = operation.amount,
+            error = %redacted(err, redact),
+            latency_ms,
+            "{} failed",
+            operation.kind
+        ),
+    }
+}
+
+/// How the `index`th installed policy decided on the current operation
+pub fn log_policy_decision(index: usize, decision: &anyhow::Result<()>) {
+    match decision {
+        Ok(()) => debug!(policy = index, decision = "approved", "policy decision"),
+        Err(err) => debug!(
+            policy = index,
+            decision = "refused",
+            reason = %redacted(err, true),
+            "policy decision"
+        ),
+    }
+}
+
+/// A cache lookup of `address`: `hit`, `stale` or `miss`
+pub fn log_cache_lookup(address: &Pubkey, kind: CachedKind, outcome: &'static str) {
+    debug!(
+        account = %hash_pubkey(address),
+        kind = ?kind,
+        outcome,
+        "cache lookup"
+    );
+}
+
+/// A completed RPC exchange
+pub fn log_rpc_response(method: &'static str, request_bytes: usize, response_bytes: usize) {
+    trace!(method, request_bytes, response_bytes, "rpc");
+}
+
+/// A failed RPC exchange
+pub fn log_rpc_error(method: &'static str, request_bytes: usize, error: impl Display) {
+    trace!(method, request_bytes, error = %redacted(error, true), "rpc");
+}
+
+/// `get_account_with_commitment`, logged
+pub(crate) fn get_account(
+    rpc: &RpcClient,
+    address: &Pubkey,
+    commitment: CommitmentConfig,
+) -> anyhow::Result<RpcResponse<Option<Account>>> {
+    let response = rpc.get_account_with_commitment(address, commitment);
+    let request_bytes = address.as_ref().len();
+    match &response {
+        Ok(response) => log_rpc_response(
+            "getAccountInfo",
+            request_bytes,
+            response
+                .value
+                .as_ref()
+                .map_or(0, |account| account.data.len()),
+        ),
+        Err(err) => log_rpc_error("getAccountInfo", request_bytes, err),
+    }
+    Ok(response?)
+}
+
+/// `get_multiple_accounts_with_commitment`, logged
+pub(crate) fn get_multiple_accounts(
+    rpc: &RpcClient,
+    addresses: &[Pubkey],
+    commitment: CommitmentConfig,
+) -> anyhow::Result<RpcResponse<Vec<Option<Account>>>> {
+    let response = rpc.get_multiple_accounts_with_commitment(addresses, commitment);
+    let request_bytes = addresses.len() * 32;
+    match &response {
+        Ok(response) => log_rpc_response(
+            "getMultipleAccounts",
+            request_bytes,
+            response
+                .value
+                .iter()
+                .flatten()
+                .map(|account| account.data.len())
+                .sum(),
+        ),
+        Err(err) => log_rpc_error("getMultipleAccounts", request_bytes, err),
+    }
+    Ok(response?)
+}
+
+/// Bytes `transaction` takes on the wire
+pub(crate) fn transaction_size(transaction: &VersionedTransaction) -> usize {
+    // Signatures are prefixed by their count, which fits one byte
+    1 + transaction.signatures.len() * 64 + transaction.message.serialize().len()
+}
@@ -0,0 +1,263 @@
+//! Scheduled maintenance: time-boxed windows in which the vault refuses
+//! deposits, announced ahead of time and ending on their own. Unlike a
+//! pause, nothing has to be sent to resume, and withdrawals stay open, so
+//! users can always take out what they have in the vault.
+
+use anchor_client::solana_sdk::{pubkey::Pubkey, system_program};
+use anchor_lang::AccountDeserialize;
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::plan::format_duration;
+use crate::schedule::format_timestamp;
+use crate::signer::{AsSigner, VaultSigner};
+use crate::token_vault::{accounts, instruction, pda, state};
+use crate::{
+    ActionReceipt, AdminState, Deadline, DeadlinePhase, Operation, OperationKind, TokenVaultClient,
+    TokenVaultError, VaultChange,
+};
+
+/// A period in which the vault refuses deposits
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MaintenanceWindow {
+    pub start: i64,
+    pub end: i64,
+    /// Shown to users while the window is upcoming or active
+    pub reason: String,
+}
+
+/// Where the current time falls relative to a maintenance window
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MaintenanceStatus {
+    Upcoming { starts_in: i64 },
+    Active { ends_in: i64 },
+    Ended,
+}
+
+impl MaintenanceWindow {
+    /// Create a window, rejecting one that ends before it starts or a reason
+    /// the program would not store
+    pub fn new(start: i64, end: i64, reason: impl Into<String>) -> Result<Self> {
+        let reason = reason.into();
+        if start < 0 {
+            return Err(anyhow!("Maintenance window cannot start before 1970"));
+        }
+        if end <= start {
+            return Err(anyhow!(
+                "Maintenance window ends ({}) before it starts ({})",
+                end,
+                start
+            ));
+        }
+        if reason.trim().is_empty() {
+            return Err(anyhow!("Give a reason for the maintenance window"));
+        }
+        if reason.len() > state::MaintenanceSchedule::MAX_REASON_LEN {
+            return Err(anyhow!(
+                "Reason is {} bytes; at most {} fit",
+                reason.len(),
+                state::MaintenanceSchedule::MAX_REASON_LEN
+            ));
+        }
+        if reason.chars().any(char::is_control) {
+            return Err(anyhow!("Reason contains control characters"));
+        }
+        Ok(Self { start, end, reason })
+    }
+
+    pub fn of(window: &state::MaintenanceWindow) -> Self {
+        Self {
+            start: window.start_ts,
+            end: window.end_ts,
+            reason: window.reason.clone(),
+        }
+    }
+
+    pub fn status(&self, now: i64) -> MaintenanceStatus {
+        if now < self.start {
+            MaintenanceStatus::Upcoming {
+                starts_in: self.start - now,
+            }
+        } else if now < self.end {
+            MaintenanceStatus::Active {
+                ends_in: self.end - now,
+            }
+        } else {
+            MaintenanceStatus::Ended
+        }
+    }
+
+    /// Whether the two windows share any second
+    pub fn overlaps(&self, other: &MaintenanceWindow) -> bool {
+        self.start < other.end && other.start < self.end
+    }
+
+    /// Human-readable state of the window with a countdown, e.g.
+    /// "active, ends in 1h 30m: database migration"
+    pub fn describe(&self, now: i64) -> String {
+        match self.status(now) {
+            MaintenanceStatus::Upcoming { starts_in } => format!(
+                "starts in {}, lasts {}: {}",
+                format_duration(starts_in),
+                format_duration(self.end - self.start),
+                self.reason
+            ),
+            MaintenanceStatus::Active { ends_in } => {
+                format!(
+                    "active, ends in {}: {}",
+                    format_duration(ends_in),
+                    self.reason
+                )
+            }
+            MaintenanceStatus::Ended => {
+                format!("ended at {}: {}", format_timestamp(self.end), self.reason)
+            }
+        }
+    }
+}
+
+impl fmt::Display for MaintenanceWindow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} .. {}: {}",
+            format_timestamp(self.start),
+            format_timestamp(self.end),
+            self.reason
+        )
+    }
+}
+
+/// Fail with `MaintenanceWindow` while one of `windows` is active at `now`
+pub fn check_maintenance(windows: &[MaintenanceWindow], now: i64) -> Result<(), TokenVaultError> {
+    match windows
+        .iter()
+        .find(|window| matches!(window.status(now), MaintenanceStatus::Active { .. }))
+    {
+        Some(window) => Err(TokenVaultError::MaintenanceWindow {
+            ends_at: window.end,
+            reason: window.reason.clone(),
+        }),
+        None => Ok(()),
+    }
+}
+
+/// The schedule after adding `window` to `windows` at `now`, as the program
+/// stores it: ended windows dropped, the rest in start order. Refuses a
+/// window that has already ended, one overlapping another that has not, and
+/// a schedule over `MaintenanceSchedule::MAX_WINDOWS`.
+pub fn schedule_window(
+    windows: &[MaintenanceWindow],
+    window: MaintenanceWindow,
+    now: i64,
+) -> Result<Vec<MaintenanceWindow>> {
+    if window.status(now) == MaintenanceStatus::Ended {
+        return Err(anyhow!(
+            "Maintenance window already ended at {}",
+            format_timestamp(window.end)
+        ));
+    }
+    let mut schedule: Vec<_> = windows
+        .iter()
+        .filter(|existing| existing.status(now) != MaintenanceStatus::Ended)
+        .cloned()
+        .collect();
+    if let Some(existing) = schedule.iter().find(|existing| existing.overlaps(&window)) {
+        return Err(TokenVaultError::MaintenanceOverlap {
+            start: existing.start,
+            end: existing.end,
+            reason: existing.reason.clone(),
+        }
+        .into());
+    }
+    if schedule.len() >= state::MaintenanceSchedule::MAX_WINDOWS {
+        return Err(anyhow!(
+            "The vault already has {} maintenance windows scheduled; wait for one to end",
+            schedule.len()
+        ));
+    }
+    schedule.push(window);
+    schedule.sort_by_key(|window| window.start);
+    Ok(schedule)
+}
+
+impl TokenVaultClient {
+    /// The current vault's maintenance windows in start order, as stored;
+    /// windows that have ended stay until the next one is scheduled
+    pub fn get_maintenance_windows(&self) -> Result<Vec<MaintenanceWindow>> {
+        let vault = self.current_vault()?;
+        self.fetch_maintenance_windows(vault, self.deadline())
+    }
+
+    pub(crate) fn fetch_maintenance_windows(
+        &self,
+        vault: Pubkey,
+        deadline: Deadline,
+    ) -> Result<Vec<MaintenanceWindow>> {
+        let (address, _) = pda::find_maintenance_schedule_address(&self.program.id(), &vault);
+        let rpc = self.rpc_within(deadline, DeadlinePhase::Fetch)?;
+        let account = match rpc
+            .get_account_with_commitment(&address, rpc.commitment())?
+            .value
+        {
+            Some(account) => account,
+            None => return Ok(Vec::new()),
+        };
+        let schedule = state::MaintenanceSchedule::try_deserialize(&mut account.data.as_slice())
+            .with_context(|| format!("Failed to decode maintenance schedule {}", address))?;
+        Ok(schedule.windows.iter().map(MaintenanceWindow::of).collect())
+    }
+
+    /// Schedule a maintenance window on the current vault from `start_ts` to
+    /// `end_ts`. Deposits are refused inside it and resume on their own
+    /// afterwards. Windows may not overlap, and immutable vaults cannot be
+    /// put into maintenance. Returns the authority's signed receipt.
+    pub fn schedule_maintenance(
+        &self,
+        authority: &dyn VaultSigner,
+        start_ts: i64,
+        end_ts: i64,
+        reason: String,
+    ) -> Result<ActionReceipt> {
+        let window = MaintenanceWindow::new(start_ts, end_ts, reason)?;
+        let (vault, _, deadline) = self.check_vault_authority(authority)?;
+        let before = self.fetch_maintenance_windows(vault, deadline)?;
+        let after = schedule_window(&before, window.clone(), self.cluster_time_within(deadline)?)?;
+
+        let (maintenance_schedule, _) =
+            pda::find_maintenance_schedule_address(&self.program.id(), &vault);
+        let signer = AsSigner(authority);
+        let request = self
+            .vault_request(&[vault])?
+            .accounts(accounts::ScheduleMaintenance {
+                authority: authority.pubkey(),
+                vault,
+                maintenance_schedule,
+                system_program: system_program::ID,
+            })
+            .args(instruction::ScheduleMaintenance {
+                start_ts: window.start,
+                end_ts: window.end,
+                reason: window.reason.clone(),
+            })
+            .signer(&signer);
+        let operation = Operation::new(
+            OperationKind::ScheduleMaintenance,
+            authority.pubkey(),
+            Some(vault),
+            0,
+        );
+        let signature = self.send_operation(operation, request, deadline)?;
+        self.invalidate(&maintenance_schedule);
+
+        self.action_receipt(
+            authority,
+            vault,
+            VaultChange::MaintenanceScheduled { window },
+            signature,
+            AdminState::Maintenance { windows: before },
+            AdminState::Maintenance { windows: after },
+        )
+    }
+}
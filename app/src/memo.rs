@@ -0,0 +1,89 @@
+//! Memos on deposits and withdrawals, for reconciling transfers against
+//! invoices or other records kept off chain. A memo is an SPL Memo
+//! instruction in the same transaction as the transfer, signed by the
+//! depositor or withdrawer, and is read back from the transaction itself.
+
+use anchor_client::solana_sdk::{
+    instruction::Instruction, message::VersionedMessage, pubkey::Pubkey,
+};
+use anyhow::Result;
+use solana_transaction_status::EncodedConfirmedTransactionWithStatusMeta;
+
+use crate::signer::VaultSigner;
+use crate::{DepositSource, TokenVaultClient, TokenVaultError, TxReceipt};
+
+/// Longest memo the client attaches, in bytes. The memo program sets no
+/// limit of its own, but a memo shares the transaction's 1232 bytes with
+/// the transfer and its signatures, and a longer one leaves too little room.
+pub const MAX_MEMO_LEN: usize = 512;
+
+/// How `deposit_with_options` sends a deposit
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DepositOptions {
+    /// Attach this memo to the deposit's transaction. At most
+    /// `MAX_MEMO_LEN` bytes.
+    pub memo: Option<String>,
+}
+
+/// Refuse a memo the client would not attach
+pub fn check_memo(memo: &str) -> Result<(), TokenVaultError> {
+    if memo.len() > MAX_MEMO_LEN {
+        return Err(TokenVaultError::MemoTooLong {
+            len: memo.len(),
+            max: MAX_MEMO_LEN,
+        });
+    }
+    Ok(())
+}
+
+/// SPL Memo instruction recording `memo`, which `signer` must sign
+pub fn memo_instruction(memo: &str, signer: &Pubkey) -> Instruction {
+    spl_memo::build_memo(memo.as_bytes(), &[signer])
+}
+
+/// Memos of the SPL Memo instructions in `transaction`, in instruction
+/// order. Memos that are not UTF-8 are left out.
+pub fn transaction_memos(transaction: &EncodedConfirmedTransactionWithStatusMeta) -> Vec<String> {
+    let Some(decoded) = transaction.transaction.transaction.decode() else {
+        return Vec::new();
+    };
+    let (keys, instructions) = match &decoded.message {
+        VersionedMessage::Legacy(message) => (&message.account_keys, &message.instructions),
+        VersionedMessage::V0(message) => (&message.account_keys, &message.instructions),
+    };
+    instructions
+        .iter()
+        // Program ids are never loaded from lookup tables
+        .filter(|ix| {
+            keys.get(ix.program_id_index as usize)
+                .is_some_and(|program| *program == spl_memo::id() || *program == spl_memo::v1::id())
+        })
+        .filter_map(|ix| String::from_utf8(ix.data.clone()).ok())
+        .collect()
+}
+
+impl TokenVaultClient {
+    /// `deposit` with `options`; `DepositOptions::default()` behaves like
+    /// `deposit` itself. The receipt echoes the memo sent, if any.
+    pub fn deposit_with_options(
+        &self,
+        depositor: &dyn VaultSigner,
+        amount: u64,
+        options: DepositOptions,
+    ) -> Result<TxReceipt> {
+        let vault = self.current_vault()?;
+        let (signature, _) = self.send_deposit(
+            vault,
+            depositor,
+            DepositSource::Associated,
+            amount,
+            None,
+            None,
+            options.memo.as_deref(),
+        )?;
+        Ok(TxReceipt {
+            memo: options.memo,
+            ..self.tx_receipt(signature)
+        })
+    }
+}
@@ -0,0 +1,412 @@
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use anchor_lang::AccountDeserialize;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::time::Duration;
+
+use crate::amount::base_units_to_ui;
+use crate::key_policy::is_mainnet;
+use crate::logging;
+use crate::plan::{format_bps, format_duration};
+use crate::signer::VaultSigner;
+use crate::token_vault::{pda, state::Vault};
+use crate::vault_config::ui_to_base_units;
+use crate::{
+    ActionReceipt, DeadlinePhase, DepositWindow, DustPolicy, ReservePolicy, TokenVaultClient,
+    VaultConfig, WithdrawalLimit,
+};
+
+/// What `mirror_vault_config` copies besides the vault's own configuration
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MirrorOptions {
+    /// Make the destination allowlist exactly the source's
+    pub allowlist: bool,
+    /// Copy the notification config: webhook hash, explorer and support contact
+    pub metadata: bool,
+    /// Mint of the destination vault; the source vault's mint when `None`,
+    /// which only exists on both clusters for cloned or well-known mints
+    pub destination_mint: Option<Pubkey>,
+    /// Report what would change without sending anything
+    pub dry_run: bool,
+    /// Allow a mainnet destination
+    pub allow_mainnet_destination: bool,
+}
+
+/// Outcome for one mirrored field
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "status", content = "reason")]
+pub enum MirrorStatus {
+    Applied,
+    /// Would be applied, in a dry run
+    Planned,
+    Skipped(String),
+    Failed(String),
+}
+
+impl fmt::Display for MirrorStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Applied => write!(f, "applied"),
+            Self::Planned => write!(f, "planned"),
+            Self::Skipped(reason) => write!(f, "skipped: {}", reason),
+            Self::Failed(reason) => write!(f, "failed: {}", reason),
+        }
+    }
+}
+
+/// One field of the source vault and what became of it on the destination
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MirroredField {
+    pub field: String,
+    pub source: String,
+    /// The destination's value before mirroring; `None` when the
+    /// destination had nothing to compare
+    pub destination: Option<String>,
+    #[serde(flatten)]
+    pub status: MirrorStatus,
+}
+
+/// Result of mirroring a vault to another cluster
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MirrorReport {
+    #[serde(with = "crate::serde_utils::pubkey")]
+    pub source_vault: Pubkey,
+    #[serde(with = "crate::serde_utils::pubkey")]
+    pub destination_vault: Pubkey,
+    pub name: String,
+    /// The destination vault was (or, in a dry run, would be) created
+    pub created: bool,
+    pub dry_run: bool,
+    pub fields: Vec<MirroredField>,
+    /// The authority's signed receipts for the metadata changes applied
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub receipts: Vec<ActionReceipt>,
+}
+
+impl MirrorReport {
+    pub fn has_failures(&self) -> bool {
+        self.fields
+            .iter()
+            .any(|field| matches!(field.status, MirrorStatus::Failed(_)))
+    }
+}
+
+impl fmt::Display for MirrorReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let action = match (self.created, self.dry_run) {
+            (true, true) => "would create",
+            (true, false) => "created",
+            (false, _) => "existing",
+        };
+        writeln!(
+            f,
+            "Mirror of vault '{}' {} -> {} ({})",
+            self.name, self.source_vault, self.destination_vault, action
+        )?;
+        for field in &self.fields {
+            match &field.destination {
+                Some(destination) if *destination != field.source => writeln!(
+                    f,
+                    "  {}: {} -> {} [{}]",
+                    field.field, destination, field.source, field.status
+                )?,
+                _ => writeln!(f, "  {}: {} [{}]", field.field, field.source, field.status)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The configuration that recreates `source` on a mint with
+/// `destination_decimals` decimals, with amounts converted through whole
+/// tokens. Fails when an amount cannot be expressed in the new decimals.
+pub fn mirrored_config(
+    source: &Vault,
+    source_decimals: u8,
+    destination_decimals: u8,
+) -> Result<VaultConfig> {
+    let convert = |field: &str, raw: u64| -> Result<u64> {
+        if raw == u64::MAX || source_decimals == destination_decimals {
+            return Ok(raw);
+        }
+        ui_to_base_units(
+            &base_units_to_ui(raw, source_decimals),
+            destination_decimals,
+        )
+        .map_err(|err| anyhow!("Cannot convert {} {}: {}", field, raw, err))
+    };
+    let dust = DustPolicy::of(source);
+    VaultConfig::builder()
+        .name(source.name.clone())
+        .fee_bps(source.fee_percentage)
+        .timelock(Duration::from_secs(source.withdrawal_timelock.max(0) as u64))
+        .withdrawal_limit(convert("withdrawal_limit", source.withdrawal_limit)?)
+        .fee_collector(source.fee_collector)
+        .immutable(source.immutable)
+        .deposit_window(DepositWindow::of(source))
+        .dust_policy(DustPolicy {
+            threshold: convert("dust_threshold", dust.threshold)?,
+            ..dust
+        })
+        .reserve(ReservePolicy::of(source))
+        .build()
+}
+
+/// Each configuration field of `config` against `destination`, as
+/// `(field, source, destination)` display strings
+pub fn config_fields(
+    config: &VaultConfig,
+    destination: Option<&Vault>,
+) -> Vec<(&'static str, String, Option<String>)> {
+    let limit = match &config.withdrawal_limit {
+        WithdrawalLimit::BaseUnits(raw) => raw.to_string(),
+        WithdrawalLimit::Ui(amount) => amount.clone(),
+    };
+    vec![
+        (
+            "fee_percentage",
+            format_bps(config.fee_bps),
+            destination.map(|vault| format_bps(vault.fee_percentage)),
+        ),
+        (
+            "withdrawal_timelock",
+            format_duration(config.timelock),
+            destination.map(|vault| format_duration(vault.withdrawal_timelock)),
+        ),
+        (
+            "withdrawal_limit",
+            limit,
+            destination.map(|vault| vault.withdrawal_limit.to_string()),
+        ),
+        (
+            "fee_collector",
+            config
+                .fee_collector
+                .map(|collector| collector.to_string())
+                .unwrap_or_default(),
+            destination.map(|vault| vault.fee_collector.to_string()),
+        ),
+        (
+            "immutable",
+            config.immutable.to_string(),
+            destination.map(|vault| vault.immutable.to_string()),
+        ),
+        (
+            "deposit_window",
+            config.deposit_window.to_string(),
+            destination.map(|vault| DepositWindow::of(vault).to_string()),
+        ),
+        (
+            "dust_policy",
+            config.dust_policy.to_string(),
+            destination.map(|vault| DustPolicy::of(vault).to_string()),
+        ),
+        (
+            "reserve",
+            config.reserve.to_string(),
+            destination.map(|vault| ReservePolicy::of(vault).to_string()),
+        ),
+    ]
+}
+
+impl TokenVaultClient {
+    /// Recreate the current vault's configuration on `destination`'s
+    /// cluster, as a vault of the same name owned by `authority`. A missing
+    /// destination vault is created; the program cannot change an existing
+    /// vault's configuration, so differing fields of one are reported as
+    /// failed. Afterwards `destination` has the mirrored vault selected.
+    pub fn mirror_vault_config(
+        &self,
+        destination: &mut TokenVaultClient,
+        authority: &dyn VaultSigner,
+        options: &MirrorOptions,
+    ) -> Result<MirrorReport> {
+        let source_vault = self.current_vault()?;
+        if is_mainnet(&destination.cluster) && !options.allow_mainnet_destination {
+            return Err(anyhow!(
+                "Refusing to mirror into mainnet ({}); allow a mainnet destination explicitly",
+                destination.cluster.url()
+            ));
+        }
+
+        let deadline = self.deadline();
+        let (source, _) = self.fetch_vault_state(source_vault, deadline, false)?;
+        let source_mint = self.fetch_mint(source.token_mint, deadline)?;
+        let destination_mint = options.destination_mint.unwrap_or(source.token_mint);
+        let destination_deadline = destination.deadline();
+        let mint = destination.fetch_mint(destination_mint, destination_deadline)?;
+        let config = mirrored_config(&source, source_mint.decimals, mint.decimals)?;
+
+        let (destination_vault, _) = pda::find_vault_address(
+            &destination.program.id(),
+            &authority.pubkey(),
+            &destination_mint,
+            &source.name,
+        );
+        let rpc = destination.rpc_within(destination_deadline, DeadlinePhase::Fetch)?;
+        let existing = match logging::get_account(&rpc, &destination_vault, rpc.commitment())?.value
+        {
+            Some(account) => Some(Vault::try_deserialize(&mut account.data.as_slice())?),
+            None => None,
+        };
+        let created = existing.is_none();
+
+        let mut fields: Vec<_> = config_fields(&config, existing.as_ref())
+            .into_iter()
+            .map(|(field, source, current)| {
+                let status = match &current {
+                    None if options.dry_run => MirrorStatus::Planned,
+                    None => MirrorStatus::Applied,
+                    Some(current) if *current == source => {
+                        MirrorStatus::Skipped("already matches".to_string())
+                    }
+                    Some(_) => MirrorStatus::Failed(
+                        "the program cannot change it after creation".to_string(),
+                    ),
+                };
+                MirroredField {
+                    field: field.to_string(),
+                    source,
+                    destination: current,
+                    status,
+                }
+            })
+            .collect();
+
+        if created && !options.dry_run {
+            destination.initialize_vault(authority, destination_mint, &config)?;
+        }
+        destination.set_vault(destination_vault);
+
+        if options.allowlist {
+            fields.push(self.mirror_allowlist(destination, authority, options, created));
+        }
+        let mut receipts = Vec::new();
+        if options.metadata {
+            fields.push(self.mirror_metadata(
+                destination,
+                authority,
+                options,
+                created,
+                &mut receipts,
+            ));
+        }
+
+        Ok(MirrorReport {
+            source_vault,
+            destination_vault,
+            name: source.name,
+            created,
+            dry_run: options.dry_run,
+            fields,
+            receipts,
+        })
+    }
+
+    fn mirror_allowlist(
+        &self,
+        destination: &TokenVaultClient,
+        authority: &dyn VaultSigner,
+        options: &MirrorOptions,
+        created: bool,
+    ) -> MirroredField {
+        let field = |source: String, current: Option<String>, status| MirroredField {
+            field: "allowlist".to_string(),
+            source,
+            destination: current,
+            status,
+        };
+        let desired: Vec<_> = match self.get_allowlist() {
+            Ok(allowlist) => allowlist.into_iter().collect(),
+            Err(err) => {
+                return field(
+                    "?".to_string(),
+                    None,
+                    MirrorStatus::Failed(format!("{:#}", err)),
+                )
+            }
+        };
+        let source = format!("{} wallets", desired.len());
+        if created && options.dry_run {
+            return field(source, None, MirrorStatus::Planned);
+        }
+        let plan = match destination.sync_allowlist(authority.pubkey(), &desired) {
+            Ok(plan) => plan,
+            Err(err) => return field(source, None, MirrorStatus::Failed(format!("{:#}", err))),
+        };
+        let current = Some(format!("{} wallets", plan.unchanged + plan.removals.len()));
+        let status = if plan.is_empty() {
+            MirrorStatus::Skipped("already matches".to_string())
+        } else if options.dry_run {
+            MirrorStatus::Planned
+        } else {
+            match destination.execute_allowlist_sync(authority, &plan, None) {
+                Ok(_) => MirrorStatus::Applied,
+                Err(err) => MirrorStatus::Failed(format!("{:#}", err)),
+            }
+        };
+        field(source, current, status)
+    }
+
+    fn mirror_metadata(
+        &self,
+        destination: &TokenVaultClient,
+        authority: &dyn VaultSigner,
+        options: &MirrorOptions,
+        created: bool,
+        receipts: &mut Vec<ActionReceipt>,
+    ) -> MirroredField {
+        let field = |source: String, current: Option<String>, status| MirroredField {
+            field: "metadata".to_string(),
+            source,
+            destination: current,
+            status,
+        };
+        let describe = |config: &Option<crate::NotificationConfig>| match config {
+            Some(config) => config.to_string().replace('\n', "; "),
+            None => "none".to_string(),
+        };
+        let desired = match self.get_notification_config() {
+            Ok(config) => config,
+            Err(err) => {
+                return field(
+                    "?".to_string(),
+                    None,
+                    MirrorStatus::Failed(format!("{:#}", err)),
+                )
+            }
+        };
+        let source = describe(&desired);
+        let current = match created {
+            true => None,
+            false => match destination.get_notification_config() {
+                Ok(config) => config,
+                Err(err) => return field(source, None, MirrorStatus::Failed(format!("{:#}", err))),
+            },
+        };
+        if current == desired {
+            let status = match created {
+                true => MirrorStatus::Skipped("source has none".to_string()),
+                false => MirrorStatus::Skipped("already matches".to_string()),
+            };
+            return field(source, Some(describe(&current)), status);
+        }
+        let status = if options.dry_run {
+            MirrorStatus::Planned
+        } else {
+            let result = match &desired {
+                Some(config) => destination.set_notification_config(authority, config),
+                None => destination.clear_notification_config(authority),
+            };
+            match result {
+                Ok(receipt) => {
+                    receipts.push(receipt);
+                    MirrorStatus::Applied
+                }
+                Err(err) => MirrorStatus::Failed(format!("{:#}", err)),
+            }
+        };
+        field(source, (!created).then(|| describe(&current)), status)
+    }
+}
@@ -0,0 +1,275 @@
+use anchor_client::solana_sdk::{
+    hash::{hash, Hash},
+    pubkey::Pubkey,
+    system_program,
+};
+use anchor_lang::AccountDeserialize;
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::signer::{AsSigner, VaultSigner};
+use crate::token_vault::{accounts, instruction, pda, state};
+use crate::{
+    ActionReceipt, AdminState, CheckedAgainst, Deadline, DeadlinePhase, Operation, OperationKind,
+    TokenVaultClient, VaultChange,
+};
+
+/// Where wallets integrating a vault find its notification metadata
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NotificationConfig {
+    /// SHA-256 of the webhook URL; see `hash_webhook_url`
+    #[serde(default, with = "crate::serde_utils::option_hash")]
+    pub webhook_url_hash: Option<Hash>,
+    /// Base URL of the preferred block explorer; empty for none
+    #[serde(default)]
+    pub explorer: String,
+    /// Email address, URL or handle for support requests; empty for none
+    #[serde(default)]
+    pub support_contact: String,
+}
+
+impl NotificationConfig {
+    /// Publish the hash of `url`, so wallets that know the URL can confirm it
+    /// without the URL being readable on chain
+    pub fn webhook_url(mut self, url: &str) -> Self {
+        self.webhook_url_hash = Some(hash_webhook_url(url));
+        self
+    }
+
+    pub fn explorer(mut self, explorer: impl Into<String>) -> Self {
+        self.explorer = explorer.into();
+        self
+    }
+
+    pub fn support_contact(mut self, contact: impl Into<String>) -> Self {
+        self.support_contact = contact.into();
+        self
+    }
+
+    /// Whether `url` is the webhook URL this config published
+    pub fn is_webhook_url(&self, url: &str) -> bool {
+        self.webhook_url_hash == Some(hash_webhook_url(url))
+    }
+
+    /// Refuse fields the program would reject: over-long or control
+    /// characters in either string, an explorer that is not an http(s) URL,
+    /// or a config with nothing in it, which should be cleared instead
+    pub fn validate(&self) -> Result<()> {
+        check_field(
+            "Explorer",
+            &self.explorer,
+            state::NotificationConfig::MAX_EXPLORER_LEN,
+        )?;
+        check_field(
+            "Support contact",
+            &self.support_contact,
+            state::NotificationConfig::MAX_SUPPORT_CONTACT_LEN,
+        )?;
+        if !self.explorer.is_empty()
+            && !self.explorer.starts_with("https://")
+            && !self.explorer.starts_with("http://")
+        {
+            return Err(anyhow!(
+                "Explorer '{}' must be an http or https URL",
+                self.explorer
+            ));
+        }
+        if self.webhook_url_hash.is_none()
+            && self.explorer.is_empty()
+            && self.support_contact.is_empty()
+        {
+            return Err(anyhow!(
+                "Notification config is empty; clear it to reclaim its rent instead"
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn of(config: &state::NotificationConfig) -> Self {
+        Self {
+            webhook_url_hash: config.webhook_url_hash.map(Hash::new_from_array),
+            explorer: config.explorer.clone(),
+            support_contact: config.support_contact.clone(),
+        }
+    }
+}
+
+impl fmt::Display for NotificationConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let or_none = |field: &str| match field {
+            "" => "none".to_string(),
+            field => field.to_string(),
+        };
+        writeln!(f, "Explorer: {}", or_none(&self.explorer))?;
+        writeln!(f, "Support Contact: {}", or_none(&self.support_contact))?;
+        match &self.webhook_url_hash {
+            Some(hash) => write!(f, "Webhook URL Hash: {}", hash),
+            None => write!(f, "Webhook URL Hash: none"),
+        }
+    }
+}
+
+/// SHA-256 of a webhook URL, ignoring surrounding whitespace
+pub fn hash_webhook_url(url: &str) -> Hash {
+    hash(url.trim().as_bytes())
+}
+
+fn check_field(what: &str, value: &str, max_len: usize) -> Result<()> {
+    if value.len() > max_len {
+        return Err(anyhow!(
+            "{} is {} bytes; at most {} fit",
+            what,
+            value.len(),
+            max_len
+        ));
+    }
+    if value.chars().any(char::is_control) {
+        return Err(anyhow!("{} contains control characters", what));
+    }
+    Ok(())
+}
+
+impl TokenVaultClient {
+    /// The current vault's notification config; `None` when the authority
+    /// has not published one
+    pub fn get_notification_config(&self) -> Result<Option<NotificationConfig>> {
+        let vault = self.current_vault()?;
+        self.fetch_notification_config(vault, self.deadline())
+    }
+
+    pub(crate) fn fetch_notification_config(
+        &self,
+        vault: Pubkey,
+        deadline: Deadline,
+    ) -> Result<Option<NotificationConfig>> {
+        let (address, _) = pda::find_notification_config_address(&self.program.id(), &vault);
+        let rpc = self.rpc_within(deadline, DeadlinePhase::Fetch)?;
+        let account = match rpc
+            .get_account_with_commitment(&address, rpc.commitment())?
+            .value
+        {
+            Some(account) => account,
+            None => return Ok(None),
+        };
+        let config = state::NotificationConfig::try_deserialize(&mut account.data.as_slice())
+            .with_context(|| format!("Failed to decode notification config {}", address))?;
+        Ok(Some(NotificationConfig::of(&config)))
+    }
+
+    /// Publish `config` for the current vault, creating its account on first
+    /// use. It is metadata for wallets, not vault configuration, so immutable
+    /// vaults can change it too. Returns the authority's signed receipt.
+    pub fn set_notification_config(
+        &self,
+        authority: &dyn VaultSigner,
+        config: &NotificationConfig,
+    ) -> Result<ActionReceipt> {
+        config.validate()?;
+        let (vault, notification_config, deadline) =
+            self.check_notification_authority(authority)?;
+        let before = self.fetch_notification_config(vault, deadline)?;
+
+        let signer = AsSigner(authority);
+        let request = self
+            .vault_request(&[vault])?
+            .accounts(accounts::SetNotificationConfig {
+                authority: authority.pubkey(),
+                vault,
+                notification_config,
+                system_program: system_program::ID,
+            })
+            .args(instruction::SetNotificationConfig {
+                webhook_url_hash: config.webhook_url_hash.map(|hash| hash.to_bytes()),
+                explorer: config.explorer.clone(),
+                support_contact: config.support_contact.clone(),
+            })
+            .signer(&signer);
+        let operation = Operation::new(
+            OperationKind::SetNotificationConfig,
+            authority.pubkey(),
+            Some(vault),
+            0,
+        );
+        let signature = self.send_operation(operation, request, deadline)?;
+        self.invalidate(&notification_config);
+
+        self.action_receipt(
+            authority,
+            vault,
+            VaultChange::NotificationsSet {
+                config: config.clone(),
+            },
+            signature,
+            AdminState::Notifications { config: before },
+            AdminState::Notifications {
+                config: Some(config.clone()),
+            },
+        )
+    }
+
+    /// Remove the current vault's notification config, refunding its rent to
+    /// the authority, and return the authority's signed receipt
+    pub fn clear_notification_config(&self, authority: &dyn VaultSigner) -> Result<ActionReceipt> {
+        let (vault, notification_config, deadline) =
+            self.check_notification_authority(authority)?;
+        let before = self
+            .fetch_notification_config(vault, deadline)?
+            .ok_or_else(|| anyhow!("Vault {} has no notification config", vault))?;
+
+        let signer = AsSigner(authority);
+        let request = self
+            .vault_request(&[vault])?
+            .accounts(accounts::ClearNotificationConfig {
+                authority: authority.pubkey(),
+                vault,
+                notification_config,
+            })
+            .args(instruction::ClearNotificationConfig {})
+            .signer(&signer);
+        let operation = Operation::new(
+            OperationKind::ClearNotificationConfig,
+            authority.pubkey(),
+            Some(vault),
+            0,
+        );
+        let signature = self.send_operation(operation, request, deadline)?;
+        self.invalidate(&notification_config);
+
+        self.action_receipt(
+            authority,
+            vault,
+            VaultChange::NotificationsCleared,
+            signature,
+            AdminState::Notifications {
+                config: Some(before),
+            },
+            AdminState::Notifications { config: None },
+        )
+    }
+
+    /// The vault, its notification config address and the call's deadline,
+    /// once `authority` is known to control the vault
+    fn check_notification_authority(
+        &self,
+        authority: &dyn VaultSigner,
+    ) -> Result<(Pubkey, Pubkey, Deadline)> {
+        self.check_signer(&authority.pubkey())?;
+        let deadline = self.deadline();
+        self.check_features(deadline)?;
+        let vault = self.current_vault()?;
+
+        let (vault_data, provenance) = self.fetch_vault_state(vault, deadline, true)?;
+        if vault_data.authority != authority.pubkey() {
+            return Err(anyhow!(
+                "{} is not the authority of vault {}",
+                authority.pubkey(),
+                vault
+            ))
+            .context(CheckedAgainst(provenance));
+        }
+        let (notification_config, _) =
+            pda::find_notification_config_address(&self.program.id(), &vault);
+        Ok((vault, notification_config, deadline))
+    }
+}
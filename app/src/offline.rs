@@ -0,0 +1,335 @@
+//! Transactions for keys the client never holds, such as a vault authority
+//! that is a Squads multisig or a cold wallet. Each `build_*_transaction`
+//! takes the signer as a plain `Pubkey`, runs the pre-checks of the method
+//! that would send the operation, and returns the transaction unsigned,
+//! paid by that signer. `serialize_base64` writes it in the wire format
+//! Squads imports and `solana ... --sign-only` flows pass around;
+//! `send_signed_transaction` submits it once it carries its signatures.
+//!
+//! The recent blockhash is fetched when the transaction is built and
+//! expires after about a minute. Flows that take longer should rebuild the
+//! transaction, or take only its instructions and message from it.
+
+use anchor_client::solana_sdk::{
+    instruction::Instruction, message::Message, pubkey::Pubkey, system_program, sysvar,
+    transaction::Transaction,
+};
+use anchor_lang::{InstructionData, ToAccountMetas};
+use anchor_spl::associated_token::get_associated_token_address;
+use anchor_spl::token;
+use anyhow::{anyhow, Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
+
+use crate::logging;
+use crate::pause::check_not_paused;
+use crate::priority_fee::compute_budget_instructions;
+use crate::token_vault::{
+    accounts, instruction,
+    pda::{self, VaultAddresses},
+};
+use crate::vault_update::check_new_holder;
+use crate::{
+    freeze, CheckedAgainst, Deadline, DeadlinePhase, DustMode, OperationKind, TokenVaultClient,
+    TxReceipt, UpdateVaultParams, VaultConfig, WithdrawalQuote,
+};
+
+/// `transaction` in the wire format, base64 encoded
+pub fn serialize_base64(transaction: &Transaction) -> Result<String> {
+    let bytes = bincode::serialize(transaction).context("Failed to serialize transaction")?;
+    Ok(STANDARD.encode(bytes))
+}
+
+/// The transaction `serialize_base64` wrote
+pub fn deserialize_base64(encoded: &str) -> Result<Transaction> {
+    let bytes = STANDARD
+        .decode(encoded.trim())
+        .context("Transaction is not valid base64")?;
+    bincode::deserialize(&bytes).context("Failed to deserialize transaction")
+}
+
+impl TokenVaultClient {
+    /// `initialize_vault` for `authority`, unsigned
+    pub fn build_initialize_vault_transaction(
+        &self,
+        authority: Pubkey,
+        token_mint: Pubkey,
+        config: &VaultConfig,
+    ) -> Result<Transaction> {
+        self.check_signer(&authority)?;
+        let deadline = self.deadline();
+        self.check_features(deadline)?;
+        let mint = self.fetch_mint(token_mint, deadline)?;
+        freeze::check_freezable_mint(
+            token_mint,
+            &mint,
+            self.strict_mint_policy,
+            config.acknowledge_freezable_mint,
+        )?;
+        let withdrawal_limit = config
+            .withdrawal_limit
+            .to_base_units(mint.decimals)
+            .context("Invalid withdrawal limit")?;
+        let addresses =
+            VaultAddresses::derive(&self.program.id(), &authority, &token_mint, &config.name)?;
+
+        let initialize = self.program_instruction(
+            accounts::InitializeVault {
+                authority,
+                vault: addresses.vault,
+                vault_token_account: addresses.vault_token_account,
+                reserve_token_account: addresses.reserve_token_account,
+                token_mint,
+                token_program: token::ID,
+                system_program: system_program::ID,
+                rent: sysvar::rent::ID,
+            },
+            instruction::InitializeVault {
+                name: config.name.clone(),
+                fee_percentage: config.fee_bps,
+                withdrawal_timelock: config.timelock,
+                withdrawal_limit,
+                immutable: config.immutable,
+                deposit_window_start: config.deposit_window.start,
+                deposit_window_end: config.deposit_window.end,
+                dust_threshold: config.dust_policy.threshold,
+                waive_dust_fee: config.dust_policy.mode == DustMode::WaiveFee,
+                reserve_bps: config.reserve.bps,
+                reserve_timelock: config.reserve.timelock,
+                fee_collector: config.fee_collector.unwrap_or(authority),
+            },
+        );
+        self.unsigned_transaction(&addresses.vault, vec![initialize], &authority, deadline)
+    }
+
+    /// `deposit` from `depositor`'s associated token account, unsigned
+    pub fn build_deposit_transaction(&self, depositor: Pubkey, amount: u64) -> Result<Transaction> {
+        crate::dust::ensure_nonzero("deposit", amount)?;
+        self.check_signer(&depositor)?;
+        let deadline = self.deadline();
+        self.check_features(deadline)?;
+        let vault = self.current_vault()?;
+        let (vault_data, provenance) = self.fetch_vault_state(vault, deadline, true)?;
+        self.vault_lifecycle(vault, &vault_data, deadline)?
+            .check(OperationKind::Deposit)
+            .context(CheckedAgainst(provenance))?;
+        self.verify_deposit_source(&depositor, &vault_data.token_mint, amount, deadline)
+            .context(CheckedAgainst(provenance))?;
+
+        let (vault_token_account, reserve_token_account) = self.vault_token_accounts(&vault);
+        let deposit = self.program_instruction(
+            accounts::Deposit {
+                depositor,
+                vault,
+                vault_token_account,
+                reserve_token_account,
+                depositor_token_account: get_associated_token_address(
+                    &depositor,
+                    &vault_data.token_mint,
+                ),
+                token_program: token::ID,
+                position: pda::find_position_address(&self.program.id(), &vault, &depositor).0,
+                system_program: system_program::ID,
+            },
+            instruction::Deposit { amount },
+        );
+        self.unsigned_transaction(&vault, vec![deposit], &depositor, deadline)
+    }
+
+    /// `withdraw` to `withdrawer`'s associated token account, unsigned. The
+    /// account is created first if it does not exist, with `withdrawer`
+    /// paying the rent.
+    pub fn build_withdraw_transaction(
+        &self,
+        withdrawer: Pubkey,
+        amount: u64,
+    ) -> Result<Transaction> {
+        self.check_signer(&withdrawer)?;
+        let deadline = self.deadline();
+        self.check_features(deadline)?;
+        let vault = self.current_vault()?;
+        let (vault_data, provenance) = self.fetch_vault_state(vault, deadline, true)?;
+        check_not_paused(&vault_data).context(CheckedAgainst(provenance))?;
+        WithdrawalQuote::for_vault(&vault_data, amount).context(CheckedAgainst(provenance))?;
+        self.check_accounting(&vault_data, amount)
+            .context(CheckedAgainst(provenance))?;
+
+        let (vault_token_account, _) = self.vault_token_accounts(&vault);
+        let create =
+            spl_associated_token_account::instruction::create_associated_token_account_idempotent(
+                &withdrawer,
+                &withdrawer,
+                &vault_data.token_mint,
+                &token::ID,
+            );
+        let withdraw = self.program_instruction(
+            accounts::Withdraw {
+                withdrawer,
+                vault,
+                vault_token_account,
+                withdrawer_token_account: get_associated_token_address(
+                    &withdrawer,
+                    &vault_data.token_mint,
+                ),
+                token_program: token::ID,
+                position: pda::find_position_address(&self.program.id(), &vault, &withdrawer).0,
+            },
+            instruction::Withdraw { amount },
+        );
+        self.unsigned_transaction(&vault, vec![create, withdraw], &withdrawer, deadline)
+    }
+
+    /// `update_vault_config` by `authority`, unsigned
+    pub fn build_update_vault_config_transaction(
+        &self,
+        authority: Pubkey,
+        params: UpdateVaultParams,
+    ) -> Result<Transaction> {
+        params.check()?;
+        if params.is_empty() {
+            return Err(anyhow!("No settings to update"));
+        }
+        let (vault, _, deadline) = self.check_authority_key(&authority)?;
+        let update = self.program_instruction(
+            accounts::UpdateVaultConfig { authority, vault },
+            instruction::UpdateVaultConfig {
+                fee_percentage: params.fee_percentage,
+                withdrawal_timelock: params.withdrawal_timelock,
+                withdrawal_limit: params.withdrawal_limit,
+            },
+        );
+        self.unsigned_transaction(&vault, vec![update], &authority, deadline)
+    }
+
+    /// `transfer_authority` from `authority` to `new_authority`, unsigned
+    pub fn build_transfer_authority_transaction(
+        &self,
+        authority: Pubkey,
+        new_authority: Pubkey,
+    ) -> Result<Transaction> {
+        let (vault, vault_data, deadline) = self.check_authority_key(&authority)?;
+        if !check_new_holder("authority", vault_data.authority, new_authority)? {
+            return Err(anyhow!(
+                "{} is already the authority of vault {}",
+                new_authority,
+                vault
+            ));
+        }
+        let transfer = self.program_instruction(
+            accounts::TransferAuthority { authority, vault },
+            instruction::TransferAuthority { new_authority },
+        );
+        self.unsigned_transaction(&vault, vec![transfer], &authority, deadline)
+    }
+
+    /// `set_fee_collector` by `authority`, unsigned
+    pub fn build_set_fee_collector_transaction(
+        &self,
+        authority: Pubkey,
+        new_fee_collector: Pubkey,
+    ) -> Result<Transaction> {
+        let (vault, vault_data, deadline) = self.check_authority_key(&authority)?;
+        if !check_new_holder("fee collector", vault_data.fee_collector, new_fee_collector)? {
+            return Err(anyhow!(
+                "{} is already the fee collector of vault {}",
+                new_fee_collector,
+                vault
+            ));
+        }
+        let set = self.program_instruction(
+            accounts::SetFeeCollector { authority, vault },
+            instruction::SetFeeCollector { new_fee_collector },
+        );
+        self.unsigned_transaction(&vault, vec![set], &authority, deadline)
+    }
+
+    /// `pause_vault` by `authority`, unsigned
+    pub fn build_pause_vault_transaction(&self, authority: Pubkey) -> Result<Transaction> {
+        let (vault, vault_data, deadline) = self.check_authority_key(&authority)?;
+        if vault_data.paused {
+            return Err(anyhow!("Vault {} is already paused", vault));
+        }
+        let pause = self.program_instruction(
+            accounts::PauseVault { authority, vault },
+            instruction::PauseVault {},
+        );
+        self.unsigned_transaction(&vault, vec![pause], &authority, deadline)
+    }
+
+    /// `unpause_vault` by `authority`, unsigned
+    pub fn build_unpause_vault_transaction(&self, authority: Pubkey) -> Result<Transaction> {
+        let (vault, vault_data, deadline) = self.check_authority_key(&authority)?;
+        if !vault_data.paused {
+            return Err(anyhow!("Vault {} is already not paused", vault));
+        }
+        let unpause = self.program_instruction(
+            accounts::UnpauseVault { authority, vault },
+            instruction::UnpauseVault {},
+        );
+        self.unsigned_transaction(&vault, vec![unpause], &authority, deadline)
+    }
+
+    /// Submit a transaction built by a `build_*_transaction` method once
+    /// every signature it needs is on it, and wait for its confirmation
+    pub fn send_signed_transaction(&self, transaction: &Transaction) -> Result<TxReceipt> {
+        if !transaction.is_signed() {
+            return Err(anyhow!(
+                "Transaction is missing {} of its {} signatures",
+                transaction
+                    .signatures
+                    .iter()
+                    .filter(|signature| **signature == Default::default())
+                    .count(),
+                transaction.message.header.num_required_signatures
+            ));
+        }
+        transaction
+            .verify()
+            .context("Transaction signatures do not match its message")?;
+        let signature = self.submit(&transaction.clone().into(), self.deadline())?;
+        logging::info!(signature = %signature, "signed transaction confirmed");
+        Ok(self.tx_receipt(signature))
+    }
+
+    fn program_instruction(
+        &self,
+        accounts: impl ToAccountMetas,
+        data: impl InstructionData,
+    ) -> Instruction {
+        Instruction {
+            program_id: self.program.id(),
+            accounts: accounts.to_account_metas(None),
+            data: data.data(),
+        }
+    }
+
+    /// The vault's token account and reserve token account
+    fn vault_token_accounts(&self, vault: &Pubkey) -> (Pubkey, Pubkey) {
+        let program_id = self.program.id();
+        (
+            crate::token_vault::pda::find_vault_token_account_address(&program_id, vault).0,
+            crate::token_vault::pda::find_reserve_token_account_address(&program_id, vault).0,
+        )
+    }
+
+    /// `instructions` as an unsigned transaction paid by `fee_payer`, after
+    /// the compute budget instructions of the client's priority fee
+    fn unsigned_transaction(
+        &self,
+        vault: &Pubkey,
+        instructions: Vec<Instruction>,
+        fee_payer: &Pubkey,
+        deadline: Deadline,
+    ) -> Result<Transaction> {
+        let mut all = match self.priority_fee {
+            Some(config) => {
+                compute_budget_instructions(config.cu_limit, self.priority_fee(&[*vault])?)
+            }
+            None => Vec::new(),
+        };
+        all.extend(instructions);
+        let rpc = self.rpc_within(deadline, DeadlinePhase::Fetch)?;
+        let mut transaction = Transaction::new_unsigned(Message::new(&all, Some(fee_payer)));
+        transaction.message.recent_blockhash = rpc.get_latest_blockhash()?;
+        Ok(transaction)
+    }
+}
@@ -0,0 +1,106 @@
+//! The client's core vault operations as a trait. Code written against
+//! `VaultOps` runs on a `TokenVaultClient` in production and on
+//! `testing::MockVaultClient` (feature `testing`) in unit tests, which need
+//! no validator.
+//!
+//! ```
+//! # use anyhow::Result;
+//! # use token_vault_client::{TxReceipt, VaultOps, VaultSigner};
+//! /// Deposit only what the vault credits in full, skipping the reserve cut
+//! fn deposit_if_uncut(
+//!     vault: &dyn VaultOps,
+//!     depositor: &dyn VaultSigner,
+//!     amount: u64,
+//! ) -> Result<Option<TxReceipt>> {
+//!     if vault.quote_deposit(amount)?.reserve > 0 {
+//!         return Ok(None);
+//!     }
+//!     vault.deposit(depositor, amount).map(Some)
+//! }
+//! ```
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use anyhow::Result;
+
+use crate::signer::VaultSigner;
+use crate::token_vault::state::Vault;
+use crate::{
+    DepositQuote, InitializedVault, TokenVaultClient, TxReceipt, VaultConfig, WithdrawPreview,
+    WithdrawalQuote,
+};
+
+/// Creating a vault, moving tokens in and out of the current one, and
+/// reading and previewing it. Every method behaves as the
+/// `TokenVaultClient` method of the same name.
+pub trait VaultOps {
+    /// The vault the other methods act on; `None` until one is set
+    fn vault_address(&self) -> Option<Pubkey>;
+
+    fn initialize_vault(
+        &self,
+        authority: &dyn VaultSigner,
+        token_mint: Pubkey,
+        config: &VaultConfig,
+    ) -> Result<InitializedVault>;
+
+    fn deposit(&self, depositor: &dyn VaultSigner, amount: u64) -> Result<TxReceipt>;
+
+    fn withdraw(
+        &self,
+        withdrawer: &dyn VaultSigner,
+        amount: u64,
+        recipient: Option<Pubkey>,
+    ) -> Result<TxReceipt>;
+
+    fn get_vault_info(&self) -> Result<Vault>;
+
+    fn quote_deposit(&self, amount: u64) -> Result<DepositQuote>;
+
+    fn quote_withdrawal(&self, amount: u64) -> Result<WithdrawalQuote>;
+
+    fn preview_withdraw(&self, amount: u64) -> Result<WithdrawPreview>;
+}
+
+impl VaultOps for TokenVaultClient {
+    fn vault_address(&self) -> Option<Pubkey> {
+        self.vault_address
+    }
+
+    fn initialize_vault(
+        &self,
+        authority: &dyn VaultSigner,
+        token_mint: Pubkey,
+        config: &VaultConfig,
+    ) -> Result<InitializedVault> {
+        TokenVaultClient::initialize_vault(self, authority, token_mint, config)
+    }
+
+    fn deposit(&self, depositor: &dyn VaultSigner, amount: u64) -> Result<TxReceipt> {
+        TokenVaultClient::deposit(self, depositor, amount)
+    }
+
+    fn withdraw(
+        &self,
+        withdrawer: &dyn VaultSigner,
+        amount: u64,
+        recipient: Option<Pubkey>,
+    ) -> Result<TxReceipt> {
+        TokenVaultClient::withdraw(self, withdrawer, amount, recipient)
+    }
+
+    fn get_vault_info(&self) -> Result<Vault> {
+        TokenVaultClient::get_vault_info(self)
+    }
+
+    fn quote_deposit(&self, amount: u64) -> Result<DepositQuote> {
+        TokenVaultClient::quote_deposit(self, amount)
+    }
+
+    fn quote_withdrawal(&self, amount: u64) -> Result<WithdrawalQuote> {
+        TokenVaultClient::quote_withdrawal(self, amount)
+    }
+
+    fn preview_withdraw(&self, amount: u64) -> Result<WithdrawPreview> {
+        TokenVaultClient::preview_withdraw(self, amount)
+    }
+}
@@ -0,0 +1,262 @@
+//! Cursor pagination shared by the listing APIs.
+//!
+//! Every listing returns a `Page` whose `next_cursor` resumes after its last
+//! item. Program accounts are paged by a key that never changes for the
+//! life of the account (a vault's name and address, an allowlisted wallet),
+//! and each page holds only keys after the cursor's. History is paged by
+//! signature, walking back from the newest. Either way, an item present for
+//! the whole iteration is returned exactly once, even when others are added
+//! or removed between pages. Items added or removed mid-iteration may or
+//! may not appear.
+//!
+//! The RPC cannot page program accounts itself, so every page of vaults or
+//! allowlist entries is a full scan whose result is cut down client-side.
+//! Paging bounds what is returned and passed on, not the RPC's work.
+
+use anchor_client::solana_client::{
+    rpc_client::RpcClient,
+    rpc_config::RpcProgramAccountsConfig,
+    rpc_request::RpcRequest,
+    rpc_response::{OptionalContext, RpcKeyedAccount},
+};
+use anchor_client::solana_sdk::{account::Account, pubkey::Pubkey};
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
+use crate::{VaultSummary, VaultTransaction};
+
+/// Most items a page may hold, which is also the most signatures
+/// `getSignaturesForAddress` returns at once
+pub const MAX_PAGE_LIMIT: usize = 1_000;
+
+/// An item of a paged listing
+pub trait Paginated {
+    /// Names the listing, so a cursor from one listing is refused by another
+    const LISTING: &'static str;
+    /// What a cursor remembers of the last item of a page
+    type Key: Clone + fmt::Debug + PartialEq + Serialize + DeserializeOwned;
+
+    fn page_key(&self) -> Self::Key;
+}
+
+/// Vaults are ordered by name, then address, as `list_vaults` returns them
+impl Paginated for VaultSummary {
+    const LISTING: &'static str = "vaults";
+    type Key = (String, Pubkey);
+
+    fn page_key(&self) -> Self::Key {
+        (self.name.clone(), self.address)
+    }
+}
+
+/// Allowlisted wallets, in address order
+impl Paginated for Pubkey {
+    const LISTING: &'static str = "allowlist";
+    type Key = Pubkey;
+
+    fn page_key(&self) -> Self::Key {
+        *self
+    }
+}
+
+/// History, newest first; the key is the signature to continue before
+impl Paginated for VaultTransaction {
+    const LISTING: &'static str = "history";
+    type Key = String;
+
+    fn page_key(&self) -> Self::Key {
+        self.signature.to_string()
+    }
+}
+
+/// Where the next page of a `T` listing starts. Opaque: pass on the string
+/// from `Display` (URL-safe base64) and read it back with `FromStr`.
+pub struct Cursor<T: Paginated> {
+    after: T::Key,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CursorBody<K> {
+    listing: String,
+    after: K,
+}
+
+impl<T: Paginated> Cursor<T> {
+    /// Cursor resuming after `item`
+    pub fn after(item: &T) -> Self {
+        Self {
+            after: item.page_key(),
+        }
+    }
+
+    /// Key of the last item already returned
+    pub fn key(&self) -> &T::Key {
+        &self.after
+    }
+}
+
+impl<T: Paginated> Clone for Cursor<T> {
+    fn clone(&self) -> Self {
+        Self {
+            after: self.after.clone(),
+        }
+    }
+}
+
+impl<T: Paginated> fmt::Debug for Cursor<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Cursor")
+            .field("listing", &T::LISTING)
+            .field("after", &self.after)
+            .finish()
+    }
+}
+
+impl<T: Paginated> PartialEq for Cursor<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.after == other.after
+    }
+}
+
+impl<T: Paginated> fmt::Display for Cursor<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let body = CursorBody {
+            listing: T::LISTING.to_string(),
+            after: &self.after,
+        };
+        let json = serde_json::to_vec(&body).map_err(|_| fmt::Error)?;
+        f.write_str(&URL_SAFE_NO_PAD.encode(json))
+    }
+}
+
+impl<T: Paginated> FromStr for Cursor<T> {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let json = URL_SAFE_NO_PAD
+            .decode(s.trim())
+            .map_err(|_| anyhow!("Invalid cursor: not URL-safe base64"))?;
+        let body: CursorBody<serde_json::Value> =
+            serde_json::from_slice(&json).map_err(|_| anyhow!("Invalid cursor"))?;
+        if body.listing != T::LISTING {
+            return Err(anyhow!(
+                "Cursor is for the {} listing, not {}",
+                body.listing,
+                T::LISTING
+            ));
+        }
+        Ok(Self {
+            after: serde_json::from_value(body.after).map_err(|_| anyhow!("Invalid cursor"))?,
+        })
+    }
+}
+
+impl<T: Paginated> Serialize for Cursor<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de, T: Paginated> Deserialize<'de> for Cursor<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// One page of a listing
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(bound(
+    serialize = "T: Paginated + Serialize",
+    deserialize = "T: Paginated + Deserialize<'de>"
+))]
+pub struct Page<T: Paginated> {
+    pub items: Vec<T>,
+    /// Where the next page starts; `None` on the last page
+    pub next_cursor: Option<Cursor<T>>,
+    /// Slot of the read this page was cut from
+    pub context_slot: u64,
+}
+
+impl<T: Paginated> Page<T> {
+    pub fn is_last(&self) -> bool {
+        self.next_cursor.is_none()
+    }
+}
+
+/// Refuse page sizes of zero or above `MAX_PAGE_LIMIT`
+pub(crate) fn check_limit(limit: usize) -> Result<()> {
+    if limit == 0 || limit > MAX_PAGE_LIMIT {
+        return Err(anyhow!(
+            "Page limit must be between 1 and {}",
+            MAX_PAGE_LIMIT
+        ));
+    }
+    Ok(())
+}
+
+/// The page of `items` after `cursor`, by key order. `items` is a full
+/// read taken at `context_slot`.
+pub fn paginate<T>(
+    mut items: Vec<T>,
+    cursor: Option<&Cursor<T>>,
+    limit: usize,
+    context_slot: u64,
+) -> Page<T>
+where
+    T: Paginated,
+    T::Key: Ord,
+{
+    items.sort_by_key(|item| item.page_key());
+    if let Some(cursor) = cursor {
+        items.retain(|item| item.page_key() > cursor.after);
+    }
+    let next_cursor = match items.len() > limit {
+        true => {
+            items.truncate(limit);
+            items.last().map(Cursor::after)
+        }
+        false => None,
+    };
+    Page {
+        items,
+        next_cursor,
+        context_slot,
+    }
+}
+
+/// `getProgramAccounts` with the slot it was answered at
+pub(crate) fn program_accounts_with_slot(
+    rpc: &RpcClient,
+    program_id: &Pubkey,
+    mut config: RpcProgramAccountsConfig,
+) -> Result<(Vec<(Pubkey, Account)>, u64)> {
+    config.with_context = Some(true);
+    let response: OptionalContext<Vec<RpcKeyedAccount>> = rpc.send(
+        RpcRequest::GetProgramAccounts,
+        serde_json::json!([program_id.to_string(), config]),
+    )?;
+    let (slot, keyed) = match response {
+        OptionalContext::Context(response) => (response.context.slot, response.value),
+        OptionalContext::NoContext(_) => {
+            return Err(anyhow!(
+                "RPC answered getProgramAccounts without a context slot"
+            ))
+        }
+    };
+    let accounts = keyed
+        .into_iter()
+        .map(|keyed| {
+            let address = keyed.pubkey.parse()?;
+            let account = keyed
+                .account
+                .decode()
+                .ok_or_else(|| anyhow!("Account {} could not be decoded", address))?;
+            Ok((address, account))
+        })
+        .collect::<Result<_>>()?;
+    Ok((accounts, slot))
+}
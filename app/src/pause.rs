@@ -0,0 +1,88 @@
+//! Stopping a vault's deposits and withdrawals, for example while an
+//! incident is investigated, and letting them through again. A paused vault
+//! is in the `Paused` stage of its lifecycle; `check_not_paused` refuses its
+//! withdrawals before a transaction fee is spent.
+
+use anyhow::{anyhow, Result};
+
+use crate::signer::{AsSigner, VaultSigner};
+use crate::token_vault::{accounts, instruction, state::Vault};
+use crate::{
+    ActionReceipt, AdminState, Operation, OperationKind, TokenVaultClient, TokenVaultError,
+    VaultChange,
+};
+
+/// Fail with `VaultPaused` when `vault` is paused
+pub fn check_not_paused(vault: &Vault) -> Result<(), TokenVaultError> {
+    if vault.paused {
+        return Err(TokenVaultError::VaultPaused {
+            authority: vault.authority,
+        });
+    }
+    Ok(())
+}
+
+impl TokenVaultClient {
+    /// Stop deposits and withdrawals into and out of the current vault until
+    /// `unpause_vault`. Withdrawal requests can still be made and cancelled;
+    /// they execute once the vault is unpaused. Fails when the vault is
+    /// already paused.
+    pub fn pause_vault(&self, authority: &dyn VaultSigner) -> Result<ActionReceipt> {
+        self.set_paused(authority, true)
+    }
+
+    /// Let deposits and withdrawals through the current vault again
+    pub fn unpause_vault(&self, authority: &dyn VaultSigner) -> Result<ActionReceipt> {
+        self.set_paused(authority, false)
+    }
+
+    fn set_paused(&self, authority: &dyn VaultSigner, paused: bool) -> Result<ActionReceipt> {
+        let (vault, vault_data, deadline) = self.check_vault_authority(authority)?;
+        if vault_data.paused == paused {
+            return Err(anyhow!(
+                "Vault {} is already {}",
+                vault,
+                AdminState::Paused { paused }
+            ));
+        }
+
+        let request = self.vault_request(&[vault])?;
+        let (request, kind, change) = if paused {
+            (
+                request
+                    .accounts(accounts::PauseVault {
+                        authority: authority.pubkey(),
+                        vault,
+                    })
+                    .args(instruction::PauseVault {}),
+                OperationKind::PauseVault,
+                VaultChange::Paused,
+            )
+        } else {
+            (
+                request
+                    .accounts(accounts::UnpauseVault {
+                        authority: authority.pubkey(),
+                        vault,
+                    })
+                    .args(instruction::UnpauseVault {}),
+                OperationKind::UnpauseVault,
+                VaultChange::Unpaused,
+            )
+        };
+        let signer = AsSigner(authority);
+        let request = request.signer(&signer);
+        let operation = Operation::new(kind, authority.pubkey(), Some(vault), 0);
+        let signature = self.send_operation(operation, request, deadline)?;
+        self.invalidate(&vault);
+
+        self.action_receipt(
+            authority,
+            vault,
+            change,
+            signature,
+            AdminState::Paused { paused: !paused },
+            AdminState::Paused { paused },
+        )
+    }
+}
@@ -0,0 +1,346 @@
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use anchor_lang::AccountDeserialize;
+use anchor_spl::token::TokenAccount;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::freeze;
+use crate::token_vault::pda;
+use crate::vault_update::check_new_holder;
+use crate::{
+    CostEstimate, DeadlinePhase, DepositWindow, DustPolicy, TokenVaultClient, UpdateVaultParams,
+    VaultConfig,
+};
+
+/// Requested changes to a vault's configuration; `None` leaves a field untouched
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConfigChanges {
+    pub fee_percentage: Option<u16>,
+    pub withdrawal_timelock: Option<i64>,
+    pub withdrawal_limit: Option<u64>,
+    pub deposit_window: Option<DepositWindow>,
+    pub dust_policy: Option<DustPolicy>,
+}
+
+/// A single field that an operation would change
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FieldChange {
+    pub field: String,
+    pub old: String,
+    pub new: String,
+}
+
+/// Per-field preview of what an operation would change on a vault
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConfigDiff {
+    pub operation: String,
+    pub vault: String,
+    pub changes: Vec<FieldChange>,
+}
+
+impl ConfigDiff {
+    fn new(operation: &str, vault: Pubkey) -> Self {
+        Self {
+            operation: operation.to_string(),
+            vault: vault.to_string(),
+            changes: Vec::new(),
+        }
+    }
+
+    fn push(&mut self, field: &str, old: String, new: String) {
+        if old != new {
+            self.changes.push(FieldChange {
+                field: field.to_string(),
+                old,
+                new,
+            });
+        }
+    }
+
+    /// True when applying the operation would not change anything
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+impl fmt::Display for ConfigDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} on vault {}", self.operation, self.vault)?;
+        if self.changes.is_empty() {
+            return write!(f, "  (no changes)");
+        }
+        for change in &self.changes {
+            writeln!(f, "  {}: {} -> {}", change.field, change.old, change.new)?;
+        }
+        Ok(())
+    }
+}
+
+/// Preview of closing a vault: the rent that would be reclaimed and the
+/// token balances that have to be drained first
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClosePlan {
+    pub vault: String,
+    pub vault_token_account: String,
+    pub reclaimable_lamports: u64,
+    pub token_balance: u64,
+    /// The insurance reserve's token account, emptied with `release_reserve`
+    #[serde(default)]
+    pub reserve_token_account: String,
+    #[serde(default)]
+    pub reserve_balance: u64,
+    /// Both token accounts are empty
+    pub can_close: bool,
+}
+
+impl fmt::Display for ClosePlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "close_vault on vault {}", self.vault)?;
+        writeln!(
+            f,
+            "  rent reclaimed: {} ({} lamports)",
+            format_sol(self.reclaimable_lamports),
+            self.reclaimable_lamports
+        )?;
+        writeln!(
+            f,
+            "  token balance in {}: {} (must be 0)",
+            self.vault_token_account, self.token_balance
+        )?;
+        writeln!(
+            f,
+            "  reserve balance in {}: {} (must be 0; see release_reserve)",
+            self.reserve_token_account, self.reserve_balance
+        )?;
+        if !self.can_close {
+            write!(
+                f,
+                "  vault cannot be closed until its token and reserve accounts are empty"
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Preview of creating a vault: its address, the resolved configuration and
+/// who could freeze its tokens
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InitPlan {
+    pub vault: String,
+    pub name: String,
+    pub token_mint: String,
+    pub fee_percentage: u16,
+    pub withdrawal_timelock: i64,
+    /// In base units, converted with the mint's decimals
+    pub withdrawal_limit: u64,
+    pub fee_collector: String,
+    /// The mint's freeze authority, which could freeze the vault's token account
+    pub freeze_authority: Option<String>,
+    /// False when the client's strict mint policy would refuse the mint
+    pub can_create: bool,
+    /// Rent and fees the authority pays to create the vault
+    pub cost: CostEstimate,
+}
+
+impl fmt::Display for InitPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "initialize_vault {} ({})", self.vault, self.name)?;
+        writeln!(f, "  mint: {}", self.token_mint)?;
+        writeln!(f, "  fee: {}", format_bps(self.fee_percentage))?;
+        writeln!(
+            f,
+            "  withdrawal timelock: {}",
+            format_duration(self.withdrawal_timelock)
+        )?;
+        writeln!(f, "  withdrawal limit: {}", self.withdrawal_limit)?;
+        writeln!(f, "  fee collector: {}", self.fee_collector)?;
+        writeln!(f, "  cost: {}", self.cost)?;
+        match &self.freeze_authority {
+            None => write!(f, "  freeze authority: none")?,
+            Some(authority) => {
+                writeln!(f, "  freeze authority: {}", authority)?;
+                write!(
+                    f,
+                    "  warning: the freeze authority can freeze the vault's token account, \
+                     halting every deposit and withdrawal"
+                )?;
+            }
+        }
+        if !self.can_create {
+            write!(
+                f,
+                "\n  the strict mint policy refuses this mint unless it is acknowledged"
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl TokenVaultClient {
+    /// Preview a configuration update without sending a transaction
+    pub fn plan_update(&self, changes: &ConfigChanges) -> Result<ConfigDiff> {
+        let vault = self.current_vault()?;
+        let vault_data = self.get_vault_info()?;
+        Self::ensure_mutable(vault, &vault_data)?;
+
+        UpdateVaultParams::from(changes).check()?;
+        let mut diff = ConfigDiff::new("update_vault_config", vault);
+        if let Some(fee_percentage) = changes.fee_percentage {
+            diff.push(
+                "fee_percentage",
+                format_bps(vault_data.fee_percentage),
+                format_bps(fee_percentage),
+            );
+        }
+        if let Some(withdrawal_timelock) = changes.withdrawal_timelock {
+            diff.push(
+                "withdrawal_timelock",
+                format_duration(vault_data.withdrawal_timelock),
+                format_duration(withdrawal_timelock),
+            );
+        }
+        if let Some(withdrawal_limit) = changes.withdrawal_limit {
+            diff.push(
+                "withdrawal_limit",
+                vault_data.withdrawal_limit.to_string(),
+                withdrawal_limit.to_string(),
+            );
+        }
+        if let Some(window) = changes.deposit_window {
+            let window = DepositWindow::new(window.start, window.end)?;
+            diff.push(
+                "deposit_window",
+                DepositWindow::of(&vault_data).to_string(),
+                window.to_string(),
+            );
+        }
+        if let Some(policy) = changes.dust_policy {
+            diff.push(
+                "dust_policy",
+                DustPolicy::of(&vault_data).to_string(),
+                policy.to_string(),
+            );
+        }
+
+        Ok(diff)
+    }
+
+    /// Preview a fee collector change without sending a transaction
+    pub fn plan_set_fee_collector(&self, new_fee_collector: Pubkey) -> Result<ConfigDiff> {
+        let vault = self.current_vault()?;
+        let vault_data = self.get_vault_info()?;
+        Self::ensure_mutable(vault, &vault_data)?;
+
+        check_new_holder("fee collector", vault_data.fee_collector, new_fee_collector)?;
+        let mut diff = ConfigDiff::new("set_fee_collector", vault);
+        diff.push(
+            "fee_collector",
+            vault_data.fee_collector.to_string(),
+            new_fee_collector.to_string(),
+        );
+        Ok(diff)
+    }
+
+    /// Preview creating a vault without sending a transaction
+    pub fn plan_initialize_vault(
+        &self,
+        authority: Pubkey,
+        token_mint: Pubkey,
+        config: &VaultConfig,
+    ) -> Result<InitPlan> {
+        let pda::VaultAddresses { vault, .. } =
+            pda::VaultAddresses::derive(&self.program.id(), &authority, &token_mint, &config.name)?;
+        let mint = self.fetch_mint(token_mint, self.deadline())?;
+        let withdrawal_limit = config
+            .withdrawal_limit
+            .to_base_units(mint.decimals)
+            .context("Invalid withdrawal limit")?;
+        let can_create = freeze::check_freezable_mint(
+            token_mint,
+            &mint,
+            self.strict_mint_policy,
+            config.acknowledge_freezable_mint,
+        )
+        .is_ok();
+
+        Ok(InitPlan {
+            vault: vault.to_string(),
+            name: config.name.clone(),
+            token_mint: token_mint.to_string(),
+            fee_percentage: config.fee_bps,
+            withdrawal_timelock: config.timelock,
+            withdrawal_limit,
+            fee_collector: config.fee_collector.unwrap_or(authority).to_string(),
+            freeze_authority: freeze::freeze_authority(&mint)
+                .map(|authority| authority.to_string()),
+            can_create,
+            cost: self.estimate_init_cost(&authority)?,
+        })
+    }
+
+    /// Preview closing the vault: rent to be reclaimed and the remaining
+    /// balances of its token and reserve accounts. A funded reserve blocks
+    /// the close like tokens left in the vault do, rather than being
+    /// stranded. An immutable vault can never be closed.
+    pub fn plan_close_vault(&self) -> Result<ClosePlan> {
+        let vault = self.current_vault()?;
+        let deadline = self.deadline();
+        Self::ensure_mutable(vault, &self.fetch_vault(vault, deadline)?)?;
+
+        // Derive the vault token account address
+        let (vault_token_account, _) =
+            pda::find_vault_token_account_address(&self.program.id(), &vault);
+        let (reserve_token_account, _) =
+            pda::find_reserve_token_account_address(&self.program.id(), &vault);
+
+        let rpc = self.rpc_within(deadline, DeadlinePhase::Fetch)?;
+        let vault_lamports = rpc.get_account(&vault)?.lamports;
+        let token_account = rpc.get_account(&vault_token_account)?;
+        let token_balance =
+            TokenAccount::try_deserialize(&mut token_account.data.as_slice())?.amount;
+        let reserve_balance = self.reserve_token_balance(vault, deadline)?;
+
+        Ok(ClosePlan {
+            vault: vault.to_string(),
+            vault_token_account: vault_token_account.to_string(),
+            reclaimable_lamports: vault_lamports.saturating_add(token_account.lamports),
+            token_balance,
+            reserve_token_account: reserve_token_account.to_string(),
+            reserve_balance,
+            can_close: token_balance == 0 && reserve_balance == 0,
+        })
+    }
+}
+
+/// Format basis points as a percentage, e.g. 150 -> "1.50%"
+pub fn format_bps(bps: u16) -> String {
+    format!("{}.{:02}%", bps / 100, bps % 100)
+}
+
+/// Format a number of seconds as a compact duration, e.g. 90061 -> "1d 1h 1m 1s"
+pub fn format_duration(seconds: i64) -> String {
+    if seconds <= 0 {
+        return "none".to_string();
+    }
+
+    let units = [("d", 86_400), ("h", 3_600), ("m", 60), ("s", 1)];
+    let mut remaining = seconds;
+    let mut parts = Vec::new();
+    for (suffix, size) in units {
+        if remaining >= size {
+            parts.push(format!("{}{}", remaining / size, suffix));
+            remaining %= size;
+        }
+    }
+    parts.join(" ")
+}
+
+/// Format lamports as SOL with full precision
+pub fn format_sol(lamports: u64) -> String {
+    format!(
+        "{}.{:09} SOL",
+        lamports / 1_000_000_000,
+        lamports % 1_000_000_000
+    )
+}
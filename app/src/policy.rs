@@ -0,0 +1,225 @@
+use anchor_client::solana_sdk::{
+    pubkey::Pubkey,
+    signature::{Keypair, Signature},
+};
+use anyhow::Result;
+use std::fmt;
+use std::process;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use crate::request::VaultRequest;
+use crate::retry;
+use crate::{logging, Deadline, TokenVaultClient};
+
+/// Which mutation an `Operation` is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OperationKind {
+    InitializeVault,
+    Deposit,
+    Withdraw,
+    RequestWithdrawal,
+    CancelWithdrawal,
+    SweepLamports,
+    ReleaseReserve,
+    UpdateVaultConfig,
+    TransferAuthority,
+    SetFeeCollector,
+    PauseVault,
+    UnpauseVault,
+    ClaimFees,
+    AllowlistSync,
+    SetFeatureFlags,
+    SetNotificationConfig,
+    ClearNotificationConfig,
+    RepairFeePath,
+    ScheduleMaintenance,
+    FundFeePayers,
+    CreateLookupTable,
+}
+
+impl fmt::Display for OperationKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self {
+            Self::InitializeVault => "initialize-vault",
+            Self::Deposit => "deposit",
+            Self::Withdraw => "withdraw",
+            Self::RequestWithdrawal => "request-withdrawal",
+            Self::CancelWithdrawal => "cancel-withdrawal",
+            Self::SweepLamports => "sweep-lamports",
+            Self::ReleaseReserve => "release-reserve",
+            Self::UpdateVaultConfig => "update-vault-config",
+            Self::TransferAuthority => "transfer-authority",
+            Self::SetFeeCollector => "set-fee-collector",
+            Self::PauseVault => "pause-vault",
+            Self::UnpauseVault => "unpause-vault",
+            Self::ClaimFees => "claim-fees",
+            Self::AllowlistSync => "allowlist-sync",
+            Self::SetFeatureFlags => "set-feature-flags",
+            Self::SetNotificationConfig => "set-notification-config",
+            Self::ClearNotificationConfig => "clear-notification-config",
+            Self::RepairFeePath => "repair-fee-path",
+            Self::ScheduleMaintenance => "schedule-maintenance",
+            Self::FundFeePayers => "fund-fee-payers",
+            Self::CreateLookupTable => "create-lookup-table",
+        };
+        write!(f, "{}", kind)
+    }
+}
+
+/// A mutation about to be signed, as seen by `OperationPolicy` hooks
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Operation {
+    /// Distinguishes attempts, so hooks can match an outcome to its approval
+    pub id: u64,
+    pub kind: OperationKind,
+    /// The key whose authority the operation exercises
+    pub signer: Pubkey,
+    pub vault: Option<Pubkey>,
+    /// Tokens the operation moves out of the signer's control; 0 if none
+    pub amount: u64,
+}
+
+impl Operation {
+    pub fn new(kind: OperationKind, signer: Pubkey, vault: Option<Pubkey>, amount: u64) -> Self {
+        Self {
+            id: next_operation_id(),
+            kind,
+            signer,
+            vault,
+            amount,
+        }
+    }
+}
+
+/// Unique within the process and, with the pid and start time mixed in,
+/// across processes sharing a policy's state
+fn next_operation_id() -> u64 {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_nanos() as u64)
+        .unwrap_or_default();
+    nanos ^ (u64::from(process::id()) << 40) ^ COUNTER.fetch_add(1, Ordering::Relaxed)
+}
+
+/// Hook consulted by every mutation: before the transaction is signed, and
+/// again once its outcome is known
+pub trait OperationPolicy: Send + Sync {
+    /// Approve `operation`; an error aborts it before anything is signed
+    fn before_sign(&self, operation: &Operation) -> Result<()>;
+
+    /// `operation` was confirmed by the cluster
+    fn after_confirm(&self, _operation: &Operation, _signature: &Signature) {}
+
+    /// `operation` failed after approval; `may_have_landed` is set when the
+    /// transaction was submitted and the cluster has not refused it, so it
+    /// could still be confirmed
+    fn after_failure(&self, _operation: &Operation, _may_have_landed: bool) {}
+}
+
+impl TokenVaultClient {
+    /// Consult `policy` on every mutation from here on, after the policies
+    /// already installed
+    pub fn with_policy(&mut self, policy: Arc<dyn OperationPolicy>) -> &mut Self {
+        self.policies.push(policy);
+        self
+    }
+
+    /// `send_request`, wrapped in the installed policies' hooks
+    pub(crate) fn send_operation(
+        &self,
+        operation: Operation,
+        request: VaultRequest,
+        deadline: Deadline,
+    ) -> Result<Signature> {
+        self.send_operations(&[operation], request, deadline, None)
+    }
+
+    /// `request` carrying all of `operations` in one transaction, paid by
+    /// `fee_payer` if any instead of the client's payer or fee pool. Every
+    /// policy approves every operation before anything is signed, and each
+    /// operation is confirmed or failed, and logged, on its own.
+    pub(crate) fn send_operations(
+        &self,
+        operations: &[Operation],
+        request: VaultRequest,
+        deadline: Deadline,
+        fee_payer: Option<&Keypair>,
+    ) -> Result<Signature> {
+        let span = logging::transaction_span(operations);
+        let _entered = span.enter();
+        let started = Instant::now();
+        for (index, operation) in operations.iter().enumerate() {
+            for (approved, policy) in self.policies.iter().enumerate() {
+                let decision = policy.before_sign(operation);
+                logging::log_policy_decision(approved, &decision);
+                if let Err(err) = decision {
+                    for operation in &operations[..index] {
+                        for policy in &self.policies {
+                            policy.after_failure(operation, false);
+                        }
+                    }
+                    for policy in &self.policies[..approved] {
+                        policy.after_failure(operation, false);
+                    }
+                    for operation in operations {
+                        logging::log_operation(
+                            operation,
+                            Err(&err),
+                            started.elapsed(),
+                            self.log_redaction,
+                        );
+                    }
+                    return Err(err);
+                }
+            }
+        }
+
+        self.submitted.set(None);
+        let request = match fee_payer {
+            Some(fee_payer) => Ok(request.payer(Rc::new(fee_payer.insecure_clone()))),
+            None => self.draw_fee_payer(request, deadline),
+        };
+        let sent = request.and_then(|request| self.send_request(request, deadline));
+        // Whether or not it landed, the vault may have changed
+        for vault in operations.iter().filter_map(|operation| operation.vault) {
+            self.invalidate(&vault);
+        }
+        if let Ok(signature) = &sent {
+            span.record("signature", signature.to_string().as_str());
+        }
+        for operation in operations {
+            logging::log_operation(
+                operation,
+                sent.as_ref(),
+                started.elapsed(),
+                self.log_redaction,
+            );
+        }
+        match sent {
+            Ok(signature) => {
+                for operation in operations {
+                    for policy in &self.policies {
+                        policy.after_confirm(operation, &signature);
+                    }
+                }
+                Ok(signature)
+            }
+            Err(err) => {
+                // Once submitted, a transport error or a timeout leaves the
+                // outcome unknown; only the cluster refusing it settles that
+                let may_have_landed =
+                    self.submitted.get() == Some(true) && !retry::is_rejection(&err);
+                for operation in operations {
+                    for policy in &self.policies {
+                        policy.after_failure(operation, may_have_landed);
+                    }
+                }
+                Err(err)
+            }
+        }
+    }
+}
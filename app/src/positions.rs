@@ -0,0 +1,81 @@
+//! What each depositor has put into and taken out of a vault. The program
+//! keeps a `DepositorPosition` PDA per vault and depositor, created by the
+//! first deposit and updated by every deposit and withdrawal after it, so a
+//! wallet's totals are one read instead of a replay of the vault's history.
+
+use anchor_client::solana_client::{
+    rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
+    rpc_filter::{Memcmp, RpcFilterType},
+};
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use anchor_lang::{AccountDeserialize, Discriminator};
+use anyhow::{Context, Result};
+use solana_account_decoder::UiAccountEncoding;
+
+use crate::pagination::program_accounts_with_slot;
+use crate::token_vault::{pda, state::DepositorPosition};
+use crate::{Deadline, DeadlinePhase, TokenVaultClient};
+
+impl TokenVaultClient {
+    /// The position of `depositor` in the current vault; `None` before their
+    /// first deposit
+    pub fn get_position(&self, depositor: Pubkey) -> Result<Option<DepositorPosition>> {
+        self.fetch_position(self.current_vault()?, depositor, self.deadline())
+    }
+
+    /// Every position in the current vault with its address, by depositor
+    pub fn list_positions(&self) -> Result<Vec<(Pubkey, DepositorPosition)>> {
+        self.scan_positions(self.current_vault()?)
+    }
+
+    pub(crate) fn fetch_position(
+        &self,
+        vault: Pubkey,
+        depositor: Pubkey,
+        deadline: Deadline,
+    ) -> Result<Option<DepositorPosition>> {
+        let (address, _) = pda::find_position_address(&self.program.id(), &vault, &depositor);
+        let account = match self.fetch_optional_account(&address, deadline)? {
+            Some(account) => account,
+            None => return Ok(None),
+        };
+        let position = DepositorPosition::try_deserialize(&mut account.data.as_slice())
+            .with_context(|| format!("Failed to decode position {}", address))?;
+        Ok(Some(position))
+    }
+
+    pub(crate) fn scan_positions(&self, vault: Pubkey) -> Result<Vec<(Pubkey, DepositorPosition)>> {
+        let rpc = self.rpc_within(self.deadline(), DeadlinePhase::Fetch)?;
+        let config = RpcProgramAccountsConfig {
+            filters: Some(vec![
+                RpcFilterType::DataSize(DepositorPosition::LEN as u64),
+                RpcFilterType::Memcmp(Memcmp::new_raw_bytes(
+                    0,
+                    DepositorPosition::DISCRIMINATOR.to_vec(),
+                )),
+                RpcFilterType::Memcmp(Memcmp::new_raw_bytes(
+                    DepositorPosition::VAULT_OFFSET,
+                    vault.to_bytes().to_vec(),
+                )),
+            ]),
+            account_config: RpcAccountInfoConfig {
+                encoding: Some(UiAccountEncoding::Base64),
+                commitment: Some(rpc.commitment()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let (accounts, _) = program_accounts_with_slot(&rpc, &self.program.id(), config)?;
+        let mut positions = accounts
+            .into_iter()
+            .map(|(address, account)| {
+                let position = DepositorPosition::try_deserialize(&mut account.data.as_slice())
+                    .with_context(|| format!("Failed to decode position {}", address))?;
+                Ok((address, position))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        positions.sort_by_key(|(_, position)| position.depositor);
+        Ok(positions)
+    }
+}
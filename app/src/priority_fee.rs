@@ -0,0 +1,142 @@
+//! Compute budget instructions for every transaction the client sends: a
+//! compute unit limit and a priority fee, fixed or sampled from the fees
+//! recently paid to write the same accounts.
+
+use anchor_client::solana_sdk::{
+    compute_budget::ComputeBudgetInstruction, instruction::Instruction, pubkey::Pubkey,
+};
+use anchor_client::RequestBuilder;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::request::VaultRequest;
+use crate::shared::SharedKeypair;
+use crate::{DeadlinePhase, PriorityFees, TokenVaultClient};
+
+/// How the price of a compute unit is chosen
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PriorityFeeStrategy {
+    /// Always this many micro-lamports per compute unit
+    Fixed(u64),
+    /// The 75th percentile of recent priority fees paid for the accounts
+    /// the transaction writes, capped at `max_micro_lamports_per_cu`
+    Auto {
+        max_micro_lamports_per_cu: Option<u64>,
+    },
+}
+
+impl PriorityFeeStrategy {
+    /// Price per compute unit, given recent fees for the written accounts.
+    /// `Fixed` ignores the samples.
+    pub fn price(&self, samples: &[u64]) -> u64 {
+        match *self {
+            PriorityFeeStrategy::Fixed(micro_lamports_per_cu) => micro_lamports_per_cu,
+            PriorityFeeStrategy::Auto {
+                max_micro_lamports_per_cu,
+            } => {
+                let p75 = PriorityFees::of(samples).p75;
+                max_micro_lamports_per_cu.map_or(p75, |max| p75.min(max))
+            }
+        }
+    }
+}
+
+/// Compute budget for `TokenVaultClient::with_priority_fee`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PriorityFeeConfig {
+    pub strategy: PriorityFeeStrategy,
+    /// Compute unit limit; the runtime's default when `None`
+    pub cu_limit: Option<u32>,
+}
+
+impl PriorityFeeConfig {
+    /// A fixed price of `micro_lamports_per_cu`
+    pub fn fixed(micro_lamports_per_cu: u64) -> Self {
+        Self {
+            strategy: PriorityFeeStrategy::Fixed(micro_lamports_per_cu),
+            cu_limit: None,
+        }
+    }
+
+    /// The recent 75th percentile, uncapped
+    pub fn auto() -> Self {
+        Self {
+            strategy: PriorityFeeStrategy::Auto {
+                max_micro_lamports_per_cu: None,
+            },
+            cu_limit: None,
+        }
+    }
+
+    pub fn with_cu_limit(mut self, cu_limit: u32) -> Self {
+        self.cu_limit = Some(cu_limit);
+        self
+    }
+}
+
+/// Instructions setting `cu_limit` and `micro_lamports_per_cu`, in the order
+/// they go at the start of a transaction. A zero price adds no instruction.
+pub fn compute_budget_instructions(
+    cu_limit: Option<u32>,
+    micro_lamports_per_cu: u64,
+) -> Vec<Instruction> {
+    let limit = cu_limit.map(ComputeBudgetInstruction::set_compute_unit_limit);
+    let price = Some(micro_lamports_per_cu)
+        .filter(|price| *price > 0)
+        .map(ComputeBudgetInstruction::set_compute_unit_price);
+    limit.into_iter().chain(price).collect()
+}
+
+impl TokenVaultClient {
+    /// Attach compute budget instructions to every transaction the client
+    /// sends
+    pub fn with_priority_fee(&mut self, config: PriorityFeeConfig) -> &mut Self {
+        self.priority_fee = Some(config);
+        self
+    }
+
+    /// Price per compute unit a transaction writing `writable` would pay;
+    /// zero without a priority fee. `Auto` asks the cluster for recent fees.
+    pub fn priority_fee(&self, writable: &[Pubkey]) -> Result<u64> {
+        let strategy = match self.priority_fee {
+            None => return Ok(0),
+            Some(PriorityFeeConfig { strategy, .. }) => strategy,
+        };
+        let samples: Vec<u64> = match strategy {
+            PriorityFeeStrategy::Fixed(_) => Vec::new(),
+            PriorityFeeStrategy::Auto { .. } => self
+                .rpc_within(self.deadline(), DeadlinePhase::Fetch)?
+                .get_recent_prioritization_fees(writable)?
+                .into_iter()
+                .map(|fee| fee.prioritization_fee)
+                .collect(),
+        };
+        Ok(strategy.price(&samples))
+    }
+
+    /// Start a transaction that writes `writable`: empty, or with the
+    /// compute budget instructions first when a priority fee is set
+    pub fn request(&self, writable: &[Pubkey]) -> Result<RequestBuilder<'_>> {
+        let request = self.program.request();
+        let cu_limit = match self.priority_fee {
+            None => return Ok(request),
+            Some(config) => config.cu_limit,
+        };
+        let price = self.priority_fee(writable)?;
+        Ok(compute_budget_instructions(cu_limit, price)
+            .into_iter()
+            .fold(request, |request, instruction| {
+                request.instruction(instruction)
+            }))
+    }
+
+    /// `request`, for a transaction the client sends itself and signs as
+    /// its `TransactionVersion` asks
+    pub(crate) fn vault_request(&self, writable: &[Pubkey]) -> Result<VaultRequest<'_>> {
+        Ok(VaultRequest::new(
+            self.request(writable)?,
+            SharedKeypair::rc(&self.payer),
+        ))
+    }
+}
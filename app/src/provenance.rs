@@ -0,0 +1,194 @@
+use anchor_client::solana_client::rpc_client::RpcClient;
+use anchor_client::solana_sdk::{
+    commitment_config::{CommitmentConfig, CommitmentLevel},
+    pubkey::Pubkey,
+};
+use anchor_lang::AccountDeserialize;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::token_vault::state::Vault;
+use crate::{
+    logging, CachedKind, Deadline, DeadlinePhase, TokenVaultClient, TokenVaultError, VaultSummary,
+};
+
+/// Where a piece of on-chain state came from: the slot the RPC node answered
+/// at and the commitment level it was asked for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Provenance {
+    pub slot: u64,
+    pub commitment: CommitmentLevel,
+}
+
+impl Provenance {
+    /// The data was read at `required` or a stronger commitment
+    pub fn is_at_least(&self, required: CommitmentLevel) -> bool {
+        rank(self.commitment) >= rank(required)
+    }
+}
+
+impl fmt::Display for Provenance {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "slot {} ({})", self.slot, self.commitment)
+    }
+}
+
+/// Context attached to a pre-check error, naming the state it was based on.
+/// Recover it with `err.downcast_ref::<CheckedAgainst>()`; the typed error
+/// underneath still downcasts as before.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CheckedAgainst(pub Provenance);
+
+impl fmt::Display for CheckedAgainst {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Pre-check failed against vault state from {}", self.0)
+    }
+}
+
+/// Processed < confirmed < finalized
+fn rank(commitment: CommitmentLevel) -> u8 {
+    let config = CommitmentConfig { commitment };
+    if config.is_finalized() {
+        2
+    } else if config.is_at_least_confirmed() {
+        1
+    } else {
+        0
+    }
+}
+
+/// Commitment to read pre-check state at: `read`, raised to `send` for a
+/// strict client whose reads are weaker than its sends
+pub fn precheck_commitment(
+    read: CommitmentConfig,
+    send: CommitmentConfig,
+    strict: bool,
+) -> CommitmentConfig {
+    if strict && rank(read.commitment) < rank(send.commitment) {
+        send
+    } else {
+        read
+    }
+}
+
+impl TokenVaultClient {
+    /// Read account state at `commitment` instead of the commitment
+    /// transactions are confirmed at
+    pub fn with_read_commitment(&mut self, commitment: CommitmentConfig) -> &mut Self {
+        self.read_commitment = Some(commitment);
+        self
+    }
+
+    /// Run `op` with its account reads made at `commitment`, overriding
+    /// `with_read_commitment` for this call only. Strict mode still raises
+    /// the pre-checks of mutations to the send commitment.
+    pub fn at_commitment<T>(
+        &self,
+        commitment: CommitmentConfig,
+        op: impl FnOnce(&Self) -> Result<T>,
+    ) -> Result<T> {
+        let previous = self.call_commitment.replace(Some(commitment));
+        let result = op(self);
+        self.call_commitment.set(previous);
+        result
+    }
+
+    /// `get_vault_info` read at `commitment`, e.g. `finalized` for a last
+    /// check before a large withdrawal
+    pub fn get_vault_info_with_commitment(&self, commitment: CommitmentConfig) -> Result<Vault> {
+        self.at_commitment(commitment, |client| client.get_vault_info())
+    }
+
+    /// Commitment reads through `rpc` are made at: the call's, else the
+    /// client's read commitment, else `rpc`'s own
+    pub(crate) fn read_commitment_for(&self, rpc: &RpcClient) -> CommitmentConfig {
+        self.call_commitment
+            .get()
+            .or(self.read_commitment)
+            .unwrap_or(rpc.commitment())
+    }
+
+    /// In strict mode, mutation pre-checks only trust state read at least at
+    /// the send commitment, re-fetching when reads are configured weaker
+    pub fn with_strict_commitment(&mut self, strict: bool) -> &mut Self {
+        self.strict_commitment = strict;
+        self
+    }
+
+    /// Also fetch optional per-vault accounts, such as the notification
+    /// config, for `get_vault_summary`; off by default to save the requests
+    pub fn with_extensions(&mut self, include: bool) -> &mut Self {
+        self.include_extensions = include;
+        self
+    }
+
+    /// The configured vault, with the slot and commitment it was read at,
+    /// who can freeze its tokens, the live balance of its token account and
+    /// the cluster time its deposit window counts down from, plus its
+    /// optional accounts when `with_extensions` is set
+    pub fn get_vault_summary(&self) -> Result<VaultSummary> {
+        let deadline = self.deadline();
+        let vault = self.current_vault()?;
+        let (vault_data, provenance) = match &self.cache {
+            Some(cache) => {
+                let rpc = self.rpc_within(deadline, DeadlinePhase::Fetch)?;
+                let commitment = self.read_commitment_for(&rpc);
+                let cached = cache.get_account(&rpc, vault, commitment, CachedKind::Vault)?;
+                let vault_data = Vault::try_deserialize(&mut cached.account.data.as_slice())?;
+                (vault_data, cached.provenance())
+            }
+            None => self.fetch_vault_state(vault, deadline, false)?,
+        };
+        let (notifications, maintenance) = if self.include_extensions {
+            (
+                self.fetch_notification_config(vault, deadline)?,
+                self.fetch_maintenance_windows(vault, deadline)?,
+            )
+        } else {
+            (None, Vec::new())
+        };
+        Ok(VaultSummary {
+            freeze: Some(self.freeze_status(vault, &vault_data, deadline)?),
+            balance: Some(self.fetch_vault_balance(vault, &vault_data, deadline)?),
+            notifications,
+            maintenance,
+            provenance: Some(provenance),
+            cluster_time: Some(self.cluster_time_within(deadline)?),
+            ..VaultSummary::of(vault, &vault_data)
+        })
+    }
+
+    /// Read and decode a vault account along with its provenance, bounded by
+    /// `deadline`. For a mutation in strict mode, reads configured weaker than
+    /// the send commitment are made at the send commitment instead. Served
+    /// from the vault cache when it holds a fresh read at that commitment.
+    pub(crate) fn fetch_vault_state(
+        &self,
+        vault: Pubkey,
+        deadline: Deadline,
+        for_mutation: bool,
+    ) -> Result<(Vault, Provenance)> {
+        let rpc = self.rpc_within(deadline, DeadlinePhase::Fetch)?;
+        let commitment = precheck_commitment(
+            self.read_commitment_for(&rpc),
+            rpc.commitment(),
+            for_mutation && self.strict_commitment,
+        );
+        if let Some(cached) = self.vault_cache.get(&vault, commitment) {
+            return Ok(cached);
+        }
+
+        let response = logging::get_account(&rpc, &vault, commitment)?;
+        let account = response
+            .value
+            .ok_or(TokenVaultError::VaultNotFound { vault })?;
+        let vault_data = Vault::try_deserialize(&mut account.data.as_slice())?;
+        let provenance = Provenance {
+            slot: response.context.slot,
+            commitment: commitment.commitment,
+        };
+        self.vault_cache.store(vault, &vault_data, provenance);
+        Ok((vault_data, provenance))
+    }
+}
@@ -0,0 +1,185 @@
+//! A client for indexers and dashboards that only read. It needs no
+//! keypair, and it has no method that signs or sends, so a mutation is a
+//! compile error rather than a refusal at run time:
+//!
+//! ```compile_fail
+//! # use token_vault_client::ReadOnlyVaultClient;
+//! # use anchor_client::{solana_sdk::signature::Keypair, Cluster};
+//! # fn run(client: ReadOnlyVaultClient, depositor: Keypair) {
+//! client.deposit(&depositor, 1_000);
+//! # }
+//! ```
+//!
+//! anchor-client cannot build a program handle without a payer, so the
+//! client holds a throwaway one that nothing can reach.
+
+use anchor_client::solana_sdk::{pubkey::Pubkey, signature::Keypair};
+use anchor_client::Cluster;
+use anyhow::Result;
+
+use crate::token_vault::{
+    pda::{VaultAddresses, VaultNameTooLong},
+    state::{DepositorPosition, Vault},
+};
+use crate::{
+    CacheConfig, ClientConfig, DepositQuote, FreezeStatus, MintAmount, PendingWithdrawal,
+    RpcCacheConfig, StateChange, TokenVaultClient, VaultLifecycle, VaultSnapshot, VaultSummary,
+    WithdrawPreview, WithdrawalQuote,
+};
+
+/// `TokenVaultClient`'s reads, without a payer; see the module docs
+#[derive(Clone)]
+pub struct ReadOnlyVaultClient {
+    client: TokenVaultClient,
+}
+
+impl ReadOnlyVaultClient {
+    pub fn new(cluster: Cluster, program_id: Pubkey) -> Result<Self> {
+        Self::new_with_config(cluster, program_id, ClientConfig::default())
+    }
+
+    /// Create a client with the commitment, timeout and endpoints in
+    /// `config`
+    pub fn new_with_config(
+        cluster: Cluster,
+        program_id: Pubkey,
+        config: ClientConfig,
+    ) -> Result<Self> {
+        Ok(Self {
+            client: TokenVaultClient::new_with_config(cluster, Keypair::new(), program_id, config)?,
+        })
+    }
+
+    /// Set the vault address the single-vault reads use
+    pub fn with_vault(&mut self, vault_address: Pubkey) -> &mut Self {
+        self.client.set_vault(vault_address);
+        self
+    }
+
+    /// Enable caching of account reads
+    pub fn with_rpc_cache(&mut self, config: RpcCacheConfig) -> &mut Self {
+        self.client.with_rpc_cache(config);
+        self
+    }
+
+    /// Reuse decoded vaults between calls
+    pub fn with_vault_cache(&mut self, config: CacheConfig) -> &mut Self {
+        self.client.with_vault_cache(config);
+        self
+    }
+
+    pub fn program_id(&self) -> Pubkey {
+        self.client.program.id()
+    }
+
+    /// Addresses of the vault `authority` would create for `token_mint`
+    /// under `name`
+    pub fn derive_vault_addresses(
+        &self,
+        authority: &Pubkey,
+        token_mint: &Pubkey,
+        name: &str,
+    ) -> Result<VaultAddresses, VaultNameTooLong> {
+        VaultAddresses::derive(&self.program_id(), authority, token_mint, name)
+    }
+
+    pub fn get_vault_info(&self) -> Result<Vault> {
+        self.client.get_vault_info()
+    }
+
+    pub fn get_vault_info_fresh(&self) -> Result<Vault> {
+        self.client.get_vault_info_fresh()
+    }
+
+    pub fn get_vault_summary(&self) -> Result<VaultSummary> {
+        self.client.get_vault_summary()
+    }
+
+    pub fn get_vault_balance(&self) -> Result<MintAmount> {
+        self.client.get_vault_balance()
+    }
+
+    pub fn get_vault_snapshot(&self) -> Result<VaultSnapshot> {
+        self.client.get_vault_snapshot()
+    }
+
+    pub fn get_lifecycle(&self) -> Result<VaultLifecycle> {
+        self.client.get_lifecycle()
+    }
+
+    pub fn get_freeze_status(&self) -> Result<FreezeStatus> {
+        self.client.get_freeze_status()
+    }
+
+    pub fn get_accrued_fees(&self) -> Result<u64> {
+        self.client.get_accrued_fees()
+    }
+
+    pub fn get_position(&self, depositor: Pubkey) -> Result<Option<DepositorPosition>> {
+        self.client.get_position(depositor)
+    }
+
+    pub fn list_positions(&self) -> Result<Vec<(Pubkey, DepositorPosition)>> {
+        self.client.list_positions()
+    }
+
+    pub fn get_pending_withdrawals(&self, withdrawer: Pubkey) -> Result<Vec<PendingWithdrawal>> {
+        self.client.get_pending_withdrawals(withdrawer)
+    }
+
+    pub fn get_changes_since(&self, slot: u64) -> Result<Vec<StateChange>> {
+        self.client.get_changes_since(slot)
+    }
+
+    pub fn get_vaults(&self, addresses: &[Pubkey]) -> Result<Vec<Option<Vault>>> {
+        self.client.get_vaults(addresses)
+    }
+
+    pub fn get_vault_balances(&self, vaults: &[Pubkey]) -> Result<Vec<Option<u64>>> {
+        self.client.get_vault_balances(vaults)
+    }
+
+    pub fn list_vaults(&self, authority: Option<Pubkey>) -> Result<Vec<VaultSummary>> {
+        self.client.list_vaults(authority)
+    }
+
+    pub fn list_vaults_by_authority(&self, authority: Pubkey) -> Result<Vec<(Pubkey, Vault)>> {
+        self.client.list_vaults_by_authority(authority)
+    }
+
+    pub fn list_vaults_by_mint(&self, mint: Pubkey) -> Result<Vec<(Pubkey, Vault)>> {
+        self.client.list_vaults_by_mint(mint)
+    }
+
+    pub fn find_vaults_by_name(&self, pattern: &str) -> Result<Vec<VaultSummary>> {
+        self.client.find_vaults_by_name(pattern)
+    }
+
+    pub fn quote_deposit(&self, amount: u64) -> Result<DepositQuote> {
+        self.client.quote_deposit(amount)
+    }
+
+    pub fn quote_withdrawal(&self, amount: u64) -> Result<WithdrawalQuote> {
+        self.client.quote_withdrawal(amount)
+    }
+
+    pub fn preview_withdraw(&self, amount: u64) -> Result<WithdrawPreview> {
+        self.client.preview_withdraw(amount)
+    }
+
+    #[cfg(feature = "subscriptions")]
+    pub fn subscribe_vault_events(
+        &self,
+        vault: Pubkey,
+    ) -> Result<crate::subscription::Subscription<crate::VaultEvent>> {
+        self.client.subscribe_vault_events(vault)
+    }
+
+    #[cfg(feature = "subscriptions")]
+    pub fn subscribe_vault_state(
+        &self,
+        vault: Pubkey,
+    ) -> Result<crate::subscription::Subscription<Vault>> {
+        self.client.subscribe_vault_state(vault)
+    }
+}
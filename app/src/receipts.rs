@@ -0,0 +1,239 @@
+//! Signed receipts for the authority's administrative actions, kept as
+//! evidence that can be checked long after the fact without an RPC node.
+
+use anchor_client::solana_sdk::{pubkey::Pubkey, signature::Signature};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::plan::{format_bps, format_duration};
+use crate::signer::VaultSigner;
+use crate::{MaintenanceWindow, NotificationConfig, TokenVaultClient, VaultChange};
+
+/// Domain separator prepended to every receipt message, so a receipt
+/// signature can never pass as a transaction or an attestation
+pub const RECEIPT_DOMAIN: &[u8] = b"token-vault:action-receipt:v1";
+
+/// The administrative state an action changed, as it was before or after it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum AdminState {
+    /// The vault's notification config; `None` when it has none
+    Notifications { config: Option<NotificationConfig> },
+    /// Tokens in the vault's insurance reserve
+    ReserveBalance { amount: u64 },
+    /// The vault's maintenance schedule, in start order
+    Maintenance { windows: Vec<MaintenanceWindow> },
+    /// The settings `update_vault_config` changes
+    Config {
+        fee_percentage: u16,
+        withdrawal_timelock: i64,
+        withdrawal_limit: u64,
+    },
+    /// Whether deposits and withdrawals are stopped
+    Paused { paused: bool },
+}
+
+impl fmt::Display for AdminState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Notifications {
+                config: Some(config),
+            } => write!(f, "notifications {}", config),
+            Self::Notifications { config: None } => write!(f, "no notifications"),
+            Self::ReserveBalance { amount } => write!(f, "reserve balance {}", amount),
+            Self::Maintenance { windows } if windows.is_empty() => write!(f, "no maintenance"),
+            Self::Maintenance { windows } => {
+                write!(f, "maintenance")?;
+                for (i, window) in windows.iter().enumerate() {
+                    write!(f, "{} {}", if i == 0 { "" } else { ";" }, window)?;
+                }
+                Ok(())
+            }
+            Self::Config {
+                fee_percentage,
+                withdrawal_timelock,
+                withdrawal_limit,
+            } => write!(
+                f,
+                "fee {}, withdrawal timelock {}, withdrawal limit {}",
+                format_bps(*fee_percentage),
+                format_duration(*withdrawal_timelock),
+                withdrawal_limit
+            ),
+            Self::Paused { paused: true } => write!(f, "paused"),
+            Self::Paused { paused: false } => write!(f, "not paused"),
+        }
+    }
+}
+
+/// Evidence that the vault authority performed `action`, signed by the
+/// authority key over everything else in the receipt
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ActionReceipt {
+    #[serde(with = "crate::serde_utils::pubkey")]
+    pub program_id: Pubkey,
+    #[serde(with = "crate::serde_utils::pubkey")]
+    pub vault: Pubkey,
+    #[serde(with = "crate::serde_utils::pubkey")]
+    pub authority: Pubkey,
+    pub action: VaultChange,
+    /// Slot the transaction landed in
+    pub slot: u64,
+    #[serde(with = "crate::serde_utils::signature")]
+    pub transaction: Signature,
+    pub before: AdminState,
+    pub after: AdminState,
+    /// The authority's signature over `message()`
+    #[serde(with = "crate::serde_utils::signature")]
+    pub authority_signature: Signature,
+}
+
+/// The receipt fields that are signed, in their canonical order
+#[derive(Serialize)]
+struct SignedFields<'a> {
+    #[serde(with = "crate::serde_utils::pubkey")]
+    program_id: &'a Pubkey,
+    #[serde(with = "crate::serde_utils::pubkey")]
+    vault: &'a Pubkey,
+    #[serde(with = "crate::serde_utils::pubkey")]
+    authority: &'a Pubkey,
+    action: &'a VaultChange,
+    slot: u64,
+    #[serde(with = "crate::serde_utils::signature")]
+    transaction: &'a Signature,
+    before: &'a AdminState,
+    after: &'a AdminState,
+}
+
+impl ActionReceipt {
+    /// Sign a receipt for `action` with `authority`
+    #[allow(clippy::too_many_arguments)]
+    pub fn sign(
+        authority: &dyn VaultSigner,
+        program_id: Pubkey,
+        vault: Pubkey,
+        action: VaultChange,
+        slot: u64,
+        transaction: Signature,
+        before: AdminState,
+        after: AdminState,
+    ) -> Result<Self> {
+        let mut receipt = Self {
+            program_id,
+            vault,
+            authority: authority.pubkey(),
+            action,
+            slot,
+            transaction,
+            before,
+            after,
+            authority_signature: Signature::default(),
+        };
+        receipt.authority_signature = authority.sign_message(&receipt.message()?)?;
+        Ok(receipt)
+    }
+
+    /// The exact bytes that are signed: the domain separator followed by
+    /// the compact JSON of every field but the authority signature
+    pub fn message(&self) -> Result<Vec<u8>> {
+        let fields = SignedFields {
+            program_id: &self.program_id,
+            vault: &self.vault,
+            authority: &self.authority,
+            action: &self.action,
+            slot: self.slot,
+            transaction: &self.transaction,
+            before: &self.before,
+            after: &self.after,
+        };
+        let mut message = RECEIPT_DOMAIN.to_vec();
+        serde_json::to_writer(&mut message, &fields)?;
+        Ok(message)
+    }
+
+    /// Encode as a single line of JSON
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    /// Decode from JSON
+    pub fn from_json(json: &str) -> Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+}
+
+impl fmt::Display for ActionReceipt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} on vault {} by {} at slot {} ({}): {} -> {}",
+            self.action,
+            self.vault,
+            self.authority,
+            self.slot,
+            self.transaction,
+            self.before,
+            self.after
+        )
+    }
+}
+
+/// Check, without any network access, that `receipt` was signed by
+/// `expected_authority` and has not been altered since. The key need not
+/// be the vault's current authority: receipts of a former authority verify
+/// against that former key.
+pub fn verify_action_receipt(receipt: &ActionReceipt, expected_authority: &Pubkey) -> Result<()> {
+    if receipt.authority != *expected_authority {
+        return Err(anyhow!(
+            "Receipt was issued by {}, not {}",
+            receipt.authority,
+            expected_authority
+        ));
+    }
+    if !receipt
+        .authority_signature
+        .verify(expected_authority.as_ref(), &receipt.message()?)
+    {
+        return Err(anyhow!(
+            "Receipt signature does not match its contents; it was altered or not signed by {}",
+            expected_authority
+        ));
+    }
+    Ok(())
+}
+
+impl TokenVaultClient {
+    /// Receipt for `action`, which `transaction` just applied to `vault`
+    pub(crate) fn action_receipt(
+        &self,
+        authority: &dyn VaultSigner,
+        vault: Pubkey,
+        action: VaultChange,
+        transaction: Signature,
+        before: AdminState,
+        after: AdminState,
+    ) -> Result<ActionReceipt> {
+        // The action already landed, so this lookup is not held to its deadline
+        let slot = self
+            .program
+            .rpc()
+            .get_signature_statuses(&[transaction])?
+            .value
+            .into_iter()
+            .flatten()
+            .next()
+            .map(|status| status.slot)
+            .ok_or_else(|| anyhow!("No status for confirmed transaction {}", transaction))?;
+        ActionReceipt::sign(
+            authority,
+            self.program.id(),
+            vault,
+            action,
+            slot,
+            transaction,
+            before,
+            after,
+        )
+    }
+}
@@ -0,0 +1,80 @@
+//! The transaction a mutation sends, as it is being built. Anchor's
+//! `RequestBuilder` only signs legacy transactions and keeps its signers to
+//! itself; `VaultRequest` wraps it and remembers the payer and signers, so
+//! the client can sign the same instructions as a v0 transaction too.
+
+use anchor_client::solana_sdk::{
+    instruction::Instruction, pubkey::Pubkey, signer::Signer, transaction::Transaction,
+};
+use anchor_client::RequestBuilder;
+use anchor_lang::{InstructionData, ToAccountMetas};
+use anyhow::Result;
+use std::rc::Rc;
+
+use crate::assembly::dedup_signers;
+
+/// `RequestBuilder`, with the keys that will sign it on record
+pub(crate) struct VaultRequest<'a> {
+    builder: RequestBuilder<'a>,
+    payer: Rc<dyn Signer>,
+    signers: Vec<&'a dyn Signer>,
+}
+
+impl<'a> VaultRequest<'a> {
+    /// `builder`, which `payer` pays for
+    pub(crate) fn new(builder: RequestBuilder<'a>, payer: Rc<dyn Signer>) -> Self {
+        Self {
+            builder,
+            payer,
+            signers: Vec::new(),
+        }
+    }
+
+    pub(crate) fn instruction(mut self, instruction: Instruction) -> Self {
+        self.builder = self.builder.instruction(instruction);
+        self
+    }
+
+    pub(crate) fn accounts(mut self, accounts: impl ToAccountMetas) -> Self {
+        self.builder = self.builder.accounts(accounts);
+        self
+    }
+
+    pub(crate) fn args(mut self, args: impl InstructionData) -> Self {
+        self.builder = self.builder.args(args);
+        self
+    }
+
+    pub(crate) fn signer(mut self, signer: &'a dyn Signer) -> Self {
+        self.builder = self.builder.signer(signer);
+        self.signers.push(signer);
+        self
+    }
+
+    pub(crate) fn payer(mut self, payer: Rc<dyn Signer>) -> Self {
+        self.builder = self.builder.payer(payer.clone());
+        self.payer = payer;
+        self
+    }
+
+    /// Every instruction, in the order they execute
+    pub(crate) fn instructions(&self) -> Result<Vec<Instruction>> {
+        Ok(self.builder.instructions()?)
+    }
+
+    pub(crate) fn payer_key(&self) -> Pubkey {
+        self.payer.pubkey()
+    }
+
+    /// The payer and then the signers, each key once
+    pub(crate) fn signing_keys(&self) -> Vec<&dyn Signer> {
+        let mut all = vec![self.payer.as_ref()];
+        all.extend(self.signers.iter().copied());
+        dedup_signers(&all)
+    }
+
+    /// The request as anchor signs it, with a fresh blockhash
+    pub(crate) fn legacy_transaction(&self) -> Result<Transaction> {
+        Ok(self.builder.signed_transaction()?)
+    }
+}
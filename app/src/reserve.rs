@@ -0,0 +1,248 @@
+use anchor_client::solana_sdk::{program_pack::Pack, pubkey::Pubkey};
+use anchor_spl::token::{self, spl_token};
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::dust::{ensure_nonzero, BPS_DENOMINATOR};
+use crate::plan::format_duration;
+use crate::signer::{AsSigner, VaultSigner};
+use crate::token_vault::{self, pda, state::Vault};
+use crate::{
+    ActionReceipt, AdminState, CheckedAgainst, Deadline, DeadlinePhase, MintAmount, Operation,
+    OperationKind, TokenVaultClient, TokenVaultError, VaultChange,
+};
+
+/// Vault-level insurance reserve: the share of each deposit set aside, and
+/// how long the authority must wait between releases
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ReservePolicy {
+    /// Basis points of every deposit diverted into the reserve
+    pub bps: u16,
+    /// Seconds between reserve releases
+    pub timelock: i64,
+}
+
+impl ReservePolicy {
+    pub const NONE: Self = Self {
+        bps: 0,
+        timelock: 0,
+    };
+
+    /// Validate a policy: at most 100% of a deposit, and a non-negative timelock
+    pub fn new(bps: u16, timelock: i64) -> Result<Self> {
+        if bps as u64 > BPS_DENOMINATOR {
+            return Err(anyhow!(
+                "Reserve share of {} bps exceeds {} bps",
+                bps,
+                BPS_DENOMINATOR
+            ));
+        }
+        if timelock < 0 {
+            return Err(anyhow!("Reserve timelock cannot be negative"));
+        }
+        Ok(Self { bps, timelock })
+    }
+
+    /// The policy stored on a vault
+    pub fn of(vault: &Vault) -> Self {
+        Self {
+            bps: vault.reserve_bps,
+            timelock: vault.reserve_timelock,
+        }
+    }
+
+    /// Part of a deposit of `amount` that goes to the reserve
+    pub fn carve_out(&self, amount: u64) -> u64 {
+        reserve_carve_out(amount, self.bps)
+    }
+}
+
+impl fmt::Display for ReservePolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.bps == 0 {
+            return write!(f, "none");
+        }
+        write!(
+            f,
+            "{} bps of deposits, released at most every {}",
+            self.bps,
+            format_duration(self.timelock)
+        )
+    }
+}
+
+/// What a deposit would credit to the vault, computed the way the program does
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DepositQuote {
+    pub amount: u64,
+    /// Carved out into the insurance reserve
+    pub reserve: u64,
+    /// Added to the vault's `total_deposited`
+    pub credited: u64,
+}
+
+impl DepositQuote {
+    /// Quote depositing `amount` into `vault`, refusing zero amounts
+    pub fn for_vault(vault: &Vault, amount: u64) -> Result<Self, TokenVaultError> {
+        ensure_nonzero("deposit", amount)?;
+        let reserve = ReservePolicy::of(vault).carve_out(amount);
+        Ok(Self {
+            amount,
+            reserve,
+            credited: amount - reserve,
+        })
+    }
+}
+
+impl fmt::Display for DepositQuote {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "deposit {}: reserve {}, credit {}",
+            self.amount, self.reserve, self.credited
+        )
+    }
+}
+
+/// Reserve share of a deposit, rounded down like the program does
+pub fn reserve_carve_out(amount: u64, reserve_bps: u16) -> u64 {
+    (amount as u128 * reserve_bps as u128 / BPS_DENOMINATOR as u128) as u64
+}
+
+/// Unix timestamp from which the vault's reserve may be released again
+pub fn reserve_unlocks_at(vault: &Vault) -> i64 {
+    vault
+        .last_reserve_release
+        .saturating_add(vault.reserve_timelock)
+}
+
+/// Refuse a release the program would reject: zero, more than the reserve
+/// holds, or before the reserve timelock has passed at cluster time `now`
+pub fn check_release(vault: &Vault, amount: u64, now: i64) -> Result<(), TokenVaultError> {
+    ensure_nonzero("release", amount)?;
+    let unlocks_at = reserve_unlocks_at(vault);
+    if now < unlocks_at {
+        return Err(TokenVaultError::ReserveLocked { unlocks_at });
+    }
+    if amount > vault.total_reserved {
+        return Err(TokenVaultError::ReserveInsufficient {
+            requested: amount,
+            available: vault.total_reserved,
+        });
+    }
+    Ok(())
+}
+
+impl TokenVaultClient {
+    /// Preview a deposit into the current vault: the reserve carve-out and the
+    /// amount credited
+    pub fn quote_deposit(&self, amount: u64) -> Result<DepositQuote> {
+        let vault = self.current_vault()?;
+        let (vault_data, provenance) = self.fetch_vault_state(vault, self.deadline(), false)?;
+        DepositQuote::for_vault(&vault_data, amount).context(CheckedAgainst(provenance))
+    }
+
+    /// Tokens held in the current vault's insurance reserve
+    pub fn get_reserve_balance(&self) -> Result<MintAmount> {
+        let vault = self.current_vault()?;
+        let deadline = self.deadline();
+        let (vault_data, _) = self.fetch_vault_state(vault, deadline, false)?;
+        let mint = self.fetch_mint(vault_data.token_mint, deadline)?;
+        Ok(MintAmount::new(
+            self.reserve_token_balance(vault, deadline)?,
+            vault_data.token_mint,
+            mint.decimals,
+        ))
+    }
+
+    pub(crate) fn reserve_token_balance(&self, vault: Pubkey, deadline: Deadline) -> Result<u64> {
+        let (reserve_token_account, _) =
+            pda::find_reserve_token_account_address(&self.program.id(), &vault);
+        let rpc = self.rpc_within(deadline, DeadlinePhase::Fetch)?;
+        let account = rpc.get_account(&reserve_token_account)?;
+        Ok(spl_token::state::Account::unpack(&account.data)?.amount)
+    }
+
+    /// Release `amount` from the current vault's insurance reserve to the
+    /// associated token account of `destination`, created first if needed.
+    /// Only the authority may release, and only once the reserve timelock
+    /// since the previous release has passed. Returns the authority's signed
+    /// receipt for the release.
+    pub fn release_reserve(
+        &self,
+        authority: &dyn VaultSigner,
+        amount: u64,
+        destination: Pubkey,
+    ) -> Result<ActionReceipt> {
+        self.check_signer(&authority.pubkey())?;
+        let deadline = self.deadline();
+        self.check_features(deadline)?;
+        let vault = self.current_vault()?;
+
+        let (vault_data, provenance) = self.fetch_vault_state(vault, deadline, true)?;
+        if vault_data.authority != authority.pubkey() {
+            return Err(anyhow!(
+                "{} is not the authority of vault {}",
+                authority.pubkey(),
+                vault
+            ))
+            .context(CheckedAgainst(provenance));
+        }
+        check_release(&vault_data, amount, self.cluster_time_within(deadline)?)
+            .context(CheckedAgainst(provenance))?;
+
+        let (reserve_token_account, _) =
+            pda::find_reserve_token_account_address(&self.program.id(), &vault);
+        let reserve_before = self.reserve_token_balance(vault, deadline)?;
+        let destination_token_account = anchor_spl::associated_token::get_associated_token_address(
+            &destination,
+            &vault_data.token_mint,
+        );
+
+        let signer = AsSigner(authority);
+        let request = self
+            .vault_request(&[vault])?
+            .instruction(
+                spl_associated_token_account::instruction::create_associated_token_account_idempotent(
+                    &self.program.payer(),
+                    &destination,
+                    &vault_data.token_mint,
+                    &token::ID,
+                ),
+            )
+            .accounts(token_vault::accounts::ReleaseReserve {
+                authority: authority.pubkey(),
+                vault,
+                reserve_token_account,
+                destination_token_account,
+                token_program: token::ID,
+            })
+            .args(token_vault::instruction::ReleaseReserve { amount })
+            .signer(&signer);
+        let operation = Operation::new(
+            OperationKind::ReleaseReserve,
+            authority.pubkey(),
+            Some(vault),
+            amount,
+        );
+        let signature = self.send_operation(operation, request, deadline)?;
+
+        self.invalidate(&vault);
+        self.invalidate(&reserve_token_account);
+        self.invalidate(&destination_token_account);
+
+        self.action_receipt(
+            authority,
+            vault,
+            VaultChange::ReserveReleased { amount },
+            signature,
+            AdminState::ReserveBalance {
+                amount: reserve_before,
+            },
+            AdminState::ReserveBalance {
+                amount: reserve_before.saturating_sub(amount),
+            },
+        )
+    }
+}
@@ -0,0 +1,183 @@
+//! Resubmitting transactions that failed for reasons unrelated to what they
+//! do: an expired blockhash, or an RPC node that did not answer. Program
+//! errors are never retried. Before every resubmission the previous
+//! attempt's signature is looked up, so a transaction that landed after all
+//! is never sent a second time.
+
+use anchor_client::solana_client::{
+    client_error::{ClientError, ClientErrorKind},
+    rpc_request::{RpcError, RpcResponseErrorData},
+};
+use anchor_client::solana_sdk::{
+    commitment_config::CommitmentConfig, signature::Signature, transaction::TransactionError,
+};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::logging;
+use crate::request::VaultRequest;
+use crate::{Deadline, DeadlinePhase, TokenVaultClient};
+
+/// Failures a `RetryPolicy` can resubmit after
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RetryCondition {
+    /// The transaction's blockhash expired before it landed
+    BlockhashExpired,
+    /// The RPC node could not be reached, or reported itself unhealthy
+    TransientRpc,
+}
+
+impl RetryCondition {
+    /// The condition `err` falls under, if any. Errors raised by the
+    /// program, and anything else the client does not recognize, are `None`.
+    pub fn of(err: &anyhow::Error) -> Option<Self> {
+        let err = client_error(err)?;
+        if let Some(err) = err.get_transaction_error() {
+            return (err == TransactionError::BlockhashNotFound)
+                .then_some(RetryCondition::BlockhashExpired);
+        }
+        match err.kind() {
+            // What send-and-confirm reports once the blockhash has expired
+            ClientErrorKind::RpcError(RpcError::ForUser(message))
+                if message.starts_with("unable to confirm transaction") =>
+            {
+                Some(RetryCondition::BlockhashExpired)
+            }
+            ClientErrorKind::Io(_) | ClientErrorKind::Reqwest(_) => {
+                Some(RetryCondition::TransientRpc)
+            }
+            ClientErrorKind::RpcError(RpcError::RpcResponseError {
+                data: RpcResponseErrorData::NodeUnhealthy { .. },
+                ..
+            }) => Some(RetryCondition::TransientRpc),
+            _ => None,
+        }
+    }
+}
+
+/// `err` is the cluster refusing a transaction, in preflight or once
+/// processed, so it did not take effect and never will. A transaction
+/// reported as already processed landed after all.
+pub fn is_rejection(err: &anyhow::Error) -> bool {
+    let rejection = match err.downcast_ref::<TransactionError>() {
+        Some(err) => Some(err.clone()),
+        None => client_error(err).and_then(ClientError::get_transaction_error),
+    };
+    matches!(rejection, Some(err) if err != TransactionError::AlreadyProcessed)
+}
+
+fn client_error(err: &anyhow::Error) -> Option<&ClientError> {
+    match err.downcast_ref::<anchor_client::ClientError>() {
+        Some(anchor_client::ClientError::SolanaClientError(err)) => Some(err),
+        _ => err.downcast_ref::<ClientError>(),
+    }
+}
+
+/// When the client resubmits a failed transaction
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetryPolicy {
+    /// Resubmissions after the first attempt
+    pub max_retries: u32,
+    /// Pause before the first resubmission, doubled before every further one
+    pub backoff: Duration,
+    pub max_backoff: Duration,
+    pub retry_on: Vec<RetryCondition>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(8),
+            retry_on: vec![
+                RetryCondition::BlockhashExpired,
+                RetryCondition::TransientRpc,
+            ],
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Whether the `attempt`th submission, having failed with `err`, is
+    /// followed by another
+    pub fn retries(&self, err: &anyhow::Error, attempt: u32) -> bool {
+        let covered =
+            RetryCondition::of(err).map_or(false, |condition| self.retry_on.contains(&condition));
+        covered && attempt <= self.max_retries
+    }
+
+    /// Pause after the `attempt`th submission failed
+    pub fn backoff_after(&self, attempt: u32) -> Duration {
+        let doublings = attempt.saturating_sub(1).min(31);
+        self.backoff
+            .saturating_mul(1 << doublings)
+            .min(self.max_backoff)
+    }
+}
+
+impl TokenVaultClient {
+    /// Resubmit transactions that fail as `policy` allows; by default a
+    /// failed submission is final
+    pub fn with_retry_policy(&mut self, policy: RetryPolicy) -> &mut Self {
+        self.retry_policy = Some(policy);
+        self
+    }
+
+    /// Sign and submit `request`, resubmitting under the retry policy. The
+    /// same transaction is resent while its blockhash is valid, and is only
+    /// rebuilt with a fresh one after that blockhash expired without it
+    /// landing, so at most one of the attempts can ever execute.
+    pub(crate) fn send_with_retries(
+        &self,
+        request: &VaultRequest,
+        deadline: Deadline,
+    ) -> Result<Signature> {
+        deadline.check(DeadlinePhase::Send, None)?;
+        let mut transaction = self.sign_request(request, deadline)?;
+        let mut attempts = 1;
+        loop {
+            let err = match self.submit(&transaction, deadline) {
+                Ok(signature) => {
+                    self.send_attempts.set(Some(attempts));
+                    return Ok(signature);
+                }
+                Err(err) => err,
+            };
+            match &self.retry_policy {
+                Some(policy) if policy.retries(&err, attempts) => {
+                    deadline.sleep(policy.backoff_after(attempts), DeadlinePhase::Send)?
+                }
+                _ => return Err(err),
+            }
+
+            let signature = transaction.signatures[0];
+            let rpc = self.rpc_within(deadline, DeadlinePhase::Send)?;
+            match rpc
+                .get_signature_status_with_commitment(&signature, CommitmentConfig::processed())?
+            {
+                // The failed attempt landed after all; only its confirmation is missing
+                Some(Ok(())) => {
+                    rpc.poll_for_signature_with_commitment(&signature, rpc.commitment())?;
+                    self.note_write();
+                    self.send_attempts.set(Some(attempts));
+                    return Ok(signature);
+                }
+                Some(Err(err)) => return Err(err.into()),
+                None => {}
+            }
+            if !rpc.is_blockhash_valid(&transaction.message.recent_blockhash(), rpc.commitment())? {
+                transaction = self.sign_request(request, deadline)?;
+            }
+            attempts += 1;
+            logging::warn!(
+                attempt = attempts,
+                previous = %signature,
+                error = %err,
+                "resubmitting transaction"
+            );
+        }
+    }
+}
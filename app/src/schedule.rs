@@ -0,0 +1,161 @@
+use anchor_client::solana_sdk::{account::from_account, clock::Clock, sysvar};
+use anyhow::{anyhow, Result};
+use chrono::DateTime;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::token_vault::state::Vault;
+use crate::{Deadline, DeadlinePhase, TokenVaultClient, TokenVaultError};
+
+/// Period in which a vault accepts deposits; a bound of 0 means "no bound"
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DepositWindow {
+    pub start: i64,
+    pub end: i64,
+}
+
+/// Where the current time falls relative to a deposit window
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DepositWindowStatus {
+    Unrestricted,
+    NotYetOpen { opens_in: i64 },
+    Open { closes_in: Option<i64> },
+    Closed { closed_at: i64 },
+}
+
+impl DepositWindow {
+    pub const UNRESTRICTED: Self = Self { start: 0, end: 0 };
+
+    /// Create a window, rejecting one that closes before it opens
+    pub fn new(start: i64, end: i64) -> Result<Self> {
+        if start < 0 || end < 0 {
+            return Err(anyhow!("Deposit window bounds cannot be negative"));
+        }
+        if start != 0 && end != 0 && end <= start {
+            return Err(anyhow!(
+                "Deposit window closes ({}) before it opens ({})",
+                end,
+                start
+            ));
+        }
+        Ok(Self { start, end })
+    }
+
+    /// The window stored on a vault
+    pub fn of(vault: &Vault) -> Self {
+        Self {
+            start: vault.deposit_window_start,
+            end: vault.deposit_window_end,
+        }
+    }
+
+    pub fn is_unrestricted(&self) -> bool {
+        self.start == 0 && self.end == 0
+    }
+
+    pub fn status(&self, now: i64) -> DepositWindowStatus {
+        if self.is_unrestricted() {
+            DepositWindowStatus::Unrestricted
+        } else if self.start != 0 && now < self.start {
+            DepositWindowStatus::NotYetOpen {
+                opens_in: self.start - now,
+            }
+        } else if self.end != 0 && now >= self.end {
+            DepositWindowStatus::Closed {
+                closed_at: self.end,
+            }
+        } else {
+            DepositWindowStatus::Open {
+                closes_in: (self.end != 0).then(|| self.end - now),
+            }
+        }
+    }
+
+    /// Fail with `DepositWindowClosed` unless deposits are accepted at `now`
+    pub fn check(&self, now: i64) -> Result<(), TokenVaultError> {
+        match self.status(now) {
+            DepositWindowStatus::Unrestricted | DepositWindowStatus::Open { .. } => Ok(()),
+            _ => Err(TokenVaultError::DepositWindowClosed {
+                opens_at: self.start,
+                closes_at: self.end,
+            }),
+        }
+    }
+
+    /// Human-readable state of the window with a countdown, e.g. "open, closes in 2h 5m"
+    pub fn describe(&self, now: i64) -> String {
+        match self.status(now) {
+            DepositWindowStatus::Unrestricted => "unrestricted".to_string(),
+            DepositWindowStatus::NotYetOpen { opens_in } => {
+                format!(
+                    "not yet open, opens in {}",
+                    crate::plan::format_duration(opens_in)
+                )
+            }
+            DepositWindowStatus::Open { closes_in: None } => "open".to_string(),
+            DepositWindowStatus::Open {
+                closes_in: Some(closes_in),
+            } => format!(
+                "open, closes in {}",
+                crate::plan::format_duration(closes_in)
+            ),
+            DepositWindowStatus::Closed { closed_at } => {
+                format!("closed since {}", format_timestamp(closed_at))
+            }
+        }
+    }
+}
+
+impl fmt::Display for DepositWindow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let bound = |ts: i64| {
+            if ts == 0 {
+                "-".to_string()
+            } else {
+                format_timestamp(ts)
+            }
+        };
+        if self.is_unrestricted() {
+            write!(f, "unrestricted")
+        } else {
+            write!(f, "{} .. {}", bound(self.start), bound(self.end))
+        }
+    }
+}
+
+impl TokenVaultClient {
+    /// Current unix timestamp according to the cluster's Clock sysvar, or the
+    /// injected clock source
+    pub fn cluster_time(&self) -> Result<i64> {
+        self.cluster_time_within(self.deadline())
+    }
+
+    /// Read the time from `clock` instead of the cluster, e.g. to test
+    /// deposit windows without waiting for them
+    #[cfg(feature = "test-hooks")]
+    pub fn with_clock_source(
+        &mut self,
+        clock: std::sync::Arc<dyn crate::ClockSource>,
+    ) -> &mut Self {
+        self.clock = Some(clock);
+        self
+    }
+
+    pub(crate) fn cluster_time_within(&self, deadline: Deadline) -> Result<i64> {
+        if let Some(clock) = &self.clock {
+            return clock.unix_timestamp();
+        }
+        let rpc = self.rpc_within(deadline, DeadlinePhase::Fetch)?;
+        let account = rpc.get_account(&sysvar::clock::ID)?;
+        let clock: Clock =
+            from_account(&account).ok_or_else(|| anyhow!("Failed to decode Clock sysvar"))?;
+        Ok(clock.unix_timestamp)
+    }
+}
+
+/// Format a unix timestamp as an RFC 3339 UTC string
+pub fn format_timestamp(ts: i64) -> String {
+    DateTime::from_timestamp(ts, 0)
+        .map(|dt| dt.to_rfc3339())
+        .unwrap_or_else(|| ts.to_string())
+}
@@ -0,0 +1,156 @@
+//! Serde adapters that encode keys, signatures and hashes as base58 strings,
+//! and unix timestamps as ISO 8601 UTC
+
+/// Serialize a `Pubkey` as its base58 string
+pub mod pubkey {
+    use anchor_client::solana_sdk::pubkey::Pubkey;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+    use std::str::FromStr;
+
+    pub fn serialize<S: Serializer>(pubkey: &Pubkey, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(pubkey)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Pubkey, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        Pubkey::from_str(&s).map_err(D::Error::custom)
+    }
+}
+
+/// Serialize an `Option<Pubkey>` as a base58 string or null
+pub mod option_pubkey {
+    use anchor_client::solana_sdk::pubkey::Pubkey;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+    use std::str::FromStr;
+
+    pub fn serialize<S: Serializer>(
+        pubkey: &Option<Pubkey>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match pubkey {
+            Some(pubkey) => serializer.collect_str(pubkey),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Pubkey>, D::Error> {
+        Option::<String>::deserialize(deserializer)?
+            .map(|s| Pubkey::from_str(&s).map_err(D::Error::custom))
+            .transpose()
+    }
+}
+
+/// Serialize a `Signature` as its base58 string
+pub mod signature {
+    use anchor_client::solana_sdk::signature::Signature;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+    use std::str::FromStr;
+
+    pub fn serialize<S: Serializer>(
+        signature: &Signature,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(signature)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Signature, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        Signature::from_str(&s).map_err(D::Error::custom)
+    }
+}
+
+/// Serialize an `Option<Signature>` as a base58 string or null
+pub mod option_signature {
+    use anchor_client::solana_sdk::signature::Signature;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+    use std::str::FromStr;
+
+    pub fn serialize<S: Serializer>(
+        signature: &Option<Signature>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match signature {
+            Some(signature) => serializer.collect_str(signature),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Signature>, D::Error> {
+        Option::<String>::deserialize(deserializer)?
+            .map(|s| Signature::from_str(&s).map_err(D::Error::custom))
+            .transpose()
+    }
+}
+
+/// Serialize a set of `Pubkey`s as a list of base58 strings
+pub mod pubkey_set {
+    use anchor_client::solana_sdk::pubkey::Pubkey;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+    use std::collections::BTreeSet;
+    use std::str::FromStr;
+
+    pub fn serialize<S: Serializer>(
+        keys: &BTreeSet<Pubkey>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(keys.iter().map(|key| key.to_string()))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<BTreeSet<Pubkey>, D::Error> {
+        Vec::<String>::deserialize(deserializer)?
+            .iter()
+            .map(|s| Pubkey::from_str(s).map_err(D::Error::custom))
+            .collect()
+    }
+}
+
+/// Serialize an `Option<Hash>` as a base58 string or null
+pub mod option_hash {
+    use anchor_client::solana_sdk::hash::Hash;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+    use std::str::FromStr;
+
+    pub fn serialize<S: Serializer>(hash: &Option<Hash>, serializer: S) -> Result<S::Ok, S::Error> {
+        match hash {
+            Some(hash) => serializer.collect_str(hash),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Hash>, D::Error> {
+        Option::<String>::deserialize(deserializer)?
+            .map(|s| Hash::from_str(&s).map_err(D::Error::custom))
+            .transpose()
+    }
+}
+
+/// Serialize a unix timestamp as ISO 8601 UTC, e.g. `2023-11-14T22:13:20Z`.
+/// Output only; use with `serialize_with`.
+pub mod timestamp {
+    use serde::Serializer;
+
+    pub fn serialize<S: Serializer>(ts: &i64, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&crate::json::iso_timestamp(*ts))
+    }
+}
+
+/// Serialize an `Option` of a unix timestamp as ISO 8601 UTC or null.
+/// Output only; use with `serialize_with`.
+pub mod option_timestamp {
+    use serde::Serializer;
+
+    pub fn serialize<S: Serializer>(ts: &Option<i64>, serializer: S) -> Result<S::Ok, S::Error> {
+        match ts {
+            Some(ts) => serializer.serialize_str(&crate::json::iso_timestamp(*ts)),
+            None => serializer.serialize_none(),
+        }
+    }
+}
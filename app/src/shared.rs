@@ -0,0 +1,139 @@
+//! What lets one `TokenVaultClient` be shared between threads. anchor-client
+//! keeps its payer in an `Rc` inside `Program`, so the client holds the
+//! pieces of a program instead and builds anchor's request builders per
+//! transaction. Per-call state such as a `within` deadline is kept per
+//! thread, so calls on one thread never see another's.
+
+use anchor_client::{
+    solana_client::rpc_client::RpcClient,
+    solana_sdk::{
+        commitment_config::CommitmentConfig,
+        pubkey::Pubkey,
+        signature::{Keypair, Signature},
+        signer::{Signer, SignerError},
+    },
+    Cluster, RequestBuilder,
+};
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use std::thread::{self, ThreadId};
+
+/// A keypair shared between threads, as the `Signer` anchor takes for a
+/// request's payer
+pub(crate) struct SharedKeypair(pub Arc<Keypair>);
+
+impl SharedKeypair {
+    pub(crate) fn rc(keypair: &Arc<Keypair>) -> Rc<dyn Signer> {
+        Rc::new(Self(keypair.clone()))
+    }
+}
+
+impl Signer for SharedKeypair {
+    fn try_pubkey(&self) -> Result<Pubkey, SignerError> {
+        self.0.try_pubkey()
+    }
+
+    fn try_sign_message(&self, message: &[u8]) -> Result<Signature, SignerError> {
+        self.0.try_sign_message(message)
+    }
+
+    fn is_interactive(&self) -> bool {
+        false
+    }
+}
+
+/// The program the client talks to, through the endpoint transactions are
+/// sent to. Mirrors the parts of anchor's `Program` the client uses.
+#[derive(Clone)]
+pub(crate) struct SharedProgram {
+    program_id: Pubkey,
+    cluster: Cluster,
+    payer: Arc<Keypair>,
+    commitment: CommitmentConfig,
+}
+
+impl SharedProgram {
+    pub(crate) fn new(
+        cluster: Cluster,
+        payer: Arc<Keypair>,
+        program_id: Pubkey,
+        commitment: CommitmentConfig,
+    ) -> Self {
+        Self {
+            program_id,
+            cluster,
+            payer,
+            commitment,
+        }
+    }
+
+    /// The same program through `cluster`
+    pub(crate) fn with_cluster(&self, cluster: Cluster) -> Self {
+        Self {
+            cluster,
+            ..self.clone()
+        }
+    }
+
+    pub(crate) fn id(&self) -> Pubkey {
+        self.program_id
+    }
+
+    pub(crate) fn payer(&self) -> Pubkey {
+        self.payer.pubkey()
+    }
+
+    /// A connection to the endpoint, at the client's commitment
+    pub(crate) fn rpc(&self) -> RpcClient {
+        RpcClient::new_with_commitment(self.cluster.url().to_string(), self.commitment)
+    }
+
+    /// An empty request, paid for by the client's payer
+    pub(crate) fn request(&self) -> RequestBuilder<'_> {
+        RequestBuilder::from(
+            self.program_id,
+            self.cluster.url(),
+            SharedKeypair::rc(&self.payer),
+            Some(self.commitment),
+        )
+    }
+}
+
+/// A value each thread sets for the call it is making, such as the
+/// deadline of `within`. Clones share the values.
+#[derive(Debug)]
+pub(crate) struct PerThread<T>(Arc<Mutex<HashMap<ThreadId, T>>>);
+
+impl<T> Clone for PerThread<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<T> Default for PerThread<T> {
+    fn default() -> Self {
+        Self(Arc::new(Mutex::new(HashMap::new())))
+    }
+}
+
+impl<T: Copy> PerThread<T> {
+    /// This thread's value
+    pub(crate) fn get(&self) -> Option<T> {
+        self.0.lock().unwrap().get(&thread::current().id()).copied()
+    }
+
+    /// Set or clear this thread's value, returning the previous one
+    pub(crate) fn replace(&self, value: Option<T>) -> Option<T> {
+        let mut values = self.0.lock().unwrap();
+        let id = thread::current().id();
+        match value {
+            Some(value) => values.insert(id, value),
+            None => values.remove(&id),
+        }
+    }
+
+    pub(crate) fn set(&self, value: Option<T>) {
+        self.replace(value);
+    }
+}
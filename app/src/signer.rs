@@ -0,0 +1,126 @@
+//! Keys that sign for the client without being a `Keypair` in memory:
+//! hardware wallets, KMS-backed keys, a co-signing service. Every method
+//! that signs takes a `&dyn VaultSigner`. Anything that is a
+//! `solana_sdk` `Signer`, `Keypair` included, is one already, so existing
+//! callers pass their keypairs as before.
+//!
+//! The client still builds, retries and confirms transactions itself; it
+//! asks the signer for one signature over each message it sends.
+
+use anchor_client::solana_sdk::{
+    pubkey::Pubkey,
+    signature::Signature,
+    signer::{Signer, SignerError},
+};
+use anyhow::{anyhow, Context, Result};
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::str::FromStr;
+
+/// A key that can sign transaction messages
+pub trait VaultSigner {
+    fn pubkey(&self) -> Pubkey;
+
+    /// Signature of `pubkey` over the serialized `message`
+    fn sign_message(&self, message: &[u8]) -> Result<Signature>;
+
+    /// Whether signing waits for a person, for example to confirm on a
+    /// device
+    fn is_interactive(&self) -> bool {
+        false
+    }
+}
+
+impl<T: Signer + ?Sized> VaultSigner for T {
+    fn pubkey(&self) -> Pubkey {
+        Signer::pubkey(self)
+    }
+
+    fn sign_message(&self, message: &[u8]) -> Result<Signature> {
+        Ok(self.try_sign_message(message)?)
+    }
+
+    fn is_interactive(&self) -> bool {
+        Signer::is_interactive(self)
+    }
+}
+
+/// A `VaultSigner` as the `Signer` transaction builders take
+pub(crate) struct AsSigner<'a>(pub &'a dyn VaultSigner);
+
+impl Signer for AsSigner<'_> {
+    fn try_pubkey(&self) -> Result<Pubkey, SignerError> {
+        Ok(self.0.pubkey())
+    }
+
+    fn try_sign_message(&self, message: &[u8]) -> Result<Signature, SignerError> {
+        self.0
+            .sign_message(message)
+            .map_err(|err| SignerError::Custom(format!("{:#}", err)))
+    }
+
+    fn is_interactive(&self) -> bool {
+        self.0.is_interactive()
+    }
+}
+
+/// A signer that hands each message to a callback, such as a client for a
+/// signing service. Signatures that do not verify for the key are refused.
+pub struct RemoteSigner {
+    pubkey: Pubkey,
+    sign: Box<dyn Fn(&[u8]) -> Result<Signature>>,
+}
+
+impl RemoteSigner {
+    pub fn new(pubkey: Pubkey, sign: impl Fn(&[u8]) -> Result<Signature> + 'static) -> Self {
+        Self {
+            pubkey,
+            sign: Box::new(sign),
+        }
+    }
+
+    /// A signer that runs `program` with `args` for each message, writing
+    /// the message to its stdin and reading a base58 signature from its
+    /// stdout
+    pub fn command(pubkey: Pubkey, program: &str, args: &[&str]) -> Self {
+        let program = program.to_string();
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        Self::new(pubkey, move |message| {
+            let mut child = Command::new(&program)
+                .args(&args)
+                .stdin(Stdio::piped())
+                .stdout(Stdio::piped())
+                .spawn()
+                .with_context(|| format!("Failed to run signer {}", program))?;
+            child
+                .stdin
+                .take()
+                .expect("stdin is piped")
+                .write_all(message)?;
+            let output = child.wait_with_output()?;
+            if !output.status.success() {
+                return Err(anyhow!("Signer {} exited with {}", program, output.status));
+            }
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            Signature::from_str(stdout.trim())
+                .map_err(|err| anyhow!("Signer {} printed no signature: {}", program, err))
+        })
+    }
+}
+
+impl VaultSigner for RemoteSigner {
+    fn pubkey(&self) -> Pubkey {
+        self.pubkey
+    }
+
+    fn sign_message(&self, message: &[u8]) -> Result<Signature> {
+        let signature = (self.sign)(message)?;
+        if !signature.verify(self.pubkey.as_ref(), message) {
+            return Err(anyhow!(
+                "Remote signer returned a signature that is not {}'s",
+                self.pubkey
+            ));
+        }
+        Ok(signature)
+    }
+}
@@ -0,0 +1,100 @@
+//! Dry runs: a deposit or withdrawal built exactly as it would be sent,
+//! simulated by the cluster instead of submitted.
+
+use anchor_client::solana_client::rpc_client::RpcClient;
+use anyhow::{anyhow, Result};
+use std::fmt;
+
+use crate::history;
+use crate::signer::VaultSigner;
+use crate::token_vault::events::WithdrawEvent;
+use crate::{DepositSource, Payout, TokenVaultClient, TokenVaultError, WithdrawOptions};
+
+/// What the cluster reported for a simulated transaction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Simulation {
+    /// Compute units the transaction used, if the node reported them
+    pub units_consumed: Option<u64>,
+    pub logs: Vec<String>,
+    /// Why the transaction would fail, typed like a failed send; `None` if
+    /// it would succeed
+    pub error: Option<TokenVaultError>,
+}
+
+impl Simulation {
+    pub fn succeeded(&self) -> bool {
+        self.error.is_none()
+    }
+
+    /// Withdraw events the program would emit
+    pub fn withdraw_events(&self) -> Vec<WithdrawEvent> {
+        history::events(&self.logs)
+    }
+
+    /// Total fee the simulated withdrawals would add to the vault's accrued
+    /// fees
+    pub fn fee_transferred(&self) -> u64 {
+        self.withdraw_events().iter().map(|event| event.fee).sum()
+    }
+
+    /// Fail with the simulation's error, if it has one
+    pub fn check(&self) -> Result<(), TokenVaultError> {
+        match &self.error {
+            Some(err) => Err(err.clone()),
+            None => Ok(()),
+        }
+    }
+}
+
+impl fmt::Display for Simulation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.error {
+            None => write!(f, "simulation succeeded")?,
+            Some(err) => write!(f, "simulation failed: {}", err)?,
+        }
+        if let Some(units) = self.units_consumed {
+            write!(f, ", {} compute units", units)?;
+        }
+        Ok(())
+    }
+}
+
+impl TokenVaultClient {
+    /// Simulate `deposit` of `amount` into the current vault. The client's
+    /// pre-checks still run and fail as they would for a real deposit;
+    /// what the program would do is in the returned `Simulation`.
+    pub fn simulate_deposit(&self, depositor: &dyn VaultSigner, amount: u64) -> Result<Simulation> {
+        let vault = self.current_vault()?;
+        self.simulating(|client| {
+            client.send_deposit(vault, depositor, DepositSource::Associated, amount, None)
+        })?
+        .ok_or_else(|| anyhow!("The deposit sent no transaction to simulate"))
+    }
+
+    /// Simulate `withdraw` of `amount` from the current vault to the
+    /// withdrawer's own token account, like `simulate_deposit`
+    pub fn simulate_withdraw(
+        &self,
+        withdrawer: &dyn VaultSigner,
+        amount: u64,
+    ) -> Result<Simulation> {
+        let vault = self.current_vault()?;
+        self.simulating(|client| {
+            client.send_withdraw(
+                vault,
+                withdrawer,
+                amount,
+                None,
+                Payout::Wallet(withdrawer.pubkey()),
+                WithdrawOptions::default(),
+            )
+        })?
+        .ok_or_else(|| anyhow!("The withdrawal sent no transaction to simulate"))
+    }
+
+    /// Connection to the endpoint transactions are sent and simulated
+    /// through, at the client's send commitment
+    pub fn rpc(&self) -> RpcClient {
+        self.program.rpc()
+    }
+}
@@ -0,0 +1,366 @@
+use anchor_client::solana_sdk::{pubkey::Pubkey, signature::Signature};
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::logging;
+use crate::policy::{Operation, OperationPolicy};
+use crate::TokenVaultError;
+
+/// Length of a one-day rolling window
+pub const DAY_SECONDS: i64 = 24 * 60 * 60;
+
+/// How long to wait for another process holding a state file's lock
+const LOCK_TIMEOUT: Duration = Duration::from_secs(10);
+const LOCK_RETRY_INTERVAL: Duration = Duration::from_millis(5);
+/// A lock file older than this was left by a process that died holding it
+const STALE_LOCK_AGE: Duration = Duration::from_secs(60);
+
+/// Caps on what one signer may move
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpendingLimits {
+    /// Largest amount a single operation may move
+    pub per_transaction: Option<u64>,
+    /// Largest total moved within any `window_seconds`
+    pub per_window: Option<u64>,
+    pub window_seconds: i64,
+}
+
+impl SpendingLimits {
+    /// At most `amount` per rolling day
+    pub fn daily(amount: u64) -> Self {
+        Self {
+            per_transaction: None,
+            per_window: Some(amount),
+            window_seconds: DAY_SECONDS,
+        }
+    }
+}
+
+/// One approved operation counted against its signer's window
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpendEntry {
+    #[serde(with = "crate::serde_utils::pubkey")]
+    pub signer: Pubkey,
+    pub operation: u64,
+    pub amount: u64,
+    /// Unix timestamp of the approval
+    pub at: i64,
+    /// Set once confirmed; an entry without one is in flight, or its outcome
+    /// was never learned and it is counted as spent
+    #[serde(default, with = "crate::serde_utils::option_signature")]
+    pub signature: Option<Signature>,
+}
+
+/// Everything a `SpendingGuard` remembers between operations
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpendingLedger {
+    pub entries: Vec<SpendEntry>,
+}
+
+impl SpendingLedger {
+    /// Entries of `signer` still inside a window of `window_seconds` at `now`
+    fn in_window(
+        &self,
+        signer: Pubkey,
+        window_seconds: i64,
+        now: i64,
+    ) -> impl Iterator<Item = &SpendEntry> {
+        self.entries
+            .iter()
+            .filter(move |entry| entry.signer == signer && entry.at > now - window_seconds)
+    }
+}
+
+/// Where a `SpendingGuard` keeps its ledger
+pub trait SpendingStore: Send + Sync {
+    /// Apply `update` to the ledger, atomically with respect to every other
+    /// user of the store. Nothing is persisted if `update` fails.
+    fn update(&self, update: &mut dyn FnMut(&mut SpendingLedger) -> Result<()>) -> Result<()>;
+}
+
+/// Ledger kept in memory; lost when the process exits
+#[derive(Debug, Default)]
+pub struct MemorySpendingStore {
+    ledger: Mutex<SpendingLedger>,
+}
+
+impl SpendingStore for MemorySpendingStore {
+    fn update(&self, update: &mut dyn FnMut(&mut SpendingLedger) -> Result<()>) -> Result<()> {
+        let mut ledger = self
+            .ledger
+            .lock()
+            .map_err(|_| anyhow!("Spending ledger lock poisoned"))?;
+        let mut next = ledger.clone();
+        update(&mut next)?;
+        *ledger = next;
+        Ok(())
+    }
+}
+
+/// Ledger kept in a JSON file, so restarts don't reset the window. Processes
+/// sharing the file take turns through a `.lock` file next to it.
+#[derive(Debug)]
+pub struct FileSpendingStore {
+    path: PathBuf,
+    local: Mutex<()>,
+}
+
+impl FileSpendingStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            local: Mutex::new(()),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn lock_path(&self) -> PathBuf {
+        self.path.with_extension("lock")
+    }
+
+    fn acquire(&self) -> Result<FileLock> {
+        let path = self.lock_path();
+        let started = SystemTime::now();
+        loop {
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(_) => return Ok(FileLock { path }),
+                Err(err) if err.kind() == ErrorKind::AlreadyExists => {
+                    let age = fs::metadata(&path)
+                        .and_then(|metadata| metadata.modified())
+                        .ok()
+                        .and_then(|modified| modified.elapsed().ok());
+                    if age.is_some_and(|age| age > STALE_LOCK_AGE) {
+                        let _ = fs::remove_file(&path);
+                        continue;
+                    }
+                    if started.elapsed().unwrap_or_default() > LOCK_TIMEOUT {
+                        return Err(anyhow!(
+                            "Timed out waiting for spending state lock {}",
+                            path.display()
+                        ));
+                    }
+                    thread::sleep(LOCK_RETRY_INTERVAL);
+                }
+                Err(err) => {
+                    return Err(err)
+                        .with_context(|| format!("Creating lock file {}", path.display()))
+                }
+            }
+        }
+    }
+
+    fn load(&self) -> Result<SpendingLedger> {
+        match fs::read_to_string(&self.path) {
+            Ok(contents) => serde_json::from_str(&contents)
+                .with_context(|| format!("Reading spending state {}", self.path.display())),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(SpendingLedger::default()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    fn save(&self, ledger: &SpendingLedger) -> Result<()> {
+        // Write-then-rename so a kill mid-write never leaves a truncated ledger
+        let tmp = self.path.with_extension("tmp");
+        fs::write(&tmp, serde_json::to_vec_pretty(ledger)?)?;
+        fs::rename(&tmp, &self.path)?;
+        Ok(())
+    }
+}
+
+impl SpendingStore for FileSpendingStore {
+    fn update(&self, update: &mut dyn FnMut(&mut SpendingLedger) -> Result<()>) -> Result<()> {
+        let _local = self
+            .local
+            .lock()
+            .map_err(|_| anyhow!("Spending state lock poisoned"))?;
+        let _lock = self.acquire()?;
+        let mut ledger = self.load()?;
+        update(&mut ledger)?;
+        self.save(&ledger)
+    }
+}
+
+/// Held lock file, removed on drop
+struct FileLock {
+    path: PathBuf,
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Built-in `OperationPolicy` capping how much each configured signer moves,
+/// per transaction and over a rolling window. Operations are counted when
+/// approved, before signing, so concurrent operations can't overdraw the
+/// window; a failure that certainly did not land gives the allowance back.
+pub struct SpendingGuard {
+    limits: BTreeMap<Pubkey, SpendingLimits>,
+    store: Box<dyn SpendingStore>,
+}
+
+impl SpendingGuard {
+    pub fn new(store: impl SpendingStore + 'static) -> Self {
+        Self {
+            limits: BTreeMap::new(),
+            store: Box::new(store),
+        }
+    }
+
+    /// Guard persisting its ledger to `path`
+    pub fn with_state_file(path: impl Into<PathBuf>) -> Self {
+        Self::new(FileSpendingStore::new(path))
+    }
+
+    /// Apply `limits` to `signer`; signers without limits are not tracked
+    pub fn set_limits(&mut self, signer: Pubkey, limits: SpendingLimits) -> &mut Self {
+        self.limits.insert(signer, limits);
+        self
+    }
+
+    pub fn limits(&self, signer: &Pubkey) -> Option<&SpendingLimits> {
+        self.limits.get(signer)
+    }
+
+    /// What `signer` may still move in its window at `now`; `None` if unlimited
+    pub fn window_remaining_at(&self, signer: Pubkey, now: i64) -> Result<Option<u64>> {
+        let limits = match self.limits.get(&signer) {
+            Some(limits) => *limits,
+            None => return Ok(None),
+        };
+        let mut remaining = None;
+        self.store.update(&mut |ledger| {
+            remaining = window_remaining(ledger, signer, &limits, now);
+            Ok(())
+        })?;
+        Ok(remaining)
+    }
+
+    /// Count `operation` against its signer's limits at `now`, or fail with
+    /// `SpendingLimitExceeded` if it would exceed them
+    pub fn reserve_at(&self, operation: &Operation, now: i64) -> Result<()> {
+        let limits = match self.limits.get(&operation.signer) {
+            Some(limits) if operation.amount > 0 => *limits,
+            _ => return Ok(()),
+        };
+        self.store.update(&mut |ledger| {
+            prune(ledger, &self.limits, now);
+            let window_remaining = window_remaining(ledger, operation.signer, &limits, now);
+            let over_transaction = limits
+                .per_transaction
+                .is_some_and(|limit| operation.amount > limit);
+            let over_window = window_remaining.is_some_and(|left| operation.amount > left);
+            if over_transaction || over_window {
+                let resets_at = ledger
+                    .in_window(operation.signer, limits.window_seconds, now)
+                    .map(|entry| entry.at + limits.window_seconds)
+                    .min()
+                    .unwrap_or(now);
+                return Err(TokenVaultError::SpendingLimitExceeded {
+                    window_remaining: window_remaining.unwrap_or(u64::MAX),
+                    resets_at,
+                }
+                .into());
+            }
+            ledger.entries.push(SpendEntry {
+                signer: operation.signer,
+                operation: operation.id,
+                amount: operation.amount,
+                at: now,
+                signature: None,
+            });
+            Ok(())
+        })
+    }
+
+    /// Record the signature `operation` was confirmed with
+    pub fn confirm(&self, operation: &Operation, signature: Signature) -> Result<()> {
+        self.store.update(&mut |ledger| {
+            for entry in ledger.entries.iter_mut() {
+                if entry.signer == operation.signer && entry.operation == operation.id {
+                    entry.signature = Some(signature);
+                }
+            }
+            Ok(())
+        })
+    }
+
+    /// Give back the allowance of an operation that never landed
+    pub fn release(&self, operation: &Operation) -> Result<()> {
+        self.store.update(&mut |ledger| {
+            ledger.entries.retain(|entry| {
+                !(entry.signer == operation.signer && entry.operation == operation.id)
+            });
+            Ok(())
+        })
+    }
+}
+
+impl OperationPolicy for SpendingGuard {
+    fn before_sign(&self, operation: &Operation) -> Result<()> {
+        self.reserve_at(operation, unix_now())
+    }
+
+    fn after_confirm(&self, operation: &Operation, signature: &Signature) {
+        // The entry already counts against the window either way
+        if let Err(err) = self.confirm(operation, *signature) {
+            logging::warn!(
+                error = %logging::redacted(&err, false),
+                "could not record confirmed spend"
+            );
+        }
+    }
+
+    fn after_failure(&self, operation: &Operation, may_have_landed: bool) {
+        if may_have_landed {
+            return;
+        }
+        if let Err(err) = self.release(operation) {
+            logging::warn!(
+                error = %logging::redacted(&err, false),
+                "could not release failed spend"
+            );
+        }
+    }
+}
+
+fn window_remaining(
+    ledger: &SpendingLedger,
+    signer: Pubkey,
+    limits: &SpendingLimits,
+    now: i64,
+) -> Option<u64> {
+    let limit = limits.per_window?;
+    let spent: u64 = ledger
+        .in_window(signer, limits.window_seconds, now)
+        .map(|entry| entry.amount)
+        .sum();
+    Some(limit.saturating_sub(spent))
+}
+
+/// Drop entries that have rolled out of their signer's window
+fn prune(ledger: &mut SpendingLedger, limits: &BTreeMap<Pubkey, SpendingLimits>, now: i64) {
+    ledger.entries.retain(|entry| {
+        limits
+            .get(&entry.signer)
+            .is_none_or(|limits| entry.at > now - limits.window_seconds)
+    });
+}
+
+fn unix_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs() as i64)
+        .unwrap_or_default()
+}
@@ -0,0 +1,52 @@
+//! Deposits of tokens that belong to someone other than the key paying for
+//! the transaction. A relayer can pay the fee of a deposit the token owner
+//! signs, and a delegate approved on the owner's token account can deposit
+//! out of it without the owner signing at all.
+
+use anchor_client::solana_sdk::{pubkey::Pubkey, signature::Keypair};
+use anchor_spl::associated_token::get_associated_token_address;
+use anyhow::Result;
+
+use crate::signer::VaultSigner;
+use crate::{DepositSource, TokenVaultClient, TxReceipt};
+
+impl TokenVaultClient {
+    /// Deposit `amount` from `owner`'s associated token account for the
+    /// vault's mint, with `payer` paying the transaction fee instead of the
+    /// client's payer or fee pool. `owner` signs as the transfer authority,
+    /// `payer` as the fee payer.
+    pub fn deposit_for(
+        &self,
+        payer: &Keypair,
+        owner: &dyn VaultSigner,
+        amount: u64,
+    ) -> Result<TxReceipt> {
+        let vault = self.current_vault()?;
+        let (signature, _) = self.send_deposit(
+            vault,
+            owner,
+            DepositSource::Associated,
+            amount,
+            None,
+            Some(payer),
+            None,
+        )?;
+        Ok(self.tx_receipt(signature))
+    }
+
+    /// Deposit `amount` out of `owner`'s associated token account for the
+    /// vault's mint, with `delegate` signing as its approved delegate. A
+    /// delegation that is missing or smaller than `amount` is refused before
+    /// sending.
+    pub fn deposit_delegated(
+        &self,
+        delegate: &dyn VaultSigner,
+        owner: Pubkey,
+        amount: u64,
+    ) -> Result<TxReceipt> {
+        let vault = self.current_vault()?;
+        let (vault_data, _) = self.fetch_vault_state(vault, self.deadline(), false)?;
+        let source = get_associated_token_address(&owner, &vault_data.token_mint);
+        self.deposit_from(delegate, source, amount)
+    }
+}
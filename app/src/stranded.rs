@@ -0,0 +1,179 @@
+use anchor_client::solana_sdk::{
+    account::Account, program_pack::Pack, pubkey::Pubkey, system_program,
+};
+use anchor_spl::token::{self, spl_token};
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::logging;
+use crate::plan::format_sol;
+use crate::signer::{AsSigner, VaultSigner};
+use crate::token_vault;
+use crate::{CheckedAgainst, DeadlinePhase, Operation, OperationKind, TokenVaultClient};
+
+/// Lamports held by one of the vault's accounts compared with what it needs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StrandedAccount {
+    #[serde(with = "crate::serde_utils::pubkey")]
+    pub address: Pubkey,
+    pub lamports: u64,
+    pub rent_exempt_minimum: u64,
+    /// Lamports that belong to the account's contents, e.g. wrapped SOL in a
+    /// native token account; never swept
+    pub reserved: u64,
+    /// Lamports that can be swept without losing rent exemption
+    pub surplus: u64,
+}
+
+impl StrandedAccount {
+    pub fn new(address: Pubkey, lamports: u64, rent_exempt_minimum: u64, reserved: u64) -> Self {
+        Self {
+            address,
+            lamports,
+            rent_exempt_minimum,
+            reserved,
+            surplus: lamports.saturating_sub(rent_exempt_minimum.saturating_add(reserved)),
+        }
+    }
+}
+
+/// Stranded lamports across the vault and its token account
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StrandedReport {
+    pub vault: StrandedAccount,
+    pub vault_token_account: StrandedAccount,
+}
+
+impl StrandedReport {
+    pub fn total_surplus(&self) -> u64 {
+        self.vault.surplus + self.vault_token_account.surplus
+    }
+}
+
+impl fmt::Display for StrandedReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (label, account) in [
+            ("vault", &self.vault),
+            ("vault token account", &self.vault_token_account),
+        ] {
+            writeln!(
+                f,
+                "  {} {}: {} held, {} rent-exempt minimum, {} stranded",
+                label,
+                account.address,
+                format_sol(account.lamports),
+                format_sol(account.rent_exempt_minimum),
+                format_sol(account.surplus)
+            )?;
+        }
+        write!(f, "  total stranded: {}", format_sol(self.total_surplus()))
+    }
+}
+
+impl TokenVaultClient {
+    /// Lamports sitting in the vault and its token account above their rent-exempt minimum
+    pub fn get_stranded_lamports(&self) -> Result<StrandedReport> {
+        let vault = self.current_vault()?;
+        let (vault_token_account, _) =
+            token_vault::pda::find_vault_token_account_address(&self.program.id(), &vault);
+
+        let rpc = self.rpc_within(self.deadline(), DeadlinePhase::Fetch)?;
+        let vault_account = rpc.get_account(&vault)?;
+        let token_account = rpc.get_account(&vault_token_account)?;
+
+        let stranded = |address: Pubkey, account: &Account, reserved: u64| -> Result<_> {
+            let minimum = rpc.get_minimum_balance_for_rent_exemption(account.data.len())?;
+            Ok(StrandedAccount::new(
+                address,
+                account.lamports,
+                minimum,
+                reserved,
+            ))
+        };
+
+        // Lamports of a native (wrapped SOL) token account are its token balance
+        let token_state = spl_token::state::Account::unpack(&token_account.data)?;
+        let reserved = if token_state.is_native() {
+            token_state.amount
+        } else {
+            0
+        };
+
+        Ok(StrandedReport {
+            vault: stranded(vault, &vault_account, 0)?,
+            vault_token_account: stranded(vault_token_account, &token_account, reserved)?,
+        })
+    }
+
+    /// Send the lamports above rent exemption in the vault and its token account
+    /// to `destination`, returning the amount swept
+    pub fn sweep_lamports(&self, authority: &dyn VaultSigner, destination: Pubkey) -> Result<u64> {
+        self.check_signer(&authority.pubkey())?;
+        let deadline = self.deadline();
+        self.check_features(deadline)?;
+        let vault = self.current_vault()?;
+
+        let (vault_data, provenance) = self.fetch_vault_state(vault, deadline, true)?;
+        if vault_data.authority != authority.pubkey() {
+            return Err(anyhow!(
+                "{} is not the authority of vault {}",
+                authority.pubkey(),
+                vault
+            ))
+            .context(CheckedAgainst(provenance));
+        }
+
+        // Lamports sent to a program-owned account could be stranded all over again
+        let rpc = self.rpc_within(deadline, DeadlinePhase::Fetch)?;
+        if let Some(account) = rpc
+            .get_account_with_commitment(&destination, rpc.commitment())?
+            .value
+        {
+            if account.owner != system_program::ID {
+                return Err(anyhow!(
+                    "Sweep destination {} is owned by {}, not the system program",
+                    destination,
+                    account.owner
+                ));
+            }
+        }
+
+        let report = self.get_stranded_lamports()?;
+        let surplus = report.total_surplus();
+        if surplus == 0 {
+            logging::info!(
+                vault = %logging::log_key(&vault, self.log_redaction),
+                "nothing to sweep"
+            );
+            return Ok(0);
+        }
+
+        logging::debug!(surplus = %format_sol(surplus), "sweeping stranded lamports");
+
+        let signer = AsSigner(authority);
+        let request = self
+            .vault_request(&[vault])?
+            .accounts(token_vault::accounts::SweepLamports {
+                authority: authority.pubkey(),
+                vault,
+                vault_token_account: report.vault_token_account.address,
+                destination,
+                token_program: token::ID,
+            })
+            .args(token_vault::instruction::SweepLamports {})
+            .signer(&signer);
+        let operation = Operation::new(
+            OperationKind::SweepLamports,
+            authority.pubkey(),
+            Some(vault),
+            0,
+        );
+        self.send_operation(operation, request, deadline)?;
+
+        self.invalidate(&vault);
+        self.invalidate(&report.vault_token_account.address);
+        self.invalidate(&destination);
+        Ok(surplus)
+    }
+}
@@ -0,0 +1,168 @@
+//! A channel of operations in, a `Stream` of outcomes out, for pipelines
+//! that submit many operations and want one ordered feed of results instead
+//! of awaiting each call.
+//!
+//! ```no_run
+//! # use token_vault_client::{TokenVaultClient, VaultOperation};
+//! # use anchor_client::solana_sdk::signature::Keypair;
+//! # fn run(client: &TokenVaultClient, signer: &Keypair) -> anyhow::Result<()> {
+//! use futures::{executor::block_on, StreamExt};
+//!
+//! let (mut sender, outcomes) = client.operation_sink(signer, 16);
+//! let producer = std::thread::spawn(move || {
+//!     block_on(async {
+//!         for n in 0..100u64 {
+//!             let deposit = VaultOperation::Deposit { vault: None, amount: 1_000, mint: None };
+//!             sender.send(format!("deposit-{}", n), deposit).await?;
+//!         }
+//!         anyhow::Ok(())
+//!     })
+//! });
+//! block_on(outcomes.for_each(|outcome| async move { println!("{:?}", outcome) }));
+//! producer.join().unwrap()?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The client blocks while it sends and confirms, and it cannot leave the
+//! thread that owns it, so operations run one at a time on whichever thread
+//! polls the stream. Senders are `Send`: producers can live on other threads
+//! or tasks, and their `send` waits while the queue is full.
+
+use anchor_client::solana_sdk::signature::Signature;
+use anyhow::{anyhow, Result};
+use futures::channel::mpsc;
+use futures::{SinkExt, Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use crate::json::ErrorBody;
+use crate::signer::VaultSigner;
+use crate::{BatchOutcome, TokenVaultClient, VaultOperation};
+
+/// Something that can carry out a vault operation; the client, or a fake
+/// chain in tests
+pub trait OperationExecutor {
+    fn execute(&self, signer: &dyn VaultSigner, operation: &VaultOperation) -> Result<Signature>;
+}
+
+impl OperationExecutor for TokenVaultClient {
+    fn execute(&self, signer: &dyn VaultSigner, operation: &VaultOperation) -> Result<Signature> {
+        let vault = operation
+            .vault()
+            .or(self.vault_address)
+            .ok_or_else(|| anyhow!("No vault: set \"vault\" or pass a default vault"))?;
+        self.send_vault_operation(vault, signer, operation)
+    }
+}
+
+/// An operation submitted to an `operation_sink`
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct OperationRequest {
+    id: String,
+    operation: VaultOperation,
+}
+
+/// Result of one submitted operation, tagged with the caller's id
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OperationOutcome {
+    pub id: String,
+    #[serde(flatten)]
+    pub outcome: BatchOutcome,
+}
+
+/// Feeds an `operation_sink`. Clones feed the same stream, which ends once
+/// every sender is dropped and the queued operations have run.
+#[derive(Debug, Clone)]
+pub struct OperationSender {
+    queue: mpsc::Sender<OperationRequest>,
+}
+
+impl OperationSender {
+    /// Queue `operation` under `id`, waiting while the queue is full. The id
+    /// is its idempotency key: an id seen before is skipped, not run again.
+    /// Fails once the stream has been dropped.
+    pub async fn send(&mut self, id: impl Into<String>, operation: VaultOperation) -> Result<()> {
+        let request = OperationRequest {
+            id: id.into(),
+            operation,
+        };
+        self.queue
+            .send(request)
+            .await
+            .map_err(|_| anyhow!("Operation stream was dropped"))
+    }
+}
+
+/// Outcomes of the operations sent to an `operation_sink`, in the order they
+/// completed
+pub struct OperationStream<'a, E: OperationExecutor> {
+    executor: &'a E,
+    signer: &'a dyn VaultSigner,
+    queue: mpsc::Receiver<OperationRequest>,
+    seen: HashSet<String>,
+}
+
+impl<E: OperationExecutor> Stream for OperationStream<'_, E> {
+    type Item = OperationOutcome;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        let request = match this.queue.poll_next_unpin(cx) {
+            Poll::Ready(Some(request)) => request,
+            Poll::Ready(None) => return Poll::Ready(None),
+            Poll::Pending => return Poll::Pending,
+        };
+        let outcome = if this.seen.insert(request.id.clone()) {
+            match this.executor.execute(this.signer, &request.operation) {
+                Ok(signature) => BatchOutcome::Executed { signature },
+                Err(err) => BatchOutcome::Failed {
+                    error: ErrorBody::of(&err),
+                },
+            }
+        } else {
+            BatchOutcome::Skipped {
+                reason: format!("operation id '{}' was already submitted", request.id),
+            }
+        };
+        Poll::Ready(Some(OperationOutcome {
+            id: request.id,
+            outcome,
+        }))
+    }
+}
+
+/// A sender for operations and the stream of their outcomes. At most
+/// `max_in_flight` operations wait per sender before `send` applies
+/// backpressure; operations are signed by `signer`.
+pub fn operation_sink<'a, E: OperationExecutor>(
+    executor: &'a E,
+    signer: &'a dyn VaultSigner,
+    max_in_flight: usize,
+) -> (OperationSender, OperationStream<'a, E>) {
+    // The channel holds one slot per sender on top of its buffer
+    let (queue, receiver) = mpsc::channel(max_in_flight.saturating_sub(1));
+    (
+        OperationSender { queue },
+        OperationStream {
+            executor,
+            signer,
+            queue: receiver,
+            seen: HashSet::new(),
+        },
+    )
+}
+
+impl TokenVaultClient {
+    /// `operation_sink` running operations through this client; operations
+    /// without a vault go to the client's vault
+    pub fn operation_sink<'a>(
+        &'a self,
+        signer: &'a dyn VaultSigner,
+        max_in_flight: usize,
+    ) -> (OperationSender, OperationStream<'a, Self>) {
+        operation_sink(self, signer, max_in_flight)
+    }
+}
@@ -0,0 +1,276 @@
+//! Live vault activity over the cluster's WebSocket endpoint, for bots that
+//! would otherwise poll. `subscribe_vault_events` follows the program's
+//! logs with `logsSubscribe` and yields the events of one vault;
+//! `subscribe_vault_state` follows the vault account with
+//! `accountSubscribe` and yields it decoded on every change.
+//!
+//! ```no_run
+//! # use token_vault_client::TokenVaultClient;
+//! # use anchor_client::solana_sdk::pubkey::Pubkey;
+//! # fn run(client: &TokenVaultClient, vault: Pubkey) -> anyhow::Result<()> {
+//! use futures::{executor::block_on_stream, StreamExt};
+//!
+//! let events = client.subscribe_vault_events(vault)?;
+//! for event in block_on_stream(events.take(10)) {
+//!     println!("{:?}", event);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! A worker thread owns the connection. When it drops, the worker
+//! reconnects and subscribes again, backing off up to
+//! `MAX_RECONNECT_DELAY`. Activity while it is disconnected is not
+//! replayed; `get_changes_since` catches up from the last slot seen. The
+//! stream ends after `shutdown`, which unsubscribes and waits for the
+//! worker, or once it is dropped.
+
+use anchor_client::solana_client::pubsub_client::{
+    AccountSubscription, LogsSubscription, PubsubClient,
+};
+use anchor_client::solana_client::rpc_config::{
+    RpcAccountInfoConfig, RpcTransactionLogsConfig, RpcTransactionLogsFilter,
+};
+use anchor_client::solana_client::rpc_response::{Response, RpcLogsResponse};
+use anchor_client::solana_sdk::{account::Account, pubkey::Pubkey};
+use anchor_lang::AccountDeserialize;
+use anyhow::Result;
+use futures::channel::mpsc;
+use futures::Stream;
+use solana_account_decoder::{UiAccount, UiAccountEncoding};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::events::parse_events;
+use crate::history::program_logs;
+use crate::logging;
+use crate::token_vault::state::Vault;
+use crate::{TokenVaultClient, VaultEvent};
+
+/// Longest wait between reconnection attempts
+pub const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+
+/// First wait after a dropped connection; doubled on each failed attempt
+const FIRST_RECONNECT_DELAY: Duration = Duration::from_millis(500);
+
+/// How often the worker checks for `shutdown` while the socket is quiet
+const SHUTDOWN_POLL: Duration = Duration::from_millis(250);
+
+/// A live feed of `T`; see the module docs
+pub struct Subscription<T> {
+    items: mpsc::UnboundedReceiver<T>,
+    stop: Arc<AtomicBool>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl<T> Subscription<T> {
+    /// Unsubscribe and wait for the worker thread to exit
+    pub fn shutdown(mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+impl<T> Stream for Subscription<T> {
+    type Item = T;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        Pin::new(&mut self.items).poll_next(cx)
+    }
+}
+
+impl<T> Drop for Subscription<T> {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+/// One message, or none, from a pubsub connection
+enum Next<M> {
+    Message(M),
+    Idle,
+    Closed,
+}
+
+/// A connected pubsub subscription and the channel of its messages
+trait Feed {
+    type Message;
+
+    fn next(&mut self, wait: Duration) -> Next<Self::Message>;
+
+    fn close(self);
+}
+
+impl Feed for LogsSubscription {
+    type Message = Response<RpcLogsResponse>;
+
+    fn next(&mut self, wait: Duration) -> Next<Self::Message> {
+        match self.1.recv_timeout(wait) {
+            Ok(message) => Next::Message(message),
+            Err(err) if err.is_timeout() => Next::Idle,
+            Err(_) => Next::Closed,
+        }
+    }
+
+    fn close(mut self) {
+        let _ = self.0.shutdown();
+    }
+}
+
+impl Feed for AccountSubscription {
+    type Message = Response<UiAccount>;
+
+    fn next(&mut self, wait: Duration) -> Next<Self::Message> {
+        match self.1.recv_timeout(wait) {
+            Ok(message) => Next::Message(message),
+            Err(err) if err.is_timeout() => Next::Idle,
+            Err(_) => Next::Closed,
+        }
+    }
+
+    fn close(mut self) {
+        let _ = self.0.shutdown();
+    }
+}
+
+/// Run `feed` on a worker thread, converting each message with `convert`
+/// and calling `connect` again whenever the connection drops
+fn spawn<F, T>(
+    feed: F,
+    mut connect: impl FnMut() -> Result<F> + Send + 'static,
+    mut convert: impl FnMut(F::Message) -> Vec<T> + Send + 'static,
+) -> Subscription<T>
+where
+    F: Feed + Send + 'static,
+    T: Send + 'static,
+{
+    let (sender, items) = mpsc::unbounded();
+    let stop = Arc::new(AtomicBool::new(false));
+    let stopped = stop.clone();
+    let worker = thread::spawn(move || {
+        let mut feed = Some(feed);
+        let mut delay = FIRST_RECONNECT_DELAY;
+        while !stopped.load(Ordering::Relaxed) {
+            let Some(mut current) = feed.take() else {
+                match connect() {
+                    Ok(connected) => {
+                        logging::info!("subscription reconnected");
+                        feed = Some(connected);
+                        delay = FIRST_RECONNECT_DELAY;
+                    }
+                    Err(err) => {
+                        logging::warn!(error = %err, "subscription reconnect failed");
+                        sleep_unless_stopped(delay, &stopped);
+                        delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+                    }
+                }
+                continue;
+            };
+            match current.next(SHUTDOWN_POLL) {
+                Next::Message(message) => {
+                    for item in convert(message) {
+                        if sender.unbounded_send(item).is_err() {
+                            // Nobody is listening any more
+                            current.close();
+                            return;
+                        }
+                    }
+                    feed = Some(current);
+                }
+                Next::Idle => feed = Some(current),
+                Next::Closed => {
+                    logging::warn!("subscription connection dropped; reconnecting");
+                    current.close();
+                }
+            }
+        }
+        if let Some(current) = feed {
+            current.close();
+        }
+    });
+    Subscription {
+        items,
+        stop,
+        worker: Some(worker),
+    }
+}
+
+fn sleep_unless_stopped(delay: Duration, stopped: &AtomicBool) {
+    let mut slept = Duration::ZERO;
+    while slept < delay && !stopped.load(Ordering::Relaxed) {
+        let step = SHUTDOWN_POLL.min(delay - slept);
+        thread::sleep(step);
+        slept += step;
+    }
+}
+
+impl TokenVaultClient {
+    /// Deposit and withdraw events of `vault` as the program logs them, from
+    /// successful transactions only. Their `timestamp` is `None`:
+    /// `logsSubscribe` reports no block time.
+    pub fn subscribe_vault_events(&self, vault: Pubkey) -> Result<Subscription<VaultEvent>> {
+        let url = self.ws_url.clone();
+        let program_id = self.program.id();
+        let commitment = self.read_commitment_for(&self.program.rpc());
+        let connect = move || -> Result<LogsSubscription> {
+            Ok(PubsubClient::logs_subscribe(
+                &url,
+                RpcTransactionLogsFilter::Mentions(vec![program_id.to_string()]),
+                RpcTransactionLogsConfig {
+                    commitment: Some(commitment),
+                },
+            )?)
+        };
+        let feed = connect()?;
+        let program_key = program_id.to_string();
+        Ok(spawn(feed, connect, move |response| {
+            if response.value.err.is_some() {
+                return Vec::new();
+            }
+            let logs = program_logs(response.value.logs, &program_key);
+            parse_events(&logs, None)
+                .into_iter()
+                .filter(|event| event.vault() == vault)
+                .collect()
+        }))
+    }
+
+    /// `vault`'s account, decoded, each time it changes. An update that
+    /// does not decode as a vault, for example because the account was
+    /// closed, is skipped with a warning.
+    pub fn subscribe_vault_state(&self, vault: Pubkey) -> Result<Subscription<Vault>> {
+        let url = self.ws_url.clone();
+        let commitment = self.read_commitment_for(&self.program.rpc());
+        let connect = move || -> Result<AccountSubscription> {
+            Ok(PubsubClient::account_subscribe(
+                &url,
+                &vault,
+                Some(RpcAccountInfoConfig {
+                    encoding: Some(UiAccountEncoding::Base64),
+                    commitment: Some(commitment),
+                    ..Default::default()
+                }),
+            )?)
+        };
+        let feed = connect()?;
+        Ok(spawn(feed, connect, move |response| {
+            let decoded = response
+                .value
+                .decode::<Account>()
+                .and_then(|account| Vault::try_deserialize(&mut account.data.as_slice()).ok());
+            match decoded {
+                Some(vault_data) => vec![vault_data],
+                None => {
+                    logging::warn!(vault = %vault, slot = response.context.slot, "skipped undecodable vault update");
+                    Vec::new()
+                }
+            }
+        }))
+    }
+}
@@ -0,0 +1,297 @@
+use anchor_client::solana_client::rpc_client::GetConfirmedSignaturesForAddress2Config;
+use anchor_client::solana_sdk::{hash::hashv, pubkey::Pubkey};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::backfill::{fetch_transaction, BackfillConfig};
+use crate::features::{fetch_program_features, CLIENT_VERSION};
+use crate::token_vault::state::Vault;
+use crate::{
+    DeadlinePhase, EndpointHealth, FeatureSet, FreezeStatus, ProgramFeatures, TokenVaultClient,
+    VaultTransaction,
+};
+
+/// Version of the support bundle layout; bumped on every incompatible change
+pub const SUPPORT_BUNDLE_SCHEMA_VERSION: u32 = 1;
+
+/// Most transactions a bundle includes
+pub const MAX_BUNDLE_TRANSACTIONS: usize = 100;
+
+/// Settings the client was run with, as far as they are safe to share.
+/// Filled in by the caller, which knows where its settings came from.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EffectiveConfig {
+    pub profile: Option<String>,
+    pub cluster: String,
+    pub write_url: Option<String>,
+    pub read_url: Option<String>,
+    /// Path of the keypair file; the key itself is never included
+    pub keypair_path: Option<String>,
+    pub program_id: String,
+    pub key_policy: bool,
+    pub strict_mint_policy: bool,
+    #[serde(default)]
+    pub strict_accounting: bool,
+}
+
+/// Everything needed to diagnose a failing vault operation, in one document.
+/// Sections that could not be collected hold the error instead.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SupportBundle {
+    pub schema_version: u32,
+    /// Unix timestamp of collection, by the local clock
+    pub generated_at: i64,
+    pub client_version: String,
+    /// Cargo features the client was built with
+    pub client_features: Vec<String>,
+    /// Program features the client can handle
+    pub supported_program_features: String,
+    pub program_id: String,
+    pub config: Option<EffectiveConfig>,
+    pub endpoints: EndpointHealth,
+    pub program_features: Result<Option<ProgramFeatures>, String>,
+    pub vault: Option<String>,
+    pub vault_account: Option<Result<Value, String>>,
+    pub freeze: Option<Result<FreezeStatus, String>>,
+    /// Newest first
+    pub transactions: Option<Result<Vec<VaultTransaction>, String>>,
+}
+
+/// What to strip from a bundle before it leaves the machine
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RedactionRules {
+    /// Replace every public key, including ones inside log lines, with a
+    /// stable hash so the bundle still shows which accounts are the same
+    pub hash_pubkeys: bool,
+}
+
+impl SupportBundle {
+    /// The bundle as JSON with `rules` applied. URLs are always cut down to
+    /// their host and anything shaped like keypair bytes is always removed.
+    pub fn to_redacted_json(&self, rules: RedactionRules) -> Result<String> {
+        let mut value = serde_json::to_value(self)?;
+        redact(&mut value, rules);
+        Ok(serde_json::to_string_pretty(&value)?)
+    }
+}
+
+/// Cargo features compiled into this build
+fn client_features() -> Vec<String> {
+    [
+        ("async-client", cfg!(feature = "async-client")),
+        ("cli", cfg!(feature = "cli")),
+        ("example", cfg!(feature = "example")),
+        ("test-fixtures", cfg!(feature = "test-fixtures")),
+        ("test-hooks", cfg!(feature = "test-hooks")),
+    ]
+    .into_iter()
+    .filter(|(_, enabled)| *enabled)
+    .map(|(name, _)| name.to_string())
+    .collect()
+}
+
+impl TokenVaultClient {
+    /// Collect a support bundle for the current vault, if one is set,
+    /// including its `last` transactions. A section that fails to load is
+    /// recorded as its error; the bundle itself only fails for bad arguments.
+    pub fn collect_support_bundle(
+        &self,
+        config: Option<EffectiveConfig>,
+        last: usize,
+    ) -> Result<SupportBundle> {
+        if last > MAX_BUNDLE_TRANSACTIONS {
+            return Err(anyhow!(
+                "A bundle holds at most {} transactions",
+                MAX_BUNDLE_TRANSACTIONS
+            ));
+        }
+        let deadline = self.deadline();
+        let program_id = self.program.id();
+        let features = self
+            .rpc_within(deadline, DeadlinePhase::Fetch)
+            .and_then(|rpc| fetch_program_features(&rpc, &program_id))
+            .map_err(|err| format!("{:#}", err));
+
+        let mut bundle = SupportBundle {
+            schema_version: SUPPORT_BUNDLE_SCHEMA_VERSION,
+            generated_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_secs() as i64)
+                .unwrap_or_default(),
+            client_version: CLIENT_VERSION.to_string(),
+            client_features: client_features(),
+            supported_program_features: FeatureSet::SUPPORTED.to_string(),
+            program_id: program_id.to_string(),
+            config,
+            endpoints: self.endpoint_health(),
+            program_features: features,
+            vault: None,
+            vault_account: None,
+            freeze: None,
+            transactions: None,
+        };
+
+        let Some(vault) = self.vault_address else {
+            return Ok(bundle);
+        };
+        bundle.vault = Some(vault.to_string());
+        let vault_data = self.fetch_vault_state(vault, deadline, false);
+        bundle.vault_account = Some(
+            vault_data
+                .as_ref()
+                .map(|(vault_data, _)| vault_json(vault_data))
+                .map_err(|err| format!("{:#}", err)),
+        );
+        bundle.freeze = match &vault_data {
+            Ok((vault_data, _)) => Some(
+                self.freeze_status(vault, vault_data, deadline)
+                    .map_err(|err| format!("{:#}", err)),
+            ),
+            Err(err) => Some(Err(format!("{:#}", err))),
+        };
+        bundle.transactions = Some(
+            self.recent_transactions(vault, last)
+                .map_err(|err| format!("{:#}", err)),
+        );
+        Ok(bundle)
+    }
+
+    /// The `last` transactions touching `address`, newest first
+    fn recent_transactions(&self, address: Pubkey, last: usize) -> Result<Vec<VaultTransaction>> {
+        if last == 0 {
+            return Ok(Vec::new());
+        }
+        let config = BackfillConfig {
+            deadline: self.deadline(),
+            ..Default::default()
+        };
+        let rpc = self.read_rpc_until(config.deadline);
+        let statuses = rpc.get_signatures_for_address_with_config(
+            &address,
+            GetConfirmedSignaturesForAddress2Config {
+                limit: Some(last),
+                commitment: Some(config.commitment),
+                ..Default::default()
+            },
+        )?;
+        statuses
+            .iter()
+            .map(|status| {
+                fetch_transaction(&rpc, &config, &self.program.id(), &status.signature)
+                    .map(|(transaction, ..)| transaction)
+            })
+            .collect()
+    }
+}
+
+/// Every field of a vault account
+fn vault_json(vault: &Vault) -> Value {
+    json!({
+        "authority": vault.authority.to_string(),
+        "token_mint": vault.token_mint.to_string(),
+        "fee_collector": vault.fee_collector.to_string(),
+        "fee_percentage": vault.fee_percentage,
+        "withdrawal_timelock": vault.withdrawal_timelock,
+        "withdrawal_limit": vault.withdrawal_limit,
+        "total_deposited": vault.total_deposited,
+        "name": vault.name,
+        "bump": vault.bump,
+        "immutable": vault.immutable,
+        "deposit_window_start": vault.deposit_window_start,
+        "deposit_window_end": vault.deposit_window_end,
+        "dust_threshold": vault.dust_threshold,
+        "waive_dust_fee": vault.waive_dust_fee,
+        "reserve_bps": vault.reserve_bps,
+        "reserve_timelock": vault.reserve_timelock,
+        "last_reserve_release": vault.last_reserve_release,
+        "total_reserved": vault.total_reserved,
+        "accounting_anomaly": vault.accounting_anomaly,
+        "paused": vault.paused,
+        "accrued_fees": vault.accrued_fees,
+    })
+}
+
+/// Apply `rules`, plus the redactions that always apply, to every value
+/// nested in `value`
+pub fn redact(value: &mut Value, rules: RedactionRules) {
+    match value {
+        Value::String(text) => *text = redact_text(text, rules),
+        Value::Array(items) if looks_like_keypair(items) => {
+            *value = Value::String("<redacted keypair>".to_string())
+        }
+        Value::Array(items) => items.iter_mut().for_each(|item| redact(item, rules)),
+        Value::Object(fields) => fields.values_mut().for_each(|field| redact(field, rules)),
+        _ => {}
+    }
+}
+
+/// 64 bytes, the length of a serialized ed25519 keypair
+fn looks_like_keypair(items: &[Value]) -> bool {
+    items.len() == 64
+        && items
+            .iter()
+            .all(|item| item.as_u64().is_some_and(|byte| byte <= u8::MAX as u64))
+}
+
+/// Redact every word of `text`, so URLs and keys quoted inside error
+/// messages and log lines are caught as well
+fn redact_text(text: &str, rules: RedactionRules) -> String {
+    text.split(' ')
+        .map(|word| redact_word(word, rules))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn redact_word(word: &str, rules: RedactionRules) -> String {
+    // Errors quote URLs in parentheses or quotes
+    let start = word.find(|c: char| c.is_ascii_alphanumeric()).unwrap_or(0);
+    let end = word
+        .rfind(|c: char| !matches!(c, ')' | '\'' | '"' | ',' | ':' | ';'))
+        .map_or(0, |end| end + 1)
+        .max(start);
+    let (prefix, rest) = word.split_at(start);
+    let (core, suffix) = rest.split_at(end - start);
+    let core = match redact_url(core) {
+        Some(url) => url,
+        None if rules.hash_pubkeys => match Pubkey::from_str(core) {
+            Ok(pubkey) => hash_pubkey(&pubkey),
+            Err(_) => core.to_string(),
+        },
+        None => core.to_string(),
+    };
+    format!("{}{}{}", prefix, core, suffix)
+}
+
+/// `text` with the http(s) or ws(s) URL it contains cut down to
+/// `scheme://host[:port]`, dropping credentials, path and query, which is
+/// where RPC providers put API keys; `None` when it contains no such URL
+fn redact_url(text: &str) -> Option<String> {
+    let separator = text.find("://")?;
+    let scheme_start = text[..separator]
+        .rfind(|c: char| !c.is_ascii_alphabetic())
+        .map_or(0, |index| index + 1);
+    let (before, url) = text.split_at(scheme_start);
+    let (scheme, rest) = url.split_once("://")?;
+    if !matches!(scheme, "http" | "https" | "ws" | "wss") {
+        return None;
+    }
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    let host = authority.rsplit('@').next().unwrap_or_default();
+    if host.len() == rest.trim_end_matches('/').len() {
+        return Some(text.to_string());
+    }
+    Some(format!("{}{}://{}/<redacted>", before, scheme, host))
+}
+
+/// Stable stand-in for a public key: the same key always hashes the same
+pub fn hash_pubkey(pubkey: &Pubkey) -> String {
+    let hash = hashv(&[b"token-vault-support-bundle", pubkey.as_ref()]);
+    let hex: String = hash.as_ref()[..8]
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    format!("pubkey:{}", hex)
+}
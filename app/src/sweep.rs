@@ -0,0 +1,159 @@
+//! Moving everything at once. `withdraw_all` takes as much as one
+//! withdrawal from the vault may, and `deposit_all` deposits a depositor's
+//! whole token balance. Each reads the balance it moves right before
+//! building the transaction, and sends nothing when there is nothing to
+//! move.
+//!
+//! `withdraw_all` is bounded by the withdrawer's `DepositorPosition` as
+//! well as by the vault as a whole: its tokens less accrued fees, its
+//! recorded `total_deposited` and its per-withdrawal limit. The program
+//! refuses a withdrawal past the position, so with several depositors the
+//! vault-wide figures alone would offer one of them everyone's funds.
+
+use anchor_client::solana_sdk::{program_pack::Pack, pubkey::Pubkey};
+use anchor_spl::associated_token::get_associated_token_address;
+use anchor_spl::token::spl_token::state::Account as TokenAccount;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::dust::{check_withdrawal_limit, WithdrawalQuote};
+use crate::signer::VaultSigner;
+use crate::token_vault::{
+    pda,
+    state::{DepositorPosition, Vault},
+};
+use crate::{logging, Deadline, Payout, TokenVaultClient, TxReceipt, WithdrawOptions};
+
+/// What `withdraw_all` or `deposit_all` moved
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SweepReceipt {
+    /// Base units withdrawn or deposited; 0 when nothing was sent
+    pub amount: u64,
+    /// The transaction, or `None` when there was nothing to move
+    pub receipt: Option<TxReceipt>,
+}
+
+impl SweepReceipt {
+    fn nothing() -> Self {
+        Self {
+            amount: 0,
+            receipt: None,
+        }
+    }
+
+    /// Nothing was moved and no transaction sent
+    pub fn is_empty(&self) -> bool {
+        self.receipt.is_none()
+    }
+}
+
+/// The most one withdrawal from `vault` may take when its token account
+/// holds `balance` and the withdrawer's position is `position`; 0 without
+/// a position. Accrued fees belong to the fee collector and are left
+/// behind. The fee comes out of the amount withdrawn, so every cap applies
+/// to the gross amount as is: grossing a net figure back up through the fee
+/// rate instead can round one unit past the limit. The result passes the
+/// same dust and limit checks as `withdraw`, so dust the vault rejects is 0
+/// rather than a transaction the client would refuse.
+pub fn max_withdrawal(vault: &Vault, balance: u64, position: Option<&DepositorPosition>) -> u64 {
+    let amount = balance
+        .saturating_sub(vault.accrued_fees)
+        .min(vault.total_deposited)
+        .min(vault.withdrawal_limit)
+        .min(position.map_or(0, DepositorPosition::net_deposited));
+    let allowed = WithdrawalQuote::for_vault(vault, amount).is_ok()
+        && check_withdrawal_limit(vault, amount).is_ok();
+    if allowed {
+        amount
+    } else {
+        0
+    }
+}
+
+impl TokenVaultClient {
+    /// Withdraw as much as one withdrawal from the current vault may take
+    /// into the withdrawer's associated token account, up to what their
+    /// position holds. The vault is read from the cluster rather than any
+    /// cache.
+    pub fn withdraw_all(&self, withdrawer: &dyn VaultSigner) -> Result<SweepReceipt> {
+        self.withdraw_all_in(self.current_vault()?, withdrawer)
+    }
+
+    /// Deposit the depositor's whole balance of the current vault's mint,
+    /// leaving `keep` behind in their associated token account
+    pub fn deposit_all(&self, depositor: &dyn VaultSigner, keep: u64) -> Result<SweepReceipt> {
+        self.deposit_all_in(self.current_vault()?, depositor, keep)
+    }
+
+    pub(crate) fn withdraw_all_in(
+        &self,
+        vault: Pubkey,
+        withdrawer: &dyn VaultSigner,
+    ) -> Result<SweepReceipt> {
+        let deadline = self.deadline();
+        self.invalidate(&vault);
+        let (vault_data, _) = self.fetch_vault_state(vault, deadline, true)?;
+        let (vault_token_account, _) =
+            pda::find_vault_token_account_address(&self.program.id(), &vault);
+        let balance = self.token_balance(&vault_token_account, deadline)?;
+        let position = self.fetch_position(vault, withdrawer.pubkey(), deadline)?;
+
+        let amount = max_withdrawal(&vault_data, balance, position.as_ref());
+        if amount == 0 {
+            logging::info!(
+                vault = %logging::log_key(&vault, self.log_redaction),
+                "nothing to withdraw"
+            );
+            return Ok(SweepReceipt::nothing());
+        }
+        let receipt = self.withdraw_in_mint(
+            vault,
+            withdrawer,
+            amount,
+            Some(vault_data.token_mint),
+            Payout::Wallet(withdrawer.pubkey()),
+            WithdrawOptions::default(),
+        )?;
+        Ok(SweepReceipt {
+            amount,
+            receipt: Some(receipt),
+        })
+    }
+
+    pub(crate) fn deposit_all_in(
+        &self,
+        vault: Pubkey,
+        depositor: &dyn VaultSigner,
+        keep: u64,
+    ) -> Result<SweepReceipt> {
+        let deadline = self.deadline();
+        let (vault_data, _) = self.fetch_vault_state(vault, deadline, true)?;
+        let source = get_associated_token_address(&depositor.pubkey(), &vault_data.token_mint);
+        let balance = self.token_balance(&source, deadline)?;
+
+        let amount = balance.saturating_sub(keep);
+        if amount == 0 {
+            logging::info!(
+                vault = %logging::log_key(&vault, self.log_redaction),
+                balance,
+                keep,
+                "nothing to deposit"
+            );
+            return Ok(SweepReceipt::nothing());
+        }
+        let receipt =
+            self.deposit_in_mint(vault, depositor, None, amount, Some(vault_data.token_mint))?;
+        Ok(SweepReceipt {
+            amount,
+            receipt: Some(receipt),
+        })
+    }
+
+    /// Tokens held by `account`; 0 when it does not exist
+    fn token_balance(&self, account: &Pubkey, deadline: Deadline) -> Result<u64> {
+        Ok(match self.fetch_optional_account(account, deadline)? {
+            Some(account) => TokenAccount::unpack(&account.data)?.amount,
+            None => 0,
+        })
+    }
+}
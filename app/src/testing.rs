@@ -0,0 +1,369 @@
+//! An in-memory stand-in for `TokenVaultClient`, for unit tests of code
+//! written against `VaultOps`. No validator, RPC node or funded keypair is
+//! needed.
+//!
+//! ```
+//! # use anchor_client::solana_sdk::{pubkey::Pubkey, signature::Keypair, signer::Signer};
+//! # use token_vault_client::testing::{MockCall, MockVaultClient};
+//! # use std::time::Duration;
+//! # use token_vault_client::{VaultConfig, VaultOps};
+//! let mint = Pubkey::new_unique();
+//! let mut mock = MockVaultClient::new();
+//! mock.add_mint(mint, 6);
+//! let authority = Keypair::new();
+//! let config = VaultConfig::builder()
+//!     .name("treasury")
+//!     .fee_bps(100)
+//!     .timelock(Duration::ZERO)
+//!     .withdrawal_limit(1_000_000)
+//!     .build()?;
+//! let vault = mock.initialize_vault(&authority, mint, &config)?.address;
+//! mock.with_vault(vault);
+//!
+//! let user = Keypair::new();
+//! mock.deposit(&user, 500_000)?;
+//! mock.withdraw(&user, 200_000, None)?;
+//! assert_eq!(mock.get_vault_info()?.total_deposited, 300_000);
+//! assert_eq!(mock.get_vault_info()?.accrued_fees, 2_000);
+//! assert_eq!(
+//!     mock.calls()[2],
+//!     MockCall::Withdraw { withdrawer: user.pubkey(), amount: 200_000, recipient: None }
+//! );
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+//!
+//! The mock applies the program's arithmetic: the reserve carve-out of
+//! deposits, withdrawal fees with the dust policy, and the clamp of
+//! `total_deposited`. It refuses what the client would refuse, with the same
+//! `TokenVaultError`s: no vault set, a vault that does not exist, zero
+//! amounts, dust, a paused vault, a closed deposit window and withdrawals
+//! over the limit. RPC failures, frozen accounts, maintenance windows and
+//! token balances are not modelled.
+
+use anchor_client::solana_sdk::{pubkey::Pubkey, signature::Signature};
+use anyhow::{anyhow, Context, Result};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::accounting::total_deposited_after_withdrawal;
+use crate::dust::{check_withdrawal_limit, ensure_nonzero};
+use crate::lifecycle::lifecycle;
+use crate::pause::check_not_paused;
+use crate::signer::VaultSigner;
+use crate::token_vault::{self, state::Vault};
+use crate::{
+    DepositEvent, DepositQuote, DustMode, FreezeStatus, InitializedVault, OperationKind,
+    TokenVaultError, TxReceipt, VaultConfig, VaultEvent, VaultOps, WithdrawEvent, WithdrawPreview,
+    WithdrawalQuote,
+};
+
+/// Fee the mock reports for every transaction, one signature's worth
+pub const MOCK_TRANSACTION_FEE: u64 = 5_000;
+
+/// A call made on a `MockVaultClient`, recorded whether or not it succeeded
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MockCall {
+    InitializeVault {
+        authority: Pubkey,
+        token_mint: Pubkey,
+        name: String,
+    },
+    Deposit {
+        depositor: Pubkey,
+        amount: u64,
+    },
+    Withdraw {
+        withdrawer: Pubkey,
+        amount: u64,
+        recipient: Option<Pubkey>,
+    },
+    GetVaultInfo,
+    QuoteDeposit {
+        amount: u64,
+    },
+    QuoteWithdrawal {
+        amount: u64,
+    },
+    PreviewWithdraw {
+        amount: u64,
+    },
+}
+
+#[derive(Default)]
+struct MockState {
+    vaults: HashMap<Pubkey, Vault>,
+    mint_decimals: HashMap<Pubkey, u8>,
+    calls: Vec<MockCall>,
+    /// Unix time deposit windows are checked at; the system clock when `None`
+    now: Option<i64>,
+    slot: u64,
+}
+
+/// `VaultOps` over vaults held in memory; see the module docs
+pub struct MockVaultClient {
+    program_id: Pubkey,
+    vault_address: Option<Pubkey>,
+    state: RefCell<MockState>,
+}
+
+impl Default for MockVaultClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MockVaultClient {
+    /// A mock of the deployed program, with no vaults or mints
+    pub fn new() -> Self {
+        Self::with_program_id(token_vault::ID)
+    }
+
+    /// A mock deriving vault addresses under `program_id`
+    pub fn with_program_id(program_id: Pubkey) -> Self {
+        Self {
+            program_id,
+            vault_address: None,
+            state: RefCell::new(MockState::default()),
+        }
+    }
+
+    /// Set the vault the other methods act on, as `TokenVaultClient::with_vault`
+    pub fn with_vault(&mut self, vault_address: Pubkey) -> &mut Self {
+        self.vault_address = Some(vault_address);
+        self
+    }
+
+    /// Make `mint` known with `decimals`; `initialize_vault` refuses mints
+    /// it does not know, as the client does mints that do not exist
+    pub fn add_mint(&self, mint: Pubkey, decimals: u8) {
+        self.state.borrow_mut().mint_decimals.insert(mint, decimals);
+    }
+
+    /// Put `vault` at `address`, replacing any vault there
+    pub fn insert_vault(&self, address: Pubkey, vault: Vault) {
+        self.state.borrow_mut().vaults.insert(address, vault);
+    }
+
+    /// The vault at `address`, if there is one
+    pub fn vault(&self, address: Pubkey) -> Option<Vault> {
+        self.state.borrow().vaults.get(&address).cloned()
+    }
+
+    /// Change the vault at `address` in place, e.g. to pause it
+    pub fn update_vault(&self, address: Pubkey, update: impl FnOnce(&mut Vault)) -> Result<()> {
+        let mut state = self.state.borrow_mut();
+        let vault = state
+            .vaults
+            .get_mut(&address)
+            .ok_or(TokenVaultError::VaultNotFound { vault: address })?;
+        update(vault);
+        Ok(())
+    }
+
+    /// Check deposit windows at `now` instead of the system clock
+    pub fn set_time(&self, now: i64) {
+        self.state.borrow_mut().now = Some(now);
+    }
+
+    /// Every call made so far, in order
+    pub fn calls(&self) -> Vec<MockCall> {
+        self.state.borrow().calls.clone()
+    }
+
+    /// Forget the calls made so far
+    pub fn clear_calls(&self) {
+        self.state.borrow_mut().calls.clear();
+    }
+
+    fn record(&self, call: MockCall) {
+        self.state.borrow_mut().calls.push(call);
+    }
+
+    fn now(&self) -> Result<i64> {
+        match self.state.borrow().now {
+            Some(now) => Ok(now),
+            None => Ok(SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64),
+        }
+    }
+
+    fn current_vault(&self) -> Result<(Pubkey, Vault), TokenVaultError> {
+        let address = self.vault_address.ok_or(TokenVaultError::VaultNotSet)?;
+        let vault = self
+            .vault(address)
+            .ok_or(TokenVaultError::VaultNotFound { vault: address })?;
+        Ok((address, vault))
+    }
+
+    /// A receipt for a transaction landing in the next slot
+    fn receipt(&self, event: VaultEvent) -> TxReceipt {
+        let mut state = self.state.borrow_mut();
+        state.slot += 1;
+        TxReceipt {
+            slot: Some(state.slot),
+            fee_paid: Some(MOCK_TRANSACTION_FEE),
+            events: Some(vec![event]),
+            ..TxReceipt::signature_only(Signature::new_unique())
+        }
+    }
+}
+
+impl VaultOps for MockVaultClient {
+    fn vault_address(&self) -> Option<Pubkey> {
+        self.vault_address
+    }
+
+    fn initialize_vault(
+        &self,
+        authority: &dyn VaultSigner,
+        token_mint: Pubkey,
+        config: &VaultConfig,
+    ) -> Result<InitializedVault> {
+        self.record(MockCall::InitializeVault {
+            authority: authority.pubkey(),
+            token_mint,
+            name: config.name.clone(),
+        });
+        let decimals = *self
+            .state
+            .borrow()
+            .mint_decimals
+            .get(&token_mint)
+            .ok_or_else(|| anyhow!("Failed to fetch mint {}", token_mint))?;
+        let withdrawal_limit = config
+            .withdrawal_limit
+            .to_base_units(decimals)
+            .context("Invalid withdrawal limit")?;
+        let addresses = token_vault::pda::VaultAddresses::derive(
+            &self.program_id,
+            &authority.pubkey(),
+            &token_mint,
+            &config.name,
+        )?;
+        if self.vault(addresses.vault).is_some() {
+            return Err(anyhow!("Vault {} already exists", addresses.vault));
+        }
+
+        self.insert_vault(
+            addresses.vault,
+            Vault {
+                authority: authority.pubkey(),
+                token_mint,
+                fee_collector: config.fee_collector.unwrap_or(authority.pubkey()),
+                fee_percentage: config.fee_bps,
+                withdrawal_timelock: config.timelock,
+                withdrawal_limit,
+                name: config.name.clone(),
+                bump: addresses.vault_bump,
+                immutable: config.immutable,
+                deposit_window_start: config.deposit_window.start,
+                deposit_window_end: config.deposit_window.end,
+                dust_threshold: config.dust_policy.threshold,
+                waive_dust_fee: config.dust_policy.mode == DustMode::WaiveFee,
+                reserve_bps: config.reserve.bps,
+                reserve_timelock: config.reserve.timelock,
+                last_reserve_release: self.now()?,
+                // Counters and flags start at zero
+                ..Vault::default()
+            },
+        );
+        let mut state = self.state.borrow_mut();
+        state.slot += 1;
+        Ok(InitializedVault {
+            address: addresses.vault,
+            receipt: TxReceipt {
+                slot: Some(state.slot),
+                fee_paid: Some(MOCK_TRANSACTION_FEE),
+                ..TxReceipt::signature_only(Signature::new_unique())
+            },
+        })
+    }
+
+    fn deposit(&self, depositor: &dyn VaultSigner, amount: u64) -> Result<TxReceipt> {
+        self.record(MockCall::Deposit {
+            depositor: depositor.pubkey(),
+            amount,
+        });
+        let address = self.vault_address.ok_or(TokenVaultError::VaultNotSet)?;
+        ensure_nonzero("deposit", amount)?;
+        let (_, vault) = self.current_vault()?;
+        let quote = DepositQuote::for_vault(&vault, amount)?;
+        let unfrozen = FreezeStatus {
+            freeze_authority: None,
+            vault_token_account_frozen: false,
+        };
+        lifecycle(&vault, &unfrozen, &[], self.now()?).check(OperationKind::Deposit)?;
+
+        self.update_vault(address, |vault| {
+            vault.total_deposited = vault.total_deposited.saturating_add(quote.credited);
+            vault.total_reserved = vault.total_reserved.saturating_add(quote.reserve);
+        })?;
+        Ok(self.receipt(VaultEvent::Deposit(DepositEvent {
+            vault: address,
+            depositor: depositor.pubkey(),
+            amount,
+            timestamp: Some(self.now()?),
+            memo: None,
+        })))
+    }
+
+    fn withdraw(
+        &self,
+        withdrawer: &dyn VaultSigner,
+        amount: u64,
+        recipient: Option<Pubkey>,
+    ) -> Result<TxReceipt> {
+        self.record(MockCall::Withdraw {
+            withdrawer: withdrawer.pubkey(),
+            amount,
+            recipient,
+        });
+        let (address, vault) = self.current_vault()?;
+        check_not_paused(&vault)?;
+        let quote = WithdrawalQuote::for_vault(&vault, amount)?;
+        check_withdrawal_limit(&vault, amount)?;
+
+        self.update_vault(address, |vault| {
+            let (total_deposited, anomaly) =
+                total_deposited_after_withdrawal(vault.total_deposited, amount);
+            vault.total_deposited = total_deposited;
+            vault.accounting_anomaly |= anomaly;
+            vault.accrued_fees = vault.accrued_fees.saturating_add(quote.fee);
+        })?;
+        Ok(self.receipt(VaultEvent::Withdraw(WithdrawEvent {
+            vault: address,
+            withdrawer: withdrawer.pubkey(),
+            recipient: recipient.unwrap_or_else(|| withdrawer.pubkey()),
+            amount,
+            fee: quote.fee,
+            timestamp: Some(self.now()?),
+            memo: None,
+        })))
+    }
+
+    fn get_vault_info(&self) -> Result<Vault> {
+        self.record(MockCall::GetVaultInfo);
+        Ok(self.current_vault()?.1)
+    }
+
+    fn quote_deposit(&self, amount: u64) -> Result<DepositQuote> {
+        self.record(MockCall::QuoteDeposit { amount });
+        Ok(DepositQuote::for_vault(&self.current_vault()?.1, amount)?)
+    }
+
+    fn quote_withdrawal(&self, amount: u64) -> Result<WithdrawalQuote> {
+        self.record(MockCall::QuoteWithdrawal { amount });
+        Ok(WithdrawalQuote::for_vault(
+            &self.current_vault()?.1,
+            amount,
+        )?)
+    }
+
+    fn preview_withdraw(&self, amount: u64) -> Result<WithdrawPreview> {
+        self.record(MockCall::PreviewWithdraw { amount });
+        Ok(WithdrawPreview::for_vault(
+            &self.current_vault()?.1,
+            amount,
+        )?)
+    }
+}
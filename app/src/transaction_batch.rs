@@ -0,0 +1,318 @@
+//! Several operations composed into one transaction, so they land or fail
+//! together: a withdrawal from one vault and a deposit into another, or a
+//! deposit with a custom instruction ahead of it. Deposits and withdrawals
+//! are resolved and checked exactly as the standalone methods do it, when
+//! the batch is sent.
+
+use anchor_client::solana_sdk::{
+    hash::Hash,
+    instruction::Instruction,
+    packet::PACKET_DATA_SIZE,
+    pubkey::Pubkey,
+    signature::{Keypair, Signature},
+    signer::Signer,
+};
+use anyhow::{anyhow, Result};
+use std::collections::BTreeSet;
+use std::mem;
+
+use crate::signer::{AsSigner, VaultSigner};
+use crate::transaction_version::{compile_message, transaction_size};
+use crate::{
+    memo, Deadline, DepositSource, Operation, Payout, TokenVaultClient, TokenVaultError, TxReceipt,
+    WithdrawOptions,
+};
+
+/// Instructions of one operation, resolved and checked but not yet sent
+pub(crate) struct PreparedOperation {
+    /// The operation policies approve and logs report; none for
+    /// instructions the client did not build
+    pub(crate) operation: Option<Operation>,
+    pub(crate) instructions: Vec<Instruction>,
+    /// Accounts to drop from the cache once sent
+    pub(crate) touched: Vec<Pubkey>,
+}
+
+/// Refuse a transaction of `size` bytes that does not fit in a packet
+pub fn check_transaction_size(size: usize) -> Result<(), TokenVaultError> {
+    if size > PACKET_DATA_SIZE {
+        return Err(TokenVaultError::TransactionTooLarge {
+            size,
+            max: PACKET_DATA_SIZE,
+        });
+    }
+    Ok(())
+}
+
+/// One step of a `TransactionBatch`, resolved when the batch is sent
+#[derive(Debug, Clone)]
+enum BatchStep {
+    Deposit {
+        vault: Option<Pubkey>,
+        depositor: Pubkey,
+        amount: u64,
+    },
+    Withdraw {
+        vault: Option<Pubkey>,
+        withdrawer: Pubkey,
+        amount: u64,
+        recipient: Option<Pubkey>,
+    },
+    Memo {
+        memo: String,
+        signer: Pubkey,
+    },
+    Instruction(Instruction),
+}
+
+/// Operations to send in one transaction, built with `TokenVaultClient::batch`:
+///
+/// ```ignore
+/// client
+///     .batch()
+///     .add_withdraw_from(vault_a, user.pubkey(), 1_000, None)
+///     .add_deposit_to(vault_b, user.pubkey(), 1_000)
+///     .send(&[&user])?;
+/// ```
+///
+/// Steps keep their order. Nothing is fetched or checked until `send`.
+#[must_use = "a batch does nothing until sent"]
+pub struct TransactionBatch<'c> {
+    client: &'c TokenVaultClient,
+    steps: Vec<BatchStep>,
+}
+
+impl TokenVaultClient {
+    /// An empty batch against this client
+    pub fn batch(&self) -> TransactionBatch<'_> {
+        TransactionBatch {
+            client: self,
+            steps: Vec::new(),
+        }
+    }
+
+    /// Send `prepared` in one transaction signed by `signers`, refusing it
+    /// before signing when it would not fit in a packet as the client's
+    /// `TransactionVersion` compiles it
+    pub(crate) fn send_prepared(
+        &self,
+        prepared: Vec<PreparedOperation>,
+        signers: &[&dyn Signer],
+        deadline: Deadline,
+        fee_payer: Option<&Keypair>,
+    ) -> Result<Signature> {
+        let vaults: Vec<Pubkey> = prepared
+            .iter()
+            .filter_map(|prepared| prepared.operation.as_ref()?.vault)
+            .collect();
+        let mut request = self.vault_request(&vaults)?;
+        for instruction in prepared.iter().flat_map(|prepared| &prepared.instructions) {
+            request = request.instruction(instruction.clone());
+        }
+        for signer in signers {
+            request = request.signer(*signer);
+        }
+
+        let payer = fee_payer.map_or_else(|| self.program.payer(), Signer::pubkey);
+        let lookup_tables = self.resolve_lookup_tables(deadline)?;
+        let message = compile_message(
+            &payer,
+            &request.instructions()?,
+            &lookup_tables,
+            Hash::default(),
+        )?;
+        let mut size = transaction_size(&message);
+        if fee_payer.is_none() && self.fee_pool.is_some() {
+            // A payer drawn from the pool is one more key and signature
+            size += mem::size_of::<Pubkey>() + mem::size_of::<Signature>();
+        }
+        check_transaction_size(size)?;
+
+        let operations: Vec<Operation> = prepared
+            .iter()
+            .filter_map(|prepared| prepared.operation.clone())
+            .collect();
+        let signature = self.send_operations(&operations, request, deadline, fee_payer)?;
+        for address in prepared.iter().flat_map(|prepared| &prepared.touched) {
+            self.invalidate(address);
+        }
+        Ok(signature)
+    }
+}
+
+impl<'c> TransactionBatch<'c> {
+    /// Deposit `amount` from `depositor`'s associated token account into
+    /// the client's vault
+    pub fn add_deposit(self, depositor: Pubkey, amount: u64) -> Self {
+        self.step(BatchStep::Deposit {
+            vault: None,
+            depositor,
+            amount,
+        })
+    }
+
+    /// `add_deposit` into `vault`, which need not be the client's vault
+    pub fn add_deposit_to(self, vault: Pubkey, depositor: Pubkey, amount: u64) -> Self {
+        self.step(BatchStep::Deposit {
+            vault: Some(vault),
+            depositor,
+            amount,
+        })
+    }
+
+    /// Withdraw `amount` from the client's vault to `recipient`'s associated
+    /// token account, or `withdrawer`'s own
+    pub fn add_withdraw(self, withdrawer: Pubkey, amount: u64, recipient: Option<Pubkey>) -> Self {
+        self.step(BatchStep::Withdraw {
+            vault: None,
+            withdrawer,
+            amount,
+            recipient,
+        })
+    }
+
+    /// `add_withdraw` from `vault`, which need not be the client's vault
+    pub fn add_withdraw_from(
+        self,
+        vault: Pubkey,
+        withdrawer: Pubkey,
+        amount: u64,
+        recipient: Option<Pubkey>,
+    ) -> Self {
+        self.step(BatchStep::Withdraw {
+            vault: Some(vault),
+            withdrawer,
+            amount,
+            recipient,
+        })
+    }
+
+    /// Attach `memo`, signed by `signer`. The receipt echoes every memo of
+    /// the batch, one per line.
+    pub fn add_memo(self, memo: impl Into<String>, signer: Pubkey) -> Self {
+        self.step(BatchStep::Memo {
+            memo: memo.into(),
+            signer,
+        })
+    }
+
+    /// Add an instruction the client did not build, such as creating an
+    /// account the next step needs. Policies are not consulted on it.
+    pub fn add_instruction(self, instruction: Instruction) -> Self {
+        self.step(BatchStep::Instruction(instruction))
+    }
+
+    fn step(mut self, step: BatchStep) -> Self {
+        self.steps.push(step);
+        self
+    }
+
+    /// Resolve every step and send them in one transaction, signed once by
+    /// each of `signers`. The signers must be exactly the keys the steps
+    /// need besides the client's payer; a key given twice signs once.
+    pub fn send(self, signers: &[&dyn VaultSigner]) -> Result<TxReceipt> {
+        let client = self.client;
+        if self.steps.is_empty() {
+            return Err(anyhow!("The batch has nothing to send"));
+        }
+        // The client's payer signs anyway, and every other key signs once
+        let payer = client.program.payer();
+        let mut given = BTreeSet::new();
+        let mut unique = Vec::new();
+        for signer in signers {
+            let key = signer.pubkey();
+            client.check_signer(&key)?;
+            if key != payer && given.insert(key) {
+                unique.push(AsSigner(*signer));
+            }
+        }
+
+        let deadline = client.deadline();
+        let mut prepared = Vec::with_capacity(self.steps.len());
+        let mut memos = Vec::new();
+        for step in self.steps {
+            prepared.push(match step {
+                BatchStep::Deposit {
+                    vault,
+                    depositor,
+                    amount,
+                } => {
+                    let vault = vault.map_or_else(|| client.current_vault(), Ok)?;
+                    let (prepared, _) = client.prepare_deposit(
+                        vault,
+                        depositor,
+                        DepositSource::Associated,
+                        amount,
+                        None,
+                        None,
+                        deadline,
+                    )?;
+                    prepared
+                }
+                BatchStep::Withdraw {
+                    vault,
+                    withdrawer,
+                    amount,
+                    recipient,
+                } => {
+                    let vault = vault.map_or_else(|| client.current_vault(), Ok)?;
+                    client.prepare_withdraw(
+                        vault,
+                        withdrawer,
+                        amount,
+                        None,
+                        Payout::Wallet(recipient.unwrap_or(withdrawer)),
+                        &WithdrawOptions::default(),
+                        deadline,
+                    )?
+                }
+                BatchStep::Memo { memo, signer } => {
+                    memo::check_memo(&memo)?;
+                    let instruction = memo::memo_instruction(&memo, &signer);
+                    memos.push(memo);
+                    PreparedOperation {
+                        operation: None,
+                        instructions: vec![instruction],
+                        touched: Vec::new(),
+                    }
+                }
+                BatchStep::Instruction(instruction) => PreparedOperation {
+                    operation: None,
+                    touched: instruction
+                        .accounts
+                        .iter()
+                        .filter(|meta| meta.is_writable)
+                        .map(|meta| meta.pubkey)
+                        .collect(),
+                    instructions: vec![instruction],
+                },
+            });
+        }
+
+        // Signing panics on a signer the transaction does not name, and
+        // fails without one it does
+        let required: BTreeSet<Pubkey> = prepared
+            .iter()
+            .flat_map(|prepared| &prepared.instructions)
+            .flat_map(|instruction| &instruction.accounts)
+            .filter(|meta| meta.is_signer && meta.pubkey != payer)
+            .map(|meta| meta.pubkey)
+            .collect();
+        if let Some(missing) = required.difference(&given).next() {
+            return Err(anyhow!("The batch needs a signature from {}", missing));
+        }
+        if let Some(extra) = given.difference(&required).next() {
+            return Err(anyhow!(
+                "{} was given to sign the batch, but none of its steps need it",
+                extra
+            ));
+        }
+
+        let signers: Vec<&dyn Signer> = unique.iter().map(|signer| signer as &dyn Signer).collect();
+        let signature = client.send_prepared(prepared, &signers, deadline, None)?;
+        Ok(TxReceipt {
+            memo: (!memos.is_empty()).then(|| memos.join("\n")),
+            ..client.tx_receipt(signature)
+        })
+    }
+}
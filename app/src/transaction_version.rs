@@ -0,0 +1,209 @@
+//! Versioned transactions. A legacy transaction names every account in
+//! full, 32 bytes each, and a withdrawal that also creates token accounts
+//! and sets a compute budget comes close to the 1232-byte packet limit. A
+//! v0 transaction can instead point into address lookup tables, one byte
+//! per account. The client sends legacy transactions unless told to use v0
+//! with a set of lookup tables; `create_vault_lookup_table` makes one for a
+//! vault's common accounts.
+
+use anchor_client::solana_sdk::{
+    address_lookup_table_account::AddressLookupTableAccount,
+    commitment_config::CommitmentConfig,
+    hash::Hash,
+    instruction::Instruction,
+    message::{v0, Message, VersionedMessage},
+    pubkey::Pubkey,
+    transaction::VersionedTransaction,
+};
+use anchor_spl::associated_token::get_associated_token_address;
+use anyhow::{Context, Result};
+use solana_address_lookup_table_program::{instruction as lookup_table, state::AddressLookupTable};
+
+use crate::request::VaultRequest;
+use crate::signer::{AsSigner, VaultSigner};
+use crate::token_vault::pda;
+use crate::{Deadline, DeadlinePhase, Operation, OperationKind, TokenVaultClient, TxReceipt};
+
+/// Which kind of transaction the client signs and sends
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum TransactionVersion {
+    /// Legacy transactions, naming every account in full
+    #[default]
+    Legacy,
+    /// v0 transactions, drawing accounts from `lookup_tables`. Without any
+    /// table a v0 message saves nothing, so the client sends legacy ones.
+    V0 { lookup_tables: Vec<Pubkey> },
+}
+
+/// A lookup table made by `create_vault_lookup_table`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VaultLookupTable {
+    pub address: Pubkey,
+    /// The accounts it holds, in table order
+    pub addresses: Vec<Pubkey>,
+    pub receipt: TxReceipt,
+}
+
+/// The message of `instructions` paid by `payer`: v0 over `lookup_tables`,
+/// or legacy when there are none
+pub fn compile_message(
+    payer: &Pubkey,
+    instructions: &[Instruction],
+    lookup_tables: &[AddressLookupTableAccount],
+    recent_blockhash: Hash,
+) -> Result<VersionedMessage> {
+    if lookup_tables.is_empty() {
+        return Ok(VersionedMessage::Legacy(Message::new_with_blockhash(
+            instructions,
+            Some(payer),
+            &recent_blockhash,
+        )));
+    }
+    let message = v0::Message::try_compile(payer, instructions, lookup_tables, recent_blockhash)
+        .context("Failed to compile a v0 message")?;
+    Ok(VersionedMessage::V0(message))
+}
+
+/// Bytes a transaction of `message` takes on the wire, with every
+/// signature in place
+pub fn transaction_size(message: &VersionedMessage) -> usize {
+    let signatures = usize::from(message.header().num_required_signatures);
+    // The signature count fits one byte below 128
+    1 + signatures * 64 + message.serialize().len()
+}
+
+impl TokenVaultClient {
+    /// Sign and send `version` transactions from here on. Lookup tables are
+    /// read when first used and then kept, as a table's entries never
+    /// change once written.
+    pub fn with_transaction_version(&mut self, version: TransactionVersion) -> &mut Self {
+        self.transaction_version = version;
+        self.lookup_tables.lock().unwrap().clear();
+        self
+    }
+
+    /// The address lookup table at `address`, with the addresses it holds
+    pub fn get_address_lookup_table(&self, address: Pubkey) -> Result<AddressLookupTableAccount> {
+        self.fetch_lookup_table(address, self.deadline())
+    }
+
+    fn fetch_lookup_table(
+        &self,
+        address: Pubkey,
+        deadline: Deadline,
+    ) -> Result<AddressLookupTableAccount> {
+        let rpc = self.rpc_within(deadline, DeadlinePhase::Fetch)?;
+        let account = rpc
+            .get_account(&address)
+            .with_context(|| format!("Failed to fetch lookup table {}", address))?;
+        let table = AddressLookupTable::deserialize(&account.data)
+            .with_context(|| format!("{} is not an address lookup table", address))?;
+        Ok(AddressLookupTableAccount {
+            key: address,
+            addresses: table.addresses.to_vec(),
+        })
+    }
+
+    /// The lookup tables of the client's `TransactionVersion`; none for
+    /// legacy transactions
+    pub(crate) fn resolve_lookup_tables(
+        &self,
+        deadline: Deadline,
+    ) -> Result<Vec<AddressLookupTableAccount>> {
+        let TransactionVersion::V0 { lookup_tables } = &self.transaction_version else {
+            return Ok(Vec::new());
+        };
+        lookup_tables
+            .iter()
+            .map(|address| {
+                if let Some(table) = self.lookup_tables.lock().unwrap().get(address) {
+                    return Ok(table.clone());
+                }
+                let table = self.fetch_lookup_table(*address, deadline)?;
+                self.lookup_tables
+                    .lock()
+                    .unwrap()
+                    .insert(*address, table.clone());
+                Ok(table)
+            })
+            .collect()
+    }
+
+    /// `request` signed as the client's `TransactionVersion` asks
+    pub(crate) fn sign_request(
+        &self,
+        request: &VaultRequest,
+        deadline: Deadline,
+    ) -> Result<VersionedTransaction> {
+        if self.transaction_version == TransactionVersion::Legacy {
+            return Ok(request.legacy_transaction()?.into());
+        }
+        let lookup_tables = self.resolve_lookup_tables(deadline)?;
+        let blockhash = self
+            .rpc_within(deadline, DeadlinePhase::Send)?
+            .get_latest_blockhash()?;
+        let message = compile_message(
+            &request.payer_key(),
+            &request.instructions()?,
+            &lookup_tables,
+            blockhash,
+        )?;
+        VersionedTransaction::try_new(message, &request.signing_keys())
+            .context("Failed to sign transaction")
+    }
+
+    /// Create an address lookup table holding the current vault's common
+    /// accounts: the vault, its token and reserve token accounts, its mint
+    /// and the fee collector's token account. `authority` owns the table;
+    /// the client's payer pays its rent. A table serves transactions from
+    /// the slot after its creation, once added to `TransactionVersion::V0`.
+    pub fn create_vault_lookup_table(
+        &self,
+        authority: &dyn VaultSigner,
+    ) -> Result<VaultLookupTable> {
+        let vault = self.current_vault()?;
+        self.check_signer(&authority.pubkey())?;
+        let deadline = self.deadline();
+        let vault_data = self.fetch_vault(vault, deadline)?;
+        let program_id = self.program.id();
+        let addresses = vec![
+            vault,
+            pda::find_vault_token_account_address(&program_id, &vault).0,
+            pda::find_reserve_token_account_address(&program_id, &vault).0,
+            vault_data.token_mint,
+            get_associated_token_address(&vault_data.fee_collector, &vault_data.token_mint),
+        ];
+
+        // The table's address derives from a slot the cluster still knows
+        let recent_slot = self
+            .rpc_within(deadline, DeadlinePhase::Fetch)?
+            .get_slot_with_commitment(CommitmentConfig::finalized())?;
+        let payer = self.program.payer();
+        let (create, address) =
+            lookup_table::create_lookup_table(authority.pubkey(), payer, recent_slot);
+        let extend = lookup_table::extend_lookup_table(
+            address,
+            authority.pubkey(),
+            Some(payer),
+            addresses.clone(),
+        );
+        let signer = AsSigner(authority);
+        let request = self
+            .vault_request(&[address])?
+            .instruction(create)
+            .instruction(extend)
+            .signer(&signer);
+        let operation = Operation::new(
+            OperationKind::CreateLookupTable,
+            authority.pubkey(),
+            Some(vault),
+            0,
+        );
+        let signature = self.send_operation(operation, request, deadline)?;
+        Ok(VaultLookupTable {
+            address,
+            addresses,
+            receipt: self.tx_receipt(signature),
+        })
+    }
+}
@@ -0,0 +1,140 @@
+//! What a confirmed deposit, withdrawal or vault creation returns. The
+//! signature is always known; the slot and fee come from one
+//! `getTransaction` call after confirmation, which
+//! `with_receipt_details(false)` skips for callers that cannot spare the
+//! round trip. The same call reads back the events the program logged.
+
+use anchor_client::solana_client::rpc_config::RpcTransactionConfig;
+use anchor_client::solana_sdk::{pubkey::Pubkey, signature::Signature};
+use serde::{Deserialize, Serialize};
+use solana_transaction_status::{EncodedConfirmedTransactionWithStatusMeta, UiTransactionEncoding};
+use std::fmt;
+
+use crate::logging;
+use crate::{events, TokenVaultClient, VaultEvent};
+
+/// A confirmed transaction sent by the client
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TxReceipt {
+    #[serde(with = "crate::serde_utils::signature")]
+    pub signature: Signature,
+    /// Slot the transaction landed in; `None` when not looked up, or when
+    /// the lookup failed
+    pub slot: Option<u64>,
+    /// Fee paid in lamports, under the same conditions
+    pub fee_paid: Option<u64>,
+    /// Submissions it took; more than one when the client's `RetryPolicy`
+    /// resubmitted it
+    #[serde(default = "first_attempt")]
+    pub attempts: u32,
+    /// Deposit and withdraw events the program logged, under the same
+    /// conditions as `slot`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub events: Option<Vec<VaultEvent>>,
+    /// Memo the client attached to the transaction, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memo: Option<String>,
+}
+
+fn first_attempt() -> u32 {
+    1
+}
+
+impl TxReceipt {
+    /// Receipt carrying only the signature
+    pub fn signature_only(signature: Signature) -> Self {
+        Self {
+            signature,
+            slot: None,
+            fee_paid: None,
+            attempts: 1,
+            events: None,
+            memo: None,
+        }
+    }
+
+    /// Receipt of `signature` from its `getTransaction` answer
+    pub fn from_transaction(
+        signature: Signature,
+        transaction: &EncodedConfirmedTransactionWithStatusMeta,
+    ) -> Self {
+        Self {
+            signature,
+            slot: Some(transaction.slot),
+            fee_paid: transaction.transaction.meta.as_ref().map(|meta| meta.fee),
+            attempts: 1,
+            events: None,
+            memo: None,
+        }
+    }
+}
+
+impl fmt::Display for TxReceipt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.signature)?;
+        if let Some(slot) = self.slot {
+            write!(f, " (slot {}", slot)?;
+            if let Some(fee) = self.fee_paid {
+                write!(f, ", fee {} lamports", fee)?;
+            }
+            write!(f, ")")?;
+        }
+        if self.attempts > 1 {
+            write!(f, " after {} attempts", self.attempts)?;
+        }
+        Ok(())
+    }
+}
+
+/// A vault created by `initialize_vault`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InitializedVault {
+    #[serde(with = "crate::serde_utils::pubkey")]
+    pub address: Pubkey,
+    pub receipt: TxReceipt,
+}
+
+impl TokenVaultClient {
+    /// Look up the slot and fee of every transaction the client confirms
+    /// (the default), or return receipts carrying only the signature
+    pub fn with_receipt_details(&mut self, details: bool) -> &mut Self {
+        self.receipt_details = details;
+        self
+    }
+
+    /// Receipt of a transaction the client just confirmed. The transaction
+    /// already landed, so a failed lookup leaves the details out rather
+    /// than failing the call, and is not held to the call's deadline.
+    pub(crate) fn tx_receipt(&self, signature: Signature) -> TxReceipt {
+        TxReceipt {
+            attempts: self.send_attempts.get().unwrap_or(1),
+            ..self.tx_details(signature)
+        }
+    }
+
+    fn tx_details(&self, signature: Signature) -> TxReceipt {
+        if !self.receipt_details {
+            return TxReceipt::signature_only(signature);
+        }
+        let rpc = self.program.rpc();
+        let config = RpcTransactionConfig {
+            encoding: Some(UiTransactionEncoding::Base64),
+            commitment: Some(rpc.commitment()),
+            max_supported_transaction_version: Some(0),
+        };
+        match rpc.get_transaction_with_config(&signature, config) {
+            Ok(transaction) => TxReceipt {
+                events: Some(events::transaction_events(&self.program.id(), &transaction)),
+                ..TxReceipt::from_transaction(signature, &transaction)
+            },
+            Err(err) => {
+                logging::warn!(
+                    signature = %signature,
+                    error = %err,
+                    "transaction details lookup failed"
+                );
+                TxReceipt::signature_only(signature)
+            }
+        }
+    }
+}
@@ -0,0 +1,225 @@
+//! Loading keypairs from wherever a deployment keeps them: a JSON file as
+//! the Solana CLI writes it, an environment variable, a base58 string, raw
+//! bytes or a BIP39 seed phrase. Errors name the source and what is wrong
+//! with it, never the secret itself.
+
+use anchor_client::solana_sdk::derivation_path::DerivationPath;
+use anchor_client::solana_sdk::signature::{
+    keypair_from_seed, keypair_from_seed_and_derivation_path, Keypair, Signer,
+};
+use anyhow::{anyhow, Result};
+use bip39::{Language, Mnemonic, Seed};
+use std::fmt;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+/// Bytes of a keypair: the 32-byte secret followed by the 32-byte public key
+pub const KEYPAIR_LEN: usize = 64;
+
+/// The first account's path, which `solana-keygen --derivation-path` and
+/// most wallets default to
+pub const DEFAULT_DERIVATION_PATH: &str = "m/44'/501'/0'/0'";
+
+/// Word counts a BIP39 mnemonic can have
+const MNEMONIC_WORD_COUNTS: [usize; 5] = [12, 15, 18, 21, 24];
+
+/// Where a keypair comes from
+#[derive(Clone, PartialEq, Eq)]
+pub enum KeypairSource {
+    /// A JSON array of 64 bytes, as `solana-keygen` writes; `~` is expanded
+    File(PathBuf),
+    /// An environment variable holding a base58 string or a JSON byte array
+    Env(String),
+    /// The base58 string of the 64 bytes, as wallets export it
+    Base58(String),
+    /// The 64 bytes themselves
+    Bytes(Vec<u8>),
+}
+
+impl KeypairSource {
+    /// Load the keypair
+    pub fn resolve(&self) -> Result<Keypair> {
+        match self {
+            Self::File(path) => read_keypair(path),
+            Self::Env(var) => load_keypair_from_env(var),
+            Self::Base58(encoded) => keypair_from_base58(encoded),
+            Self::Bytes(bytes) => keypair_from_bytes(bytes),
+        }
+    }
+}
+
+/// Names the file or variable, but not a key given inline
+impl fmt::Debug for KeypairSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::File(path) => f.debug_tuple("File").field(path).finish(),
+            Self::Env(var) => f.debug_tuple("Env").field(var).finish(),
+            Self::Base58(_) => f.write_str("Base58(<redacted>)"),
+            Self::Bytes(_) => f.write_str("Bytes(<redacted>)"),
+        }
+    }
+}
+
+/// Load the keypair file at `keypair_path`, expanding a leading `~`
+pub fn load_keypair(keypair_path: &str) -> Result<Keypair> {
+    read_keypair(Path::new(keypair_path))
+}
+
+fn read_keypair(path: &Path) -> Result<Keypair> {
+    let expanded = PathBuf::from(shellexpand::tilde(&path.to_string_lossy()).as_ref());
+    let contents = fs::read_to_string(&expanded).map_err(|e| match e.kind() {
+        ErrorKind::NotFound => anyhow!(
+            "Failed to read keypair from {}: file not found",
+            path.display()
+        ),
+        _ => anyhow!("Failed to read keypair from {}: {}", path.display(), e),
+    })?;
+    keypair_from_json(&contents)
+        .map_err(|e| anyhow!("Failed to read keypair from {}: {}", path.display(), e))
+}
+
+/// Load a keypair from the environment variable `var`, holding either the
+/// base58 string of its 64 bytes or a JSON array of them
+pub fn load_keypair_from_env(var: &str) -> Result<Keypair> {
+    let value = std::env::var(var).map_err(|e| match e {
+        std::env::VarError::NotPresent => anyhow!("Environment variable {} is not set", var),
+        std::env::VarError::NotUnicode(_) => {
+            anyhow!("Environment variable {} is not valid UTF-8", var)
+        }
+    })?;
+    let value = value.trim();
+    let keypair = if value.starts_with('[') {
+        keypair_from_json(value)
+    } else {
+        keypair_from_base58(value)
+    };
+    keypair.map_err(|e| anyhow!("Keypair in environment variable {}: {}", var, e))
+}
+
+/// A keypair from the base58 string of its 64 bytes
+pub fn keypair_from_base58(encoded: &str) -> Result<Keypair> {
+    // The decoder's errors quote the offending character, a piece of the secret
+    let bytes = bs58::decode(encoded.trim())
+        .into_vec()
+        .map_err(|_| anyhow!("Keypair is not valid base58"))?;
+    keypair_from_bytes(&bytes)
+}
+
+/// A keypair from its 64 bytes: the secret, then the public key it derives
+pub fn keypair_from_bytes(bytes: &[u8]) -> Result<Keypair> {
+    if bytes.len() != KEYPAIR_LEN {
+        return Err(anyhow!(
+            "Keypair must be {} bytes, got {}",
+            KEYPAIR_LEN,
+            bytes.len()
+        ));
+    }
+    // The public half is checked rather than taken on trust; a wrong one
+    // would sign transactions that fail verification
+    let keypair = keypair_from_seed(&bytes[..32])
+        .map_err(|_| anyhow!("Keypair bytes are not a valid keypair"))?;
+    if keypair.pubkey().as_ref() != &bytes[32..] {
+        return Err(anyhow!(
+            "Keypair's public key does not match its secret key"
+        ));
+    }
+    Ok(keypair)
+}
+
+/// A keypair from a JSON array of its 64 bytes
+fn keypair_from_json(json: &str) -> Result<Keypair> {
+    // serde_json's messages quote the unexpected value, so only its
+    // position is passed on
+    let bytes: Vec<u8> = serde_json::from_str(json).map_err(|e| {
+        anyhow!(
+            "malformed JSON at line {} column {}; expected an array of {} bytes",
+            e.line(),
+            e.column(),
+            KEYPAIR_LEN
+        )
+    })?;
+    keypair_from_bytes(&bytes)
+}
+
+/// The keypair a BIP39 mnemonic derives at `derivation_path`, by default
+/// `m/44'/501'/0'/0'`, with SLIP-0010 ed25519 derivation; the same keypair
+/// `solana-keygen recover 'prompt://?full-path=...'` and wallets recover.
+/// `passphrase` is the optional BIP39 passphrase, not a wallet password.
+pub fn keypair_from_mnemonic(
+    phrase: &str,
+    passphrase: Option<&str>,
+    derivation_path: Option<&str>,
+) -> Result<Keypair> {
+    let path = parse_derivation_path(derivation_path.unwrap_or(DEFAULT_DERIVATION_PATH))?;
+    let words: Vec<String> = phrase.split_whitespace().map(str::to_lowercase).collect();
+    if !MNEMONIC_WORD_COUNTS.contains(&words.len()) {
+        return Err(anyhow!(
+            "Mnemonic has {} words; expected 12, 15, 18, 21 or 24",
+            words.len()
+        ));
+    }
+    // Positions, not words: the words are the secret
+    let wordmap = Language::English.wordmap();
+    if let Some(position) = words
+        .iter()
+        .position(|word| wordmap.get_bits(word).is_err())
+    {
+        return Err(anyhow!(
+            "Mnemonic word {} is not in the BIP39 English word list",
+            position + 1
+        ));
+    }
+    let mnemonic = Mnemonic::from_phrase(&words.join(" "), Language::English).map_err(|e| {
+        match e.downcast_ref::<bip39::ErrorKind>() {
+            Some(bip39::ErrorKind::InvalidChecksum) => {
+                anyhow!("Mnemonic checksum does not match; a word is wrong or out of order")
+            }
+            _ => anyhow!("Mnemonic is not a valid BIP39 phrase"),
+        }
+    })?;
+    let seed = Seed::new(&mnemonic, passphrase.unwrap_or(""));
+    keypair_from_seed_and_derivation_path(seed.as_bytes(), Some(path))
+        .map_err(|e| anyhow!("Cannot derive keypair from mnemonic: {}", e))
+}
+
+/// Parse a derivation path such as `m/44'/501'/0'/0'`. Ed25519 keys only
+/// derive hardened children, so every index must be marked with `'` or `h`.
+pub fn parse_derivation_path(path: &str) -> Result<DerivationPath> {
+    let invalid = |reason: String| anyhow!("Invalid derivation path '{}': {}", path, reason);
+    let mut components = path.trim().split('/');
+    if components.next() != Some("m") {
+        return Err(invalid("expected it to start with m/".to_string()));
+    }
+    let mut indexes = Vec::new();
+    for component in components {
+        if component.is_empty() {
+            return Err(invalid("empty index".to_string()));
+        }
+        let Some(index) = component
+            .strip_suffix('\'')
+            .or_else(|| component.strip_suffix('h'))
+        else {
+            return Err(invalid(format!(
+                "index '{}' is not hardened; ed25519 derivation needs every index \
+                 hardened, as in {}'",
+                component, component
+            )));
+        };
+        let index: u32 = index
+            .parse()
+            .map_err(|_| invalid(format!("'{}' is not an index", index)))?;
+        if index >= 1 << 31 {
+            return Err(invalid(format!(
+                "index {} is out of range; hardened indexes are below 2^31",
+                index
+            )));
+        }
+        indexes.push(format!("{}'", index));
+    }
+    if indexes.is_empty() {
+        return Err(invalid("expected at least one index".to_string()));
+    }
+    let canonical = format!("m/{}", indexes.join("/"));
+    DerivationPath::from_absolute_path_str(&canonical).map_err(|e| invalid(e.to_string()))
+}
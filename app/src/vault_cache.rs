@@ -0,0 +1,152 @@
+//! Decoded vault accounts kept between calls. Every deposit and withdrawal
+//! reads its vault before building the transaction, and a vault's mint,
+//! authority and fee settings rarely change, so a burst of operations can
+//! share one read. Entries expire after `CacheConfig::ttl`, and the client
+//! drops a vault's entry after each of its own mutations of that vault.
+//!
+//! Unlike `RpcCache`, which keeps raw accounts for the read methods, this
+//! cache sits under every pre-check. It is off by default.
+
+use anchor_client::solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey};
+use anyhow::Result;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::token_vault::state::Vault;
+use crate::{logging, CachedKind, Provenance, TokenVaultClient};
+
+/// Whether and for how long the client reuses a decoded vault
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CacheConfig {
+    /// How long an entry is served after it was read
+    pub ttl: Duration,
+    pub enabled: bool,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            ttl: Duration::from_secs(2),
+            enabled: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct CachedVault {
+    vault: Vault,
+    provenance: Provenance,
+    fetched_at: Instant,
+}
+
+/// Vaults keyed by address, each with the slot and commitment it was read
+/// at. Safe to share between threads.
+#[derive(Debug, Default)]
+pub struct VaultCache {
+    config: CacheConfig,
+    entries: Mutex<HashMap<Pubkey, CachedVault>>,
+}
+
+impl VaultCache {
+    pub fn new(config: CacheConfig) -> Self {
+        Self {
+            config,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn config(&self) -> CacheConfig {
+        self.config
+    }
+
+    /// The cached `vault`, if it was read at `commitment` within the TTL
+    pub fn get(&self, vault: &Pubkey, commitment: CommitmentConfig) -> Option<(Vault, Provenance)> {
+        if !self.config.enabled {
+            return None;
+        }
+        let entries = self.entries.lock().unwrap();
+        let outcome = match entries.get(vault) {
+            Some(entry) if entry.provenance.commitment != commitment.commitment => "miss",
+            Some(entry) if entry.fetched_at.elapsed() < self.config.ttl => {
+                logging::log_cache_lookup(vault, CachedKind::Vault, "hit");
+                return Some((entry.vault.clone(), entry.provenance));
+            }
+            Some(_) => "stale",
+            None => "miss",
+        };
+        logging::log_cache_lookup(vault, CachedKind::Vault, outcome);
+        None
+    }
+
+    /// Keep `data` for `vault`, unless an entry read at a later slot is
+    /// already cached
+    pub fn store(&self, vault: Pubkey, data: &Vault, provenance: Provenance) {
+        if !self.config.enabled {
+            return;
+        }
+        let mut entries = self.entries.lock().unwrap();
+        match entries.get(&vault) {
+            Some(existing)
+                if existing.provenance.commitment == provenance.commitment
+                    && existing.provenance.slot > provenance.slot => {}
+            _ => {
+                entries.insert(
+                    vault,
+                    CachedVault {
+                        vault: data.clone(),
+                        provenance,
+                        fetched_at: Instant::now(),
+                    },
+                );
+            }
+        }
+    }
+
+    pub fn invalidate(&self, vault: &Pubkey) {
+        self.entries.lock().unwrap().remove(vault);
+    }
+
+    pub fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+
+    /// Vaults currently cached, fresh or not
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl TokenVaultClient {
+    /// Reuse decoded vaults as `config` says; replaces the cache and
+    /// everything in it
+    pub fn with_vault_cache(&mut self, config: CacheConfig) -> &mut Self {
+        self.vault_cache = Arc::new(VaultCache::new(config));
+        self
+    }
+
+    /// The client's vault cache
+    pub fn vault_cache(&self) -> &VaultCache {
+        &self.vault_cache
+    }
+
+    /// Forget what the client has cached of the current vault, so the next
+    /// call reads it again
+    pub fn refresh_vault(&self) -> Result<()> {
+        let vault = self.current_vault()?;
+        self.invalidate(&vault);
+        Ok(())
+    }
+
+    /// `get_vault_info` read from the cluster rather than any cache, for
+    /// balances such as `total_deposited` that other clients change. The
+    /// read replaces the cached vault.
+    pub fn get_vault_info_fresh(&self) -> Result<Vault> {
+        self.refresh_vault()?;
+        self.fetch_vault(self.current_vault()?, self.deadline())
+    }
+}
@@ -0,0 +1,354 @@
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use anyhow::{anyhow, Result};
+use std::marker::PhantomData;
+use std::time::Duration;
+
+use crate::dust::BPS_DENOMINATOR;
+use crate::token_vault::pda::MAX_VAULT_NAME_LEN;
+use crate::{DepositWindow, DustPolicy, ReservePolicy};
+
+/// Typestate marker: a required field has not been given yet
+pub struct Missing;
+
+/// Typestate marker: a required field has been given
+pub struct Set;
+
+/// Withdrawal cap, either in base units or as a decimal amount of whole tokens
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WithdrawalLimit {
+    BaseUnits(u64),
+    /// Converted with the mint's decimals when the vault is created
+    Ui(String),
+}
+
+impl WithdrawalLimit {
+    /// The limit in base units of a mint with `decimals` decimals
+    pub fn to_base_units(&self, decimals: u8) -> Result<u64> {
+        match self {
+            Self::BaseUnits(amount) => Ok(*amount),
+            Self::Ui(amount) => ui_to_base_units(amount, decimals),
+        }
+    }
+}
+
+/// Validated parameters of a new vault, produced by `VaultConfigBuilder::build`
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct VaultConfig {
+    pub name: String,
+    pub fee_bps: u16,
+    /// Seconds between a deposit and its withdrawal
+    pub timelock: i64,
+    pub withdrawal_limit: WithdrawalLimit,
+    /// Receives withdrawal fees; the authority when `None`
+    pub fee_collector: Option<Pubkey>,
+    pub immutable: bool,
+    pub deposit_window: DepositWindow,
+    pub dust_policy: DustPolicy,
+    pub reserve: ReservePolicy,
+    /// Create the vault even if a third party can freeze the mint's tokens,
+    /// when the client enforces a strict mint policy
+    pub acknowledge_freezable_mint: bool,
+}
+
+impl VaultConfig {
+    pub fn builder() -> VaultConfigBuilder {
+        VaultConfigBuilder::new()
+    }
+}
+
+/// Builds a `VaultConfig` by field name instead of position.
+///
+/// The name, fee, timelock and withdrawal limit are required; `build` only
+/// exists once all four are set:
+///
+/// ```
+/// # use std::time::Duration;
+/// # use token_vault_client::VaultConfig;
+/// let config = VaultConfig::builder()
+///     .name("treasury")
+///     .fee_bps(100)
+///     .timelock(Duration::from_secs(86_400))
+///     .withdrawal_limit_ui("1000")
+///     .build()
+///     .unwrap();
+/// ```
+///
+/// ```compile_fail
+/// # use std::time::Duration;
+/// # use token_vault_client::VaultConfig;
+/// // No withdrawal limit
+/// let config = VaultConfig::builder()
+///     .name("treasury")
+///     .fee_bps(100)
+///     .timelock(Duration::from_secs(86_400))
+///     .build();
+/// ```
+///
+/// ```compile_fail
+/// # use token_vault_client::VaultConfig;
+/// // No timelock
+/// let config = VaultConfig::builder()
+///     .name("treasury")
+///     .fee_bps(100)
+///     .withdrawal_limit(1_000_000_000)
+///     .build();
+/// ```
+///
+/// ```compile_fail
+/// # use std::time::Duration;
+/// # use token_vault_client::VaultConfig;
+/// // No name
+/// let config = VaultConfig::builder()
+///     .fee_bps(100)
+///     .timelock(Duration::from_secs(86_400))
+///     .withdrawal_limit(1_000_000_000)
+///     .build();
+/// ```
+///
+/// ```compile_fail
+/// # use std::time::Duration;
+/// # use token_vault_client::VaultConfig;
+/// // No fee
+/// let config = VaultConfig::builder()
+///     .name("treasury")
+///     .timelock(Duration::from_secs(86_400))
+///     .withdrawal_limit(1_000_000_000)
+///     .build();
+/// ```
+#[derive(Debug, Clone)]
+pub struct VaultConfigBuilder<Name = Missing, Fee = Missing, Timelock = Missing, Limit = Missing> {
+    name: String,
+    fee_bps: u16,
+    timelock: Duration,
+    withdrawal_limit: Option<WithdrawalLimit>,
+    fee_collector: Option<Pubkey>,
+    immutable: bool,
+    deposit_window: DepositWindow,
+    dust_policy: DustPolicy,
+    reserve: ReservePolicy,
+    acknowledge_freezable_mint: bool,
+    state: PhantomData<(Name, Fee, Timelock, Limit)>,
+}
+
+impl VaultConfigBuilder {
+    pub fn new() -> Self {
+        Self {
+            name: String::new(),
+            fee_bps: 0,
+            timelock: Duration::ZERO,
+            withdrawal_limit: None,
+            fee_collector: None,
+            immutable: false,
+            deposit_window: DepositWindow::UNRESTRICTED,
+            dust_policy: DustPolicy::DISABLED,
+            reserve: ReservePolicy::NONE,
+            acknowledge_freezable_mint: false,
+            state: PhantomData,
+        }
+    }
+}
+
+impl Default for VaultConfigBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<N, F, T, L> VaultConfigBuilder<N, F, T, L> {
+    /// The same fields under different typestate markers
+    fn mark<N2, F2, T2, L2>(self) -> VaultConfigBuilder<N2, F2, T2, L2> {
+        VaultConfigBuilder {
+            name: self.name,
+            fee_bps: self.fee_bps,
+            timelock: self.timelock,
+            withdrawal_limit: self.withdrawal_limit,
+            fee_collector: self.fee_collector,
+            immutable: self.immutable,
+            deposit_window: self.deposit_window,
+            dust_policy: self.dust_policy,
+            reserve: self.reserve,
+            acknowledge_freezable_mint: self.acknowledge_freezable_mint,
+            state: PhantomData,
+        }
+    }
+
+    /// Account that receives withdrawal fees; defaults to the authority
+    pub fn fee_collector(mut self, fee_collector: Pubkey) -> Self {
+        self.fee_collector = Some(fee_collector);
+        self
+    }
+
+    /// Never allow the configuration, pause state or authority to change.
+    /// This is irreversible.
+    pub fn immutable(mut self, immutable: bool) -> Self {
+        self.immutable = immutable;
+        self
+    }
+
+    /// Only accept deposits inside `window`; unrestricted by default
+    pub fn deposit_window(mut self, window: DepositWindow) -> Self {
+        self.deposit_window = window;
+        self
+    }
+
+    /// How withdrawals below a threshold are handled; disabled by default
+    pub fn dust_policy(mut self, policy: DustPolicy) -> Self {
+        self.dust_policy = policy;
+        self
+    }
+
+    /// Share of deposits set aside in the insurance reserve; none by default
+    pub fn reserve(mut self, reserve: ReservePolicy) -> Self {
+        self.reserve = reserve;
+        self
+    }
+
+    /// Accept that the mint's freeze authority can freeze the vault's
+    /// tokens; required under a strict mint policy
+    pub fn acknowledge_freezable_mint(mut self, acknowledged: bool) -> Self {
+        self.acknowledge_freezable_mint = acknowledged;
+        self
+    }
+}
+
+impl<F, T, L> VaultConfigBuilder<Missing, F, T, L> {
+    /// Vault name, at most `MAX_VAULT_NAME_LEN` bytes of UTF-8
+    pub fn name(mut self, name: impl Into<String>) -> VaultConfigBuilder<Set, F, T, L> {
+        self.name = name.into();
+        self.mark()
+    }
+}
+
+impl<N, T, L> VaultConfigBuilder<N, Missing, T, L> {
+    /// Withdrawal fee in basis points; 100 is 1%
+    pub fn fee_bps(mut self, fee_bps: u16) -> VaultConfigBuilder<N, Set, T, L> {
+        self.fee_bps = fee_bps;
+        self.mark()
+    }
+}
+
+impl<N, F, L> VaultConfigBuilder<N, F, Missing, L> {
+    /// How long deposits stay locked before they can be withdrawn
+    pub fn timelock(mut self, timelock: Duration) -> VaultConfigBuilder<N, F, Set, L> {
+        self.timelock = timelock;
+        self.mark()
+    }
+}
+
+impl<N, F, T> VaultConfigBuilder<N, F, T, Missing> {
+    /// Maximum amount per withdrawal, in base units
+    pub fn withdrawal_limit(mut self, base_units: u64) -> VaultConfigBuilder<N, F, T, Set> {
+        self.withdrawal_limit = Some(WithdrawalLimit::BaseUnits(base_units));
+        self.mark()
+    }
+
+    /// Maximum amount per withdrawal in whole tokens, e.g. `"1000.5"`;
+    /// converted with the mint's decimals when the vault is created
+    pub fn withdrawal_limit_ui(mut self, amount: &str) -> VaultConfigBuilder<N, F, T, Set> {
+        self.withdrawal_limit = Some(WithdrawalLimit::Ui(amount.trim().to_string()));
+        self.mark()
+    }
+}
+
+impl VaultConfigBuilder<Set, Set, Set, Set> {
+    /// Check every field and produce the config
+    pub fn build(self) -> Result<VaultConfig> {
+        if self.name.is_empty() {
+            return Err(anyhow!("Vault name cannot be empty"));
+        }
+        // Names are used verbatim as a PDA seed, which is capped at 32 bytes
+        if self.name.len() > MAX_VAULT_NAME_LEN {
+            return Err(anyhow!(
+                "Vault name is {} bytes; at most {} bytes of UTF-8 are allowed",
+                self.name.len(),
+                MAX_VAULT_NAME_LEN
+            ));
+        }
+        if self.fee_bps as u64 > BPS_DENOMINATOR {
+            return Err(anyhow!(
+                "Fee of {} bps exceeds {} bps",
+                self.fee_bps,
+                BPS_DENOMINATOR
+            ));
+        }
+        let timelock = i64::try_from(self.timelock.as_secs())
+            .map_err(|_| anyhow!("Timelock of {:?} is too long", self.timelock))?;
+        let withdrawal_limit = self.withdrawal_limit.expect("set by typestate");
+        if let WithdrawalLimit::Ui(amount) = &withdrawal_limit {
+            // Decimals are checked against the mint when the vault is created
+            split_decimal(amount).map_err(|err| anyhow!("Invalid withdrawal limit: {}", err))?;
+        }
+
+        Ok(VaultConfig {
+            name: self.name,
+            fee_bps: self.fee_bps,
+            timelock,
+            withdrawal_limit,
+            fee_collector: self.fee_collector,
+            immutable: self.immutable,
+            deposit_window: DepositWindow::new(self.deposit_window.start, self.deposit_window.end)?,
+            dust_policy: self.dust_policy,
+            reserve: ReservePolicy::new(self.reserve.bps, self.reserve.timelock)?,
+            acknowledge_freezable_mint: self.acknowledge_freezable_mint,
+        })
+    }
+}
+
+/// Most decimals a mint can have for whole tokens to convert: one token
+/// of a mint with more is over `u64::MAX` base units
+pub const MAX_UI_DECIMALS: u8 = 19;
+
+/// Convert a decimal amount of whole tokens to base units of a mint with
+/// `decimals` decimals, refusing precision the mint cannot represent. Zero
+/// is zero at any decimals; other amounts need at most `MAX_UI_DECIMALS`.
+pub fn ui_to_base_units(amount: &str, decimals: u8) -> Result<u64> {
+    let (whole, fraction) = split_decimal(amount)?;
+    let fraction = fraction.trim_end_matches('0');
+    if whole.bytes().all(|b| b == b'0') && fraction.is_empty() {
+        return Ok(0);
+    }
+    if decimals > MAX_UI_DECIMALS {
+        return Err(anyhow!(
+            "Mint decimals {} are out of range; amounts convert for at most {}",
+            decimals,
+            MAX_UI_DECIMALS
+        ));
+    }
+    if fraction.len() > decimals as usize {
+        return Err(anyhow!(
+            "'{}' has more than {} decimal places",
+            amount,
+            decimals
+        ));
+    }
+
+    let overflow = || anyhow!("'{}' is too large", amount);
+    let pow10 = |exp: usize| 10u64.checked_pow(exp as u32).ok_or_else(overflow);
+    let whole: u64 = match whole {
+        "" => 0,
+        digits => digits.parse().map_err(|_| overflow())?,
+    };
+    let fraction_units = match fraction {
+        "" => 0,
+        digits => digits
+            .parse::<u64>()
+            .map_err(|_| overflow())?
+            .checked_mul(pow10(decimals as usize - digits.len())?)
+            .ok_or_else(overflow)?,
+    };
+    whole
+        .checked_mul(pow10(decimals as usize)?)
+        .and_then(|units| units.checked_add(fraction_units))
+        .ok_or_else(overflow)
+}
+
+/// The whole and fractional digits of a plain decimal number like `1000.25`
+pub(crate) fn split_decimal(amount: &str) -> Result<(&str, &str)> {
+    let (whole, fraction) = amount.split_once('.').unwrap_or((amount, ""));
+    let is_digits = |part: &str| part.bytes().all(|b| b.is_ascii_digit());
+    if (whole.is_empty() && fraction.is_empty()) || !is_digits(whole) || !is_digits(fraction) {
+        return Err(anyhow!("'{}' is not a decimal amount", amount));
+    }
+    Ok((whole, fraction))
+}
@@ -0,0 +1,189 @@
+//! A vault bound to a client at the type level. `with_vault` stores the
+//! vault on the client, so a call made before it fails at run time with
+//! `VaultNotSet`, and every user of the client works on the same vault.
+//! `client.vault(address)` instead returns a `VaultHandle` for that vault
+//! alone; handles for different vaults can be used side by side, from as
+//! many threads as share the client.
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use anyhow::Result;
+use std::sync::Mutex;
+
+use crate::signer::VaultSigner;
+use crate::token_vault::{
+    pda,
+    state::{DepositorPosition, Vault},
+};
+use crate::{
+    MintAmount, Payout, SweepReceipt, TokenVaultClient, TxReceipt, VaultSnapshot, WithdrawOptions,
+};
+
+/// One vault of a client, made with `TokenVaultClient::vault`. The vault's
+/// state is read once and kept for the life of the handle; `refresh` reads
+/// it again, and the handle's own deposits and withdrawals drop it.
+pub struct VaultHandle<'c> {
+    client: &'c TokenVaultClient,
+    address: Pubkey,
+    token_account: Pubkey,
+    state: Mutex<Option<Vault>>,
+}
+
+impl TokenVaultClient {
+    /// A handle on the vault at `address`. Nothing is read until a method
+    /// of the handle needs it.
+    pub fn vault(&self, address: Pubkey) -> VaultHandle<'_> {
+        let (token_account, _) =
+            pda::find_vault_token_account_address(&self.program.id(), &address);
+        VaultHandle {
+            client: self,
+            address,
+            token_account,
+            state: Mutex::new(None),
+        }
+    }
+}
+
+impl<'c> VaultHandle<'c> {
+    pub fn address(&self) -> Pubkey {
+        self.address
+    }
+
+    /// The vault's token account
+    pub fn token_account(&self) -> Pubkey {
+        self.token_account
+    }
+
+    pub fn client(&self) -> &'c TokenVaultClient {
+        self.client
+    }
+
+    /// The vault's state, read on first use and then kept
+    pub fn info(&self) -> Result<Vault> {
+        if let Some(state) = self.cached() {
+            return Ok(state);
+        }
+        let state = self
+            .client
+            .fetch_vault(self.address, self.client.deadline())?;
+        *self.state.lock().unwrap() = Some(state.clone());
+        Ok(state)
+    }
+
+    /// The state `info` would return without reading, if it was read
+    pub fn cached(&self) -> Option<Vault> {
+        self.state.lock().unwrap().clone()
+    }
+
+    /// Read the vault from the cluster again, bypassing the handle's state
+    /// and the client's caches
+    pub fn refresh(&self) -> Result<Vault> {
+        self.forget();
+        self.client.invalidate(&self.address);
+        self.info()
+    }
+
+    fn forget(&self) {
+        self.state.lock().unwrap().take();
+    }
+
+    /// The live balances of the vault and its reserve
+    pub fn snapshot(&self) -> Result<VaultSnapshot> {
+        let state = self.info()?;
+        self.client
+            .snapshot_vault(self.address, &state, self.client.deadline())
+    }
+
+    /// Everything the vault holds: its token account plus its insurance
+    /// reserve
+    pub fn balance(&self) -> Result<MintAmount> {
+        Ok(self.snapshot()?.total())
+    }
+
+    /// The position of `depositor` in this vault; `None` before their first
+    /// deposit. Always read from the cluster.
+    pub fn position(&self, depositor: Pubkey) -> Result<Option<DepositorPosition>> {
+        self.client
+            .fetch_position(self.address, depositor, self.client.deadline())
+    }
+
+    /// Every position in this vault with its address, by depositor
+    pub fn positions(&self) -> Result<Vec<(Pubkey, DepositorPosition)>> {
+        self.client.scan_positions(self.address)
+    }
+
+    /// `TokenVaultClient::deposit` into this vault
+    pub fn deposit(&self, depositor: &dyn VaultSigner, amount: u64) -> Result<TxReceipt> {
+        let receipt = self
+            .client
+            .deposit_in_mint(self.address, depositor, None, amount, None);
+        self.forget();
+        receipt
+    }
+
+    /// `TokenVaultClient::deposit_from` into this vault
+    pub fn deposit_from(
+        &self,
+        depositor: &dyn VaultSigner,
+        source: Pubkey,
+        amount: u64,
+    ) -> Result<TxReceipt> {
+        let receipt =
+            self.client
+                .deposit_in_mint(self.address, depositor, Some(source), amount, None);
+        self.forget();
+        receipt
+    }
+
+    /// `TokenVaultClient::withdraw` from this vault
+    pub fn withdraw(
+        &self,
+        withdrawer: &dyn VaultSigner,
+        amount: u64,
+        recipient: Option<Pubkey>,
+    ) -> Result<TxReceipt> {
+        let recipient = recipient.unwrap_or_else(|| withdrawer.pubkey());
+        self.withdraw_with(withdrawer, amount, Payout::Wallet(recipient))
+    }
+
+    /// `TokenVaultClient::withdraw_to` from this vault
+    pub fn withdraw_to(
+        &self,
+        withdrawer: &dyn VaultSigner,
+        destination: Pubkey,
+        amount: u64,
+    ) -> Result<TxReceipt> {
+        self.withdraw_with(withdrawer, amount, Payout::TokenAccount(destination))
+    }
+
+    /// `TokenVaultClient::deposit_all` into this vault
+    pub fn deposit_all(&self, depositor: &dyn VaultSigner, keep: u64) -> Result<SweepReceipt> {
+        let receipt = self.client.deposit_all_in(self.address, depositor, keep);
+        self.forget();
+        receipt
+    }
+
+    /// `TokenVaultClient::withdraw_all` from this vault
+    pub fn withdraw_all(&self, withdrawer: &dyn VaultSigner) -> Result<SweepReceipt> {
+        let receipt = self.client.withdraw_all_in(self.address, withdrawer);
+        self.forget();
+        receipt
+    }
+
+    fn withdraw_with(
+        &self,
+        withdrawer: &dyn VaultSigner,
+        amount: u64,
+        payout: Payout,
+    ) -> Result<TxReceipt> {
+        let receipt = self.client.withdraw_in_mint(
+            self.address,
+            withdrawer,
+            amount,
+            None,
+            payout,
+            WithdrawOptions::default(),
+        );
+        self.forget();
+        receipt
+    }
+}
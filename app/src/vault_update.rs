@@ -0,0 +1,252 @@
+//! Changing a vault's fee, timelock and withdrawal limit after creation,
+//! and handing its authority or fees to other keys. `plan_update` and
+//! `plan_set_fee_collector` preview the same changes without sending them.
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::dust::BPS_DENOMINATOR;
+use crate::logging;
+use crate::signer::{AsSigner, VaultSigner};
+use crate::token_vault::{accounts, instruction, state::Vault};
+use crate::{
+    ActionReceipt, AdminState, CheckedAgainst, ConfigChanges, Deadline, Operation, OperationKind,
+    TokenVaultClient, VaultChange,
+};
+
+/// Settings `update_vault_config` changes; `None` leaves one as it is
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UpdateVaultParams {
+    pub fee_percentage: Option<u16>,
+    pub withdrawal_timelock: Option<i64>,
+    pub withdrawal_limit: Option<u64>,
+}
+
+impl UpdateVaultParams {
+    /// True when no setting would change
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Fail on a setting the program would reject
+    pub fn check(&self) -> Result<()> {
+        if let Some(fee_percentage) = self.fee_percentage {
+            if fee_percentage as u64 > BPS_DENOMINATOR {
+                return Err(anyhow!(
+                    "Fee percentage {} exceeds {} basis points",
+                    fee_percentage,
+                    BPS_DENOMINATOR
+                ));
+            }
+        }
+        if self
+            .withdrawal_timelock
+            .map_or(false, |timelock| timelock < 0)
+        {
+            return Err(anyhow!("Withdrawal timelock cannot be negative"));
+        }
+        Ok(())
+    }
+
+    /// `vault`'s settings with these applied
+    pub fn applied_to(&self, vault: &Vault) -> AdminState {
+        AdminState::Config {
+            fee_percentage: self.fee_percentage.unwrap_or(vault.fee_percentage),
+            withdrawal_timelock: self
+                .withdrawal_timelock
+                .unwrap_or(vault.withdrawal_timelock),
+            withdrawal_limit: self.withdrawal_limit.unwrap_or(vault.withdrawal_limit),
+        }
+    }
+}
+
+impl From<&ConfigChanges> for UpdateVaultParams {
+    fn from(changes: &ConfigChanges) -> Self {
+        Self {
+            fee_percentage: changes.fee_percentage,
+            withdrawal_timelock: changes.withdrawal_timelock,
+            withdrawal_limit: changes.withdrawal_limit,
+        }
+    }
+}
+
+impl From<UpdateVaultParams> for ConfigChanges {
+    fn from(params: UpdateVaultParams) -> Self {
+        Self {
+            fee_percentage: params.fee_percentage,
+            withdrawal_timelock: params.withdrawal_timelock,
+            withdrawal_limit: params.withdrawal_limit,
+            ..Self::default()
+        }
+    }
+}
+
+/// Whether `role` has to move from `current` to `new`: an error for the
+/// default pubkey, which no one can sign for, and `false` when `new` already
+/// holds it
+pub fn check_new_holder(role: &str, current: Pubkey, new: Pubkey) -> Result<bool> {
+    if new == Pubkey::default() {
+        return Err(anyhow!(
+            "Refusing to make the default pubkey the vault's {}",
+            role
+        ));
+    }
+    Ok(new != current)
+}
+
+impl TokenVaultClient {
+    /// Change the current vault's fee, timelock or withdrawal limit. The
+    /// settings are checked, and the signer against the vault's authority,
+    /// before anything is sent. Returns the authority's signed receipt.
+    pub fn update_vault_config(
+        &self,
+        authority: &dyn VaultSigner,
+        params: UpdateVaultParams,
+    ) -> Result<ActionReceipt> {
+        params.check()?;
+        if params.is_empty() {
+            return Err(anyhow!("No settings to update"));
+        }
+        let (vault, vault_data, deadline) = self.check_vault_authority(authority)?;
+
+        let signer = AsSigner(authority);
+        let request = self
+            .vault_request(&[vault])?
+            .accounts(accounts::UpdateVaultConfig {
+                authority: authority.pubkey(),
+                vault,
+            })
+            .args(instruction::UpdateVaultConfig {
+                fee_percentage: params.fee_percentage,
+                withdrawal_timelock: params.withdrawal_timelock,
+                withdrawal_limit: params.withdrawal_limit,
+            })
+            .signer(&signer);
+        let operation = Operation::new(
+            OperationKind::UpdateVaultConfig,
+            authority.pubkey(),
+            Some(vault),
+            0,
+        );
+        let signature = self.send_operation(operation, request, deadline)?;
+        self.invalidate(&vault);
+
+        self.action_receipt(
+            authority,
+            vault,
+            VaultChange::ConfigUpdated {
+                fee_percentage: params.fee_percentage,
+                withdrawal_timelock: params.withdrawal_timelock,
+                withdrawal_limit: params.withdrawal_limit,
+            },
+            signature,
+            UpdateVaultParams::default().applied_to(&vault_data),
+            params.applied_to(&vault_data),
+        )
+    }
+
+    /// Hand the current vault to `new_authority`, for example a multisig.
+    /// Returns the vault as read again after confirmation. When
+    /// `new_authority` already is the authority, nothing is sent.
+    pub fn transfer_authority(
+        &self,
+        current_authority: &dyn VaultSigner,
+        new_authority: Pubkey,
+    ) -> Result<Vault> {
+        let (vault, vault_data, deadline) = self.check_vault_authority(current_authority)?;
+        if !check_new_holder("authority", vault_data.authority, new_authority)? {
+            logging::warn!(%vault, %new_authority, "already the vault authority; nothing sent");
+            return Ok(vault_data);
+        }
+
+        let signer = AsSigner(current_authority);
+        let request = self
+            .vault_request(&[vault])?
+            .accounts(accounts::TransferAuthority {
+                authority: current_authority.pubkey(),
+                vault,
+            })
+            .args(instruction::TransferAuthority { new_authority })
+            .signer(&signer);
+        let operation = Operation::new(
+            OperationKind::TransferAuthority,
+            current_authority.pubkey(),
+            Some(vault),
+            0,
+        );
+        self.send_operation(operation, request, deadline)?;
+        self.invalidate(&vault);
+        self.fetch_vault(vault, deadline)
+    }
+
+    /// Send the current vault's future withdrawal fees to
+    /// `new_fee_collector`, like `transfer_authority`. Fee claims, not
+    /// withdrawals, need the new collector's token account;
+    /// `repair_fee_path` creates it.
+    pub fn set_fee_collector(
+        &self,
+        authority: &dyn VaultSigner,
+        new_fee_collector: Pubkey,
+    ) -> Result<Vault> {
+        let (vault, vault_data, deadline) = self.check_vault_authority(authority)?;
+        if !check_new_holder("fee collector", vault_data.fee_collector, new_fee_collector)? {
+            logging::warn!(%vault, %new_fee_collector, "already the fee collector; nothing sent");
+            return Ok(vault_data);
+        }
+
+        let signer = AsSigner(authority);
+        let request = self
+            .vault_request(&[vault])?
+            .accounts(accounts::SetFeeCollector {
+                authority: authority.pubkey(),
+                vault,
+            })
+            .args(instruction::SetFeeCollector { new_fee_collector })
+            .signer(&signer);
+        let operation = Operation::new(
+            OperationKind::SetFeeCollector,
+            authority.pubkey(),
+            Some(vault),
+            0,
+        );
+        self.send_operation(operation, request, deadline)?;
+        self.invalidate(&vault);
+        self.fetch_vault(vault, deadline)
+    }
+
+    /// The current vault, fetched for a change only `authority` may make,
+    /// after checking that it does hold the authority and that the vault is
+    /// mutable. Both come before the program's feature flags are read, so
+    /// a cached vault is refused without a request.
+    pub(crate) fn check_vault_authority(
+        &self,
+        authority: &dyn VaultSigner,
+    ) -> Result<(Pubkey, Vault, Deadline)> {
+        self.check_authority_key(&authority.pubkey())
+    }
+
+    /// `check_vault_authority` for an authority whose keypair the client
+    /// does not hold
+    pub(crate) fn check_authority_key(
+        &self,
+        authority: &Pubkey,
+    ) -> Result<(Pubkey, Vault, Deadline)> {
+        self.check_signer(authority)?;
+        let deadline = self.deadline();
+        let vault = self.current_vault()?;
+
+        let (vault_data, provenance) = self.fetch_vault_state(vault, deadline, true)?;
+        if vault_data.authority != *authority {
+            return Err(anyhow!(
+                "{} is not the authority of vault {}",
+                authority,
+                vault
+            ))
+            .context(CheckedAgainst(provenance));
+        }
+        Self::ensure_mutable(vault, &vault_data).context(CheckedAgainst(provenance))?;
+        self.check_features(deadline)?;
+        Ok((vault, vault_data, deadline))
+    }
+}
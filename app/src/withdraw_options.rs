@@ -0,0 +1,96 @@
+//! Withdrawals pay into the withdrawer's associated token account; the fee
+//! stays in the vault until the fee collector claims it. If the account was
+//! never created, the program fails with an opaque "account not found". The
+//! client can create it in the same transaction instead.
+
+use anchor_client::solana_sdk::{instruction::Instruction, pubkey::Pubkey};
+use anchor_spl::token;
+use anyhow::Result;
+
+use crate::signer::VaultSigner;
+use crate::{Payout, TokenVaultClient, TokenVaultError, TxReceipt};
+
+/// How `withdraw_with_options` treats the accounts a withdrawal pays into,
+/// and what else its transaction carries
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WithdrawOptions {
+    /// Create a missing withdrawer token account in the withdrawal's
+    /// transaction, with the client's payer paying the rent.
+    /// When off, a missing account fails before anything is sent.
+    pub create_ata: bool,
+    /// Send a withdrawal over the vault's withdrawal limit instead of
+    /// refusing it with `TokenVaultError::WithdrawalLimitExceeded`, for
+    /// callers who know the state the check reads is stale. The program
+    /// still enforces the limit it holds.
+    pub skip_limit_check: bool,
+    /// Attach this memo to the withdrawal's transaction. At most
+    /// `memo::MAX_MEMO_LEN` bytes.
+    pub memo: Option<String>,
+}
+
+/// An associated token account a withdrawal pays into
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PayoutAccount {
+    pub owner: Pubkey,
+    pub address: Pubkey,
+    pub exists: bool,
+}
+
+/// Instructions to put ahead of a withdrawal so that every account in
+/// `accounts` exists, with `payer` funding the rent. Each missing account is
+/// created once, in the order given. Without `options.create_ata`, the
+/// first missing account is a `TokenVaultError::TokenAccountMissing` instead.
+pub fn create_missing_accounts(
+    payer: &Pubkey,
+    mint: &Pubkey,
+    accounts: &[PayoutAccount],
+    options: &WithdrawOptions,
+) -> Result<Vec<Instruction>> {
+    let mut missing: Vec<&PayoutAccount> = Vec::new();
+    for account in accounts.iter().filter(|account| !account.exists) {
+        if !options.create_ata {
+            return Err(TokenVaultError::TokenAccountMissing {
+                owner: account.owner,
+                account: account.address,
+            }
+            .into());
+        }
+        if !missing.iter().any(|seen| seen.address == account.address) {
+            missing.push(account);
+        }
+    }
+    Ok(missing
+        .into_iter()
+        .map(|account| {
+            spl_associated_token_account::instruction::create_associated_token_account_idempotent(
+                payer,
+                &account.owner,
+                mint,
+                &token::ID,
+            )
+        })
+        .collect())
+}
+
+impl TokenVaultClient {
+    /// `withdraw` with `options`; `WithdrawOptions::default()` behaves like
+    /// `withdraw` itself. The receipt echoes the memo sent, if any.
+    pub fn withdraw_with_options(
+        &self,
+        withdrawer: &dyn VaultSigner,
+        amount: u64,
+        recipient: Option<Pubkey>,
+        options: WithdrawOptions,
+    ) -> Result<TxReceipt> {
+        let vault = self.current_vault()?;
+        let recipient = recipient.unwrap_or_else(|| withdrawer.pubkey());
+        self.withdraw_in_mint(
+            vault,
+            withdrawer,
+            amount,
+            None,
+            Payout::Wallet(recipient),
+            options,
+        )
+    }
+}
@@ -0,0 +1,363 @@
+//! Two-step withdrawals: a withdrawer requests an amount, which creates a
+//! request PDA per vault and withdrawer, and executes it once the vault's
+//! `withdrawal_timelock` has passed since the request, or cancels it.
+
+use anchor_client::solana_client::{
+    rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
+    rpc_filter::{Memcmp, RpcFilterType},
+};
+use anchor_client::solana_sdk::{pubkey::Pubkey, system_program};
+use anchor_lang::{AccountDeserialize, Discriminator};
+use anchor_spl::token;
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use solana_account_decoder::UiAccountEncoding;
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::dust::check_withdrawal_limit;
+use crate::logging;
+use crate::pagination::program_accounts_with_slot;
+use crate::pause::check_not_paused;
+use crate::plan::format_duration;
+use crate::schedule::format_timestamp;
+use crate::signer::{AsSigner, VaultSigner};
+use crate::support::hash_pubkey;
+use crate::token_vault::{
+    accounts, instruction, pda,
+    state::{Vault, WithdrawalRequest},
+};
+use crate::{
+    assembly, withdraw_options, CheckedAgainst, Deadline, DeadlinePhase, Operation, OperationKind,
+    PayoutAccount, TokenVaultClient, TokenVaultError, TxReceipt, WithdrawOptions, WithdrawalQuote,
+};
+
+/// A requested withdrawal and when it can be executed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PendingWithdrawal {
+    /// The request PDA, passed to `execute_withdrawal` and `cancel_withdrawal`
+    #[serde(with = "crate::serde_utils::pubkey")]
+    pub address: Pubkey,
+    #[serde(with = "crate::serde_utils::pubkey")]
+    pub vault: Pubkey,
+    #[serde(with = "crate::serde_utils::pubkey")]
+    pub withdrawer: Pubkey,
+    pub amount: u64,
+    pub requested_at: i64,
+    /// Unix timestamp from which the request can be executed
+    pub executable_at: i64,
+    /// Seconds until `executable_at` at the cluster time the request was
+    /// read; 0 once it is executable
+    pub remaining: i64,
+}
+
+impl PendingWithdrawal {
+    /// `request` at `address`, timed against `vault`'s timelock at cluster time `now`
+    pub fn of(address: Pubkey, request: &WithdrawalRequest, vault: &Vault, now: i64) -> Self {
+        let executable_at = request
+            .requested_at
+            .saturating_add(vault.withdrawal_timelock);
+        Self {
+            address,
+            vault: request.vault,
+            withdrawer: request.withdrawer,
+            amount: request.amount,
+            requested_at: request.requested_at,
+            executable_at,
+            remaining: executable_at.saturating_sub(now).max(0),
+        }
+    }
+
+    pub fn is_executable(&self) -> bool {
+        self.remaining == 0
+    }
+
+    /// Fail with `WithdrawalTimelocked` unless the request is executable
+    pub fn check_executable(&self) -> Result<(), TokenVaultError> {
+        if !self.is_executable() {
+            return Err(TokenVaultError::WithdrawalTimelocked {
+                executable_at: self.executable_at,
+            });
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for PendingWithdrawal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} from vault {}, ",
+            self.address, self.amount, self.vault
+        )?;
+        match self.remaining {
+            0 => write!(f, "executable"),
+            remaining => write!(
+                f,
+                "executable in {} (at {})",
+                format_duration(remaining),
+                format_timestamp(self.executable_at)
+            ),
+        }
+    }
+}
+
+impl TokenVaultClient {
+    /// Request to withdraw `amount` from the current vault. The tokens stay
+    /// in the vault until `execute_withdrawal` after the vault's withdrawal
+    /// timelock; a withdrawer has at most one request per vault. Returns the
+    /// request's address.
+    pub fn request_withdrawal(&self, withdrawer: &dyn VaultSigner, amount: u64) -> Result<Pubkey> {
+        self.check_signer(&withdrawer.pubkey())?;
+        let deadline = self.deadline();
+        self.check_features(deadline)?;
+        let vault = self.current_vault()?;
+        let (address, _) =
+            pda::find_withdrawal_request_address(&self.program.id(), &vault, &withdrawer.pubkey());
+
+        // Refuse what the execution would refuse anyway
+        let (vault_data, provenance) = self.fetch_vault_state(vault, deadline, true)?;
+        WithdrawalQuote::for_vault(&vault_data, amount).context(CheckedAgainst(provenance))?;
+        check_withdrawal_limit(&vault_data, amount).context(CheckedAgainst(provenance))?;
+        self.check_accounting(&vault_data, amount)
+            .context(CheckedAgainst(provenance))?;
+        if let Some(pending) = self.fetch_withdrawal_request(address, deadline)? {
+            return Err(anyhow!(
+                "Withdrawer {} already has a pending request {} for {}; execute or cancel it first",
+                withdrawer.pubkey(),
+                address,
+                pending.amount
+            ));
+        }
+
+        let signer = AsSigner(withdrawer);
+        let request = self
+            .vault_request(&[vault])?
+            .accounts(accounts::RequestWithdrawal {
+                withdrawer: withdrawer.pubkey(),
+                vault,
+                withdrawal_request: address,
+                system_program: system_program::ID,
+            })
+            .args(instruction::RequestWithdrawal { amount })
+            .signer(&signer);
+        let operation = Operation::new(
+            OperationKind::RequestWithdrawal,
+            withdrawer.pubkey(),
+            Some(vault),
+            0,
+        );
+        self.send_operation(operation, request, deadline)?;
+        self.invalidate(&address);
+        logging::debug!(
+            request = %hash_pubkey(&address),
+            amount,
+            "withdrawal requested"
+        );
+        Ok(address)
+    }
+
+    /// Execute the withdrawal `request` of `withdrawer` into their associated
+    /// token account. Fails with `WithdrawalTimelocked` before the vault's
+    /// timelock has passed since the request, at cluster time.
+    pub fn execute_withdrawal(
+        &self,
+        withdrawer: &dyn VaultSigner,
+        request: Pubkey,
+    ) -> Result<TxReceipt> {
+        self.check_signer(&withdrawer.pubkey())?;
+        let deadline = self.deadline();
+        self.check_features(deadline)?;
+        let pending = self.fetch_own_request(withdrawer, request, deadline)?;
+        let vault = pending.vault;
+        let (vault_data, provenance) = self.fetch_vault_state(vault, deadline, true)?;
+        let now = self.cluster_time_within(deadline)?;
+        let pending = PendingWithdrawal::of(request, &pending, &vault_data, now);
+        pending.check_executable()?;
+        check_not_paused(&vault_data).context(CheckedAgainst(provenance))?;
+        let amount = pending.amount;
+
+        // The vault's policy may have changed since the request
+        WithdrawalQuote::for_vault(&vault_data, amount).context(CheckedAgainst(provenance))?;
+        check_withdrawal_limit(&vault_data, amount).context(CheckedAgainst(provenance))?;
+        self.check_accounting(&vault_data, amount)
+            .context(CheckedAgainst(provenance))?;
+
+        let token_mint = vault_data.token_mint;
+        let (vault_token_account, _) =
+            pda::find_vault_token_account_address(&self.program.id(), &vault);
+        let withdrawer_token_account = anchor_spl::associated_token::get_associated_token_address(
+            &withdrawer.pubkey(),
+            &token_mint,
+        );
+        assembly::check_not_self_transfer(&vault_token_account, &withdrawer_token_account)?;
+        let (position, _) =
+            pda::find_position_address(&self.program.id(), &vault, &withdrawer.pubkey());
+        let exists =
+            self.check_not_frozen(vault, &vault_data, Some(withdrawer_token_account), deadline)?;
+        // Without `create_ata` this only refuses a missing token account
+        withdraw_options::create_missing_accounts(
+            &self.program.payer(),
+            &token_mint,
+            &[PayoutAccount {
+                owner: withdrawer.pubkey(),
+                address: withdrawer_token_account,
+                exists,
+            }],
+            &WithdrawOptions::default(),
+        )
+        .context(CheckedAgainst(provenance))?;
+
+        let signer = AsSigner(withdrawer);
+        let request_builder = self
+            .vault_request(&[vault])?
+            .accounts(accounts::ExecuteWithdrawal {
+                withdrawer: withdrawer.pubkey(),
+                vault,
+                vault_token_account,
+                withdrawer_token_account,
+                withdrawal_request: request,
+                token_program: token::ID,
+                position,
+            })
+            .args(instruction::ExecuteWithdrawal {})
+            .signer(&signer);
+        let operation = Operation::new(
+            OperationKind::Withdraw,
+            withdrawer.pubkey(),
+            Some(vault),
+            amount,
+        );
+        let signature = self.send_operation(operation, request_builder, deadline)?;
+
+        self.invalidate(&vault);
+        self.invalidate(&vault_token_account);
+        self.invalidate(&withdrawer_token_account);
+        self.invalidate(&request);
+        self.invalidate(&position);
+        logging::debug!(
+            request = %hash_pubkey(&request),
+            "withdrawal request executed"
+        );
+        Ok(self.tx_receipt(signature))
+    }
+
+    /// Close the withdrawal `request` of `withdrawer` without paying it out;
+    /// the request's rent goes back to the withdrawer
+    pub fn cancel_withdrawal(
+        &self,
+        withdrawer: &dyn VaultSigner,
+        request: Pubkey,
+    ) -> Result<TxReceipt> {
+        self.check_signer(&withdrawer.pubkey())?;
+        let deadline = self.deadline();
+        self.check_features(deadline)?;
+        let pending = self.fetch_own_request(withdrawer, request, deadline)?;
+
+        let signer = AsSigner(withdrawer);
+        let request_builder = self
+            .vault_request(&[pending.vault])?
+            .accounts(accounts::CancelWithdrawal {
+                withdrawer: withdrawer.pubkey(),
+                vault: pending.vault,
+                withdrawal_request: request,
+            })
+            .args(instruction::CancelWithdrawal {})
+            .signer(&signer);
+        let operation = Operation::new(
+            OperationKind::CancelWithdrawal,
+            withdrawer.pubkey(),
+            Some(pending.vault),
+            0,
+        );
+        let signature = self.send_operation(operation, request_builder, deadline)?;
+        self.invalidate(&request);
+        Ok(self.tx_receipt(signature))
+    }
+
+    /// Every withdrawal request of `withdrawer`, across vaults, soonest
+    /// executable first, with the time left on each at cluster time
+    pub fn get_pending_withdrawals(&self, withdrawer: Pubkey) -> Result<Vec<PendingWithdrawal>> {
+        let deadline = self.deadline();
+        let rpc = self.rpc_within(deadline, DeadlinePhase::Fetch)?;
+        let config = RpcProgramAccountsConfig {
+            filters: Some(vec![
+                RpcFilterType::DataSize(WithdrawalRequest::LEN as u64),
+                RpcFilterType::Memcmp(Memcmp::new_raw_bytes(
+                    0,
+                    WithdrawalRequest::DISCRIMINATOR.to_vec(),
+                )),
+                RpcFilterType::Memcmp(Memcmp::new_raw_bytes(
+                    WithdrawalRequest::WITHDRAWER_OFFSET,
+                    withdrawer.to_bytes().to_vec(),
+                )),
+            ]),
+            account_config: RpcAccountInfoConfig {
+                encoding: Some(UiAccountEncoding::Base64),
+                commitment: Some(rpc.commitment()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let (requests, _) = program_accounts_with_slot(&rpc, &self.program.id(), config)?;
+        let now = self.cluster_time_within(deadline)?;
+
+        let mut vaults: BTreeMap<Pubkey, Vault> = BTreeMap::new();
+        let mut pending = Vec::new();
+        for (address, account) in requests {
+            let request = WithdrawalRequest::try_deserialize(&mut account.data.as_slice())
+                .with_context(|| format!("Failed to decode withdrawal request {}", address))?;
+            if !vaults.contains_key(&request.vault) {
+                let (vault_data, _) = self.fetch_vault_state(request.vault, deadline, false)?;
+                vaults.insert(request.vault, vault_data);
+            }
+            pending.push(PendingWithdrawal::of(
+                address,
+                &request,
+                &vaults[&request.vault],
+                now,
+            ));
+        }
+        pending.sort_by_key(|request| (request.executable_at, request.address));
+        Ok(pending)
+    }
+
+    pub(crate) fn fetch_withdrawal_request(
+        &self,
+        address: Pubkey,
+        deadline: Deadline,
+    ) -> Result<Option<WithdrawalRequest>> {
+        let rpc = self.rpc_within(deadline, DeadlinePhase::Fetch)?;
+        let account = match rpc
+            .get_account_with_commitment(&address, rpc.commitment())?
+            .value
+        {
+            Some(account) => account,
+            None => return Ok(None),
+        };
+        let request = WithdrawalRequest::try_deserialize(&mut account.data.as_slice())
+            .with_context(|| format!("Failed to decode withdrawal request {}", address))?;
+        Ok(Some(request))
+    }
+
+    /// The request at `address`, which must exist and belong to `withdrawer`
+    fn fetch_own_request(
+        &self,
+        withdrawer: &dyn VaultSigner,
+        address: Pubkey,
+        deadline: Deadline,
+    ) -> Result<WithdrawalRequest> {
+        let request = self
+            .fetch_withdrawal_request(address, deadline)?
+            .ok_or_else(|| anyhow!("Withdrawal request {} not found", address))?;
+        if request.withdrawer != withdrawer.pubkey() {
+            return Err(anyhow!(
+                "Withdrawal request {} belongs to {}, not {}",
+                address,
+                request.withdrawer,
+                withdrawer.pubkey()
+            ));
+        }
+        Ok(request)
+    }
+}
@@ -0,0 +1,135 @@
+//! Native SOL for vaults of the native mint. Such a vault holds wrapped SOL
+//! (wSOL), an SPL token whose balance is the lamports of its token account.
+//! `deposit_sol` wraps lamports into the depositor's wSOL account and
+//! deposits them in one transaction; `withdraw_sol` withdraws into that
+//! account and closes it in one transaction, so the lamports arrive as
+//! native SOL.
+
+use anchor_client::solana_sdk::{
+    instruction::Instruction, program_pack::Pack, pubkey::Pubkey, system_instruction,
+};
+use anchor_spl::associated_token::get_associated_token_address;
+use anchor_spl::token::{self, spl_token};
+use anyhow::{anyhow, Result};
+use spl_token::{native_mint, state::Account as TokenAccount};
+
+use crate::logging;
+use crate::signer::VaultSigner;
+use crate::{
+    Deadline, DeadlinePhase, DepositSource, Payout, TokenVaultClient, TxReceipt, WithdrawOptions,
+};
+
+/// `owner`'s wSOL account: its associated token account of the native mint
+pub fn wsol_account(owner: &Pubkey) -> Pubkey {
+    get_associated_token_address(owner, &native_mint::ID)
+}
+
+/// Create `owner`'s wSOL account unless it exists, with `owner` paying the
+/// rent
+pub fn create_instruction(owner: &Pubkey) -> Instruction {
+    spl_associated_token_account::instruction::create_associated_token_account_idempotent(
+        owner,
+        owner,
+        &native_mint::ID,
+        &token::ID,
+    )
+}
+
+/// Instructions that wrap `lamports` of `owner`'s SOL: create its wSOL
+/// account unless it exists, transfer the lamports into it, and sync the
+/// token balance with them
+pub fn wrap_instructions(owner: &Pubkey, lamports: u64) -> Result<Vec<Instruction>> {
+    let account = wsol_account(owner);
+    Ok(vec![
+        create_instruction(owner),
+        system_instruction::transfer(owner, &account, lamports),
+        spl_token::instruction::sync_native(&token::ID, &account)?,
+    ])
+}
+
+/// Close `owner`'s wSOL account, unwrapping everything in it, rent
+/// included, to `owner` as native SOL
+pub fn unwrap_instruction(owner: &Pubkey) -> Result<Instruction> {
+    Ok(spl_token::instruction::close_account(
+        &token::ID,
+        &wsol_account(owner),
+        owner,
+        owner,
+        &[],
+    )?)
+}
+
+impl TokenVaultClient {
+    /// Deposit `lamports` of native SOL into the current vault, wrapping
+    /// them in the same transaction. The depositor's wSOL account is reused
+    /// if it exists; otherwise it is created for the deposit and closed
+    /// again, returning its rent. A vault of another mint than the native
+    /// one is refused with `TokenVaultError::MintMismatch`.
+    pub fn deposit_sol(&self, depositor: &dyn VaultSigner, lamports: u64) -> Result<TxReceipt> {
+        let vault = self.current_vault()?;
+        let (signature, quote) = self.send_deposit(
+            vault,
+            depositor,
+            DepositSource::WrappedSol,
+            lamports,
+            Some(native_mint::ID),
+            None,
+            None,
+        )?;
+        if quote.reserve > 0 {
+            logging::debug!(
+                reserve = quote.reserve,
+                credited = quote.credited,
+                "reserve carve-out"
+            );
+        }
+        Ok(self.tx_receipt(signature))
+    }
+
+    /// Withdraw `lamports` from the current vault as native SOL. The
+    /// withdrawal pays into the withdrawer's wSOL account, created if
+    /// missing, which is then closed; wSOL it already held is unwrapped
+    /// too. A vault of another mint than the native one is refused with
+    /// `TokenVaultError::MintMismatch`.
+    pub fn withdraw_sol(&self, withdrawer: &dyn VaultSigner, lamports: u64) -> Result<TxReceipt> {
+        self.withdraw_in_mint(
+            self.current_vault()?,
+            withdrawer,
+            lamports,
+            Some(native_mint::ID),
+            Payout::Unwrapped,
+            WithdrawOptions::default(),
+        )
+    }
+
+    /// Refuse to wrap more lamports than `owner` holds, counting the rent
+    /// of its wSOL account when the deposit must create it
+    pub(crate) fn verify_sol_to_wrap(
+        &self,
+        owner: &Pubkey,
+        lamports: u64,
+        deadline: Deadline,
+    ) -> Result<()> {
+        let rpc = self.rpc_within(deadline, DeadlinePhase::Fetch)?;
+        let rent = match rpc
+            .get_account_with_commitment(&wsol_account(owner), rpc.commitment())?
+            .value
+        {
+            Some(_) => 0,
+            None => rpc.get_minimum_balance_for_rent_exemption(TokenAccount::LEN)?,
+        };
+        let need = lamports.saturating_add(rent);
+        let have = rpc
+            .get_balance_with_commitment(owner, rpc.commitment())?
+            .value;
+        if have < need {
+            return Err(anyhow!(
+                "Wallet {} holds {} lamports, less than the {} needed to wrap",
+                owner,
+                have,
+                need
+            ));
+        }
+        Ok(())
+    }
+}
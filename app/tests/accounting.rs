@@ -0,0 +1,62 @@
+mod common;
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use token_vault_client::accounting::{check_total_deposited, total_deposited_after_withdrawal};
+use token_vault_client::books::{books_report, BooksReplay};
+use token_vault_client::token_vault::state::Vault;
+use token_vault_client::TokenVaultError;
+
+fn vault(total_deposited: u64, accounting_anomaly: bool) -> Vault {
+    Vault {
+        total_deposited,
+        accounting_anomaly,
+        ..common::vault()
+    }
+}
+
+fn underflow(recorded: u64, amount: u64) -> TokenVaultError {
+    TokenVaultError::AccountingUnderflow {
+        counter: "total_deposited",
+        recorded,
+        amount,
+    }
+}
+
+#[test]
+fn withdrawing_exactly_the_total_is_fine() {
+    assert_eq!(check_total_deposited(&vault(1_000, false), 1_000), Ok(()));
+    assert_eq!(total_deposited_after_withdrawal(1_000, 1_000), (0, false));
+}
+
+#[test]
+fn one_past_the_total_underflows() {
+    assert_eq!(
+        check_total_deposited(&vault(1_000, false), 1_001),
+        Err(underflow(1_000, 1_001))
+    );
+    assert_eq!(check_total_deposited(&vault(1_000, false), 999), Ok(()));
+    assert_eq!(total_deposited_after_withdrawal(1_000, 1_001), (0, true));
+}
+
+#[test]
+fn massively_divergent_totals_clamp_to_zero() {
+    assert_eq!(
+        check_total_deposited(&vault(0, false), u64::MAX),
+        Err(underflow(0, u64::MAX))
+    );
+    assert_eq!(total_deposited_after_withdrawal(1, u64::MAX), (0, true));
+    let err = underflow(5, u64::MAX).to_string();
+    assert!(err.contains("verify-books"), "{}", err);
+}
+
+#[test]
+fn a_flagged_vault_never_has_balanced_books() {
+    let mut replay = BooksReplay::new(Pubkey::new_unique());
+    replay.saw_initialization = true;
+
+    assert!(books_report(&replay, &vault(0, false), 0, 0).is_consistent());
+
+    let report = books_report(&replay, &vault(0, true), 0, 0);
+    assert!(!report.is_consistent());
+    assert_eq!(report.discrepancies[0].field, "accounting_anomaly");
+}
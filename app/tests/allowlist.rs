@@ -0,0 +1,105 @@
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use std::collections::BTreeSet;
+use token_vault_client::allowlist::{
+    allowlist_csv, parse_allowlist_csv, DEFAULT_ALLOWLIST_BATCH_SIZE,
+};
+use token_vault_client::{AllowlistChange, AllowlistSyncPlan};
+
+const ENTRY_RENT: u64 = 1_398_960;
+
+fn wallets(count: usize) -> BTreeSet<Pubkey> {
+    (0..count).map(|_| Pubkey::new_unique()).collect()
+}
+
+#[test]
+fn export_round_trips_through_import() {
+    let wallets = wallets(25);
+    assert_eq!(
+        parse_allowlist_csv(&allowlist_csv(&wallets)).unwrap(),
+        wallets
+    );
+}
+
+#[test]
+fn import_skips_header_comments_blanks_and_extra_columns() {
+    let wallet = Pubkey::new_unique();
+    let csv = format!(
+        "Wallet,approved_on\n# Q3 approvals\n\n\"{}\",2024-07-01\n{}\n",
+        wallet, wallet
+    );
+    let parsed = parse_allowlist_csv(&csv).unwrap();
+    assert_eq!(parsed, BTreeSet::from([wallet]));
+}
+
+#[test]
+fn import_reports_the_bad_line() {
+    let csv = format!("wallet\n{}\nnot-a-key\n", Pubkey::new_unique());
+    let err = parse_allowlist_csv(&csv).unwrap_err().to_string();
+    assert!(err.contains("Line 3"), "{}", err);
+}
+
+#[test]
+fn plan_diffs_current_against_desired() {
+    let kept = wallets(3);
+    let stale = wallets(2);
+    let new = wallets(4);
+    let current: BTreeSet<_> = kept.union(&stale).copied().collect();
+    let desired: BTreeSet<_> = kept.union(&new).copied().collect();
+
+    let plan = AllowlistSyncPlan::new(
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        &current,
+        &desired,
+        ENTRY_RENT,
+    );
+    assert_eq!(plan.additions, new);
+    assert_eq!(plan.removals, stale);
+    assert_eq!(plan.unchanged, 3);
+    assert_eq!(plan.rent_delta(), 2 * ENTRY_RENT as i128);
+
+    // Removals come first so their refunds fund the additions
+    let changes = plan.changes();
+    assert!(matches!(changes[0], AllowlistChange::Remove(_)));
+    assert!(matches!(changes[1], AllowlistChange::Remove(_)));
+    assert!(matches!(changes[2], AllowlistChange::Add(_)));
+}
+
+#[test]
+fn plan_counts_transactions_and_refunds() {
+    let current = wallets(3_000);
+    let plan = AllowlistSyncPlan::new(
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        &current,
+        &BTreeSet::new(),
+        ENTRY_RENT,
+    );
+    assert_eq!(
+        plan.transaction_count(),
+        3_000 / DEFAULT_ALLOWLIST_BATCH_SIZE
+    );
+    assert_eq!(plan.rent_delta(), -3_000 * ENTRY_RENT as i128);
+
+    let unchanged = AllowlistSyncPlan::new(
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        &current,
+        &current,
+        ENTRY_RENT,
+    );
+    assert!(unchanged.is_empty());
+    assert_eq!(unchanged.transaction_count(), 0);
+}
+
+#[test]
+fn plan_rounds_partial_batches_up() {
+    let plan = AllowlistSyncPlan::new(
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        &BTreeSet::new(),
+        &wallets(DEFAULT_ALLOWLIST_BATCH_SIZE + 1),
+        ENTRY_RENT,
+    );
+    assert_eq!(plan.transaction_count(), 2);
+}
@@ -0,0 +1,102 @@
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use proptest::prelude::*;
+use token_vault_client::amount::base_units_to_ui;
+use token_vault_client::batch::parse_operations;
+use token_vault_client::vault_config::ui_to_base_units;
+use token_vault_client::{AddressBook, MintAmount, TokenVaultError, VaultOperation};
+
+proptest! {
+    #[test]
+    fn ui_amounts_round_trip(raw: u64, decimals in 0u8..=12) {
+        let ui = base_units_to_ui(raw, decimals);
+        prop_assert_eq!(ui_to_base_units(&ui, decimals).unwrap(), raw);
+
+        let mint = Pubkey::new_unique();
+        let amount = MintAmount::new(raw, mint, decimals);
+        prop_assert_eq!(MintAmount::from_ui(&amount.ui(), mint, decimals).unwrap(), amount);
+    }
+
+    #[test]
+    fn ui_amounts_scale_by_the_decimals(whole in 0u64..1_000_000, decimals in 0u8..=12) {
+        let raw = ui_to_base_units(&whole.to_string(), decimals).unwrap();
+        prop_assert_eq!(raw, whole * 10u64.pow(decimals as u32));
+    }
+}
+
+#[test]
+fn ui_rendering_drops_trailing_zeros() {
+    assert_eq!(base_units_to_ui(0, 6), "0");
+    assert_eq!(base_units_to_ui(5, 2), "0.05");
+    assert_eq!(base_units_to_ui(1_500_000, 6), "1.5");
+    assert_eq!(base_units_to_ui(1_000, 0), "1000");
+}
+
+#[test]
+fn zero_converts_at_any_decimals() {
+    for decimals in [0, 6, 19, 20, u8::MAX] {
+        for zero in ["0", "0.000", ".0", "000"] {
+            assert_eq!(ui_to_base_units(zero, decimals).unwrap(), 0);
+        }
+    }
+    assert_eq!(ui_to_base_units("1", 19).unwrap(), 10u64.pow(19));
+    let err = ui_to_base_units("1", 20).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Mint decimals 20 are out of range; amounts convert for at most 19"
+    );
+    assert!(ui_to_base_units("0.5", u8::MAX).is_err());
+}
+
+#[test]
+fn amounts_of_different_mints_do_not_combine() {
+    let usdc = Pubkey::new_unique();
+    let bonk = Pubkey::new_unique();
+    let a = MintAmount::new(1_500_000, usdc, 6);
+    let b = MintAmount::new(500_000, usdc, 6);
+
+    assert_eq!(a.checked_add(&b).unwrap().raw, 2_000_000);
+    assert_eq!(a.checked_sub(&b).unwrap().raw, 1_000_000);
+    assert!(b.checked_sub(&a).is_err());
+    assert!(MintAmount::new(u64::MAX, usdc, 6).checked_add(&b).is_err());
+
+    let err = a.checked_add(&MintAmount::new(1_000, bonk, 5)).unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<TokenVaultError>(),
+        Some(&TokenVaultError::MintMismatch {
+            expected: usdc,
+            actual: bonk,
+        })
+    );
+}
+
+#[test]
+fn amounts_render_with_the_symbol_from_the_address_book() {
+    let usdc = Pubkey::new_unique();
+    let amount = MintAmount::new(1_500_000, usdc, 6);
+    assert_eq!(amount.to_string(), format!("1.5 {}", usdc));
+
+    let book: AddressBook = [(usdc, "USDC")].into_iter().collect();
+    assert_eq!(amount.display(&book).to_string(), "1.5 USDC");
+    assert_eq!(
+        amount.display(&AddressBook::new()).to_string(),
+        amount.to_string()
+    );
+}
+
+#[test]
+fn batch_entries_may_name_their_mint() {
+    let mint = Pubkey::new_unique();
+    let lines = parse_operations(&format!(
+        "{{\"op\":\"deposit\",\"amount\":1000,\"mint\":\"{}\"}}",
+        mint
+    ));
+    assert_eq!(lines[0].request.as_ref().unwrap().mint(), Some(mint));
+    assert_eq!(
+        lines[0].request,
+        Ok(VaultOperation::Deposit {
+            vault: None,
+            amount: 1000,
+            mint: Some(mint),
+        })
+    );
+}
@@ -0,0 +1,309 @@
+use anchor_client::solana_sdk::{
+    hash::Hash,
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    system_program,
+    transaction::Transaction,
+};
+use anchor_lang::{InstructionData, ToAccountMetas};
+use anchor_spl::associated_token::get_associated_token_address;
+use anchor_spl::token;
+use std::collections::HashSet;
+use token_vault_client::assembly::{
+    check_not_self_transfer, dedup_signers, merge_account_metas, sign_transaction,
+};
+use token_vault_client::token_vault::{self, pda};
+use token_vault_client::TokenVaultError;
+
+/// The accounts a vault's deposits, withdrawals and fee claims touch
+struct Accounts {
+    mint: Pubkey,
+    vault: Pubkey,
+    vault_token_account: Pubkey,
+    reserve_token_account: Pubkey,
+}
+
+impl Accounts {
+    fn new() -> Self {
+        let vault = Pubkey::new_unique();
+        Self {
+            mint: Pubkey::new_unique(),
+            vault,
+            vault_token_account: pda::find_vault_token_account_address(&token_vault::ID, &vault).0,
+            reserve_token_account: pda::find_reserve_token_account_address(
+                &token_vault::ID,
+                &vault,
+            )
+            .0,
+        }
+    }
+
+    fn token_account(&self, owner: &Keypair) -> Pubkey {
+        get_associated_token_address(&owner.pubkey(), &self.mint)
+    }
+
+    fn deposit(&self, depositor: &Keypair) -> Instruction {
+        let accounts = token_vault::accounts::Deposit {
+            depositor: depositor.pubkey(),
+            vault: self.vault,
+            vault_token_account: self.vault_token_account,
+            reserve_token_account: self.reserve_token_account,
+            depositor_token_account: self.token_account(depositor),
+            token_program: token::ID,
+            position: pda::find_position_address(
+                &token_vault::ID,
+                &self.vault,
+                &depositor.pubkey(),
+            )
+            .0,
+            system_program: system_program::ID,
+        };
+        Instruction {
+            program_id: token_vault::ID,
+            accounts: accounts.to_account_metas(None),
+            data: token_vault::instruction::Deposit { amount: 1_000 }.data(),
+        }
+    }
+
+    fn withdraw(&self, withdrawer: &Keypair) -> Instruction {
+        let accounts = token_vault::accounts::Withdraw {
+            withdrawer: withdrawer.pubkey(),
+            vault: self.vault,
+            vault_token_account: self.vault_token_account,
+            withdrawer_token_account: self.token_account(withdrawer),
+            token_program: token::ID,
+            position: pda::find_position_address(
+                &token_vault::ID,
+                &self.vault,
+                &withdrawer.pubkey(),
+            )
+            .0,
+        };
+        Instruction {
+            program_id: token_vault::ID,
+            accounts: accounts.to_account_metas(None),
+            data: token_vault::instruction::Withdraw { amount: 500 }.data(),
+        }
+    }
+
+    fn claim_fees(&self, fee_collector: &Keypair) -> Instruction {
+        let accounts = token_vault::accounts::ClaimFees {
+            fee_collector: fee_collector.pubkey(),
+            vault: self.vault,
+            vault_token_account: self.vault_token_account,
+            fee_collector_token_account: self.token_account(fee_collector),
+            token_program: token::ID,
+        };
+        Instruction {
+            program_id: token_vault::ID,
+            accounts: accounts.to_account_metas(None),
+            data: token_vault::instruction::ClaimFees {}.data(),
+        }
+    }
+}
+
+/// Sign a deposit, a withdrawal and a fee claim in one transaction paid by
+/// `authority`, passing every role's signer even when roles share a key
+fn assemble(authority: &Keypair, depositor: &Keypair, fee_collector: &Keypair) -> Transaction {
+    let accounts = Accounts::new();
+    let instructions = [
+        accounts.deposit(depositor),
+        accounts.withdraw(depositor),
+        accounts.claim_fees(fee_collector),
+    ];
+    sign_transaction(
+        &instructions,
+        authority,
+        &[depositor, depositor, fee_collector],
+        Hash::new_unique(),
+    )
+    .unwrap()
+}
+
+/// The transaction verifies, lists every key once with the flags its
+/// instructions ask for, and keeps each instruction's own account positions
+fn check_assembled(transaction: &Transaction, distinct_signers: usize) {
+    transaction.verify().unwrap();
+    let message = &transaction.message;
+    let keys: HashSet<_> = message.account_keys.iter().collect();
+    assert_eq!(keys.len(), message.account_keys.len());
+    assert_eq!(
+        message.header.num_required_signatures as usize,
+        distinct_signers
+    );
+    assert_eq!(transaction.signatures.len(), distinct_signers);
+
+    let instructions: Vec<Instruction> = message
+        .instructions
+        .iter()
+        .map(|compiled| Instruction {
+            program_id: message.account_keys[compiled.program_id_index as usize],
+            accounts: compiled
+                .accounts
+                .iter()
+                .map(|&index| {
+                    let index = index as usize;
+                    let pubkey = message.account_keys[index];
+                    match message.is_writable(index) {
+                        true => AccountMeta::new(pubkey, message.is_signer(index)),
+                        false => AccountMeta::new_readonly(pubkey, message.is_signer(index)),
+                    }
+                })
+                .collect(),
+            data: compiled.data.clone(),
+        })
+        .collect();
+    // A deposit, a withdrawal, then a fee claim
+    let lens: Vec<_> = instructions.iter().map(|ix| ix.accounts.len()).collect();
+    assert_eq!(lens, vec![8, 6, 5]);
+    for meta in merge_account_metas(instructions.iter().flat_map(|ix| ix.accounts.clone())) {
+        let index = message
+            .account_keys
+            .iter()
+            .position(|key| *key == meta.pubkey)
+            .unwrap();
+        assert_eq!(message.is_signer(index), meta.is_signer);
+        assert_eq!(message.is_writable(index), meta.is_writable);
+    }
+}
+
+#[test]
+fn distinct_roles_each_sign() {
+    let (authority, depositor, fee_collector) = (Keypair::new(), Keypair::new(), Keypair::new());
+    let accounts = Accounts::new();
+    let transaction = sign_transaction(
+        &[
+            accounts.deposit(&depositor),
+            accounts.withdraw(&depositor),
+            accounts.claim_fees(&fee_collector),
+        ],
+        &authority,
+        &[&depositor, &fee_collector],
+        Hash::new_unique(),
+    )
+    .unwrap();
+    check_assembled(&transaction, 3);
+}
+
+#[test]
+fn authority_as_depositor_signs_once() {
+    let (authority, fee_collector) = (Keypair::new(), Keypair::new());
+    let accounts = Accounts::new();
+    let transaction = sign_transaction(
+        &[
+            accounts.deposit(&authority),
+            accounts.withdraw(&authority),
+            accounts.claim_fees(&fee_collector),
+        ],
+        &authority,
+        &[&authority, &authority, &fee_collector],
+        Hash::new_unique(),
+    )
+    .unwrap();
+    check_assembled(&transaction, 2);
+}
+
+#[test]
+fn depositor_as_fee_collector_shares_one_writable_account() {
+    let (authority, depositor) = (Keypair::new(), Keypair::new());
+    let accounts = Accounts::new();
+    let withdraw = accounts.withdraw(&depositor);
+    let claim = accounts.claim_fees(&depositor);
+    assert_eq!(withdraw.accounts[3].pubkey, claim.accounts[3].pubkey);
+
+    let transaction = sign_transaction(
+        &[accounts.deposit(&depositor), withdraw, claim],
+        &authority,
+        &[&depositor, &depositor],
+        Hash::new_unique(),
+    )
+    .unwrap();
+    check_assembled(&transaction, 2);
+    let shared = accounts.token_account(&depositor);
+    let index = transaction
+        .message
+        .account_keys
+        .iter()
+        .position(|key| *key == shared)
+        .unwrap();
+    assert!(transaction.message.is_writable(index));
+    assert!(!transaction.message.is_signer(index));
+}
+
+#[test]
+fn authority_depositor_and_fee_collector_as_one_key() {
+    let authority = Keypair::new();
+    let transaction = assemble(&authority, &authority, &authority);
+    check_assembled(&transaction, 1);
+}
+
+#[test]
+fn signer_the_instructions_do_not_need_is_rejected() {
+    let (authority, depositor, stranger) = (Keypair::new(), Keypair::new(), Keypair::new());
+    let accounts = Accounts::new();
+    let result = sign_transaction(
+        &[accounts.deposit(&depositor)],
+        &authority,
+        &[&depositor, &stranger],
+        Hash::new_unique(),
+    );
+    assert!(result.is_err());
+}
+
+#[test]
+fn missing_signer_is_rejected() {
+    let (authority, depositor) = (Keypair::new(), Keypair::new());
+    let accounts = Accounts::new();
+    let result = sign_transaction(
+        &[accounts.deposit(&depositor)],
+        &authority,
+        &[],
+        Hash::new_unique(),
+    );
+    assert!(result.is_err());
+}
+
+#[test]
+fn dedup_keeps_first_signer_of_each_key_in_order() {
+    let (a, b) = (Keypair::new(), Keypair::new());
+    let signers = dedup_signers(&[&a, &b, &a, &b, &a]);
+    let keys: Vec<_> = signers.iter().map(|signer| signer.pubkey()).collect();
+    assert_eq!(keys, vec![a.pubkey(), b.pubkey()]);
+}
+
+#[test]
+fn merged_metas_union_signer_and_writable_flags() {
+    let (key, other) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let merged = merge_account_metas([
+        AccountMeta::new_readonly(key, true),
+        AccountMeta::new_readonly(other, false),
+        AccountMeta::new(key, false),
+    ]);
+    assert_eq!(
+        merged,
+        vec![
+            AccountMeta::new(key, true),
+            AccountMeta::new_readonly(other, false)
+        ]
+    );
+}
+
+#[test]
+fn self_transfer_is_a_typed_error() {
+    let accounts = Accounts::new();
+    let err = check_not_self_transfer(&accounts.vault_token_account, &accounts.vault_token_account)
+        .unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<TokenVaultError>(),
+        Some(&TokenVaultError::SelfTransfer {
+            account: accounts.vault_token_account
+        })
+    );
+    let depositor = Keypair::new();
+    assert!(check_not_self_transfer(
+        &accounts.token_account(&depositor),
+        &accounts.vault_token_account
+    )
+    .is_ok());
+}
@@ -0,0 +1,106 @@
+use anchor_client::solana_sdk::{pubkey::Pubkey, signature::Keypair, signer::Signer};
+use anchor_client::Cluster;
+use std::sync::Arc;
+use std::time::Duration;
+use token_vault_client::fixtures::{VaultFixture, PROGRAM_ID_ENV, VALIDATOR_ENV};
+use token_vault_client::{token_vault, AsyncTokenVaultClient, VaultConfig};
+
+fn assert_send_sync<T: Send + Sync + Clone + 'static>() {}
+
+async fn offline_client() -> AsyncTokenVaultClient {
+    AsyncTokenVaultClient::new(Cluster::Localnet, Keypair::new(), Pubkey::new_unique())
+        .await
+        .unwrap()
+}
+
+#[test]
+fn the_handle_can_move_between_tasks() {
+    assert_send_sync::<AsyncTokenVaultClient>();
+}
+
+#[tokio::test]
+async fn calls_from_spawned_tasks_share_one_client() {
+    let client = offline_client().await;
+    let tasks: Vec<_> = (0..4)
+        .map(|_| {
+            let client = client.clone();
+            tokio::spawn(async move { client.call(|c| Ok(c.write_rpc_url())).await.unwrap() })
+        })
+        .collect();
+    for task in tasks {
+        assert_eq!(task.await.unwrap(), "http://127.0.0.1:8899");
+    }
+
+    // A vault set by one call is there for the next
+    let depositor = Arc::new(Keypair::new());
+    let err = client.deposit(depositor.clone(), 1_000).await.unwrap_err();
+    assert!(err.to_string().contains("Vault address not set"), "{}", err);
+    client.set_vault(Pubkey::new_unique()).await.unwrap();
+    let err = client.deposit(depositor, 1_000).await.unwrap_err();
+    assert!(
+        !err.to_string().contains("Vault address not set"),
+        "{}",
+        err
+    );
+}
+
+#[tokio::test]
+async fn errors_and_panics_come_back_as_errors() {
+    let client = offline_client().await;
+    let err = client
+        .call::<(), _>(|_| Err(anyhow::anyhow!("refused")))
+        .await
+        .unwrap_err();
+    assert_eq!(err.to_string(), "refused");
+
+    let err = client.call::<(), _>(|_| panic!("boom")).await.unwrap_err();
+    assert!(err.to_string().contains("client thread stopped"), "{}", err);
+    // The thread is gone, so later calls fail the same way
+    assert!(client.get_vault_info().await.is_err());
+}
+
+/// Needs a validator with the token vault program deployed; see
+/// `fixtures::VALIDATOR_ENV` and `fixtures::PROGRAM_ID_ENV`
+#[tokio::test]
+#[ignore = "needs a local validator; run with --ignored"]
+async fn deposits_and_withdrawals_await_without_blocking_the_runtime() {
+    let fixture = VaultFixture::builder().build().unwrap();
+    let cluster: Cluster = std::env::var(VALIDATOR_ENV)
+        .unwrap_or_else(|_| "localnet".to_string())
+        .parse()
+        .unwrap();
+    let program_id = std::env::var(PROGRAM_ID_ENV)
+        .map(|id| id.parse().unwrap())
+        .unwrap_or(token_vault::ID);
+    let client =
+        AsyncTokenVaultClient::new(cluster, fixture.authority().insecure_clone(), program_id)
+            .await
+            .unwrap();
+    client.set_vault(fixture.vault()).await.unwrap();
+
+    let user = Arc::new(fixture.user(0).unwrap().keypair.insecure_clone());
+    let deposit = client.deposit(user.clone(), 10_000).await.unwrap();
+    let withdrawal = client.withdraw(user, 4_000, None).await.unwrap();
+    assert_ne!(deposit.signature, withdrawal.signature);
+    assert_eq!(
+        client.get_vault_info().await.unwrap().total_deposited,
+        fixture.client().get_vault_info().unwrap().total_deposited
+    );
+
+    let config = VaultConfig::builder()
+        .name("async-created")
+        .fee_bps(0)
+        .timelock(Duration::ZERO)
+        .withdrawal_limit(u64::MAX)
+        .build()
+        .unwrap();
+    let authority = Arc::new(fixture.authority().insecure_clone());
+    let created = client
+        .initialize_vault(authority.clone(), fixture.mint(), config)
+        .await
+        .unwrap();
+    client.set_vault(created.address).await.unwrap();
+    let vault = client.get_vault_info().await.unwrap();
+    assert_eq!(vault.authority, authority.pubkey());
+    assert_eq!(vault.name, "async-created");
+}
@@ -0,0 +1,144 @@
+use anchor_client::solana_sdk::{pubkey::Pubkey, signature::Signature};
+use token_vault_client::batch::{parse_operations, PlannedLine, PlannedOperation};
+use token_vault_client::json::ErrorBody;
+use token_vault_client::{BatchOutcome, BatchPlan, BatchResult, TokenVaultError, VaultOperation};
+
+#[test]
+fn operations_parse_with_their_line_numbers() {
+    let vault = Pubkey::new_unique();
+    let recipient = Pubkey::new_unique();
+    let input = format!(
+        "{{\"op\":\"deposit\",\"amount\":1000}}\n\
+         \n\
+         {{\"op\":\"withdraw\",\"amount\":500,\"to\":\"{}\",\"vault\":\"{}\"}}\n\
+         not json\n\
+         {{\"op\":\"withdraw\",\"amount\":500,\"recipient\":\"{}\"}}\n\
+         {{\"op\":\"burn\",\"amount\":1}}\n",
+        recipient, vault, recipient
+    );
+    let lines = parse_operations(&input);
+
+    let numbers: Vec<_> = lines.iter().map(|line| line.line).collect();
+    assert_eq!(numbers, vec![1, 3, 4, 5, 6]);
+    assert_eq!(
+        lines[0].request,
+        Ok(VaultOperation::Deposit {
+            vault: None,
+            amount: 1000,
+            mint: None,
+        })
+    );
+    assert_eq!(
+        lines[1].request,
+        Ok(VaultOperation::Withdraw {
+            vault: Some(vault),
+            amount: 500,
+            mint: None,
+            to: Some(recipient),
+        })
+    );
+    assert!(lines[2].request.is_err());
+    // A misspelt recipient must not fall back to withdrawing to the signer
+    assert!(lines[3].request.as_ref().unwrap_err().contains("recipient"));
+    assert!(lines[4].request.is_err());
+}
+
+fn dust() -> TokenVaultError {
+    TokenVaultError::DustWithdrawal {
+        amount: 5,
+        threshold: 10,
+    }
+}
+
+fn plan() -> BatchPlan {
+    let vault = Pubkey::new_unique();
+    BatchPlan {
+        cost: None,
+        lines: vec![
+            PlannedLine {
+                line: 1,
+                plan: Ok(PlannedOperation {
+                    vault,
+                    operation: VaultOperation::Deposit {
+                        vault: None,
+                        amount: 1000,
+                        mint: None,
+                    },
+                    quote: None,
+                    deposit_quote: None,
+                }),
+            },
+            PlannedLine {
+                line: 2,
+                plan: Err(ErrorBody::from(&dust())),
+            },
+        ],
+    }
+}
+
+#[test]
+fn skipped_batches_still_report_every_line_in_order() {
+    let plan = plan();
+    assert!(plan.has_rejections());
+    assert_eq!(
+        plan.skipped("not confirmed"),
+        vec![
+            BatchResult {
+                line: 1,
+                outcome: BatchOutcome::Skipped {
+                    reason: "not confirmed".to_string()
+                },
+            },
+            BatchResult {
+                line: 2,
+                outcome: BatchOutcome::Rejected {
+                    error: ErrorBody::from(&dust())
+                },
+            },
+        ]
+    );
+    let shown = plan.to_string();
+    assert!(shown.contains("line 1: deposit 1000"), "{}", shown);
+    assert!(shown.contains("line 2: Withdrawal of 5"), "{}", shown);
+}
+
+#[test]
+fn results_are_flat_json_objects() {
+    let signature = Signature::default();
+    let result = BatchResult {
+        line: 7,
+        outcome: BatchOutcome::Executed { signature },
+    };
+    let json = serde_json::to_value(&result).unwrap();
+    assert_eq!(
+        json,
+        serde_json::json!({
+            "line": 7,
+            "status": "executed",
+            "signature": signature.to_string(),
+        })
+    );
+    assert_eq!(serde_json::from_value::<BatchResult>(json).unwrap(), result);
+}
+
+#[test]
+fn rejections_carry_the_error_code_and_details() {
+    let result = &plan().skipped("not confirmed")[1];
+    let json = serde_json::to_value(result).unwrap();
+    assert_eq!(
+        json,
+        serde_json::json!({
+            "line": 2,
+            "status": "rejected",
+            "error": {
+                "code": "dust_withdrawal",
+                "message": "Withdrawal of 5 is below the vault's dust threshold of 10",
+                "details": { "amount": "5", "threshold": "10" },
+            },
+        })
+    );
+    assert_eq!(
+        serde_json::from_value::<BatchResult>(json).unwrap(),
+        *result
+    );
+}
@@ -0,0 +1,97 @@
+mod common;
+
+use anchor_client::solana_sdk::{account::Account, program_pack::Pack, pubkey::Pubkey};
+use anchor_lang::AccountSerialize;
+use anchor_spl::token::{self, spl_token};
+use common::key;
+use spl_token::state::{Account as TokenAccount, AccountState};
+use std::cell::RefCell;
+use token_vault_client::bulk::{
+    decode_token_balance, decode_vault, in_chunks, MAX_MULTIPLE_ACCOUNTS,
+};
+use token_vault_client::token_vault::{self, state::Vault};
+
+fn vault_account(name: &str) -> Account {
+    let vault = Vault {
+        authority: key(1),
+        token_mint: key(2),
+        fee_collector: key(3),
+        total_deposited: 7,
+        name: name.to_string(),
+        ..common::vault()
+    };
+    let mut data = Vec::new();
+    vault.try_serialize(&mut data).unwrap();
+    Account {
+        data,
+        owner: token_vault::ID,
+        ..Account::default()
+    }
+}
+
+#[test]
+fn chunks_keep_the_order_of_the_addresses() {
+    let addresses: Vec<Pubkey> = (0..250).map(|_| Pubkey::new_unique()).collect();
+    let sizes = RefCell::new(Vec::new());
+    let answers = in_chunks(&addresses, |chunk| {
+        sizes.borrow_mut().push(chunk.len());
+        Ok(chunk.to_vec())
+    })
+    .unwrap();
+    assert_eq!(answers, addresses);
+    assert_eq!(sizes.into_inner(), [MAX_MULTIPLE_ACCOUNTS, 100, 50]);
+
+    let none: Vec<Pubkey> = in_chunks(&[], |_| unreachable!()).unwrap();
+    assert!(none.is_empty());
+}
+
+#[test]
+fn short_answers_are_refused() {
+    let err = in_chunks(&[key(1), key(2)], |_| Ok(vec![0])).unwrap_err();
+    assert_eq!(err.to_string(), "Asked for 2 accounts, got 1");
+}
+
+#[test]
+fn missing_accounts_are_none() {
+    assert!(decode_vault(&key(9), None).unwrap().is_none());
+    let vault = decode_vault(&key(9), Some(&vault_account("treasury")))
+        .unwrap()
+        .unwrap();
+    assert_eq!(vault.name, "treasury");
+
+    let not_a_vault = Account {
+        data: vec![0; 16],
+        ..Account::default()
+    };
+    let err = decode_vault(&key(9), Some(&not_a_vault)).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        format!("Account {} is not a vault", key(9))
+    );
+}
+
+#[test]
+fn balances_come_from_token_accounts() {
+    let mut data = vec![0; TokenAccount::LEN];
+    TokenAccount::pack(
+        TokenAccount {
+            mint: key(2),
+            owner: key(4),
+            amount: 1_500,
+            state: AccountState::Initialized,
+            ..TokenAccount::default()
+        },
+        &mut data,
+    )
+    .unwrap();
+    let account = Account {
+        data,
+        owner: token::ID,
+        ..Account::default()
+    };
+    assert_eq!(
+        decode_token_balance(&key(5), Some(&account)).unwrap(),
+        Some(1_500)
+    );
+    assert_eq!(decode_token_balance(&key(5), None).unwrap(), None);
+}
@@ -0,0 +1,423 @@
+mod common;
+
+use anchor_client::solana_sdk::{
+    commitment_config::CommitmentLevel, pubkey::Pubkey, signature::Signature,
+};
+use anchor_lang::{AnchorSerialize, Discriminator, InstructionData};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use token_vault_client::changes::{merge_into_summary, vault_changes};
+use token_vault_client::fixtures::VaultFixture;
+use token_vault_client::token_vault::{
+    events::{AccountingAnomaly, WithdrawEvent},
+    instruction,
+    state::Vault,
+};
+use token_vault_client::{
+    NotificationConfig, Provenance, StateChange, VaultChange, VaultInstruction,
+    VaultBalance, VaultInstructionKind, VaultSummary, VaultTransaction,
+};
+
+const READ_AT: u64 = 100;
+
+fn vault() -> Vault {
+    Vault {
+        fee_percentage: 25,
+        total_deposited: 500_000,
+        reserve_bps: 100,
+        total_reserved: 5_000,
+        ..common::vault()
+    }
+}
+
+/// A vault instruction with its signer first, the vault second and then
+/// any other accounts
+fn ix(vault: Pubkey, signer: Pubkey, data: Vec<u8>, others: &[Pubkey]) -> VaultInstruction {
+    let mut accounts = vec![signer.to_string(), vault.to_string()];
+    accounts.extend(others.iter().map(Pubkey::to_string));
+    VaultInstruction {
+        kind: VaultInstructionKind::from_data(&data),
+        index: 0,
+        accounts,
+        data,
+    }
+}
+
+fn transaction(slot: u64, instructions: Vec<VaultInstruction>) -> VaultTransaction {
+    VaultTransaction {
+        signature: Signature::new_unique(),
+        slot,
+        block_time: None,
+        succeeded: true,
+        instructions,
+        logs: Vec::new(),
+    }
+}
+
+fn anomaly_log(vault: Pubkey, recorded: u64, amount: u64) -> String {
+    let event = AccountingAnomaly {
+        vault,
+        counter: "total_deposited".to_string(),
+        recorded,
+        amount,
+    };
+    let mut data = AccountingAnomaly::DISCRIMINATOR.to_vec();
+    data.extend(event.try_to_vec().unwrap());
+    format!("Program data: {}", STANDARD.encode(data))
+}
+
+fn withdraw_log(vault: Pubkey, withdrawer: Pubkey, amount: u64, fee: u64) -> String {
+    let event = WithdrawEvent {
+        vault,
+        withdrawer,
+        recipient: withdrawer,
+        amount,
+        fee,
+    };
+    let mut data = WithdrawEvent::DISCRIMINATOR.to_vec();
+    data.extend(event.try_to_vec().unwrap());
+    format!("Program data: {}", STANDARD.encode(data))
+}
+
+fn summary_at(address: Pubkey, vault: &Vault, slot: u64) -> VaultSummary {
+    VaultSummary {
+        provenance: Some(Provenance {
+            slot,
+            commitment: CommitmentLevel::Confirmed,
+        }),
+        ..VaultSummary::of(address, vault)
+    }
+}
+
+fn notifications() -> NotificationConfig {
+    NotificationConfig::default()
+        .webhook_url("https://hooks.example.com/vault")
+        .explorer("https://explorer.solana.com")
+        .support_contact("ops@example.com")
+}
+
+/// What happened to the vault after it was read at `READ_AT`, with one
+/// transaction that was already part of the read. `user` deposits,
+/// withdraws and is allowlisted.
+fn history(address: Pubkey, vault: &Vault, user: Pubkey) -> Vec<VaultTransaction> {
+    let authority = vault.authority;
+    let deposit = |amount| instruction::Deposit { amount }.data();
+    let withdraw = |amount| instruction::Withdraw { amount }.data();
+    let config = notifications();
+
+    let mut failed = transaction(102, vec![ix(address, user, withdraw(999), &[])]);
+    failed.succeeded = false;
+    let mut anomaly = transaction(106, vec![ix(address, user, withdraw(700_000), &[])]);
+    anomaly.logs = vec![anomaly_log(address, 598_000, 700_000)];
+
+    vec![
+        transaction(READ_AT, vec![ix(address, user, deposit(1), &[])]),
+        transaction(101, vec![ix(address, user, deposit(200_000), &[])]),
+        failed,
+        transaction(103, vec![ix(address, user, withdraw(100_000), &[])]),
+        transaction(
+            104,
+            vec![ix(
+                address,
+                authority,
+                instruction::ReleaseReserve { amount: 3_000 }.data(),
+                &[],
+            )],
+        ),
+        transaction(
+            105,
+            vec![
+                ix(
+                    address,
+                    authority,
+                    instruction::AddAllowlistEntry { wallet: user }.data(),
+                    &[Pubkey::new_unique()],
+                ),
+                ix(
+                    address,
+                    authority,
+                    instruction::SetNotificationConfig {
+                        webhook_url_hash: config.webhook_url_hash.map(|hash| hash.to_bytes()),
+                        explorer: config.explorer.clone(),
+                        support_contact: config.support_contact.clone(),
+                    }
+                    .data(),
+                    &[Pubkey::new_unique()],
+                ),
+                // Another vault's deposit in the same transaction
+                ix(Pubkey::new_unique(), user, deposit(42), &[]),
+            ],
+        ),
+        anomaly,
+    ]
+}
+
+fn changes(address: Pubkey, vault: &Vault, user: Pubkey) -> Vec<StateChange> {
+    history(address, vault, user)
+        .iter()
+        .flat_map(|transaction| vault_changes(&address, vault.reserve_bps, transaction))
+        .collect()
+}
+
+#[test]
+fn history_decodes_to_normalized_changes() {
+    let address = Pubkey::new_unique();
+    let (vault, user) = (vault(), Pubkey::new_unique());
+    let kinds: Vec<_> = changes(address, &vault, user)
+        .into_iter()
+        .map(|change| (change.slot, change.change))
+        .collect();
+    assert_eq!(
+        kinds,
+        vec![
+            (
+                READ_AT,
+                VaultChange::Deposited {
+                    amount: 1,
+                    reserved: 0
+                }
+            ),
+            (
+                101,
+                VaultChange::Deposited {
+                    amount: 200_000,
+                    reserved: 2_000
+                }
+            ),
+            (
+                103,
+                VaultChange::Withdrawn {
+                    amount: 100_000,
+                    fee: 0
+                }
+            ),
+            (104, VaultChange::ReserveReleased { amount: 3_000 }),
+            (105, VaultChange::AllowlistAdded { wallet: user }),
+            (
+                105,
+                VaultChange::NotificationsSet {
+                    config: notifications()
+                }
+            ),
+            (
+                106,
+                VaultChange::Withdrawn {
+                    amount: 700_000,
+                    fee: 0
+                }
+            ),
+            (
+                106,
+                VaultChange::AccountingAnomaly {
+                    counter: "total_deposited".to_string()
+                }
+            ),
+        ]
+    );
+}
+
+#[test]
+fn executed_requests_are_withdrawals() {
+    let (address, user) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let vault = vault();
+    let request = transaction(
+        101,
+        vec![ix(
+            address,
+            user,
+            instruction::RequestWithdrawal { amount: 50_000 }.data(),
+            &[],
+        )],
+    );
+    let mut execute = transaction(
+        102,
+        vec![ix(
+            address,
+            user,
+            instruction::ExecuteWithdrawal {}.data(),
+            &[],
+        )],
+    );
+    execute.logs = vec![withdraw_log(address, user, 50_000, 125)];
+
+    assert!(vault_changes(&address, vault.reserve_bps, &request).is_empty());
+    let changes = vault_changes(&address, vault.reserve_bps, &execute);
+    assert_eq!(
+        changes
+            .iter()
+            .map(|change| change.change.clone())
+            .collect::<Vec<_>>(),
+        vec![VaultChange::Withdrawn {
+            amount: 50_000,
+            fee: 125
+        }]
+    );
+
+    let mut summary = summary_at(address, &vault, READ_AT);
+    merge_into_summary(&mut summary, &changes);
+    assert_eq!(summary.total_deposited, 450_000);
+    assert_eq!(summary.accrued_fees, 125);
+}
+
+#[test]
+fn fees_stay_in_the_balance_until_claimed() {
+    let (address, user) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let vault = vault();
+    let mut withdraw = transaction(
+        101,
+        vec![ix(
+            address,
+            user,
+            instruction::Withdraw { amount: 40_000 }.data(),
+            &[],
+        )],
+    );
+    withdraw.logs = vec![withdraw_log(address, user, 40_000, 100)];
+    let claim = transaction(
+        102,
+        vec![ix(
+            address,
+            vault.fee_collector,
+            instruction::ClaimFees {}.data(),
+            &[],
+        )],
+    );
+
+    let mut summary = VaultSummary {
+        balance: Some(VaultBalance::of(Pubkey::new_unique(), &vault, 500_000, 6)),
+        ..summary_at(address, &vault, READ_AT)
+    };
+    merge_into_summary(
+        &mut summary,
+        &vault_changes(&address, vault.reserve_bps, &withdraw),
+    );
+    assert_eq!(summary.total_deposited, 460_000);
+    assert_eq!(summary.accrued_fees, 100);
+    let balance = summary.balance.unwrap();
+    assert_eq!(balance.amount, 460_100);
+    assert_eq!(balance.discrepancy, 0);
+
+    let changes = vault_changes(&address, vault.reserve_bps, &claim);
+    assert_eq!(changes[0].change, VaultChange::FeesClaimed);
+    merge_into_summary(&mut summary, &changes);
+    assert_eq!(summary.accrued_fees, 0);
+    let balance = summary.balance.unwrap();
+    assert_eq!(balance.amount, 460_000);
+    assert_eq!(balance.discrepancy, 0);
+}
+
+#[test]
+fn merged_summary_equals_a_fresh_read() {
+    let address = Pubkey::new_unique();
+    let before = vault();
+    let changes = changes(address, &before, Pubkey::new_unique());
+
+    let mut summary = summary_at(address, &before, READ_AT);
+    merge_into_summary(&mut summary, &changes);
+
+    // The vault as read after the last change
+    let after = Vault {
+        total_deposited: 0,
+        total_reserved: 4_000,
+        accounting_anomaly: true,
+        paused: false,
+        ..before
+    };
+    let fresh = VaultSummary {
+        notifications: Some(notifications()),
+        ..summary_at(address, &after, 106)
+    };
+    assert_eq!(summary, fresh);
+}
+
+#[test]
+fn a_live_balance_follows_deposits_and_withdrawals() {
+    let address = Pubkey::new_unique();
+    let before = vault();
+    let changes = changes(address, &before, Pubkey::new_unique());
+
+    // 1_000 tokens were sent straight to the token account
+    let mut summary = VaultSummary {
+        balance: Some(VaultBalance::of(Pubkey::new_unique(), &before, 501_000, 6)),
+        ..summary_at(address, &before, READ_AT)
+    };
+    assert_eq!(summary.balance.unwrap().discrepancy, 1_000);
+    merge_into_summary(&mut summary, &changes[..3]);
+
+    assert_eq!(summary.total_deposited, 598_000);
+    let balance = summary.balance.unwrap();
+    assert_eq!(balance.amount, 599_000);
+    assert_eq!(balance.discrepancy, 1_000);
+}
+
+#[test]
+fn merging_is_idempotent_and_order_of_reads_does_not_matter() {
+    let address = Pubkey::new_unique();
+    let before = vault();
+    let changes = changes(address, &before, Pubkey::new_unique());
+
+    let mut once = summary_at(address, &before, READ_AT);
+    merge_into_summary(&mut once, &changes);
+    let mut twice = once.clone();
+    merge_into_summary(&mut twice, &changes);
+    assert_eq!(twice, once);
+
+    // Merging in two halves ends in the same place
+    let mut halves = summary_at(address, &before, READ_AT);
+    let (first, second) = changes.split_at(3);
+    merge_into_summary(&mut halves, first);
+    merge_into_summary(&mut halves, second);
+    assert_eq!(halves, once);
+}
+
+#[test]
+fn changes_serialize_flat_with_their_kind() {
+    let change = StateChange {
+        slot: 7,
+        signature: Signature::new_unique(),
+        change: VaultChange::Withdrawn { amount: 10, fee: 1 },
+    };
+    let json = serde_json::to_value(&change).unwrap();
+    assert_eq!(json["change"], "withdrawn");
+    assert_eq!(json["amount"], 10);
+    assert_eq!(json["fee"], 1);
+    assert_eq!(json["slot"], 7);
+    assert_eq!(serde_json::from_value::<StateChange>(json).unwrap(), change);
+
+    let cleared = serde_json::to_value(VaultChange::NotificationsCleared).unwrap();
+    assert_eq!(
+        cleared,
+        serde_json::json!({ "change": "notifications_cleared" })
+    );
+    assert!(change.to_string().ends_with(": withdrawn 10"));
+}
+
+/// Needs a validator with the token vault program deployed; see
+/// `fixtures::VALIDATOR_ENV` and `fixtures::PROGRAM_ID_ENV`
+#[test]
+#[ignore = "needs a local validator; run with --ignored"]
+fn changes_since_a_read_bring_its_summary_up_to_date() {
+    let mut fixture = VaultFixture::builder().build().unwrap();
+    fixture.client_mut().with_extensions(true);
+    fixture.deposit_as(0, 1_000_000).unwrap();
+    let mut summary = fixture.client().get_vault_summary().unwrap();
+    let read_at = summary.provenance.unwrap().slot;
+
+    fixture.deposit_as(0, 500_000).unwrap();
+    fixture.withdraw_as(0, 300_000).unwrap();
+    fixture
+        .client()
+        .set_notification_config(fixture.authority(), &notifications())
+        .unwrap();
+
+    let changes = fixture.client().get_changes_since(read_at).unwrap();
+    assert_eq!(changes.len(), 3);
+    assert!(changes.iter().all(|change| change.slot > read_at));
+    merge_into_summary(&mut summary, &changes);
+
+    let mut fresh = fixture.client().get_vault_summary().unwrap();
+    let merged_through = summary.provenance.unwrap().slot;
+    assert!(fresh.provenance.unwrap().slot >= merged_through);
+    // Nothing changed between the last change and the fresh read
+    fresh.provenance = summary.provenance;
+    assert_eq!(summary, fresh);
+}
@@ -0,0 +1,174 @@
+use clap::error::ErrorKind;
+use clap_complete::Shell;
+use std::collections::BTreeMap;
+use token_vault_client::cli::{build_cli, write_completions, write_man_page, CliConfig, Profile};
+
+fn subcommand_names() -> Vec<String> {
+    let cli = build_cli();
+    let mut names = Vec::new();
+    for sub in cli.get_subcommands() {
+        names.push(sub.get_name().to_string());
+        for nested in sub.get_subcommands() {
+            names.push(nested.get_name().to_string());
+        }
+    }
+    names
+}
+
+fn completions(shell: Shell, config: &CliConfig) -> String {
+    let mut out = Vec::new();
+    write_completions(shell, config, &mut out);
+    String::from_utf8(out).unwrap()
+}
+
+#[test]
+fn cli_definition_is_consistent() {
+    build_cli().debug_assert();
+}
+
+#[test]
+fn completions_cover_every_subcommand() {
+    let names = subcommand_names();
+    assert!(names.len() > 10);
+
+    for shell in [Shell::Bash, Shell::Zsh, Shell::Fish, Shell::PowerShell] {
+        let script = completions(shell, &CliConfig::default());
+        assert!(!script.is_empty(), "{} completions are empty", shell);
+        for name in &names {
+            assert!(
+                script.contains(name.as_str()),
+                "{} completions are missing `{}`",
+                shell,
+                name
+            );
+        }
+    }
+}
+
+#[test]
+fn completions_offer_profiles_and_vault_aliases() {
+    let config = CliConfig {
+        profiles: BTreeMap::from([("staging-east".to_string(), Profile::default())]),
+        vaults: BTreeMap::from([(
+            "treasury-q3".to_string(),
+            "9xQeWvG816bUx9EPjHmaT23yvVM2ZWbrrpZb9PusVFin".to_string(),
+        )]),
+        mints: BTreeMap::new(),
+    };
+    // clap_complete's PowerShell script completes flags but not their values
+    for shell in [Shell::Bash, Shell::Zsh, Shell::Fish] {
+        let script = completions(shell, &config);
+        assert!(script.contains("staging-east"), "{} lacks profiles", shell);
+        assert!(
+            script.contains("treasury-q3"),
+            "{} lacks vault aliases",
+            shell
+        );
+    }
+}
+
+#[test]
+fn vault_accepts_addresses_when_aliases_exist() {
+    // Aliases are only completion hints; the parser still takes raw addresses
+    let matches = build_cli()
+        .try_get_matches_from([
+            "token-vault",
+            "--vault",
+            "9xQeWvG816bUx9EPjHmaT23yvVM2ZWbrrpZb9PusVFin",
+            "info",
+        ])
+        .unwrap();
+    assert!(matches.get_one::<String>("vault").is_some());
+}
+
+#[test]
+fn man_page_documents_subcommands() {
+    let mut out = Vec::new();
+    write_man_page(&mut out).unwrap();
+    let page = String::from_utf8(out).unwrap();
+    assert!(page.starts_with(".ie") || page.contains(".TH"));
+    for name in ["init", "deposit", "withdraw", "completions", "man"] {
+        assert!(page.contains(name), "man page is missing `{}`", name);
+    }
+}
+
+#[test]
+fn vault_selectors_are_mutually_exclusive() {
+    let parse = |args: &[&str]| {
+        build_cli().try_get_matches_from(["token-vault"].iter().chain(args).chain(&["info"]))
+    };
+    assert!(parse(&["--vault-name", "treasury"]).is_ok());
+    assert!(parse(&["--alias", "ops"]).is_ok());
+    assert!(parse(&["--vault-name", "treasury", "--alias", "ops"]).is_err());
+    assert!(parse(&[
+        "--vault",
+        "9xQeWvG816bUx9EPjHmaT23yvVM2ZWbrrpZb9PusVFin",
+        "--alias",
+        "ops"
+    ])
+    .is_err());
+}
+
+#[test]
+fn init_needs_an_explicit_withdrawal_limit() {
+    const MINT: &str = "9xQeWvG816bUx9EPjHmaT23yvVM2ZWbrrpZb9PusVFin";
+    let parse = |extra: &[&str]| {
+        let args = ["token-vault", "init", "--mint", MINT, "--name", "treasury"];
+        build_cli().try_get_matches_from(args.iter().chain(extra))
+    };
+    let terms = ["--fee-bps", "25", "--timelock", "3600"];
+    let err = parse(&terms).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::MissingRequiredArgument);
+    assert!(err.to_string().contains("--withdrawal-limit"), "{}", err);
+
+    let limited = [&terms[..], &["--withdrawal-limit", "1000000"]].concat();
+    assert!(parse(&limited).is_ok());
+    let in_tokens = [&terms[..], &["--withdrawal-limit-ui", "1.5"]].concat();
+    assert!(parse(&in_tokens).is_ok());
+    let both = [&limited[..], &["--withdrawal-limit-ui", "1.5"]].concat();
+    assert!(parse(&both).is_err());
+
+    // The fee and timelock are never assumed either
+    for missing in ["--fee-bps", "--timelock"] {
+        let args: Vec<&str> = limited
+            .chunks(2)
+            .filter(|pair| pair[0] != missing)
+            .flatten()
+            .copied()
+            .collect();
+        let err = parse(&args).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::MissingRequiredArgument);
+    }
+}
+
+#[test]
+fn immutable_vaults_need_an_explicit_confirmation() {
+    let parse = |extra: &[&str]| {
+        let args = [
+            "token-vault",
+            "init",
+            "--mint",
+            "9xQeWvG816bUx9EPjHmaT23yvVM2ZWbrrpZb9PusVFin",
+            "--name",
+            "treasury",
+            "--fee-bps",
+            "0",
+            "--timelock",
+            "0",
+            "--withdrawal-limit",
+            "1000000",
+        ];
+        build_cli().try_get_matches_from(args.iter().chain(extra))
+    };
+    let err = parse(&["--immutable"]).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::MissingRequiredArgument);
+    assert!(err.to_string().contains("--i-understand"), "{}", err);
+    assert!(parse(&["--i-understand"]).is_err());
+
+    let immutable = |extra: &[&str]| {
+        let matches = parse(extra).unwrap();
+        matches.subcommand().unwrap().1.get_flag("immutable")
+    };
+    assert!(immutable(&["--immutable", "--i-understand"]));
+    assert!(!immutable(&[]));
+}
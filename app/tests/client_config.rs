@@ -0,0 +1,70 @@
+use anchor_client::solana_sdk::{
+    commitment_config::CommitmentConfig, pubkey::Pubkey, signature::Keypair,
+};
+use anchor_client::Cluster;
+use std::time::Duration;
+use token_vault_client::{CacheConfig, ClientConfig, TokenVaultClient, TransactionVersion};
+
+#[test]
+fn the_default_config_is_what_new_uses() {
+    let config = ClientConfig::default();
+    assert_eq!(config.commitment, CommitmentConfig::confirmed());
+    assert_eq!(config.rpc_timeout, None);
+    assert_eq!(config.transport(&Cluster::Devnet).unwrap(), Cluster::Devnet);
+
+    let client =
+        TokenVaultClient::new(Cluster::Localnet, Keypair::new(), Pubkey::new_unique()).unwrap();
+    assert!(client.deadline().is_none());
+}
+
+#[test]
+fn configured_endpoints_replace_the_cluster_ones() {
+    let config = ClientConfig {
+        rpc_url: Some("http://127.0.0.1:8999".to_string()),
+        ..ClientConfig::default()
+    };
+    assert_eq!(
+        config.transport(&Cluster::Localnet).unwrap(),
+        Cluster::Custom(
+            "http://127.0.0.1:8999".to_string(),
+            "ws://127.0.0.1:8999".to_string()
+        )
+    );
+
+    let config = ClientConfig {
+        ws_url: Some("ws://127.0.0.1:9000".to_string()),
+        ..ClientConfig::default()
+    };
+    assert_eq!(
+        config.transport(&Cluster::Localnet).unwrap(),
+        Cluster::Custom(
+            Cluster::Localnet.url().to_string(),
+            "ws://127.0.0.1:9000".to_string()
+        )
+    );
+}
+
+#[test]
+fn new_with_config_applies_the_endpoint_and_timeout() {
+    let config = ClientConfig {
+        commitment: CommitmentConfig::finalized(),
+        read_commitment: Some(CommitmentConfig::processed()),
+        rpc_timeout: Some(Duration::from_secs(5)),
+        rpc_url: Some("http://127.0.0.1:8999".to_string()),
+        ws_url: None,
+        priority_fee: None,
+        transaction_version: TransactionVersion::Legacy,
+        vault_cache: CacheConfig::default(),
+    };
+    let client = TokenVaultClient::new_with_config(
+        Cluster::Localnet,
+        Keypair::new(),
+        Pubkey::new_unique(),
+        config,
+    )
+    .unwrap();
+    assert_eq!(client.write_rpc_url(), "http://127.0.0.1:8999");
+    assert_eq!(client.read_rpc_url(), "http://127.0.0.1:8999");
+    let remaining = client.deadline().remaining().unwrap();
+    assert!(remaining <= Duration::from_secs(5) && remaining > Duration::from_secs(4));
+}
@@ -0,0 +1,59 @@
+use anchor_client::{
+    solana_sdk::{pubkey::Pubkey, signature::Keypair},
+    Cluster,
+};
+use std::sync::Arc;
+use token_vault_client::clock::ManualClock;
+use token_vault_client::{DepositWindow, DepositWindowStatus, TokenVaultClient, TokenVaultError};
+
+const DAY: i64 = 24 * 60 * 60;
+const NOW: i64 = 1_700_000_000;
+
+/// A client whose clock is faked; nothing here needs a reachable cluster
+fn client_at(now: i64) -> (TokenVaultClient, Arc<ManualClock>) {
+    let mut client =
+        TokenVaultClient::new(Cluster::Localnet, Keypair::new(), Pubkey::new_unique()).unwrap();
+    let clock = Arc::new(ManualClock::new(now));
+    client.with_clock_source(clock.clone());
+    (client, clock)
+}
+
+#[test]
+fn client_reads_the_injected_clock() {
+    let (client, clock) = client_at(NOW);
+    assert_eq!(client.cluster_time().unwrap(), NOW);
+    clock.warp_to_timestamp(NOW + 7 * DAY);
+    assert_eq!(client.cluster_time().unwrap(), NOW + 7 * DAY);
+    clock.advance(-DAY);
+    assert_eq!(client.cluster_time().unwrap(), NOW + 6 * DAY);
+}
+
+#[test]
+fn deposit_window_opens_and_closes_on_the_client_clock() {
+    let (client, clock) = client_at(NOW);
+    let window = DepositWindow::new(NOW + 7 * DAY, NOW + 14 * DAY).unwrap();
+
+    let now = client.cluster_time().unwrap();
+    assert_eq!(
+        window.status(now),
+        DepositWindowStatus::NotYetOpen { opens_in: 7 * DAY }
+    );
+    assert!(matches!(
+        window.check(now),
+        Err(TokenVaultError::DepositWindowClosed { .. })
+    ));
+
+    clock.warp_to_timestamp(NOW + 7 * DAY);
+    assert_eq!(window.check(client.cluster_time().unwrap()), Ok(()));
+
+    clock.warp_to_timestamp(NOW + 14 * DAY - 1);
+    assert_eq!(window.check(client.cluster_time().unwrap()), Ok(()));
+
+    clock.advance(1);
+    assert_eq!(
+        window.status(client.cluster_time().unwrap()),
+        DepositWindowStatus::Closed {
+            closed_at: NOW + 14 * DAY
+        }
+    );
+}
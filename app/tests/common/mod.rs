@@ -0,0 +1,51 @@
+//! Fixtures shared by the integration tests. A test file takes them with
+//! `mod common;`; most use only some of them.
+
+#![allow(dead_code)]
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use token_vault_client::token_vault::state::{DepositorPosition, Vault};
+use token_vault_client::VaultSummary;
+
+/// A key of `n` repeated, for tests that name accounts by number
+pub fn key(n: u8) -> Pubkey {
+    Pubkey::new_from_array([n; 32])
+}
+
+/// A vault with fresh keys, no fee, timelock or withdrawal limit, and
+/// nothing deposited. Tests set what they exercise by struct update:
+/// `Vault { fee_percentage: 25, ..common::vault() }`.
+pub fn vault() -> Vault {
+    Vault {
+        authority: Pubkey::new_unique(),
+        token_mint: Pubkey::new_unique(),
+        fee_collector: Pubkey::new_unique(),
+        withdrawal_limit: u64::MAX,
+        name: "treasury".to_string(),
+        bump: 255,
+        ..Vault::default()
+    }
+}
+
+/// The summary of a fresh vault called `name`, as discovery lists it
+pub fn summary(name: &str) -> VaultSummary {
+    let vault = Vault {
+        name: name.to_string(),
+        ..vault()
+    };
+    VaultSummary::of(Pubkey::new_unique(), &vault)
+}
+
+/// A position in a fresh vault that has put in `deposited` and taken out
+/// `withdrawn`
+pub fn position(deposited: u64, withdrawn: u64) -> DepositorPosition {
+    DepositorPosition {
+        vault: Pubkey::new_unique(),
+        depositor: Pubkey::new_unique(),
+        deposited,
+        withdrawn,
+        last_deposit_ts: 1_700_000_000,
+        last_withdraw_ts: 0,
+        bump: 254,
+    }
+}
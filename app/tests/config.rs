@@ -0,0 +1,160 @@
+use anchor_client::solana_sdk::{
+    commitment_config::CommitmentConfig,
+    pubkey::Pubkey,
+    signature::{write_keypair_file, Keypair},
+};
+use std::fs;
+use std::path::PathBuf;
+use token_vault_client::config::{default_path, CONFIG_ENV, DEFAULT_KEYPAIR_PATH};
+use token_vault_client::{token_vault, ConfigFile, TokenVaultClient};
+
+const CONFIG: &str = r#"
+rpc_url = "devnet"
+keypair_path = "~/.config/solana/devnet.json"
+
+[profiles.mainnet]
+rpc_url = "https://api.mainnet-beta.solana.com"
+ws_url = "wss://mainnet.example.com"
+default_vault = "9xQeWvG816bUx9EPjHmaT23yvVM2ZWbrrpZb9PusVFin"
+commitment = "finalized"
+"#;
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("{}-{}", Pubkey::new_unique(), name))
+}
+
+#[test]
+fn profiles_override_the_top_level_settings() {
+    let config = ConfigFile::parse(CONFIG).unwrap();
+    let resolved = config.settings(Some("mainnet")).unwrap().resolve().unwrap();
+    assert_eq!(
+        resolved.cluster.url(),
+        "https://api.mainnet-beta.solana.com"
+    );
+    assert_eq!(
+        resolved.client_config.ws_url.as_deref(),
+        Some("wss://mainnet.example.com")
+    );
+    assert_eq!(
+        resolved.default_vault,
+        Some(
+            "9xQeWvG816bUx9EPjHmaT23yvVM2ZWbrrpZb9PusVFin"
+                .parse()
+                .unwrap()
+        )
+    );
+    assert_eq!(
+        resolved.client_config.commitment,
+        CommitmentConfig::finalized()
+    );
+    // Not set by the profile, so the top level's
+    assert_eq!(resolved.keypair_path, "~/.config/solana/devnet.json");
+
+    let resolved = config.settings(None).unwrap().resolve().unwrap();
+    assert_eq!(resolved.default_vault, None);
+    assert_eq!(
+        resolved.client_config.commitment,
+        CommitmentConfig::confirmed()
+    );
+}
+
+#[test]
+fn a_missing_file_means_devnet_defaults() {
+    let config = ConfigFile::load(&temp_path("missing.toml")).unwrap();
+    assert_eq!(config, ConfigFile::default());
+    let resolved = config.settings(None).unwrap().resolve().unwrap();
+    assert_eq!(resolved.cluster.url(), "https://api.devnet.solana.com");
+    assert_eq!(resolved.program_id, token_vault::ID);
+    assert_eq!(resolved.keypair_path, DEFAULT_KEYPAIR_PATH);
+    assert_eq!(resolved.client_config.ws_url, None);
+}
+
+#[test]
+fn unknown_keys_are_listed_not_fatal() {
+    let config = ConfigFile::parse(
+        r#"
+        rpc_ulr = "mainnet"
+
+        [profiles.devnet]
+        url = "devnet"
+        keypair = "~/devnet.json"
+        read_url = "https://replica.example.com"
+        strict_mint_policy = true
+        comitment = "finalized"
+
+        [vaults]
+        treasury = "9xQeWvG816bUx9EPjHmaT23yvVM2ZWbrrpZb9PusVFin"
+        "#,
+    )
+    .unwrap();
+    assert_eq!(
+        config.ignored_keys,
+        ["rpc_ulr", "profiles.devnet.comitment"]
+    );
+    // The command line's names for the same settings are understood
+    let devnet = &config.profiles["devnet"];
+    assert_eq!(devnet.rpc_url.as_deref(), Some("devnet"));
+    assert_eq!(devnet.keypair_path.as_deref(), Some("~/devnet.json"));
+}
+
+#[test]
+fn bad_settings_are_refused() {
+    let config = ConfigFile::parse(CONFIG).unwrap();
+    let err = config.settings(Some("testnet")).unwrap_err();
+    assert!(
+        err.to_string().contains("known profiles: mainnet"),
+        "{}",
+        err
+    );
+
+    let config = ConfigFile::parse("commitment = \"max\"").unwrap();
+    let err = config.settings(None).unwrap().resolve().unwrap_err();
+    assert!(
+        err.to_string().contains("Invalid commitment 'max'"),
+        "{}",
+        err
+    );
+
+    let config = ConfigFile::parse("program_id = \"vault\"").unwrap();
+    let err = config.settings(None).unwrap().resolve().unwrap_err();
+    assert!(err.to_string().contains("Invalid program_id"), "{}", err);
+}
+
+#[test]
+fn the_environment_names_the_default_file() {
+    let path = temp_path("config.toml");
+    std::env::set_var(CONFIG_ENV, &path);
+    assert_eq!(default_path(), path);
+    std::env::remove_var(CONFIG_ENV);
+    assert!(default_path().ends_with(".config/token-vault/config.toml"));
+}
+
+#[test]
+fn clients_are_built_from_a_config_file() {
+    let keypair_path = temp_path("payer.json");
+    write_keypair_file(&Keypair::new(), &keypair_path).unwrap();
+    let config_path = temp_path("config.toml");
+    fs::write(
+        &config_path,
+        format!(
+            "[profiles.local]\nrpc_url = \"localnet\"\nkeypair_path = \"{}\"\n",
+            keypair_path.display()
+        ),
+    )
+    .unwrap();
+
+    let client = TokenVaultClient::from_config_profile(Some(&config_path), Some("local")).unwrap();
+    assert_eq!(client.cluster().url(), "http://127.0.0.1:8899");
+
+    // The payer's keypair must be readable
+    fs::write(&config_path, "keypair_path = \"/nonexistent/id.json\"\n").unwrap();
+    let err = TokenVaultClient::from_config(Some(&config_path)).unwrap_err();
+    assert!(
+        err.to_string().contains("Failed to read keypair"),
+        "{}",
+        err
+    );
+
+    fs::remove_file(config_path).unwrap();
+    fs::remove_file(keypair_path).unwrap();
+}
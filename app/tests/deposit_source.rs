@@ -0,0 +1,234 @@
+use anchor_client::solana_sdk::{
+    account::Account, program_option::COption, program_pack::Pack, pubkey::Pubkey,
+    signature::Signer,
+};
+use anchor_spl::associated_token::get_associated_token_address;
+use anchor_spl::token::{self, spl_token};
+use spl_token::state::{Account as TokenAccount, AccountState};
+use token_vault_client::deposit_source::{
+    check_deposit_source, check_source_account, check_token_account_mint,
+};
+use token_vault_client::fixtures::VaultFixture;
+use token_vault_client::{SourceSuggestion, TokenHolding, TokenVaultError};
+
+struct Wallet {
+    owner: Pubkey,
+    usdc: Pubkey,
+    usdt: Pubkey,
+}
+
+impl Wallet {
+    fn new() -> Self {
+        Self {
+            owner: Pubkey::new_unique(),
+            usdc: Pubkey::new_unique(),
+            usdt: Pubkey::new_unique(),
+        }
+    }
+
+    fn holding(&self, mint: Pubkey, amount: u64) -> TokenHolding {
+        TokenHolding {
+            address: get_associated_token_address(&self.owner, &mint),
+            mint,
+            amount,
+        }
+    }
+}
+
+fn mismatch(err: &anyhow::Error) -> (Pubkey, Pubkey, SourceSuggestion) {
+    match err.downcast_ref::<TokenVaultError>() {
+        Some(TokenVaultError::SourceMintMismatch {
+            vault_mint,
+            source_mint,
+            suggestion,
+        }) => (*vault_mint, *source_mint, suggestion.clone()),
+        other => panic!("expected a mint mismatch, got {:?}: {}", other, err),
+    }
+}
+
+#[test]
+fn enough_of_the_vault_mint_passes_whatever_else_is_held() {
+    let wallet = Wallet::new();
+    let holdings = [
+        wallet.holding(wallet.usdc, 500),
+        wallet.holding(wallet.usdt, 9_000),
+    ];
+    check_deposit_source(&wallet.owner, &wallet.usdc, 500, &holdings).unwrap();
+}
+
+#[test]
+fn holding_only_another_mint_names_both_mints() {
+    let wallet = Wallet::new();
+    let holdings = [wallet.holding(wallet.usdt, 9_000)];
+
+    let err = check_deposit_source(&wallet.owner, &wallet.usdc, 500, &holdings).unwrap_err();
+    let (vault_mint, source_mint, suggestion) = mismatch(&err);
+    assert_eq!(vault_mint, wallet.usdc);
+    assert_eq!(source_mint, wallet.usdt);
+    let ata = get_associated_token_address(&wallet.owner, &wallet.usdc);
+    assert_eq!(suggestion, SourceSuggestion::NoAccount { address: ata });
+    assert!(err.to_string().contains(&ata.to_string()), "{}", err);
+}
+
+#[test]
+fn an_empty_account_of_the_vault_mint_is_suggested_with_its_balance() {
+    let wallet = Wallet::new();
+    let other = Pubkey::new_unique();
+    let holdings = [
+        wallet.holding(wallet.usdc, 0),
+        wallet.holding(wallet.usdt, 9_000),
+        wallet.holding(other, 10),
+    ];
+
+    let err = check_deposit_source(&wallet.owner, &wallet.usdc, 500, &holdings).unwrap_err();
+    let (_, source_mint, suggestion) = mismatch(&err);
+    // The largest other holding is the likely mix-up
+    assert_eq!(source_mint, wallet.usdt);
+    assert_eq!(
+        suggestion,
+        SourceSuggestion::Account {
+            address: get_associated_token_address(&wallet.owner, &wallet.usdc),
+            balance: 0,
+        }
+    );
+    assert!(err.to_string().contains("which holds 0"), "{}", err);
+}
+
+#[test]
+fn without_any_token_accounts_the_missing_account_is_named() {
+    let wallet = Wallet::new();
+
+    let err = check_deposit_source(&wallet.owner, &wallet.usdc, 500, &[]).unwrap_err();
+    assert!(err.downcast_ref::<TokenVaultError>().is_none());
+    assert!(err.to_string().contains("has no token account"), "{}", err);
+
+    // Short of the vault's mint, with nothing else held
+    let holdings = [wallet.holding(wallet.usdc, 100)];
+    let err = check_deposit_source(&wallet.owner, &wallet.usdc, 500, &holdings).unwrap_err();
+    assert!(err.to_string().contains("holds 100"), "{}", err);
+}
+
+/// An initialized token account of `mint`, as the cluster would return it
+fn named_account(mint: Pubkey, owner: Pubkey, amount: u64) -> (TokenAccount, Account) {
+    let state = TokenAccount {
+        mint,
+        owner,
+        amount,
+        state: AccountState::Initialized,
+        ..TokenAccount::default()
+    };
+    let mut data = vec![0; TokenAccount::LEN];
+    TokenAccount::pack(state, &mut data).unwrap();
+    let account = Account {
+        lamports: 2_039_280,
+        data,
+        owner: token::ID,
+        executable: false,
+        rent_epoch: 0,
+    };
+    (state, account)
+}
+
+#[test]
+fn a_named_account_of_another_mint_is_a_typed_error() {
+    let wallet = Wallet::new();
+    let address = Pubkey::new_unique();
+    let (_, account) = named_account(wallet.usdt, wallet.owner, 9_000);
+
+    let err = check_token_account_mint(&address, Some(&account), &wallet.usdc).unwrap_err();
+    match err.downcast_ref::<TokenVaultError>() {
+        Some(TokenVaultError::TokenAccountMintMismatch {
+            account,
+            vault_mint,
+            account_mint,
+        }) => {
+            assert_eq!(*account, address);
+            assert_eq!(*vault_mint, wallet.usdc);
+            assert_eq!(*account_mint, wallet.usdt);
+        }
+        other => panic!("expected a mint mismatch, got {:?}: {}", other, err),
+    }
+
+    let (_, account) = named_account(wallet.usdc, wallet.owner, 9_000);
+    let state = check_token_account_mint(&address, Some(&account), &wallet.usdc).unwrap();
+    assert_eq!(state.amount, 9_000);
+}
+
+#[test]
+fn a_named_account_must_exist_and_belong_to_the_token_program() {
+    let wallet = Wallet::new();
+    let address = Pubkey::new_unique();
+
+    let err = check_token_account_mint(&address, None, &wallet.usdc).unwrap_err();
+    assert!(err.to_string().contains("does not exist"), "{}", err);
+
+    let (_, mut account) = named_account(wallet.usdc, wallet.owner, 9_000);
+    account.owner = Pubkey::new_unique();
+    let err = check_token_account_mint(&address, Some(&account), &wallet.usdc).unwrap_err();
+    assert!(err.to_string().contains("not the token program"), "{}", err);
+    assert!(err.downcast_ref::<TokenVaultError>().is_none());
+}
+
+#[test]
+fn a_named_source_is_spent_by_its_owner_or_an_approved_delegate() {
+    let wallet = Wallet::new();
+    let address = Pubkey::new_unique();
+    let (mut state, _) = named_account(wallet.usdc, wallet.owner, 1_000);
+
+    check_source_account(&address, &state, &wallet.owner, 1_000).unwrap();
+    let err = check_source_account(&address, &state, &wallet.owner, 1_001).unwrap_err();
+    assert!(err.to_string().contains("holds 1000"), "{}", err);
+
+    let delegate = Pubkey::new_unique();
+    let err = check_source_account(&address, &state, &delegate, 500).unwrap_err();
+    assert!(err.to_string().contains("neither its owner"), "{}", err);
+
+    state.delegate = COption::Some(delegate);
+    state.delegated_amount = 400;
+    check_source_account(&address, &state, &delegate, 400).unwrap();
+    let err = check_source_account(&address, &state, &delegate, 500).unwrap_err();
+    assert!(err.to_string().contains("only 400"), "{}", err);
+}
+
+/// Needs a validator with the token vault program deployed; see
+/// `fixtures::VALIDATOR_ENV` and `fixtures::PROGRAM_ID_ENV`
+#[test]
+#[ignore = "needs a local validator; run with --ignored"]
+fn depositing_another_mint_fails_before_sending() {
+    let usdc = VaultFixture::builder().build().unwrap();
+    let usdt = VaultFixture::builder().build().unwrap();
+    let holder = &usdt.user(0).unwrap().keypair;
+
+    let err = usdc.client().deposit(holder, 1_000).unwrap_err();
+    let (vault_mint, source_mint, _) = mismatch(&err);
+    assert_eq!(vault_mint, usdc.mint());
+    assert_eq!(source_mint, usdt.mint());
+    // Nothing was sent, so the holder's tokens are untouched
+    assert_eq!(
+        usdt.balance_of(0).unwrap(),
+        usdt.amount(usdt.initial_balance())
+    );
+}
+
+/// Needs a validator with the token vault program deployed
+#[test]
+#[ignore = "needs a local validator; run with --ignored"]
+fn depositing_from_an_account_of_another_mint_fails_before_sending() {
+    let usdc = VaultFixture::builder().build().unwrap();
+    let usdt = VaultFixture::builder().build().unwrap();
+    let holder = &usdt.user(0).unwrap().keypair;
+    let source = get_associated_token_address(&holder.pubkey(), &usdt.mint());
+
+    let err = usdc
+        .client()
+        .deposit_from(holder, source, 1_000)
+        .unwrap_err();
+    assert!(
+        matches!(
+            err.downcast_ref(),
+            Some(TokenVaultError::TokenAccountMintMismatch { .. })
+        ),
+        "{}",
+        err
+    );
+}
@@ -0,0 +1,231 @@
+mod common;
+
+use anchor_client::solana_sdk::{pubkey::Pubkey, signature::Signer};
+use anchor_lang::AccountSerialize;
+use common::summary;
+use std::fs;
+use token_vault_client::discovery::{match_vaults, resolve_vault_name};
+use token_vault_client::fixtures::VaultFixture;
+use token_vault_client::token_vault::state::Vault;
+use token_vault_client::{
+    DustPolicy, NameMatch, ReservePolicy, TokenVaultError, VaultFilter, VaultIndex, VaultSummary,
+};
+
+fn names(vaults: &[&VaultSummary]) -> Vec<String> {
+    vaults.iter().map(|vault| vault.name.clone()).collect()
+}
+
+#[test]
+fn names_match_by_exact_prefix_then_substring_ignoring_case() {
+    assert_eq!(
+        NameMatch::of("Treasury", "treasury"),
+        Some(NameMatch::Exact)
+    );
+    assert_eq!(
+        NameMatch::of("treasury-q3", "TREAS"),
+        Some(NameMatch::Prefix)
+    );
+    assert_eq!(
+        NameMatch::of("ops-treasury", "treasury"),
+        Some(NameMatch::Substring)
+    );
+    assert_eq!(NameMatch::of("payroll", "treasury"), None);
+
+    let vaults = vec![
+        summary("ops-treasury"),
+        summary("treasury-q4"),
+        summary("payroll"),
+        summary("treasury-q3"),
+    ];
+    assert_eq!(
+        names(&match_vaults(&vaults, "treasury")),
+        vec!["treasury-q3", "treasury-q4", "ops-treasury"]
+    );
+}
+
+#[test]
+fn resolution_prefers_an_exact_name() {
+    let vaults = vec![summary("treasury"), summary("treasury-q3")];
+    assert_eq!(
+        resolve_vault_name(&vaults, "treasury").unwrap().address,
+        vaults[0].address
+    );
+    assert_eq!(
+        resolve_vault_name(&vaults, "q3").unwrap().address,
+        vaults[1].address
+    );
+    assert!(resolve_vault_name(&vaults, "payroll").is_err());
+}
+
+#[test]
+fn ambiguous_names_list_every_match() {
+    let vaults = vec![summary("treasury-q3"), summary("treasury-q4")];
+    let err = resolve_vault_name(&vaults, "treasury").unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<TokenVaultError>(),
+        Some(&TokenVaultError::AmbiguousVaultName {
+            pattern: "treasury".to_string(),
+            matches: vec![
+                ("treasury-q3".to_string(), vaults[0].address),
+                ("treasury-q4".to_string(), vaults[1].address),
+            ],
+        })
+    );
+    let message = err.to_string();
+    assert!(message.contains(&vaults[0].address.to_string()));
+    assert!(message.contains(&vaults[1].address.to_string()));
+}
+
+#[test]
+fn index_keeps_aliases_across_refreshes_and_reloads() {
+    let path = std::env::temp_dir().join(format!("token-vault-index-{}.json", std::process::id()));
+    let _ = fs::remove_file(&path);
+    assert_eq!(VaultIndex::load(&path).unwrap(), None);
+
+    let treasury = summary("treasury-q3");
+    let mut index = VaultIndex {
+        program_id: Pubkey::new_unique(),
+        ..Default::default()
+    };
+    index.refresh(
+        "http://localhost:8899",
+        1_700_000_000,
+        vec![treasury.clone()],
+    );
+    index
+        .aliases
+        .insert("ops".to_string(), treasury.address.to_string());
+    index.save(&path).unwrap();
+
+    let mut reloaded = VaultIndex::load(&path).unwrap().unwrap();
+    assert_eq!(reloaded, index);
+    assert_eq!(reloaded.resolve_alias("ops").unwrap(), treasury.address);
+    assert_eq!(reloaded.resolve_name("q3").unwrap(), treasury.address);
+    assert!(reloaded.resolve_alias("payroll").is_err());
+
+    reloaded.refresh(
+        "http://localhost:8899",
+        1_700_000_600,
+        vec![summary("payroll")],
+    );
+    assert_eq!(reloaded.resolve_alias("ops").unwrap(), treasury.address);
+    assert!(reloaded.resolve_name("treasury").is_err());
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn summaries_show_an_immutable_vault() {
+    let vault = Vault {
+        immutable: true,
+        ..common::vault()
+    };
+    let immutable = VaultSummary::of(Pubkey::new_unique(), &vault);
+    assert!(immutable.immutable);
+    assert!(immutable.to_string().contains(", immutable"));
+    assert!(!summary("treasury").to_string().contains("immutable"));
+}
+
+#[test]
+fn summaries_carry_the_vaults_settings() {
+    let vault = Vault {
+        fee_percentage: 25,
+        withdrawal_timelock: 3_600,
+        withdrawal_limit: 1_000_000,
+        dust_threshold: 100,
+        waive_dust_fee: true,
+        reserve_bps: 500,
+        reserve_timelock: 86_400,
+        ..common::vault()
+    };
+    let read = VaultSummary::of(Pubkey::new_unique(), &vault);
+    assert_eq!(read.fee_collector, vault.fee_collector);
+    assert_eq!(read.fee_percentage, 25);
+    assert_eq!(read.withdrawal_timelock, 3_600);
+    assert_eq!(read.withdrawal_limit, 1_000_000);
+    assert_eq!(read.dust_policy(), DustPolicy::of(&vault));
+    assert_eq!(read.reserve_policy(), ReservePolicy::of(&vault));
+}
+
+#[test]
+fn summaries_count_down_the_deposit_window_from_the_cluster_time() {
+    let vault = Vault {
+        deposit_window_start: 1_700_003_600,
+        deposit_window_end: 1_700_090_000,
+        ..common::vault()
+    };
+    let mut read = VaultSummary::of(Pubkey::new_unique(), &vault);
+    assert_eq!(read.deposit_window().start, 1_700_003_600);
+    assert_eq!(read.deposit_countdown(), None);
+    assert!(read
+        .to_string()
+        .contains(", deposits 2023-11-14T23:13:20+00:00 .. "));
+
+    read.cluster_time = Some(1_700_000_000);
+    assert_eq!(
+        read.deposit_countdown().as_deref(),
+        Some("not yet open, opens in 1h")
+    );
+    assert!(read
+        .to_string()
+        .contains(", deposits not yet open, opens in 1h"));
+    read.cluster_time = Some(1_700_086_400);
+    assert_eq!(
+        read.deposit_countdown().as_deref(),
+        Some("open, closes in 1h")
+    );
+    assert!(!summary("treasury").to_string().contains("deposits"));
+}
+
+fn vault_account(authority: Pubkey, token_mint: Pubkey) -> Vec<u8> {
+    let vault = Vault {
+        authority,
+        token_mint,
+        ..common::vault()
+    };
+    let mut data = Vec::new();
+    vault.try_serialize(&mut data).unwrap();
+    data
+}
+
+#[test]
+fn filters_match_the_authority_and_mint_of_a_vault_account() {
+    let (authority, mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let data = vault_account(authority, mint);
+
+    assert!(VaultFilter::Authority(authority)
+        .memcmp()
+        .bytes_match(&data));
+    assert!(VaultFilter::TokenMint(mint).memcmp().bytes_match(&data));
+    // Neither key matches in the other's place
+    assert!(!VaultFilter::Authority(mint).memcmp().bytes_match(&data));
+    assert!(!VaultFilter::TokenMint(authority)
+        .memcmp()
+        .bytes_match(&data));
+    assert!(!VaultFilter::Authority(Pubkey::new_unique())
+        .memcmp()
+        .bytes_match(&data));
+}
+
+/// Needs a validator with the token vault program deployed; see
+/// `fixtures::VALIDATOR_ENV` and `fixtures::PROGRAM_ID_ENV`
+#[test]
+#[ignore = "needs a local validator; run with --ignored"]
+fn vaults_are_listed_by_authority_and_mint() {
+    let fixture = VaultFixture::builder().build().unwrap();
+    let client = fixture.client();
+
+    let owned = client
+        .list_vaults_by_authority(fixture.authority().pubkey())
+        .unwrap();
+    assert!(owned.iter().any(|(address, _)| *address == fixture.vault()));
+    let by_mint = client.list_vaults_by_mint(fixture.mint()).unwrap();
+    assert_eq!(by_mint.len(), 1);
+    assert_eq!(by_mint[0].0, fixture.vault());
+    assert_eq!(by_mint[0].1.token_mint, fixture.mint());
+
+    // No vault holds a fresh mint
+    assert!(client
+        .list_vaults_by_mint(Pubkey::new_unique())
+        .unwrap()
+        .is_empty());
+}
@@ -0,0 +1,211 @@
+mod common;
+
+use token_vault_client::dust::{
+    check_withdrawal_limit, withdrawal_fee, WithdrawCapacity, WithdrawPreview, WithdrawalQuote,
+};
+use token_vault_client::token_vault::state::Vault;
+use token_vault_client::{DustMode, DustPolicy, TokenVaultError};
+
+const THRESHOLD: u64 = 1_000;
+
+fn vault(fee_percentage: u16, policy: DustPolicy) -> Vault {
+    Vault {
+        fee_percentage,
+        dust_threshold: policy.threshold,
+        waive_dust_fee: policy.mode == DustMode::WaiveFee,
+        ..common::vault()
+    }
+}
+
+#[test]
+fn zero_amount_is_always_rejected() {
+    for policy in [
+        DustPolicy::DISABLED,
+        DustPolicy::reject_below(THRESHOLD),
+        DustPolicy::waive_fee_below(THRESHOLD),
+    ] {
+        assert_eq!(
+            WithdrawalQuote::for_vault(&vault(100, policy), 0),
+            Err(TokenVaultError::ZeroAmount {
+                operation: "withdraw"
+            })
+        );
+    }
+}
+
+#[test]
+fn reject_mode_around_the_threshold() {
+    let vault = vault(100, DustPolicy::reject_below(THRESHOLD));
+    for amount in [1, THRESHOLD - 1] {
+        assert_eq!(
+            WithdrawalQuote::for_vault(&vault, amount),
+            Err(TokenVaultError::DustWithdrawal {
+                amount,
+                threshold: THRESHOLD
+            })
+        );
+    }
+
+    let at = WithdrawalQuote::for_vault(&vault, THRESHOLD).unwrap();
+    assert!(!at.is_dust);
+    assert_eq!(at.fee, 10);
+    assert_eq!(at.net_amount, THRESHOLD - 10);
+
+    let above = WithdrawalQuote::for_vault(&vault, THRESHOLD + 1).unwrap();
+    assert!(!above.is_dust);
+    assert_eq!(above.fee, 10);
+}
+
+#[test]
+fn waive_fee_mode_around_the_threshold() {
+    let vault = vault(100, DustPolicy::waive_fee_below(THRESHOLD));
+    for amount in [1, THRESHOLD - 1] {
+        let quote = WithdrawalQuote::for_vault(&vault, amount).unwrap();
+        assert!(quote.is_dust);
+        assert!(quote.fee_waived);
+        assert!(!quote.fee_rounds_to_zero);
+        assert_eq!(quote.fee, 0);
+        assert_eq!(quote.net_amount, amount);
+    }
+
+    for amount in [THRESHOLD, THRESHOLD + 1] {
+        let quote = WithdrawalQuote::for_vault(&vault, amount).unwrap();
+        assert!(!quote.is_dust);
+        assert!(!quote.fee_waived);
+        assert_eq!(quote.fee, 10);
+    }
+}
+
+#[test]
+fn disabled_policy_flags_fees_that_round_to_zero() {
+    let vault = vault(100, DustPolicy::DISABLED);
+
+    let one = WithdrawalQuote::for_vault(&vault, 1).unwrap();
+    assert!(!one.is_dust);
+    assert!(one.fee_rounds_to_zero);
+    assert_eq!(one.fee, 0);
+    assert_eq!(one.net_amount, 1);
+
+    // 1% of 100 is the smallest amount with a non-zero fee
+    assert!(
+        WithdrawalQuote::for_vault(&vault, 99)
+            .unwrap()
+            .fee_rounds_to_zero
+    );
+    assert!(
+        !WithdrawalQuote::for_vault(&vault, 100)
+            .unwrap()
+            .fee_rounds_to_zero
+    );
+}
+
+#[test]
+fn fee_free_vault_never_reports_rounding() {
+    let vault = vault(0, DustPolicy::DISABLED);
+    let quote = WithdrawalQuote::for_vault(&vault, 1).unwrap();
+    assert_eq!(quote.fee, 0);
+    assert!(!quote.fee_rounds_to_zero);
+}
+
+#[test]
+fn fee_does_not_overflow() {
+    assert_eq!(withdrawal_fee(u64::MAX, 10_000), u64::MAX);
+    assert_eq!(withdrawal_fee(u64::MAX, 1), u64::MAX / 10_000);
+}
+
+#[test]
+fn previews_flag_amounts_over_the_withdrawal_limit() {
+    let mut vault = vault(250, DustPolicy::DISABLED);
+    vault.withdrawal_limit = 10_000;
+
+    let at = WithdrawPreview::for_vault(&vault, 10_000).unwrap();
+    assert!(!at.exceeds_limit);
+    assert_eq!((at.quote.fee, at.quote.net_amount), (250, 9_750));
+    assert_eq!(at.to_string(), "withdraw 10000: fee 250, receive 9750");
+
+    let over = WithdrawPreview::for_vault(&vault, 10_001).unwrap();
+    assert!(over.exceeds_limit);
+    assert_eq!(
+        over.to_string(),
+        "withdraw 10001: fee 250, receive 9751 (exceeds the withdrawal limit of 10000)"
+    );
+}
+
+#[test]
+fn withdrawals_over_the_limit_are_refused() {
+    let mut vault = vault(250, DustPolicy::DISABLED);
+    vault.withdrawal_limit = 10_000;
+    assert_eq!(check_withdrawal_limit(&vault, 10_000), Ok(()));
+    assert_eq!(
+        check_withdrawal_limit(&vault, 10_001),
+        Err(TokenVaultError::WithdrawalLimitExceeded {
+            requested: 10_001,
+            remaining: 10_000
+        })
+    );
+}
+
+#[test]
+fn capacity_is_the_per_withdrawal_limit_and_never_resets() {
+    let mut vault = vault(250, DustPolicy::DISABLED);
+    vault.withdrawal_limit = 10_000;
+    let capacity = WithdrawCapacity::of(&vault);
+    assert_eq!(
+        capacity,
+        WithdrawCapacity {
+            remaining: 10_000,
+            window_resets_at: None
+        }
+    );
+    assert_eq!(capacity.check(10_000), Ok(()));
+    assert_eq!(
+        capacity.check(10_001),
+        Err(TokenVaultError::WithdrawalLimitExceeded {
+            requested: 10_001,
+            remaining: 10_000
+        })
+    );
+}
+
+#[test]
+fn previews_round_the_fee_down_for_tiny_amounts() {
+    // 3% of 33 is 0.99, of 34 is 1.02
+    let vault = vault(300, DustPolicy::DISABLED);
+    let below = WithdrawPreview::for_vault(&vault, 33).unwrap();
+    assert_eq!((below.quote.fee, below.quote.net_amount), (0, 33));
+    assert!(below.quote.fee_rounds_to_zero);
+    let above = WithdrawPreview::for_vault(&vault, 34).unwrap();
+    assert_eq!((above.quote.fee, above.quote.net_amount), (1, 33));
+
+    let free = WithdrawPreview::for_vault(&vault(0, DustPolicy::DISABLED), 1).unwrap();
+    assert_eq!((free.quote.fee, free.quote.net_amount), (0, 1));
+    assert!(!free.quote.fee_rounds_to_zero);
+}
+
+#[test]
+fn previews_near_u64_max_do_not_overflow() {
+    for (fee_percentage, fee) in [
+        (10_000, u64::MAX),
+        (
+            9_999,
+            u64::MAX / 10_000 * 9_999 + (u64::MAX % 10_000) * 9_999 / 10_000,
+        ),
+        (1, u64::MAX / 10_000),
+    ] {
+        let preview =
+            WithdrawPreview::for_vault(&vault(fee_percentage, DustPolicy::DISABLED), u64::MAX)
+                .unwrap();
+        assert_eq!(preview.quote.fee, fee, "{} bps", fee_percentage);
+        assert_eq!(preview.quote.net_amount, u64::MAX - fee);
+        assert!(!preview.exceeds_limit);
+    }
+}
+
+#[test]
+fn previews_serialize_the_quote_inline() {
+    let preview = WithdrawPreview::for_vault(&vault(100, DustPolicy::DISABLED), 1_000).unwrap();
+    let json = serde_json::to_value(preview).unwrap();
+    assert_eq!(json["fee"], 10);
+    assert_eq!(json["net_amount"], 990);
+    assert_eq!(json["exceeds_limit"], false);
+}
@@ -0,0 +1,119 @@
+use anchor_client::solana_sdk::{pubkey::Pubkey, signature::Keypair};
+use anchor_client::Cluster;
+use token_vault_client::endpoints::{custom_cluster, infer_ws_url};
+use token_vault_client::{EndpointHealth, EndpointStatus, TokenVaultClient};
+
+fn status(url: &str, slot: Result<u64, &str>) -> EndpointStatus {
+    EndpointStatus {
+        url: url.to_string(),
+        slot: slot.map_err(str::to_string),
+    }
+}
+
+#[test]
+fn lag_is_the_write_slot_minus_the_read_slot() {
+    let mut health = EndpointHealth {
+        write: status("https://premium.example.com", Ok(1_000)),
+        read: Some(status("https://replica.example.com", Ok(990))),
+    };
+    assert_eq!(health.lag(), Some(10));
+    assert!(health.to_string().ends_with("Replica lag: 10 slots"));
+
+    health.read = Some(status(
+        "https://replica.example.com",
+        Err("connection refused"),
+    ));
+    assert_eq!(health.lag(), None);
+    assert!(health
+        .to_string()
+        .contains("Read RPC: https://replica.example.com unreachable: connection refused"));
+
+    health.read = None;
+    assert_eq!(health.lag(), None);
+    assert!(health.to_string().ends_with("Read RPC: same as write"));
+}
+
+#[test]
+fn reads_default_to_the_write_endpoint() {
+    let mut client =
+        TokenVaultClient::new(Cluster::Localnet, Keypair::new(), Pubkey::new_unique()).unwrap();
+    assert_eq!(client.read_rpc_url(), "http://127.0.0.1:8899");
+    assert_eq!(client.write_rpc_url(), "http://127.0.0.1:8899");
+
+    client.with_read_rpc("https://replica.example.com").unwrap();
+    client
+        .with_write_rpc("https://premium.example.com")
+        .unwrap();
+    assert_eq!(client.read_rpc_url(), "https://replica.example.com");
+    assert_eq!(client.write_rpc_url(), "https://premium.example.com");
+
+    assert!(client.with_read_rpc("replica.example.com").is_err());
+}
+
+#[test]
+fn custom_rpc_urls_are_validated_and_get_a_matching_websocket() {
+    assert_eq!(
+        custom_cluster("https://rpc.example.com/?api-key=abc", None).unwrap(),
+        Cluster::Custom(
+            "https://rpc.example.com/?api-key=abc".to_string(),
+            "wss://rpc.example.com/?api-key=abc".to_string()
+        )
+    );
+    assert_eq!(
+        infer_ws_url("http://10.0.0.5:8899").unwrap(),
+        "ws://10.0.0.5:8899"
+    );
+    assert_eq!(
+        custom_cluster("http://10.0.0.5:8899", Some("ws://10.0.0.5:8900")).unwrap(),
+        Cluster::Custom(
+            "http://10.0.0.5:8899".to_string(),
+            "ws://10.0.0.5:8900".to_string()
+        )
+    );
+
+    for (rpc_url, ws_url, message) in [
+        ("rpc.example.com", None, "missing scheme"),
+        (
+            "ftp://rpc.example.com",
+            None,
+            "scheme must be http or https",
+        ),
+        ("https://", None, "missing host"),
+        ("https://:8899", None, "missing host"),
+        ("https://rpc.example .com", None, "contains whitespace"),
+        (
+            "https://rpc.example.com",
+            Some("https://rpc.example.com"),
+            "scheme must be ws or wss",
+        ),
+    ] {
+        let err = custom_cluster(rpc_url, ws_url).unwrap_err().to_string();
+        assert!(err.contains(message), "{}: {}", rpc_url, err);
+    }
+}
+
+#[test]
+fn clients_for_a_custom_rpc_report_their_endpoint() {
+    let client = TokenVaultClient::new_with_rpc(
+        "http://127.0.0.1:8999",
+        None,
+        Keypair::new(),
+        Pubkey::new_unique(),
+    )
+    .unwrap();
+    assert_eq!(client.rpc_url(), "http://127.0.0.1:8999");
+    assert_eq!(
+        client.cluster(),
+        &Cluster::Custom(
+            "http://127.0.0.1:8999".to_string(),
+            "ws://127.0.0.1:8999".to_string()
+        )
+    );
+    assert!(TokenVaultClient::new_with_rpc(
+        "127.0.0.1:8999",
+        None,
+        Keypair::new(),
+        Pubkey::new_unique()
+    )
+    .is_err());
+}
@@ -0,0 +1,134 @@
+use anchor_client::solana_client::{
+    client_error::{ClientError, ClientErrorKind},
+    rpc_request::{RpcError, RpcResponseErrorData},
+    rpc_response::RpcSimulateTransactionResult,
+};
+use anchor_client::solana_sdk::{
+    instruction::InstructionError, pubkey::Pubkey, signature::Keypair,
+    transaction::TransactionError,
+};
+use anchor_client::Cluster;
+use anyhow::{anyhow, Context};
+use token_vault_client::error::parse_program_error;
+use token_vault_client::json::ErrorBody;
+use token_vault_client::{TokenVaultClient, TokenVaultError};
+
+const NOT_INITIALIZED: &str = "Program log: AnchorError caused by account: vault. Error Code: \
+     AccountNotInitialized. Error Number: 3012. Error Message: The program expected this \
+     account to be already initialized.";
+
+fn preflight_failure(logs: Vec<String>) -> ClientError {
+    ClientErrorKind::RpcError(RpcError::RpcResponseError {
+        code: -32002,
+        message: "Transaction simulation failed".to_string(),
+        data: RpcResponseErrorData::SendTransactionPreflightFailure(RpcSimulateTransactionResult {
+            err: Some(TransactionError::InstructionError(
+                0,
+                InstructionError::Custom(3012),
+            )),
+            logs: Some(logs),
+            accounts: None,
+            units_consumed: None,
+            return_data: None,
+            inner_instructions: None,
+        }),
+    })
+    .into()
+}
+
+#[test]
+fn anchor_error_lines_give_code_and_name() {
+    let logs = vec![
+        "Program log: Instruction: Deposit".to_string(),
+        NOT_INITIALIZED.to_string(),
+    ];
+    assert_eq!(
+        parse_program_error(&logs),
+        Some((3012, "AccountNotInitialized".to_string()))
+    );
+    assert_eq!(
+        parse_program_error(&["Program log: Instruction: Deposit".to_string()]),
+        None
+    );
+}
+
+#[test]
+fn a_typed_error_survives_added_context() {
+    let vault = Pubkey::new_unique();
+    let err = anyhow::Error::from(TokenVaultError::VaultNotFound { vault })
+        .context("Failed to read the vault");
+    assert_eq!(
+        TokenVaultError::from(err),
+        TokenVaultError::VaultNotFound { vault }
+    );
+}
+
+#[test]
+fn a_preflight_failure_is_a_named_program_error() {
+    let logs = vec![NOT_INITIALIZED.to_string()];
+    let err = anyhow::Error::from(preflight_failure(logs.clone())).context("Deposit failed");
+    assert_eq!(
+        TokenVaultError::of(&err),
+        TokenVaultError::ProgramError {
+            code: 3012,
+            name: Some("AccountNotInitialized".to_string()),
+            logs,
+        }
+    );
+
+    let body = ErrorBody::of(&err);
+    assert_eq!(body.code, "program_error");
+    assert_eq!(body.details["code"], 3012);
+    assert_eq!(body.details["name"], "AccountNotInitialized");
+}
+
+#[test]
+fn a_custom_code_without_logs_is_an_unnamed_program_error() {
+    let err: anyhow::Error = ClientError::from(ClientErrorKind::TransactionError(
+        TransactionError::InstructionError(1, InstructionError::Custom(6000)),
+    ))
+    .into();
+    assert_eq!(
+        TokenVaultError::of(&err),
+        TokenVaultError::ProgramError {
+            code: 6000,
+            name: None,
+            logs: Vec::new(),
+        }
+    );
+}
+
+#[test]
+fn other_client_failures_are_rpc_errors() {
+    let err: anyhow::Error =
+        ClientError::from(ClientErrorKind::Custom("connection refused".to_string())).into();
+    match TokenVaultError::of(&err) {
+        TokenVaultError::Rpc { message } => assert!(message.contains("connection refused")),
+        other => panic!("expected an RPC error, got {:?}", other),
+    }
+    assert_eq!(ErrorBody::of(&err).code, "rpc");
+}
+
+#[test]
+fn untyped_errors_keep_their_whole_message() {
+    let err = Err::<(), _>(anyhow!("bad input"))
+        .context("Failed to parse")
+        .unwrap_err();
+    assert_eq!(
+        TokenVaultError::of(&err),
+        TokenVaultError::Other {
+            message: "Failed to parse: bad input".to_string(),
+        }
+    );
+}
+
+#[test]
+fn calls_without_a_vault_fail_with_vault_not_set() {
+    let client =
+        TokenVaultClient::new(Cluster::Localnet, Keypair::new(), Pubkey::new_unique()).unwrap();
+    let err = match client.get_vault_info() {
+        Ok(_) => panic!("read a vault without an address"),
+        Err(err) => err,
+    };
+    assert_eq!(TokenVaultError::of(&err), TokenVaultError::VaultNotSet);
+}
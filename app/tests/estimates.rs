@@ -0,0 +1,161 @@
+use anchor_client::solana_sdk::{
+    program_pack::Pack,
+    pubkey::Pubkey,
+    rent::Rent,
+    signature::{Keypair, Signer},
+};
+use anchor_client::Cluster;
+use anchor_spl::token::spl_token::state::Account as TokenAccount;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use token_vault_client::estimates::VAULT_ACCOUNT_LEN;
+use token_vault_client::{EstimateTables, PriorityFees, TokenVaultClient};
+
+const LOCALNET: &str = "http://127.0.0.1:8899";
+const HOUR: Duration = Duration::from_secs(60 * 60);
+
+fn tables(refreshed_at: i64) -> EstimateTables {
+    let rent = Rent::default();
+    EstimateTables {
+        cluster: LOCALNET.to_string(),
+        slot: 250_000_000,
+        refreshed_at,
+        lamports_per_byte_year: rent.lamports_per_byte_year,
+        exemption_threshold: rent.exemption_threshold,
+        lamports_per_signature: 5_000,
+        priority_fees: PriorityFees::of(&[0, 100, 200, 300]),
+    }
+}
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64
+}
+
+/// A client for a cluster that is not running, so any refresh fails
+fn offline_client() -> TokenVaultClient {
+    TokenVaultClient::new(Cluster::Localnet, Keypair::new(), Pubkey::new_unique()).unwrap()
+}
+
+#[test]
+fn rent_and_fees_come_from_the_tables() {
+    let tables = tables(0);
+    assert_eq!(
+        tables.rent_exempt_minimum(TokenAccount::LEN),
+        Rent::default().minimum_balance(TokenAccount::LEN)
+    );
+    assert_eq!(tables.fee_for(2), 10_000);
+}
+
+#[test]
+fn priority_fee_percentiles() {
+    let samples: Vec<u64> = (1..=100).collect();
+    assert_eq!(
+        PriorityFees::of(&samples),
+        PriorityFees {
+            p50: 50,
+            p75: 75,
+            p90: 90,
+        }
+    );
+    assert_eq!(PriorityFees::of(&[]), PriorityFees::default());
+    assert_eq!(PriorityFees::of(&[7]).p90, 7);
+}
+
+#[test]
+fn tables_go_stale_at_their_ttl() {
+    let tables = tables(1_000);
+    assert!(!tables.is_stale(1_000 + 3_599, HOUR));
+    assert!(tables.is_stale(1_000 + 3_600, HOUR));
+}
+
+#[test]
+fn estimates_name_their_slot_and_flag_staleness() {
+    let tables = tables(0);
+    let fresh = tables.cost(2_000_000, 5_000, false).to_string();
+    assert!(
+        fresh.contains("estimates as of slot 250000000"),
+        "{}",
+        fresh
+    );
+    assert!(!fresh.contains("STALE"), "{}", fresh);
+    assert!(tables.cost(0, 5_000, true).to_string().contains("STALE"));
+}
+
+#[test]
+fn saved_tables_load_only_for_their_cluster() {
+    let path = std::env::temp_dir()
+        .join(format!("estimates-{}", Pubkey::new_unique()))
+        .join("estimates.json");
+    assert_eq!(EstimateTables::load(&path, LOCALNET).unwrap(), None);
+
+    let saved = tables(now());
+    saved.save(&path).unwrap();
+    let loaded = EstimateTables::load(&path, LOCALNET).unwrap();
+    let elsewhere = EstimateTables::load(&path, "https://api.devnet.solana.com").unwrap();
+    std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+
+    assert_eq!(loaded, Some(saved));
+    assert_eq!(elsewhere, None);
+}
+
+#[test]
+fn fresh_tables_answer_without_the_cluster() {
+    let mut client = offline_client();
+    client.with_estimate_tables(tables(now()));
+
+    let authority = Keypair::new().pubkey();
+    let cost = client.estimate_init_cost(&authority).unwrap();
+    let expected = tables(0);
+    assert_eq!(
+        cost.rent_lamports,
+        expected.rent_exempt_minimum(VAULT_ACCOUNT_LEN)
+            + 2 * expected.rent_exempt_minimum(TokenAccount::LEN)
+    );
+    // The authority signs alongside the payer
+    assert_eq!(cost.fee_lamports, 10_000);
+    assert_eq!(cost.as_of_slot, 250_000_000);
+    assert!(!cost.stale);
+}
+
+#[test]
+fn stale_tables_are_used_when_the_refresh_fails() {
+    let mut client = offline_client();
+    client.with_estimate_tables(tables(now() - 2 * 60 * 60));
+
+    let (tables, stale) = client.estimates().unwrap();
+    assert!(stale);
+    assert_eq!(tables.slot, 250_000_000);
+    assert!(
+        client
+            .estimate_init_cost(&Pubkey::new_unique())
+            .unwrap()
+            .stale
+    );
+
+    // A longer TTL makes the same tables fresh again
+    client.with_estimates_ttl(3 * HOUR);
+    assert!(!client.estimates().unwrap().1);
+}
+
+#[test]
+fn without_tables_an_unreachable_cluster_is_an_error() {
+    let client = offline_client();
+    assert!(client.refresh_estimates().is_err());
+    assert!(client.estimates().is_err());
+}
+
+#[test]
+fn a_client_starts_from_tables_saved_by_another_process() {
+    let path = std::env::temp_dir().join(format!("estimates-{}.json", Pubkey::new_unique()));
+    tables(now()).save(&path).unwrap();
+
+    let mut client = offline_client();
+    client.with_estimates_path(path.clone()).unwrap();
+    let (tables, stale) = client.estimates().unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(tables.slot, 250_000_000);
+    assert!(!stale);
+}
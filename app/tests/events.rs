@@ -0,0 +1,152 @@
+mod common;
+
+use anchor_client::solana_sdk::signature::Signature;
+use anchor_lang::Event;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use common::key;
+use solana_transaction_status::{
+    EncodedConfirmedTransactionWithStatusMeta, EncodedTransaction,
+    EncodedTransactionWithStatusMeta, TransactionStatusMeta,
+};
+use token_vault_client::events::{parse_events, transaction_events};
+use token_vault_client::token_vault::{self, events};
+use token_vault_client::{DepositEvent, TxReceipt, VaultEvent, WithdrawEvent};
+
+fn data_log(data: &[u8]) -> String {
+    format!("Program data: {}", STANDARD.encode(data))
+}
+
+fn deposit_log(amount: u64) -> String {
+    data_log(
+        &events::DepositEvent {
+            vault: key(1),
+            depositor: key(2),
+            amount,
+        }
+        .data(),
+    )
+}
+
+fn withdraw_log(amount: u64, fee: u64) -> String {
+    data_log(
+        &events::WithdrawEvent {
+            vault: key(1),
+            withdrawer: key(2),
+            recipient: key(3),
+            amount,
+            fee,
+        }
+        .data(),
+    )
+}
+
+#[test]
+fn deposits_and_withdrawals_keep_their_order() {
+    let logs = vec![
+        "Program log: Instruction: Deposit".to_string(),
+        deposit_log(1_000),
+        withdraw_log(400, 4),
+    ];
+    assert_eq!(
+        parse_events(&logs, Some(1_700_000_000)),
+        vec![
+            VaultEvent::Deposit(DepositEvent {
+                vault: key(1),
+                depositor: key(2),
+                amount: 1_000,
+                timestamp: Some(1_700_000_000),
+                memo: None,
+            }),
+            VaultEvent::Withdraw(WithdrawEvent {
+                vault: key(1),
+                withdrawer: key(2),
+                recipient: key(3),
+                amount: 400,
+                fee: 4,
+                timestamp: Some(1_700_000_000),
+                memo: None,
+            }),
+        ]
+    );
+}
+
+#[test]
+fn bad_lines_are_skipped_not_fatal() {
+    let mut truncated = events::DepositEvent {
+        vault: key(1),
+        depositor: key(2),
+        amount: 5,
+    }
+    .data();
+    truncated.truncate(20);
+    let anomaly = events::AccountingAnomaly {
+        vault: key(1),
+        counter: "total_deposited".to_string(),
+        recorded: 3,
+        amount: 5,
+    }
+    .data();
+    let logs = vec![
+        "Program data: not base64!".to_string(),
+        data_log(&[1, 2, 3]),
+        data_log(&[9; 40]),
+        data_log(&truncated),
+        data_log(&anomaly),
+        deposit_log(7),
+    ];
+    let parsed = parse_events(&logs, None);
+    assert_eq!(parsed.len(), 1);
+    assert!(matches!(&parsed[0], VaultEvent::Deposit(event) if event.amount == 7));
+}
+
+#[test]
+fn only_the_vault_programs_lines_count() {
+    let program = token_vault::ID.to_string();
+    let other = key(9).to_string();
+    let logs = vec![
+        format!("Program {} invoke [1]", other),
+        deposit_log(1),
+        format!("Program {} success", other),
+        format!("Program {} invoke [1]", program),
+        deposit_log(2),
+        format!("Program {} success", program),
+    ];
+    let meta = TransactionStatusMeta {
+        log_messages: Some(logs),
+        ..Default::default()
+    };
+    let transaction = EncodedConfirmedTransactionWithStatusMeta {
+        slot: 42,
+        transaction: EncodedTransactionWithStatusMeta {
+            transaction: EncodedTransaction::LegacyBinary(String::new()),
+            meta: Some(meta.into()),
+            version: None,
+        },
+        block_time: Some(1_700_000_000),
+    };
+    let parsed = transaction_events(&token_vault::ID, &transaction);
+    assert!(matches!(
+        parsed.as_slice(),
+        [VaultEvent::Deposit(DepositEvent {
+            amount: 2,
+            timestamp: Some(1_700_000_000),
+            ..
+        })]
+    ));
+}
+
+#[test]
+fn receipts_carry_events_only_when_looked_up() {
+    let receipt = TxReceipt::signature_only(Signature::new_unique());
+    let json = serde_json::to_value(&receipt).unwrap();
+    assert!(json.get("events").is_none());
+
+    let receipt = TxReceipt {
+        events: Some(parse_events(&[deposit_log(3)], None)),
+        ..receipt
+    };
+    let json = serde_json::to_value(&receipt).unwrap();
+    assert_eq!(json["events"][0]["kind"], "deposit");
+    assert_eq!(json["events"][0]["depositor"], key(2).to_string());
+    assert_eq!(serde_json::from_value::<TxReceipt>(json).unwrap(), receipt);
+}
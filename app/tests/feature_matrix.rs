@@ -0,0 +1,25 @@
+use std::path::Path;
+use std::process::Command;
+
+/// Compiles the client once per feature combination, which takes minutes
+#[test]
+#[ignore = "slow: compiles every feature combination; run with --ignored"]
+fn every_feature_combination_compiles() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).parent().unwrap();
+    let status = Command::new(env!("CARGO"))
+        .current_dir(root)
+        .args([
+            "run",
+            "--quiet",
+            "--package",
+            "xtask",
+            "--",
+            "feature-matrix",
+        ])
+        .status()
+        .expect("failed to run cargo");
+    assert!(
+        status.success(),
+        "see the output above for the failing combination"
+    );
+}
@@ -0,0 +1,67 @@
+use token_vault_client::{Feature, FeatureSet, ProgramFeatures, TokenVaultError};
+
+fn program(enabled: &[Feature], min_client_version: &str) -> ProgramFeatures {
+    ProgramFeatures {
+        enabled: FeatureSet::of(enabled),
+        min_client_version: min_client_version.to_string(),
+    }
+}
+
+#[test]
+fn supported_features_pass() {
+    let features = program(&[Feature::DustPolicy, Feature::Allowlist], "0.1.0");
+    assert_eq!(features.check(FeatureSet::SUPPORTED, "0.1.0"), Ok(()));
+    assert_eq!(program(&[], "").check(FeatureSet::EMPTY, "0.0.1"), Ok(()));
+}
+
+#[test]
+fn unsupported_feature_is_required() {
+    let features = program(&[Feature::TwoStepWithdrawals], "");
+    assert_eq!(
+        features.check(FeatureSet::SUPPORTED, "9.9.9"),
+        Err(TokenVaultError::FeatureRequired {
+            feature: "two-step-withdrawals".to_string()
+        })
+    );
+}
+
+#[test]
+fn unknown_bits_are_required_features() {
+    let features = ProgramFeatures {
+        enabled: FeatureSet(1 << 40),
+        min_client_version: String::new(),
+    };
+    assert_eq!(
+        features.check(FeatureSet(u64::MAX), "1.0.0"),
+        Err(TokenVaultError::FeatureRequired {
+            feature: "bit 40".to_string()
+        })
+    );
+}
+
+#[test]
+fn versions_compare_numerically() {
+    let features = program(&[], "0.10.0");
+    assert_eq!(
+        features.check(FeatureSet::SUPPORTED, "0.9.3"),
+        Err(TokenVaultError::ClientTooOld {
+            minimum: "0.10.0".to_string()
+        })
+    );
+    assert_eq!(features.check(FeatureSet::SUPPORTED, "0.10.0"), Ok(()));
+    assert_eq!(features.check(FeatureSet::SUPPORTED, "0.10.1-beta"), Ok(()));
+    assert_eq!(features.check(FeatureSet::SUPPORTED, "1.0"), Ok(()));
+}
+
+#[test]
+fn errors_tell_how_to_fix_them() {
+    let message = TokenVaultError::ClientTooOld {
+        minimum: "2.0.0".to_string(),
+    }
+    .to_string();
+    assert!(
+        message.contains("2.0.0") && message.contains("upgrade"),
+        "{}",
+        message
+    );
+}
@@ -0,0 +1,55 @@
+mod common;
+
+use anchor_lang::{AccountDeserialize, AccountSerialize, InstructionData, ToAccountMetas};
+use common::key;
+use token_vault_client::estimates::VAULT_ACCOUNT_LEN;
+use token_vault_client::token_vault::{
+    accounts, instruction, pda::MAX_VAULT_NAME_LEN, state::Vault,
+};
+
+#[test]
+fn only_the_fee_collector_signs() {
+    let metas = accounts::ClaimFees {
+        fee_collector: key(1),
+        vault: key(2),
+        vault_token_account: key(3),
+        fee_collector_token_account: key(4),
+        token_program: key(5),
+    }
+    .to_account_metas(None);
+    let signers: Vec<_> = metas
+        .iter()
+        .filter(|m| m.is_signer)
+        .map(|m| m.pubkey)
+        .collect();
+    assert_eq!(signers, vec![key(1)]);
+    let writable: Vec<_> = metas
+        .iter()
+        .filter(|m| m.is_writable)
+        .map(|m| m.pubkey)
+        .collect();
+    assert_eq!(writable, vec![key(2), key(3), key(4)]);
+    assert_eq!(
+        instruction::ClaimFees {}.data(),
+        [82, 251, 233, 156, 12, 52, 184, 202]
+    );
+}
+
+#[test]
+fn accrued_fees_fit_in_the_vault_account() {
+    let vault = Vault {
+        authority: key(1),
+        token_mint: key(2),
+        fee_collector: key(3),
+        fee_percentage: 25,
+        total_deposited: 1_000,
+        name: "v".repeat(MAX_VAULT_NAME_LEN),
+        accrued_fees: 42,
+        ..common::vault()
+    };
+    let mut data = Vec::new();
+    vault.try_serialize(&mut data).unwrap();
+    assert_eq!(data.len(), VAULT_ACCOUNT_LEN);
+    let read = Vault::try_deserialize(&mut data.as_slice()).unwrap();
+    assert_eq!(read.accrued_fees, 42);
+}
@@ -0,0 +1,200 @@
+use anchor_client::solana_sdk::{
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+};
+use anyhow::Result;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use token_vault_client::{FeeReservePool, PayerChoice, TokenVaultError};
+
+const FLOOR: u64 = 10_000;
+const TOP_UP: u64 = 50_000;
+const FEE: u64 = 5_000;
+
+/// Balances of the pool's payers, standing in for the cluster
+struct Ledger {
+    balances: RefCell<HashMap<Pubkey, u64>>,
+}
+
+impl Ledger {
+    fn new(pool: &FeeReservePool, balances: &[u64]) -> Self {
+        Self {
+            balances: RefCell::new(
+                pool.payers()
+                    .into_iter()
+                    .zip(balances.iter().copied())
+                    .collect(),
+            ),
+        }
+    }
+
+    fn balance(&self, payer: &Pubkey) -> Result<u64> {
+        Ok(self.balances.borrow()[payer])
+    }
+
+    fn add(&self, payer: &Pubkey, lamports: u64) {
+        *self.balances.borrow_mut().get_mut(payer).unwrap() += lamports;
+    }
+
+    fn charge(&self, payer: &Pubkey, lamports: u64) {
+        *self.balances.borrow_mut().get_mut(payer).unwrap() -= lamports;
+    }
+
+    /// Choose a payer as the client does before a transaction, apply any
+    /// top-up and charge the fee; returns the payer used
+    fn pay(&self, pool: &FeeReservePool) -> Result<usize> {
+        let index = match pool.choose(|payer| self.balance(payer))? {
+            PayerChoice::Use(index) => index,
+            PayerChoice::TopUp { payer, lamports } => {
+                self.add(&pool.payers()[payer], lamports);
+                pool.note_top_up(payer, lamports);
+                payer
+            }
+        };
+        pool.note_operation(index);
+        self.charge(&pool.payers()[index], FEE);
+        Ok(index)
+    }
+}
+
+fn pool(payers: usize) -> FeeReservePool {
+    FeeReservePool::new((0..payers).map(|_| Keypair::new()).collect(), FLOOR).unwrap()
+}
+
+fn with_reserve(payers: usize) -> FeeReservePool {
+    let mut pool = pool(payers);
+    pool.with_reserve(Keypair::new(), TOP_UP).unwrap();
+    pool
+}
+
+fn exhausted(err: &anyhow::Error) -> bool {
+    matches!(
+        err.downcast_ref(),
+        Some(TokenVaultError::FeePoolExhausted {
+            floor_lamports: FLOOR,
+            ..
+        })
+    )
+}
+
+#[test]
+fn a_payer_below_the_floor_is_rotated_out() {
+    let pool = pool(3);
+    let ledger = Ledger::new(&pool, &[FLOOR + FEE, 20_000, 20_000]);
+
+    assert_eq!(ledger.pay(&pool).unwrap(), 0);
+    // Payer 0 is now at the floor, which is still enough
+    assert_eq!(ledger.pay(&pool).unwrap(), 0);
+    assert_eq!(ledger.pay(&pool).unwrap(), 1);
+    // The pool stays on the new payer rather than going back to the first
+    assert_eq!(ledger.pay(&pool).unwrap(), 1);
+    assert_eq!(pool.current(), 1);
+}
+
+#[test]
+fn without_a_reserve_the_pool_runs_out() {
+    let pool = pool(2);
+    let ledger = Ledger::new(&pool, &[FLOOR + FEE, FLOOR]);
+
+    let used: Vec<usize> = (0..3).map(|_| ledger.pay(&pool).unwrap()).collect();
+    assert_eq!(used, [0, 0, 1]);
+    let err = ledger.pay(&pool).unwrap_err();
+    assert!(exhausted(&err), "{}", err);
+    assert!(err.to_string().contains("dev fund-pool"), "{}", err);
+}
+
+#[test]
+fn an_exhausted_pool_tops_up_its_current_payer_from_the_reserve() {
+    let pool = with_reserve(2);
+    let ledger = Ledger::new(&pool, &[FLOOR - 1, 0]);
+
+    let choice = pool.choose(|payer| ledger.balance(payer)).unwrap();
+    assert_eq!(
+        choice,
+        PayerChoice::TopUp {
+            payer: 0,
+            lamports: TOP_UP - (FLOOR - 1),
+        }
+    );
+    assert_eq!(ledger.pay(&pool).unwrap(), 0);
+    assert_eq!(ledger.balance(&pool.payers()[0]).unwrap(), TOP_UP - FEE);
+    assert_eq!(pool.topped_up_lamports(), TOP_UP - (FLOOR - 1));
+}
+
+#[test]
+fn top_ups_stop_at_the_budget() {
+    let mut pool = with_reserve(1);
+    pool.with_top_up_budget(TOP_UP);
+    let ledger = Ledger::new(&pool, &[0]);
+
+    // One top-up from zero uses the whole budget
+    let mut operations = 0;
+    let err = loop {
+        match ledger.pay(&pool) {
+            Ok(_) => operations += 1,
+            Err(err) => break err,
+        }
+    };
+    assert!(exhausted(&err), "{}", err);
+    assert_eq!(pool.topped_up_lamports(), TOP_UP);
+    assert_eq!(operations, (TOP_UP - FLOOR) / FEE + 1);
+}
+
+#[test]
+fn a_long_run_survives_on_the_reserve_and_reports_every_payer() {
+    let pool = with_reserve(3);
+    let ledger = Ledger::new(&pool, &[30_000, 30_000, 30_000]);
+
+    for _ in 0..1_000 {
+        ledger.pay(&pool).unwrap();
+    }
+    // The balances the pool last saw are one fee behind the ledger
+    for (index, payer) in pool.payers().iter().enumerate() {
+        pool.observe(index, ledger.balance(payer).unwrap());
+    }
+
+    let report = pool.report();
+    assert_eq!(
+        report.iter().map(|spend| spend.operations).sum::<u64>(),
+        1_000
+    );
+    assert_eq!(
+        report.iter().map(|spend| spend.spent_lamports).sum::<u64>(),
+        1_000 * FEE
+    );
+    assert!(report.iter().all(|spend| spend.operations > 0));
+    assert_eq!(
+        report
+            .iter()
+            .map(|spend| spend.topped_up_lamports)
+            .sum::<u64>(),
+        pool.topped_up_lamports()
+    );
+    for spend in &report {
+        assert_eq!(
+            spend.balance_lamports,
+            Some(ledger.balance(&spend.payer).unwrap())
+        );
+    }
+}
+
+#[test]
+fn pools_need_payers_and_top_ups_above_the_floor() {
+    assert!(FeeReservePool::new(Vec::new(), FLOOR).is_err());
+    let mut pool = pool(1);
+    assert!(pool.with_reserve(Keypair::new(), FLOOR).is_err());
+}
+
+#[test]
+fn saved_payers_are_reused() {
+    let dir = std::env::temp_dir().join(format!("fee-pool-{}", Pubkey::new_unique()));
+    let first = FeeReservePool::save_payers(&dir, 2).unwrap();
+    let more = FeeReservePool::save_payers(&dir, 12).unwrap();
+    let loaded = FeeReservePool::load_payers(&dir).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    let keys = |payers: &[Keypair]| payers.iter().map(Signer::pubkey).collect::<Vec<_>>();
+    assert_eq!(keys(&more[..2]), keys(&first));
+    // Names sort in creation order, past ten payers too
+    assert_eq!(keys(&loaded), keys(&more));
+}
@@ -0,0 +1,36 @@
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use token_vault_client::fixtures::VaultFixture;
+
+/// Deposit and withdraw as user 0 of a fresh fixture, checking only that
+/// fixture's balances; returns its vault and mint
+fn round_trip() -> (Pubkey, Pubkey) {
+    let fixture = VaultFixture::builder()
+        .fee_bps(50)
+        .users(2)
+        .build()
+        .unwrap();
+    let initial = fixture.initial_balance();
+
+    fixture.deposit_as(0, 1_000_000).unwrap();
+    assert_eq!(fixture.balance_of(0).unwrap().raw, initial - 1_000_000);
+    assert_eq!(fixture.balance_of(1).unwrap().raw, initial);
+
+    fixture.withdraw_as(0, 1_000_000).unwrap();
+    // 50 bps of the withdrawal goes to the fee collector
+    assert_eq!(fixture.balance_of(0).unwrap().raw, initial - 5_000);
+    (fixture.vault(), fixture.mint())
+}
+
+/// Needs a validator with the token vault program deployed; see
+/// `fixtures::VALIDATOR_ENV` and `fixtures::PROGRAM_ID_ENV`
+#[test]
+#[ignore = "needs a local validator; run with --ignored"]
+fn fixtures_run_in_parallel_without_sharing_accounts() {
+    let (first, second) = std::thread::scope(|scope| {
+        let first = scope.spawn(round_trip);
+        let second = scope.spawn(round_trip);
+        (first.join().unwrap(), second.join().unwrap())
+    });
+    assert_ne!(first.0, second.0);
+    assert_ne!(first.1, second.1);
+}
@@ -0,0 +1,105 @@
+use anchor_client::solana_sdk::{program_option::COption, pubkey::Pubkey};
+use anchor_spl::token::spl_token::state::{Account, AccountState, Mint};
+use token_vault_client::freeze::{check_freezable_mint, check_token_accounts};
+use token_vault_client::{FreezeStatus, TokenVaultError};
+
+fn mint(freeze_authority: Option<Pubkey>) -> Mint {
+    Mint {
+        decimals: 6,
+        is_initialized: true,
+        freeze_authority: freeze_authority.map_or(COption::None, COption::Some),
+        ..Mint::default()
+    }
+}
+
+fn token_account(state: AccountState) -> Account {
+    Account {
+        state,
+        ..Account::default()
+    }
+}
+
+#[test]
+fn a_frozen_vault_account_names_who_can_thaw_it() {
+    let issuer = Pubkey::new_unique();
+    let user = Pubkey::new_unique();
+    let frozen = token_account(AccountState::Frozen);
+    let open = token_account(AccountState::Initialized);
+
+    let err = check_token_accounts(&mint(Some(issuer)), &frozen, Some((user, &frozen)));
+    // The vault's account is reported first, as it blocks every user
+    assert_eq!(
+        err,
+        Err(TokenVaultError::VaultTokenAccountFrozen {
+            freeze_authority: Some(issuer),
+        })
+    );
+    assert_eq!(
+        err.unwrap_err().to_string(),
+        format!(
+            "The vault's token account is frozen; only the mint's freeze authority {} can thaw it",
+            issuer
+        )
+    );
+
+    assert_eq!(
+        check_token_accounts(&mint(Some(issuer)), &open, Some((user, &frozen))),
+        Err(TokenVaultError::TokenAccountFrozen {
+            account: user,
+            freeze_authority: Some(issuer),
+        })
+    );
+    assert_eq!(
+        check_token_accounts(&mint(Some(issuer)), &open, Some((user, &open))),
+        Ok(())
+    );
+    assert_eq!(check_token_accounts(&mint(None), &open, None), Ok(()));
+}
+
+#[test]
+fn strict_policy_requires_acknowledging_freezable_mints() {
+    let address = Pubkey::new_unique();
+    let issuer = Pubkey::new_unique();
+
+    assert_eq!(
+        check_freezable_mint(address, &mint(Some(issuer)), true, false),
+        Err(TokenVaultError::FreezableMintNotAcknowledged {
+            mint: address,
+            freeze_authority: issuer,
+        })
+    );
+    assert_eq!(
+        check_freezable_mint(address, &mint(Some(issuer)), true, true),
+        Ok(())
+    );
+    assert_eq!(
+        check_freezable_mint(address, &mint(Some(issuer)), false, false),
+        Ok(())
+    );
+    assert_eq!(
+        check_freezable_mint(address, &mint(None), true, false),
+        Ok(())
+    );
+}
+
+#[test]
+fn freeze_status_describes_the_risk() {
+    let issuer = Pubkey::new_unique();
+    let status = FreezeStatus {
+        freeze_authority: Some(issuer),
+        vault_token_account_frozen: false,
+    };
+    assert_eq!(status.check(), Ok(()));
+    assert_eq!(status.to_string(), format!("freezable by {}", issuer));
+
+    let revoked = FreezeStatus {
+        freeze_authority: None,
+        vault_token_account_frozen: true,
+    };
+    assert_eq!(
+        revoked.to_string(),
+        "vault token account FROZEN; the mint's freeze authority was revoked, so it can never be thawed"
+    );
+    let json = serde_json::to_string(&status).unwrap();
+    assert_eq!(serde_json::from_str::<FreezeStatus>(&json).unwrap(), status);
+}
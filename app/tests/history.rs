@@ -0,0 +1,292 @@
+use anchor_client::solana_sdk::{
+    hash::Hash,
+    instruction::CompiledInstruction,
+    message::{
+        v0::{self, LoadedAddresses, MessageAddressTableLookup},
+        Message, MessageHeader, VersionedMessage,
+    },
+    pubkey::Pubkey,
+    signature::Signature,
+    transaction::VersionedTransaction,
+};
+use anchor_lang::{AnchorSerialize, Discriminator, InstructionData};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use solana_transaction_status::{
+    EncodableWithMeta, EncodedConfirmedTransactionWithStatusMeta, EncodedTransactionWithStatusMeta,
+    TransactionStatusMeta, UiTransactionEncoding,
+};
+use token_vault_client::history::{decode_transaction, decode_transaction_within, program_logs};
+use token_vault_client::token_vault::{events::WithdrawEvent, instruction};
+use token_vault_client::{VaultInstructionKind, VaultTransaction};
+
+fn event_log(event: &WithdrawEvent) -> String {
+    let mut data = WithdrawEvent::DISCRIMINATOR.to_vec();
+    data.extend(event.try_to_vec().unwrap());
+    format!("Program data: {}", STANDARD.encode(data))
+}
+
+#[test]
+fn withdraw_events_carry_the_recipient() {
+    let withdrawer = Pubkey::new_unique();
+    let recipient = Pubkey::new_unique();
+    let event = WithdrawEvent {
+        vault: Pubkey::new_unique(),
+        withdrawer,
+        recipient,
+        amount: 10_000,
+        fee: 100,
+    };
+    let transaction = VaultTransaction {
+        signature: Signature::default(),
+        slot: 1,
+        block_time: None,
+        succeeded: true,
+        instructions: Vec::new(),
+        logs: vec![
+            "Program log: Instruction: Withdraw".to_string(),
+            event_log(&event),
+            "Program data: not base64!".to_string(),
+        ],
+    };
+
+    let events = transaction.withdraw_events();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].withdrawer, withdrawer);
+    assert_eq!(events[0].recipient, recipient);
+    assert_eq!(events[0].amount, 10_000);
+}
+
+/// `message` with `logs`, as `getTransaction` returns it in base64
+fn fetched(
+    message: VersionedMessage,
+    logs: Vec<String>,
+    loaded_addresses: LoadedAddresses,
+) -> EncodedConfirmedTransactionWithStatusMeta {
+    let transaction = VersionedTransaction {
+        signatures: vec![Signature::default()],
+        message,
+    };
+    let meta = TransactionStatusMeta {
+        log_messages: Some(logs),
+        loaded_addresses,
+        ..Default::default()
+    };
+    EncodedConfirmedTransactionWithStatusMeta {
+        slot: 42,
+        transaction: EncodedTransactionWithStatusMeta {
+            transaction: transaction.encode_with_meta(UiTransactionEncoding::Base64, &meta),
+            meta: Some(meta.into()),
+            version: None,
+        },
+        block_time: Some(1_700_000_000),
+    }
+}
+
+/// Logs of an aggregator instruction that calls nothing of ours
+fn aggregator_logs(aggregator: &Pubkey, index: usize) -> Vec<String> {
+    vec![
+        format!("Program {} invoke [1]", aggregator),
+        format!("Program log: route {} {}", index, "x".repeat(200)),
+        format!("Program {} success", aggregator),
+    ]
+}
+
+/// Logs of a token vault instruction that calls the token program
+fn vault_logs(program: &Pubkey, event: Option<&WithdrawEvent>) -> Vec<String> {
+    let token_program = anchor_spl::token::ID;
+    let mut logs = vec![
+        format!("Program {} invoke [1]", program),
+        "Program log: Instruction: Vault".to_string(),
+        format!("Program {} invoke [2]", token_program),
+        "Program log: Instruction: Transfer".to_string(),
+        format!("Program {} success", token_program),
+    ];
+    logs.extend(event.map(event_log));
+    logs.push(format!("Program {} success", program));
+    logs
+}
+
+#[test]
+fn huge_transactions_decode_to_their_vault_instructions_only() {
+    let program = Pubkey::new_unique();
+    let aggregator = Pubkey::new_unique();
+    let (payer, vault, user) = (
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+    );
+    let event = WithdrawEvent {
+        vault,
+        withdrawer: payer,
+        recipient: user,
+        amount: 400,
+        fee: 1,
+    };
+
+    // Three vault operations among 1,000 instructions
+    let vault_ops = [
+        (10, instruction::Deposit { amount: 1_000 }.data()),
+        (500, instruction::Withdraw { amount: 400 }.data()),
+        (990, instruction::AddAllowlistEntry { wallet: user }.data()),
+    ];
+    let mut instructions = Vec::new();
+    let mut logs = Vec::new();
+    for index in 0..1_000 {
+        match vault_ops.iter().find(|(at, _)| *at == index) {
+            Some((_, data)) => {
+                instructions.push(CompiledInstruction::new_from_raw_parts(
+                    1,
+                    data.clone(),
+                    vec![0, 3, 4],
+                ));
+                logs.extend(vault_logs(&program, (index == 500).then_some(&event)));
+            }
+            None => {
+                instructions.push(CompiledInstruction::new_from_raw_parts(
+                    2,
+                    vec![7; 512],
+                    vec![0, 4],
+                ));
+                logs.extend(aggregator_logs(&aggregator, index));
+            }
+        }
+    }
+    let message = Message {
+        header: MessageHeader {
+            num_required_signatures: 1,
+            num_readonly_signed_accounts: 0,
+            num_readonly_unsigned_accounts: 2,
+        },
+        account_keys: vec![payer, program, aggregator, vault, user],
+        recent_blockhash: Hash::new_unique(),
+        instructions,
+    };
+    let fetch = || {
+        fetched(
+            VersionedMessage::Legacy(message.clone()),
+            logs.clone(),
+            LoadedAddresses::default(),
+        )
+    };
+
+    let (transaction, stats) =
+        decode_transaction_within(&program, Signature::default(), fetch(), 64 * 1024).unwrap();
+    let kinds: Vec<_> = transaction
+        .instructions
+        .iter()
+        .map(|ix| (ix.index, ix.kind))
+        .collect();
+    assert_eq!(
+        kinds,
+        vec![
+            (10, VaultInstructionKind::Deposit),
+            (500, VaultInstructionKind::Withdraw),
+            (990, VaultInstructionKind::AddAllowlistEntry),
+        ]
+    );
+    for (ix, (_, data)) in transaction.instructions.iter().zip(&vault_ops) {
+        assert_eq!(&ix.data, data);
+        assert_eq!(
+            ix.accounts,
+            vec![payer.to_string(), vault.to_string(), user.to_string()]
+        );
+    }
+
+    // Only the vault's own log lines survive, and its events with them
+    assert!(stats.trimmed);
+    assert_eq!(transaction.logs.len(), 3 * 3 + 1);
+    assert!(!transaction.logs.iter().any(|log| log.contains("route")
+        || log.contains("Transfer")
+        || log.contains(&aggregator.to_string())));
+    assert_eq!(transaction.withdraw_events().len(), 1);
+
+    // Memory held is the vault's share, not the transaction's
+    assert!(stats.fetched_bytes > 700_000, "{:?}", stats);
+    assert!(stats.retained_bytes < 2_000, "{:?}", stats);
+    assert_eq!(stats.retained_bytes, transaction.retained_bytes());
+
+    // Under the cap, every log line is kept
+    let (untrimmed, stats) =
+        decode_transaction_within(&program, Signature::default(), fetch(), usize::MAX).unwrap();
+    assert!(!stats.trimmed);
+    assert_eq!(untrimmed.logs, logs);
+    assert_eq!(untrimmed.instructions, transaction.instructions);
+}
+
+#[test]
+fn accounts_loaded_from_lookup_tables_are_resolved() {
+    let program = Pubkey::new_unique();
+    let (payer, vault, user, table) = (
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+    );
+    let message = v0::Message {
+        header: MessageHeader {
+            num_required_signatures: 1,
+            num_readonly_signed_accounts: 0,
+            num_readonly_unsigned_accounts: 1,
+        },
+        account_keys: vec![payer, program],
+        recent_blockhash: Hash::new_unique(),
+        // Index 2 is the loaded writable vault, index 3 the loaded readonly user
+        instructions: vec![CompiledInstruction::new_from_raw_parts(
+            1,
+            instruction::Deposit { amount: 5 }.data(),
+            vec![0, 2, 3],
+        )],
+        address_table_lookups: vec![MessageAddressTableLookup {
+            account_key: table,
+            writable_indexes: vec![0],
+            readonly_indexes: vec![1],
+        }],
+    };
+    let loaded = LoadedAddresses {
+        writable: vec![vault],
+        readonly: vec![user],
+    };
+    let transaction = decode_transaction(
+        &program,
+        Signature::default(),
+        fetched(VersionedMessage::V0(message), Vec::new(), loaded),
+    )
+    .unwrap();
+    assert_eq!(transaction.instructions.len(), 1);
+    assert_eq!(
+        transaction.instructions[0].accounts,
+        vec![payer.to_string(), vault.to_string(), user.to_string()]
+    );
+}
+
+#[test]
+fn program_logs_follow_the_invocation_stack() {
+    let (ours, other) = (
+        Pubkey::new_unique().to_string(),
+        Pubkey::new_unique().to_string(),
+    );
+    let logs: Vec<String> = [
+        format!("Program {} invoke [1]", other),
+        format!("Program {} invoke [2]", ours),
+        "Program log: ours".to_string(),
+        format!("Program {} invoke [3]", other),
+        "Program log: theirs, then success".to_string(),
+        format!("Program {} consumed 100 of 200 compute units", other),
+        format!("Program {} success", other),
+        "Program data: AAAA".to_string(),
+        format!("Program {} failed: custom program error: 0x1", ours),
+        "Program log: theirs again".to_string(),
+        format!("Program {} success", other),
+    ]
+    .into_iter()
+    .collect();
+    assert_eq!(
+        program_logs(logs.clone(), &ours),
+        vec![
+            logs[1].clone(),
+            logs[2].clone(),
+            logs[7].clone(),
+            logs[8].clone()
+        ]
+    );
+}
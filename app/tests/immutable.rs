@@ -0,0 +1,144 @@
+mod common;
+
+use anchor_client::solana_sdk::{
+    commitment_config::CommitmentConfig,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+};
+use anchor_client::Cluster;
+use anyhow::Result;
+use std::time::Duration;
+use token_vault_client::token_vault::{self, state::Vault};
+use token_vault_client::{
+    CacheConfig, ClientConfig, ConfigChanges, Provenance, TokenVaultClient, TokenVaultError,
+    UpdateVaultParams,
+};
+
+/// A client for an immutable vault held by `authority`. The vault is only
+/// in the client's cache and no validator is running, so a call that got
+/// as far as a request would fail with a connection error instead.
+fn client(authority: &Keypair) -> (TokenVaultClient, Pubkey) {
+    let config = ClientConfig {
+        vault_cache: CacheConfig {
+            ttl: Duration::from_secs(600),
+            enabled: true,
+        },
+        ..ClientConfig::default()
+    };
+    let mut client = TokenVaultClient::new_with_config(
+        Cluster::Localnet,
+        Keypair::new(),
+        token_vault::ID,
+        config,
+    )
+    .unwrap();
+    let address = Pubkey::new_unique();
+    #[allow(deprecated)]
+    client.with_vault(address);
+    let vault = Vault {
+        authority: authority.pubkey(),
+        immutable: true,
+        ..common::vault()
+    };
+    let provenance = Provenance {
+        slot: 10,
+        commitment: CommitmentConfig::confirmed().commitment,
+    };
+    client.vault_cache().store(address, &vault, provenance);
+    (client, address)
+}
+
+fn assert_immutable<T: std::fmt::Debug>(result: Result<T>, vault: Pubkey) {
+    let err = result.unwrap_err();
+    assert_eq!(
+        TokenVaultError::of(&err),
+        TokenVaultError::VaultImmutable { vault },
+        "{:#}",
+        err
+    );
+}
+
+fn fee_change() -> UpdateVaultParams {
+    UpdateVaultParams {
+        fee_percentage: Some(50),
+        ..UpdateVaultParams::default()
+    }
+}
+
+#[test]
+fn config_updates_are_refused() {
+    let authority = Keypair::new();
+    let (client, vault) = client(&authority);
+    assert_immutable(client.update_vault_config(&authority, fee_change()), vault);
+    assert_immutable(
+        client.build_update_vault_config_transaction(authority.pubkey(), fee_change()),
+        vault,
+    );
+    assert_immutable(
+        client.plan_update(&ConfigChanges::from(fee_change())),
+        vault,
+    );
+}
+
+#[test]
+fn pausing_and_unpausing_are_refused() {
+    let authority = Keypair::new();
+    let (client, vault) = client(&authority);
+    assert_immutable(client.pause_vault(&authority), vault);
+    assert_immutable(client.unpause_vault(&authority), vault);
+    assert_immutable(
+        client.build_pause_vault_transaction(authority.pubkey()),
+        vault,
+    );
+    assert_immutable(
+        client.build_unpause_vault_transaction(authority.pubkey()),
+        vault,
+    );
+}
+
+#[test]
+fn authority_transfers_are_refused() {
+    let authority = Keypair::new();
+    let (client, vault) = client(&authority);
+    let successor = Pubkey::new_unique();
+    assert_immutable(client.transfer_authority(&authority, successor), vault);
+    assert_immutable(
+        client.build_transfer_authority_transaction(authority.pubkey(), successor),
+        vault,
+    );
+}
+
+#[test]
+fn fee_collector_changes_are_refused() {
+    let authority = Keypair::new();
+    let (client, vault) = client(&authority);
+    let collector = Pubkey::new_unique();
+    assert_immutable(client.set_fee_collector(&authority, collector), vault);
+    assert_immutable(
+        client.build_set_fee_collector_transaction(authority.pubkey(), collector),
+        vault,
+    );
+    assert_immutable(client.plan_set_fee_collector(collector), vault);
+}
+
+#[test]
+fn maintenance_cannot_be_scheduled() {
+    let authority = Keypair::new();
+    let (client, vault) = client(&authority);
+    assert_immutable(
+        client.schedule_maintenance(
+            &authority,
+            2_000_000_000,
+            2_000_003_600,
+            "Upgrade".to_string(),
+        ),
+        vault,
+    );
+}
+
+#[test]
+fn close_plans_are_refused() {
+    let authority = Keypair::new();
+    let (client, vault) = client(&authority);
+    assert_immutable(client.plan_close_vault(), vault);
+}
@@ -0,0 +1,606 @@
+mod common;
+
+use anchor_client::solana_sdk::{
+    instruction::Instruction, pubkey::Pubkey, system_program, transaction::Transaction,
+};
+use anchor_lang::{InstructionData, ToAccountMetas};
+use anchor_spl::associated_token::get_associated_token_address;
+use anchor_spl::token;
+use anyhow::Result;
+use std::collections::HashMap;
+use token_vault_client::intent::verify_transaction;
+use token_vault_client::token_vault::{
+    self, accounts, instruction, pda,
+    state::{Vault, WithdrawalRequest},
+};
+use token_vault_client::{
+    AccountLookup, IntentViolation, TokenVaultError, VerifiedAction, VerifiedIntent,
+};
+
+/// On-chain state served from memory
+#[derive(Default)]
+struct Chain {
+    vaults: HashMap<Pubkey, Vault>,
+    token_accounts: HashMap<Pubkey, (Pubkey, Pubkey)>,
+    owners: HashMap<Pubkey, Pubkey>,
+    withdrawal_requests: HashMap<Pubkey, WithdrawalRequest>,
+}
+
+impl AccountLookup for Chain {
+    fn vault(&self, address: &Pubkey) -> Result<Option<Vault>> {
+        Ok(self.vaults.get(address).cloned())
+    }
+
+    fn token_account(&self, address: &Pubkey) -> Result<Option<(Pubkey, Pubkey)>> {
+        Ok(self.token_accounts.get(address).copied())
+    }
+
+    fn account_owner(&self, address: &Pubkey) -> Result<Option<Pubkey>> {
+        Ok(self.owners.get(address).copied())
+    }
+
+    fn withdrawal_request(&self, address: &Pubkey) -> Result<Option<WithdrawalRequest>> {
+        Ok(self.withdrawal_requests.get(address).cloned())
+    }
+}
+
+struct Fixture {
+    chain: Chain,
+    user: Pubkey,
+    authority: Pubkey,
+    vault: Pubkey,
+    vault_token_account: Pubkey,
+    mint: Pubkey,
+    fee_collector: Pubkey,
+}
+
+fn fixture() -> Fixture {
+    let authority = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let fee_collector = Pubkey::new_unique();
+    let (vault, bump) = pda::find_vault_address(&token_vault::ID, &authority, &mint, "savings");
+    let (vault_token_account, _) = pda::find_vault_token_account_address(&token_vault::ID, &vault);
+
+    let mut chain = Chain::default();
+    chain.vaults.insert(
+        vault,
+        Vault {
+            authority,
+            token_mint: mint,
+            fee_collector,
+            fee_percentage: 100,
+            withdrawal_limit: 0,
+            total_deposited: 1_000_000,
+            name: "savings".to_string(),
+            bump,
+            ..common::vault()
+        },
+    );
+
+    Fixture {
+        chain,
+        user: Pubkey::new_unique(),
+        authority,
+        vault,
+        vault_token_account,
+        mint,
+        fee_collector,
+    }
+}
+
+fn transaction(
+    payer: &Pubkey,
+    accounts: impl ToAccountMetas,
+    data: impl InstructionData,
+) -> Transaction {
+    let ix = Instruction {
+        program_id: token_vault::ID,
+        accounts: accounts.to_account_metas(None),
+        data: data.data(),
+    };
+    Transaction::new_with_payer(&[ix], Some(payer))
+}
+
+impl Fixture {
+    fn withdraw_accounts(&self) -> accounts::Withdraw {
+        accounts::Withdraw {
+            withdrawer: self.user,
+            vault: self.vault,
+            vault_token_account: self.vault_token_account,
+            withdrawer_token_account: get_associated_token_address(&self.user, &self.mint),
+            token_program: token::ID,
+            position: pda::find_position_address(&token_vault::ID, &self.vault, &self.user).0,
+        }
+    }
+
+    fn withdraw(&self, accounts: accounts::Withdraw) -> Transaction {
+        transaction(
+            &self.user,
+            accounts,
+            instruction::Withdraw { amount: 10_000 },
+        )
+    }
+
+    fn verify(&self, transaction: &Transaction) -> Result<VerifiedIntent> {
+        verify_transaction(&token_vault::ID, transaction, &self.chain)
+    }
+}
+
+fn violation(result: Result<VerifiedIntent>) -> IntentViolation {
+    match result
+        .expect_err("transaction should be rejected")
+        .downcast()
+    {
+        Ok(TokenVaultError::UnsafeTransaction { violation, .. }) => violation,
+        other => panic!("unexpected error: {:?}", other),
+    }
+}
+
+#[test]
+fn legitimate_withdraw_verifies() {
+    let f = fixture();
+    let intent = f.verify(&f.withdraw(f.withdraw_accounts())).unwrap();
+    assert_eq!(intent.fee_payer, Some(f.user));
+    assert!(intent.other_programs.is_empty());
+    assert_eq!(
+        intent.actions,
+        vec![VerifiedAction::Withdraw {
+            vault: f.vault,
+            withdrawer: f.user,
+            destination: get_associated_token_address(&f.user, &f.mint),
+            amount: 10_000,
+            fee: 100,
+            net_amount: 9_900,
+            fee_collector: f.fee_collector,
+        }]
+    );
+}
+
+impl Fixture {
+    fn withdrawal_request(&self) -> Pubkey {
+        pda::find_withdrawal_request_address(&token_vault::ID, &self.vault, &self.user).0
+    }
+
+    fn execute_accounts(&self) -> accounts::ExecuteWithdrawal {
+        let withdraw = self.withdraw_accounts();
+        accounts::ExecuteWithdrawal {
+            withdrawer: withdraw.withdrawer,
+            vault: withdraw.vault,
+            vault_token_account: withdraw.vault_token_account,
+            withdrawer_token_account: withdraw.withdrawer_token_account,
+            withdrawal_request: self.withdrawal_request(),
+            token_program: withdraw.token_program,
+            position: withdraw.position,
+        }
+    }
+}
+
+#[test]
+fn executed_requests_withdraw_the_requested_amount() {
+    let mut f = fixture();
+    let request = transaction(
+        &f.user,
+        accounts::RequestWithdrawal {
+            withdrawer: f.user,
+            vault: f.vault,
+            withdrawal_request: f.withdrawal_request(),
+            system_program: system_program::ID,
+        },
+        instruction::RequestWithdrawal { amount: 20_000 },
+    );
+    assert_eq!(
+        f.verify(&request).unwrap().actions,
+        vec![VerifiedAction::RequestWithdrawal {
+            vault: f.vault,
+            withdrawer: f.user,
+            amount: 20_000,
+        }]
+    );
+
+    let execute = transaction(
+        &f.user,
+        f.execute_accounts(),
+        instruction::ExecuteWithdrawal {},
+    );
+    // Nothing to execute before the request exists
+    assert!(matches!(
+        violation(f.verify(&execute)),
+        IntentViolation::WithdrawalRejected { .. }
+    ));
+
+    f.chain.withdrawal_requests.insert(
+        f.withdrawal_request(),
+        WithdrawalRequest {
+            vault: f.vault,
+            withdrawer: f.user,
+            amount: 20_000,
+            requested_at: 1_700_000_000,
+            bump: 254,
+        },
+    );
+    assert_eq!(
+        f.verify(&execute).unwrap().actions,
+        vec![VerifiedAction::Withdraw {
+            vault: f.vault,
+            withdrawer: f.user,
+            destination: get_associated_token_address(&f.user, &f.mint),
+            amount: 20_000,
+            fee: 200,
+            net_amount: 19_800,
+            fee_collector: f.fee_collector,
+        }]
+    );
+}
+
+#[test]
+fn someone_elses_withdrawal_request_is_rejected() {
+    let f = fixture();
+    let (theirs, _) =
+        pda::find_withdrawal_request_address(&token_vault::ID, &f.vault, &f.authority);
+    let accounts = accounts::ExecuteWithdrawal {
+        withdrawal_request: theirs,
+        ..f.execute_accounts()
+    };
+    let execute = transaction(&f.user, accounts, instruction::ExecuteWithdrawal {});
+    assert_eq!(
+        violation(f.verify(&execute)),
+        IntentViolation::WithdrawalRequestMismatch {
+            expected: f.withdrawal_request(),
+            actual: theirs,
+        }
+    );
+}
+
+impl Fixture {
+    fn claim_fees(&self, accounts: accounts::ClaimFees) -> Transaction {
+        let fee_collector = accounts.fee_collector;
+        transaction(&fee_collector, accounts, instruction::ClaimFees {})
+    }
+
+    fn claim_accounts(&self) -> accounts::ClaimFees {
+        accounts::ClaimFees {
+            fee_collector: self.fee_collector,
+            vault: self.vault,
+            vault_token_account: self.vault_token_account,
+            fee_collector_token_account: get_associated_token_address(
+                &self.fee_collector,
+                &self.mint,
+            ),
+            token_program: token::ID,
+        }
+    }
+}
+
+#[test]
+fn claims_pay_the_accrued_fees_to_the_fee_collector() {
+    let mut f = fixture();
+    f.chain.vaults.get_mut(&f.vault).unwrap().accrued_fees = 300;
+    assert_eq!(
+        f.verify(&f.claim_fees(f.claim_accounts())).unwrap().actions,
+        vec![VerifiedAction::ClaimFees {
+            vault: f.vault,
+            fee_collector: f.fee_collector,
+            destination: get_associated_token_address(&f.fee_collector, &f.mint),
+            amount: 300,
+        }]
+    );
+}
+
+#[test]
+fn substituted_fee_collector_is_rejected() {
+    let f = fixture();
+    let attacker = Pubkey::new_unique();
+    let accounts = accounts::ClaimFees {
+        fee_collector_token_account: get_associated_token_address(&attacker, &f.mint),
+        ..f.claim_accounts()
+    };
+    assert!(matches!(
+        violation(f.verify(&f.claim_fees(accounts))),
+        IntentViolation::FeeCollectorMismatch { .. }
+    ));
+
+    let accounts = accounts::ClaimFees {
+        fee_collector: attacker,
+        ..f.claim_accounts()
+    };
+    assert_eq!(
+        violation(f.verify(&f.claim_fees(accounts))),
+        IntentViolation::NotFeeCollector {
+            signer: attacker,
+            fee_collector: f.fee_collector,
+        }
+    );
+}
+
+#[test]
+fn destination_owned_by_someone_else_is_rejected() {
+    let mut f = fixture();
+    let attacker = Pubkey::new_unique();
+    let attacker_account = Pubkey::new_unique();
+    f.chain
+        .token_accounts
+        .insert(attacker_account, (attacker, f.mint));
+    let accounts = accounts::Withdraw {
+        withdrawer_token_account: attacker_account,
+        ..f.withdraw_accounts()
+    };
+    assert_eq!(
+        violation(f.verify(&f.withdraw(accounts))),
+        IntentViolation::NotOwnedBySigner {
+            account: attacker_account,
+            signer: f.user,
+            mint: f.mint,
+        }
+    );
+}
+
+#[test]
+fn non_associated_account_of_the_signer_is_accepted() {
+    let mut f = fixture();
+    let own_account = Pubkey::new_unique();
+    f.chain.token_accounts.insert(own_account, (f.user, f.mint));
+    let accounts = accounts::Withdraw {
+        withdrawer_token_account: own_account,
+        ..f.withdraw_accounts()
+    };
+    assert!(f.verify(&f.withdraw(accounts)).is_ok());
+}
+
+#[test]
+fn wrong_vault_token_account_is_rejected() {
+    let f = fixture();
+    let accounts = accounts::Withdraw {
+        vault_token_account: Pubkey::new_unique(),
+        ..f.withdraw_accounts()
+    };
+    assert!(matches!(
+        violation(f.verify(&f.withdraw(accounts))),
+        IntentViolation::VaultTokenAccountMismatch { .. }
+    ));
+}
+
+#[test]
+fn wrong_token_program_is_rejected() {
+    let f = fixture();
+    let accounts = accounts::Withdraw {
+        token_program: Pubkey::new_unique(),
+        ..f.withdraw_accounts()
+    };
+    assert!(matches!(
+        violation(f.verify(&f.withdraw(accounts))),
+        IntentViolation::UnexpectedTokenProgram { .. }
+    ));
+}
+
+#[test]
+fn someone_elses_position_is_rejected() {
+    let f = fixture();
+    let (position, _) = pda::find_position_address(&token_vault::ID, &f.vault, &f.authority);
+    let accounts = accounts::Withdraw {
+        position,
+        ..f.withdraw_accounts()
+    };
+    assert_eq!(
+        violation(f.verify(&f.withdraw(accounts))),
+        IntentViolation::PositionMismatch {
+            expected: pda::find_position_address(&token_vault::ID, &f.vault, &f.user).0,
+            actual: position,
+        }
+    );
+}
+
+#[test]
+fn unknown_instruction_is_rejected() {
+    let f = fixture();
+    let ix = Instruction {
+        program_id: token_vault::ID,
+        accounts: f.withdraw_accounts().to_account_metas(None),
+        data: vec![0; 16],
+    };
+    let transaction = Transaction::new_with_payer(&[ix], Some(&f.user));
+    assert_eq!(
+        violation(f.verify(&transaction)),
+        IntentViolation::UnknownInstruction
+    );
+}
+
+#[test]
+fn unsigned_withdrawer_is_rejected() {
+    let f = fixture();
+    let payer = Pubkey::new_unique();
+    let ix = Instruction {
+        program_id: token_vault::ID,
+        accounts: f.withdraw_accounts().to_account_metas(Some(false)),
+        data: instruction::Withdraw { amount: 10_000 }.data(),
+    };
+    let transaction = Transaction::new_with_payer(&[ix], Some(&payer));
+    assert_eq!(
+        violation(f.verify(&transaction)),
+        IntentViolation::MissingSigner { account: f.user }
+    );
+}
+
+#[test]
+fn unknown_vault_is_rejected() {
+    let f = fixture();
+    let vault = Pubkey::new_unique();
+    let accounts = accounts::Withdraw {
+        vault,
+        ..f.withdraw_accounts()
+    };
+    assert_eq!(
+        violation(f.verify(&f.withdraw(accounts))),
+        IntentViolation::VaultNotFound { vault }
+    );
+}
+
+#[test]
+fn sweep_by_non_authority_is_rejected() {
+    let f = fixture();
+    let accounts = accounts::SweepLamports {
+        authority: f.user,
+        vault: f.vault,
+        vault_token_account: f.vault_token_account,
+        destination: f.user,
+        token_program: token::ID,
+    };
+    let transaction = transaction(&f.user, accounts, instruction::SweepLamports {});
+    assert_eq!(
+        violation(f.verify(&transaction)),
+        IntentViolation::NotVaultAuthority {
+            signer: f.user,
+            authority: f.authority,
+        }
+    );
+}
+
+#[test]
+fn deposit_into_a_substituted_reserve_is_rejected() {
+    let f = fixture();
+    let reserve = Pubkey::new_unique();
+    let accounts = accounts::Deposit {
+        depositor: f.user,
+        vault: f.vault,
+        vault_token_account: f.vault_token_account,
+        reserve_token_account: reserve,
+        depositor_token_account: get_associated_token_address(&f.user, &f.mint),
+        token_program: token::ID,
+        position: pda::find_position_address(&token_vault::ID, &f.vault, &f.user).0,
+        system_program: system_program::ID,
+    };
+    let transaction = transaction(&f.user, accounts, instruction::Deposit { amount: 10_000 });
+    assert_eq!(
+        violation(f.verify(&transaction)),
+        IntentViolation::ReserveTokenAccountMismatch {
+            expected: pda::find_reserve_token_account_address(&token_vault::ID, &f.vault).0,
+            actual: reserve,
+        }
+    );
+}
+
+#[test]
+fn reserve_release_by_non_authority_is_rejected() {
+    let f = fixture();
+    let accounts = accounts::ReleaseReserve {
+        authority: f.user,
+        vault: f.vault,
+        reserve_token_account: pda::find_reserve_token_account_address(&token_vault::ID, &f.vault)
+            .0,
+        destination_token_account: get_associated_token_address(&f.user, &f.mint),
+        token_program: token::ID,
+    };
+    let transaction = transaction(
+        &f.user,
+        accounts,
+        instruction::ReleaseReserve { amount: 10_000 },
+    );
+    assert_eq!(
+        violation(f.verify(&transaction)),
+        IntentViolation::NotVaultAuthority {
+            signer: f.user,
+            authority: f.authority,
+        }
+    );
+}
+
+#[test]
+fn sweep_to_program_owned_account_is_rejected() {
+    let mut f = fixture();
+    let destination = Pubkey::new_unique();
+    f.chain.owners.insert(destination, token::ID);
+    let accounts = accounts::SweepLamports {
+        authority: f.authority,
+        vault: f.vault,
+        vault_token_account: f.vault_token_account,
+        destination,
+        token_program: token::ID,
+    };
+    let tx = transaction(&f.authority, accounts, instruction::SweepLamports {});
+    assert_eq!(
+        violation(f.verify(&tx)),
+        IntentViolation::DestinationNotSystemAccount { destination }
+    );
+
+    f.chain.owners.insert(destination, system_program::ID);
+    assert!(f.verify(&tx).is_ok());
+}
+
+#[test]
+fn other_programs_are_reported_not_verified() {
+    let f = fixture();
+    let memo = Pubkey::new_unique();
+    let withdraw = f.withdraw(f.withdraw_accounts());
+    let mut ixs: Vec<Instruction> = vec![Instruction {
+        program_id: memo,
+        accounts: vec![],
+        data: b"hello".to_vec(),
+    }];
+    ixs.push(Instruction {
+        program_id: token_vault::ID,
+        accounts: f.withdraw_accounts().to_account_metas(None),
+        data: instruction::Withdraw { amount: 10_000 }.data(),
+    });
+    let transaction = Transaction::new_with_payer(&ixs, Some(&f.user));
+    let intent = f.verify(&transaction).unwrap();
+    assert_eq!(intent.other_programs, vec![memo]);
+    assert_eq!(intent.actions, f.verify(&withdraw).unwrap().actions);
+}
+
+#[test]
+fn violation_reports_instruction_index() {
+    let f = fixture();
+    let good = Instruction {
+        program_id: token_vault::ID,
+        accounts: f.withdraw_accounts().to_account_metas(None),
+        data: instruction::Withdraw { amount: 10_000 }.data(),
+    };
+    let bad = Instruction {
+        data: vec![0; 16],
+        ..good.clone()
+    };
+    let transaction = Transaction::new_with_payer(&[good, bad], Some(&f.user));
+    let err = f.verify(&transaction).unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<TokenVaultError>(),
+        Some(TokenVaultError::UnsafeTransaction { instruction: 1, .. })
+    ));
+}
+
+#[test]
+fn allowlist_change_by_non_authority_is_rejected() {
+    let f = fixture();
+    let wallet = Pubkey::new_unique();
+    let (allowlist_entry, _) =
+        pda::find_allowlist_entry_address(&token_vault::ID, &f.vault, &wallet);
+    let accounts = accounts::AddAllowlistEntry {
+        authority: f.user,
+        vault: f.vault,
+        allowlist_entry,
+        system_program: system_program::ID,
+    };
+    let tx = transaction(&f.user, accounts, instruction::AddAllowlistEntry { wallet });
+    assert!(matches!(
+        violation(f.verify(&tx)),
+        IntentViolation::NotVaultAuthority { .. }
+    ));
+
+    let accounts = accounts::AddAllowlistEntry {
+        authority: f.authority,
+        vault: f.vault,
+        allowlist_entry,
+        system_program: system_program::ID,
+    };
+    let tx = transaction(
+        &f.authority,
+        accounts,
+        instruction::AddAllowlistEntry { wallet },
+    );
+    assert_eq!(
+        f.verify(&tx).unwrap().actions,
+        vec![VerifiedAction::AddAllowlistEntry {
+            vault: f.vault,
+            authority: f.authority,
+            wallet,
+        }]
+    );
+}
@@ -0,0 +1,356 @@
+use anchor_client::solana_sdk::{signature::Keypair, signer::Signer, transaction::Transaction};
+use anchor_spl::token::spl_token;
+use std::time::Duration;
+use token_vault_client::harness::TestContext;
+use token_vault_client::token_vault::pda::VaultAddresses;
+use token_vault_client::{TransactionVersion, VaultConfig};
+
+/// Starts its own `solana-test-validator`, which must be on the `PATH`, with
+/// the program built by `anchor build`; see `harness::PROGRAM_SO_ENV`
+#[test]
+#[ignore = "starts solana-test-validator; run with --ignored"]
+fn initialize_deposit_withdraw_end_to_end() {
+    let ctx = TestContext::start().unwrap();
+    let authority = ctx.payer();
+    let user = ctx.funded_user(10_000_000).unwrap();
+    // The authority collects fees into its associated token account
+    let fee_account = ctx.create_token_account(&authority.pubkey(), 0).unwrap();
+
+    let mut client = ctx.client().unwrap();
+    let config = VaultConfig::builder()
+        .name("it")
+        .fee_bps(100)
+        .timelock(Duration::ZERO)
+        .withdrawal_limit(5_000_000)
+        .build()
+        .unwrap();
+    let vault = client
+        .initialize_vault(authority, ctx.mint(), &config)
+        .unwrap()
+        .address;
+    let addresses =
+        VaultAddresses::derive(&ctx.program_id(), &authority.pubkey(), &ctx.mint(), "it").unwrap();
+    assert_eq!(vault, addresses.vault);
+    #[allow(deprecated)]
+    client.with_vault(vault);
+
+    let info = client.get_vault_info().unwrap();
+    assert_eq!(info.authority, authority.pubkey());
+    assert_eq!(info.fee_percentage, 100);
+    assert_eq!(info.total_deposited, 0);
+
+    client.deposit(&user.keypair, 4_000_000).unwrap();
+    assert_eq!(ctx.token_balance(&user.token_account).unwrap(), 6_000_000);
+    assert_eq!(
+        ctx.token_balance(&addresses.vault_token_account).unwrap(),
+        4_000_000
+    );
+    assert_eq!(client.get_vault_info().unwrap().total_deposited, 4_000_000);
+
+    client.withdraw(&user.keypair, 1_000_000, None).unwrap();
+    // 100 bps of the withdrawal stays in the vault for the fee collector
+    assert_eq!(ctx.token_balance(&user.token_account).unwrap(), 6_990_000);
+    assert_eq!(ctx.token_balance(&fee_account).unwrap(), 0);
+    assert_eq!(
+        ctx.token_balance(&addresses.vault_token_account).unwrap(),
+        3_010_000
+    );
+    let info = client.get_vault_info().unwrap();
+    assert_eq!(info.total_deposited, 3_000_000);
+    assert_eq!(info.accrued_fees, 10_000);
+
+    assert_eq!(client.claim_fees(authority).unwrap(), 10_000);
+    assert_eq!(ctx.token_balance(&fee_account).unwrap(), 10_000);
+    assert_eq!(
+        ctx.token_balance(&addresses.vault_token_account).unwrap(),
+        3_000_000
+    );
+    assert_eq!(client.get_vault_info().unwrap().accrued_fees, 0);
+}
+
+#[test]
+#[ignore = "starts solana-test-validator; run with --ignored"]
+fn sponsored_and_delegated_deposits_end_to_end() {
+    let ctx = TestContext::start().unwrap();
+    let authority = ctx.payer();
+    // The owner holds tokens but no SOL, so every fee must be sponsored
+    let owner = Keypair::new();
+    let owner_account = ctx
+        .create_token_account(&owner.pubkey(), 3_000_000)
+        .unwrap();
+    let relayer = ctx.funded_user(0).unwrap();
+
+    let mut client = ctx.client().unwrap();
+    let config = VaultConfig::builder()
+        .name("sponsored")
+        .fee_bps(0)
+        .timelock(Duration::ZERO)
+        .withdrawal_limit(5_000_000)
+        .build()
+        .unwrap();
+    let vault = client
+        .initialize_vault(authority, ctx.mint(), &config)
+        .unwrap()
+        .address;
+    #[allow(deprecated)]
+    client.with_vault(vault);
+
+    client
+        .deposit_for(&relayer.keypair, &owner, 1_000_000)
+        .unwrap();
+    assert_eq!(ctx.token_balance(&owner_account).unwrap(), 2_000_000);
+    assert_eq!(ctx.rpc().get_balance(&owner.pubkey()).unwrap(), 0);
+
+    let approve = spl_token::instruction::approve(
+        &spl_token::ID,
+        &owner_account,
+        &relayer.keypair.pubkey(),
+        &owner.pubkey(),
+        &[],
+        500_000,
+    )
+    .unwrap();
+    let transaction = Transaction::new_signed_with_payer(
+        &[approve],
+        Some(&authority.pubkey()),
+        &[authority, &owner],
+        ctx.rpc().get_latest_blockhash().unwrap(),
+    );
+    ctx.rpc()
+        .send_and_confirm_transaction(&transaction)
+        .unwrap();
+
+    // More than the delegation is refused before anything is sent
+    let err = client
+        .deposit_delegated(&relayer.keypair, owner.pubkey(), 600_000)
+        .unwrap_err();
+    assert!(format!("{:#}", err).contains("as a delegate"), "{:#}", err);
+    client
+        .deposit_delegated(&relayer.keypair, owner.pubkey(), 500_000)
+        .unwrap();
+    assert_eq!(ctx.token_balance(&owner_account).unwrap(), 1_500_000);
+    assert_eq!(client.get_vault_info().unwrap().total_deposited, 1_500_000);
+}
+
+#[test]
+#[ignore = "starts solana-test-validator; run with --ignored"]
+fn batched_operations_land_in_one_transaction() {
+    let ctx = TestContext::start().unwrap();
+    let authority = ctx.payer();
+    let user = ctx.funded_user(3_000_000).unwrap();
+
+    let client = ctx.client().unwrap();
+    let mut vaults = Vec::new();
+    for name in ["batch-a", "batch-b"] {
+        let config = VaultConfig::builder()
+            .name(name)
+            .fee_bps(0)
+            .timelock(Duration::ZERO)
+            .withdrawal_limit(5_000_000)
+            .build()
+            .unwrap();
+        let vault = client
+            .initialize_vault(authority, ctx.mint(), &config)
+            .unwrap()
+            .address;
+        vaults.push(vault);
+    }
+    let (a, b) = (client.vault(vaults[0]), client.vault(vaults[1]));
+    a.deposit(&user.keypair, 2_000_000).unwrap();
+
+    let receipt = client
+        .batch()
+        .add_withdraw_from(vaults[0], user.keypair.pubkey(), 1_500_000, None)
+        .add_deposit_to(vaults[1], user.keypair.pubkey(), 1_500_000)
+        .add_memo("rebalance", user.keypair.pubkey())
+        .send(&[&user.keypair, &user.keypair])
+        .unwrap();
+    assert_eq!(receipt.memo.as_deref(), Some("rebalance"));
+    assert_eq!(ctx.token_balance(&user.token_account).unwrap(), 1_000_000);
+    assert_eq!(a.refresh().unwrap().total_deposited, 500_000);
+    assert_eq!(b.info().unwrap().total_deposited, 1_500_000);
+}
+
+#[test]
+#[ignore = "starts solana-test-validator; run with --ignored"]
+fn v0_transactions_over_a_vault_lookup_table() {
+    let ctx = TestContext::start().unwrap();
+    let authority = ctx.payer();
+    let user = ctx.funded_user(2_000_000).unwrap();
+    ctx.create_token_account(&authority.pubkey(), 0).unwrap();
+
+    let mut client = ctx.client().unwrap();
+    let config = VaultConfig::builder()
+        .name("v0")
+        .fee_bps(0)
+        .timelock(Duration::ZERO)
+        .withdrawal_limit(5_000_000)
+        .build()
+        .unwrap();
+    let vault = client
+        .initialize_vault(authority, ctx.mint(), &config)
+        .unwrap()
+        .address;
+    #[allow(deprecated)]
+    client.with_vault(vault);
+
+    let table = client.create_vault_lookup_table(authority).unwrap();
+    assert_eq!(table.addresses[0], vault);
+    let created = table.receipt.slot.unwrap_or_default();
+    while ctx.rpc().get_slot().unwrap() <= created {
+        std::thread::sleep(Duration::from_millis(100));
+    }
+    let resolved = client.get_address_lookup_table(table.address).unwrap();
+    assert_eq!(resolved.addresses, table.addresses);
+
+    client.with_transaction_version(TransactionVersion::V0 {
+        lookup_tables: vec![table.address],
+    });
+    client.deposit(&user.keypair, 1_000_000).unwrap();
+    client.withdraw(&user.keypair, 400_000, None).unwrap();
+    assert_eq!(ctx.token_balance(&user.token_account).unwrap(), 1_400_000);
+    assert_eq!(client.get_vault_info().unwrap().total_deposited, 600_000);
+}
+
+#[test]
+#[ignore = "starts solana-test-validator; run with --ignored"]
+fn vault_handles_work_side_by_side() {
+    let ctx = TestContext::start().unwrap();
+    let authority = ctx.payer();
+    let user = ctx.funded_user(3_000_000).unwrap();
+
+    let client = ctx.client().unwrap();
+    let mut handles = Vec::new();
+    for name in ["handle-a", "handle-b"] {
+        let config = VaultConfig::builder()
+            .name(name)
+            .fee_bps(0)
+            .timelock(Duration::ZERO)
+            .withdrawal_limit(5_000_000)
+            .build()
+            .unwrap();
+        let vault = client
+            .initialize_vault(authority, ctx.mint(), &config)
+            .unwrap()
+            .address;
+        handles.push(client.vault(vault));
+    }
+    let (a, b) = (&handles[0], &handles[1]);
+
+    assert_eq!(a.info().unwrap().total_deposited, 0);
+    assert!(a.cached().is_some());
+    a.deposit(&user.keypair, 1_000_000).unwrap();
+    // The deposit dropped what the handle had read
+    assert!(a.cached().is_none());
+    b.deposit(&user.keypair, 2_000_000).unwrap();
+    b.withdraw(&user.keypair, 500_000, None).unwrap();
+
+    assert_eq!(a.info().unwrap().total_deposited, 1_000_000);
+    assert_eq!(b.info().unwrap().total_deposited, 1_500_000);
+    assert_eq!(ctx.token_balance(&b.token_account()).unwrap(), 1_500_000);
+    assert_eq!(b.balance().unwrap().raw, 1_500_000);
+    assert_eq!(ctx.token_balance(&user.token_account).unwrap(), 500_000);
+}
+
+#[test]
+#[ignore = "starts solana-test-validator; run with --ignored"]
+fn sweeps_move_whole_balances() {
+    let ctx = TestContext::start().unwrap();
+    let authority = ctx.payer();
+    let user = ctx.funded_user(3_000_000).unwrap();
+
+    let client = ctx.client().unwrap();
+    let config = VaultConfig::builder()
+        .name("sweep")
+        .fee_bps(100)
+        .timelock(Duration::ZERO)
+        .withdrawal_limit(1_999_999)
+        .build()
+        .unwrap();
+    let vault = client
+        .initialize_vault(authority, ctx.mint(), &config)
+        .unwrap()
+        .address;
+    let vault = client.vault(vault);
+
+    let deposited = vault.deposit_all(&user.keypair, 500_000).unwrap();
+    assert_eq!(deposited.amount, 2_500_000);
+    assert_eq!(ctx.token_balance(&user.token_account).unwrap(), 500_000);
+    assert!(vault
+        .deposit_all(&user.keypair, 500_000)
+        .unwrap()
+        .is_empty());
+
+    // Capped by the withdrawal limit; the fee comes out of the amount and
+    // stays in the vault
+    let withdrawn = vault.withdraw_all(&user.keypair).unwrap();
+    assert_eq!(withdrawn.amount, 1_999_999);
+    assert_eq!(ctx.token_balance(&user.token_account).unwrap(), 2_480_000);
+    assert_eq!(vault.info().unwrap().accrued_fees, 19_999);
+
+    // Accrued fees are left behind for the fee collector
+    let rest = vault.withdraw_all(&user.keypair).unwrap();
+    assert_eq!(rest.amount, 500_001);
+    assert_eq!(ctx.token_balance(&user.token_account).unwrap(), 2_975_001);
+    assert_eq!(ctx.token_balance(&vault.token_account()).unwrap(), 24_999);
+    let info = vault.info().unwrap();
+    assert_eq!(info.total_deposited, 0);
+    assert_eq!(info.accrued_fees, 24_999);
+    assert!(vault.withdraw_all(&user.keypair).unwrap().is_empty());
+}
+
+#[test]
+#[ignore = "starts solana-test-validator; run with --ignored"]
+fn positions_track_each_depositor() {
+    let ctx = TestContext::start().unwrap();
+    let authority = ctx.payer();
+    let alice = ctx.funded_user(2_000_000).unwrap();
+    let bob = ctx.funded_user(2_000_000).unwrap();
+
+    let client = ctx.client().unwrap();
+    let config = VaultConfig::builder()
+        .name("positions")
+        .fee_bps(0)
+        .timelock(Duration::ZERO)
+        .withdrawal_limit(5_000_000)
+        .build()
+        .unwrap();
+    let vault = client
+        .initialize_vault(authority, ctx.mint(), &config)
+        .unwrap()
+        .address;
+    let vault = client.vault(vault);
+
+    assert_eq!(vault.position(alice.keypair.pubkey()).unwrap(), None);
+    vault.deposit(&alice.keypair, 1_000_000).unwrap();
+    vault.deposit(&alice.keypair, 500_000).unwrap();
+    vault.withdraw(&alice.keypair, 300_000, None).unwrap();
+    vault.deposit(&bob.keypair, 700_000).unwrap();
+
+    let position = vault.position(alice.keypair.pubkey()).unwrap().unwrap();
+    assert_eq!(position.vault, vault.address());
+    assert_eq!(position.deposited, 1_500_000);
+    assert_eq!(position.withdrawn, 300_000);
+    assert!(position.last_withdraw_ts >= position.last_deposit_ts);
+    let bob_position = vault.position(bob.keypair.pubkey()).unwrap().unwrap();
+    assert_eq!(bob_position.deposited, 700_000);
+    assert_eq!(bob_position.last_withdraw_ts, 0);
+
+    let depositors: Vec<_> = vault
+        .positions()
+        .unwrap()
+        .into_iter()
+        .map(|(_, position)| position.depositor)
+        .collect();
+    let mut expected = vec![alice.keypair.pubkey(), bob.keypair.pubkey()];
+    expected.sort();
+    assert_eq!(depositors, expected);
+
+    // Each depositor sweeps their own position, not the whole vault
+    assert_eq!(vault.withdraw_all(&bob.keypair).unwrap().amount, 700_000);
+    assert_eq!(
+        vault.withdraw_all(&alice.keypair).unwrap().amount,
+        1_200_000
+    );
+    assert_eq!(vault.info().unwrap().total_deposited, 0);
+}
@@ -0,0 +1,205 @@
+//! Golden files pin the JSON contract of `token_vault_client::json`. A change
+//! to any of them is a breaking change for scripts reading `--json` output.
+//! After an intended change, rewrite them with `UPDATE_GOLDEN=1 cargo test
+//! --test json_contract` and review the diff.
+
+mod common;
+
+use anchor_client::solana_sdk::{commitment_config::CommitmentLevel, signature::Signature};
+use anyhow::{anyhow, Context};
+use common::key;
+use serde::Serialize;
+use std::path::PathBuf;
+use token_vault_client::json::{
+    iso_timestamp, Amount, ErrorBody, PageView, TransactionView, TransferView, VaultView,
+};
+use token_vault_client::{
+    FreezeStatus, MaintenanceWindow, MintAmount, Page, Provenance, TokenVaultError, TxReceipt,
+    VaultBalance, VaultInstruction, VaultInstructionKind, VaultSummary, VaultTransaction,
+};
+
+const NOW: i64 = 1_700_000_000;
+
+/// Compare `value`, pretty-printed, with `tests/golden/<name>.json`
+fn assert_golden(name: &str, value: &impl Serialize) {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(format!("{}.json", name));
+    let actual = serde_json::to_string_pretty(value).unwrap() + "\n";
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::write(&path, &actual).unwrap();
+        return;
+    }
+    let expected =
+        std::fs::read_to_string(&path).unwrap_or_else(|err| panic!("{}: {}", path.display(), err));
+    assert!(
+        actual == expected,
+        "{} no longer matches; set UPDATE_GOLDEN=1 if the change is intended\n{}",
+        path.display(),
+        actual
+    );
+}
+
+fn summary() -> VaultSummary {
+    VaultSummary {
+        address: key(1),
+        name: "treasury".to_string(),
+        authority: key(2),
+        token_mint: key(3),
+        fee_collector: key(5),
+        fee_percentage: 25,
+        withdrawal_timelock: 3_600,
+        withdrawal_limit: 5_000_000_000,
+        dust_threshold: 1_000,
+        waive_dust_fee: true,
+        reserve_bps: 250,
+        reserve_timelock: 86_400,
+        total_deposited: 1_234_567_890,
+        total_reserved: 30_864_197,
+        accrued_fees: 12_110,
+        accounting_anomaly: false,
+        paused: false,
+        immutable: false,
+        deposit_window_start: 0,
+        deposit_window_end: NOW + 7_200,
+        balance: Some(VaultBalance {
+            token_account: key(4),
+            amount: 1_234_600_000,
+            decimals: 6,
+            discrepancy: 20_000,
+        }),
+        freeze: Some(FreezeStatus {
+            freeze_authority: None,
+            vault_token_account_frozen: false,
+        }),
+        provenance: Some(Provenance {
+            slot: 245_000_000,
+            commitment: CommitmentLevel::Confirmed,
+        }),
+        cluster_time: Some(NOW),
+        notifications: None,
+        maintenance: vec![MaintenanceWindow::new(NOW, NOW + 3_600, "upgrade").unwrap()],
+    }
+}
+
+#[test]
+fn vault_summary() {
+    assert_golden("vault_summary", &VaultView::of(&summary(), 6));
+}
+
+#[test]
+fn deposit_result() {
+    let receipt = TxReceipt {
+        signature: Signature::from([7; 64]),
+        slot: Some(245_000_123),
+        fee_paid: Some(5_000),
+        attempts: 1,
+        events: None,
+        memo: None,
+    };
+    let amount = MintAmount::new(1_500_000, key(3), 6);
+    assert_golden(
+        "deposit_result",
+        &TransferView::deposit(key(1), amount, &receipt),
+    );
+}
+
+#[test]
+fn error() {
+    let err = anyhow!(TokenVaultError::MaintenanceWindow {
+        ends_at: NOW + 3_600,
+        reason: "upgrade".to_string(),
+    })
+    .context("Failed to deposit into vault treasury");
+    assert_golden("error", &ErrorBody::of(&err));
+}
+
+#[test]
+fn amounts_keep_every_digit_as_strings() {
+    let json = serde_json::to_value(Amount::new(u64::MAX, 6)).unwrap();
+    assert_eq!(
+        json,
+        serde_json::json!({
+            "raw": "18446744073709551615",
+            "ui": "18446744073709.551615",
+            "decimals": 6,
+        })
+    );
+    assert_eq!(Amount::new(1_000_000, 6).ui, "1");
+    assert_eq!(Amount::lamports(5_000).ui, "0.000005");
+    assert_eq!(Amount::signed(-1_500, 3).ui, "-1.5");
+    assert_eq!(Amount::signed(-1_500, 3).raw, "-1500");
+    assert_eq!(Amount::signed(7, 0), Amount::new(7, 0));
+}
+
+#[test]
+fn timestamps_are_utc_with_a_z() {
+    assert_eq!(iso_timestamp(NOW), "2023-11-14T22:13:20Z");
+    assert_eq!(iso_timestamp(0), "1970-01-01T00:00:00Z");
+}
+
+#[test]
+fn untyped_errors_have_the_other_code_and_no_details() {
+    let err = Err::<(), _>(anyhow!("connection refused"))
+        .context("Failed to fetch vault")
+        .unwrap_err();
+    let body = ErrorBody::of(&err);
+    assert_eq!(body.code, "other");
+    assert_eq!(body.message, "Failed to fetch vault: connection refused");
+    assert!(body.details.is_empty());
+}
+
+#[test]
+fn typed_errors_are_found_under_context() {
+    let err = anyhow!(TokenVaultError::DepositWindowClosed {
+        opens_at: NOW,
+        closes_at: 0,
+    })
+    .context("Failed to deposit");
+    let body = ErrorBody::of(&err);
+    assert_eq!(body.code, "deposit_window_closed");
+    assert_eq!(
+        serde_json::Value::Object(body.details),
+        // An unbounded end of the window is null, not the epoch
+        serde_json::json!({ "opens_at": "2023-11-14T22:13:20Z", "closes_at": null })
+    );
+}
+
+#[test]
+fn pages_carry_their_cursor_as_a_string() {
+    let page = Page {
+        items: vec![key(9)],
+        next_cursor: None,
+        context_slot: 12,
+    };
+    let view = PageView::of(&page, page.items.iter().map(ToString::to_string).collect());
+    assert_eq!(
+        serde_json::to_value(view).unwrap(),
+        serde_json::json!({
+            "items": [key(9).to_string()],
+            "next_cursor": null,
+            "context_slot": 12,
+        })
+    );
+}
+
+#[test]
+fn history_entries_name_instructions_in_snake_case() {
+    let transaction = VaultTransaction {
+        signature: Signature::from([7; 64]),
+        slot: 42,
+        block_time: Some(NOW),
+        succeeded: true,
+        instructions: vec![VaultInstruction {
+            kind: VaultInstructionKind::InitializeVault,
+            index: 0,
+            accounts: vec![key(1).to_string()],
+            data: vec![1, 2, 3],
+        }],
+        logs: Vec::new(),
+    };
+    let json = serde_json::to_value(TransactionView::from(&transaction)).unwrap();
+    assert_eq!(json["block_time"], "2023-11-14T22:13:20Z");
+    assert_eq!(json["instructions"][0]["kind"], "initialize_vault");
+    assert_eq!(json["instructions"][0]["data"], "AQID");
+}
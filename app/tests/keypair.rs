@@ -0,0 +1,237 @@
+use anchor_client::solana_sdk::signature::{write_keypair_file, Keypair, Signer};
+use std::fs;
+use std::path::PathBuf;
+use token_vault_client::utils::{
+    keypair_from_base58, keypair_from_bytes, keypair_from_mnemonic, load_keypair,
+    load_keypair_from_env, parse_derivation_path,
+};
+use token_vault_client::KeypairSource;
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("{}-{}", Keypair::new().pubkey(), name))
+}
+
+/// Assert that `err` mentions `expected` and none of `secret`
+fn assert_error(err: anyhow::Error, expected: &str, secret: &str) {
+    let message = format!("{:#}", err);
+    assert!(message.contains(expected), "{}", message);
+    assert!(!message.contains(secret), "secret leaked: {}", message);
+}
+
+#[test]
+fn every_source_yields_the_same_keypair() {
+    let keypair = Keypair::new();
+    let path = temp_path("id.json");
+    write_keypair_file(&keypair, &path).unwrap();
+    let var = format!("TOKEN_VAULT_TEST_KEYPAIR_{}", std::process::id());
+    std::env::set_var(&var, keypair.to_base58_string());
+
+    for source in [
+        KeypairSource::File(path.clone()),
+        KeypairSource::Env(var.clone()),
+        KeypairSource::Base58(keypair.to_base58_string()),
+        KeypairSource::Bytes(keypair.to_bytes().to_vec()),
+    ] {
+        assert_eq!(source.resolve().unwrap().pubkey(), keypair.pubkey());
+    }
+    assert_eq!(
+        load_keypair(path.to_str().unwrap()).unwrap().pubkey(),
+        keypair.pubkey()
+    );
+
+    // CI hands over the byte array instead
+    let json = serde_json::to_string(&keypair.to_bytes().to_vec()).unwrap();
+    std::env::set_var(&var, json);
+    assert_eq!(
+        load_keypair_from_env(&var).unwrap().pubkey(),
+        keypair.pubkey()
+    );
+
+    std::env::remove_var(&var);
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn missing_sources_are_named() {
+    let err = load_keypair("/nonexistent/id.json").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Failed to read keypair from /nonexistent/id.json: file not found"
+    );
+
+    let err = load_keypair_from_env("TOKEN_VAULT_TEST_UNSET_KEYPAIR").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Environment variable TOKEN_VAULT_TEST_UNSET_KEYPAIR is not set"
+    );
+}
+
+#[test]
+fn malformed_files_do_not_echo_their_contents() {
+    let secret = Keypair::new().to_base58_string();
+    let path = temp_path("id.json");
+    fs::write(&path, format!("\"{}\"", secret)).unwrap();
+    let err = load_keypair(path.to_str().unwrap()).unwrap_err();
+    assert_error(err, "malformed JSON at line 1", &secret);
+
+    fs::write(&path, "[1, 2, 3]").unwrap();
+    let err = load_keypair(path.to_str().unwrap()).unwrap_err();
+    assert_error(err, "Keypair must be 64 bytes, got 3", &secret);
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn bad_base58_is_refused_without_echo() {
+    let secret = format!("{}0OIl", Keypair::new().to_base58_string());
+    assert_error(
+        keypair_from_base58(&secret).unwrap_err(),
+        "Keypair is not valid base58",
+        &secret,
+    );
+
+    let short = bs58::encode([7u8; 32]).into_string();
+    assert_error(
+        keypair_from_base58(&short).unwrap_err(),
+        "Keypair must be 64 bytes, got 32",
+        &short,
+    );
+
+    let var = format!("TOKEN_VAULT_TEST_BAD_KEYPAIR_{}", std::process::id());
+    std::env::set_var(&var, &secret);
+    assert_error(
+        load_keypair_from_env(&var).unwrap_err(),
+        &format!(
+            "Keypair in environment variable {}: Keypair is not valid base58",
+            var
+        ),
+        &secret,
+    );
+    std::env::remove_var(&var);
+}
+
+#[test]
+fn bytes_must_be_a_matching_keypair() {
+    let err = keypair_from_bytes(&[1; 63]).unwrap_err();
+    assert_eq!(err.to_string(), "Keypair must be 64 bytes, got 63");
+
+    let mut bytes = Keypair::new().to_bytes();
+    bytes[32..].copy_from_slice(&Keypair::new().pubkey().to_bytes());
+    let err = keypair_from_bytes(&bytes).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Keypair's public key does not match its secret key"
+    );
+}
+
+#[test]
+fn debug_output_redacts_inline_keys() {
+    let keypair = Keypair::new();
+    let base58 = KeypairSource::Base58(keypair.to_base58_string());
+    assert_eq!(format!("{:?}", base58), "Base58(<redacted>)");
+    let bytes = KeypairSource::Bytes(keypair.to_bytes().to_vec());
+    assert_eq!(format!("{:?}", bytes), "Bytes(<redacted>)");
+    let env = KeypairSource::Env("PAYER_KEYPAIR".to_string());
+    assert_eq!(format!("{:?}", env), "Env(\"PAYER_KEYPAIR\")");
+}
+
+/// The all-zero-entropy test mnemonic
+const ABANDON: &str =
+    "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
+fn derived(phrase: &str, passphrase: Option<&str>, path: Option<&str>) -> String {
+    keypair_from_mnemonic(phrase, passphrase, path)
+        .unwrap()
+        .pubkey()
+        .to_string()
+}
+
+#[test]
+fn mnemonics_derive_the_keys_solana_keygen_does() {
+    // solana-keygen recover 'prompt://?full-path=...' gives the same keys
+    assert_eq!(
+        derived(ABANDON, None, None),
+        "HAgk14JpMQLgt6rVgv7cBQFJWFto5Dqxi472uT3DKpqk"
+    );
+    assert_eq!(
+        derived(ABANDON, None, Some("m/44'/501'/0'/0'")),
+        "HAgk14JpMQLgt6rVgv7cBQFJWFto5Dqxi472uT3DKpqk"
+    );
+    assert_eq!(
+        derived(ABANDON, None, Some("m/44'/501'/1'/0'")),
+        "Hh8QwFUA6MtVu1qAoq12ucvFHNwCcVTV7hpWjeY1Hztb"
+    );
+    assert_eq!(
+        derived(ABANDON, None, Some("m/44'/501'/0'")),
+        "GjJyeC1r2RgkuoCWMyPYkCWSGSGLcz266EaAkLA27AhL"
+    );
+    assert_eq!(
+        derived(ABANDON, None, Some("m/44h/501h")),
+        "D2PPQSYFe83nDzk96FqGumVU8JA7J8vj2Rhjc2oXzEi5"
+    );
+    assert_eq!(
+        derived(ABANDON, Some("TREZOR"), None),
+        "7zSmbu6gKkb6HB7UDPtHYjwCWuBHU1D4TpNZFm4sndQe"
+    );
+
+    let words = format!("{} art", "abandon ".repeat(23));
+    assert_eq!(
+        derived(&words, None, None),
+        "3Cy3YNTFywCmxoxt8n7UH6hg6dLo5uACowX3CFceaSnx"
+    );
+    // Extra whitespace and capitals do not change the phrase
+    assert_eq!(
+        derived(&format!("  {}\n", words.to_uppercase()), None, None),
+        "3Cy3YNTFywCmxoxt8n7UH6hg6dLo5uACowX3CFceaSnx"
+    );
+}
+
+#[test]
+fn bad_mnemonics_are_refused_without_echo() {
+    let short = ABANDON.replacen("abandon ", "", 1);
+    assert_error(
+        keypair_from_mnemonic(&short, None, None).unwrap_err(),
+        "Mnemonic has 11 words; expected 12, 15, 18, 21 or 24",
+        &short,
+    );
+
+    let typo = ABANDON.replacen("abandon", "abandun", 3);
+    let err = keypair_from_mnemonic(&typo, None, None).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Mnemonic word 1 is not in the BIP39 English word list"
+    );
+
+    let swapped = "abandon ".repeat(12);
+    assert_error(
+        keypair_from_mnemonic(&swapped, None, None).unwrap_err(),
+        "Mnemonic checksum does not match",
+        "abandon",
+    );
+}
+
+#[test]
+fn derivation_paths_must_be_hardened_and_absolute() {
+    assert_eq!(
+        format!("{:?}", parse_derivation_path("m/44'/501'/0'/0'").unwrap()),
+        "m/44'/501'/0'/0'"
+    );
+    assert_eq!(
+        format!("{:?}", parse_derivation_path("m/44h/501h/2h").unwrap()),
+        "m/44'/501'/2'"
+    );
+
+    for (path, reason) in [
+        ("44'/501'/0'/0'", "expected it to start with m/"),
+        ("m", "expected at least one index"),
+        ("m/", "empty index"),
+        ("m/44'/501'/0/0", "index '0' is not hardened"),
+        ("m/44'/sol'", "'sol' is not an index"),
+        ("m/44'/2147483648'", "index 2147483648 is out of range"),
+    ] {
+        let err = parse_derivation_path(path).unwrap_err();
+        assert!(err.to_string().contains(reason), "{}: {}", path, err);
+    }
+
+    let err = keypair_from_mnemonic(ABANDON, None, Some("m/44'/501'/0/0")).unwrap_err();
+    assert!(err.to_string().contains("is not hardened"), "{}", err);
+}
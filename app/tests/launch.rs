@@ -0,0 +1,400 @@
+use anchor_client::solana_sdk::{
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+};
+use anyhow::{anyhow, Result};
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeSet;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::PathBuf;
+use token_vault_client::cli::load_launch_plan;
+use token_vault_client::launch::{AppliedStep, LaunchAllowlist, LaunchMetadata, LaunchVault};
+use token_vault_client::{
+    verify_launch_report, LaunchCheck, LaunchOptions, LaunchPlan, LaunchReport, LaunchStep,
+    LaunchTarget, MaintenanceWindow, StepStatus, TokenVaultError, VaultLauncher,
+};
+
+fn plan() -> LaunchPlan {
+    LaunchPlan {
+        vault: LaunchVault {
+            name: "treasury".to_string(),
+            mint: Pubkey::new_unique(),
+            fee_bps: 25,
+            timelock: 3_600,
+            withdrawal_limit: None,
+            withdrawal_limit_ui: Some("1000".to_string()),
+            fee_collector: Some(Pubkey::new_unique()),
+            immutable: false,
+            deposit_opens: 0,
+            deposit_closes: 0,
+            dust_threshold: 0,
+            waive_dust_fee: false,
+            reserve_bps: 0,
+            reserve_timelock: 0,
+            acknowledge_freezable_mint: false,
+        },
+        metadata: Some(LaunchMetadata {
+            explorer: "https://explorer.solana.com".to_string(),
+            support_contact: "support@example.com".to_string(),
+            webhook_url: None,
+        }),
+        allowlist: Some(LaunchAllowlist {
+            wallets: [Pubkey::new_unique()].into_iter().collect(),
+        }),
+        maintenance: vec![
+            MaintenanceWindow::new(2_000_000_000, 2_000_003_600, "Upgrade").unwrap(),
+            MaintenanceWindow::new(2_000_100_000, 2_000_103_600, "Audit").unwrap(),
+        ],
+    }
+}
+
+fn resume_path() -> PathBuf {
+    std::env::temp_dir().join(format!("launch-{}.resume.json", Pubkey::new_unique()))
+}
+
+/// An in-memory cluster that records every step sent to it
+#[derive(Default)]
+struct Chain {
+    on_chain: RefCell<Vec<LaunchStep>>,
+    sent: RefCell<Vec<LaunchStep>>,
+    simulations: Cell<usize>,
+    /// Simulating this step fails
+    rejects: Option<LaunchStep>,
+    /// The process dies right after this step lands
+    dies_after: Cell<Option<LaunchStep>>,
+    invalid: bool,
+}
+
+impl Chain {
+    fn sends_of(&self, step: LaunchStep) -> usize {
+        self.sent
+            .borrow()
+            .iter()
+            .filter(|sent| **sent == step)
+            .count()
+    }
+}
+
+struct Target<'a>(&'a Chain);
+
+impl LaunchTarget for Target<'_> {
+    fn program_id(&self) -> Pubkey {
+        Pubkey::new_from_array([7; 32])
+    }
+
+    fn vault_address(&self, _plan: &LaunchPlan) -> Result<Pubkey> {
+        Ok(Pubkey::new_from_array([9; 32]))
+    }
+
+    fn validate(&self, plan: &LaunchPlan) -> Result<()> {
+        match self.0.invalid {
+            true => Err(anyhow!("Mint {} does not exist", plan.vault.mint)),
+            false => Ok(()),
+        }
+    }
+
+    fn is_applied(&self, _plan: &LaunchPlan, step: LaunchStep) -> Result<bool> {
+        Ok(self.0.on_chain.borrow().contains(&step))
+    }
+
+    fn simulate(&self, _plan: &LaunchPlan, step: LaunchStep) -> Result<()> {
+        self.0.simulations.set(self.0.simulations.get() + 1);
+        match self.0.rejects {
+            Some(rejected) if rejected == step => Err(anyhow!("custom program error: 0x1771")),
+            _ => Ok(()),
+        }
+    }
+
+    fn apply(&self, _plan: &LaunchPlan, step: LaunchStep) -> Result<AppliedStep> {
+        self.0.on_chain.borrow_mut().push(step);
+        self.0.sent.borrow_mut().push(step);
+        if self.0.dies_after.get() == Some(step) {
+            self.0.dies_after.set(None);
+            panic!("killed after {}", step);
+        }
+        Ok(AppliedStep::default())
+    }
+
+    fn checks(&self, _plan: &LaunchPlan) -> Vec<LaunchCheck> {
+        vec![LaunchCheck::of("config", Ok(()), |_| {
+            "matches the plan".to_string()
+        })]
+    }
+}
+
+fn launch(chain: &Chain, plan: &LaunchPlan, options: LaunchOptions) -> Result<LaunchReport> {
+    VaultLauncher::new(Target(chain), plan, options).run(&Keypair::new())
+}
+
+#[test]
+fn steps_follow_the_plan() {
+    let plan = plan();
+    assert_eq!(
+        plan.steps(),
+        vec![
+            LaunchStep::CreateVault,
+            LaunchStep::FeePath,
+            LaunchStep::Metadata,
+            LaunchStep::Allowlist,
+            LaunchStep::Maintenance(0),
+            LaunchStep::Maintenance(1),
+        ]
+    );
+    let bare = LaunchPlan {
+        metadata: None,
+        allowlist: None,
+        maintenance: Vec::new(),
+        ..plan
+    };
+    assert_eq!(
+        bare.steps(),
+        vec![LaunchStep::CreateVault, LaunchStep::FeePath]
+    );
+}
+
+#[test]
+fn a_launch_applies_every_step_once_and_checks_the_vault() {
+    let plan = plan();
+    let chain = Chain::default();
+    let report = launch(&chain, &plan, LaunchOptions::default()).unwrap();
+
+    assert_eq!(*chain.sent.borrow(), plan.steps());
+    assert_eq!(chain.simulations.get(), plan.steps().len());
+    assert!(report
+        .steps
+        .iter()
+        .all(|step| step.outcome == StepStatus::Applied));
+    assert_eq!(report.checks.len(), 1);
+    assert!(report.is_complete());
+}
+
+#[test]
+fn a_killed_launch_resumes_without_repeating_a_step() {
+    let plan = plan();
+    let path = resume_path();
+    let options = LaunchOptions {
+        resume_path: Some(path.clone()),
+        dry_run: false,
+    };
+    let chain = Chain::default();
+
+    // Killed twice: once after a step was recorded, once before it could be
+    chain.dies_after.set(Some(LaunchStep::Metadata));
+    let killed = catch_unwind(AssertUnwindSafe(|| launch(&chain, &plan, options.clone())));
+    assert!(killed.is_err());
+    chain.dies_after.set(Some(LaunchStep::Maintenance(0)));
+    let killed = catch_unwind(AssertUnwindSafe(|| launch(&chain, &plan, options.clone())));
+    assert!(killed.is_err());
+
+    let report = launch(&chain, &plan, options.clone()).unwrap();
+    for step in plan.steps() {
+        assert_eq!(chain.sends_of(step), 1, "{}", step);
+    }
+    // The steps that landed without being recorded were found on chain
+    let outcome = |step| {
+        report
+            .steps
+            .iter()
+            .find(|record| record.step == step)
+            .unwrap()
+            .outcome
+            .clone()
+    };
+    assert_eq!(outcome(LaunchStep::CreateVault), StepStatus::Applied);
+    assert_eq!(outcome(LaunchStep::Metadata), StepStatus::AlreadyApplied);
+    assert_eq!(
+        outcome(LaunchStep::Maintenance(0)),
+        StepStatus::AlreadyApplied
+    );
+    assert_eq!(outcome(LaunchStep::Maintenance(1)), StepStatus::Applied);
+    assert!(report.is_complete());
+
+    // Running the finished plan again sends nothing
+    launch(&chain, &plan, options).unwrap();
+    assert_eq!(chain.sent.borrow().len(), plan.steps().len());
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn a_failed_simulation_stops_the_launch_before_sending() {
+    let plan = plan();
+    let path = resume_path();
+    let options = LaunchOptions {
+        resume_path: Some(path.clone()),
+        dry_run: false,
+    };
+    let chain = Chain {
+        rejects: Some(LaunchStep::Allowlist),
+        ..Chain::default()
+    };
+
+    let err = launch(&chain, &plan, options.clone()).unwrap_err();
+    assert!(format!("{:#}", err).contains("step 'allowlist'"));
+    assert!(format!("{:#}", err).contains(&path.display().to_string()));
+    assert_eq!(
+        *chain.sent.borrow(),
+        vec![
+            LaunchStep::CreateVault,
+            LaunchStep::FeePath,
+            LaunchStep::Metadata
+        ]
+    );
+
+    // Fixed, the rerun picks up at the failed step
+    let chain = Chain {
+        on_chain: chain.on_chain,
+        ..Chain::default()
+    };
+    let report = launch(&chain, &plan, options).unwrap();
+    assert_eq!(chain.sends_of(LaunchStep::CreateVault), 0);
+    assert_eq!(chain.sends_of(LaunchStep::Allowlist), 1);
+    assert!(report.is_complete());
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn a_resume_file_of_another_plan_is_refused() {
+    let plan = plan();
+    let path = resume_path();
+    let options = LaunchOptions {
+        resume_path: Some(path.clone()),
+        dry_run: false,
+    };
+    launch(&Chain::default(), &plan, options.clone()).unwrap();
+
+    let mut changed = plan.clone();
+    changed.vault.fee_bps = 30;
+    let chain = Chain::default();
+    let err = launch(&chain, &changed, options).unwrap_err();
+    assert!(err.to_string().contains("another launch plan"));
+    assert!(chain.sent.borrow().is_empty());
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn an_invalid_plan_sends_nothing() {
+    let chain = Chain {
+        invalid: true,
+        ..Chain::default()
+    };
+    assert!(launch(&chain, &plan(), LaunchOptions::default()).is_err());
+    assert!(chain.sent.borrow().is_empty());
+
+    // Its maintenance windows would need an authority-gated change
+    let mut plan = plan();
+    plan.vault.immutable = true;
+    let chain = Chain::default();
+    let err = launch(&chain, &plan, LaunchOptions::default()).unwrap_err();
+    assert_eq!(
+        TokenVaultError::of(&err),
+        TokenVaultError::VaultImmutable {
+            vault: Pubkey::new_from_array([9; 32])
+        }
+    );
+    assert!(chain.sent.borrow().is_empty());
+    assert_eq!(chain.simulations.get(), 0);
+
+    plan.maintenance.clear();
+    launch(&Chain::default(), &plan, LaunchOptions::default()).unwrap();
+}
+
+#[test]
+fn a_dry_run_sends_and_records_nothing() {
+    let plan = plan();
+    let path = resume_path();
+    let chain = Chain::default();
+    let options = LaunchOptions {
+        resume_path: Some(path.clone()),
+        dry_run: true,
+    };
+
+    let report = launch(&chain, &plan, options).unwrap();
+    assert!(chain.sent.borrow().is_empty());
+    assert_eq!(chain.simulations.get(), 1);
+    assert!(!path.exists());
+    assert!(report
+        .steps
+        .iter()
+        .all(|step| step.outcome == StepStatus::Planned));
+    assert!(report.checks.is_empty());
+    assert!(!report.is_complete());
+}
+
+#[test]
+fn the_report_is_signed_by_the_authority() {
+    let authority = Keypair::new();
+    let plan = plan();
+    let chain = Chain::default();
+    let report = VaultLauncher::new(Target(&chain), &plan, LaunchOptions::default())
+        .run(&authority)
+        .unwrap();
+    verify_launch_report(&report, &authority.pubkey()).unwrap();
+    assert!(verify_launch_report(&report, &Pubkey::new_unique()).is_err());
+
+    // It still verifies after a round trip through JSON
+    let json = serde_json::to_string(&report).unwrap();
+    let parsed = serde_json::from_str(&json).unwrap();
+    verify_launch_report(&parsed, &authority.pubkey()).unwrap();
+
+    let mut tampered = report;
+    tampered.steps[0].outcome = StepStatus::AlreadyApplied;
+    assert!(verify_launch_report(&tampered, &authority.pubkey()).is_err());
+}
+
+#[test]
+fn the_withdrawal_limit_is_converted_with_the_mint_decimals() {
+    let authority = Pubkey::new_unique();
+    let plan = plan();
+    let config = plan.vault_config(&authority, 6).unwrap();
+    assert_eq!(
+        config.withdrawal_limit.to_base_units(6).unwrap(),
+        1_000_000_000
+    );
+    assert_eq!(config.fee_collector, plan.vault.fee_collector);
+
+    let mut both = plan;
+    both.vault.withdrawal_limit = Some(5);
+    assert!(both.vault_config(&authority, 6).is_err());
+}
+
+#[test]
+fn a_toml_plan_parses() {
+    let mint = Pubkey::new_unique();
+    let wallet = Pubkey::new_unique();
+    let path = std::env::temp_dir().join(format!("launch-{}.toml", Pubkey::new_unique()));
+    std::fs::write(
+        &path,
+        format!(
+            r#"
+[vault]
+name = "treasury"
+mint = "{mint}"
+fee_bps = 25
+timelock = 86400
+withdrawal_limit_ui = "10000"
+
+[metadata]
+explorer = "https://explorer.solana.com"
+
+[allowlist]
+wallets = ["{wallet}"]
+
+[[maintenance]]
+start = 2000000000
+end = 2000003600
+reason = "Upgrade"
+"#
+        ),
+    )
+    .unwrap();
+
+    let plan = load_launch_plan(&path).unwrap();
+    assert_eq!(plan.vault.mint, mint);
+    assert_eq!(plan.vault.fee_collector, None);
+    assert_eq!(plan.allowlist.unwrap().wallets, BTreeSet::from([wallet]));
+    assert_eq!(plan.maintenance.len(), 1);
+
+    std::fs::write(&path, "[vault]\nname = \"treasury\"\nfee = 25\n").unwrap();
+    assert!(load_launch_plan(&path).is_err());
+    std::fs::remove_file(path).unwrap();
+}
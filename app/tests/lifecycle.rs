@@ -0,0 +1,239 @@
+mod common;
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use token_vault_client::lifecycle::{lifecycle, VAULT_OPERATIONS};
+use token_vault_client::maintenance::check_maintenance;
+use token_vault_client::pause::check_not_paused;
+use token_vault_client::token_vault::state::Vault;
+use token_vault_client::{
+    DepositWindow, FreezeStatus, MaintenanceWindow, OperationKind, TokenVaultError, VaultLifecycle,
+};
+
+const HOUR: i64 = 60 * 60;
+const NOW: i64 = 1_700_000_000;
+
+fn vault(window: DepositWindow) -> Vault {
+    Vault {
+        deposit_window_start: window.start,
+        deposit_window_end: window.end,
+        ..common::vault()
+    }
+}
+
+fn freeze(frozen: bool) -> FreezeStatus {
+    FreezeStatus {
+        freeze_authority: Some(Pubkey::new_unique()),
+        vault_token_account_frozen: frozen,
+    }
+}
+
+fn maintenance(start: i64, end: i64) -> MaintenanceWindow {
+    MaintenanceWindow::new(start, end, "migration").unwrap()
+}
+
+/// Whether `kind` acts on an existing vault. No wildcard arm, so a new
+/// operation does not compile until it is placed in or out of the matrix.
+fn acts_on_a_vault(kind: OperationKind) -> bool {
+    match kind {
+        OperationKind::Deposit
+        | OperationKind::Withdraw
+        | OperationKind::RequestWithdrawal
+        | OperationKind::CancelWithdrawal
+        | OperationKind::SweepLamports
+        | OperationKind::ReleaseReserve
+        | OperationKind::UpdateVaultConfig
+        | OperationKind::TransferAuthority
+        | OperationKind::SetFeeCollector
+        | OperationKind::PauseVault
+        | OperationKind::UnpauseVault
+        | OperationKind::ClaimFees
+        | OperationKind::AllowlistSync
+        | OperationKind::SetFeatureFlags
+        | OperationKind::SetNotificationConfig
+        | OperationKind::ClearNotificationConfig
+        | OperationKind::RepairFeePath
+        | OperationKind::ScheduleMaintenance => true,
+        OperationKind::InitializeVault
+        | OperationKind::FundFeePayers
+        | OperationKind::CreateLookupTable => false,
+    }
+}
+
+/// One vault in every stage, each alongside what it refuses
+fn stages() -> Vec<(VaultLifecycle, &'static [OperationKind])> {
+    let open = vault(DepositWindow::UNRESTRICTED);
+    let later = vault(DepositWindow::new(NOW + HOUR, 0).unwrap());
+    let over = vault(DepositWindow::new(0, NOW - HOUR).unwrap());
+    let active_maintenance = [maintenance(NOW - HOUR, NOW + HOUR)];
+    let paused = Vault {
+        paused: true,
+        ..vault(DepositWindow::UNRESTRICTED)
+    };
+    vec![
+        (lifecycle(&open, &freeze(false), &[], NOW), &[]),
+        (
+            lifecycle(&open, &freeze(true), &[], NOW),
+            &[
+                OperationKind::Deposit,
+                OperationKind::Withdraw,
+                OperationKind::ClaimFees,
+            ],
+        ),
+        (
+            lifecycle(&later, &freeze(false), &[], NOW),
+            &[OperationKind::Deposit],
+        ),
+        (
+            lifecycle(&over, &freeze(false), &[], NOW),
+            &[OperationKind::Deposit],
+        ),
+        (
+            lifecycle(&open, &freeze(false), &active_maintenance, NOW),
+            &[OperationKind::Deposit],
+        ),
+        (
+            lifecycle(&paused, &freeze(false), &[], NOW),
+            &[OperationKind::Deposit, OperationKind::Withdraw],
+        ),
+    ]
+}
+
+#[test]
+fn the_operation_list_covers_every_vault_operation() {
+    assert!(VAULT_OPERATIONS.iter().all(|kind| acts_on_a_vault(*kind)));
+    for kind in [
+        OperationKind::InitializeVault,
+        OperationKind::FundFeePayers,
+        OperationKind::CreateLookupTable,
+    ] {
+        assert!(!VAULT_OPERATIONS.contains(&kind));
+    }
+}
+
+#[test]
+fn every_stage_refuses_exactly_its_operations() {
+    let stages = stages();
+    let names: Vec<String> = stages
+        .iter()
+        .map(|(stage, _)| format!("{:?}", stage))
+        .collect();
+    assert!(names[0].starts_with("Active"), "{}", names[0]);
+    assert!(names[1].starts_with("Frozen"), "{}", names[1]);
+    assert!(names[2].starts_with("DepositsNotOpen"), "{}", names[2]);
+    assert!(names[3].starts_with("DepositsClosed"), "{}", names[3]);
+    assert!(names[4].starts_with("Maintenance"), "{}", names[4]);
+    assert!(names[5].starts_with("Paused"), "{}", names[5]);
+
+    for (stage, refused) in &stages {
+        for kind in VAULT_OPERATIONS {
+            assert_eq!(
+                stage.permits(kind),
+                !refused.contains(&kind),
+                "{:?} in {:?}",
+                kind,
+                stage
+            );
+            assert_eq!(stage.check(kind).is_ok(), stage.permits(kind));
+        }
+        let permitted: Vec<_> = VAULT_OPERATIONS
+            .into_iter()
+            .filter(|kind| !refused.contains(kind))
+            .collect();
+        assert_eq!(stage.permitted_operations(), permitted, "{:?}", stage);
+    }
+}
+
+#[test]
+fn refusals_are_the_errors_the_single_checks_raise() {
+    let frozen = freeze(true);
+    assert_eq!(
+        lifecycle(&vault(DepositWindow::UNRESTRICTED), &frozen, &[], NOW)
+            .check(OperationKind::Withdraw),
+        frozen.check()
+    );
+
+    let window = DepositWindow::new(0, NOW - HOUR).unwrap();
+    assert_eq!(
+        lifecycle(&vault(window), &freeze(false), &[], NOW).check(OperationKind::Deposit),
+        window.check(NOW)
+    );
+
+    let windows = [maintenance(NOW - HOUR, NOW + HOUR)];
+    assert_eq!(
+        lifecycle(
+            &vault(DepositWindow::UNRESTRICTED),
+            &freeze(false),
+            &windows,
+            NOW
+        )
+        .check(OperationKind::Deposit),
+        check_maintenance(&windows, NOW)
+    );
+
+    let paused = Vault {
+        paused: true,
+        ..vault(DepositWindow::UNRESTRICTED)
+    };
+    assert_eq!(
+        lifecycle(&paused, &freeze(false), &[], NOW).check(OperationKind::Withdraw),
+        check_not_paused(&paused)
+    );
+}
+
+#[test]
+fn the_most_lasting_restriction_wins() {
+    let over = vault(DepositWindow::new(0, NOW - HOUR).unwrap());
+    let windows = [maintenance(NOW - HOUR, NOW + HOUR)];
+
+    assert!(matches!(
+        lifecycle(&over, &freeze(true), &windows, NOW),
+        VaultLifecycle::Frozen { .. }
+    ));
+    assert!(matches!(
+        lifecycle(&over, &freeze(false), &windows, NOW),
+        VaultLifecycle::DepositsClosed { .. }
+    ));
+
+    // Unpausing takes the authority alone, so a pause is outlasted by a freeze
+    let paused = Vault {
+        paused: true,
+        ..over
+    };
+    assert!(matches!(
+        lifecycle(&paused, &freeze(true), &windows, NOW),
+        VaultLifecycle::Frozen { .. }
+    ));
+    assert!(matches!(
+        lifecycle(&paused, &freeze(false), &windows, NOW),
+        VaultLifecycle::Paused { .. }
+    ));
+}
+
+#[test]
+fn stages_follow_the_clock() {
+    let open = vault(DepositWindow::new(NOW, NOW + 4 * HOUR).unwrap());
+    let windows = [maintenance(NOW + HOUR, NOW + 2 * HOUR)];
+    let at = |now| lifecycle(&open, &freeze(false), &windows, now);
+
+    assert!(matches!(
+        at(NOW - 1),
+        VaultLifecycle::DepositsNotOpen { .. }
+    ));
+    assert_eq!(at(NOW), VaultLifecycle::Active);
+    assert_eq!(
+        at(NOW + HOUR),
+        VaultLifecycle::Maintenance {
+            until: NOW + 2 * HOUR,
+            reason: "migration".to_string(),
+        }
+    );
+    assert_eq!(at(NOW + 2 * HOUR), VaultLifecycle::Active);
+    assert!(matches!(
+        at(NOW + 4 * HOUR),
+        VaultLifecycle::DepositsClosed { .. }
+    ));
+    assert!(matches!(
+        at(NOW + HOUR).check(OperationKind::Deposit),
+        Err(TokenVaultError::MaintenanceWindow { .. })
+    ));
+}
@@ -0,0 +1,108 @@
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use proptest::prelude::*;
+use token_vault_client::liquidity::liquidity_history;
+use token_vault_client::{LiquidityProfile, VaultSnapshot};
+
+fn snapshot(vault_token_balance: u64, reserve_balance: u64) -> VaultSnapshot {
+    VaultSnapshot {
+        taken_at: 1_000,
+        vault: Pubkey::new_from_array([1; 32]),
+        mint: Pubkey::new_from_array([2; 32]),
+        decimals: 6,
+        vault_token_balance,
+        vault_token_frozen: false,
+        reserve_balance,
+        reserve_frozen: false,
+        reserve_unlocks_at: 0,
+        withdrawal_limit: u64::MAX,
+        withdrawal_timelock: 0,
+    }
+}
+
+fn bucket_sum(profile: &LiquidityProfile) -> u64 {
+    profile.buckets().iter().map(|(_, amount)| amount.raw).sum()
+}
+
+#[test]
+fn the_reserve_is_timelocked_until_it_unlocks() {
+    let locked = VaultSnapshot {
+        reserve_unlocks_at: 1_001,
+        ..snapshot(700, 300)
+    };
+    let profile = LiquidityProfile::of(&locked);
+    assert_eq!(profile.withdrawable.raw, 700);
+    assert_eq!(profile.timelocked.raw, 300);
+    assert_eq!(profile.reserved.raw, 0);
+
+    let unlocked = VaultSnapshot {
+        reserve_unlocks_at: 1_000,
+        ..locked
+    };
+    let profile = LiquidityProfile::of(&unlocked);
+    assert_eq!(profile.timelocked.raw, 0);
+    assert_eq!(profile.reserved.raw, 300);
+}
+
+#[test]
+fn frozen_accounts_are_not_withdrawable_and_the_limit_caps_one_withdrawal() {
+    let frozen = VaultSnapshot {
+        vault_token_frozen: true,
+        withdrawal_limit: 50,
+        ..snapshot(700, 300)
+    };
+    let profile = LiquidityProfile::of(&frozen);
+    assert_eq!(profile.frozen.raw, 700);
+    assert_eq!(profile.withdrawable.raw, 0);
+    assert_eq!(profile.max_single_withdrawal.raw, 0);
+
+    let limited = VaultSnapshot {
+        withdrawal_limit: 50,
+        ..snapshot(700, 300)
+    };
+    assert_eq!(LiquidityProfile::of(&limited).max_single_withdrawal.raw, 50);
+}
+
+#[test]
+fn history_is_ordered_and_limited_to_one_vault() {
+    let later = VaultSnapshot {
+        taken_at: 2_000,
+        ..snapshot(10, 0)
+    };
+    let history = liquidity_history(&[later, snapshot(20, 0)]).unwrap();
+    let times: Vec<_> = history.iter().map(|profile| profile.taken_at).collect();
+    assert_eq!(times, [1_000, 2_000]);
+    assert_eq!(history[0].withdrawable.raw, 20);
+
+    let other_vault = VaultSnapshot {
+        vault: Pubkey::new_unique(),
+        ..snapshot(10, 0)
+    };
+    assert!(liquidity_history(&[snapshot(20, 0), other_vault]).is_err());
+    assert!(liquidity_history(&[]).unwrap().is_empty());
+}
+
+proptest! {
+    #[test]
+    fn buckets_always_sum_to_the_vault_balance(
+        vault_token_balance in 0..=u64::MAX / 2,
+        reserve_balance in 0..=u64::MAX / 2,
+        vault_token_frozen: bool,
+        reserve_frozen: bool,
+        reserve_unlocks_at in 0i64..2_000,
+        withdrawal_limit: u64,
+    ) {
+        let snapshot = VaultSnapshot {
+            vault_token_frozen,
+            reserve_frozen,
+            reserve_unlocks_at,
+            withdrawal_limit,
+            ..snapshot(vault_token_balance, reserve_balance)
+        };
+        let profile = LiquidityProfile::of(&snapshot);
+
+        // `get_vault_balance` is the snapshot's total
+        prop_assert_eq!(profile.total, snapshot.total());
+        prop_assert_eq!(bucket_sum(&profile), snapshot.total().raw);
+        prop_assert!(profile.max_single_withdrawal.raw <= profile.withdrawable.raw);
+    }
+}
@@ -0,0 +1,376 @@
+#![cfg(feature = "tracing")]
+
+use anchor_client::solana_client::rpc_client::RpcClient;
+use anchor_client::solana_sdk::{
+    commitment_config::CommitmentConfig,
+    pubkey::Pubkey,
+    signature::{Keypair, Signature, Signer},
+};
+use anyhow::anyhow;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use token_vault_client::logging::{
+    log_cache_lookup, log_operation, log_policy_decision, log_rpc_error, log_rpc_response,
+    operation_span,
+};
+use token_vault_client::support::hash_pubkey;
+use token_vault_client::{CachedKind, Operation, OperationKind, RpcCache, RpcCacheConfig};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::subscriber::Interest;
+use tracing::{Event, Level, Metadata, Subscriber};
+
+/// Fields that belong to trace-level RPC lines only
+const TRACE_ONLY_FIELDS: [&str; 3] = ["method", "request_bytes", "response_bytes"];
+
+#[derive(Debug, Clone)]
+struct Captured {
+    level: Level,
+    fields: BTreeMap<String, String>,
+    correlation_id: Option<String>,
+}
+
+impl Captured {
+    fn text(&self) -> String {
+        format!("{:?}", self.fields)
+    }
+}
+
+struct FieldVisitor<'a>(&'a mut BTreeMap<String, String>);
+
+impl Visit for FieldVisitor<'_> {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0
+            .insert(field.name().to_string(), format!("{:?}", value));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), value.to_string());
+    }
+}
+
+/// Fields of the spans opened, in order
+type OpenedSpans = Arc<Mutex<Vec<BTreeMap<String, String>>>>;
+
+/// Records every event up to `max_level`, with the correlation id of the
+/// span it was logged in
+struct Capture {
+    max_level: Level,
+    events: Arc<Mutex<Vec<Captured>>>,
+    opened: OpenedSpans,
+    spans: Mutex<HashMap<u64, String>>,
+    entered: Mutex<Vec<u64>>,
+    next_id: AtomicU64,
+}
+
+impl Capture {
+    fn new(max_level: Level) -> (Self, Arc<Mutex<Vec<Captured>>>, OpenedSpans) {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let opened = Arc::new(Mutex::new(Vec::new()));
+        let capture = Self {
+            max_level,
+            events: Arc::clone(&events),
+            opened: Arc::clone(&opened),
+            spans: Mutex::new(HashMap::new()),
+            entered: Mutex::new(Vec::new()),
+            next_id: AtomicU64::new(1),
+        };
+        (capture, events, opened)
+    }
+}
+
+impl Subscriber for Capture {
+    fn register_callsite(&self, _: &'static Metadata<'static>) -> Interest {
+        // Tests run with different levels on different threads
+        Interest::sometimes()
+    }
+
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        // Only the client's own events, not those of the HTTP stack
+        *metadata.level() <= self.max_level && metadata.target().starts_with("token_vault_client")
+    }
+
+    fn new_span(&self, attributes: &Attributes<'_>) -> Id {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let mut fields = BTreeMap::new();
+        attributes.record(&mut FieldVisitor(&mut fields));
+        self.opened.lock().unwrap().push(fields.clone());
+        if let Some(correlation_id) = fields.remove("correlation_id") {
+            self.spans.lock().unwrap().insert(id, correlation_id);
+        }
+        Id::from_u64(id)
+    }
+
+    fn record(&self, _: &Id, _: &Record<'_>) {}
+
+    fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let mut fields = BTreeMap::new();
+        event.record(&mut FieldVisitor(&mut fields));
+        let correlation_id = fields.get("correlation_id").cloned().or_else(|| {
+            let spans = self.spans.lock().unwrap();
+            self.entered
+                .lock()
+                .unwrap()
+                .iter()
+                .rev()
+                .find_map(|id| spans.get(id).cloned())
+        });
+        self.events.lock().unwrap().push(Captured {
+            level: *event.metadata().level(),
+            fields,
+            correlation_id,
+        });
+    }
+
+    fn enter(&self, span: &Id) {
+        self.entered.lock().unwrap().push(span.into_u64());
+    }
+
+    fn exit(&self, span: &Id) {
+        let mut entered = self.entered.lock().unwrap();
+        if let Some(position) = entered.iter().rposition(|id| *id == span.into_u64()) {
+            entered.remove(position);
+        }
+    }
+}
+
+/// Events `run` logs at `max_level` and above
+fn capture(max_level: Level, run: impl FnOnce()) -> Vec<Captured> {
+    let (subscriber, events, _) = Capture::new(max_level);
+    tracing::subscriber::with_default(subscriber, run);
+    let events = events.lock().unwrap().clone();
+    events
+}
+
+/// Fields of the spans `run` opens
+fn capture_spans(run: impl FnOnce()) -> Vec<BTreeMap<String, String>> {
+    let (subscriber, _, opened) = Capture::new(Level::TRACE);
+    tracing::subscriber::with_default(subscriber, run);
+    let opened = opened.lock().unwrap().clone();
+    opened
+}
+
+fn deposit(vault: Pubkey) -> Operation {
+    Operation::new(
+        OperationKind::Deposit,
+        Keypair::new().pubkey(),
+        Some(vault),
+        1_500_000,
+    )
+}
+
+/// One mutation as the client logs it: a cache miss, two policy decisions,
+/// an RPC exchange and the summary, all inside the operation span
+fn run_operation(operation: &Operation, redact: bool) {
+    let span = operation_span(operation);
+    let _entered = span.enter();
+    log_cache_lookup(&operation.vault.unwrap(), CachedKind::Vault, "miss");
+    log_rpc_response("getAccountInfo", 32, 180);
+    log_policy_decision(0, &Ok(()));
+    log_policy_decision(1, &Ok(()));
+    log_rpc_response("sendAndConfirmTransaction", 412, 64);
+    log_operation(
+        operation,
+        Ok(&Signature::new_unique()),
+        Duration::from_millis(840),
+        redact,
+    );
+}
+
+#[test]
+fn info_is_one_summary_line_per_operation() {
+    let vault = Pubkey::new_unique();
+    let operation = deposit(vault);
+    let events = capture(Level::INFO, || run_operation(&operation, false));
+
+    assert_eq!(events.len(), 1);
+    let summary = &events[0];
+    assert_eq!(summary.level, Level::INFO);
+    let names: Vec<_> = summary.fields.keys().map(String::as_str).collect();
+    assert_eq!(
+        names,
+        vec![
+            "amount",
+            "correlation_id",
+            "latency_ms",
+            "message",
+            "op",
+            "signature",
+            "vault"
+        ]
+    );
+    assert_eq!(summary.fields["op"], "deposit");
+    assert_eq!(summary.fields["vault"], vault.to_string());
+    assert_eq!(summary.fields["amount"], "1500000");
+    assert_eq!(summary.fields["latency_ms"], "840");
+    assert_eq!(summary.correlation_id, Some(operation.id.to_string()));
+    for field in TRACE_ONLY_FIELDS {
+        assert!(!summary.fields.contains_key(field));
+    }
+}
+
+#[test]
+fn debug_adds_decisions_and_trace_adds_rpc() {
+    let operation = deposit(Pubkey::new_unique());
+    let debug = capture(Level::DEBUG, || run_operation(&operation, false));
+    let levels: Vec<_> = debug.iter().map(|event| event.level).collect();
+    assert_eq!(
+        levels,
+        vec![Level::DEBUG, Level::DEBUG, Level::DEBUG, Level::INFO]
+    );
+    assert_eq!(debug[0].fields["outcome"], "miss");
+    assert_eq!(debug[1].fields["decision"], "approved");
+
+    let trace = capture(Level::TRACE, || run_operation(&operation, false));
+    assert_eq!(trace.len(), 6);
+    let rpc: Vec<_> = trace
+        .iter()
+        .filter(|event| event.level == Level::TRACE)
+        .collect();
+    assert_eq!(rpc.len(), 2);
+    assert_eq!(rpc[1].fields["method"], "sendAndConfirmTransaction");
+    assert_eq!(rpc[1].fields["request_bytes"], "412");
+    for event in &rpc {
+        assert!(!event.fields.contains_key("amount"));
+        assert!(!event.fields.contains_key("signature"));
+    }
+}
+
+#[test]
+fn every_event_of_an_operation_carries_its_correlation_id() {
+    let operation = deposit(Pubkey::new_unique());
+    let events = capture(Level::TRACE, || run_operation(&operation, false));
+    for event in &events {
+        assert_eq!(event.correlation_id, Some(operation.id.to_string()));
+    }
+
+    let outside = capture(Level::TRACE, || log_rpc_response("getSlot", 0, 8));
+    assert_eq!(outside[0].correlation_id, None);
+}
+
+#[test]
+fn keys_below_info_are_always_hashed() {
+    let vault = Pubkey::new_unique();
+    let operation = deposit(vault);
+    let events = capture(Level::TRACE, || run_operation(&operation, false));
+    for event in events.iter().filter(|event| event.level > Level::INFO) {
+        assert!(!event.text().contains(&vault.to_string()), "{:?}", event);
+    }
+    assert_eq!(events[0].fields["account"], hash_pubkey(&vault));
+}
+
+#[test]
+fn operation_spans_carry_the_operation_with_keys_hashed() {
+    let signer = Keypair::new();
+    let vault = Pubkey::new_unique();
+    let operation = Operation::new(OperationKind::Deposit, signer.pubkey(), Some(vault), 7);
+    let spans = capture_spans(|| run_operation(&operation, false));
+    assert_eq!(spans.len(), 1);
+    let span = &spans[0];
+    assert_eq!(span["correlation_id"], operation.id.to_string());
+    assert_eq!(span["vault"], hash_pubkey(&vault));
+    assert_eq!(span["signer"], hash_pubkey(&signer.pubkey()));
+    assert_eq!(span["amount"], "7");
+    // Recorded once the operation confirms
+    assert!(!span.contains_key("signature"));
+}
+
+#[test]
+fn log_redaction_hashes_keys_at_info() {
+    let vault = Pubkey::new_unique();
+    let operation = deposit(vault);
+    let events = capture(Level::TRACE, || run_operation(&operation, true));
+    for event in &events {
+        assert!(!event.text().contains(&vault.to_string()), "{:?}", event);
+    }
+    let summary = events.last().unwrap();
+    assert_eq!(summary.fields["vault"], hash_pubkey(&vault));
+}
+
+#[test]
+fn failures_warn_without_secrets() {
+    let vault = Pubkey::new_unique();
+    let operation = deposit(vault);
+    let err = anyhow!(
+        "Account {} not found (https://rpc.example.com/v1/secret-api-key)",
+        vault
+    );
+    let events = capture(Level::INFO, || {
+        log_operation(&operation, Err(&err), Duration::from_millis(5), true)
+    });
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].level, Level::WARN);
+    let error = &events[0].fields["error"];
+    assert!(!error.contains("secret-api-key"));
+    assert!(!error.contains(&vault.to_string()));
+    assert!(error.contains(&hash_pubkey(&vault)));
+
+    let trace = capture(Level::TRACE, || {
+        log_rpc_error("getAccountInfo", 32, &err);
+        log_policy_decision(0, &Err(anyhow!("{} is over its daily limit", vault)));
+    });
+    for event in &trace {
+        assert!(!event.text().contains("secret-api-key"));
+        assert!(!event.text().contains(&vault.to_string()));
+    }
+}
+
+#[test]
+fn key_material_is_never_logged() {
+    let signer = Keypair::new();
+    let operation = Operation::new(
+        OperationKind::Withdraw,
+        signer.pubkey(),
+        Some(Pubkey::new_unique()),
+        10,
+    );
+    let events = capture(Level::TRACE, || run_operation(&operation, false));
+    let secret = signer.to_base58_string();
+    let bytes = format!("{:?}", signer.to_bytes());
+    for event in &events {
+        let text = event.text();
+        assert!(!text.contains(&secret));
+        assert!(!text.contains(&bytes));
+        assert!(!text.contains(&signer.pubkey().to_string()));
+    }
+}
+
+#[test]
+fn cache_lookups_are_logged_at_debug_and_fetches_at_trace() {
+    // Nothing listens on port 1, so the fetch fails right away
+    let cache = RpcCache::new(RpcCacheConfig::default(), "http://127.0.0.1:1".to_string());
+    let rpc = RpcClient::new("http://127.0.0.1:1".to_string());
+    let address = Pubkey::new_unique();
+    let events = capture(Level::TRACE, || {
+        let fetched = cache.get_account(
+            &rpc,
+            address,
+            CommitmentConfig::confirmed(),
+            CachedKind::Vault,
+        );
+        assert!(fetched.is_err());
+    });
+
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0].level, Level::DEBUG);
+    assert_eq!(events[0].fields["outcome"], "miss");
+    assert_eq!(events[0].fields["kind"], "Vault");
+    assert_eq!(events[1].level, Level::TRACE);
+    assert_eq!(events[1].fields["method"], "getAccountInfo");
+    assert!(events[1].fields.contains_key("error"));
+
+    let info = capture(Level::INFO, || {
+        let _ = cache.get_account(
+            &rpc,
+            address,
+            CommitmentConfig::confirmed(),
+            CachedKind::Vault,
+        );
+    });
+    assert!(info.is_empty());
+}
@@ -0,0 +1,145 @@
+use anchor_client::solana_sdk::signer::Signer;
+use token_vault_client::fixtures::VaultFixture;
+use token_vault_client::maintenance::{check_maintenance, schedule_window};
+use token_vault_client::token_vault::state::MaintenanceSchedule;
+use token_vault_client::{
+    verify_action_receipt, AdminState, MaintenanceStatus, MaintenanceWindow, TokenVaultError,
+};
+
+const NOW: i64 = 1_700_000_000;
+const HOUR: i64 = 3_600;
+
+fn window(start: i64, end: i64) -> MaintenanceWindow {
+    MaintenanceWindow::new(start, end, "database migration").unwrap()
+}
+
+#[test]
+fn windows_need_an_order_and_a_storable_reason() {
+    assert!(MaintenanceWindow::new(NOW, NOW, "upgrade").is_err());
+    assert!(MaintenanceWindow::new(NOW + HOUR, NOW, "upgrade").is_err());
+    assert!(MaintenanceWindow::new(NOW, NOW + HOUR, "  ").is_err());
+    assert!(MaintenanceWindow::new(NOW, NOW + HOUR, "line\nbreak").is_err());
+    let longest = "x".repeat(MaintenanceSchedule::MAX_REASON_LEN);
+    assert!(MaintenanceWindow::new(NOW, NOW + HOUR, longest.clone()).is_ok());
+    assert!(MaintenanceWindow::new(NOW, NOW + HOUR, longest + "x").is_err());
+}
+
+#[test]
+fn deposits_are_refused_only_while_a_window_is_active() {
+    let windows = [window(NOW + HOUR, NOW + 3 * HOUR)];
+    assert!(check_maintenance(&windows, NOW).is_ok());
+    assert_eq!(
+        check_maintenance(&windows, NOW + HOUR),
+        Err(TokenVaultError::MaintenanceWindow {
+            ends_at: NOW + 3 * HOUR,
+            reason: "database migration".to_string(),
+        })
+    );
+    // The window ends on its own, with nothing sent
+    assert!(check_maintenance(&windows, NOW + 3 * HOUR).is_ok());
+    assert!(check_maintenance(&[], NOW).is_ok());
+}
+
+#[test]
+fn status_counts_down_to_the_start_and_then_the_end() {
+    let window = window(NOW + HOUR, NOW + 3 * HOUR);
+    assert_eq!(
+        window.status(NOW),
+        MaintenanceStatus::Upcoming { starts_in: HOUR }
+    );
+    assert_eq!(
+        window.describe(NOW),
+        "starts in 1h, lasts 2h: database migration"
+    );
+    assert_eq!(
+        window.status(NOW + 2 * HOUR),
+        MaintenanceStatus::Active { ends_in: HOUR }
+    );
+    assert_eq!(
+        window.describe(NOW + 2 * HOUR),
+        "active, ends in 1h: database migration"
+    );
+    assert_eq!(window.status(NOW + 3 * HOUR), MaintenanceStatus::Ended);
+}
+
+#[test]
+fn overlapping_windows_are_rejected_and_ended_ones_dropped() {
+    let ended = window(NOW - 2 * HOUR, NOW - HOUR);
+    let upcoming = window(NOW + HOUR, NOW + 2 * HOUR);
+    let existing = [ended, upcoming.clone()];
+
+    let err = schedule_window(&existing, window(NOW + 90 * 60, NOW + 3 * HOUR), NOW).unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<TokenVaultError>(),
+        Some(&TokenVaultError::MaintenanceOverlap {
+            start: upcoming.start,
+            end: upcoming.end,
+            reason: upcoming.reason.clone(),
+        })
+    );
+
+    // Back to back is not an overlap; the ended window may be overlapped
+    // since it is dropped
+    let earlier = window(NOW - 90 * 60, NOW + HOUR);
+    let schedule = schedule_window(&existing, earlier.clone(), NOW).unwrap();
+    assert_eq!(schedule, vec![earlier, upcoming]);
+
+    assert!(schedule_window(&[], window(NOW - HOUR, NOW), NOW).is_err());
+}
+
+#[test]
+fn the_schedule_holds_a_bounded_number_of_windows() {
+    let full: Vec<_> = (0..MaintenanceSchedule::MAX_WINDOWS as i64)
+        .map(|i| window(NOW + (2 * i + 1) * HOUR, NOW + (2 * i + 2) * HOUR))
+        .collect();
+    let err = schedule_window(&full, window(NOW + 100 * HOUR, NOW + 101 * HOUR), NOW).unwrap_err();
+    assert!(err.to_string().contains("wait for one to end"), "{}", err);
+    // Once the first has ended there is room again
+    assert!(schedule_window(
+        &full,
+        window(NOW + 100 * HOUR, NOW + 101 * HOUR),
+        NOW + 2 * HOUR
+    )
+    .is_ok());
+}
+
+/// Needs a validator with the token vault program deployed; see
+/// `fixtures::VALIDATOR_ENV` and `fixtures::PROGRAM_ID_ENV`
+#[test]
+#[ignore = "needs a local validator; run with --ignored"]
+fn deposits_pause_for_maintenance_while_withdrawals_continue() {
+    let fixture = VaultFixture::builder().build().unwrap();
+    fixture.deposit_as(0, 10_000).unwrap();
+
+    let client = fixture.client();
+    let now = client.cluster_time().unwrap();
+    let receipt = client
+        .schedule_maintenance(fixture.authority(), now, now + HOUR, "upgrade".to_string())
+        .unwrap();
+    verify_action_receipt(&receipt, &fixture.authority().pubkey()).unwrap();
+    assert_eq!(receipt.before, AdminState::Maintenance { windows: vec![] });
+
+    let err = fixture.deposit_as(0, 1_000).unwrap_err();
+    assert!(
+        matches!(
+            err.downcast_ref::<TokenVaultError>(),
+            Some(TokenVaultError::MaintenanceWindow { ends_at, .. }) if *ends_at == now + HOUR
+        ),
+        "{}",
+        err
+    );
+    fixture.withdraw_as(0, 1_000).unwrap();
+
+    let err = client
+        .schedule_maintenance(
+            fixture.authority(),
+            now + 60,
+            now + 2 * HOUR,
+            "more".to_string(),
+        )
+        .unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<TokenVaultError>(),
+        Some(TokenVaultError::MaintenanceOverlap { .. })
+    ));
+}
@@ -0,0 +1,110 @@
+use anchor_client::solana_sdk::{
+    message::{Message, VersionedMessage},
+    pubkey::Pubkey,
+    signature::Signature,
+    transaction::VersionedTransaction,
+};
+use anchor_lang::Event;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use solana_transaction_status::{
+    EncodableWithMeta, EncodedConfirmedTransactionWithStatusMeta, EncodedTransactionWithStatusMeta,
+    TransactionStatusMeta, UiTransactionEncoding,
+};
+use token_vault_client::events::transaction_events;
+use token_vault_client::memo::{check_memo, memo_instruction, transaction_memos, MAX_MEMO_LEN};
+use token_vault_client::token_vault::{self, events};
+use token_vault_client::{TokenVaultError, TxReceipt};
+
+/// A deposit of `memos` with its event logged, as `getTransaction` returns
+/// it in base64
+fn fetched(depositor: &Pubkey, memos: &[&str]) -> EncodedConfirmedTransactionWithStatusMeta {
+    let instructions: Vec<_> = memos
+        .iter()
+        .map(|memo| memo_instruction(memo, depositor))
+        .collect();
+    let transaction = VersionedTransaction {
+        signatures: vec![Signature::default()],
+        message: VersionedMessage::Legacy(Message::new(&instructions, Some(depositor))),
+    };
+    let event = events::DepositEvent {
+        vault: Pubkey::new_unique(),
+        depositor: *depositor,
+        amount: 1_000,
+    };
+    let program = token_vault::ID;
+    let meta = TransactionStatusMeta {
+        log_messages: Some(vec![
+            format!("Program {} invoke [1]", program),
+            format!("Program data: {}", STANDARD.encode(event.data())),
+            format!("Program {} success", program),
+        ]),
+        ..Default::default()
+    };
+    EncodedConfirmedTransactionWithStatusMeta {
+        slot: 42,
+        transaction: EncodedTransactionWithStatusMeta {
+            transaction: transaction.encode_with_meta(UiTransactionEncoding::Base64, &meta),
+            meta: Some(meta.into()),
+            version: None,
+        },
+        block_time: None,
+    }
+}
+
+#[test]
+fn memos_over_the_limit_are_refused() {
+    check_memo(&"x".repeat(MAX_MEMO_LEN)).unwrap();
+    assert_eq!(
+        check_memo(&"x".repeat(MAX_MEMO_LEN + 1)),
+        Err(TokenVaultError::MemoTooLong {
+            len: MAX_MEMO_LEN + 1,
+            max: MAX_MEMO_LEN
+        })
+    );
+    // The limit is in bytes, not characters
+    let err = check_memo(&"é".repeat(MAX_MEMO_LEN / 2 + 1)).unwrap_err();
+    assert_eq!(err.code(), "memo_too_long");
+}
+
+#[test]
+fn memos_are_signed_by_the_depositor() {
+    let depositor = Pubkey::new_unique();
+    let instruction = memo_instruction("invoice 2024-0042", &depositor);
+    assert_eq!(instruction.program_id, spl_memo::id());
+    assert_eq!(instruction.data, b"invoice 2024-0042");
+    assert_eq!(instruction.accounts.len(), 1);
+    assert_eq!(instruction.accounts[0].pubkey, depositor);
+    assert!(instruction.accounts[0].is_signer);
+}
+
+#[test]
+fn events_carry_the_transactions_memo() {
+    let depositor = Pubkey::new_unique();
+    let transaction = fetched(&depositor, &["invoice 2024-0042"]);
+    assert_eq!(transaction_memos(&transaction), ["invoice 2024-0042"]);
+    let events = transaction_events(&token_vault::ID, &transaction);
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].memo(), Some("invoice 2024-0042"));
+
+    let transaction = fetched(&depositor, &["first", "second"]);
+    let events = transaction_events(&token_vault::ID, &transaction);
+    assert_eq!(events[0].memo(), Some("first\nsecond"));
+
+    let events = transaction_events(&token_vault::ID, &fetched(&depositor, &[]));
+    assert_eq!(events[0].memo(), None);
+}
+
+#[test]
+fn receipts_serialize_a_memo_only_when_sent() {
+    let receipt = TxReceipt::signature_only(Signature::new_unique());
+    let json = serde_json::to_value(&receipt).unwrap();
+    assert!(json.get("memo").is_none());
+
+    let receipt = TxReceipt {
+        memo: Some("invoice 2024-0042".to_string()),
+        ..receipt
+    };
+    let json = serde_json::to_value(&receipt).unwrap();
+    assert_eq!(json["memo"], "invoice 2024-0042");
+    assert_eq!(serde_json::from_value::<TxReceipt>(json).unwrap(), receipt);
+}
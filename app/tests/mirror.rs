@@ -0,0 +1,166 @@
+mod common;
+
+use anchor_client::solana_sdk::{pubkey::Pubkey, signature::Keypair};
+use anchor_client::Cluster;
+use token_vault_client::mirror::{config_fields, mirrored_config};
+use token_vault_client::token_vault::state::Vault;
+use token_vault_client::{
+    MirrorOptions, MirrorReport, MirrorStatus, MirroredField, TokenVaultClient,
+};
+
+fn vault() -> Vault {
+    Vault {
+        fee_percentage: 25,
+        withdrawal_timelock: 3_600,
+        withdrawal_limit: 5_000_000,
+        total_deposited: 123_456,
+        dust_threshold: 1_000,
+        waive_dust_fee: true,
+        ..common::vault()
+    }
+}
+
+fn field<'a>(
+    fields: &'a [(&'static str, String, Option<String>)],
+    name: &str,
+) -> &'a (&'static str, String, Option<String>) {
+    fields.iter().find(|(field, ..)| *field == name).unwrap()
+}
+
+#[test]
+fn mirrored_config_matches_its_source() {
+    let source = vault();
+    let config = mirrored_config(&source, 6, 6).unwrap();
+    for (name, mirrored, existing) in config_fields(&config, Some(&source)) {
+        assert_eq!(Some(mirrored), existing, "{}", name);
+    }
+}
+
+#[test]
+fn amounts_are_converted_through_whole_tokens() {
+    let source = vault();
+    let config = mirrored_config(&source, 6, 9).unwrap();
+    let fields = config_fields(&config, Some(&source));
+    assert_eq!(field(&fields, "withdrawal_limit").1, "5000000000");
+    assert_ne!(
+        Some(&field(&fields, "dust_policy").1),
+        field(&fields, "dust_policy").2.as_ref()
+    );
+
+    // Only what changes with the decimals differs
+    let differing: Vec<_> = fields
+        .iter()
+        .filter(|(_, mirrored, existing)| Some(mirrored) != existing.as_ref())
+        .map(|(name, ..)| *name)
+        .collect();
+    assert_eq!(differing, vec!["withdrawal_limit", "dust_policy"]);
+}
+
+#[test]
+fn unlimited_withdrawals_stay_unlimited() {
+    let source = Vault {
+        withdrawal_limit: u64::MAX,
+        dust_threshold: 0,
+        ..vault()
+    };
+    let config = mirrored_config(&source, 9, 2).unwrap();
+    let fields = config_fields(&config, None);
+    assert_eq!(field(&fields, "withdrawal_limit").1, u64::MAX.to_string());
+    assert_eq!(field(&fields, "withdrawal_limit").2, None);
+}
+
+#[test]
+fn amounts_too_precise_for_the_destination_are_refused() {
+    let source = Vault {
+        withdrawal_limit: 5_000_001,
+        ..vault()
+    };
+    let err = mirrored_config(&source, 6, 2).unwrap_err();
+    assert!(err.to_string().contains("withdrawal_limit"), "{}", err);
+}
+
+fn report() -> MirrorReport {
+    MirrorReport {
+        source_vault: Pubkey::new_unique(),
+        destination_vault: Pubkey::new_unique(),
+        name: "treasury".to_string(),
+        created: false,
+        dry_run: true,
+        fields: vec![
+            MirroredField {
+                field: "fee_percentage".to_string(),
+                source: "0.25%".to_string(),
+                destination: Some("0.25%".to_string()),
+                status: MirrorStatus::Skipped("already matches".to_string()),
+            },
+            MirroredField {
+                field: "withdrawal_limit".to_string(),
+                source: "5000000".to_string(),
+                destination: Some("1000".to_string()),
+                status: MirrorStatus::Failed("cannot change".to_string()),
+            },
+            MirroredField {
+                field: "allowlist".to_string(),
+                source: "3 wallets".to_string(),
+                destination: None,
+                status: MirrorStatus::Planned,
+            },
+        ],
+        receipts: Vec::new(),
+    }
+}
+
+#[test]
+fn report_shows_each_field_and_what_changes() {
+    let report = report();
+    assert!(report.has_failures());
+    let text = report.to_string();
+    assert!(text.contains("(existing)"), "{}", text);
+    assert!(text.contains("  fee_percentage: 0.25% [skipped: already matches]"));
+    assert!(text.contains("  withdrawal_limit: 1000 -> 5000000 [failed: cannot change]"));
+    assert!(text.contains("  allowlist: 3 wallets [planned]"));
+
+    let created = MirrorReport {
+        created: true,
+        fields: report.fields[2..].to_vec(),
+        ..report
+    };
+    assert!(!created.has_failures());
+    assert!(created.to_string().contains("(would create)"));
+}
+
+#[test]
+fn report_json_flattens_each_status() {
+    let report = report();
+    let json = serde_json::to_value(&report).unwrap();
+    assert_eq!(json["fields"][0]["status"], "skipped");
+    assert_eq!(json["fields"][0]["reason"], "already matches");
+    assert_eq!(json["fields"][2]["status"], "planned");
+    assert_eq!(json["fields"][2]["destination"], serde_json::Value::Null);
+    assert_eq!(
+        serde_json::from_value::<MirrorReport>(json).unwrap(),
+        report
+    );
+}
+
+#[test]
+fn mainnet_destinations_need_explicit_permission() {
+    let mut source = TokenVaultClient::new(
+        Cluster::Localnet,
+        Keypair::new(),
+        token_vault_client::token_vault::ID,
+    )
+    .unwrap();
+    #[allow(deprecated)]
+    source.with_vault(Pubkey::new_unique());
+    let mut destination = TokenVaultClient::new(
+        Cluster::Mainnet,
+        Keypair::new(),
+        token_vault_client::token_vault::ID,
+    )
+    .unwrap();
+    let err = source
+        .mirror_vault_config(&mut destination, &Keypair::new(), &MirrorOptions::default())
+        .unwrap_err();
+    assert!(err.to_string().contains("mainnet"), "{}", err);
+}
@@ -0,0 +1,78 @@
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use anchor_lang::{AccountSerialize, Discriminator};
+use token_vault_client::notification::hash_webhook_url;
+use token_vault_client::token_vault::state;
+use token_vault_client::NotificationConfig;
+
+fn full_config() -> NotificationConfig {
+    NotificationConfig::default()
+        .explorer("https://explorer.solana.com")
+        .support_contact("support@example.com")
+        .webhook_url("https://hooks.example.com/vault?token=secret")
+}
+
+#[test]
+fn only_the_hash_of_the_webhook_url_is_kept() {
+    let config = full_config();
+
+    assert!(config.is_webhook_url(" https://hooks.example.com/vault?token=secret\n"));
+    assert!(!config.is_webhook_url("https://hooks.example.com/other"));
+    let json = serde_json::to_string(&config).unwrap();
+    assert!(!json.contains("secret"));
+    assert!(json
+        .contains(&hash_webhook_url("https://hooks.example.com/vault?token=secret").to_string()));
+    assert_eq!(
+        serde_json::from_str::<NotificationConfig>(&json).unwrap(),
+        config
+    );
+}
+
+#[test]
+fn fields_are_validated_before_sending() {
+    assert!(full_config().validate().is_ok());
+    assert!(NotificationConfig::default()
+        .support_contact("@vault-team")
+        .validate()
+        .is_ok());
+
+    let too_long = "x".repeat(state::NotificationConfig::MAX_SUPPORT_CONTACT_LEN + 1);
+    let err = full_config()
+        .support_contact(too_long)
+        .validate()
+        .unwrap_err();
+    assert!(err.to_string().contains("at most 64"), "{}", err);
+
+    let err = full_config()
+        .explorer("explorer.solana.com")
+        .validate()
+        .unwrap_err();
+    assert!(err.to_string().contains("http"), "{}", err);
+
+    let err = full_config()
+        .support_contact("line\nbreak")
+        .validate()
+        .unwrap_err();
+    assert!(err.to_string().contains("control characters"), "{}", err);
+
+    let err = NotificationConfig::default().validate().unwrap_err();
+    assert!(err.to_string().contains("clear it"), "{}", err);
+}
+
+#[test]
+fn a_maximal_config_fits_its_account() {
+    let config = full_config();
+    let account = state::NotificationConfig {
+        vault: Pubkey::new_unique(),
+        webhook_url_hash: config.webhook_url_hash.map(|hash| hash.to_bytes()),
+        explorer: "e".repeat(state::NotificationConfig::MAX_EXPLORER_LEN),
+        support_contact: "s".repeat(state::NotificationConfig::MAX_SUPPORT_CONTACT_LEN),
+        bump: 255,
+    };
+    let mut data = Vec::new();
+    account.try_serialize(&mut data).unwrap();
+
+    assert_eq!(data.len(), state::NotificationConfig::LEN);
+    assert_eq!(data[..8], state::NotificationConfig::DISCRIMINATOR);
+    let decoded = NotificationConfig::of(&account);
+    assert_eq!(decoded.webhook_url_hash, config.webhook_url_hash);
+}
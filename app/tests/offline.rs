@@ -0,0 +1,54 @@
+use anchor_client::solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    message::Message,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    transaction::Transaction,
+};
+use anchor_client::Cluster;
+use token_vault_client::offline::{deserialize_base64, serialize_base64};
+use token_vault_client::{token_vault, TokenVaultClient};
+
+fn transfer_like(payer: &Pubkey, other: &Pubkey) -> Transaction {
+    let instruction = Instruction {
+        program_id: token_vault::ID,
+        accounts: vec![
+            AccountMeta::new(*payer, true),
+            AccountMeta::new(*other, true),
+        ],
+        data: vec![1, 2, 3],
+    };
+    Transaction::new_unsigned(Message::new(&[instruction], Some(payer)))
+}
+
+#[test]
+fn base64_round_trips_in_the_wire_format() {
+    let payer = Keypair::new();
+    let transaction = transfer_like(&payer.pubkey(), &Pubkey::new_unique());
+    let encoded = serialize_base64(&transaction).unwrap();
+    assert_eq!(
+        deserialize_base64(&format!("{}\n", encoded)).unwrap(),
+        transaction
+    );
+    assert!(deserialize_base64("not base64!").is_err());
+    assert!(deserialize_base64("AAAA").is_err());
+}
+
+#[test]
+fn partly_signed_transactions_are_not_sent() {
+    let client = TokenVaultClient::new(Cluster::Localnet, Keypair::new(), token_vault::ID).unwrap();
+    let payer = Keypair::new();
+    let cosigner = Keypair::new();
+    let mut transaction = transfer_like(&payer.pubkey(), &cosigner.pubkey());
+    transaction.partial_sign(&[&payer], Default::default());
+    let err = client.send_signed_transaction(&transaction).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Transaction is missing 1 of its 2 signatures"
+    );
+
+    // A signature over another message is caught before sending
+    transaction.partial_sign(&[&cosigner], Default::default());
+    transaction.message.instructions[0].data = vec![9];
+    assert!(client.send_signed_transaction(&transaction).is_err());
+}
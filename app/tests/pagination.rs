@@ -0,0 +1,155 @@
+mod common;
+
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use common::summary;
+use proptest::prelude::*;
+use std::collections::BTreeSet;
+use token_vault_client::fixtures::VaultFixture;
+use token_vault_client::pagination::{paginate, MAX_PAGE_LIMIT};
+use token_vault_client::{Cursor, Page, VaultSummary, VaultTransaction};
+
+#[test]
+fn cursors_round_trip_and_refuse_other_listings() {
+    let vaults = vec![summary("b"), summary("a"), summary("c")];
+    let page = paginate(vaults, None, 2, 7);
+    assert_eq!(page.context_slot, 7);
+    assert_eq!(
+        page.items
+            .iter()
+            .map(|v| v.name.as_str())
+            .collect::<Vec<_>>(),
+        ["a", "b"]
+    );
+    let cursor = page.next_cursor.clone().unwrap();
+
+    let encoded = cursor.to_string();
+    assert!(encoded
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+    assert_eq!(encoded.parse::<Cursor<VaultSummary>>().unwrap(), cursor);
+    let err = encoded.parse::<Cursor<Pubkey>>().unwrap_err();
+    assert!(err.to_string().contains("vaults listing"), "{}", err);
+    assert!("not a cursor!".parse::<Cursor<Pubkey>>().is_err());
+
+    // Pages pass through JSON with the cursor as its string
+    let json = serde_json::to_value(&page).unwrap();
+    assert_eq!(json["next_cursor"], encoded);
+    let decoded: Page<VaultSummary> = serde_json::from_value(json).unwrap();
+    assert_eq!(decoded, page);
+}
+
+#[test]
+fn the_last_page_has_no_cursor() {
+    let wallets: Vec<Pubkey> = (0..4).map(|_| Pubkey::new_unique()).collect();
+    let first = paginate(wallets.clone(), None, 2, 1);
+    let last = paginate(wallets.clone(), first.next_cursor.as_ref(), 2, 1);
+    assert!(!first.is_last());
+    assert!(last.is_last());
+    assert!(paginate(wallets, None, 4, 1).is_last());
+}
+
+#[test]
+fn history_cursors_carry_the_signature_to_continue_before() {
+    let transaction = VaultTransaction {
+        signature: Default::default(),
+        slot: 3,
+        block_time: None,
+        succeeded: true,
+        instructions: Vec::new(),
+        logs: Vec::new(),
+    };
+    let cursor = Cursor::after(&transaction);
+    assert_eq!(cursor.key(), &transaction.signature.to_string());
+    assert!(cursor.to_string().parse::<Cursor<VaultSummary>>().is_err());
+}
+
+/// A change to the collection between two pages
+#[derive(Debug, Clone)]
+enum Mutation {
+    Add(u8),
+    Remove(usize),
+}
+
+fn mutations() -> impl Strategy<Value = Vec<Mutation>> {
+    prop::collection::vec(
+        prop_oneof![
+            any::<u8>().prop_map(Mutation::Add),
+            any::<usize>().prop_map(Mutation::Remove),
+        ],
+        0..4,
+    )
+}
+
+proptest! {
+    #[test]
+    fn stable_items_are_listed_exactly_once_while_others_come_and_go(
+        stable in prop::collection::vec(any::<u8>(), 0..40),
+        churn in prop::collection::vec(mutations(), 0..20),
+        limit in 1usize..8,
+    ) {
+        let stable: Vec<VaultSummary> =
+            stable.iter().map(|n| summary(&format!("vault-{}", n))).collect();
+        let mut transient: Vec<VaultSummary> = Vec::new();
+        let mut listed = Vec::new();
+        let mut cursor = None;
+        let mut churn = churn.into_iter();
+
+        for _ in 0..=stable.len() + 20 * 4 {
+            let collection = stable.iter().chain(&transient).cloned().collect();
+            let page = paginate(collection, cursor.as_ref(), limit, 0);
+            prop_assert!(page.items.len() <= limit);
+            listed.extend(page.items.iter().map(|v| v.address));
+            cursor = page.next_cursor;
+            if cursor.is_none() {
+                break;
+            }
+            for mutation in churn.next().unwrap_or_default() {
+                match mutation {
+                    Mutation::Add(n) => transient.push(summary(&format!("vault-{}", n))),
+                    Mutation::Remove(i) if !transient.is_empty() => {
+                        transient.remove(i % transient.len());
+                    }
+                    Mutation::Remove(_) => {}
+                }
+            }
+        }
+        prop_assert!(cursor.is_none());
+
+        for vault in &stable {
+            prop_assert_eq!(listed.iter().filter(|a| **a == vault.address).count(), 1);
+        }
+        // Nothing is listed twice, stable or not
+        prop_assert_eq!(listed.iter().collect::<BTreeSet<_>>().len(), listed.len());
+    }
+}
+
+/// Needs a validator with the token vault program deployed; see
+/// `fixtures::VALIDATOR_ENV` and `fixtures::PROGRAM_ID_ENV`
+#[test]
+#[ignore = "needs a local validator; run with --ignored"]
+fn history_pages_walk_back_without_gaps() {
+    let fixture = VaultFixture::builder().build().unwrap();
+    for amount in [1_000, 2_000, 3_000] {
+        fixture.deposit_as(0, amount).unwrap();
+    }
+
+    let client = fixture.client();
+    let mut signatures = Vec::new();
+    let mut cursor = None;
+    loop {
+        let page = client.get_history_page(cursor.as_ref(), 2).unwrap();
+        assert!(page.items.iter().all(|tx| tx.slot <= page.context_slot));
+        signatures.extend(page.items.iter().map(|tx| tx.signature));
+        cursor = page.next_cursor;
+        if cursor.is_none() {
+            break;
+        }
+    }
+    // At least the vault's creation and three deposits, each once
+    assert!(signatures.len() >= 4);
+    assert_eq!(
+        signatures.iter().collect::<BTreeSet<_>>().len(),
+        signatures.len()
+    );
+    assert!(client.get_history_page(None, MAX_PAGE_LIMIT + 1).is_err());
+}
@@ -0,0 +1,116 @@
+mod common;
+
+use anchor_client::solana_sdk::signature::Signature;
+use anchor_lang::{InstructionData, ToAccountMetas};
+use common::key;
+use token_vault_client::changes::{merge_into_summary, vault_changes};
+use token_vault_client::pause::check_not_paused;
+use token_vault_client::token_vault::{accounts, instruction, state::Vault};
+use token_vault_client::{
+    AdminState, TokenVaultError, VaultChange, VaultInstruction, VaultInstructionKind, VaultSummary,
+    VaultTransaction,
+};
+
+fn vault(paused: bool) -> Vault {
+    Vault {
+        authority: key(1),
+        token_mint: key(2),
+        fee_collector: key(3),
+        fee_percentage: 25,
+        withdrawal_limit: 1_000_000,
+        total_deposited: 500,
+        paused,
+        ..common::vault()
+    }
+}
+
+/// A transaction in which the authority `key(1)` sent `data` to the vault
+/// `key(4)`
+fn transaction(data: Vec<u8>) -> VaultTransaction {
+    VaultTransaction {
+        signature: Signature::new_unique(),
+        slot: 7,
+        block_time: None,
+        succeeded: true,
+        instructions: vec![VaultInstruction {
+            kind: VaultInstructionKind::from_data(&data),
+            index: 0,
+            accounts: vec![key(1).to_string(), key(4).to_string()],
+            data,
+        }],
+        logs: Vec::new(),
+    }
+}
+
+#[test]
+fn paused_vaults_are_refused_before_sending() {
+    assert_eq!(check_not_paused(&vault(false)), Ok(()));
+    let err = check_not_paused(&vault(true)).unwrap_err();
+    assert_eq!(err, TokenVaultError::VaultPaused { authority: key(1) });
+    assert_eq!(err.code(), "vault_paused");
+    assert_eq!(
+        err.to_string(),
+        format!(
+            "Vault is paused; deposits and withdrawals resume once its authority {} unpauses it",
+            key(1)
+        )
+    );
+}
+
+#[test]
+fn only_the_authority_signs_and_the_vault_is_writable() {
+    for metas in [
+        accounts::PauseVault {
+            authority: key(1),
+            vault: key(4),
+        }
+        .to_account_metas(None),
+        accounts::UnpauseVault {
+            authority: key(1),
+            vault: key(4),
+        }
+        .to_account_metas(None),
+    ] {
+        assert!(metas[0].is_signer && !metas[0].is_writable);
+        assert!(!metas[1].is_signer && metas[1].is_writable);
+    }
+    assert_eq!(
+        instruction::PauseVault {}.data(),
+        [250, 6, 228, 57, 6, 104, 19, 210]
+    );
+    assert_eq!(
+        instruction::UnpauseVault {}.data(),
+        [125, 29, 213, 213, 114, 155, 125, 63]
+    );
+}
+
+#[test]
+fn summaries_follow_pauses_in_the_change_feed() {
+    let mut changes = vault_changes(&key(4), 0, &transaction(instruction::PauseVault {}.data()));
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0].change, VaultChange::Paused);
+
+    let mut summary = VaultSummary::of(key(4), &vault(false));
+    merge_into_summary(&mut summary, &changes);
+    assert!(summary.paused);
+    assert!(summary.to_string().ends_with(", paused)"));
+
+    changes.extend(vault_changes(
+        &key(4),
+        0,
+        &transaction(instruction::UnpauseVault {}.data()),
+    ));
+    let mut summary = VaultSummary::of(key(4), &vault(false));
+    merge_into_summary(&mut summary, &changes);
+    assert_eq!(summary, VaultSummary::of(key(4), &vault(false)));
+}
+
+#[test]
+fn receipts_record_the_pause_state() {
+    assert_eq!(AdminState::Paused { paused: true }.to_string(), "paused");
+    assert_eq!(
+        AdminState::Paused { paused: false }.to_string(),
+        "not paused"
+    );
+    assert!(VaultSummary::of(key(4), &vault(true)).paused);
+}
@@ -0,0 +1,196 @@
+mod common;
+
+use anchor_client::solana_sdk::{pubkey::Pubkey, signature::Signature};
+use anchor_lang::InstructionData;
+use token_vault_client::books::{books_report, BooksReplay};
+use token_vault_client::fixtures::VaultFixture;
+use token_vault_client::token_vault::{instruction, state::Vault};
+use token_vault_client::{
+    DustPolicy, LiquidityProfile, VaultInstruction, VaultInstructionKind, VaultSnapshot,
+    VaultTransaction, WithdrawalQuote,
+};
+
+/// Every combination of (fee in bps, timelock in seconds) a vault can
+/// start with, down to the plain escrow of (0, 0)
+const MATRIX: [(u16, i64); 4] = [(0, 0), (0, 3_600), (50, 0), (50, 3_600)];
+
+const DEPOSIT: u64 = 1_000_000;
+const WITHDRAWAL: u64 = 400_000;
+
+fn vault(fee_percentage: u16, withdrawal_timelock: i64) -> Vault {
+    Vault {
+        fee_percentage,
+        withdrawal_timelock,
+        ..common::vault()
+    }
+}
+
+fn transaction(
+    slot: u64,
+    vault: Pubkey,
+    signer: Pubkey,
+    kind: VaultInstructionKind,
+    data: Vec<u8>,
+) -> VaultTransaction {
+    VaultTransaction {
+        signature: Signature::new_unique(),
+        slot,
+        block_time: None,
+        succeeded: true,
+        instructions: vec![VaultInstruction {
+            kind,
+            index: 0,
+            accounts: vec![signer.to_string(), vault.to_string()],
+            data,
+        }],
+        logs: Vec::new(),
+    }
+}
+
+#[test]
+fn quotes_charge_nothing_without_a_fee() {
+    for (fee_bps, timelock) in MATRIX {
+        let quote = WithdrawalQuote::for_vault(&vault(fee_bps, timelock), WITHDRAWAL).unwrap();
+        let expected_fee = WITHDRAWAL * fee_bps as u64 / 10_000;
+        assert_eq!(quote.fee, expected_fee, "{:?}", (fee_bps, timelock));
+        assert_eq!(quote.net_amount, WITHDRAWAL - expected_fee);
+        assert!(!quote.fee_rounds_to_zero);
+        assert!(!quote.fee_waived);
+        assert_eq!(
+            quote.to_string(),
+            format!(
+                "withdraw {}: fee {}, receive {}",
+                WITHDRAWAL,
+                expected_fee,
+                WITHDRAWAL - expected_fee
+            )
+        );
+    }
+}
+
+#[test]
+fn books_balance_with_and_without_fees_and_timelocks() {
+    for (fee_bps, timelock) in MATRIX {
+        let address = Pubkey::new_unique();
+        let user = Pubkey::new_unique();
+        let mut vault = vault(fee_bps, timelock);
+        let history = [
+            transaction(
+                1,
+                address,
+                vault.authority,
+                VaultInstructionKind::InitializeVault,
+                instruction::InitializeVault {
+                    name: vault.name.clone(),
+                    fee_percentage: fee_bps,
+                    withdrawal_timelock: timelock,
+                    withdrawal_limit: u64::MAX,
+                    immutable: false,
+                    deposit_window_start: 0,
+                    deposit_window_end: 0,
+                    dust_threshold: 0,
+                    waive_dust_fee: false,
+                    reserve_bps: 0,
+                    reserve_timelock: 0,
+                    fee_collector: vault.fee_collector,
+                }
+                .data(),
+            ),
+            transaction(
+                2,
+                address,
+                user,
+                VaultInstructionKind::Deposit,
+                instruction::Deposit { amount: DEPOSIT }.data(),
+            ),
+            transaction(
+                3,
+                address,
+                user,
+                VaultInstructionKind::Withdraw,
+                instruction::Withdraw { amount: WITHDRAWAL }.data(),
+            ),
+        ];
+        let mut replay = BooksReplay::new(address);
+        for transaction in &history {
+            replay.apply(transaction, fee_bps, DustPolicy::DISABLED, 0);
+        }
+        let fee = WITHDRAWAL * fee_bps as u64 / 10_000;
+        assert_eq!(replay.fees_collected, fee);
+        assert_eq!(replay.positions[&user].fees_paid, fee);
+        assert_eq!(replay.total_deposited, (DEPOSIT - WITHDRAWAL) as i128);
+
+        vault.total_deposited = DEPOSIT - WITHDRAWAL;
+        vault.accrued_fees = fee;
+        let report = books_report(&replay, &vault, DEPOSIT - WITHDRAWAL + fee, 0);
+        assert!(report.is_consistent(), "{}", report);
+        assert!(report
+            .to_string()
+            .contains(&format!("fees collected: {}", fee)));
+    }
+}
+
+#[test]
+fn liquidity_states_a_missing_timelock_plainly() {
+    for (_, timelock) in MATRIX {
+        let profile = LiquidityProfile::of(&VaultSnapshot {
+            taken_at: 1_000,
+            vault: Pubkey::new_unique(),
+            mint: Pubkey::new_unique(),
+            decimals: 6,
+            vault_token_balance: DEPOSIT,
+            vault_token_frozen: false,
+            reserve_balance: 0,
+            reserve_frozen: false,
+            reserve_unlocks_at: 0,
+            withdrawal_limit: u64::MAX,
+            withdrawal_timelock: timelock,
+        });
+        let text = profile.to_string();
+        match timelock {
+            0 => assert!(text.ends_with("(no depositor timelock)"), "{}", text),
+            _ => assert!(
+                text.ends_with(&format!("(depositor timelock {} seconds)", timelock)),
+                "{}",
+                text
+            ),
+        }
+    }
+}
+
+/// Needs a validator with the token vault program deployed; see
+/// `fixtures::VALIDATOR_ENV` and `fixtures::PROGRAM_ID_ENV`
+#[test]
+#[ignore = "needs a local validator; run with --ignored"]
+fn deposit_and_withdraw_across_the_matrix() {
+    for (fee_bps, timelock) in MATRIX {
+        let fixture = VaultFixture::builder()
+            .fee_bps(fee_bps)
+            .timelock_secs(timelock as u64)
+            .build()
+            .unwrap();
+        // Fixtures never create the fee collector's token account, and
+        // withdrawals do not need it: fees accrue in the vault
+        fixture.deposit_as(0, DEPOSIT).unwrap();
+        let after_deposit = fixture.balance_of(0).unwrap().raw;
+        assert_eq!(after_deposit, fixture.initial_balance() - DEPOSIT);
+
+        let withdrawn = fixture.withdraw_as(0, WITHDRAWAL);
+        if timelock > 0 {
+            // Still inside the depositor's timelock
+            assert!(withdrawn.is_err(), "{:?}", (fee_bps, timelock));
+            continue;
+        }
+        withdrawn.unwrap();
+        let fee = WITHDRAWAL * fee_bps as u64 / 10_000;
+        assert_eq!(
+            fixture.balance_of(0).unwrap().raw,
+            after_deposit + WITHDRAWAL - fee
+        );
+
+        let info = fixture.client().get_vault_info().unwrap();
+        assert_eq!(info.total_deposited, DEPOSIT - WITHDRAWAL);
+        assert_eq!(info.accrued_fees, fee);
+        assert!(fixture.client().verify_books().unwrap().is_consistent());
+    }
+}
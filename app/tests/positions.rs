@@ -0,0 +1,49 @@
+mod common;
+
+use anchor_client::solana_sdk::{pubkey::Pubkey, signature::Keypair};
+use anchor_client::Cluster;
+use anchor_lang::{AccountDeserialize, AccountSerialize};
+use common::position;
+use token_vault_client::token_vault::state::DepositorPosition;
+use token_vault_client::{TokenVaultClient, TokenVaultError};
+
+#[test]
+fn filters_match_the_account_layout() {
+    let position = position(5_000, 1_200);
+    let mut data = Vec::new();
+    position.try_serialize(&mut data).unwrap();
+    assert_eq!(data.len(), DepositorPosition::LEN);
+
+    let vault = DepositorPosition::VAULT_OFFSET;
+    assert_eq!(data[vault..vault + 32], position.vault.to_bytes());
+    let depositor = DepositorPosition::DEPOSITOR_OFFSET;
+    assert_eq!(
+        data[depositor..depositor + 32],
+        position.depositor.to_bytes()
+    );
+    assert_eq!(
+        DepositorPosition::try_deserialize(&mut data.as_slice()).unwrap(),
+        position
+    );
+}
+
+#[test]
+fn net_deposited_never_underflows() {
+    assert_eq!(position(5_000, 1_200).net_deposited(), 3_800);
+    assert_eq!(position(1_000, 1_500).net_deposited(), 0);
+}
+
+#[test]
+fn positions_are_read_from_the_current_vault() {
+    let client =
+        TokenVaultClient::new(Cluster::Localnet, Keypair::new(), Pubkey::new_unique()).unwrap();
+    for err in [
+        client.get_position(Pubkey::new_unique()).unwrap_err(),
+        client.list_positions().unwrap_err(),
+    ] {
+        assert_eq!(
+            err.downcast_ref::<TokenVaultError>(),
+            Some(&TokenVaultError::VaultNotSet)
+        );
+    }
+}
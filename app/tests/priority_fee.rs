@@ -0,0 +1,86 @@
+mod common;
+
+use anchor_client::solana_sdk::{
+    compute_budget::{self, ComputeBudgetInstruction},
+    signature::Keypair,
+};
+use anchor_client::Cluster;
+use common::key;
+use token_vault_client::priority_fee::compute_budget_instructions;
+use token_vault_client::token_vault::{self, accounts, instruction};
+use token_vault_client::{PriorityFeeConfig, PriorityFeeStrategy, TokenVaultClient};
+
+fn client() -> TokenVaultClient {
+    TokenVaultClient::new(Cluster::Localnet, Keypair::new(), token_vault::ID).unwrap()
+}
+
+fn cancel_accounts() -> accounts::CancelWithdrawal {
+    accounts::CancelWithdrawal {
+        withdrawer: key(1),
+        vault: key(2),
+        withdrawal_request: key(3),
+    }
+}
+
+#[test]
+fn budget_instructions_come_before_the_program_instruction() {
+    let mut client = client();
+    client.with_priority_fee(PriorityFeeConfig::fixed(5_000).with_cu_limit(80_000));
+    let instructions = client
+        .request(&[key(2)])
+        .unwrap()
+        .accounts(cancel_accounts())
+        .args(instruction::CancelWithdrawal {})
+        .instructions()
+        .unwrap();
+
+    assert_eq!(instructions.len(), 3);
+    assert_eq!(
+        instructions[0],
+        ComputeBudgetInstruction::set_compute_unit_limit(80_000)
+    );
+    assert_eq!(
+        instructions[1],
+        ComputeBudgetInstruction::set_compute_unit_price(5_000)
+    );
+    assert_eq!(instructions[2].program_id, token_vault::ID);
+}
+
+#[test]
+fn without_a_priority_fee_transactions_are_unchanged() {
+    let instructions = client()
+        .request(&[key(2)])
+        .unwrap()
+        .accounts(cancel_accounts())
+        .args(instruction::CancelWithdrawal {})
+        .instructions()
+        .unwrap();
+    assert_eq!(instructions.len(), 1);
+    assert_eq!(instructions[0].program_id, token_vault::ID);
+}
+
+#[test]
+fn only_the_configured_parts_of_the_budget_are_set() {
+    assert!(compute_budget_instructions(None, 0).is_empty());
+    assert_eq!(
+        compute_budget_instructions(Some(200_000), 0),
+        vec![ComputeBudgetInstruction::set_compute_unit_limit(200_000)]
+    );
+    let price_only = compute_budget_instructions(None, 1);
+    assert_eq!(price_only.len(), 1);
+    assert_eq!(price_only[0].program_id, compute_budget::id());
+}
+
+#[test]
+fn auto_pays_the_capped_75th_percentile() {
+    let samples: Vec<u64> = (0..=100).collect();
+    let uncapped = PriorityFeeConfig::auto().strategy;
+    assert_eq!(uncapped.price(&samples), 75);
+    assert_eq!(uncapped.price(&[]), 0);
+
+    let capped = PriorityFeeStrategy::Auto {
+        max_micro_lamports_per_cu: Some(50),
+    };
+    assert_eq!(capped.price(&samples), 50);
+    assert_eq!(PriorityFeeStrategy::Fixed(9).price(&samples), 9);
+}
@@ -0,0 +1,95 @@
+mod common;
+
+use anchor_client::solana_sdk::{
+    commitment_config::{CommitmentConfig, CommitmentLevel},
+    pubkey::Pubkey,
+};
+use anyhow::Context;
+use token_vault_client::dust::WithdrawalQuote;
+use token_vault_client::provenance::precheck_commitment;
+use token_vault_client::token_vault::state::Vault;
+use token_vault_client::{CheckedAgainst, Provenance, TokenVaultError, VaultSummary};
+
+fn dusty_vault() -> Vault {
+    Vault {
+        fee_percentage: 100,
+        dust_threshold: 1_000,
+        ..common::vault()
+    }
+}
+
+#[test]
+fn strict_mode_raises_weak_reads_to_the_send_commitment() {
+    let processed = CommitmentConfig::processed();
+    let confirmed = CommitmentConfig::confirmed();
+    let finalized = CommitmentConfig::finalized();
+
+    assert_eq!(precheck_commitment(processed, confirmed, false), processed);
+    assert_eq!(precheck_commitment(processed, confirmed, true), confirmed);
+    assert_eq!(precheck_commitment(confirmed, finalized, true), finalized);
+    // Reads stronger than sends are never weakened
+    assert_eq!(precheck_commitment(finalized, confirmed, true), finalized);
+}
+
+#[test]
+fn provenance_orders_commitment_levels() {
+    let at = |commitment| Provenance {
+        slot: 42,
+        commitment,
+    };
+    assert!(!at(CommitmentLevel::Processed).is_at_least(CommitmentLevel::Confirmed));
+    assert!(at(CommitmentLevel::Confirmed).is_at_least(CommitmentLevel::Confirmed));
+    assert!(at(CommitmentLevel::Finalized).is_at_least(CommitmentLevel::Confirmed));
+    assert!(!at(CommitmentLevel::Confirmed).is_at_least(CommitmentLevel::Finalized));
+}
+
+#[test]
+fn pre_check_errors_carry_provenance_and_stay_typed() {
+    let provenance = Provenance {
+        slot: 250_000_123,
+        commitment: CommitmentLevel::Processed,
+    };
+    let err = WithdrawalQuote::for_vault(&dusty_vault(), 10)
+        .context(CheckedAgainst(provenance))
+        .unwrap_err();
+
+    assert_eq!(
+        err.downcast_ref::<TokenVaultError>(),
+        Some(&TokenVaultError::DustWithdrawal {
+            amount: 10,
+            threshold: 1_000
+        })
+    );
+    assert_eq!(
+        err.downcast_ref::<CheckedAgainst>(),
+        Some(&CheckedAgainst(provenance))
+    );
+    let message = format!("{:#}", err);
+    assert!(
+        message.contains("slot 250000123 (processed)"),
+        "{}",
+        message
+    );
+}
+
+#[test]
+fn summaries_serialize_provenance_only_when_known() {
+    let mut summary = VaultSummary::of(Pubkey::new_unique(), &dusty_vault());
+    let json = serde_json::to_string(&summary).unwrap();
+    assert!(!json.contains("provenance"));
+    assert_eq!(
+        serde_json::from_str::<VaultSummary>(&json).unwrap(),
+        summary
+    );
+
+    summary.provenance = Some(Provenance {
+        slot: 7,
+        commitment: CommitmentLevel::Finalized,
+    });
+    let json = serde_json::to_string(&summary).unwrap();
+    assert!(json.contains("\"finalized\""), "{}", json);
+    assert_eq!(
+        serde_json::from_str::<VaultSummary>(&json).unwrap(),
+        summary
+    );
+}
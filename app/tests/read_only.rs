@@ -0,0 +1,26 @@
+mod common;
+
+use anchor_client::Cluster;
+use common::key;
+use token_vault_client::token_vault::{self, pda::VaultAddresses};
+use token_vault_client::{ReadOnlyVaultClient, TokenVaultError};
+
+#[test]
+fn addresses_derive_without_a_keypair() {
+    let client = ReadOnlyVaultClient::new(Cluster::Localnet, token_vault::ID).unwrap();
+    assert_eq!(client.program_id(), token_vault::ID);
+    assert_eq!(
+        client.derive_vault_addresses(&key(1), &key(2), "treasury"),
+        VaultAddresses::derive(&token_vault::ID, &key(1), &key(2), "treasury")
+    );
+    assert!(client
+        .derive_vault_addresses(&key(1), &key(2), &"x".repeat(33))
+        .is_err());
+}
+
+#[test]
+fn single_vault_reads_need_a_vault() {
+    let client = ReadOnlyVaultClient::new(Cluster::Localnet, token_vault::ID).unwrap();
+    let err = client.get_vault_info().unwrap_err();
+    assert_eq!(TokenVaultError::of(&err), TokenVaultError::VaultNotSet);
+}
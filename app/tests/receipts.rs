@@ -0,0 +1,115 @@
+use anchor_client::solana_sdk::{
+    pubkey::Pubkey,
+    signature::{Keypair, Signature, Signer},
+};
+use token_vault_client::fixtures::VaultFixture;
+use token_vault_client::{
+    verify_action_receipt, ActionReceipt, AdminState, NotificationConfig, VaultChange,
+};
+
+fn notifications() -> NotificationConfig {
+    NotificationConfig::default()
+        .explorer("https://explorer.solana.com")
+        .support_contact("support@example.com")
+}
+
+fn receipt(authority: &Keypair) -> ActionReceipt {
+    ActionReceipt::sign(
+        authority,
+        token_vault_client::token_vault::ID,
+        Pubkey::new_unique(),
+        VaultChange::NotificationsSet {
+            config: notifications(),
+        },
+        4_200,
+        Signature::new_unique(),
+        AdminState::Notifications { config: None },
+        AdminState::Notifications {
+            config: Some(notifications()),
+        },
+    )
+    .unwrap()
+}
+
+#[test]
+fn receipts_verify_offline_after_a_json_round_trip() {
+    let authority = Keypair::new();
+    let receipt = receipt(&authority);
+
+    let decoded = ActionReceipt::from_json(&receipt.to_json().unwrap()).unwrap();
+    assert_eq!(decoded, receipt);
+    verify_action_receipt(&decoded, &authority.pubkey()).unwrap();
+}
+
+#[test]
+fn tampered_receipts_are_rejected() {
+    let authority = Keypair::new();
+    let receipt = receipt(&authority);
+
+    let mut later = receipt.clone();
+    later.slot += 1;
+    let mut other_action = receipt.clone();
+    other_action.action = VaultChange::NotificationsCleared;
+    let mut other_after = receipt.clone();
+    other_after.after = AdminState::Notifications { config: None };
+    let mut other_vault = receipt.clone();
+    other_vault.vault = Pubkey::new_unique();
+    for tampered in [later, other_action, other_after, other_vault] {
+        let err = verify_action_receipt(&tampered, &authority.pubkey()).unwrap_err();
+        assert!(err.to_string().contains("altered"), "{}", err);
+    }
+
+    // Edited in its stored form rather than in memory
+    let json = receipt
+        .to_json()
+        .unwrap()
+        .replace("support@example.com", "attacker@example.com");
+    let edited = ActionReceipt::from_json(&json).unwrap();
+    assert!(verify_action_receipt(&edited, &authority.pubkey()).is_err());
+}
+
+#[test]
+fn receipts_of_a_former_authority_verify_against_its_key() {
+    let former = Keypair::new();
+    let current = Keypair::new();
+    let receipt = receipt(&former);
+
+    verify_action_receipt(&receipt, &former.pubkey()).unwrap();
+    let err = verify_action_receipt(&receipt, &current.pubkey()).unwrap_err();
+    assert!(err.to_string().contains("issued by"), "{}", err);
+
+    // Claiming the current authority issued it breaks the signature
+    let mut reattributed = receipt;
+    reattributed.authority = current.pubkey();
+    let err = verify_action_receipt(&reattributed, &current.pubkey()).unwrap_err();
+    assert!(err.to_string().contains("altered"), "{}", err);
+}
+
+/// Needs a validator with the token vault program deployed; see
+/// `fixtures::VALIDATOR_ENV` and `fixtures::PROGRAM_ID_ENV`
+#[test]
+#[ignore = "needs a local validator; run with --ignored"]
+fn admin_actions_return_verifiable_receipts() {
+    let fixture = VaultFixture::builder().build().unwrap();
+    let client = fixture.client();
+    let authority = fixture.authority();
+
+    let set = client
+        .set_notification_config(authority, &notifications())
+        .unwrap();
+    assert_eq!(set.before, AdminState::Notifications { config: None });
+    assert_eq!(
+        set.after,
+        AdminState::Notifications {
+            config: Some(notifications())
+        }
+    );
+    let cleared = client.clear_notification_config(authority).unwrap();
+    assert_eq!(cleared.action, VaultChange::NotificationsCleared);
+    assert_eq!(cleared.before, set.after);
+    assert!(cleared.slot >= set.slot);
+
+    for receipt in [set, cleared] {
+        verify_action_receipt(&receipt, &authority.pubkey()).unwrap();
+    }
+}
@@ -0,0 +1,168 @@
+mod common;
+
+use anchor_client::solana_sdk::{pubkey::Pubkey, signature::Signature};
+use anchor_lang::InstructionData;
+use token_vault_client::books::{books_report, BooksReplay};
+use token_vault_client::reserve::check_release;
+use token_vault_client::token_vault::{instruction, state::Vault};
+use token_vault_client::{
+    DepositQuote, DustPolicy, ReservePolicy, TokenVaultError, VaultInstruction,
+    VaultInstructionKind, VaultTransaction,
+};
+
+const DAY: i64 = 86_400;
+
+fn reserve_vault(reserve_bps: u16) -> Vault {
+    Vault {
+        reserve_bps,
+        reserve_timelock: 7 * DAY,
+        last_reserve_release: 1_700_000_000,
+        ..common::vault()
+    }
+}
+
+#[test]
+fn deposit_quotes_show_the_carve_out_rounded_down() {
+    let vault = reserve_vault(50);
+    assert_eq!(
+        DepositQuote::for_vault(&vault, 1_000_000).unwrap(),
+        DepositQuote {
+            amount: 1_000_000,
+            reserve: 5_000,
+            credited: 995_000,
+        }
+    );
+    // 50 bps of 199 is 0.995, which the program rounds down
+    assert_eq!(DepositQuote::for_vault(&vault, 199).unwrap().reserve, 0);
+    assert_eq!(
+        DepositQuote::for_vault(&vault, 0),
+        Err(TokenVaultError::ZeroAmount {
+            operation: "deposit"
+        })
+    );
+    assert_eq!(
+        DepositQuote::for_vault(&vault, 1_000_000)
+            .unwrap()
+            .to_string(),
+        "deposit 1000000: reserve 5000, credit 995000"
+    );
+}
+
+#[test]
+fn reserve_policies_are_validated() {
+    assert!(ReservePolicy::new(10_000, 0).is_ok());
+    assert!(ReservePolicy::new(10_001, 0).is_err());
+    assert!(ReservePolicy::new(50, -1).is_err());
+    assert_eq!(ReservePolicy::NONE.to_string(), "none");
+    assert_eq!(
+        ReservePolicy::new(50, 7 * DAY).unwrap().to_string(),
+        "50 bps of deposits, released at most every 7d"
+    );
+}
+
+#[test]
+fn releases_wait_for_the_timelock_and_the_balance() {
+    let mut vault = reserve_vault(50);
+    vault.total_reserved = 5_000;
+    let unlocks_at = vault.last_reserve_release + vault.reserve_timelock;
+
+    assert_eq!(
+        check_release(&vault, 1_000, unlocks_at - 1),
+        Err(TokenVaultError::ReserveLocked { unlocks_at })
+    );
+    assert_eq!(check_release(&vault, 5_000, unlocks_at), Ok(()));
+    assert_eq!(
+        check_release(&vault, 5_001, unlocks_at),
+        Err(TokenVaultError::ReserveInsufficient {
+            requested: 5_001,
+            available: 5_000,
+        })
+    );
+}
+
+fn transaction(
+    slot: u64,
+    vault: Pubkey,
+    signer: Pubkey,
+    kind: VaultInstructionKind,
+    data: Vec<u8>,
+) -> VaultTransaction {
+    VaultTransaction {
+        signature: Signature::new_unique(),
+        slot,
+        block_time: None,
+        succeeded: true,
+        instructions: vec![VaultInstruction {
+            kind,
+            index: 0,
+            accounts: vec![signer.to_string(), vault.to_string()],
+            data,
+        }],
+        logs: Vec::new(),
+    }
+}
+
+#[test]
+fn books_balance_with_the_reserve_split_out() {
+    let address = Pubkey::new_unique();
+    let user = Pubkey::new_unique();
+    let mut vault = reserve_vault(50);
+    let history = [
+        transaction(
+            1,
+            address,
+            vault.authority,
+            VaultInstructionKind::InitializeVault,
+            instruction::InitializeVault {
+                name: vault.name.clone(),
+                fee_percentage: 0,
+                withdrawal_timelock: 0,
+                withdrawal_limit: u64::MAX,
+                immutable: false,
+                deposit_window_start: 0,
+                deposit_window_end: 0,
+                dust_threshold: 0,
+                waive_dust_fee: false,
+                reserve_bps: 50,
+                reserve_timelock: 7 * DAY,
+                fee_collector: vault.fee_collector,
+            }
+            .data(),
+        ),
+        transaction(
+            2,
+            address,
+            user,
+            VaultInstructionKind::Deposit,
+            instruction::Deposit { amount: 1_000_000 }.data(),
+        ),
+        transaction(
+            3,
+            address,
+            vault.authority,
+            VaultInstructionKind::ReleaseReserve,
+            instruction::ReleaseReserve { amount: 2_000 }.data(),
+        ),
+    ];
+    let mut replay = BooksReplay::new(address);
+    for transaction in &history {
+        replay.apply(transaction, 0, DustPolicy::DISABLED, vault.reserve_bps);
+    }
+    assert_eq!(replay.total_deposited, 995_000);
+    assert_eq!(replay.total_reserved, 3_000);
+    assert_eq!(replay.positions[&user].reserve_paid, 5_000);
+    assert_eq!(replay.positions[&user].balance(), 995_000);
+
+    vault.total_deposited = 995_000;
+    vault.total_reserved = 3_000;
+    assert!(books_report(&replay, &vault, 995_000, 3_000).is_consistent());
+
+    // Tokens missing from the reserve are reported even when the vault balances
+    let report = books_report(&replay, &vault, 995_000, 2_500);
+    let fields: Vec<_> = report
+        .discrepancies
+        .iter()
+        .map(|discrepancy| discrepancy.field.as_str())
+        .collect();
+    assert_eq!(fields, vec!["reserve_token_balance"]);
+}
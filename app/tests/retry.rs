@@ -0,0 +1,136 @@
+use anchor_client::solana_client::{client_error::ClientError, rpc_request::RpcError};
+use anchor_client::solana_sdk::{
+    instruction::InstructionError, signature::Signature, transaction::TransactionError,
+};
+use anyhow::anyhow;
+use std::io;
+use std::time::Duration;
+use token_vault_client::retry::is_rejection;
+use token_vault_client::{DeadlinePhase, RetryCondition, RetryPolicy, TokenVaultError, TxReceipt};
+
+fn client_error(err: impl Into<ClientError>) -> anyhow::Error {
+    anyhow::Error::from(err.into())
+}
+
+#[test]
+fn expired_blockhashes_and_unreachable_nodes_are_retryable() {
+    assert_eq!(
+        RetryCondition::of(&client_error(TransactionError::BlockhashNotFound)),
+        Some(RetryCondition::BlockhashExpired)
+    );
+    let unconfirmed = RpcError::ForUser(
+        "unable to confirm transaction. This can happen in situations such as transaction \
+         expiration and insufficient fee-payer funds"
+            .to_string(),
+    );
+    assert_eq!(
+        RetryCondition::of(&client_error(unconfirmed)),
+        Some(RetryCondition::BlockhashExpired)
+    );
+
+    let refused = client_error(io::Error::from(io::ErrorKind::ConnectionRefused));
+    assert_eq!(
+        RetryCondition::of(&refused),
+        Some(RetryCondition::TransientRpc)
+    );
+    // Also when anchor wraps the error, as it does while signing
+    let wrapped = anchor_client::ClientError::SolanaClientError(ClientError::from(
+        io::Error::from(io::ErrorKind::TimedOut),
+    ));
+    assert_eq!(
+        RetryCondition::of(&anyhow::Error::from(wrapped)),
+        Some(RetryCondition::TransientRpc)
+    );
+}
+
+#[test]
+fn program_errors_are_never_retried() {
+    let policy = RetryPolicy {
+        max_retries: 10,
+        ..RetryPolicy::default()
+    };
+    let rejected = client_error(TransactionError::InstructionError(
+        0,
+        InstructionError::Custom(6003),
+    ));
+    assert_eq!(RetryCondition::of(&rejected), None);
+    assert!(!policy.retries(&rejected, 1));
+
+    let checked = anyhow!(TokenVaultError::VaultNotSet);
+    assert!(!policy.retries(&checked, 1));
+}
+
+#[test]
+fn only_the_cluster_refusing_a_transaction_rejects_it() {
+    let failed = TransactionError::InstructionError(0, InstructionError::Custom(6003));
+    assert!(is_rejection(&client_error(failed.clone())));
+    assert!(is_rejection(&anyhow::Error::from(failed)));
+    // It landed after all
+    assert!(!is_rejection(&client_error(
+        TransactionError::AlreadyProcessed
+    )));
+
+    // A send that lost its connection or timed out may still land
+    let refused = client_error(io::Error::from(io::ErrorKind::ConnectionRefused));
+    assert!(!is_rejection(&refused));
+    let timed_out = anyhow!(TokenVaultError::DeadlineExceeded {
+        phase: DeadlinePhase::Confirm,
+        signature: None,
+    });
+    assert!(!is_rejection(&timed_out));
+}
+
+#[test]
+fn retries_stop_at_the_limit_and_only_for_chosen_conditions() {
+    let expired = client_error(TransactionError::BlockhashNotFound);
+    let policy = RetryPolicy {
+        max_retries: 2,
+        retry_on: vec![RetryCondition::BlockhashExpired],
+        ..RetryPolicy::default()
+    };
+    assert!(policy.retries(&expired, 1));
+    assert!(policy.retries(&expired, 2));
+    assert!(!policy.retries(&expired, 3));
+
+    let refused = client_error(io::Error::from(io::ErrorKind::ConnectionRefused));
+    assert!(!policy.retries(&refused, 1));
+}
+
+#[test]
+fn backoff_doubles_up_to_the_maximum() {
+    let policy = RetryPolicy {
+        backoff: Duration::from_millis(250),
+        max_backoff: Duration::from_secs(1),
+        ..RetryPolicy::default()
+    };
+    let pauses: Vec<_> = (1..=5)
+        .map(|attempt| policy.backoff_after(attempt))
+        .collect();
+    assert_eq!(
+        pauses,
+        [250, 500, 1_000, 1_000, 1_000].map(Duration::from_millis)
+    );
+    assert_eq!(policy.backoff_after(u32::MAX), Duration::from_secs(1));
+}
+
+#[test]
+fn receipts_report_resubmissions() {
+    let signature = Signature::new_unique();
+    let receipt = TxReceipt {
+        attempts: 3,
+        ..TxReceipt::signature_only(signature)
+    };
+    assert_eq!(
+        receipt.to_string(),
+        format!("{} after 3 attempts", signature)
+    );
+
+    // Receipts saved before attempts were counted took one
+    let json = serde_json::json!({
+        "signature": signature.to_string(),
+        "slot": null,
+        "fee_paid": null,
+    });
+    let old: TxReceipt = serde_json::from_value(json).unwrap();
+    assert_eq!(old, TxReceipt::signature_only(signature));
+}
@@ -0,0 +1,147 @@
+mod common;
+
+use common::key;
+use serde_json::Value;
+use token_vault_client::token_vault::events::{AccountingAnomaly, DepositEvent, WithdrawEvent};
+use token_vault_client::token_vault::state::{
+    MaintenanceSchedule, MaintenanceWindow, NotificationConfig, Vault, WithdrawalRequest,
+};
+
+fn vault() -> Vault {
+    Vault {
+        authority: key(1),
+        token_mint: key(2),
+        fee_collector: key(3),
+        fee_percentage: 25,
+        withdrawal_timelock: 86_400,
+        total_deposited: 9_007_199_254_740_993,
+        bump: 254,
+        deposit_window_end: 1_700_000_000,
+        dust_threshold: 1_000,
+        waive_dust_fee: true,
+        reserve_bps: 250,
+        reserve_timelock: 3_600,
+        last_reserve_release: 1_690_000_000,
+        total_reserved: 42,
+        paused: true,
+        accrued_fees: 7,
+        ..common::vault()
+    }
+}
+
+#[test]
+fn vaults_round_trip() {
+    let vault = vault();
+    let json = serde_json::to_string(&vault).unwrap();
+    assert_eq!(serde_json::from_str::<Vault>(&json).unwrap(), vault);
+
+    let pretty = vault.to_json_pretty().unwrap();
+    assert!(pretty.contains('\n'));
+    assert_eq!(serde_json::from_str::<Vault>(&pretty).unwrap(), vault);
+}
+
+#[test]
+fn keys_are_base58_and_amounts_strings() {
+    let value: Value = serde_json::to_value(vault()).unwrap();
+    assert_eq!(value["authority"], key(1).to_string());
+    // Beyond 2^53, where a JavaScript number would round it
+    assert_eq!(value["total_deposited"], "9007199254740993");
+    assert_eq!(value["withdrawal_limit"], u64::MAX.to_string());
+    assert_eq!(value["fee_percentage"], 25);
+    assert_eq!(value["paused"], true);
+}
+
+#[test]
+fn malformed_fields_are_refused() {
+    let mut value: Value = serde_json::to_value(vault()).unwrap();
+    value["total_deposited"] = Value::from(5);
+    assert!(serde_json::from_value::<Vault>(value.clone()).is_err());
+
+    value["total_deposited"] = Value::from("5");
+    value["authority"] = Value::from("not a key");
+    assert!(serde_json::from_value::<Vault>(value).is_err());
+}
+
+#[test]
+fn other_accounts_round_trip() {
+    let request = WithdrawalRequest {
+        vault: key(1),
+        withdrawer: key(2),
+        amount: 500,
+        requested_at: 1_700_000_000,
+        bump: 255,
+    };
+    let json = serde_json::to_string(&request).unwrap();
+    assert_eq!(
+        serde_json::from_str::<WithdrawalRequest>(&json).unwrap(),
+        request
+    );
+
+    let notifications = NotificationConfig {
+        vault: key(1),
+        webhook_url_hash: Some([9; 32]),
+        explorer: "https://explorer.solana.com".to_string(),
+        support_contact: "ops@example.com".to_string(),
+        bump: 253,
+    };
+    let json = serde_json::to_string(&notifications).unwrap();
+    assert_eq!(
+        serde_json::from_str::<NotificationConfig>(&json).unwrap(),
+        notifications
+    );
+
+    let schedule = MaintenanceSchedule {
+        vault: key(1),
+        windows: vec![MaintenanceWindow {
+            start_ts: 1_700_000_000,
+            end_ts: 1_700_003_600,
+            reason: "upgrade".to_string(),
+        }],
+        bump: 252,
+    };
+    let json = serde_json::to_string(&schedule).unwrap();
+    assert_eq!(
+        serde_json::from_str::<MaintenanceSchedule>(&json).unwrap(),
+        schedule
+    );
+}
+
+#[test]
+fn events_round_trip() {
+    let deposit = DepositEvent {
+        vault: key(1),
+        depositor: key(2),
+        amount: 1_500_000,
+    };
+    let json = serde_json::to_string(&deposit).unwrap();
+    assert!(json.contains("\"amount\":\"1500000\""), "{}", json);
+    assert_eq!(
+        serde_json::from_str::<DepositEvent>(&json).unwrap(),
+        deposit
+    );
+
+    let withdraw = WithdrawEvent {
+        vault: key(1),
+        withdrawer: key(2),
+        recipient: key(3),
+        amount: 1_000_000,
+        fee: 2_500,
+    };
+    let json = serde_json::to_string(&withdraw).unwrap();
+    assert_eq!(
+        serde_json::from_str::<WithdrawEvent>(&json).unwrap(),
+        withdraw
+    );
+
+    let anomaly = AccountingAnomaly {
+        vault: key(1),
+        counter: "total_deposited".to_string(),
+        recorded: 10,
+        amount: 20,
+    };
+    let json = serde_json::to_string(&anomaly).unwrap();
+    assert_eq!(
+        serde_json::from_str::<AccountingAnomaly>(&json).unwrap(),
+        anomaly
+    );
+}
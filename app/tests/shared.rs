@@ -0,0 +1,62 @@
+use anchor_client::solana_sdk::{pubkey::Pubkey, signature::Keypair};
+use anchor_client::Cluster;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+use token_vault_client::{
+    CacheConfig, FeeReservePool, ReadOnlyVaultClient, RpcCache, TokenVaultClient, TokenVaultError,
+    VaultCache,
+};
+
+fn assert_send_sync<T: Send + Sync>() {}
+
+fn client() -> TokenVaultClient {
+    TokenVaultClient::new(Cluster::Localnet, Keypair::new(), Pubkey::new_unique()).unwrap()
+}
+
+#[test]
+fn clients_can_be_shared_between_threads() {
+    assert_send_sync::<TokenVaultClient>();
+    assert_send_sync::<ReadOnlyVaultClient>();
+    assert_send_sync::<FeeReservePool>();
+    assert_send_sync::<RpcCache>();
+    assert_send_sync::<VaultCache>();
+}
+
+#[test]
+fn clones_share_caches_but_not_their_vault() {
+    let mut client = client();
+    client.with_vault_cache(CacheConfig {
+        ttl: Duration::from_secs(60),
+        enabled: true,
+    });
+    let vault = Pubkey::new_unique();
+    let mut other = client.clone();
+    #[allow(deprecated)]
+    other.with_vault(vault);
+    assert_eq!(other.refresh_vault().ok(), Some(()));
+    let err = client.refresh_vault().unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<TokenVaultError>(),
+        Some(&TokenVaultError::VaultNotSet)
+    );
+    assert!(other.vault_cache().config().enabled);
+    assert_eq!(other.write_rpc_url(), client.write_rpc_url());
+}
+
+#[test]
+fn deadlines_apply_to_the_thread_that_set_them() {
+    let client = Arc::new(client());
+    client
+        .within(Instant::now() + Duration::from_secs(30), |inner| {
+            assert!(inner.deadline().instant().is_some());
+            let elsewhere = {
+                let client = Arc::clone(&client);
+                thread::spawn(move || client.deadline().instant())
+            };
+            assert_eq!(elsewhere.join().unwrap(), None);
+            Ok(())
+        })
+        .unwrap();
+    assert!(client.deadline().instant().is_none());
+}
@@ -0,0 +1,105 @@
+use anchor_client::solana_sdk::{
+    pubkey::Pubkey,
+    signature::{Keypair, Signature},
+};
+use anchor_client::Cluster;
+use anyhow::anyhow;
+use token_vault_client::{
+    verify_action_receipt, ActionReceipt, AdminState, RemoteSigner, TokenVaultClient, VaultChange,
+    VaultSigner,
+};
+
+/// A remote signer backed by `keypair`, as a signing service would be
+fn remote(keypair: Keypair) -> RemoteSigner {
+    RemoteSigner::new(keypair.pubkey(), move |message| {
+        keypair.sign_message(message)
+    })
+}
+
+#[test]
+fn keypairs_sign_through_the_trait() {
+    let keypair = Keypair::new();
+    let signer: &dyn VaultSigner = &keypair;
+    let signature = signer.sign_message(b"message").unwrap();
+    assert_eq!(signer.pubkey(), keypair.pubkey());
+    assert!(signature.verify(keypair.pubkey().as_ref(), b"message"));
+    assert!(!signer.is_interactive());
+}
+
+#[test]
+fn remote_signers_return_the_callbacks_signature() {
+    let keypair = Keypair::new();
+    let expected = keypair.sign_message(b"message").unwrap();
+    let signer = remote(keypair);
+    assert_eq!(signer.sign_message(b"message").unwrap(), expected);
+}
+
+#[test]
+fn remote_signers_refuse_signatures_of_another_key() {
+    let other = Keypair::new();
+    let signer = RemoteSigner::new(Pubkey::new_unique(), move |message| {
+        other.sign_message(message)
+    });
+    let err = signer.sign_message(b"message").unwrap_err();
+    assert!(err.to_string().contains("is not"), "{}", err);
+}
+
+#[test]
+fn remote_signer_failures_reach_the_caller() {
+    let signer = RemoteSigner::new(Pubkey::new_unique(), |_| -> anyhow::Result<Signature> {
+        Err(anyhow!("device locked"))
+    });
+    let err = signer.sign_message(b"message").unwrap_err();
+    assert_eq!(err.to_string(), "device locked");
+}
+
+#[cfg(unix)]
+#[test]
+fn command_signers_report_a_failing_command() {
+    let signer = RemoteSigner::command(
+        Pubkey::new_unique(),
+        "sh",
+        &["-c", "cat > /dev/null; exit 3"],
+    );
+    let err = signer.sign_message(b"message").unwrap_err();
+    assert!(err.to_string().contains("exited with"), "{}", err);
+
+    let signer = RemoteSigner::command(
+        Pubkey::new_unique(),
+        "sh",
+        &["-c", "cat > /dev/null; echo nope"],
+    );
+    let err = signer.sign_message(b"message").unwrap_err();
+    assert!(err.to_string().contains("printed no signature"), "{}", err);
+}
+
+#[test]
+fn receipts_can_be_signed_remotely() {
+    let keypair = Keypair::new();
+    let authority = keypair.pubkey();
+    let receipt = ActionReceipt::sign(
+        &remote(keypair),
+        token_vault_client::token_vault::ID,
+        Pubkey::new_unique(),
+        VaultChange::NotificationsCleared,
+        4_200,
+        Signature::new_unique(),
+        AdminState::Notifications { config: None },
+        AdminState::Notifications { config: None },
+    )
+    .unwrap();
+    verify_action_receipt(&receipt, &authority).unwrap();
+}
+
+#[test]
+fn the_client_signs_attestations_remotely() {
+    let client =
+        TokenVaultClient::new(Cluster::Localnet, Keypair::new(), Pubkey::new_unique()).unwrap();
+    let keypair = Keypair::new();
+    let authority = keypair.pubkey();
+    let attestation = client
+        .sign_vault_attestation(&remote(keypair), Pubkey::new_unique(), 7, i64::MAX)
+        .unwrap();
+    assert_eq!(attestation.authority, authority);
+    assert!(attestation.verify_signature());
+}
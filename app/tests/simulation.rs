@@ -0,0 +1,62 @@
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use anchor_lang::{AnchorSerialize, Discriminator};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use token_vault_client::token_vault::events::WithdrawEvent;
+use token_vault_client::{Simulation, TokenVaultError};
+
+fn event_log(amount: u64, fee: u64) -> String {
+    let withdrawer = Pubkey::new_unique();
+    let event = WithdrawEvent {
+        vault: Pubkey::new_unique(),
+        withdrawer,
+        recipient: withdrawer,
+        amount,
+        fee,
+    };
+    let mut data = WithdrawEvent::DISCRIMINATOR.to_vec();
+    data.extend(event.try_to_vec().unwrap());
+    format!("Program data: {}", STANDARD.encode(data))
+}
+
+#[test]
+fn a_successful_withdraw_simulation_reports_its_fee() {
+    let simulation = Simulation {
+        units_consumed: Some(21_337),
+        logs: vec![
+            "Program Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS invoke [1]".to_string(),
+            "Program log: Instruction: Withdraw".to_string(),
+            event_log(1_000_000, 2_500),
+            "Program Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS success".to_string(),
+        ],
+        error: None,
+    };
+    assert!(simulation.succeeded());
+    assert_eq!(simulation.check(), Ok(()));
+    assert_eq!(simulation.fee_transferred(), 2_500);
+    assert_eq!(simulation.withdraw_events()[0].amount, 1_000_000);
+    assert_eq!(
+        simulation.to_string(),
+        "simulation succeeded, 21337 compute units"
+    );
+}
+
+#[test]
+fn a_failed_simulation_carries_the_typed_error() {
+    let error = TokenVaultError::ProgramError {
+        code: 6003,
+        name: Some("WithdrawalLimitExceeded".to_string()),
+        logs: Vec::new(),
+    };
+    let simulation = Simulation {
+        units_consumed: None,
+        logs: Vec::new(),
+        error: Some(error.clone()),
+    };
+    assert!(!simulation.succeeded());
+    assert_eq!(simulation.check(), Err(error.clone()));
+    assert_eq!(simulation.fee_transferred(), 0);
+    assert_eq!(
+        simulation.to_string(),
+        format!("simulation failed: {}", error)
+    );
+}
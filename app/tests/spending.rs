@@ -0,0 +1,204 @@
+use anchor_client::solana_sdk::{pubkey::Pubkey, signature::Signature};
+use anyhow::Result;
+use std::fs::{self, File};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, SystemTime};
+use token_vault_client::{
+    FileSpendingStore, MemorySpendingStore, Operation, OperationKind, OperationPolicy,
+    SpendingGuard, SpendingLimits, SpendingStore, TokenVaultError,
+};
+
+const T0: i64 = 1_700_000_000;
+const DAY: i64 = 86_400;
+
+fn withdrawal(signer: Pubkey, amount: u64) -> Operation {
+    Operation::new(OperationKind::Withdraw, signer, None, amount)
+}
+
+fn guard(store: impl SpendingStore + 'static, signer: Pubkey) -> SpendingGuard {
+    let mut guard = SpendingGuard::new(store);
+    guard.set_limits(
+        signer,
+        SpendingLimits {
+            per_transaction: Some(5_000),
+            ..SpendingLimits::daily(10_000)
+        },
+    );
+    guard
+}
+
+fn exceeded(result: Result<()>) -> (u64, i64) {
+    match result.expect_err("operation should be refused").downcast() {
+        Ok(TokenVaultError::SpendingLimitExceeded {
+            window_remaining,
+            resets_at,
+        }) => (window_remaining, resets_at),
+        other => panic!("unexpected error: {:?}", other),
+    }
+}
+
+/// Fresh state file path, removed along with its lock if left over
+fn state_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!(
+        "token-vault-spending-{}-{}.json",
+        name,
+        std::process::id()
+    ));
+    let _ = fs::remove_file(&path);
+    let _ = fs::remove_file(path.with_extension("lock"));
+    path
+}
+
+#[test]
+fn window_rolls_over() {
+    let signer = Pubkey::new_unique();
+    let guard = guard(MemorySpendingStore::default(), signer);
+
+    guard.reserve_at(&withdrawal(signer, 4_000), T0).unwrap();
+    guard
+        .reserve_at(&withdrawal(signer, 4_000), T0 + 60)
+        .unwrap();
+    assert_eq!(
+        exceeded(guard.reserve_at(&withdrawal(signer, 3_000), T0 + 120)),
+        (2_000, T0 + DAY)
+    );
+    assert_eq!(
+        guard.window_remaining_at(signer, T0 + 120).unwrap(),
+        Some(2_000)
+    );
+
+    // The first spend rolls out exactly one window after it was made
+    guard
+        .reserve_at(&withdrawal(signer, 3_000), T0 + DAY)
+        .unwrap();
+    assert_eq!(
+        guard.window_remaining_at(signer, T0 + DAY).unwrap(),
+        Some(3_000)
+    );
+}
+
+#[test]
+fn per_transaction_limit_applies_within_the_window() {
+    let signer = Pubkey::new_unique();
+    let guard = guard(MemorySpendingStore::default(), signer);
+    assert_eq!(
+        exceeded(guard.reserve_at(&withdrawal(signer, 5_001), T0)),
+        (10_000, T0)
+    );
+    guard.reserve_at(&withdrawal(signer, 5_000), T0).unwrap();
+}
+
+#[test]
+fn unconfigured_signers_and_zero_amounts_are_not_tracked() {
+    let signer = Pubkey::new_unique();
+    let guard = guard(MemorySpendingStore::default(), signer);
+    let other = Pubkey::new_unique();
+    guard.reserve_at(&withdrawal(other, u64::MAX), T0).unwrap();
+    assert_eq!(guard.window_remaining_at(other, T0).unwrap(), None);
+
+    let sweep = Operation::new(OperationKind::SweepLamports, signer, None, 0);
+    guard.reserve_at(&sweep, T0).unwrap();
+    assert_eq!(guard.window_remaining_at(signer, T0).unwrap(), Some(10_000));
+}
+
+#[test]
+fn failures_release_only_what_certainly_did_not_land() {
+    let signer = Pubkey::new_unique();
+    let guard = guard(MemorySpendingStore::default(), signer);
+
+    let rejected = withdrawal(signer, 5_000);
+    guard.before_sign(&rejected).unwrap();
+    guard.after_failure(&rejected, false);
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64;
+    assert_eq!(
+        guard.window_remaining_at(signer, now).unwrap(),
+        Some(10_000)
+    );
+
+    let timed_out = withdrawal(signer, 5_000);
+    guard.before_sign(&timed_out).unwrap();
+    guard.after_failure(&timed_out, true);
+    assert_eq!(guard.window_remaining_at(signer, now).unwrap(), Some(5_000));
+
+    let confirmed = withdrawal(signer, 5_000);
+    guard.before_sign(&confirmed).unwrap();
+    guard.after_confirm(&confirmed, &Signature::default());
+    assert_eq!(guard.window_remaining_at(signer, now).unwrap(), Some(0));
+}
+
+#[test]
+fn persisted_state_survives_a_crash() {
+    let signer = Pubkey::new_unique();
+    let path = state_path("crash");
+
+    let first = guard(FileSpendingStore::new(&path), signer);
+    let in_flight = withdrawal(signer, 5_000);
+    first.reserve_at(&in_flight, T0).unwrap();
+    first.reserve_at(&withdrawal(signer, 3_000), T0).unwrap();
+    // The process dies before learning the outcome, holding the lock
+    drop(first);
+    File::create(path.with_extension("lock"))
+        .unwrap()
+        .set_modified(SystemTime::now() - Duration::from_secs(120))
+        .unwrap();
+
+    let restarted = guard(FileSpendingStore::new(&path), signer);
+    assert_eq!(
+        exceeded(restarted.reserve_at(&withdrawal(signer, 2_001), T0 + 60)),
+        (2_000, T0 + DAY)
+    );
+    restarted.release(&in_flight).unwrap();
+    restarted
+        .reserve_at(&withdrawal(signer, 5_000), T0 + 60)
+        .unwrap();
+    assert!(!path.with_extension("lock").exists());
+
+    // A truncated ledger must not silently reset the window
+    fs::write(&path, "{\"entries\": [").unwrap();
+    assert!(restarted.reserve_at(&withdrawal(signer, 1), T0).is_err());
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn concurrent_operations_cannot_overdraw_the_window() {
+    let signer = Pubkey::new_unique();
+    let path = state_path("race");
+
+    // Separate stores on one file stand in for separate processes
+    let handles: Vec<_> = (0..16)
+        .map(|_| {
+            let path = path.clone();
+            thread::spawn(move || {
+                let guard = guard(FileSpendingStore::new(path), signer);
+                guard.reserve_at(&withdrawal(signer, 1_000), T0).is_ok()
+            })
+        })
+        .collect();
+    let approved = handles
+        .into_iter()
+        .map(|handle| handle.join().unwrap())
+        .filter(|approved| *approved)
+        .count();
+    assert_eq!(approved, 10);
+    fs::remove_file(&path).unwrap();
+
+    // And one guard shared between threads
+    let shared = Arc::new(guard(MemorySpendingStore::default(), signer));
+    let handles: Vec<_> = (0..16)
+        .map(|_| {
+            let guard = Arc::clone(&shared);
+            thread::spawn(move || guard.reserve_at(&withdrawal(signer, 3_000), T0).is_ok())
+        })
+        .collect();
+    let approved = handles
+        .into_iter()
+        .map(|handle| handle.join().unwrap())
+        .filter(|approved| *approved)
+        .count();
+    assert_eq!(approved, 3);
+}
@@ -0,0 +1,51 @@
+use anchor_client::solana_sdk::pubkey::Pubkey;
+use token_vault_client::StrandedAccount;
+
+const RENT_EXEMPT_MINIMUM: u64 = 2_039_280;
+
+#[test]
+fn rent_exempt_account_has_no_surplus() {
+    let account = StrandedAccount::new(
+        Pubkey::new_unique(),
+        RENT_EXEMPT_MINIMUM,
+        RENT_EXEMPT_MINIMUM,
+        0,
+    );
+    assert_eq!(account.surplus, 0);
+}
+
+#[test]
+fn extra_lamports_are_surplus_exactly() {
+    for extra in [1, 5_000, 1_000_000_000] {
+        let account = StrandedAccount::new(
+            Pubkey::new_unique(),
+            RENT_EXEMPT_MINIMUM + extra,
+            RENT_EXEMPT_MINIMUM,
+            0,
+        );
+        assert_eq!(account.surplus, extra);
+    }
+}
+
+#[test]
+fn underfunded_account_never_goes_below_rent_exemption() {
+    let account = StrandedAccount::new(
+        Pubkey::new_unique(),
+        RENT_EXEMPT_MINIMUM - 1,
+        RENT_EXEMPT_MINIMUM,
+        0,
+    );
+    assert_eq!(account.surplus, 0);
+}
+
+#[test]
+fn wrapped_sol_balance_is_not_swept() {
+    let wrapped = 3_000_000;
+    let account = StrandedAccount::new(
+        Pubkey::new_unique(),
+        RENT_EXEMPT_MINIMUM + wrapped + 42,
+        RENT_EXEMPT_MINIMUM,
+        wrapped,
+    );
+    assert_eq!(account.surplus, 42);
+}
@@ -0,0 +1,138 @@
+use anchor_client::solana_sdk::signature::{Keypair, Signature};
+use anyhow::{anyhow, Result};
+use futures::executor::block_on;
+use futures::StreamExt;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::thread;
+use token_vault_client::stream::{operation_sink, OperationExecutor, OperationOutcome};
+use token_vault_client::{BatchOutcome, VaultOperation, VaultSigner};
+
+const PRODUCERS: u64 = 4;
+const OPERATIONS_PER_PRODUCER: u64 = 250;
+
+/// A single vault's balance, updated in place; withdrawals beyond the
+/// balance fail like the program's would
+#[derive(Default)]
+struct FakeChain {
+    balance: RefCell<u64>,
+    executed: RefCell<Vec<VaultOperation>>,
+}
+
+impl OperationExecutor for FakeChain {
+    fn execute(&self, _signer: &dyn VaultSigner, operation: &VaultOperation) -> Result<Signature> {
+        let mut balance = self.balance.borrow_mut();
+        match *operation {
+            VaultOperation::Deposit { amount, .. } => *balance += amount,
+            VaultOperation::Withdraw { amount, .. } => {
+                *balance = balance
+                    .checked_sub(amount)
+                    .ok_or_else(|| anyhow!("insufficient funds"))?
+            }
+        }
+        self.executed.borrow_mut().push(*operation);
+        Ok(Signature::new_unique())
+    }
+}
+
+fn deposit(amount: u64) -> VaultOperation {
+    VaultOperation::Deposit {
+        vault: None,
+        amount,
+        mint: None,
+    }
+}
+
+#[test]
+fn every_operation_has_exactly_one_outcome_under_load() {
+    let chain = FakeChain::default();
+    let signer = Keypair::new();
+    let (sender, outcomes) = operation_sink(&chain, &signer, 8);
+
+    let producers: Vec<_> = (0..PRODUCERS)
+        .map(|producer| {
+            let mut sender = sender.clone();
+            thread::spawn(move || {
+                block_on(async {
+                    for n in 0..OPERATIONS_PER_PRODUCER {
+                        let id = format!("{}-{}", producer, n);
+                        sender.send(id.clone(), deposit(1)).await?;
+                        // A retry after an unclear failure must not deposit twice
+                        if n % 10 == 0 {
+                            sender.send(id, deposit(1)).await?;
+                        }
+                    }
+                    anyhow::Ok(())
+                })
+            })
+        })
+        .collect();
+    // The stream ends once the producers' clones are dropped too
+    drop(sender);
+
+    let outcomes: Vec<OperationOutcome> = block_on(outcomes.collect());
+    for producer in producers {
+        producer.join().unwrap().unwrap();
+    }
+
+    let total = PRODUCERS * OPERATIONS_PER_PRODUCER;
+    let mut by_id: BTreeMap<&str, Vec<&BatchOutcome>> = BTreeMap::new();
+    for outcome in &outcomes {
+        by_id.entry(&outcome.id).or_default().push(&outcome.outcome);
+    }
+    assert_eq!(by_id.len() as u64, total);
+    for (id, results) in &by_id {
+        assert!(
+            matches!(results[0], BatchOutcome::Executed { .. }),
+            "{}: {:?}",
+            id,
+            results
+        );
+        assert!(results[1..]
+            .iter()
+            .all(|result| matches!(result, BatchOutcome::Skipped { .. })));
+    }
+    assert_eq!(chain.executed.borrow().len() as u64, total);
+    assert_eq!(*chain.balance.borrow(), total);
+}
+
+#[test]
+fn failures_are_reported_in_order_and_do_not_stop_the_stream() {
+    let chain = FakeChain::default();
+    let signer = Keypair::new();
+    let (mut sender, outcomes) = operation_sink(&chain, &signer, 4);
+
+    let producer = thread::spawn(move || {
+        block_on(async {
+            sender.send("fund", deposit(100)).await?;
+            let overdraw = VaultOperation::Withdraw {
+                vault: None,
+                amount: 500,
+                mint: None,
+                to: None,
+            };
+            sender.send("overdraw", overdraw).await?;
+            sender.send("top-up", deposit(1)).await
+        })
+    });
+    let outcomes: Vec<_> = block_on(outcomes.collect());
+    producer.join().unwrap().unwrap();
+
+    let ids: Vec<_> = outcomes.iter().map(|outcome| outcome.id.as_str()).collect();
+    assert_eq!(ids, ["fund", "overdraw", "top-up"]);
+    assert!(matches!(
+        &outcomes[1].outcome,
+        BatchOutcome::Failed { error } if error.message.contains("insufficient funds")
+    ));
+    assert_eq!(*chain.balance.borrow(), 101);
+}
+
+#[test]
+fn sending_after_the_stream_is_dropped_fails() {
+    let chain = FakeChain::default();
+    let signer = Keypair::new();
+    let (mut sender, outcomes) = operation_sink(&chain, &signer, 1);
+    drop(outcomes);
+
+    assert!(block_on(sender.send("late", deposit(1))).is_err());
+}
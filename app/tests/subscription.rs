@@ -0,0 +1,48 @@
+mod common;
+
+use anchor_client::solana_sdk::signature::Keypair;
+use anchor_client::Cluster;
+use common::key;
+use token_vault_client::{
+    token_vault, ClientConfig, DepositEvent, TokenVaultClient, VaultEvent, WithdrawEvent,
+};
+
+#[test]
+fn events_name_their_vault() {
+    let deposit = VaultEvent::Deposit(DepositEvent {
+        vault: key(1),
+        depositor: key(2),
+        amount: 5,
+        timestamp: None,
+        memo: None,
+    });
+    let withdraw = VaultEvent::Withdraw(WithdrawEvent {
+        vault: key(3),
+        withdrawer: key(2),
+        recipient: key(2),
+        amount: 5,
+        fee: 0,
+        timestamp: None,
+        memo: None,
+    });
+    assert_eq!(deposit.vault(), key(1));
+    assert_eq!(withdraw.vault(), key(3));
+}
+
+#[test]
+fn an_unreachable_endpoint_fails_the_first_subscribe() {
+    let config = ClientConfig {
+        rpc_url: Some("http://127.0.0.1:9".to_string()),
+        ws_url: Some("ws://127.0.0.1:9".to_string()),
+        ..ClientConfig::default()
+    };
+    let client = TokenVaultClient::new_with_config(
+        Cluster::Localnet,
+        Keypair::new(),
+        token_vault::ID,
+        config,
+    )
+    .unwrap();
+    assert!(client.subscribe_vault_events(key(1)).is_err());
+    assert!(client.subscribe_vault_state(key(1)).is_err());
+}
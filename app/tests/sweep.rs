@@ -0,0 +1,151 @@
+mod common;
+
+use anchor_client::solana_sdk::{pubkey::Pubkey, signature::Keypair};
+use anchor_client::Cluster;
+use common::position;
+use token_vault_client::accounting::check_total_deposited;
+use token_vault_client::dust::{check_withdrawal_limit, WithdrawalQuote};
+use token_vault_client::fixtures::VaultFixture;
+use token_vault_client::sweep::max_withdrawal;
+use token_vault_client::token_vault::state::Vault;
+use token_vault_client::{TokenVaultClient, TokenVaultError, UpdateVaultParams};
+
+fn vault(fee_percentage: u16, withdrawal_limit: u64, total_deposited: u64) -> Vault {
+    Vault {
+        fee_percentage,
+        withdrawal_limit,
+        total_deposited,
+        ..common::vault()
+    }
+}
+
+#[test]
+fn the_smallest_cap_bounds_a_withdrawal() {
+    let all = position(u64::MAX, 0);
+    assert_eq!(
+        max_withdrawal(&vault(0, 500, 1_000), 2_000, Some(&all)),
+        500
+    );
+    assert_eq!(
+        max_withdrawal(&vault(0, u64::MAX, 1_000), 2_000, Some(&all)),
+        1_000
+    );
+    assert_eq!(
+        max_withdrawal(&vault(0, u64::MAX, u64::MAX), 2_000, Some(&all)),
+        2_000
+    );
+}
+
+#[test]
+fn accrued_fees_stay_in_the_vault() {
+    let mut vault = vault(100, u64::MAX, u64::MAX);
+    vault.accrued_fees = 300;
+    let all = position(u64::MAX, 0);
+    assert_eq!(max_withdrawal(&vault, 1_000, Some(&all)), 700);
+    assert_eq!(max_withdrawal(&vault, 200, Some(&all)), 0);
+}
+
+#[test]
+fn withdrawals_stop_at_the_callers_position() {
+    // Two depositors share 10_000; each may take back only their own
+    let vault = vault(0, u64::MAX, 10_000);
+    assert_eq!(
+        max_withdrawal(&vault, 10_000, Some(&position(6_000, 1_000))),
+        5_000
+    );
+    assert_eq!(
+        max_withdrawal(&vault, 10_000, Some(&position(5_000, 0))),
+        5_000
+    );
+    // A wallet that never deposited has nothing to take
+    assert_eq!(max_withdrawal(&vault, 10_000, None), 0);
+}
+
+#[test]
+fn the_fee_never_pushes_a_withdrawal_past_the_limit() {
+    // Rates and limits where grossing up a net amount would round over
+    for fee_percentage in [1, 3, 33, 333, 999, 3_333] {
+        for withdrawal_limit in [1, 7, 101, 9_999, 1_000_003] {
+            let vault = vault(fee_percentage, withdrawal_limit, u64::MAX);
+            let amount = max_withdrawal(&vault, u64::MAX, Some(&position(u64::MAX, 0)));
+            assert_eq!(amount, withdrawal_limit);
+            assert_eq!(check_withdrawal_limit(&vault, amount), Ok(()));
+            assert_eq!(check_total_deposited(&vault, amount), Ok(()));
+            let quote = WithdrawalQuote::for_vault(&vault, amount).unwrap();
+            assert_eq!(quote.net_amount + quote.fee, amount);
+        }
+    }
+}
+
+#[test]
+fn rejected_dust_is_never_offered() {
+    let vault = Vault {
+        dust_threshold: 1_000,
+        ..vault(100, u64::MAX, u64::MAX)
+    };
+    let all = position(u64::MAX, 0);
+    // All that is left is dust the vault refuses
+    assert_eq!(max_withdrawal(&vault, 999, Some(&all)), 0);
+    assert_eq!(max_withdrawal(&vault, 1_000, Some(&all)), 1_000);
+    let waived = Vault {
+        waive_dust_fee: true,
+        ..vault
+    };
+    assert_eq!(max_withdrawal(&waived, 999, Some(&all)), 999);
+}
+
+/// Needs a validator with the token vault program deployed; see
+/// `fixtures::VALIDATOR_ENV` and `fixtures::PROGRAM_ID_ENV`
+#[test]
+#[ignore = "needs a local validator; run with --ignored"]
+fn sweeps_leave_accrued_fees_and_stop_at_the_limit() {
+    let fixture = VaultFixture::builder()
+        .fee_bps(100)
+        .users(2)
+        .build()
+        .unwrap();
+    let client = fixture.client();
+    client
+        .update_vault_config(
+            fixture.authority(),
+            UpdateVaultParams {
+                withdrawal_limit: Some(300_000),
+                ..UpdateVaultParams::default()
+            },
+        )
+        .unwrap();
+    fixture.deposit_as(0, 1_000_000).unwrap();
+    fixture.deposit_as(1, 500_000).unwrap();
+    // Accrues a fee of 1_000
+    fixture.withdraw_as(1, 100_000).unwrap();
+
+    let user = &fixture.user(0).unwrap().keypair;
+    let swept = client.withdraw_all(user).unwrap();
+    assert_eq!(swept.amount, 300_000);
+    assert!(!swept.is_empty());
+    let info = client.get_vault_info().unwrap();
+    assert_eq!(info.accrued_fees, 4_000);
+    assert_eq!(info.total_deposited, 1_100_000);
+
+    // The other depositor has 400_000 left, but still one limit at a time
+    let other = &fixture.user(1).unwrap().keypair;
+    assert_eq!(client.withdraw_all(other).unwrap().amount, 300_000);
+    assert_eq!(client.withdraw_all(other).unwrap().amount, 100_000);
+    assert!(client.withdraw_all(other).unwrap().is_empty());
+}
+
+#[test]
+fn sweeps_need_a_vault() {
+    let client =
+        TokenVaultClient::new(Cluster::Localnet, Keypair::new(), Pubkey::new_unique()).unwrap();
+    let depositor = Keypair::new();
+    for err in [
+        client.withdraw_all(&depositor).unwrap_err(),
+        client.deposit_all(&depositor, 0).unwrap_err(),
+    ] {
+        assert_eq!(
+            err.downcast_ref::<TokenVaultError>(),
+            Some(&TokenVaultError::VaultNotSet)
+        );
+    }
+}
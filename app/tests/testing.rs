@@ -0,0 +1,165 @@
+use anchor_client::solana_sdk::{pubkey::Pubkey, signature::Keypair, signer::Signer};
+use std::time::Duration;
+use token_vault_client::testing::{MockCall, MockVaultClient, MOCK_TRANSACTION_FEE};
+use token_vault_client::{TokenVaultError, VaultConfig, VaultEvent, VaultOps};
+
+/// A mock with one vault set: 1% fee, a 1_000_000 limit and no timelock
+fn mock_with_vault() -> (MockVaultClient, Pubkey) {
+    let mint = Pubkey::new_unique();
+    let mut mock = MockVaultClient::new();
+    mock.add_mint(mint, 6);
+    let config = VaultConfig::builder()
+        .name("treasury")
+        .fee_bps(100)
+        .timelock(Duration::ZERO)
+        .withdrawal_limit(1_000_000)
+        .build()
+        .unwrap();
+    let vault = mock
+        .initialize_vault(&Keypair::new(), mint, &config)
+        .unwrap()
+        .address;
+    mock.with_vault(vault);
+    mock.clear_calls();
+    (mock, vault)
+}
+
+fn vault_error(err: anyhow::Error) -> TokenVaultError {
+    err.downcast::<TokenVaultError>().unwrap()
+}
+
+#[test]
+fn deposits_and_withdrawals_follow_the_programs_arithmetic() {
+    let (mock, vault) = mock_with_vault();
+    mock.update_vault(vault, |vault| vault.reserve_bps = 1_000)
+        .unwrap();
+    let user = Keypair::new();
+
+    let receipt = mock.deposit(&user, 500_000).unwrap();
+    assert_eq!(receipt.fee_paid, Some(MOCK_TRANSACTION_FEE));
+    let info = mock.get_vault_info().unwrap();
+    assert_eq!(info.total_deposited, 450_000);
+    assert_eq!(info.total_reserved, 50_000);
+
+    let receipt = mock.withdraw(&user, 200_000, None).unwrap();
+    match &receipt.events.unwrap()[0] {
+        VaultEvent::Withdraw(event) => {
+            assert_eq!(event.fee, 2_000);
+            assert_eq!(event.recipient, user.pubkey());
+        }
+        other => panic!("unexpected event {:?}", other),
+    }
+    let info = mock.get_vault_info().unwrap();
+    assert_eq!(info.total_deposited, 250_000);
+    assert_eq!(info.accrued_fees, 2_000);
+
+    // More than was deposited clamps to zero and flags the vault
+    mock.withdraw(&user, 300_000, None).unwrap();
+    let info = mock.get_vault_info().unwrap();
+    assert_eq!(info.total_deposited, 0);
+    assert!(info.accounting_anomaly);
+}
+
+#[test]
+fn every_call_is_recorded_even_when_refused() {
+    let (mock, _) = mock_with_vault();
+    let user = Keypair::new();
+    mock.quote_deposit(10).unwrap();
+    mock.deposit(&user, 0).unwrap_err();
+    mock.withdraw(&user, 10, Some(Pubkey::default())).unwrap();
+    assert_eq!(
+        mock.calls(),
+        [
+            MockCall::QuoteDeposit { amount: 10 },
+            MockCall::Deposit {
+                depositor: user.pubkey(),
+                amount: 0
+            },
+            MockCall::Withdraw {
+                withdrawer: user.pubkey(),
+                amount: 10,
+                recipient: Some(Pubkey::default())
+            },
+        ]
+    );
+}
+
+#[test]
+fn the_mock_refuses_what_the_client_would() {
+    let (mut mock, vault) = mock_with_vault();
+    let user = Keypair::new();
+
+    assert!(matches!(
+        vault_error(mock.deposit(&user, 0).unwrap_err()),
+        TokenVaultError::ZeroAmount { .. }
+    ));
+    assert_eq!(
+        vault_error(mock.withdraw(&user, 1_000_001, None).unwrap_err()),
+        TokenVaultError::WithdrawalLimitExceeded {
+            requested: 1_000_001,
+            remaining: 1_000_000
+        }
+    );
+
+    mock.update_vault(vault, |vault| vault.paused = true)
+        .unwrap();
+    assert!(matches!(
+        vault_error(mock.withdraw(&user, 10, None).unwrap_err()),
+        TokenVaultError::VaultPaused { .. }
+    ));
+
+    mock.update_vault(vault, |vault| {
+        vault.paused = false;
+        vault.deposit_window_start = 1_000;
+        vault.deposit_window_end = 2_000;
+    })
+    .unwrap();
+    mock.set_time(2_500);
+    assert!(matches!(
+        vault_error(mock.deposit(&user, 10).unwrap_err()),
+        TokenVaultError::DepositWindowClosed { .. }
+    ));
+    mock.set_time(1_500);
+    mock.deposit(&user, 10).unwrap();
+
+    mock.with_vault(Pubkey::new_unique());
+    assert!(matches!(
+        vault_error(mock.get_vault_info().unwrap_err()),
+        TokenVaultError::VaultNotFound { .. }
+    ));
+    let unset = MockVaultClient::new();
+    assert!(matches!(
+        vault_error(unset.deposit(&user, 10).unwrap_err()),
+        TokenVaultError::VaultNotSet
+    ));
+}
+
+#[test]
+fn vaults_need_a_known_mint_and_a_free_address() {
+    let mint = Pubkey::new_unique();
+    let mock = MockVaultClient::new();
+    let authority = Keypair::new();
+    let config = VaultConfig::builder()
+        .name("treasury")
+        .fee_bps(0)
+        .timelock(Duration::ZERO)
+        .withdrawal_limit(100)
+        .build()
+        .unwrap();
+
+    let err = mock
+        .initialize_vault(&authority, mint, &config)
+        .unwrap_err();
+    assert!(err.to_string().contains("Failed to fetch mint"), "{}", err);
+
+    mock.add_mint(mint, 6);
+    let vault = mock
+        .initialize_vault(&authority, mint, &config)
+        .unwrap()
+        .address;
+    assert_eq!(mock.vault(vault).unwrap().authority, authority.pubkey());
+    let err = mock
+        .initialize_vault(&authority, mint, &config)
+        .unwrap_err();
+    assert!(err.to_string().contains("already exists"), "{}", err);
+}
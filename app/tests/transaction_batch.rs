@@ -0,0 +1,107 @@
+use anchor_client::solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    packet::PACKET_DATA_SIZE,
+    pubkey::Pubkey,
+    signature::Keypair,
+    signer::Signer,
+};
+use anchor_client::Cluster;
+use token_vault_client::json::ErrorBody;
+use token_vault_client::memo::MAX_MEMO_LEN;
+use token_vault_client::transaction_batch::check_transaction_size;
+use token_vault_client::{TokenVaultClient, TokenVaultError};
+
+fn client() -> TokenVaultClient {
+    TokenVaultClient::new(Cluster::Localnet, Keypair::new(), Pubkey::new_unique()).unwrap()
+}
+
+#[test]
+fn oversized_transactions_report_the_overage() {
+    check_transaction_size(PACKET_DATA_SIZE).unwrap();
+    let err = check_transaction_size(PACKET_DATA_SIZE + 40).unwrap_err();
+    assert_eq!(
+        err,
+        TokenVaultError::TransactionTooLarge {
+            size: PACKET_DATA_SIZE + 40,
+            max: PACKET_DATA_SIZE
+        }
+    );
+    assert_eq!(err.code(), "transaction_too_large");
+    assert!(err.to_string().contains("is 40 over the 1232"), "{}", err);
+    let body = ErrorBody::from(&err);
+    assert_eq!(body.details["size"], PACKET_DATA_SIZE + 40);
+    assert_eq!(body.details["max"], PACKET_DATA_SIZE);
+}
+
+#[test]
+fn batches_too_large_for_a_packet_are_refused_before_signing() {
+    let client = client();
+    let signer = Keypair::new();
+    let memo = "x".repeat(MAX_MEMO_LEN);
+    let err = client
+        .batch()
+        .add_memo(memo.clone(), signer.pubkey())
+        .add_memo(memo.clone(), signer.pubkey())
+        .add_memo(memo, signer.pubkey())
+        .send(&[&signer])
+        .unwrap_err();
+    match err.downcast_ref::<TokenVaultError>() {
+        Some(TokenVaultError::TransactionTooLarge { size, max }) => {
+            assert!(size > max, "{} <= {}", size, max)
+        }
+        _ => panic!("unexpected error: {:#}", err),
+    }
+}
+
+#[test]
+fn batches_need_exactly_the_signers_their_steps_name() {
+    let client = client();
+    let signer = Keypair::new();
+    let stranger = Keypair::new();
+    let batch = || {
+        client
+            .batch()
+            .add_memo("invoice 2024-0042", signer.pubkey())
+    };
+
+    let err = batch().send(&[]).unwrap_err();
+    assert!(err.to_string().contains("needs a signature"), "{}", err);
+    let err = batch().send(&[&signer, &stranger]).unwrap_err();
+    assert!(
+        err.to_string().contains(&stranger.pubkey().to_string()),
+        "{}",
+        err
+    );
+
+    // Custom instructions count too
+    let instruction = Instruction::new_with_bytes(
+        Pubkey::new_unique(),
+        &[],
+        vec![AccountMeta::new_readonly(stranger.pubkey(), true)],
+    );
+    let err = batch()
+        .add_instruction(instruction)
+        .send(&[&signer])
+        .unwrap_err();
+    assert!(
+        err.to_string().contains(&stranger.pubkey().to_string()),
+        "{}",
+        err
+    );
+}
+
+#[test]
+fn empty_batches_and_long_memos_are_refused() {
+    let client = client();
+    let signer = Keypair::new();
+    assert!(client.batch().send(&[&signer]).is_err());
+    let err = client
+        .batch()
+        .add_memo("x".repeat(MAX_MEMO_LEN + 1), signer.pubkey())
+        .send(&[&signer])
+        .unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<TokenVaultError>().map(|err| err.code()),
+        Some("memo_too_long")
+    );
+}
@@ -0,0 +1,102 @@
+use anchor_client::solana_sdk::{
+    address_lookup_table_account::AddressLookupTableAccount,
+    hash::Hash,
+    instruction::{AccountMeta, Instruction},
+    message::VersionedMessage,
+    pubkey::Pubkey,
+    signature::Signature,
+    transaction::VersionedTransaction,
+};
+use token_vault_client::memo::memo_instruction;
+use token_vault_client::transaction_version::{compile_message, transaction_size};
+use token_vault_client::{ClientConfig, TransactionVersion};
+
+/// An instruction writing `accounts`, as a withdrawal does
+fn writing(accounts: &[Pubkey]) -> Instruction {
+    Instruction::new_with_bytes(
+        Pubkey::new_unique(),
+        &[1, 2, 3],
+        accounts
+            .iter()
+            .map(|account| AccountMeta::new(*account, false))
+            .collect(),
+    )
+}
+
+fn size(instructions: &[Instruction], payer: &Pubkey) -> usize {
+    transaction_size(&compile_message(payer, instructions, &[], Hash::default()).unwrap())
+}
+
+#[test]
+fn without_lookup_tables_messages_stay_legacy() {
+    let payer = Pubkey::new_unique();
+    let message = compile_message(&payer, &[writing(&[payer])], &[], Hash::default()).unwrap();
+    assert!(matches!(message, VersionedMessage::Legacy(_)));
+    assert_eq!(
+        ClientConfig::default().transaction_version,
+        TransactionVersion::Legacy
+    );
+}
+
+#[test]
+fn lookup_tables_shrink_the_transaction() {
+    let payer = Pubkey::new_unique();
+    let accounts: Vec<Pubkey> = (0..6).map(|_| Pubkey::new_unique()).collect();
+    let instructions = [writing(&accounts)];
+    let table = AddressLookupTableAccount {
+        key: Pubkey::new_unique(),
+        addresses: accounts.clone(),
+    };
+
+    let legacy = compile_message(&payer, &instructions, &[], Hash::default()).unwrap();
+    let v0 = compile_message(&payer, &instructions, &[table], Hash::default()).unwrap();
+    let VersionedMessage::V0(message) = &v0 else {
+        panic!("expected a v0 message: {:?}", v0);
+    };
+    // The payer and the program stay in the message, the rest are looked up
+    assert_eq!(message.account_keys.len(), 2);
+    assert_eq!(message.address_table_lookups.len(), 1);
+    assert_eq!(message.address_table_lookups[0].writable_indexes.len(), 6);
+    // Six keys become six one-byte indexes, at the cost of the table's key,
+    // the version byte and three length prefixes
+    assert_eq!(
+        transaction_size(&legacy) - transaction_size(&v0),
+        6 * 32 - (6 + 32 + 1 + 3)
+    );
+}
+
+#[test]
+fn sizes_are_the_serialized_transaction() {
+    let payer = Pubkey::new_unique();
+    let accounts: Vec<Pubkey> = (0..3).map(|_| Pubkey::new_unique()).collect();
+    let table = AddressLookupTableAccount {
+        key: Pubkey::new_unique(),
+        addresses: accounts.clone(),
+    };
+    for tables in [vec![], vec![table]] {
+        let message =
+            compile_message(&payer, &[writing(&accounts)], &tables, Hash::default()).unwrap();
+        let transaction = VersionedTransaction {
+            signatures: vec![Signature::default()],
+            message: message.clone(),
+        };
+        assert_eq!(
+            transaction_size(&message),
+            bincode::serialize(&transaction).unwrap().len()
+        );
+    }
+}
+
+#[test]
+fn sizes_count_every_signature_and_key() {
+    let payer = Pubkey::new_unique();
+    let signer = Pubkey::new_unique();
+    let one = size(&[memo_instruction("a", &signer)], &payer);
+    let two = size(&[memo_instruction("ab", &signer)], &payer);
+    assert_eq!(two, one + 1);
+    // Another signer adds its key and its signature
+    let other = size(&[memo_instruction("a", &Pubkey::new_unique())], &signer);
+    assert_eq!(other, one);
+    let paid_by_signer = size(&[memo_instruction("a", &signer)], &signer);
+    assert_eq!(one, paid_by_signer + 32 + 64);
+}
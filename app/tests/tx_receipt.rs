@@ -0,0 +1,79 @@
+use anchor_client::solana_sdk::signature::Signature;
+use solana_transaction_status::{
+    EncodedConfirmedTransactionWithStatusMeta, EncodedTransaction,
+    EncodedTransactionWithStatusMeta, TransactionStatusMeta,
+};
+use token_vault_client::fixtures::VaultFixture;
+use token_vault_client::{TxReceipt, VaultEvent};
+
+fn confirmed(slot: u64, fee: u64) -> EncodedConfirmedTransactionWithStatusMeta {
+    let meta = TransactionStatusMeta {
+        fee,
+        ..Default::default()
+    };
+    EncodedConfirmedTransactionWithStatusMeta {
+        slot,
+        transaction: EncodedTransactionWithStatusMeta {
+            transaction: EncodedTransaction::LegacyBinary(String::new()),
+            meta: Some(meta.into()),
+            version: None,
+        },
+        block_time: None,
+    }
+}
+
+#[test]
+fn receipts_take_slot_and_fee_from_the_transaction() {
+    let signature = Signature::new_unique();
+    let receipt = TxReceipt::from_transaction(signature, &confirmed(42, 5_000));
+    assert_eq!(
+        receipt,
+        TxReceipt {
+            signature,
+            slot: Some(42),
+            fee_paid: Some(5_000),
+            attempts: 1,
+            events: None,
+            memo: None,
+        }
+    );
+    assert_eq!(
+        receipt.to_string(),
+        format!("{} (slot 42, fee 5000 lamports)", signature)
+    );
+    assert_eq!(
+        TxReceipt::signature_only(signature).to_string(),
+        signature.to_string()
+    );
+}
+
+#[test]
+fn receipts_serialize_the_signature_as_base58() {
+    let signature = Signature::new_unique();
+    let receipt = TxReceipt::signature_only(signature);
+    let json = serde_json::to_value(&receipt).unwrap();
+    assert_eq!(json["signature"], signature.to_string());
+    assert!(json["slot"].is_null());
+    assert_eq!(serde_json::from_value::<TxReceipt>(json).unwrap(), receipt);
+}
+
+/// Needs a validator with the token vault program deployed; see
+/// `fixtures::VALIDATOR_ENV` and `fixtures::PROGRAM_ID_ENV`
+#[test]
+#[ignore = "needs a local validator; run with --ignored"]
+fn deposits_and_withdrawals_return_their_receipts() {
+    let mut fixture = VaultFixture::builder().build().unwrap();
+    let deposit = fixture.deposit_as(0, 10_000).unwrap();
+    let withdrawal = fixture.withdraw_as(0, 5_000).unwrap();
+    assert_ne!(deposit.signature, withdrawal.signature);
+    assert!(deposit.slot.unwrap() <= withdrawal.slot.unwrap());
+    assert!(deposit.fee_paid.unwrap() > 0);
+    assert!(matches!(
+        deposit.events.as_deref(),
+        Some([VaultEvent::Deposit(event)]) if event.amount == 10_000
+    ));
+
+    fixture.client_mut().with_receipt_details(false);
+    let receipt = fixture.deposit_as(0, 1_000).unwrap();
+    assert_eq!(receipt, TxReceipt::signature_only(receipt.signature));
+}
@@ -0,0 +1,39 @@
+use std::str::FromStr;
+use token_vault_client::UiAmount;
+
+#[test]
+fn decimal_strings_convert_exactly() {
+    let amount = UiAmount::from_str("1000.5").unwrap();
+    assert_eq!(amount.to_base_units(6).unwrap(), 1_000_500_000);
+    assert_eq!(amount.to_base_units(9).unwrap(), 1_000_500_000_000);
+    assert_eq!(amount.to_string(), "1000.5");
+    // 0.1 has no exact float; here it is exact
+    assert_eq!(
+        "0.1".parse::<UiAmount>().unwrap().to_base_units(1).unwrap(),
+        1
+    );
+}
+
+#[test]
+fn extra_precision_is_refused_not_truncated() {
+    let amount: UiAmount = "1.0000001".parse().unwrap();
+    assert_eq!(
+        amount.to_base_units(6).unwrap_err().to_string(),
+        "'1.0000001' has more than 6 decimal places"
+    );
+    // Trailing zeros carry no precision
+    let amount: UiAmount = "1.5000000".parse().unwrap();
+    assert_eq!(amount.to_base_units(6).unwrap(), 1_500_000);
+}
+
+#[test]
+fn malformed_and_oversized_amounts_are_refused() {
+    for malformed in ["", ".", "1e6", "-1", "1,000", " 1"] {
+        assert!(malformed.parse::<UiAmount>().is_err(), "{:?}", malformed);
+    }
+    let huge: UiAmount = "18446744073709.551616".parse().unwrap();
+    assert_eq!(
+        huge.to_base_units(6).unwrap_err().to_string(),
+        "'18446744073709.551616' is too large"
+    );
+}
@@ -0,0 +1,122 @@
+mod common;
+
+use anchor_client::solana_sdk::{
+    commitment_config::CommitmentConfig, pubkey::Pubkey, signature::Keypair,
+};
+use anchor_client::Cluster;
+use std::time::Duration;
+use token_vault_client::token_vault::state::Vault;
+use token_vault_client::{
+    CacheConfig, ClientConfig, Provenance, TokenVaultClient, TokenVaultError, VaultCache,
+};
+
+fn vault(total_deposited: u64) -> Vault {
+    Vault {
+        total_deposited,
+        ..common::vault()
+    }
+}
+
+fn at(slot: u64) -> Provenance {
+    Provenance {
+        slot,
+        commitment: CommitmentConfig::confirmed().commitment,
+    }
+}
+
+fn enabled(ttl: Duration) -> VaultCache {
+    VaultCache::new(CacheConfig { ttl, enabled: true })
+}
+
+#[test]
+fn a_disabled_cache_keeps_nothing() {
+    let cache = VaultCache::new(CacheConfig::default());
+    assert!(!cache.config().enabled);
+    let address = Pubkey::new_unique();
+    cache.store(address, &vault(1), at(10));
+    assert!(cache.is_empty());
+    assert!(cache.get(&address, CommitmentConfig::confirmed()).is_none());
+}
+
+#[test]
+fn entries_are_served_within_the_ttl_at_their_commitment() {
+    let cache = enabled(Duration::from_secs(60));
+    let address = Pubkey::new_unique();
+    let data = vault(500);
+    cache.store(address, &data, at(10));
+
+    assert_eq!(
+        cache.get(&address, CommitmentConfig::confirmed()),
+        Some((data, at(10)))
+    );
+    // A read asked for at another commitment is not answered from this one
+    assert!(cache.get(&address, CommitmentConfig::finalized()).is_none());
+    assert!(cache
+        .get(&Pubkey::new_unique(), CommitmentConfig::confirmed())
+        .is_none());
+
+    let expired = enabled(Duration::ZERO);
+    expired.store(address, &vault(500), at(10));
+    assert_eq!(expired.len(), 1);
+    assert!(expired
+        .get(&address, CommitmentConfig::confirmed())
+        .is_none());
+}
+
+#[test]
+fn older_reads_do_not_replace_newer_ones() {
+    let cache = enabled(Duration::from_secs(60));
+    let address = Pubkey::new_unique();
+    cache.store(address, &vault(700), at(20));
+    cache.store(address, &vault(500), at(10));
+    let (cached, provenance) = cache.get(&address, CommitmentConfig::confirmed()).unwrap();
+    assert_eq!(cached.total_deposited, 700);
+    assert_eq!(provenance.slot, 20);
+
+    cache.invalidate(&address);
+    assert!(cache.is_empty());
+}
+
+#[test]
+fn the_cache_can_be_shared_between_threads() {
+    fn shareable<T: Send + Sync>() {}
+    shareable::<VaultCache>();
+}
+
+#[test]
+fn clients_drop_invalidated_vaults() {
+    let config = ClientConfig {
+        vault_cache: CacheConfig {
+            ttl: Duration::from_secs(60),
+            enabled: true,
+        },
+        ..ClientConfig::default()
+    };
+    let mut client = TokenVaultClient::new_with_config(
+        Cluster::Localnet,
+        Keypair::new(),
+        Pubkey::new_unique(),
+        config,
+    )
+    .unwrap();
+    assert!(client.vault_cache().config().enabled);
+    let err = client.refresh_vault().unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<TokenVaultError>(),
+        Some(&TokenVaultError::VaultNotSet)
+    );
+
+    let address = Pubkey::new_unique();
+    #[allow(deprecated)]
+    client.with_vault(address);
+    client.vault_cache().store(address, &vault(1), at(10));
+    client.refresh_vault().unwrap();
+    assert!(client.vault_cache().is_empty());
+
+    client.vault_cache().store(address, &vault(1), at(10));
+    client.invalidate(&address);
+    assert!(client.vault_cache().is_empty());
+
+    client.with_vault_cache(CacheConfig::default());
+    assert!(!client.vault_cache().config().enabled);
+}
@@ -0,0 +1,80 @@
+use std::time::Duration;
+use token_vault_client::vault_config::{ui_to_base_units, Set};
+use token_vault_client::{
+    DepositWindow, DustPolicy, ReservePolicy, VaultConfig, VaultConfigBuilder, WithdrawalLimit,
+};
+
+fn builder(name: &str, fee_bps: u16, limit: &str) -> VaultConfigBuilder<Set, Set, Set, Set> {
+    VaultConfig::builder()
+        .name(name)
+        .fee_bps(fee_bps)
+        .timelock(Duration::from_secs(86_400))
+        .withdrawal_limit_ui(limit)
+}
+
+#[test]
+fn optional_fields_default_to_the_permissive_policies() {
+    let config = builder("treasury", 100, " 1000.5 ").build().unwrap();
+    assert_eq!(config.name, "treasury");
+    assert_eq!(config.fee_bps, 100);
+    assert_eq!(config.timelock, 86_400);
+    assert_eq!(
+        config.withdrawal_limit,
+        WithdrawalLimit::Ui("1000.5".to_string())
+    );
+    assert_eq!(config.fee_collector, None);
+    assert!(!config.immutable);
+    assert_eq!(config.deposit_window, DepositWindow::UNRESTRICTED);
+    assert_eq!(config.dust_policy, DustPolicy::DISABLED);
+    assert_eq!(config.reserve, ReservePolicy::NONE);
+}
+
+#[test]
+fn build_rejects_invalid_fields() {
+    let error =
+        |builder: VaultConfigBuilder<Set, Set, Set, Set>| builder.build().unwrap_err().to_string();
+    assert_eq!(error(builder("", 100, "1")), "Vault name cannot be empty");
+    assert_eq!(
+        error(builder(&"v".repeat(33), 100, "1")),
+        "Vault name is 33 bytes; at most 32 bytes of UTF-8 are allowed"
+    );
+    assert_eq!(
+        error(builder("treasury", 10_001, "1")),
+        "Fee of 10001 bps exceeds 10000 bps"
+    );
+    assert_eq!(
+        error(builder("treasury", 100, "1,000")),
+        "Invalid withdrawal limit: '1,000' is not a decimal amount"
+    );
+    assert_eq!(
+        error(builder("treasury", 100, "1").reserve(ReservePolicy {
+            bps: 50,
+            timelock: -1,
+        })),
+        "Reserve timelock cannot be negative"
+    );
+    assert!(builder("treasury", 100, "1")
+        .deposit_window(DepositWindow {
+            start: 200,
+            end: 100
+        })
+        .build()
+        .is_err());
+}
+
+#[test]
+fn ui_amounts_convert_with_the_mint_decimals() {
+    assert_eq!(ui_to_base_units("1000", 6).unwrap(), 1_000_000_000);
+    assert_eq!(ui_to_base_units("1000.5", 6).unwrap(), 1_000_500_000);
+    assert_eq!(ui_to_base_units(".25", 2).unwrap(), 25);
+    assert_eq!(ui_to_base_units("7.", 0).unwrap(), 7);
+    // Trailing zeros are not extra precision
+    assert_eq!(ui_to_base_units("1.500", 1).unwrap(), 15);
+
+    assert!(ui_to_base_units("1.05", 1).is_err());
+    assert!(ui_to_base_units(".", 6).is_err());
+    assert!(ui_to_base_units("-1", 6).is_err());
+    assert!(ui_to_base_units("18446744073709551616", 0).is_err());
+    assert!(ui_to_base_units("18446744073710", 6).is_err());
+    assert_eq!(WithdrawalLimit::BaseUnits(42).to_base_units(9).unwrap(), 42);
+}
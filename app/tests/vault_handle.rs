@@ -0,0 +1,56 @@
+use anchor_client::solana_sdk::{pubkey::Pubkey, signature::Keypair};
+use anchor_client::Cluster;
+use token_vault_client::token_vault::{self, pda};
+use token_vault_client::{ClientConfig, TokenVaultClient, TokenVaultError, VaultHandle};
+
+/// A client whose endpoint refuses every connection
+fn client() -> TokenVaultClient {
+    let config = ClientConfig {
+        rpc_url: Some("http://127.0.0.1:9".to_string()),
+        ..ClientConfig::default()
+    };
+    TokenVaultClient::new_with_config(Cluster::Localnet, Keypair::new(), token_vault::ID, config)
+        .unwrap()
+}
+
+fn vault_error(err: &anyhow::Error) -> Option<&TokenVaultError> {
+    err.downcast_ref::<TokenVaultError>()
+}
+
+#[test]
+fn handles_carry_their_vault_and_token_account() {
+    let client = client();
+    let vault = Pubkey::new_unique();
+    let handle = client.vault(vault);
+    assert_eq!(handle.address(), vault);
+    assert_eq!(
+        handle.token_account(),
+        pda::find_vault_token_account_address(&token_vault::ID, &vault).0
+    );
+    // Nothing is read until asked for
+    assert!(handle.cached().is_none());
+}
+
+#[test]
+fn handles_never_need_a_current_vault() {
+    let client = client();
+    let depositor = Keypair::new();
+    let err = client.deposit(&depositor, 1_000).unwrap_err();
+    assert_eq!(vault_error(&err), Some(&TokenVaultError::VaultNotSet));
+
+    let handle = client.vault(Pubkey::new_unique());
+    for err in [
+        handle.deposit(&depositor, 1_000).unwrap_err(),
+        handle.withdraw(&depositor, 1_000, None).unwrap_err(),
+        handle.info().unwrap_err(),
+    ] {
+        assert_ne!(vault_error(&err), Some(&TokenVaultError::VaultNotSet));
+    }
+    assert!(handle.cached().is_none());
+}
+
+#[test]
+fn handles_can_be_shared_between_threads() {
+    fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<VaultHandle<'static>>();
+}
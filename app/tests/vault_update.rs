@@ -0,0 +1,207 @@
+mod common;
+
+use anchor_client::solana_sdk::{pubkey::Pubkey, signature::Signature};
+use anchor_lang::{InstructionData, ToAccountMetas};
+use common::key;
+use token_vault_client::changes::{merge_into_summary, vault_changes};
+use token_vault_client::token_vault::{accounts, instruction, state::Vault};
+use token_vault_client::vault_update::check_new_holder;
+use token_vault_client::{
+    AdminState, ConfigChanges, UpdateVaultParams, VaultChange, VaultInstruction,
+    VaultInstructionKind, VaultSummary, VaultTransaction,
+};
+
+fn vault() -> Vault {
+    Vault {
+        authority: key(1),
+        token_mint: key(2),
+        fee_collector: key(3),
+        fee_percentage: 25,
+        withdrawal_timelock: 3_600,
+        withdrawal_limit: 1_000_000,
+        ..common::vault()
+    }
+}
+
+#[test]
+fn fees_above_100_percent_are_refused_before_sending() {
+    let params = UpdateVaultParams {
+        fee_percentage: Some(10_001),
+        ..UpdateVaultParams::default()
+    };
+    assert_eq!(
+        params.check().unwrap_err().to_string(),
+        "Fee percentage 10001 exceeds 10000 basis points"
+    );
+    let full = UpdateVaultParams {
+        fee_percentage: Some(10_000),
+        ..UpdateVaultParams::default()
+    };
+    assert!(full.check().is_ok());
+
+    let negative = UpdateVaultParams {
+        withdrawal_timelock: Some(-1),
+        ..UpdateVaultParams::default()
+    };
+    assert!(negative.check().is_err());
+    // The preview refuses the same settings
+    assert!(UpdateVaultParams::from(&ConfigChanges::from(negative))
+        .check()
+        .is_err());
+}
+
+#[test]
+fn unset_settings_keep_the_vaults_values() {
+    assert!(UpdateVaultParams::default().is_empty());
+    let params = UpdateVaultParams {
+        fee_percentage: Some(10),
+        ..UpdateVaultParams::default()
+    };
+    assert!(!params.is_empty());
+    assert_eq!(
+        params.applied_to(&vault()),
+        AdminState::Config {
+            fee_percentage: 10,
+            withdrawal_timelock: 3_600,
+            withdrawal_limit: 1_000_000,
+        }
+    );
+    assert_eq!(
+        params.applied_to(&vault()).to_string(),
+        "fee 0.10%, withdrawal timelock 1h, withdrawal limit 1000000"
+    );
+}
+
+#[test]
+fn only_the_authority_signs_and_the_vault_is_writable() {
+    let metas = accounts::UpdateVaultConfig {
+        authority: key(1),
+        vault: key(4),
+    }
+    .to_account_metas(None);
+    assert!(metas[0].is_signer && !metas[0].is_writable);
+    assert!(!metas[1].is_signer && metas[1].is_writable);
+}
+
+/// A transaction in which the authority `key(1)` sent `data` to the vault
+/// `key(4)`
+fn transaction(data: Vec<u8>) -> VaultTransaction {
+    VaultTransaction {
+        signature: Signature::new_unique(),
+        slot: 7,
+        block_time: None,
+        succeeded: true,
+        instructions: vec![VaultInstruction {
+            kind: VaultInstructionKind::from_data(&data),
+            index: 0,
+            accounts: vec![key(1).to_string(), key(4).to_string()],
+            data,
+        }],
+        logs: Vec::new(),
+    }
+}
+
+#[test]
+fn updates_show_up_in_the_change_feed() {
+    let data = instruction::UpdateVaultConfig {
+        fee_percentage: None,
+        withdrawal_timelock: Some(0),
+        withdrawal_limit: Some(5),
+    }
+    .data();
+    assert_eq!(data[..8], [122, 3, 21, 222, 158, 255, 238, 157]);
+
+    let changes = vault_changes(&key(4), 0, &transaction(data));
+    assert_eq!(changes.len(), 1);
+    assert_eq!(
+        changes[0].change,
+        VaultChange::ConfigUpdated {
+            fee_percentage: None,
+            withdrawal_timelock: Some(0),
+            withdrawal_limit: Some(5),
+        }
+    );
+    assert_eq!(
+        changes[0].change.to_string(),
+        "config updated: withdrawal timelock none, withdrawal limit 5"
+    );
+}
+
+#[test]
+fn roles_never_go_to_the_default_pubkey() {
+    let err = check_new_holder("authority", key(1), Pubkey::default()).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Refusing to make the default pubkey the vault's authority"
+    );
+    // Handing a role to its holder sends nothing
+    assert!(!check_new_holder("fee collector", key(3), key(3)).unwrap());
+    assert!(check_new_holder("fee collector", key(3), key(5)).unwrap());
+}
+
+#[test]
+fn role_changes_carry_the_new_holder() {
+    assert_eq!(
+        instruction::TransferAuthority {
+            new_authority: key(9)
+        }
+        .data(),
+        [&[48, 169, 76, 72, 229, 180, 55, 161][..], &[9; 32][..]].concat()
+    );
+    let metas = accounts::SetFeeCollector {
+        authority: key(1),
+        vault: key(4),
+    }
+    .to_account_metas(None);
+    let signers: Vec<_> = metas
+        .iter()
+        .filter(|m| m.is_signer)
+        .map(|m| m.pubkey)
+        .collect();
+    assert_eq!(signers, vec![key(1)]);
+}
+
+#[test]
+fn merged_summaries_follow_authority_transfers() {
+    let transfer = instruction::TransferAuthority {
+        new_authority: key(9),
+    }
+    .data();
+    let set_collector = instruction::SetFeeCollector {
+        new_fee_collector: key(8),
+    }
+    .data();
+    let mut changes = vault_changes(&key(4), 0, &transaction(transfer));
+    changes.extend(vault_changes(&key(4), 0, &transaction(set_collector)));
+    assert_eq!(
+        changes
+            .iter()
+            .map(|change| change.change.to_string())
+            .collect::<Vec<_>>(),
+        [
+            format!("authority transferred to {}", key(9)),
+            format!("fee collector set to {}", key(8)),
+        ]
+    );
+
+    let mut summary = VaultSummary::of(key(4), &vault());
+    merge_into_summary(&mut summary, &changes);
+    assert_eq!(summary.authority, key(9));
+    assert_eq!(summary.fee_collector, key(8));
+}
+
+#[test]
+fn merged_summaries_follow_config_updates() {
+    let data = instruction::UpdateVaultConfig {
+        fee_percentage: Some(50),
+        withdrawal_timelock: None,
+        withdrawal_limit: Some(5),
+    }
+    .data();
+    let mut summary = VaultSummary::of(key(4), &vault());
+    assert_eq!(summary.fee_percentage, 25);
+    merge_into_summary(&mut summary, &vault_changes(&key(4), 0, &transaction(data)));
+    assert_eq!(summary.fee_percentage, 50);
+    assert_eq!(summary.withdrawal_timelock, 3_600);
+    assert_eq!(summary.withdrawal_limit, 5);
+}
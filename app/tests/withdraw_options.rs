@@ -0,0 +1,219 @@
+use anchor_client::solana_client::rpc_client::RpcClient;
+use anchor_client::solana_sdk::{
+    commitment_config::CommitmentConfig,
+    instruction::Instruction,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    transaction::Transaction,
+};
+use anchor_client::Cluster;
+use anchor_spl::associated_token::get_associated_token_address;
+use anchor_spl::token::{self, spl_token};
+use std::str::FromStr;
+use token_vault_client::fixtures::{VaultFixture, VALIDATOR_ENV};
+use token_vault_client::withdraw_options::create_missing_accounts;
+use token_vault_client::{PayoutAccount, TokenVaultError, WithdrawOptions};
+
+const CREATE: WithdrawOptions = WithdrawOptions {
+    create_ata: true,
+    skip_limit_check: false,
+    memo: None,
+};
+
+struct Withdrawal {
+    payer: Pubkey,
+    mint: Pubkey,
+    withdrawer: Pubkey,
+    other: Pubkey,
+}
+
+impl Withdrawal {
+    fn new() -> Self {
+        Self {
+            payer: Pubkey::new_unique(),
+            mint: Pubkey::new_unique(),
+            withdrawer: Pubkey::new_unique(),
+            other: Pubkey::new_unique(),
+        }
+    }
+
+    fn account(&self, owner: Pubkey, exists: bool) -> PayoutAccount {
+        PayoutAccount {
+            owner,
+            address: get_associated_token_address(&owner, &self.mint),
+            exists,
+        }
+    }
+
+    fn accounts(&self, withdrawer_exists: bool, other_exists: bool) -> [PayoutAccount; 2] {
+        [
+            self.account(self.withdrawer, withdrawer_exists),
+            self.account(self.other, other_exists),
+        ]
+    }
+
+    /// Owner of the account `instruction` creates, after checking that it is
+    /// an associated token account of the mint paid for by the payer
+    fn created_for(&self, instruction: &Instruction) -> Pubkey {
+        assert_eq!(instruction.program_id, spl_associated_token_account::id());
+        let keys: Vec<Pubkey> = instruction
+            .accounts
+            .iter()
+            .map(|meta| meta.pubkey)
+            .collect();
+        assert_eq!(keys[0], self.payer);
+        assert_eq!(keys[1], get_associated_token_address(&keys[2], &self.mint));
+        assert_eq!(keys[3], self.mint);
+        keys[2]
+    }
+}
+
+#[test]
+fn both_missing_accounts_are_created_in_one_transaction() {
+    let withdrawal = Withdrawal::new();
+    let instructions = create_missing_accounts(
+        &withdrawal.payer,
+        &withdrawal.mint,
+        &withdrawal.accounts(false, false),
+        &CREATE,
+    )
+    .unwrap();
+
+    let owners: Vec<Pubkey> = instructions
+        .iter()
+        .map(|instruction| withdrawal.created_for(instruction))
+        .collect();
+    assert_eq!(owners, [withdrawal.withdrawer, withdrawal.other]);
+}
+
+#[test]
+fn only_the_missing_account_is_created() {
+    let withdrawal = Withdrawal::new();
+    let instructions = create_missing_accounts(
+        &withdrawal.payer,
+        &withdrawal.mint,
+        &withdrawal.accounts(true, false),
+        &CREATE,
+    )
+    .unwrap();
+
+    assert_eq!(instructions.len(), 1);
+    assert_eq!(withdrawal.created_for(&instructions[0]), withdrawal.other);
+}
+
+#[test]
+fn existing_accounts_need_nothing_either_way() {
+    let withdrawal = Withdrawal::new();
+    for options in [WithdrawOptions::default(), CREATE] {
+        let instructions = create_missing_accounts(
+            &withdrawal.payer,
+            &withdrawal.mint,
+            &withdrawal.accounts(true, true),
+            &options,
+        )
+        .unwrap();
+        assert!(instructions.is_empty());
+    }
+}
+
+#[test]
+fn without_create_ata_a_missing_account_fails_fast() {
+    let withdrawal = Withdrawal::new();
+    let err = create_missing_accounts(
+        &withdrawal.payer,
+        &withdrawal.mint,
+        &withdrawal.accounts(true, false),
+        &WithdrawOptions::default(),
+    )
+    .unwrap_err();
+
+    let other_account = get_associated_token_address(&withdrawal.other, &withdrawal.mint);
+    match err.downcast_ref::<TokenVaultError>() {
+        Some(TokenVaultError::TokenAccountMissing { owner, account }) => {
+            assert_eq!(*owner, withdrawal.other);
+            assert_eq!(*account, other_account);
+        }
+        other => panic!("expected a missing account, got {:?}: {}", other, err),
+    }
+    assert!(err.to_string().contains("create_ata"), "{}", err);
+}
+
+#[test]
+fn an_account_listed_twice_is_created_once() {
+    let withdrawal = Withdrawal::new();
+    let accounts = [
+        withdrawal.account(withdrawal.withdrawer, false),
+        withdrawal.account(withdrawal.withdrawer, false),
+    ];
+    let instructions =
+        create_missing_accounts(&withdrawal.payer, &withdrawal.mint, &accounts, &CREATE).unwrap();
+    assert_eq!(instructions.len(), 1);
+}
+
+/// Needs a validator with the token vault program deployed; see
+/// `fixtures::VALIDATOR_ENV` and `fixtures::PROGRAM_ID_ENV`
+#[test]
+#[ignore = "needs a local validator; run with --ignored"]
+fn withdrawing_recreates_a_closed_withdrawer_account() {
+    let fixture = VaultFixture::builder().fee_bps(50).build().unwrap();
+    let collector = fixture.authority();
+    let user = &fixture.user(0).unwrap().keypair;
+    let validator = std::env::var(VALIDATOR_ENV).unwrap_or_else(|_| "localnet".to_string());
+    let rpc = RpcClient::new_with_commitment(
+        Cluster::from_str(&validator).unwrap().url().to_string(),
+        CommitmentConfig::confirmed(),
+    );
+    let close = |owner: &Keypair| {
+        let account = get_associated_token_address(&owner.pubkey(), &fixture.mint());
+        let instructions = [
+            spl_associated_token_account::instruction::create_associated_token_account_idempotent(
+                &owner.pubkey(),
+                &owner.pubkey(),
+                &fixture.mint(),
+                &token::ID,
+            ),
+            spl_token::instruction::close_account(
+                &token::ID,
+                &account,
+                &owner.pubkey(),
+                &owner.pubkey(),
+                &[],
+            )
+            .unwrap(),
+        ];
+        let transaction = Transaction::new_signed_with_payer(
+            &instructions,
+            Some(&owner.pubkey()),
+            &[owner],
+            rpc.get_latest_blockhash().unwrap(),
+        );
+        rpc.send_and_confirm_transaction(&transaction).unwrap();
+    };
+
+    // Deposit everything so the user's token account is empty, then close
+    // it and the fee collector's, which withdrawals never pay into
+    fixture.deposit_as(0, fixture.initial_balance()).unwrap();
+    close(user);
+    close(collector);
+
+    let client = fixture.client();
+    let err = client
+        .withdraw_with_options(user, 500_000, None, WithdrawOptions::default())
+        .unwrap_err();
+    assert!(
+        matches!(
+            err.downcast_ref(),
+            Some(TokenVaultError::TokenAccountMissing { owner, .. }) if *owner == user.pubkey()
+        ),
+        "{}",
+        err
+    );
+
+    client
+        .withdraw_with_options(user, 500_000, None, CREATE)
+        .unwrap();
+    assert!(fixture.balance_of(0).unwrap().raw > 0);
+    let fee_account = get_associated_token_address(&collector.pubkey(), &fixture.mint());
+    assert!(rpc.get_account(&fee_account).is_err());
+    assert!(client.get_accrued_fees().unwrap() > 0);
+}
@@ -0,0 +1,243 @@
+mod common;
+
+use anchor_client::solana_sdk::{pubkey::Pubkey, signature::Signature};
+use anchor_lang::{AccountSerialize, InstructionData, ToAccountMetas};
+use common::key;
+use token_vault_client::books::{books_report, BooksReplay};
+use token_vault_client::token_vault::{
+    accounts, instruction,
+    state::{Vault, WithdrawalRequest},
+};
+use token_vault_client::{
+    DustPolicy, PendingWithdrawal, TokenVaultError, VaultInstruction, VaultInstructionKind,
+    VaultTransaction,
+};
+
+const DAY: i64 = 86_400;
+const REQUESTED_AT: i64 = 1_700_000_000;
+
+fn timelocked_vault(withdrawal_timelock: i64) -> Vault {
+    Vault {
+        authority: key(1),
+        token_mint: key(2),
+        fee_collector: key(3),
+        withdrawal_timelock,
+        total_deposited: 1_000_000,
+        ..common::vault()
+    }
+}
+
+fn request(amount: u64) -> WithdrawalRequest {
+    WithdrawalRequest {
+        vault: key(4),
+        withdrawer: key(5),
+        amount,
+        requested_at: REQUESTED_AT,
+        bump: 254,
+    }
+}
+
+#[test]
+fn filters_match_the_account_layout() {
+    let request = request(500);
+    let mut data = Vec::new();
+    request.try_serialize(&mut data).unwrap();
+    assert_eq!(data.len(), WithdrawalRequest::LEN);
+
+    let vault = WithdrawalRequest::VAULT_OFFSET;
+    assert_eq!(data[vault..vault + 32], request.vault.to_bytes());
+    let withdrawer = WithdrawalRequest::WITHDRAWER_OFFSET;
+    assert_eq!(
+        data[withdrawer..withdrawer + 32],
+        request.withdrawer.to_bytes()
+    );
+}
+
+#[test]
+fn remaining_time_counts_down_from_the_request() {
+    let vault = timelocked_vault(2 * DAY);
+    let pending = PendingWithdrawal::of(key(6), &request(500), &vault, REQUESTED_AT + DAY);
+    assert_eq!(pending.executable_at, REQUESTED_AT + 2 * DAY);
+    assert_eq!(pending.remaining, DAY);
+    assert!(!pending.is_executable());
+    assert_eq!(
+        pending.check_executable(),
+        Err(TokenVaultError::WithdrawalTimelocked {
+            executable_at: REQUESTED_AT + 2 * DAY,
+        })
+    );
+
+    // Executable from the exact second the timelock ends, and stays so
+    for now in [REQUESTED_AT + 2 * DAY, REQUESTED_AT + 30 * DAY] {
+        let pending = PendingWithdrawal::of(key(6), &request(500), &vault, now);
+        assert_eq!(pending.remaining, 0);
+        assert_eq!(pending.check_executable(), Ok(()));
+    }
+}
+
+#[test]
+fn without_a_timelock_requests_are_executable_at_once() {
+    let pending = PendingWithdrawal::of(key(6), &request(500), &timelocked_vault(0), REQUESTED_AT);
+    assert!(pending.is_executable());
+    assert_eq!(pending.to_string().rsplit(", ").next(), Some("executable"));
+}
+
+#[test]
+fn pending_withdrawals_show_the_time_left() {
+    let vault = timelocked_vault(DAY);
+    let pending = PendingWithdrawal::of(key(6), &request(500), &vault, REQUESTED_AT + 3_600);
+    assert_eq!(
+        pending.to_string(),
+        format!(
+            "{}: 500 from vault {}, executable in 23h (at 2023-11-15T22:13:20+00:00)",
+            key(6),
+            key(4)
+        )
+    );
+
+    let json = serde_json::to_value(&pending).unwrap();
+    assert_eq!(json["vault"], key(4).to_string());
+    assert_eq!(json["remaining"], 23 * 3_600);
+    let back: PendingWithdrawal = serde_json::from_value(json).unwrap();
+    assert_eq!(back, pending);
+}
+
+#[test]
+fn request_instructions_carry_their_discriminators() {
+    assert_eq!(
+        instruction::RequestWithdrawal { amount: 7 }.data(),
+        [
+            &[251, 85, 121, 205, 56, 201, 12, 177][..],
+            &7u64.to_le_bytes()[..]
+        ]
+        .concat()
+    );
+    assert_eq!(
+        instruction::ExecuteWithdrawal {}.data(),
+        [113, 121, 203, 232, 137, 139, 248, 249]
+    );
+    assert_eq!(
+        instruction::CancelWithdrawal {}.data(),
+        [183, 104, 181, 250, 28, 128, 210, 70]
+    );
+}
+
+#[test]
+fn only_the_withdrawer_signs_and_the_request_is_writable() {
+    let metas = accounts::ExecuteWithdrawal {
+        withdrawer: key(5),
+        vault: key(4),
+        vault_token_account: key(7),
+        withdrawer_token_account: key(8),
+        withdrawal_request: key(6),
+        token_program: anchor_spl::token::ID,
+        position: key(10),
+    }
+    .to_account_metas(None);
+    let signers: Vec<_> = metas
+        .iter()
+        .filter(|m| m.is_signer)
+        .map(|m| m.pubkey)
+        .collect();
+    assert_eq!(signers, vec![key(5)]);
+    assert!(metas.iter().any(|m| m.pubkey == key(6) && m.is_writable));
+    assert!(metas.iter().any(|m| m.pubkey == key(10) && m.is_writable));
+}
+
+fn transaction(slot: u64, vault: Pubkey, signer: Pubkey, data: Vec<u8>) -> VaultTransaction {
+    VaultTransaction {
+        signature: Signature::new_unique(),
+        slot,
+        block_time: None,
+        succeeded: true,
+        instructions: vec![VaultInstruction {
+            kind: VaultInstructionKind::from_data(&data),
+            index: 0,
+            accounts: vec![signer.to_string(), vault.to_string()],
+            data,
+        }],
+        logs: Vec::new(),
+    }
+}
+
+#[test]
+fn executed_requests_balance_the_books() {
+    let (address, user) = (key(4), key(5));
+    let mut vault = timelocked_vault(DAY);
+    vault.fee_percentage = 100;
+    let init = instruction::InitializeVault {
+        name: vault.name.clone(),
+        fee_percentage: 100,
+        withdrawal_timelock: DAY,
+        withdrawal_limit: u64::MAX,
+        immutable: false,
+        deposit_window_start: 0,
+        deposit_window_end: 0,
+        dust_threshold: 0,
+        waive_dust_fee: false,
+        reserve_bps: 0,
+        reserve_timelock: 0,
+        fee_collector: vault.fee_collector,
+    };
+    let history = [
+        transaction(1, address, vault.authority, init.data()),
+        transaction(
+            2,
+            address,
+            user,
+            instruction::Deposit { amount: 10_000 }.data(),
+        ),
+        transaction(
+            3,
+            address,
+            user,
+            instruction::RequestWithdrawal { amount: 4_000 }.data(),
+        ),
+        transaction(4, address, user, instruction::ExecuteWithdrawal {}.data()),
+        // A cancelled request moves nothing
+        transaction(
+            5,
+            address,
+            user,
+            instruction::RequestWithdrawal { amount: 1_000 }.data(),
+        ),
+        transaction(6, address, user, instruction::CancelWithdrawal {}.data()),
+    ];
+    let mut replay = BooksReplay::new(address);
+    for transaction in &history {
+        replay.apply(transaction, 100, DustPolicy::DISABLED, 0);
+    }
+    assert_eq!(replay.total_deposited, 6_000);
+    assert_eq!(replay.fees_collected, 40);
+    assert_eq!(replay.accrued_fees, 40);
+    assert_eq!(replay.positions[&user].withdrawn, 4_000);
+    assert!(replay.pending_withdrawals.is_empty());
+
+    // The fee stays in the vault token account until it is claimed
+    vault.total_deposited = 6_000;
+    vault.accrued_fees = 40;
+    let report = books_report(&replay, &vault, 6_040, 0);
+    assert!(report.is_consistent(), "{}", report);
+
+    let claim = transaction(
+        7,
+        address,
+        vault.fee_collector,
+        instruction::ClaimFees {}.data(),
+    );
+    replay.apply(&claim, 100, DustPolicy::DISABLED, 0);
+    assert_eq!(replay.accrued_fees, 0);
+    assert_eq!(replay.fees_collected, 40);
+    vault.accrued_fees = 0;
+    let report = books_report(&replay, &vault, 6_000, 0);
+    assert!(report.is_consistent(), "{}", report);
+
+    // An unclaimed fee the vault no longer holds is a mismatch
+    vault.accrued_fees = 40;
+    let fields: Vec<_> = books_report(&replay, &vault, 6_000, 0)
+        .discrepancies
+        .into_iter()
+        .map(|discrepancy| discrepancy.field)
+        .collect();
+    assert_eq!(fields, vec!["accrued_fees"]);
+}
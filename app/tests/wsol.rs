@@ -0,0 +1,54 @@
+mod common;
+
+use anchor_client::solana_sdk::system_program;
+use anchor_spl::associated_token::get_associated_token_address;
+use anchor_spl::token::{self, spl_token};
+use common::key;
+use spl_token::{instruction::TokenInstruction, native_mint};
+use token_vault_client::wsol::{unwrap_instruction, wrap_instructions, wsol_account};
+
+#[test]
+fn the_wsol_account_is_the_native_mint_ata() {
+    assert_eq!(
+        wsol_account(&key(1)),
+        get_associated_token_address(&key(1), &native_mint::ID)
+    );
+}
+
+#[test]
+fn wrapping_creates_funds_then_syncs() {
+    let account = wsol_account(&key(1));
+    let instructions = wrap_instructions(&key(1), 5_000).unwrap();
+    let programs: Vec<_> = instructions.iter().map(|ix| ix.program_id).collect();
+    assert_eq!(
+        programs,
+        [
+            spl_associated_token_account::id(),
+            system_program::ID,
+            token::ID
+        ]
+    );
+    // The owner pays for the account it owns
+    assert_eq!(instructions[0].accounts[0].pubkey, key(1));
+    assert_eq!(instructions[0].accounts[1].pubkey, account);
+    assert_eq!(instructions[0].accounts[2].pubkey, key(1));
+    assert_eq!(instructions[1].accounts[1].pubkey, account);
+    assert_eq!(
+        TokenInstruction::unpack(&instructions[2].data).unwrap(),
+        TokenInstruction::SyncNative
+    );
+    assert_eq!(instructions[2].accounts[0].pubkey, account);
+}
+
+#[test]
+fn unwrapping_closes_the_account_to_its_owner() {
+    let instruction = unwrap_instruction(&key(1)).unwrap();
+    assert_eq!(instruction.program_id, token::ID);
+    assert_eq!(
+        TokenInstruction::unpack(&instruction.data).unwrap(),
+        TokenInstruction::CloseAccount
+    );
+    let accounts: Vec<_> = instruction.accounts.iter().map(|m| m.pubkey).collect();
+    assert_eq!(accounts, [wsol_account(&key(1)), key(1), key(1)]);
+    assert!(instruction.accounts[2].is_signer);
+}
@@ -0,0 +1,1111 @@
+//! Account, instruction and PDA types for the token vault program, usable
+//! without pulling in anchor-client or an async runtime.
+
+use anchor_lang::prelude::*;
+
+declare_id!("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnS");
+
+#[cfg(feature = "serde")]
+mod serde_utils;
+
+pub mod state {
+    use super::*;
+
+    /// A token vault. `Vault::default()` is all zeroes and empty keys; build
+    /// vaults by struct update over it, so a new field with a zero default
+    /// changes no construction site
+    #[account]
+    #[derive(Debug, Default, PartialEq, Eq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct Vault {
+        #[cfg_attr(feature = "serde", serde(with = "crate::serde_utils::pubkey"))]
+        pub authority: Pubkey,
+        #[cfg_attr(feature = "serde", serde(with = "crate::serde_utils::pubkey"))]
+        pub token_mint: Pubkey,
+        #[cfg_attr(feature = "serde", serde(with = "crate::serde_utils::pubkey"))]
+        pub fee_collector: Pubkey,
+        pub fee_percentage: u16,
+        pub withdrawal_timelock: i64,
+        #[cfg_attr(feature = "serde", serde(with = "crate::serde_utils::u64_string"))]
+        pub withdrawal_limit: u64,
+        #[cfg_attr(feature = "serde", serde(with = "crate::serde_utils::u64_string"))]
+        pub total_deposited: u64,
+        pub name: String,
+        pub bump: u8,
+        pub immutable: bool,
+        /// Unix timestamp from which deposits are accepted (0 = no lower bound)
+        pub deposit_window_start: i64,
+        /// Unix timestamp from which deposits are rejected (0 = no upper bound)
+        pub deposit_window_end: i64,
+        /// Withdrawals below this amount are dust (0 = no dust handling)
+        #[cfg_attr(feature = "serde", serde(with = "crate::serde_utils::u64_string"))]
+        pub dust_threshold: u64,
+        /// Dust withdrawals are allowed without a fee instead of being rejected
+        pub waive_dust_fee: bool,
+        /// Share of every deposit, in basis points, diverted into the insurance reserve
+        pub reserve_bps: u16,
+        /// Seconds that must pass after a reserve release before the next one
+        pub reserve_timelock: i64,
+        /// Unix timestamp of the last reserve release, or of the vault's creation
+        pub last_reserve_release: i64,
+        /// Tokens held in the reserve token account
+        #[cfg_attr(feature = "serde", serde(with = "crate::serde_utils::u64_string"))]
+        pub total_reserved: u64,
+        /// Set when a withdrawal exceeded `total_deposited`, which was then
+        /// clamped to zero instead of failing; the books need reconciling
+        pub accounting_anomaly: bool,
+        /// Set by the authority to stop deposits and withdrawals until it is
+        /// cleared again
+        pub paused: bool,
+        /// Withdrawal fees held in the vault token account until the fee
+        /// collector claims them
+        #[cfg_attr(feature = "serde", serde(with = "crate::serde_utils::u64_string"))]
+        pub accrued_fees: u64,
+    }
+
+    #[cfg(feature = "serde")]
+    impl Vault {
+        /// Indented JSON, with keys as base58 and amounts as decimal strings
+        pub fn to_json_pretty(&self) -> serde_json::Result<String> {
+            serde_json::to_string_pretty(self)
+        }
+    }
+
+    /// Marks `wallet` as approved for `vault`; one PDA per wallet
+    #[account]
+    #[derive(Debug, PartialEq, Eq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct AllowlistEntry {
+        #[cfg_attr(feature = "serde", serde(with = "crate::serde_utils::pubkey"))]
+        pub vault: Pubkey,
+        #[cfg_attr(feature = "serde", serde(with = "crate::serde_utils::pubkey"))]
+        pub wallet: Pubkey,
+        pub bump: u8,
+    }
+
+    impl AllowlistEntry {
+        /// Account size including the discriminator
+        pub const LEN: usize = 8 + 32 + 32 + 1;
+    }
+
+    /// A withdrawal waiting out the vault's `withdrawal_timelock`; one PDA
+    /// per vault and withdrawer, closed when it is executed or cancelled
+    #[account]
+    #[derive(Debug, PartialEq, Eq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct WithdrawalRequest {
+        #[cfg_attr(feature = "serde", serde(with = "crate::serde_utils::pubkey"))]
+        pub vault: Pubkey,
+        #[cfg_attr(feature = "serde", serde(with = "crate::serde_utils::pubkey"))]
+        pub withdrawer: Pubkey,
+        #[cfg_attr(feature = "serde", serde(with = "crate::serde_utils::u64_string"))]
+        pub amount: u64,
+        /// Unix timestamp of the request; it becomes executable
+        /// `withdrawal_timelock` seconds later
+        pub requested_at: i64,
+        pub bump: u8,
+    }
+
+    impl WithdrawalRequest {
+        /// Account size including the discriminator
+        pub const LEN: usize = 8 + 32 + 32 + 8 + 8 + 1;
+        /// Byte offset of `vault`, just after the discriminator
+        pub const VAULT_OFFSET: usize = 8;
+        /// Byte offset of `withdrawer`, just after the vault
+        pub const WITHDRAWER_OFFSET: usize = Self::VAULT_OFFSET + 32;
+    }
+
+    /// What one depositor has put into and taken out of a vault; one PDA
+    /// per vault and depositor, created by the depositor's first deposit
+    #[account]
+    #[derive(Debug, PartialEq, Eq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct DepositorPosition {
+        #[cfg_attr(feature = "serde", serde(with = "crate::serde_utils::pubkey"))]
+        pub vault: Pubkey,
+        #[cfg_attr(feature = "serde", serde(with = "crate::serde_utils::pubkey"))]
+        pub depositor: Pubkey,
+        /// Every deposit ever made, before the reserve carve-out
+        #[cfg_attr(feature = "serde", serde(with = "crate::serde_utils::u64_string"))]
+        pub deposited: u64,
+        /// Every withdrawal ever made, fees included
+        #[cfg_attr(feature = "serde", serde(with = "crate::serde_utils::u64_string"))]
+        pub withdrawn: u64,
+        /// Unix timestamp of the latest deposit
+        pub last_deposit_ts: i64,
+        /// Unix timestamp of the latest withdrawal, or 0 before the first
+        pub last_withdraw_ts: i64,
+        pub bump: u8,
+    }
+
+    impl DepositorPosition {
+        /// Account size including the discriminator
+        pub const LEN: usize = 8 + 32 + 32 + 8 + 8 + 8 + 8 + 1;
+        /// Byte offset of `vault`, just after the discriminator
+        pub const VAULT_OFFSET: usize = 8;
+        /// Byte offset of `depositor`, just after the vault
+        pub const DEPOSITOR_OFFSET: usize = Self::VAULT_OFFSET + 32;
+
+        /// Deposited less withdrawn, or 0 when more was withdrawn than
+        /// deposited
+        pub fn net_deposited(&self) -> u64 {
+            self.deposited.saturating_sub(self.withdrawn)
+        }
+    }
+
+    /// Where wallets find a vault's notification metadata; optional, one
+    /// PDA per vault, closed again when the authority clears it
+    #[account]
+    #[derive(Debug, PartialEq, Eq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct NotificationConfig {
+        #[cfg_attr(feature = "serde", serde(with = "crate::serde_utils::pubkey"))]
+        pub vault: Pubkey,
+        /// SHA-256 of the webhook URL, so the URL itself stays off chain
+        #[cfg_attr(feature = "serde", serde(with = "crate::serde_utils::option_hash"))]
+        pub webhook_url_hash: Option<[u8; 32]>,
+        /// Base URL of the block explorer the vault's users should be sent to
+        pub explorer: String,
+        /// Email address, URL or handle for support requests
+        pub support_contact: String,
+        pub bump: u8,
+    }
+
+    impl NotificationConfig {
+        /// Longest `explorer`, in UTF-8 bytes
+        pub const MAX_EXPLORER_LEN: usize = 64;
+        /// Longest `support_contact`, in UTF-8 bytes
+        pub const MAX_SUPPORT_CONTACT_LEN: usize = 64;
+        /// Account size including the discriminator, with both strings at
+        /// their maximum length
+        pub const LEN: usize = 8
+            + 32
+            + (1 + 32)
+            + (4 + Self::MAX_EXPLORER_LEN)
+            + (4 + Self::MAX_SUPPORT_CONTACT_LEN)
+            + 1;
+    }
+
+    /// A period in which the vault refuses deposits, ending on its own at
+    /// `end_ts` without a further transaction
+    #[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct MaintenanceWindow {
+        pub start_ts: i64,
+        pub end_ts: i64,
+        /// Shown to users while the window is upcoming or active
+        pub reason: String,
+    }
+
+    /// A vault's upcoming and active maintenance windows; optional, one PDA
+    /// per vault, created on first use
+    #[account]
+    #[derive(Debug, PartialEq, Eq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct MaintenanceSchedule {
+        #[cfg_attr(feature = "serde", serde(with = "crate::serde_utils::pubkey"))]
+        pub vault: Pubkey,
+        /// Non-overlapping, in start order; windows that have ended are
+        /// dropped when the next one is scheduled
+        pub windows: Vec<MaintenanceWindow>,
+        pub bump: u8,
+    }
+
+    impl MaintenanceSchedule {
+        /// Most windows the schedule holds at once
+        pub const MAX_WINDOWS: usize = 4;
+        /// Longest `reason`, in UTF-8 bytes
+        pub const MAX_REASON_LEN: usize = 64;
+        /// Account size including the discriminator, with every window's
+        /// reason at its maximum length
+        pub const LEN: usize =
+            8 + 32 + 4 + Self::MAX_WINDOWS * (8 + 8 + 4 + Self::MAX_REASON_LEN) + 1;
+    }
+
+    /// Program-wide feature switches; clients refuse to build transactions
+    /// for a program that enforces features they do not know
+    #[account]
+    #[derive(Debug, PartialEq, Eq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct FeatureFlags {
+        #[cfg_attr(feature = "serde", serde(with = "crate::serde_utils::pubkey"))]
+        pub admin: Pubkey,
+        /// Bitset of enabled features
+        #[cfg_attr(feature = "serde", serde(with = "crate::serde_utils::u64_string"))]
+        pub enabled: u64,
+        /// Oldest client version, as `major.minor.patch`, the program still serves
+        pub min_client_version: String,
+        pub bump: u8,
+    }
+}
+
+/// Client-side account lists for each instruction, in the order the program expects
+pub mod accounts {
+    use super::*;
+    use anchor_lang::solana_program::instruction::AccountMeta;
+
+    pub struct InitializeVault {
+        pub authority: Pubkey,
+        pub vault: Pubkey,
+        pub vault_token_account: Pubkey,
+        pub reserve_token_account: Pubkey,
+        pub token_mint: Pubkey,
+        pub token_program: Pubkey,
+        pub system_program: Pubkey,
+        pub rent: Pubkey,
+    }
+
+    impl ToAccountMetas for InitializeVault {
+        fn to_account_metas(&self, is_signer: Option<bool>) -> Vec<AccountMeta> {
+            vec![
+                AccountMeta::new_readonly(self.authority, is_signer.unwrap_or(true)),
+                AccountMeta::new(self.vault, false),
+                AccountMeta::new(self.vault_token_account, false),
+                AccountMeta::new(self.reserve_token_account, false),
+                AccountMeta::new_readonly(self.token_mint, false),
+                AccountMeta::new_readonly(self.token_program, false),
+                AccountMeta::new_readonly(self.system_program, false),
+                AccountMeta::new_readonly(self.rent, false),
+            ]
+        }
+    }
+
+    /// Credits the depositor's position, which the depositor pays the rent
+    /// of on their first deposit
+    pub struct Deposit {
+        pub depositor: Pubkey,
+        pub vault: Pubkey,
+        pub vault_token_account: Pubkey,
+        /// Receives the vault's `reserve_bps` share of the deposit
+        pub reserve_token_account: Pubkey,
+        pub depositor_token_account: Pubkey,
+        pub token_program: Pubkey,
+        pub position: Pubkey,
+        pub system_program: Pubkey,
+    }
+
+    impl ToAccountMetas for Deposit {
+        fn to_account_metas(&self, is_signer: Option<bool>) -> Vec<AccountMeta> {
+            vec![
+                AccountMeta::new(self.depositor, is_signer.unwrap_or(true)),
+                AccountMeta::new(self.vault, false),
+                AccountMeta::new(self.vault_token_account, false),
+                AccountMeta::new(self.reserve_token_account, false),
+                AccountMeta::new(self.depositor_token_account, false),
+                AccountMeta::new_readonly(self.token_program, false),
+                AccountMeta::new(self.position, false),
+                AccountMeta::new_readonly(self.system_program, false),
+            ]
+        }
+    }
+
+    /// Pays out the amount less the fee; the fee stays in the vault token
+    /// account, counted in `Vault::accrued_fees` until `ClaimFees`
+    pub struct Withdraw {
+        pub withdrawer: Pubkey,
+        pub vault: Pubkey,
+        pub vault_token_account: Pubkey,
+        /// Destination; may belong to a third-party recipient
+        pub withdrawer_token_account: Pubkey,
+        pub token_program: Pubkey,
+        /// The withdrawer's position; left alone when they have none
+        pub position: Pubkey,
+    }
+
+    impl ToAccountMetas for Withdraw {
+        fn to_account_metas(&self, is_signer: Option<bool>) -> Vec<AccountMeta> {
+            vec![
+                AccountMeta::new_readonly(self.withdrawer, is_signer.unwrap_or(true)),
+                AccountMeta::new(self.vault, false),
+                AccountMeta::new(self.vault_token_account, false),
+                AccountMeta::new(self.withdrawer_token_account, false),
+                AccountMeta::new_readonly(self.token_program, false),
+                AccountMeta::new(self.position, false),
+            ]
+        }
+    }
+
+    /// Creates the withdrawer's request PDA; the withdrawer pays its rent
+    pub struct RequestWithdrawal {
+        pub withdrawer: Pubkey,
+        pub vault: Pubkey,
+        pub withdrawal_request: Pubkey,
+        pub system_program: Pubkey,
+    }
+
+    impl ToAccountMetas for RequestWithdrawal {
+        fn to_account_metas(&self, is_signer: Option<bool>) -> Vec<AccountMeta> {
+            vec![
+                AccountMeta::new(self.withdrawer, is_signer.unwrap_or(true)),
+                AccountMeta::new_readonly(self.vault, false),
+                AccountMeta::new(self.withdrawal_request, false),
+                AccountMeta::new_readonly(self.system_program, false),
+            ]
+        }
+    }
+
+    /// Pays out a request whose timelock has elapsed and closes it, returning
+    /// its rent to the withdrawer; the fee accrues as for `Withdraw`
+    pub struct ExecuteWithdrawal {
+        pub withdrawer: Pubkey,
+        pub vault: Pubkey,
+        pub vault_token_account: Pubkey,
+        pub withdrawer_token_account: Pubkey,
+        pub withdrawal_request: Pubkey,
+        pub token_program: Pubkey,
+        /// The withdrawer's position, as for `Withdraw`
+        pub position: Pubkey,
+    }
+
+    impl ToAccountMetas for ExecuteWithdrawal {
+        fn to_account_metas(&self, is_signer: Option<bool>) -> Vec<AccountMeta> {
+            vec![
+                AccountMeta::new(self.withdrawer, is_signer.unwrap_or(true)),
+                AccountMeta::new(self.vault, false),
+                AccountMeta::new(self.vault_token_account, false),
+                AccountMeta::new(self.withdrawer_token_account, false),
+                AccountMeta::new(self.withdrawal_request, false),
+                AccountMeta::new_readonly(self.token_program, false),
+                AccountMeta::new(self.position, false),
+            ]
+        }
+    }
+
+    /// Closes a pending request without paying it out
+    pub struct CancelWithdrawal {
+        pub withdrawer: Pubkey,
+        pub vault: Pubkey,
+        pub withdrawal_request: Pubkey,
+    }
+
+    impl ToAccountMetas for CancelWithdrawal {
+        fn to_account_metas(&self, is_signer: Option<bool>) -> Vec<AccountMeta> {
+            vec![
+                AccountMeta::new(self.withdrawer, is_signer.unwrap_or(true)),
+                AccountMeta::new_readonly(self.vault, false),
+                AccountMeta::new(self.withdrawal_request, false),
+            ]
+        }
+    }
+
+    pub struct SweepLamports {
+        pub authority: Pubkey,
+        pub vault: Pubkey,
+        pub vault_token_account: Pubkey,
+        pub destination: Pubkey,
+        pub token_program: Pubkey,
+    }
+
+    impl ToAccountMetas for SweepLamports {
+        fn to_account_metas(&self, is_signer: Option<bool>) -> Vec<AccountMeta> {
+            vec![
+                AccountMeta::new_readonly(self.authority, is_signer.unwrap_or(true)),
+                AccountMeta::new(self.vault, false),
+                AccountMeta::new(self.vault_token_account, false),
+                AccountMeta::new(self.destination, false),
+                AccountMeta::new_readonly(self.token_program, false),
+            ]
+        }
+    }
+
+    /// Changes the fee, timelock or withdrawal limit; authority only
+    pub struct UpdateVaultConfig {
+        pub authority: Pubkey,
+        pub vault: Pubkey,
+    }
+
+    impl ToAccountMetas for UpdateVaultConfig {
+        fn to_account_metas(&self, is_signer: Option<bool>) -> Vec<AccountMeta> {
+            vec![
+                AccountMeta::new_readonly(self.authority, is_signer.unwrap_or(true)),
+                AccountMeta::new(self.vault, false),
+            ]
+        }
+    }
+
+    /// Hands the vault to a new authority; current authority only
+    pub struct TransferAuthority {
+        pub authority: Pubkey,
+        pub vault: Pubkey,
+    }
+
+    impl ToAccountMetas for TransferAuthority {
+        fn to_account_metas(&self, is_signer: Option<bool>) -> Vec<AccountMeta> {
+            vec![
+                AccountMeta::new_readonly(self.authority, is_signer.unwrap_or(true)),
+                AccountMeta::new(self.vault, false),
+            ]
+        }
+    }
+
+    /// Sends future withdrawal fees to a new collector; authority only
+    pub struct SetFeeCollector {
+        pub authority: Pubkey,
+        pub vault: Pubkey,
+    }
+
+    impl ToAccountMetas for SetFeeCollector {
+        fn to_account_metas(&self, is_signer: Option<bool>) -> Vec<AccountMeta> {
+            vec![
+                AccountMeta::new_readonly(self.authority, is_signer.unwrap_or(true)),
+                AccountMeta::new(self.vault, false),
+            ]
+        }
+    }
+
+    /// Moves the accrued fees out of the vault token account; fee collector
+    /// only
+    pub struct ClaimFees {
+        pub fee_collector: Pubkey,
+        pub vault: Pubkey,
+        pub vault_token_account: Pubkey,
+        pub fee_collector_token_account: Pubkey,
+        pub token_program: Pubkey,
+    }
+
+    impl ToAccountMetas for ClaimFees {
+        fn to_account_metas(&self, is_signer: Option<bool>) -> Vec<AccountMeta> {
+            vec![
+                AccountMeta::new_readonly(self.fee_collector, is_signer.unwrap_or(true)),
+                AccountMeta::new(self.vault, false),
+                AccountMeta::new(self.vault_token_account, false),
+                AccountMeta::new(self.fee_collector_token_account, false),
+                AccountMeta::new_readonly(self.token_program, false),
+            ]
+        }
+    }
+
+    /// Stops deposits and withdrawals; authority only
+    pub struct PauseVault {
+        pub authority: Pubkey,
+        pub vault: Pubkey,
+    }
+
+    impl ToAccountMetas for PauseVault {
+        fn to_account_metas(&self, is_signer: Option<bool>) -> Vec<AccountMeta> {
+            vec![
+                AccountMeta::new_readonly(self.authority, is_signer.unwrap_or(true)),
+                AccountMeta::new(self.vault, false),
+            ]
+        }
+    }
+
+    /// Lets deposits and withdrawals through again; authority only
+    pub struct UnpauseVault {
+        pub authority: Pubkey,
+        pub vault: Pubkey,
+    }
+
+    impl ToAccountMetas for UnpauseVault {
+        fn to_account_metas(&self, is_signer: Option<bool>) -> Vec<AccountMeta> {
+            vec![
+                AccountMeta::new_readonly(self.authority, is_signer.unwrap_or(true)),
+                AccountMeta::new(self.vault, false),
+            ]
+        }
+    }
+
+    /// Moves tokens out of the insurance reserve; authority only, once per
+    /// `reserve_timelock`
+    pub struct ReleaseReserve {
+        pub authority: Pubkey,
+        pub vault: Pubkey,
+        pub reserve_token_account: Pubkey,
+        pub destination_token_account: Pubkey,
+        pub token_program: Pubkey,
+    }
+
+    impl ToAccountMetas for ReleaseReserve {
+        fn to_account_metas(&self, is_signer: Option<bool>) -> Vec<AccountMeta> {
+            vec![
+                AccountMeta::new_readonly(self.authority, is_signer.unwrap_or(true)),
+                AccountMeta::new(self.vault, false),
+                AccountMeta::new(self.reserve_token_account, false),
+                AccountMeta::new(self.destination_token_account, false),
+                AccountMeta::new_readonly(self.token_program, false),
+            ]
+        }
+    }
+
+    pub struct AddAllowlistEntry {
+        pub authority: Pubkey,
+        pub vault: Pubkey,
+        pub allowlist_entry: Pubkey,
+        pub system_program: Pubkey,
+    }
+
+    impl ToAccountMetas for AddAllowlistEntry {
+        fn to_account_metas(&self, is_signer: Option<bool>) -> Vec<AccountMeta> {
+            vec![
+                AccountMeta::new(self.authority, is_signer.unwrap_or(true)),
+                AccountMeta::new_readonly(self.vault, false),
+                AccountMeta::new(self.allowlist_entry, false),
+                AccountMeta::new_readonly(self.system_program, false),
+            ]
+        }
+    }
+
+    /// Closes the entry, refunding its rent to the authority
+    pub struct RemoveAllowlistEntry {
+        pub authority: Pubkey,
+        pub vault: Pubkey,
+        pub allowlist_entry: Pubkey,
+    }
+
+    impl ToAccountMetas for RemoveAllowlistEntry {
+        fn to_account_metas(&self, is_signer: Option<bool>) -> Vec<AccountMeta> {
+            vec![
+                AccountMeta::new(self.authority, is_signer.unwrap_or(true)),
+                AccountMeta::new_readonly(self.vault, false),
+                AccountMeta::new(self.allowlist_entry, false),
+            ]
+        }
+    }
+
+    /// Creates the vault's notification config on first use, otherwise
+    /// overwrites it; authority only
+    pub struct SetNotificationConfig {
+        pub authority: Pubkey,
+        pub vault: Pubkey,
+        pub notification_config: Pubkey,
+        pub system_program: Pubkey,
+    }
+
+    impl ToAccountMetas for SetNotificationConfig {
+        fn to_account_metas(&self, is_signer: Option<bool>) -> Vec<AccountMeta> {
+            vec![
+                AccountMeta::new(self.authority, is_signer.unwrap_or(true)),
+                AccountMeta::new_readonly(self.vault, false),
+                AccountMeta::new(self.notification_config, false),
+                AccountMeta::new_readonly(self.system_program, false),
+            ]
+        }
+    }
+
+    /// Closes the notification config, refunding its rent to the authority
+    pub struct ClearNotificationConfig {
+        pub authority: Pubkey,
+        pub vault: Pubkey,
+        pub notification_config: Pubkey,
+    }
+
+    impl ToAccountMetas for ClearNotificationConfig {
+        fn to_account_metas(&self, is_signer: Option<bool>) -> Vec<AccountMeta> {
+            vec![
+                AccountMeta::new(self.authority, is_signer.unwrap_or(true)),
+                AccountMeta::new_readonly(self.vault, false),
+                AccountMeta::new(self.notification_config, false),
+            ]
+        }
+    }
+
+    /// Adds a window to the vault's maintenance schedule, creating the
+    /// schedule on first use; authority only
+    pub struct ScheduleMaintenance {
+        pub authority: Pubkey,
+        pub vault: Pubkey,
+        pub maintenance_schedule: Pubkey,
+        pub system_program: Pubkey,
+    }
+
+    impl ToAccountMetas for ScheduleMaintenance {
+        fn to_account_metas(&self, is_signer: Option<bool>) -> Vec<AccountMeta> {
+            vec![
+                AccountMeta::new(self.authority, is_signer.unwrap_or(true)),
+                AccountMeta::new_readonly(self.vault, false),
+                AccountMeta::new(self.maintenance_schedule, false),
+                AccountMeta::new_readonly(self.system_program, false),
+            ]
+        }
+    }
+
+    /// Creates the flags account on first use; afterwards only its admin may sign
+    pub struct SetFeatureFlags {
+        pub admin: Pubkey,
+        pub feature_flags: Pubkey,
+        pub system_program: Pubkey,
+    }
+
+    impl ToAccountMetas for SetFeatureFlags {
+        fn to_account_metas(&self, is_signer: Option<bool>) -> Vec<AccountMeta> {
+            vec![
+                AccountMeta::new(self.admin, is_signer.unwrap_or(true)),
+                AccountMeta::new(self.feature_flags, false),
+                AccountMeta::new_readonly(self.system_program, false),
+            ]
+        }
+    }
+}
+
+/// Instruction arguments; each carries the Anchor discriminator
+/// `sha256("global:<instruction_name>")[..8]`
+pub mod instruction {
+    use super::*;
+    use anchor_lang::{Discriminator, InstructionData};
+
+    #[derive(AnchorSerialize, AnchorDeserialize)]
+    pub struct InitializeVault {
+        pub name: String,
+        pub fee_percentage: u16,
+        pub withdrawal_timelock: i64,
+        pub withdrawal_limit: u64,
+        pub immutable: bool,
+        pub deposit_window_start: i64,
+        pub deposit_window_end: i64,
+        pub dust_threshold: u64,
+        pub waive_dust_fee: bool,
+        pub reserve_bps: u16,
+        pub reserve_timelock: i64,
+        pub fee_collector: Pubkey,
+    }
+
+    impl Discriminator for InitializeVault {
+        const DISCRIMINATOR: [u8; 8] = [48, 191, 163, 44, 71, 129, 63, 164];
+    }
+
+    impl InstructionData for InitializeVault {}
+
+    #[derive(AnchorSerialize, AnchorDeserialize)]
+    pub struct Deposit {
+        pub amount: u64,
+    }
+
+    impl Discriminator for Deposit {
+        const DISCRIMINATOR: [u8; 8] = [242, 35, 198, 137, 82, 225, 242, 182];
+    }
+
+    impl InstructionData for Deposit {}
+
+    #[derive(AnchorSerialize, AnchorDeserialize)]
+    pub struct Withdraw {
+        pub amount: u64,
+    }
+
+    impl Discriminator for Withdraw {
+        const DISCRIMINATOR: [u8; 8] = [183, 18, 70, 156, 148, 109, 161, 34];
+    }
+
+    impl InstructionData for Withdraw {}
+
+    /// Start the timelock on withdrawing `amount`
+    #[derive(AnchorSerialize, AnchorDeserialize)]
+    pub struct RequestWithdrawal {
+        pub amount: u64,
+    }
+
+    impl Discriminator for RequestWithdrawal {
+        const DISCRIMINATOR: [u8; 8] = [251, 85, 121, 205, 56, 201, 12, 177];
+    }
+
+    impl InstructionData for RequestWithdrawal {}
+
+    /// Withdraw the requested amount once the timelock has elapsed
+    #[derive(AnchorSerialize, AnchorDeserialize)]
+    pub struct ExecuteWithdrawal {}
+
+    impl Discriminator for ExecuteWithdrawal {
+        const DISCRIMINATOR: [u8; 8] = [113, 121, 203, 232, 137, 139, 248, 249];
+    }
+
+    impl InstructionData for ExecuteWithdrawal {}
+
+    #[derive(AnchorSerialize, AnchorDeserialize)]
+    pub struct CancelWithdrawal {}
+
+    impl Discriminator for CancelWithdrawal {
+        const DISCRIMINATOR: [u8; 8] = [183, 104, 181, 250, 28, 128, 210, 70];
+    }
+
+    impl InstructionData for CancelWithdrawal {}
+
+    /// Move lamports above rent exemption out of the vault and its token account
+    #[derive(AnchorSerialize, AnchorDeserialize)]
+    pub struct SweepLamports {}
+
+    impl Discriminator for SweepLamports {
+        const DISCRIMINATOR: [u8; 8] = [132, 96, 147, 111, 146, 104, 3, 59];
+    }
+
+    impl InstructionData for SweepLamports {}
+
+    /// `None` leaves a setting as it is
+    #[derive(AnchorSerialize, AnchorDeserialize)]
+    pub struct UpdateVaultConfig {
+        pub fee_percentage: Option<u16>,
+        pub withdrawal_timelock: Option<i64>,
+        pub withdrawal_limit: Option<u64>,
+    }
+
+    impl Discriminator for UpdateVaultConfig {
+        const DISCRIMINATOR: [u8; 8] = [122, 3, 21, 222, 158, 255, 238, 157];
+    }
+
+    impl InstructionData for UpdateVaultConfig {}
+
+    #[derive(AnchorSerialize, AnchorDeserialize)]
+    pub struct TransferAuthority {
+        pub new_authority: Pubkey,
+    }
+
+    impl Discriminator for TransferAuthority {
+        const DISCRIMINATOR: [u8; 8] = [48, 169, 76, 72, 229, 180, 55, 161];
+    }
+
+    impl InstructionData for TransferAuthority {}
+
+    #[derive(AnchorSerialize, AnchorDeserialize)]
+    pub struct SetFeeCollector {
+        pub new_fee_collector: Pubkey,
+    }
+
+    impl Discriminator for SetFeeCollector {
+        const DISCRIMINATOR: [u8; 8] = [143, 46, 10, 113, 121, 157, 245, 166];
+    }
+
+    impl InstructionData for SetFeeCollector {}
+
+    /// Claims everything in `accrued_fees`
+    #[derive(AnchorSerialize, AnchorDeserialize)]
+    pub struct ClaimFees {}
+
+    impl Discriminator for ClaimFees {
+        const DISCRIMINATOR: [u8; 8] = [82, 251, 233, 156, 12, 52, 184, 202];
+    }
+
+    impl InstructionData for ClaimFees {}
+
+    #[derive(AnchorSerialize, AnchorDeserialize)]
+    pub struct PauseVault {}
+
+    impl Discriminator for PauseVault {
+        const DISCRIMINATOR: [u8; 8] = [250, 6, 228, 57, 6, 104, 19, 210];
+    }
+
+    impl InstructionData for PauseVault {}
+
+    #[derive(AnchorSerialize, AnchorDeserialize)]
+    pub struct UnpauseVault {}
+
+    impl Discriminator for UnpauseVault {
+        const DISCRIMINATOR: [u8; 8] = [125, 29, 213, 213, 114, 155, 125, 63];
+    }
+
+    impl InstructionData for UnpauseVault {}
+
+    #[derive(AnchorSerialize, AnchorDeserialize)]
+    pub struct ReleaseReserve {
+        pub amount: u64,
+    }
+
+    impl Discriminator for ReleaseReserve {
+        const DISCRIMINATOR: [u8; 8] = [170, 102, 52, 144, 33, 176, 41, 60];
+    }
+
+    impl InstructionData for ReleaseReserve {}
+
+    #[derive(AnchorSerialize, AnchorDeserialize)]
+    pub struct AddAllowlistEntry {
+        pub wallet: Pubkey,
+    }
+
+    impl Discriminator for AddAllowlistEntry {
+        const DISCRIMINATOR: [u8; 8] = [59, 108, 25, 164, 197, 177, 166, 249];
+    }
+
+    impl InstructionData for AddAllowlistEntry {}
+
+    #[derive(AnchorSerialize, AnchorDeserialize)]
+    pub struct RemoveAllowlistEntry {}
+
+    impl Discriminator for RemoveAllowlistEntry {
+        const DISCRIMINATOR: [u8; 8] = [90, 45, 105, 22, 131, 188, 49, 94];
+    }
+
+    impl InstructionData for RemoveAllowlistEntry {}
+
+    #[derive(AnchorSerialize, AnchorDeserialize)]
+    pub struct SetNotificationConfig {
+        pub webhook_url_hash: Option<[u8; 32]>,
+        pub explorer: String,
+        pub support_contact: String,
+    }
+
+    impl Discriminator for SetNotificationConfig {
+        const DISCRIMINATOR: [u8; 8] = [202, 225, 17, 195, 159, 47, 106, 95];
+    }
+
+    impl InstructionData for SetNotificationConfig {}
+
+    #[derive(AnchorSerialize, AnchorDeserialize)]
+    pub struct ClearNotificationConfig {}
+
+    impl Discriminator for ClearNotificationConfig {
+        const DISCRIMINATOR: [u8; 8] = [196, 253, 106, 133, 207, 132, 115, 168];
+    }
+
+    impl InstructionData for ClearNotificationConfig {}
+
+    #[derive(AnchorSerialize, AnchorDeserialize)]
+    pub struct ScheduleMaintenance {
+        pub start_ts: i64,
+        pub end_ts: i64,
+        pub reason: String,
+    }
+
+    impl Discriminator for ScheduleMaintenance {
+        const DISCRIMINATOR: [u8; 8] = [79, 189, 69, 114, 20, 58, 174, 60];
+    }
+
+    impl InstructionData for ScheduleMaintenance {}
+
+    #[derive(AnchorSerialize, AnchorDeserialize)]
+    pub struct SetFeatureFlags {
+        pub enabled: u64,
+        pub min_client_version: String,
+    }
+
+    impl Discriminator for SetFeatureFlags {
+        const DISCRIMINATOR: [u8; 8] = [21, 221, 245, 250, 22, 107, 127, 78];
+    }
+
+    impl InstructionData for SetFeatureFlags {}
+}
+
+/// Events the program emits through `emit!`, logged as `Program data: <base64>`
+pub mod events {
+    use super::*;
+
+    /// Emitted by every successful deposit
+    #[event]
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct DepositEvent {
+        #[cfg_attr(feature = "serde", serde(with = "crate::serde_utils::pubkey"))]
+        pub vault: Pubkey,
+        #[cfg_attr(feature = "serde", serde(with = "crate::serde_utils::pubkey"))]
+        pub depositor: Pubkey,
+        /// Tokens moved in, the reserve carve-out included
+        #[cfg_attr(feature = "serde", serde(with = "crate::serde_utils::u64_string"))]
+        pub amount: u64,
+    }
+
+    /// Emitted by every successful withdrawal
+    #[event]
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct WithdrawEvent {
+        #[cfg_attr(feature = "serde", serde(with = "crate::serde_utils::pubkey"))]
+        pub vault: Pubkey,
+        #[cfg_attr(feature = "serde", serde(with = "crate::serde_utils::pubkey"))]
+        pub withdrawer: Pubkey,
+        /// Owner of the destination token account; the withdrawer unless the
+        /// withdrawal went to a third party
+        #[cfg_attr(feature = "serde", serde(with = "crate::serde_utils::pubkey"))]
+        pub recipient: Pubkey,
+        #[cfg_attr(feature = "serde", serde(with = "crate::serde_utils::u64_string"))]
+        pub amount: u64,
+        /// Part of `amount` kept in the vault as accrued fees
+        #[cfg_attr(feature = "serde", serde(with = "crate::serde_utils::u64_string"))]
+        pub fee: u64,
+    }
+
+    /// Emitted when a withdrawal would have taken a counter below zero; the
+    /// counter is clamped to zero and the vault flagged
+    #[event]
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct AccountingAnomaly {
+        #[cfg_attr(feature = "serde", serde(with = "crate::serde_utils::pubkey"))]
+        pub vault: Pubkey,
+        /// Name of the vault field that would have underflowed
+        pub counter: String,
+        /// The counter's value before the withdrawal
+        #[cfg_attr(feature = "serde", serde(with = "crate::serde_utils::u64_string"))]
+        pub recorded: u64,
+        #[cfg_attr(feature = "serde", serde(with = "crate::serde_utils::u64_string"))]
+        pub amount: u64,
+    }
+}
+
+/// Program-derived address helpers
+pub mod pda {
+    use super::*;
+
+    pub const VAULT_SEED: &[u8] = b"vault";
+    pub const VAULT_TOKEN_ACCOUNT_SEED: &[u8] = b"vault_token_account";
+    pub const VAULT_RESERVE_SEED: &[u8] = b"vault_reserve";
+    pub const ALLOWLIST_SEED: &[u8] = b"allowlist";
+    pub const FEATURE_FLAGS_SEED: &[u8] = b"feature_flags";
+    pub const NOTIFICATION_CONFIG_SEED: &[u8] = b"notification_config";
+    pub const MAINTENANCE_SCHEDULE_SEED: &[u8] = b"maintenance_schedule";
+    pub const WITHDRAWAL_REQUEST_SEED: &[u8] = b"withdrawal_request";
+    pub const POSITION_SEED: &[u8] = b"position";
+
+    /// Longest vault name, in UTF-8 bytes, that fits in a single PDA seed
+    pub const MAX_VAULT_NAME_LEN: usize = anchor_lang::solana_program::pubkey::MAX_SEED_LEN;
+
+    /// Address of the vault owned by `authority` for `token_mint` with the given name.
+    ///
+    /// Panics if `name` is longer than `MAX_VAULT_NAME_LEN` bytes; use
+    /// `try_find_vault_address` for untrusted names.
+    pub fn find_vault_address(
+        program_id: &Pubkey,
+        authority: &Pubkey,
+        token_mint: &Pubkey,
+        name: &str,
+    ) -> (Pubkey, u8) {
+        Pubkey::find_program_address(
+            &[
+                VAULT_SEED,
+                authority.as_ref(),
+                token_mint.as_ref(),
+                name.as_bytes(),
+            ],
+            program_id,
+        )
+    }
+
+    /// Like `find_vault_address`, but returns `None` when `name` cannot be used as a seed
+    pub fn try_find_vault_address(
+        program_id: &Pubkey,
+        authority: &Pubkey,
+        token_mint: &Pubkey,
+        name: &str,
+    ) -> Option<(Pubkey, u8)> {
+        if name.len() > MAX_VAULT_NAME_LEN {
+            return None;
+        }
+        Pubkey::try_find_program_address(
+            &[
+                VAULT_SEED,
+                authority.as_ref(),
+                token_mint.as_ref(),
+                name.as_bytes(),
+            ],
+            program_id,
+        )
+    }
+
+    /// Address of the token account holding a vault's tokens
+    pub fn find_vault_token_account_address(program_id: &Pubkey, vault: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[VAULT_TOKEN_ACCOUNT_SEED, vault.as_ref()], program_id)
+    }
+
+    /// Address of the token account holding a vault's insurance reserve
+    pub fn find_reserve_token_account_address(program_id: &Pubkey, vault: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[VAULT_RESERVE_SEED, vault.as_ref()], program_id)
+    }
+
+    /// Address of the allowlist entry approving `wallet` for `vault`
+    pub fn find_allowlist_entry_address(
+        program_id: &Pubkey,
+        vault: &Pubkey,
+        wallet: &Pubkey,
+    ) -> (Pubkey, u8) {
+        Pubkey::find_program_address(
+            &[ALLOWLIST_SEED, vault.as_ref(), wallet.as_ref()],
+            program_id,
+        )
+    }
+
+    /// Address of the pending withdrawal request of `withdrawer` from `vault`
+    pub fn find_withdrawal_request_address(
+        program_id: &Pubkey,
+        vault: &Pubkey,
+        withdrawer: &Pubkey,
+    ) -> (Pubkey, u8) {
+        Pubkey::find_program_address(
+            &[WITHDRAWAL_REQUEST_SEED, vault.as_ref(), withdrawer.as_ref()],
+            program_id,
+        )
+    }
+
+    /// Address of the position of `depositor` in `vault`
+    pub fn find_position_address(
+        program_id: &Pubkey,
+        vault: &Pubkey,
+        depositor: &Pubkey,
+    ) -> (Pubkey, u8) {
+        Pubkey::find_program_address(
+            &[POSITION_SEED, vault.as_ref(), depositor.as_ref()],
+            program_id,
+        )
+    }
+
+    /// Address of the notification config of `vault`
+    pub fn find_notification_config_address(program_id: &Pubkey, vault: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[NOTIFICATION_CONFIG_SEED, vault.as_ref()], program_id)
+    }
+
+    /// Address of the maintenance schedule of `vault`
+    pub fn find_maintenance_schedule_address(program_id: &Pubkey, vault: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[MAINTENANCE_SCHEDULE_SEED, vault.as_ref()], program_id)
+    }
+
+    /// Address of the program-wide feature flags account
+    pub fn find_feature_flags_address(program_id: &Pubkey) -> (Pubkey, u8) {
+        Pubkey::find_program_address(&[FEATURE_FLAGS_SEED], program_id)
+    }
+
+    /// A vault name longer than `MAX_VAULT_NAME_LEN` bytes, which cannot be a seed
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct VaultNameTooLong {
+        pub name: String,
+    }
+
+    impl std::fmt::Display for VaultNameTooLong {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(
+                f,
+                "Vault name '{}' is {} bytes; at most {} fit in an address seed",
+                self.name,
+                self.name.len(),
+                MAX_VAULT_NAME_LEN
+            )
+        }
+    }
+
+    impl std::error::Error for VaultNameTooLong {}
+
+    /// Every address `initialize_vault` creates, with the bumps the program
+    /// stores, derived without a client or a network
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct VaultAddresses {
+        pub vault: Pubkey,
+        pub vault_token_account: Pubkey,
+        pub reserve_token_account: Pubkey,
+        pub vault_bump: u8,
+        pub token_account_bump: u8,
+        pub reserve_bump: u8,
+    }
+
+    impl VaultAddresses {
+        /// Addresses of the vault owned by `authority` for `token_mint` with
+        /// the given name; a name that cannot be a seed is an error, not a panic
+        pub fn derive(
+            program_id: &Pubkey,
+            authority: &Pubkey,
+            token_mint: &Pubkey,
+            name: &str,
+        ) -> std::result::Result<Self, VaultNameTooLong> {
+            let (vault, vault_bump) =
+                try_find_vault_address(program_id, authority, token_mint, name).ok_or_else(
+                    || VaultNameTooLong {
+                        name: name.to_string(),
+                    },
+                )?;
+            let (vault_token_account, token_account_bump) =
+                find_vault_token_account_address(program_id, &vault);
+            let (reserve_token_account, reserve_bump) =
+                find_reserve_token_account_address(program_id, &vault);
+            Ok(Self {
+                vault,
+                vault_token_account,
+                reserve_token_account,
+                vault_bump,
+                token_account_bump,
+                reserve_bump,
+            })
+        }
+    }
+}
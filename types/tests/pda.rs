@@ -0,0 +1,268 @@
+use anchor_lang::prelude::Pubkey;
+use proptest::collection::vec;
+use proptest::prelude::*;
+use std::collections::HashSet;
+use token_vault_types::pda::{
+    find_position_address, find_reserve_token_account_address, find_vault_address,
+    find_vault_token_account_address, find_withdrawal_request_address, try_find_vault_address,
+    VaultAddresses, VaultNameTooLong, MAX_VAULT_NAME_LEN,
+};
+
+fn pubkey() -> impl Strategy<Value = Pubkey> {
+    any::<[u8; 32]>().prop_map(Pubkey::new_from_array)
+}
+
+/// Names that fit in a seed: empty, ASCII, exactly max length, and multi-byte UTF-8
+fn vault_name() -> impl Strategy<Value = String> {
+    prop_oneof![
+        Just(String::new()),
+        "[a-z0-9-]{1,32}",
+        "[a-z]{32}",
+        "\\PC{1,8}".prop_filter("fits in a seed", |s| s.len() <= MAX_VAULT_NAME_LEN),
+        (1usize..=8).prop_map(|n| "€".repeat(n)),
+    ]
+}
+
+/// Seeds exactly as the client has always spelled them out
+fn client_vault_address(
+    program_id: &Pubkey,
+    authority: &Pubkey,
+    mint: &Pubkey,
+    name: &str,
+) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            b"vault".as_ref(),
+            authority.as_ref(),
+            mint.as_ref(),
+            name.as_bytes(),
+        ],
+        program_id,
+    )
+    .0
+}
+
+proptest! {
+    #[test]
+    fn derivation_is_deterministic(
+        program_id in pubkey(),
+        authority in pubkey(),
+        mint in pubkey(),
+        name in vault_name(),
+    ) {
+        let first = find_vault_address(&program_id, &authority, &mint, &name);
+        let second = find_vault_address(&program_id, &authority, &mint, &name);
+        prop_assert_eq!(first, second);
+        prop_assert_eq!(
+            try_find_vault_address(&program_id, &authority, &mint, &name),
+            Some(first)
+        );
+    }
+
+    #[test]
+    fn helpers_match_client_seeds(
+        program_id in pubkey(),
+        authority in pubkey(),
+        mint in pubkey(),
+        name in vault_name(),
+    ) {
+        let (vault, _) = find_vault_address(&program_id, &authority, &mint, &name);
+        prop_assert_eq!(vault, client_vault_address(&program_id, &authority, &mint, &name));
+
+        let (token_account, _) = find_vault_token_account_address(&program_id, &vault);
+        let expected = Pubkey::find_program_address(
+            &[b"vault_token_account".as_ref(), vault.as_ref()],
+            &program_id,
+        )
+        .0;
+        prop_assert_eq!(token_account, expected);
+    }
+
+    #[test]
+    fn stored_bump_reproduces_address(
+        program_id in pubkey(),
+        authority in pubkey(),
+        mint in pubkey(),
+        name in vault_name(),
+    ) {
+        let (vault, bump) = find_vault_address(&program_id, &authority, &mint, &name);
+        let recreated = Pubkey::create_program_address(
+            &[
+                b"vault".as_ref(),
+                authority.as_ref(),
+                mint.as_ref(),
+                name.as_bytes(),
+                &[bump],
+            ],
+            &program_id,
+        )
+        .unwrap();
+        prop_assert_eq!(recreated, vault);
+
+        let (token_account, token_bump) = find_vault_token_account_address(&program_id, &vault);
+        let recreated = Pubkey::create_program_address(
+            &[b"vault_token_account".as_ref(), vault.as_ref(), &[token_bump]],
+            &program_id,
+        )
+        .unwrap();
+        prop_assert_eq!(recreated, token_account);
+    }
+
+    #[test]
+    fn distinct_inputs_do_not_collide(
+        program_id in pubkey(),
+        inputs in vec((pubkey(), pubkey(), vault_name()), 1..24),
+    ) {
+        let unique_inputs: HashSet<_> = inputs.iter().cloned().collect();
+        let mut addresses = HashSet::new();
+        for (authority, mint, name) in &unique_inputs {
+            let (vault, _) = find_vault_address(&program_id, authority, mint, name);
+            let (token_account, _) = find_vault_token_account_address(&program_id, &vault);
+            prop_assert!(addresses.insert(vault));
+            prop_assert!(addresses.insert(token_account));
+        }
+    }
+
+    #[test]
+    fn authority_equal_to_mint_is_supported(
+        program_id in pubkey(),
+        key in pubkey(),
+        other in pubkey(),
+        name in vault_name(),
+    ) {
+        prop_assume!(key != other);
+        let (same, _) = find_vault_address(&program_id, &key, &key, &name);
+        prop_assert_eq!(same, client_vault_address(&program_id, &key, &key, &name));
+
+        // Swapping authority and mint must address a different vault
+        let (forward, _) = find_vault_address(&program_id, &key, &other, &name);
+        let (swapped, _) = find_vault_address(&program_id, &other, &key, &name);
+        prop_assert_ne!(forward, swapped);
+    }
+
+    #[test]
+    fn names_longer_than_a_seed_are_rejected(
+        program_id in pubkey(),
+        authority in pubkey(),
+        mint in pubkey(),
+        extra in 1usize..64,
+    ) {
+        let name = "a".repeat(MAX_VAULT_NAME_LEN + extra);
+        prop_assert_eq!(try_find_vault_address(&program_id, &authority, &mint, &name), None);
+        prop_assert_eq!(
+            VaultAddresses::derive(&program_id, &authority, &mint, &name),
+            Err(VaultNameTooLong { name: name.clone() })
+        );
+    }
+
+    #[test]
+    fn vault_addresses_match_the_single_helpers(
+        program_id in pubkey(),
+        authority in pubkey(),
+        mint in pubkey(),
+        name in vault_name(),
+    ) {
+        let addresses = VaultAddresses::derive(&program_id, &authority, &mint, &name).unwrap();
+        let (vault, vault_bump) = find_vault_address(&program_id, &authority, &mint, &name);
+        let (token_account, token_bump) = find_vault_token_account_address(&program_id, &vault);
+        let (reserve, reserve_bump) = find_reserve_token_account_address(&program_id, &vault);
+        prop_assert_eq!(
+            addresses,
+            VaultAddresses {
+                vault,
+                vault_token_account: token_account,
+                reserve_token_account: reserve,
+                vault_bump,
+                token_account_bump: token_bump,
+                reserve_bump,
+            }
+        );
+    }
+
+    #[test]
+    fn withdrawal_requests_are_per_vault_and_withdrawer(
+        program_id in pubkey(),
+        vault in pubkey(),
+        withdrawer in pubkey(),
+        other in pubkey(),
+    ) {
+        prop_assume!(vault != other && withdrawer != other);
+        let (request, _) = find_withdrawal_request_address(&program_id, &vault, &withdrawer);
+        let expected = Pubkey::find_program_address(
+            &[b"withdrawal_request".as_ref(), vault.as_ref(), withdrawer.as_ref()],
+            &program_id,
+        )
+        .0;
+        prop_assert_eq!(request, expected);
+        let (other_vault, _) = find_withdrawal_request_address(&program_id, &other, &withdrawer);
+        let (other_withdrawer, _) = find_withdrawal_request_address(&program_id, &vault, &other);
+        prop_assert_ne!(request, other_vault);
+        prop_assert_ne!(request, other_withdrawer);
+    }
+
+    #[test]
+    fn positions_are_per_vault_and_depositor(
+        program_id in pubkey(),
+        vault in pubkey(),
+        depositor in pubkey(),
+        other in pubkey(),
+    ) {
+        prop_assume!(vault != other && depositor != other);
+        let (position, _) = find_position_address(&program_id, &vault, &depositor);
+        let expected = Pubkey::find_program_address(
+            &[b"position".as_ref(), vault.as_ref(), depositor.as_ref()],
+            &program_id,
+        )
+        .0;
+        prop_assert_eq!(position, expected);
+        let (other_vault, _) = find_position_address(&program_id, &other, &depositor);
+        let (other_depositor, _) = find_position_address(&program_id, &vault, &other);
+        prop_assert_ne!(position, other_vault);
+        prop_assert_ne!(position, other_depositor);
+        // A position never lands on the withdrawal request of the same pair
+        let (request, _) = find_withdrawal_request_address(&program_id, &vault, &depositor);
+        prop_assert_ne!(position, request);
+    }
+}
+
+// Regression: a 33-byte name used to panic inside find_program_address
+#[test]
+fn multi_byte_name_over_the_limit_is_rejected_not_panicking() {
+    let program_id = token_vault_types::ID;
+    let key = Pubkey::new_unique();
+
+    // 16 two-byte characters fill the seed exactly; one more overflows it
+    let at_limit = "é".repeat(16);
+    let over_limit = "é".repeat(17);
+    assert_eq!(at_limit.len(), MAX_VAULT_NAME_LEN);
+    assert!(try_find_vault_address(&program_id, &key, &key, &at_limit).is_some());
+    assert!(try_find_vault_address(&program_id, &key, &key, &over_limit).is_none());
+}
+
+// Names are seeded byte-for-byte: visually identical names in different
+// Unicode normalization forms address different vaults
+#[test]
+fn normalization_forms_address_different_vaults() {
+    let program_id = token_vault_types::ID;
+    let authority = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+
+    let composed = "caf\u{e9}";
+    let decomposed = "cafe\u{301}";
+    let (a, _) = find_vault_address(&program_id, &authority, &mint, composed);
+    let (b, _) = find_vault_address(&program_id, &authority, &mint, decomposed);
+    assert_ne!(a, b);
+}
+
+#[test]
+fn empty_name_is_a_valid_vault() {
+    let program_id = token_vault_types::ID;
+    let authority = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+
+    let (vault, _) = find_vault_address(&program_id, &authority, &mint, "");
+    assert_eq!(
+        vault,
+        client_vault_address(&program_id, &authority, &mint, "")
+    );
+}
@@ -0,0 +1,135 @@
+//! Repository maintenance tasks, run as `cargo xtask <task>`.
+//!
+//! `feature-matrix` compiles the client once per feature combination. The
+//! combinations are read from the client's manifest, so adding a feature
+//! adds it to the matrix.
+
+use anyhow::{anyhow, Context, Result};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Packages whose features are checked, relative to the workspace root
+const PACKAGES: &[(&str, &str)] = &[("token-vault-client", "app/Cargo.toml")];
+
+const USAGE: &str = "usage: cargo xtask feature-matrix [--list] [CARGO_CHECK_ARGS...]";
+
+fn main() -> Result<()> {
+    let mut args = env::args().skip(1);
+    match args.next().as_deref() {
+        Some("feature-matrix") => {
+            let rest: Vec<String> = args.collect();
+            let list = rest.iter().any(|arg| arg == "--list");
+            let passthrough: Vec<String> = rest.into_iter().filter(|arg| arg != "--list").collect();
+            feature_matrix(list, &passthrough)
+        }
+        _ => Err(anyhow!(USAGE)),
+    }
+}
+
+/// One `cargo check` invocation of the matrix
+struct Combination {
+    package: &'static str,
+    flags: Vec<String>,
+}
+
+impl Combination {
+    fn describe(&self) -> String {
+        if self.flags.is_empty() {
+            format!("{} (default features)", self.package)
+        } else {
+            format!("{} {}", self.package, self.flags.join(" "))
+        }
+    }
+}
+
+fn workspace_root() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .parent()
+        .expect("xtask lives inside the workspace")
+        .to_path_buf()
+}
+
+/// Features declared in a manifest, without `default`
+fn features(manifest: &Path) -> Result<Vec<String>> {
+    let manifest: toml::Value = fs::read_to_string(manifest)
+        .with_context(|| format!("reading {}", manifest.display()))?
+        .parse()?;
+    Ok(manifest
+        .get("features")
+        .and_then(toml::Value::as_table)
+        .map(|table| {
+            table
+                .keys()
+                .filter(|name| *name != "default")
+                .cloned()
+                .collect()
+        })
+        .unwrap_or_default())
+}
+
+/// No features, each feature alone, the defaults, and everything together
+fn matrix() -> Result<Vec<Combination>> {
+    let root = workspace_root();
+    let mut combinations = Vec::new();
+    for (package, manifest) in PACKAGES {
+        let flags = |flags: &[&str]| flags.iter().map(|flag| flag.to_string()).collect();
+        combinations.push(Combination {
+            package,
+            flags: flags(&["--no-default-features"]),
+        });
+        for feature in features(&root.join(manifest))? {
+            combinations.push(Combination {
+                package,
+                flags: flags(&["--no-default-features", "--features", &feature]),
+            });
+        }
+        combinations.push(Combination {
+            package,
+            flags: Vec::new(),
+        });
+        combinations.push(Combination {
+            package,
+            flags: flags(&["--all-features"]),
+        });
+    }
+    Ok(combinations)
+}
+
+fn feature_matrix(list: bool, passthrough: &[String]) -> Result<()> {
+    let combinations = matrix()?;
+    if list {
+        for combination in &combinations {
+            println!("{}", combination.describe());
+        }
+        return Ok(());
+    }
+
+    let cargo = env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+    let mut failed = Vec::new();
+    for combination in &combinations {
+        eprintln!("checking {}", combination.describe());
+        // Only the library and binaries: test targets pull in dev-dependency
+        // features and would hide a missing feature dependency
+        let status = Command::new(&cargo)
+            .current_dir(workspace_root())
+            .args(["check", "--package", combination.package, "--lib", "--bins"])
+            .args(&combination.flags)
+            .args(passthrough)
+            .status()?;
+        if !status.success() {
+            failed.push(combination.describe());
+        }
+    }
+
+    if failed.is_empty() {
+        eprintln!("all {} combinations compile", combinations.len());
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "combinations that failed:\n  {}",
+            failed.join("\n  ")
+        ))
+    }
+}